@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use password_game_bot::driver::web::helpers::extract_fen_from_svg;
+
+fuzz_target!(|svg: &str| {
+    for turn in ['w', 'b'] {
+        let _ = extract_fen_from_svg(svg, turn);
+    }
+});
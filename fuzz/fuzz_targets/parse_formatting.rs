@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use password_game_bot::driver::web::helpers::parse_formatting;
+
+// The password box's HTML comes from the page, so anything in it should give an error rather
+// than a panic
+fuzz_target!(|html: &str| {
+    let _ = parse_formatting(html);
+});
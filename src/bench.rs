@@ -0,0 +1,372 @@
+//! Timing the solver on a corpus of game states, without a browser or the network, so
+//! performance regressions in the solver and its helpers are caught before they slow down live
+//! play.
+
+use log::{debug, info};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "async")]
+use crate::{
+    config::Config,
+    driver::{async_web::AsyncWebDriver, web::WebDriver, AsyncDriver, Driver, DriverError},
+};
+use crate::{
+    driver::{direct::game_logic, DriverConfig},
+    game::{
+        lookup::{OfflineLookup, SharedLookup},
+        rule::RuleKind,
+        Game, GameState, Rule,
+    },
+    logging::SIMULATE,
+    password::helpers::{get_elements, get_roman_numerals},
+    solver::Solver,
+    youtube,
+};
+
+/// A rule to solve, and the solver and game state to solve it from.
+struct BenchCase {
+    solver: Solver,
+    game_state: GameState,
+    rule: Rule,
+}
+
+/// Build the corpus by playing a game from each seed (without the fire), keeping every rule the
+/// solver is asked to solve along the way. Passwords grow as each game goes on, so the corpus
+/// covers every size from empty to a full game. Rules which can't be solved offline (e.g., a
+/// video which would need searching for) are left violated, so the rest of the game is still
+/// covered.
+fn build_corpus(config: &DriverConfig, first_seed: u64, games: usize) -> Vec<BenchCase> {
+    let mut cases = Vec::new();
+    for seed in first_seed..first_seed.wrapping_add(games as u64) {
+        let mut game = Game::new_with_seed(seed);
+        game.state.lookup = SharedLookup::new(Arc::new(OfflineLookup));
+        game.state.ruleset = config.ruleset.clone();
+        let mut solver = Solver::with_seed(seed).with_strategy(config.strategy.into());
+
+        let mut unsolved = Vec::new();
+        for rule in game.rules.clone() {
+            game.state.highest_rule = rule.number();
+            match rule {
+                Rule::Egg => game.state.egg_placed = true,
+                Rule::Fire => game.state.fire_started = true,
+                Rule::Hatch => {
+                    game.state.paul.hatched = true;
+                    if let Err(e) = game_logic::hatch_egg(&mut solver.password) {
+                        debug!(target: SIMULATE, "Game with seed {} couldn't hatch Paul: {}", seed, e);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            let violated = game.rules[..game.state.highest_rule]
+                .iter()
+                .filter(|rule| {
+                    !unsolved.contains(*rule)
+                        && !rule.validate(solver.password.raw_password(), &game.state)
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            cases.extend(violated.iter().map(|rule| BenchCase {
+                solver: solver.clone(),
+                game_state: game.state.clone(),
+                rule: rule.clone(),
+            }));
+            let Some(plans) = solver.solve_all(&violated, &game.state) else {
+                debug!(target: SIMULATE, "Game with seed {} couldn't solve {:?}", seed, violated);
+                unsolved.extend(violated);
+                continue;
+            };
+            if let Err(e) = plans.iter().try_for_each(|plan| solver.carry_out(plan)) {
+                debug!(target: SIMULATE, "Game with seed {} made an invalid change: {}", seed, e);
+                unsolved.extend(violated);
+                continue;
+            }
+            game.state.sacrificed_letters = solver.sacrificed_letters.clone();
+        }
+    }
+    cases
+}
+
+/// Timings of one thing being benchmarked.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Timings {
+    samples: Vec<Duration>,
+    /// Total graphemes in the passwords timed, for the mean password length.
+    password_lengths: usize,
+}
+
+impl Timings {
+    fn record(&mut self, duration: Duration, password_length: usize) {
+        self.samples.push(duration);
+        self.password_lengths += password_length;
+    }
+
+    fn min(&self) -> Duration {
+        self.samples.iter().min().copied().unwrap_or_default()
+    }
+
+    fn max(&self) -> Duration {
+        self.samples.iter().max().copied().unwrap_or_default()
+    }
+
+    fn mean(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+
+    fn mean_password_length(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.password_lengths as f64 / self.samples.len() as f64
+    }
+}
+
+/// Timings of solving each kind of rule, and of the helpers the solver leans on hardest.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub rules: BTreeMap<RuleKind, Timings>,
+    pub helpers: BTreeMap<&'static str, Timings>,
+}
+
+/// A duration with units suited to its size, in the style of criterion.
+fn format_duration(duration: Duration) -> String {
+    let nanos = duration.as_nanos() as f64;
+    if nanos < 1e3 {
+        format!("{:.2} ns", nanos)
+    } else if nanos < 1e6 {
+        format!("{:.2} µs", nanos / 1e3)
+    } else if nanos < 1e9 {
+        format!("{:.2} ms", nanos / 1e6)
+    } else {
+        format!("{:.2} s", nanos / 1e9)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self
+            .rules
+            .iter()
+            .map(|(kind, timings)| (format!("{:?}", kind), timings))
+            .chain(
+                self.helpers
+                    .iter()
+                    .map(|(name, timings)| (name.to_string(), timings)),
+            );
+        for (name, timings) in rows {
+            writeln!(
+                f,
+                "{:<20} time: [{} {} {}] ({} samples, mean password length {:.1})",
+                name,
+                format_duration(timings.min()),
+                format_duration(timings.mean()),
+                format_duration(timings.max()),
+                timings.samples.len(),
+                timings.mean_password_length()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Time `Solver::solve_rule` (and the hot helpers) on every game state reached in `games`
+/// games with consecutive seeds from `first_seed`, `samples` times each.
+pub fn run(config: &DriverConfig, first_seed: u64, games: usize, samples: usize) -> Report {
+    youtube::configure(config.videos_path.as_deref(), config.youtube_search_budget);
+    let cases = build_corpus(config, first_seed, games);
+    info!(target: SIMULATE, "Benchmarking {} game states", cases.len());
+
+    let mut report = Report::default();
+    for case in &cases {
+        let password = case.solver.password.as_str();
+        let length = case.solver.password.len();
+        for _ in 0..samples {
+            let mut solver = case.solver.clone();
+            let start = Instant::now();
+            let changes = solver.solve_rule(&case.rule, &case.game_state);
+            let elapsed = start.elapsed();
+            report
+                .rules
+                .entry(case.rule.kind())
+                .or_default()
+                .record(elapsed, length);
+
+            if let Some(changes) = changes {
+                let start = Instant::now();
+                let _ = std::hint::black_box(solver.password.preview_changes(&changes));
+                report
+                    .helpers
+                    .entry("Password edits")
+                    .or_default()
+                    .record(start.elapsed(), length);
+            }
+
+            let start = Instant::now();
+            std::hint::black_box(get_roman_numerals(password));
+            report
+                .helpers
+                .entry("get_roman_numerals")
+                .or_default()
+                .record(start.elapsed(), length);
+
+            let start = Instant::now();
+            std::hint::black_box(get_elements(password));
+            report
+                .helpers
+                .entry("get_elements")
+                .or_default()
+                .record(start.elapsed(), length);
+        }
+    }
+    report
+}
+
+/// How the playthroughs of the real game with one driver went.
+#[cfg(feature = "async")]
+#[derive(Debug, Default)]
+pub struct DriverRuns {
+    /// How long each playthrough which was won took, and its final password length.
+    pub wins: Timings,
+    /// Why each playthrough which wasn't won ended.
+    pub failures: Vec<String>,
+}
+
+#[cfg(feature = "async")]
+impl DriverRuns {
+    fn record(&mut self, result: Result<(), DriverError>, time: Option<Duration>, length: usize) {
+        match result {
+            Ok(()) => self.wins.record(time.unwrap_or_default(), length),
+            Err(e) => self.failures.push(e.to_string()),
+        }
+    }
+}
+
+/// Playthroughs of the real game with the web driver and the async web driver.
+#[cfg(feature = "async")]
+#[derive(Debug, Default)]
+pub struct DriverComparison {
+    pub web: DriverRuns,
+    pub async_web: DriverRuns,
+}
+
+#[cfg(feature = "async")]
+impl fmt::Display for DriverComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, runs) in [("web", &self.web), ("async web", &self.async_web)] {
+            let played = runs.wins.samples.len() + runs.failures.len();
+            writeln!(
+                f,
+                "{:<20} won {}/{} time: [{} {} {}] (mean password length {:.1})",
+                name,
+                runs.wins.samples.len(),
+                played,
+                format_duration(runs.wins.min()),
+                format_duration(runs.wins.mean()),
+                format_duration(runs.wins.max()),
+                runs.wins.mean_password_length()
+            )?;
+            for failure in &runs.failures {
+                writeln!(f, "{:<20} lost: {}", "", failure)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Play the real game `runs` times with each of the web driver and the async web driver, with
+/// consecutive seeds from `first_seed` (the same seeds for both), timing the playthroughs.
+/// The async driver runs on the given runtime.
+#[cfg(feature = "async")]
+pub fn compare_drivers(
+    config: &Config,
+    runtime: &tokio::runtime::Runtime,
+    first_seed: u64,
+    runs: usize,
+) -> Result<DriverComparison, DriverError> {
+    let mut driver_config = config.driver_config();
+    // Nothing to resume, pause or report on
+    driver_config.snapshot_path = None;
+    driver_config.webhook_url = None;
+    let solver = |seed| Solver::with_seed(seed).with_strategy(driver_config.strategy.into());
+
+    let mut comparison = DriverComparison::default();
+    for seed in first_seed..first_seed.wrapping_add(runs as u64) {
+        info!(target: SIMULATE, "Playing with the web driver, seed {}", seed);
+        let mut driver = WebDriver::new(solver(seed), driver_config.clone())?;
+        config.apply(&mut driver);
+        let result = driver.play();
+        comparison
+            .web
+            .record(result, driver.time_since_start(), driver.password_length());
+        drop(driver);
+
+        info!(target: SIMULATE, "Playing with the async web driver, seed {}", seed);
+        runtime.block_on(async {
+            let mut driver = AsyncWebDriver::new(solver(seed), driver_config.clone()).await?;
+            config.apply_async(&mut driver);
+            let result = driver.play().await;
+            comparison.async_web.record(
+                result,
+                driver.time_since_start(),
+                driver.password_length(),
+            );
+            driver.close().await
+        })?;
+    }
+    Ok(comparison)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{format_duration, Timings};
+
+    #[test]
+    fn summarise_timings() {
+        let mut timings = Timings::default();
+        assert_eq!(timings.mean(), Duration::ZERO);
+        timings.record(Duration::from_micros(10), 20);
+        timings.record(Duration::from_micros(30), 40);
+        assert_eq!(timings.min(), Duration::from_micros(10));
+        assert_eq!(timings.mean(), Duration::from_micros(20));
+        assert_eq!(timings.max(), Duration::from_micros(30));
+        assert_eq!(timings.mean_password_length(), 30.0);
+
+        assert_eq!(format_duration(Duration::from_nanos(512)), "512.00 ns");
+        assert_eq!(format_duration(Duration::from_micros(1500)), "1.50 ms");
+        assert_eq!(format_duration(Duration::from_secs(2)), "2.00 s");
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn compare_drivers_report() {
+        use super::DriverComparison;
+        use crate::{driver::DriverError, game::Rule};
+
+        let mut comparison = DriverComparison::default();
+        comparison
+            .web
+            .record(Ok(()), Some(Duration::from_secs(90)), 60);
+        comparison.web.record(
+            Err(DriverError::Timeout(Rule::Hex(Default::default()))),
+            None,
+            0,
+        );
+        comparison
+            .async_web
+            .record(Ok(()), Some(Duration::from_secs(60)), 50);
+        let report = comparison.to_string();
+        assert!(report.contains("web                  won 1/2 time: [90.00 s 90.00 s 90.00 s]"));
+        assert!(report.contains("lost: timed out on rule Hex"));
+        assert!(report.contains("async web            won 1/1 time: [60.00 s"));
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{
+    driver::{Driver, DriverConfig, DriverError},
+    solver::Solver,
+};
+
+/// A bot playing the game with the given driver, e.g., `Bot::<WebDriver>::new(config)`. Sets up
+/// the solver from the driver config, so most users won't need anything else. It fails with a
+/// `DriverError`, which is `#[non_exhaustive]` like the crate's other public error types, so
+/// matches on it need a wildcard arm.
+pub struct Bot<D: Driver> {
+    driver: D,
+}
+
+impl<D: Driver> Bot<D> {
+    /// Launch the driver with the given config, and a solver using its seed and strategy.
+    pub fn new(config: DriverConfig) -> Result<Self, DriverError> {
+        let solver = match config.seed {
+            Some(seed) => Solver::with_seed(seed),
+            None => Solver::default(),
+        }
+        .with_strategy(config.strategy.into());
+        Self::with_solver(solver, config)
+    }
+
+    /// Launch the driver with the given config and solver.
+    pub fn with_solver(solver: Solver, config: DriverConfig) -> Result<Self, DriverError> {
+        Ok(Bot {
+            driver: D::new(solver, config)?,
+        })
+    }
+
+    /// Play the game.
+    pub fn play(&mut self) -> Result<(), DriverError> {
+        self.driver.play()
+    }
+
+    /// The driver, e.g., to set its driver-specific options before playing.
+    pub fn driver(&mut self) -> &mut D {
+        &mut self.driver
+    }
+
+    /// Take the driver back out of the bot.
+    pub fn into_driver(self) -> D {
+        self.driver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+    use std::sync::Arc;
+
+    use super::Bot;
+    use crate::{
+        driver::{direct::DirectDriver, DriverConfig, DriverError},
+        game::{
+            clock::{FixedClock, SharedClock},
+            lookup::{OfflineLookup, SharedLookup},
+        },
+    };
+
+    #[test]
+    fn play_simulated_game() {
+        let config = DriverConfig {
+            seed: Some(0),
+            lookup: SharedLookup::new(Arc::new(OfflineLookup)),
+            clock: SharedClock::new(Arc::new(FixedClock(
+                Local.with_ymd_and_hms(2021, 7, 1, 10, 0, 0).unwrap(),
+            ))),
+            ..Default::default()
+        };
+        let mut bot = Bot::<DirectDriver>::new(config).unwrap();
+        match bot.play() {
+            Ok(()) | Err(DriverError::CouldNotSatisfyRule(_)) => {}
+            Err(e) => panic!("simulation failed: {}", e),
+        }
+        assert!(bot.into_driver().changes_made() > 0);
+    }
+}
@@ -0,0 +1,333 @@
+//! Command line arguments.
+
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::{solver::strategy::StrategyKind, youtube::VideoDuration};
+
+/// A bot to beat The Password Game.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Log more (repeat for even more). Ignored if `RUST_LOG` is set.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// Don't log anything, not even errors. Ignored if `RUST_LOG` is set.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// JSON file of settings for the drivers.
+    #[arg(short, long, global = true)]
+    pub config: Option<PathBuf>,
+    /// How the solver chooses between equally valid solutions. Overrides the config file.
+    #[arg(long, global = true, value_enum)]
+    pub strategy: Option<StrategyKind>,
+    /// What to do. Defaults to playing the game.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    /// Verbosity for `logging::init`.
+    pub fn verbosity(&self) -> i8 {
+        if self.quiet {
+            -1
+        } else {
+            self.verbose.min(4) as i8
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Play the real game in a browser, starting again after recoverable failures.
+    Play(PlayArgs),
+    /// Play simulated games in parallel, without a browser, and summarise how they went.
+    Simulate(SimulateArgs),
+    /// Find YouTube videos for the YouTube rule.
+    Youtube {
+        #[command(subcommand)]
+        command: YoutubeCommand,
+    },
+    /// Manage the locations for the Geo rule.
+    Geo {
+        #[command(subcommand)]
+        command: GeoCommand,
+    },
+    /// Measure how long key presses take to reach the game, and record the tuned pacing.
+    Bench {
+        #[command(subcommand)]
+        command: Option<BenchCommand>,
+    },
+    /// Suggest edits to fix the violated rules in a password, without playing the game.
+    Hint(HintArgs),
+}
+
+#[derive(Debug, Default, Args)]
+pub struct PlayArgs {
+    /// Seed for the solver's random choices in the first game. Each later game uses the next
+    /// seed. Chosen randomly (and printed) if not given.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Snapshot of a run to pick up again (see `snapshot_path`), in the browser it was playing
+    /// in. Snapshots keep being saved to it, unless `snapshot_path` is set.
+    #[arg(long)]
+    pub resume: Option<PathBuf>,
+    /// Most runs to play. No limit if not given. Every run is added to the history in the
+    /// `runs.history` storage stream (`data/runs.history.jsonl` by default).
+    #[arg(long)]
+    pub max_attempts: Option<u64>,
+    /// Seconds to wait between runs, at least. Runs are recorded in the same history either way
+    /// (see `--max-attempts`).
+    #[arg(long, default_value_t = 0)]
+    pub cooldown: u64,
+    /// Stop once a run is won, rather than playing on for a better time.
+    #[arg(long)]
+    pub stop_on_success: bool,
+    /// Play with the async web driver, which reads the page, rerolls and feeds Paul in the
+    /// background while it plays.
+    #[cfg(feature = "async")]
+    #[arg(long = "async", conflicts_with = "resume")]
+    pub async_driver: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct SimulateArgs {
+    /// Seed for the first game's random choices. Each later game uses the next seed. Chosen
+    /// randomly (and printed) if not given.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Number of games to play.
+    #[arg(long, default_value_t = 1)]
+    pub iterations: usize,
+    /// Instead of playing, replay a recorded playthrough and report where it diverges.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["seed", "iterations"])]
+    pub replay: Option<PathBuf>,
+    /// Instead of playing random games, play every combination of captcha, chess puzzle,
+    /// location and a grid of colors (without the network), and fail if any can't be solved.
+    #[arg(long, conflicts_with_all = ["iterations", "replay"])]
+    pub strict: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct HintArgs {
+    /// The password, with any formatting written as `**bold**`, `_italic_`,
+    /// `{font=wingdings}...{/}` and `{size=36}...{/}` (with `\` before any of `\*_{}` in the
+    /// password itself). Read from stdin if not given.
+    pub password: Option<String>,
+    /// Numbers of the rules the password violates.
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub violated: Vec<usize>,
+    /// Letters in the captcha, for the captcha rule.
+    #[arg(long)]
+    pub captcha: Option<String>,
+    /// Position in the chess puzzle (as a FEN), for the chess rule.
+    #[arg(long)]
+    pub chess: Option<String>,
+    /// Location in the Geo rule, as `lat,long`.
+    #[arg(long, allow_hyphen_values = true)]
+    pub geo: Option<String>,
+    /// Length of the video in the YouTube rule, in seconds.
+    #[arg(long)]
+    pub youtube: Option<u32>,
+    /// Color in the hex rule, as six hex digits.
+    #[arg(long)]
+    pub hex: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum YoutubeCommand {
+    /// Search for videos of durations we don't have yet, saving them to
+    /// `src/youtube/videos.json`.
+    Harvest {
+        /// Durations to search for.
+        #[arg(long, value_enum, default_value = "long")]
+        duration: VideoDuration,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BenchCommand {
+    /// Time the solver on the game states reached in simulated games, without a browser or the
+    /// network.
+    Solver {
+        /// Seed for the first game. Each later game uses the next seed.
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Number of games to collect game states from.
+        #[arg(long, default_value_t = 10)]
+        games: usize,
+        /// Number of times to solve each game state.
+        #[arg(long, default_value_t = 10)]
+        samples: usize,
+    },
+    /// Play the real game with the web driver and the async web driver in turn, and compare
+    /// how long they take.
+    #[cfg(feature = "async")]
+    Drivers {
+        /// Seed for the first game with each driver. Each later game uses the next seed.
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Number of games to play with each driver.
+        #[arg(long, default_value_t = 3)]
+        runs: usize,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum GeoCommand {
+    /// Add the locations in Google Maps embed URLs (as shown by the Geo rule) to
+    /// `src/game/data/coordinates.txt` and `countries.txt`, finding their countries by reverse
+    /// geocoding.
+    Ingest {
+        /// Embed URLs to add.
+        #[arg(required = true)]
+        urls: Vec<String>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, Parser};
+
+    use super::{Cli, Command};
+    use crate::solver::strategy::StrategyKind;
+
+    #[test]
+    fn parse_args() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["main"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.verbosity(), 0);
+
+        let cli = Cli::try_parse_from([
+            "main",
+            "simulate",
+            "--seed",
+            "7",
+            "--iterations",
+            "3",
+            "-vv",
+            "--strategy",
+            "shortest-password",
+        ])
+        .unwrap();
+        assert_eq!(cli.verbosity(), 2);
+        assert_eq!(cli.strategy, Some(StrategyKind::ShortestPassword));
+        let Some(Command::Simulate(args)) = cli.command else {
+            panic!("expected simulate");
+        };
+        assert_eq!((args.seed, args.iterations), (Some(7), 3));
+
+        assert!(
+            Cli::try_parse_from(["main", "simulate", "--replay", "a.jsonl", "--seed", "1"])
+                .is_err()
+        );
+        let cli = Cli::try_parse_from(["main", "simulate", "--strict", "--seed", "1"]).unwrap();
+        let Some(Command::Simulate(args)) = cli.command else {
+            panic!("expected simulate");
+        };
+        assert!(args.strict);
+        assert!(
+            Cli::try_parse_from(["main", "simulate", "--strict", "--iterations", "2"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["main", "-v", "-q", "play"]).is_err());
+        let cli = Cli::try_parse_from(["main", "play", "--seed", "5"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Play(super::PlayArgs {
+                seed: Some(5),
+                resume: None,
+                max_attempts: None,
+                cooldown: 0,
+                stop_on_success: false,
+                #[cfg(feature = "async")]
+                async_driver: false,
+            }))
+        ));
+        let cli = Cli::try_parse_from(["main", "play", "--resume", "run.json"]).unwrap();
+        let Some(Command::Play(args)) = cli.command else {
+            panic!("expected play command");
+        };
+        assert_eq!(args.resume, Some("run.json".into()));
+        let cli = Cli::try_parse_from([
+            "main",
+            "play",
+            "--max-attempts",
+            "10",
+            "--cooldown",
+            "60",
+            "--stop-on-success",
+        ])
+        .unwrap();
+        let Some(Command::Play(args)) = cli.command else {
+            panic!("expected play command");
+        };
+        assert_eq!((args.max_attempts, args.cooldown), (Some(10), 60));
+        assert!(args.stop_on_success);
+
+        assert!(Cli::try_parse_from(["main", "geo", "ingest"]).is_err());
+        let cli = Cli::try_parse_from(["main", "geo", "ingest", "a", "b"]).unwrap();
+        let Some(Command::Geo {
+            command: super::GeoCommand::Ingest { urls },
+        }) = cli.command
+        else {
+            panic!("expected geo ingest");
+        };
+        assert_eq!(urls, ["a", "b"]);
+
+        let cli = Cli::try_parse_from(["main", "bench"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Bench { command: None })
+        ));
+        let cli = Cli::try_parse_from(["main", "bench", "solver", "--games", "2"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Bench {
+                command: Some(super::BenchCommand::Solver {
+                    seed: 0,
+                    games: 2,
+                    samples: 10
+                })
+            })
+        ));
+
+        assert!(Cli::try_parse_from(["main", "hint", "abc"]).is_err());
+        let cli = Cli::try_parse_from([
+            "main",
+            "hint",
+            "**abc**",
+            "--violated",
+            "5,9",
+            "--geo",
+            "-33.9,18.4",
+        ])
+        .unwrap();
+        let Some(Command::Hint(args)) = cli.command else {
+            panic!("expected hint");
+        };
+        assert_eq!(args.password.as_deref(), Some("**abc**"));
+        assert_eq!(args.violated, [5, 9]);
+        assert_eq!(args.geo.as_deref(), Some("-33.9,18.4"));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn parse_async_args() {
+        let cli = Cli::try_parse_from(["main", "play", "--async"]).unwrap();
+        let Some(Command::Play(args)) = cli.command else {
+            panic!("expected play command");
+        };
+        assert!(args.async_driver);
+        assert!(Cli::try_parse_from(["main", "play", "--async", "--resume", "a.json"]).is_err());
+
+        let cli = Cli::try_parse_from(["main", "bench", "drivers", "--runs", "5"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Bench {
+                command: Some(super::BenchCommand::Drivers { seed: 0, runs: 5 })
+            })
+        ));
+    }
+}
@@ -0,0 +1,274 @@
+//! Settings loaded from a JSON config file, overriding the defaults of the drivers.
+
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use thiserror::Error;
+
+#[cfg(feature = "async")]
+use crate::driver::async_web::AsyncWebDriver;
+use crate::{
+    driver::{
+        web::{FinalEntry, InputBackend, UnknownRulePolicy, WebDriver},
+        DriverConfig,
+    },
+    game::ruleset::{RuleOverrides, RuleSet},
+    solver::strategy::StrategyKind,
+};
+
+/// Settings from a config file. Anything not given keeps its default.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Run the browser without a window.
+    pub headless: Option<bool>,
+    /// URL of the game.
+    pub game_url: Option<String>,
+    /// How key presses are sent (`cdp` or `os`).
+    pub input: Option<InputBackend>,
+    /// How long the browser can go without any commands before it's closed.
+    pub idle_browser_timeout_secs: Option<u64>,
+    /// Allow setting the password via the page's editor API when resyncing.
+    pub allow_dom_recovery: Option<bool>,
+    /// Solve violated early rules with a single batch of appends (off by default).
+    pub batch_early_rules: Option<bool>,
+    /// Measure key injection latency before playing, and tune the pacing to match.
+    pub benchmark_pacing: Option<bool>,
+    /// Insert whole strings in one round trip, rather than a grapheme at a time.
+    pub bulk_typing: Option<bool>,
+    /// Choose fonts and font sizes in the toolbar's menus directly, rather than with the
+    /// keyboard.
+    pub dom_font_menus: Option<bool>,
+    /// How the final password is re-entered (`auto`, `clipboard` or `insert-text`).
+    pub final_entry: Option<FinalEntry>,
+    /// How the solver chooses between equally valid solutions (`fastest`, `shortest-password`
+    /// or `max-style`).
+    pub strategy: Option<StrategyKind>,
+    /// Highest cost of a captcha or hex color to accept without rerolling.
+    pub max_reroll_cost: Option<u32>,
+    /// Number of rerolls of a captcha or hex color before taking whatever we have.
+    pub max_rerolls: Option<usize>,
+    /// Longest any rule can stay violated before a playthrough is abandoned.
+    pub rule_timeout_secs: Option<u64>,
+    /// Longest a playthrough can take before it's abandoned.
+    pub game_timeout_secs: Option<u64>,
+    /// YouTube video database to load instead of the copy built into the binary.
+    pub videos_path: Option<PathBuf>,
+    /// Longest to spend searching YouTube for a video the database doesn't have (0 to never
+    /// search).
+    pub youtube_search_secs: Option<u64>,
+    /// Look up the outside information rules might need in the background at the start of a
+    /// game.
+    pub prefetch: Option<bool>,
+    /// Where to save a snapshot of the run after every turn, to resume from if the bot crashes.
+    pub snapshot_path: Option<PathBuf>,
+    /// Local port to listen for requests to pause and resume the bot on (0 to pick any).
+    pub control_port: Option<u16>,
+    /// Show the game in progress in a terminal UI instead of logging (needs the `tui`
+    /// feature).
+    pub tui: Option<bool>,
+    /// Webhook to post the outcome of each playthrough to (e.g., a Discord channel's).
+    pub webhook_url: Option<String>,
+    /// Directory to save error reports into when a run loses sync, can't satisfy a rule or times
+    /// out.
+    pub error_report_dir: Option<PathBuf>,
+    /// What to do when the page shows a violated rule we don't know (`ignore`, `warn` or
+    /// `abort`).
+    pub unknown_rules: Option<UnknownRulePolicy>,
+    /// Version of the game's rules to play by (see `RuleSet::builtin`).
+    pub ruleset: Option<String>,
+    /// Changes to some of the numbers in the rules, on top of the chosen version.
+    pub rule_overrides: Option<RuleOverrides>,
+}
+
+/// Failure modes for loading a config file.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read config file")]
+    Io(#[from] std::io::Error),
+    #[error("invalid config file")]
+    Json(#[from] serde_json::Error),
+    #[error("unknown rule set {0:?}")]
+    UnknownRuleSet(String),
+    #[error("invalid rule override: {0}")]
+    InvalidOverride(&'static str),
+}
+
+impl Config {
+    /// Load settings from the given JSON file.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let config: Config = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        if let Some(version) = &config.ruleset {
+            if RuleSet::find(version).is_none() {
+                return Err(ConfigError::UnknownRuleSet(version.clone()));
+            }
+        }
+        if let Some(overrides) = &config.rule_overrides {
+            if overrides.max_bugs == Some(0) {
+                return Err(ConfigError::InvalidOverride("max_bugs must be at least 1"));
+            }
+        }
+        Ok(config)
+    }
+
+    /// The driver config, with these settings applied.
+    pub fn driver_config(&self) -> DriverConfig {
+        let mut config = DriverConfig::default();
+        if let Some(headless) = self.headless {
+            config.headless = headless;
+        }
+        if let Some(game_url) = &self.game_url {
+            config.game_url = game_url.clone();
+        }
+        if let Some(input) = self.input {
+            config.input = input;
+        }
+        if let Some(secs) = self.idle_browser_timeout_secs {
+            config.idle_browser_timeout = Duration::from_secs(secs);
+        }
+        if let Some(strategy) = self.strategy {
+            config.strategy = strategy;
+        }
+        if let Some(max_cost) = self.max_reroll_cost {
+            config.reroll.max_cost = Some(max_cost);
+        }
+        if let Some(max_rerolls) = self.max_rerolls {
+            config.reroll.max_rerolls = max_rerolls;
+        }
+        if let Some(secs) = self.rule_timeout_secs {
+            config.rule_timeout = Some(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.game_timeout_secs {
+            config.game_timeout = Some(Duration::from_secs(secs));
+        }
+        if let Some(videos_path) = &self.videos_path {
+            config.videos_path = Some(videos_path.clone());
+        }
+        if let Some(secs) = self.youtube_search_secs {
+            config.youtube_search_budget = Duration::from_secs(secs);
+        }
+        if let Some(prefetch) = self.prefetch {
+            config.prefetch = prefetch;
+        }
+        if let Some(snapshot_path) = &self.snapshot_path {
+            config.snapshot_path = Some(snapshot_path.clone());
+        }
+        if let Some(webhook_url) = &self.webhook_url {
+            config.webhook_url = Some(webhook_url.clone());
+        }
+        if let Some(ruleset) = self.ruleset.as_deref().and_then(RuleSet::find) {
+            config.ruleset = ruleset;
+        }
+        if let Some(overrides) = &self.rule_overrides {
+            config.ruleset = config.ruleset.with_overrides(overrides);
+        }
+        config
+    }
+
+    /// Apply the settings which are options on the web driver itself.
+    pub fn apply(&self, driver: &mut WebDriver) {
+        if let Some(allow_dom_recovery) = self.allow_dom_recovery {
+            driver.allow_dom_recovery = allow_dom_recovery;
+        }
+        if let Some(batch_early_rules) = self.batch_early_rules {
+            driver.batch_early_rules = batch_early_rules;
+        }
+        if let Some(benchmark_pacing) = self.benchmark_pacing {
+            driver.benchmark_pacing = benchmark_pacing;
+        }
+        if let Some(bulk_typing) = self.bulk_typing {
+            driver.bulk_typing = bulk_typing;
+        }
+        if let Some(dom_font_menus) = self.dom_font_menus {
+            driver.dom_font_menus = dom_font_menus;
+        }
+        if let Some(final_entry) = self.final_entry {
+            driver.final_entry = final_entry;
+        }
+        if let Some(error_report_dir) = &self.error_report_dir {
+            driver.error_report_dir = Some(error_report_dir.clone());
+        }
+        if let Some(unknown_rules) = self.unknown_rules {
+            driver.unknown_rules = unknown_rules;
+        }
+    }
+
+    /// Apply the settings which are options on the async web driver itself.
+    #[cfg(feature = "async")]
+    pub fn apply_async(&self, driver: &mut AsyncWebDriver) {
+        if let Some(unknown_rules) = self.unknown_rules {
+            driver.unknown_rules = unknown_rules;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Config, ConfigError};
+    use crate::{
+        driver::web::{FinalEntry, InputBackend, UnknownRulePolicy},
+        solver::strategy::StrategyKind,
+    };
+
+    #[test]
+    fn parse_config() {
+        let config: Config = serde_json::from_str(
+            r#"{"headless": true, "input": "os", "idle_browser_timeout_secs": 60, "strategy": "max-style", "max_rerolls": 20, "error_report_dir": "reports", "rule_timeout_secs": 300, "videos_path": "videos.json", "youtube_search_secs": 0, "prefetch": false, "snapshot_path": "run.json", "tui": true, "webhook_url": "https://example.com/hook"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.input, Some(InputBackend::Os));
+        assert_eq!(config.error_report_dir, Some("reports".into()));
+        assert_eq!(config.tui, Some(true));
+        let driver_config = config.driver_config();
+        assert!(driver_config.headless);
+        assert_eq!(driver_config.input, InputBackend::Os);
+        assert_eq!(driver_config.idle_browser_timeout, Duration::from_secs(60));
+        assert_eq!(driver_config.strategy, StrategyKind::MaxStyle);
+        assert_eq!(driver_config.reroll.max_cost, None);
+        assert_eq!(driver_config.reroll.max_rerolls, 20);
+        assert_eq!(driver_config.rule_timeout, Some(Duration::from_secs(300)));
+        assert_eq!(driver_config.game_timeout, None);
+        assert_eq!(driver_config.videos_path, Some("videos.json".into()));
+        assert_eq!(driver_config.youtube_search_budget, Duration::ZERO);
+        assert!(!driver_config.prefetch);
+        assert_eq!(driver_config.snapshot_path, Some("run.json".into()));
+        assert_eq!(
+            driver_config.webhook_url.as_deref(),
+            Some("https://example.com/hook")
+        );
+
+        assert!(serde_json::from_str::<Config>(r#"{"headles": true}"#).is_err());
+
+        let config: Config = serde_json::from_str(
+            r#"{"ruleset": "2023-06", "rule_overrides": {"max_bugs": 6}, "unknown_rules": "abort", "final_entry": "insert-text"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.unknown_rules, Some(UnknownRulePolicy::Abort));
+        assert_eq!(config.final_entry, Some(FinalEntry::InsertText));
+        let ruleset = config.driver_config().ruleset;
+        assert_eq!(ruleset.version, "2023-06+custom");
+        assert_eq!(ruleset.max_bugs, 6);
+        assert_eq!(ruleset.digit_sum, 25);
+        assert!(serde_json::from_str::<Config>(r#"{"rule_overrides": {"digits": 30}}"#).is_err());
+
+        let path = std::env::temp_dir().join(format!(
+            "password-game-bot-config-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(&path, r#"{"ruleset": "1999-01"}"#).unwrap();
+        assert!(matches!(
+            Config::load(&path),
+            Err(ConfigError::UnknownRuleSet(version)) if version == "1999-01"
+        ));
+        std::fs::write(&path, r#"{"rule_overrides": {"max_bugs": 0}}"#).unwrap();
+        assert!(matches!(
+            Config::load(&path),
+            Err(ConfigError::InvalidOverride(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,826 @@
+//! A driver for the real game built on tokio and chromiumoxide. Reading the page, rerolling
+//! the captcha and color, and feeding Paul each run as their own task, so none of them waits
+//! on the others (or on the solver), where the web driver has to fit them in between its own
+//! steps.
+//!
+//! The password is entered through the page's editor rather than typed, so there's no cursor
+//! or toolbar to keep in sync.
+
+use chromiumoxide::{Browser, BrowserConfig, Page};
+use futures::StreamExt;
+use log::{debug, error, info, warn};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::watch,
+    task::{self, JoinHandle},
+    time::MissedTickBehavior,
+};
+
+use super::{direct::game_logic, prepare_lookups, AsyncDriver, DriverConfig, DriverError};
+use crate::{
+    driver::web::{
+        helpers::{
+            extract_captcha_from_img_src, extract_color_from_css_style,
+            extract_coords_from_embed_url, extract_duration_from_rule_text, extract_fen_from_svg,
+        },
+        rule_classes, UnknownRulePolicy,
+    },
+    game::{
+        rule::{RuleInstance, RuleKind},
+        GameState, Rule,
+    },
+    http,
+    logging::DRIVER_ASYNC,
+    password::{Change, ChangeError},
+    solver::{
+        reroll::{RerollKind, Reroller},
+        risk::{self, RuleStats},
+        scoring::{digit_budget, ScoringBudget},
+        Solver,
+    },
+    youtube,
+};
+use page::PageView;
+
+mod page;
+
+/// How often the page is read.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How often Paul's bugs are counted, and topped up if he's eaten any.
+const FEED_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Time for the game to re-validate the rules after the password is changed.
+const RULE_VALIDATION_WAIT: Duration = Duration::from_millis(100);
+/// Time for the page to show a new captcha or color after a reroll.
+const REROLL_WAIT: Duration = Duration::from_millis(100);
+/// Longest to wait for the password box when opening the game.
+const PAGE_LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+/// Time for the final password box to appear after confirming the password.
+const FINAL_BOX_WAIT: Duration = Duration::from_millis(500);
+/// Longest to wait for the end screen after entering the final password.
+const END_SCREEN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A driver for the actual game at https://neal.fun/password-game/, which works in the
+/// background while it plays. It takes the browser, timeout, lookup and rule set options from
+/// the driver config, and ignores those for the web driver's input, snapshots, pausing,
+/// terminal UI and webhook.
+pub struct AsyncWebDriver {
+    /// The browser. Closed when the driver is dropped.
+    browser: Browser,
+    /// Carries messages between us and the browser, for as long as the driver lives.
+    handler: JoinHandle<()>,
+    /// The tab with the game open.
+    page: Page,
+    /// The solver which will attempt to play the game.
+    solver: Solver,
+    /// State of the game, synced to the actual game's state.
+    pub game_state: GameState,
+    /// Number of bugs the feeding task keeps in the password. Zero while Paul doesn't need
+    /// feeding.
+    bug_target: Arc<AtomicUsize>,
+    /// The captcha and color we last settled on after rerolling, and when.
+    settled: BTreeMap<RerollKind, (String, Instant)>,
+    /// Path of the chess puzzle image last seen, and the position in it, so it's only fetched
+    /// once.
+    chess: Option<(String, String)>,
+    /// When each violated rule was last seen satisfied (or first seen at all).
+    violated_since: BTreeMap<RuleKind, Instant>,
+    /// Time when we started playing the game.
+    start_time: Option<Instant>,
+    /// What to do when the page shows a violated rule we don't know.
+    pub unknown_rules: UnknownRulePolicy,
+    /// Class names of the unknown rules seen so far.
+    unknown_rule_classes: BTreeSet<String>,
+    /// How often each rule has been solved in previous runs.
+    rule_stats: RuleStats,
+    /// Options the browser was launched with.
+    config: DriverConfig,
+}
+
+/// The settled value of a reroll and how many rerolls it took.
+type RerollResult = Result<(String, usize), DriverError>;
+
+/// The tasks which run alongside the play loop for one playthrough, stopped when it ends.
+struct Background {
+    /// Latest view of the page.
+    views: watch::Receiver<Option<PageView>>,
+    /// Reads the page every `POLL_INTERVAL`, only stopping if it can't.
+    watcher: Option<JoinHandle<Result<(), DriverError>>>,
+    /// Keeps Paul fed.
+    feeder: JoinHandle<()>,
+    /// Rerolls of the captcha or color in progress.
+    rerolls: BTreeMap<RerollKind, JoinHandle<RerollResult>>,
+}
+
+impl AsyncDriver for AsyncWebDriver {
+    async fn new(solver: Solver, config: DriverConfig) -> Result<Self, DriverError> {
+        prepare_lookups(&config);
+        info!(target: DRIVER_ASYNC, "Playing with rule set {}", config.ruleset.version);
+
+        let mut browser_config = BrowserConfig::builder();
+        if !config.headless {
+            browser_config = browser_config.with_head();
+        }
+        let browser_config = browser_config
+            .build()
+            .map_err(|_| DriverError::LaunchOptionsBuilderError)?;
+        let (browser, mut handler) = Browser::launch(browser_config).await?;
+        let handler = tokio::spawn(async move {
+            while let Some(result) = handler.next().await {
+                if let Err(e) = result {
+                    debug!(target: DRIVER_ASYNC, "Browser connection error: {}", e);
+                }
+            }
+        });
+        let page = browser.new_page(config.game_url.as_str()).await?;
+        let loading = Instant::now();
+        while page.find_element("div.ProseMirror").await.is_err() {
+            if loading.elapsed() > PAGE_LOAD_TIMEOUT {
+                return Err(DriverError::MalformedElement(
+                    "no password box on the page".to_owned(),
+                ));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+
+        Ok(AsyncWebDriver {
+            browser,
+            handler,
+            page,
+            solver,
+            game_state: GameState {
+                clock: config.clock.clone(),
+                lookup: config.lookup.clone(),
+                ruleset: config.ruleset.clone(),
+                ..Default::default()
+            },
+            bug_target: Arc::new(AtomicUsize::new(0)),
+            settled: BTreeMap::new(),
+            chess: None,
+            violated_since: BTreeMap::new(),
+            start_time: None,
+            unknown_rules: UnknownRulePolicy::default(),
+            unknown_rule_classes: BTreeSet::new(),
+            rule_stats: RuleStats::load(),
+            config,
+        })
+    }
+
+    async fn play(&mut self) -> Result<(), DriverError> {
+        let result = match self.config.game_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.play_once())
+                .await
+                .unwrap_or_else(|_| {
+                    warn!(
+                        target: DRIVER_ASYNC,
+                        "Playthrough has taken {:.0} seconds, giving up",
+                        timeout.as_secs_f32()
+                    );
+                    Err(DriverError::GameTimeout(timeout))
+                }),
+            None => self.play_once().await,
+        };
+        match &result {
+            Ok(()) => risk::record_run(self.game_state.highest_rule, None),
+            Err(DriverError::CouldNotSatisfyRule(rule) | DriverError::Timeout(rule)) => {
+                risk::record_run(self.game_state.highest_rule, Some(rule.kind()))
+            }
+            Err(_) => {}
+        }
+        result
+    }
+}
+
+impl Drop for AsyncWebDriver {
+    fn drop(&mut self) {
+        self.handler.abort();
+    }
+}
+
+impl AsyncWebDriver {
+    /// Get the current duration of time since we started playing. Returns none if we haven't
+    /// started playing yet.
+    pub fn time_since_start(&self) -> Option<Duration> {
+        self.start_time.map(|t| t.elapsed())
+    }
+
+    /// Number of graphemes in our password (not counting Paul's bugs).
+    pub fn password_length(&self) -> usize {
+        self.solver.password.len()
+    }
+
+    /// Close the browser, waiting for it to exit.
+    pub async fn close(&mut self) -> Result<(), DriverError> {
+        self.browser.close().await?;
+        if let Err(e) = self.browser.wait().await {
+            warn!(target: DRIVER_ASYNC, "Browser didn't exit cleanly: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Play the game through once, from the starting password.
+    async fn play_once(&mut self) -> Result<(), DriverError> {
+        self.start_time = Some(Instant::now());
+        self.settled.clear();
+        self.violated_since.clear();
+        let mut background = Background::start(&self.page, &self.bug_target);
+
+        let plan = self.solver.starting_password(&self.game_state);
+        self.solver.carry_out(&plan)?;
+        let mut next_view = self.enter_password(None).await?;
+        loop {
+            let view = background.view_since(next_view).await?;
+            next_view = Instant::now();
+            if view.ended {
+                info!(
+                    target: DRIVER_ASYNC,
+                    "Completed game in {:.2}",
+                    self.time_since_start().unwrap().as_secs_f32()
+                );
+                return Ok(());
+            }
+            if self.game_state.paul.hatched {
+                let now = self.game_state.clock.now();
+                self.game_state.paul.count_bugs(view.bugs(), now);
+            }
+
+            let (mut violated_rules, rerolling) =
+                self.violated_rules(&view, &mut background).await?;
+            self.check_timeouts(&violated_rules)?;
+            if violated_rules.is_empty() {
+                if rerolling {
+                    continue;
+                }
+                return Ok(());
+            }
+            info!(
+                target: DRIVER_ASYNC,
+                "Password: {:?}, violated rules: {:?}",
+                self.solver.password.as_str(),
+                violated_rules
+            );
+
+            if violated_rules.contains(&Rule::Fire) {
+                // Our password has no fire in it
+                debug!(target: DRIVER_ASYNC, "Putting out the fire");
+                next_view = self.enter_password(None).await?;
+                continue;
+            }
+            if view.password_without_bugs() != self.solver.password.as_str() {
+                warn!(
+                    target: DRIVER_ASYNC,
+                    "Password on the page is {:?}, entering ours again",
+                    view.password_without_bugs()
+                );
+                next_view = self.enter_password(None).await?;
+                continue;
+            }
+            if violated_rules.len() == 1 && violated_rules[0] == Rule::Final {
+                return self.confirm_final_password(&mut background).await;
+            }
+            if violated_rules.contains(&Rule::Hatch) {
+                let bug_target = self.config.ruleset.max_bugs;
+                self.bug_target.store(bug_target, Ordering::Relaxed);
+                let bugs = top_up_bugs(&self.page, bug_target).await?;
+                debug!(target: DRIVER_ASYNC, "Paul hatched, fed him {} bugs", bugs);
+                next_view = Instant::now() + RULE_VALIDATION_WAIT;
+                continue;
+            }
+            violated_rules.retain(|rule| *rule != Rule::Final);
+            if let Some(bugs) = self.bugs_for_goal_length(&violated_rules)? {
+                // We're just waiting for the number of bugs to make the password length correct
+                debug!(target: DRIVER_ASYNC, "Adjusting bugs to match goal length");
+                self.bug_target.store(bugs, Ordering::Relaxed);
+                next_view = self.enter_password(Some(bugs)).await?;
+                continue;
+            }
+
+            task::block_in_place(|| self.solve(&violated_rules))?;
+            if self.game_state.sacrificed_letters != self.solver.sacrificed_letters {
+                self.game_state.sacrificed_letters = self.solver.sacrificed_letters.clone();
+                let script = page::sacrifice_script(&self.game_state.sacrificed_letters);
+                if !evaluate::<bool>(&self.page, script).await? {
+                    return Err(DriverError::MalformedElement(
+                        "no buttons to sacrifice letters".to_owned(),
+                    ));
+                }
+            }
+            if let Some(changes) = self.solver.refresh_time_string(&self.game_state) {
+                info!(target: DRIVER_ASYNC, "Minute rolled over, updating the time string");
+                self.commit(changes)?;
+            }
+            next_view = self.enter_password(None).await?;
+        }
+    }
+
+    /// Get the violated rules on the page, highest first, and whether any are being left
+    /// until the captcha or color is rerolled.
+    async fn violated_rules(
+        &mut self,
+        view: &PageView,
+        background: &mut Background,
+    ) -> Result<(Vec<Rule>, bool), DriverError> {
+        let malformed = |what: &str| DriverError::MalformedElement(format!("no {}", what));
+        let mut violated_rules = Vec::new();
+        let mut rerolling = false;
+        for class_attribute in &view.rules {
+            for class in rule_classes(class_attribute) {
+                let Ok(kind) = serde_plain::from_str::<RuleKind>(class) else {
+                    // We can't solve unknown rules, so just work on the rest
+                    self.note_unknown_rule(class)?;
+                    continue;
+                };
+                if self.game_state.highest_rule < kind.number() {
+                    self.game_state.highest_rule = kind.number();
+                    debug!(
+                        target: DRIVER_ASYNC,
+                        "New rule {:?}, solved in an estimated {:.0}% of runs",
+                        kind,
+                        self.rule_stats.success_probability(kind) * 100.0
+                    );
+                }
+
+                let instance = match kind {
+                    RuleKind::Egg => {
+                        self.game_state.egg_placed = true;
+                        None
+                    }
+                    RuleKind::Fire => {
+                        self.game_state.fire_started = true;
+                        None
+                    }
+                    RuleKind::Hatch => {
+                        if !self.game_state.paul.hatched {
+                            self.game_state.paul.hatched = true;
+                            game_logic::hatch_egg(&mut self.solver.password)?;
+                        }
+                        None
+                    }
+                    RuleKind::Captcha => {
+                        let answer = view
+                            .captcha
+                            .as_deref()
+                            .and_then(extract_captcha_from_img_src)
+                            .ok_or_else(|| malformed("captcha image"))?;
+                        if !self
+                            .settled(RerollKind::Captcha, &answer, view.taken, background)
+                            .await?
+                        {
+                            rerolling = true;
+                            continue;
+                        }
+                        Some(RuleInstance::Captcha(answer))
+                    }
+                    RuleKind::Hex => {
+                        let style = view.color.as_deref().ok_or_else(|| malformed("color"))?;
+                        let color = extract_color_from_css_style(style);
+                        if !self
+                            .settled(
+                                RerollKind::Color,
+                                &color.to_hex_string(),
+                                view.taken,
+                                background,
+                            )
+                            .await?
+                        {
+                            rerolling = true;
+                            continue;
+                        }
+                        Some(RuleInstance::Hex(color))
+                    }
+                    RuleKind::Geo => {
+                        let url = view.geo.as_deref().ok_or_else(|| malformed("map"))?;
+                        let Some(geo) = extract_coords_from_embed_url(url) else {
+                            return Err(DriverError::MalformedElement(format!(
+                                "no coordinates in Google Maps embed URL {:?}",
+                                url
+                            )));
+                        };
+                        Some(RuleInstance::Geo(geo))
+                    }
+                    RuleKind::Chess => Some(RuleInstance::Chess(self.chess_position(view)?)),
+                    RuleKind::Wordle => {
+                        // Fetch the answer now, so a network failure ends the run here rather
+                        // than looking like the rule can't be solved
+                        let today = self.game_state.clock.now().date_naive();
+                        task::block_in_place(|| self.game_state.lookup.wordle_answer(today))?;
+                        None
+                    }
+                    RuleKind::Youtube => {
+                        let text = view.youtube.as_deref().ok_or_else(|| malformed("video"))?;
+                        let Some(seconds) = extract_duration_from_rule_text(text) else {
+                            return Err(DriverError::MalformedElement(format!(
+                                "no video length in the YouTube rule {:?}",
+                                text
+                            )));
+                        };
+                        Some(RuleInstance::Youtube(seconds))
+                    }
+                    _ => None,
+                };
+                let rule = Rule::new(kind, instance);
+                violated_rules.push(rule);
+            }
+        }
+        violated_rules.sort_by_key(Rule::kind);
+        violated_rules.reverse();
+        Ok((violated_rules, rerolling))
+    }
+
+    /// Whether the captcha or color seen on the page is the one we settled on, rerolling it in
+    /// the background if not. The rule is left unsolved until the reroll settles, while the
+    /// other rules are worked on.
+    async fn settled(
+        &mut self,
+        kind: RerollKind,
+        value: &str,
+        seen: Instant,
+        background: &mut Background,
+    ) -> Result<bool, DriverError> {
+        if let Some(reroll) = background.rerolls.remove(&kind) {
+            if !reroll.is_finished() {
+                background.rerolls.insert(kind, reroll);
+                return Ok(false);
+            }
+            let (accepted, rerolls) = reroll.await??;
+            debug!(
+                target: DRIVER_ASYNC,
+                "Settled on {:?} {:?} after {} rerolls", kind, accepted, rerolls
+            );
+            self.settled.insert(kind, (accepted, Instant::now()));
+        }
+        match self.settled.get(&kind) {
+            Some((accepted, _)) if accepted == value => return Ok(true),
+            // Seen before the reroll settled, so wait for the page to catch up
+            Some((_, settled_at)) if seen < *settled_at => return Ok(false),
+            _ => {}
+        }
+        let digit_budget = digit_budget(&self.solver.password, &self.game_state);
+        background.rerolls.insert(
+            kind,
+            tokio::spawn(reroll(
+                self.page.clone(),
+                kind,
+                self.config.reroll.clone(),
+                self.game_state.sacrificed_letters.clone(),
+                digit_budget,
+            )),
+        );
+        Ok(false)
+    }
+
+    /// Deal with a violated rule we don't know, as the unknown rule policy says.
+    fn note_unknown_rule(&mut self, class: &str) -> Result<(), DriverError> {
+        match self.unknown_rules {
+            UnknownRulePolicy::Ignore => {}
+            UnknownRulePolicy::Warn => {
+                if self.unknown_rule_classes.insert(class.to_owned()) {
+                    warn!(
+                        target: DRIVER_ASYNC,
+                        "Unknown rule {:?} is violated, carrying on with the rules we know", class
+                    );
+                }
+            }
+            UnknownRulePolicy::Abort => return Err(DriverError::UnknownRule(class.to_owned())),
+        }
+        Ok(())
+    }
+
+    /// Get the position in the chess puzzle, as a FEN.
+    fn chess_position(&mut self, view: &PageView) -> Result<String, DriverError> {
+        let (Some(path), Some(to_move)) = (&view.chess_image, &view.chess_move) else {
+            return Err(DriverError::MalformedElement("no chess puzzle".to_owned()));
+        };
+        if let Some((seen, fen)) = &self.chess {
+            if seen == path {
+                return Ok(fen.clone());
+            }
+        }
+        // Player to move is in the text, and the position is in the SVG
+        let to_move = if to_move.contains("White") { 'w' } else { 'b' };
+        let url = format!("https://neal.fun{}", path);
+        let body = task::block_in_place(|| http::get_text(&url))?;
+        let Some(fen) = extract_fen_from_svg(&body, to_move) else {
+            return Err(DriverError::MalformedElement(format!(
+                "no chess board in the puzzle image {:?}",
+                url
+            )));
+        };
+        self.chess = Some((path.clone(), fen.clone()));
+        Ok(fen)
+    }
+
+    /// If the password only needs the right number of bugs to be the goal length, how many.
+    /// Any length beyond what Paul can eat is made up with padding.
+    fn bugs_for_goal_length(
+        &mut self,
+        violated_rules: &[Rule],
+    ) -> Result<Option<usize>, DriverError> {
+        let Some(goal_length) = self.solver.goal_length else {
+            return Ok(None);
+        };
+        if self.solver.length_string.is_none()
+            || violated_rules.last() != Some(&Rule::IncludeLength)
+            || !violated_rules[..violated_rules.len() - 1]
+                .iter()
+                .all(|rule| *rule == Rule::PrimeLength)
+            || self.solver.password.len() > goal_length
+        {
+            return Ok(None);
+        }
+        let missing = goal_length - self.solver.password.len();
+        let bugs = missing.min(self.config.ruleset.max_bugs);
+        if bugs < missing {
+            // Don't overfeed Paul!
+            self.commit(vec![Change::Append {
+                string: self.solver.strategy.padding().repeat(missing - bugs),
+                protected: false,
+            }])?;
+        }
+        Ok(Some(bugs))
+    }
+
+    /// Plan changes which satisfy the violated rules (highest first) and make them to our
+    /// password. Blocks, on the solver and on any YouTube search.
+    fn solve(&mut self, violated_rules: &[Rule]) -> Result<(), DriverError> {
+        self.search_for_missing_videos(violated_rules);
+        for rule in violated_rules {
+            if let Some(reason) = risk::infeasibility(&self.solver, rule, &self.game_state) {
+                info!(
+                    target: DRIVER_ASYNC,
+                    "Giving up early, as {:?} can't be satisfied: {}", rule, reason
+                );
+                return Err(DriverError::CouldNotSatisfyRule(rule.clone()));
+            }
+        }
+
+        let first_rule = violated_rules.last().unwrap();
+        let removals = if *first_rule == Rule::IncludeLength {
+            // Shrink the password as far as we can before its length is fixed
+            self.solver.compact_password(&self.game_state)
+        } else if Solver::wants_garbage_collection(first_rule) {
+            self.solver.collect_garbage(&self.game_state)
+        } else {
+            Vec::new()
+        };
+        self.commit(removals)?;
+
+        if let Some(plans) = self.solver.solve_all(violated_rules, &self.game_state) {
+            for plan in &plans {
+                self.solver.carry_out(plan)?;
+            }
+            return Ok(());
+        }
+        // Fall back to the lowest violated rule on its own
+        let Some(plan) = self
+            .solver
+            .plan_rule(first_rule, &self.game_state)
+            .filter(|plan| self.solver.password.preview_changes(&plan.changes).is_ok())
+        else {
+            return Err(DriverError::CouldNotSatisfyRule(first_rule.clone()));
+        };
+        self.solver.carry_out(&plan)?;
+        Ok(())
+    }
+
+    /// If the video database has no video which fits the password for a violated YouTube rule,
+    /// search YouTube for one (adding it to the database), as the solver only plans with the
+    /// database. Paul is fed in the background, so the search can take its whole budget.
+    fn search_for_missing_videos(&self, violated_rules: &[Rule]) {
+        for rule in violated_rules {
+            let Rule::Youtube(seconds) = rule else {
+                continue;
+            };
+            let ruleset = &self.game_state.ruleset;
+            if self.solver.choose_video(*seconds, ruleset).is_none() {
+                let end = Instant::now() + youtube::search_budget();
+                self.solver.search_video(*seconds, ruleset, Some(end));
+            }
+        }
+    }
+
+    /// Abandon the playthrough if any of the violated rules has been violated for longer than
+    /// the rule timeout. The game timeout is enforced by `play`.
+    fn check_timeouts(&mut self, violated_rules: &[Rule]) -> Result<(), DriverError> {
+        let now = Instant::now();
+        self.violated_since
+            .retain(|kind, _| violated_rules.iter().any(|rule| rule.kind() == *kind));
+        for rule in violated_rules {
+            let since = *self.violated_since.entry(rule.kind()).or_insert(now);
+            if let Some(timeout) = self.config.rule_timeout {
+                if now - since > timeout {
+                    warn!(
+                        target: DRIVER_ASYNC,
+                        "{:?} has been violated for {:.0} seconds, giving up",
+                        rule,
+                        (now - since).as_secs_f32()
+                    );
+                    return Err(DriverError::Timeout(rule.clone()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Confirm our password is final, enter it into the final password box, and wait for the
+    /// end screen.
+    async fn confirm_final_password(
+        &mut self,
+        background: &mut Background,
+    ) -> Result<(), DriverError> {
+        // Paul doesn't need feeding any more
+        self.bug_target.store(0, Ordering::Relaxed);
+        if !evaluate::<bool>(&self.page, page::CONFIRM_SCRIPT.to_owned()).await? {
+            return Err(DriverError::MalformedElement(
+                "no button to confirm the final password".to_owned(),
+            ));
+        }
+        tokio::time::sleep(FINAL_BOX_WAIT).await;
+        let script = page::final_password_script(self.solver.password.raw_password());
+        if !evaluate::<bool>(&self.page, script).await? {
+            return Err(DriverError::MalformedElement(
+                "no empty final password box".to_owned(),
+            ));
+        }
+
+        let entered = Instant::now();
+        loop {
+            let view = background.view_since(Instant::now()).await?;
+            if view.ended {
+                info!(
+                    target: DRIVER_ASYNC,
+                    "Completed game in {:.2}",
+                    self.time_since_start().unwrap().as_secs_f32()
+                );
+                return Ok(());
+            }
+            if entered.elapsed() > END_SCREEN_TIMEOUT {
+                return Err(DriverError::Timeout(Rule::Final));
+            }
+        }
+    }
+
+    /// Make the given changes to our password.
+    fn commit(&mut self, changes: Vec<Change>) -> Result<(), ChangeError> {
+        for change in changes {
+            self.solver.password.queue_change(change);
+        }
+        self.solver.password.commit_changes()
+    }
+
+    /// Replace the password on the page with ours, followed by the given number of bugs (or as
+    /// many as there are now). Returns when the game will have re-validated the rules.
+    async fn enter_password(&mut self, bugs: Option<usize>) -> Result<Instant, DriverError> {
+        let script = page::set_password_script(self.solver.password.raw_password(), bugs);
+        let Ok(bugs) = usize::try_from(evaluate::<i64>(&self.page, script).await?) else {
+            error!(target: DRIVER_ASYNC, "Failed to find the editor to set the password");
+            return Err(DriverError::LostSync);
+        };
+        if self.game_state.paul.hatched {
+            let now = self.game_state.clock.now();
+            self.game_state.paul.count_bugs(bugs, now);
+        }
+        Ok(Instant::now() + RULE_VALIDATION_WAIT)
+    }
+}
+
+impl Background {
+    /// Start watching the page and feeding Paul.
+    fn start(page: &Page, bug_target: &Arc<AtomicUsize>) -> Self {
+        let (sender, views) = watch::channel(None);
+        Background {
+            views,
+            watcher: Some(tokio::spawn(watch_page(page.clone(), sender))),
+            feeder: tokio::spawn(feed_paul(page.clone(), Arc::clone(bug_target))),
+            rerolls: BTreeMap::new(),
+        }
+    }
+
+    /// Wait for a view of the page taken no earlier than the given time.
+    async fn view_since(&mut self, since: Instant) -> Result<PageView, DriverError> {
+        loop {
+            if let Some(view) = self.views.borrow_and_update().as_ref() {
+                if view.taken >= since {
+                    return Ok(view.clone());
+                }
+            }
+            if self.views.changed().await.is_err() {
+                // The watcher only stops if it can't read the page
+                let watcher = self.watcher.take();
+                return Err(match watcher {
+                    Some(watcher) => watcher.await?.err().unwrap_or(DriverError::LostSync),
+                    None => DriverError::LostSync,
+                });
+            }
+        }
+    }
+}
+
+impl Drop for Background {
+    fn drop(&mut self) {
+        if let Some(watcher) = &self.watcher {
+            watcher.abort();
+        }
+        self.feeder.abort();
+        for reroll in self.rerolls.values() {
+            reroll.abort();
+        }
+    }
+}
+
+/// Run a script in the page, and get its result.
+async fn evaluate<T: DeserializeOwned>(page: &Page, script: String) -> Result<T, DriverError> {
+    page.evaluate_expression(script)
+        .await?
+        .into_value()
+        .map_err(|e| DriverError::MalformedElement(format!("unexpected script result: {}", e)))
+}
+
+/// Read the page every `POLL_INTERVAL`, for as long as anyone's watching.
+async fn watch_page(page: Page, views: watch::Sender<Option<PageView>>) -> Result<(), DriverError> {
+    let script = page::view_script();
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let taken = Instant::now();
+        let mut view = evaluate::<PageView>(&page, script.clone()).await?;
+        view.taken = taken;
+        if views.send(Some(view)).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// Keep the password topped up to `bug_target` bugs, checking every `FEED_CHECK_INTERVAL`.
+async fn feed_paul(page: Page, bug_target: Arc<AtomicUsize>) {
+    let mut interval = tokio::time::interval(FEED_CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let target = bug_target.load(Ordering::Relaxed);
+        if target == 0 {
+            continue;
+        }
+        match top_up_bugs(&page, target).await {
+            Ok(0) => {}
+            Ok(bugs) => debug!(target: DRIVER_ASYNC, "Fed Paul {} bugs", bugs),
+            Err(e) => warn!(target: DRIVER_ASYNC, "Failed to feed Paul: {}", e),
+        }
+    }
+}
+
+/// Add bugs to the end of the password until there are `target` of them, returning how many
+/// were added.
+async fn top_up_bugs(page: &Page, target: usize) -> Result<usize, DriverError> {
+    evaluate(page, page::top_up_script(target)).await
+}
+
+/// Reroll the captcha or color until the reroll policy accepts it, returning what was
+/// accepted and the number of rerolls it took.
+async fn reroll(
+    page: Page,
+    kind: RerollKind,
+    budget: ScoringBudget,
+    sacrificed_letters: Vec<char>,
+    digit_budget: u32,
+) -> RerollResult {
+    let refresh = match kind {
+        RerollKind::Captcha => "img.captcha-refresh",
+        RerollKind::Color => "img.refresh",
+    };
+    let script = page::view_script();
+    let mut reroller = Reroller::new(kind, &budget);
+    loop {
+        let view = evaluate::<PageView>(&page, script.clone()).await?;
+        let value = match kind {
+            RerollKind::Captcha => view
+                .captcha
+                .as_deref()
+                .and_then(extract_captcha_from_img_src),
+            RerollKind::Color => view
+                .color
+                .as_deref()
+                .map(|style| extract_color_from_css_style(style).to_hex_string()),
+        };
+        let Some(value) = value else {
+            return Err(DriverError::MalformedElement(format!(
+                "no {:?} to reroll",
+                kind
+            )));
+        };
+        if reroller.accept(&value, &sacrificed_letters, digit_budget) {
+            return Ok((value, reroller.rerolls()));
+        }
+        debug!(target: DRIVER_ASYNC, "Rerolling {:?}...", kind);
+        evaluate::<bool>(&page, page::click_script(refresh)).await?;
+        tokio::time::sleep(REROLL_WAIT).await;
+    }
+}
@@ -0,0 +1,277 @@
+use serde::Deserialize;
+use std::time::Instant;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    driver::web::{helpers::prosemirror_doc, SCAN_SCRIPT},
+    game::rule::RuleKind,
+    password::Password,
+};
+
+/// What Paul eats.
+const BUG: &str = "🐛";
+
+/// Script which confirms the password is final, returning whether the button was there.
+pub const CONFIRM_SCRIPT: &str = r#"(() => {
+    const yes = Array.from(document.querySelectorAll(".final-password button")).find(
+        (button) => button.innerText.trim() === "Yes"
+    );
+    if (yes) {
+        yes.click();
+    }
+    return yes !== undefined;
+})()"#;
+
+/// Everything the driver needs from the page at one moment: the violated rules, the password,
+/// and whatever the violated rules show which is needed to solve them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageView {
+    /// When the page started being read, so any change made before then is reflected.
+    #[serde(skip, default = "Instant::now")]
+    pub taken: Instant,
+    /// Class attributes of the violated rules.
+    pub rules: Vec<String>,
+    /// Text of the password box, including any bugs.
+    pub password: String,
+    /// `src` of the captcha image, if the captcha rule is violated.
+    pub captcha: Option<String>,
+    /// `style` of the color to include, if the hex rule is violated.
+    pub color: Option<String>,
+    /// `src` of the Google Maps embed, if the Geo rule is violated.
+    pub geo: Option<String>,
+    /// `src` of the chess puzzle, if the chess rule is violated.
+    pub chess_image: Option<String>,
+    /// Text saying whose move it is in the chess puzzle, if the chess rule is violated.
+    pub chess_move: Option<String>,
+    /// Text of the YouTube rule, if it's violated.
+    pub youtube: Option<String>,
+    /// Whether the end screen is showing.
+    pub ended: bool,
+}
+
+impl PageView {
+    /// Number of bugs in the password box.
+    pub fn bugs(&self) -> usize {
+        self.password.graphemes(true).filter(|g| *g == BUG).count()
+    }
+
+    /// Text of the password box without the bugs, to compare with our password.
+    pub fn password_without_bugs(&self) -> String {
+        self.password.replace(BUG, "")
+    }
+}
+
+/// Script which reads a `PageView`. Elements the violated rules show are scrolled into the
+/// rules list if it's virtualized and they aren't rendered (see `SCAN_SCRIPT`).
+pub fn view_script() -> String {
+    let class = |kind: RuleKind| serde_json::to_string(&kind).unwrap();
+    format!(
+        r#"(async () => {{
+            const scan = {scan};
+            const rules = Array.from(
+                document.querySelectorAll("div.rule-error"),
+                (rule) => rule.className
+            );
+            const find = async (kind, selector) => {{
+                if (!rules.some((rule) => rule.split(/\s+/).includes(kind))) {{
+                    return null;
+                }}
+                if (!document.querySelector(selector)) {{
+                    await scan(selector);
+                }}
+                return document.querySelector(selector);
+            }};
+            const attribute = async (kind, selector, name) => {{
+                const element = await find(kind, selector);
+                return element ? element.getAttribute(name) : null;
+            }};
+            const text = async (kind, selector) => {{
+                const element = await find(kind, selector);
+                return element ? element.innerText : null;
+            }};
+            const box = document.querySelector("div.ProseMirror");
+            return {{
+                rules,
+                password: box ? box.innerText.replace(/\n+$/, "") : "",
+                captcha: await attribute({captcha}, "img.captcha-img", "src"),
+                color: await attribute({hex}, "div.rand-color", "style"),
+                geo: await attribute({geo}, "iframe.geo", "src"),
+                chessImage: await attribute({chess}, "img.chess-img", "src"),
+                chessMove: await text({chess}, "div.move"),
+                youtube: await text({youtube}, "div.rule-error." + {youtube}),
+                ended: document.querySelector(".end-screen") !== null,
+            }};
+        }})()"#,
+        scan = SCAN_SCRIPT,
+        captcha = class(RuleKind::Captcha),
+        hex = class(RuleKind::Hex),
+        geo = class(RuleKind::Geo),
+        chess = class(RuleKind::Chess),
+        youtube = class(RuleKind::Youtube),
+    )
+}
+
+/// A text node of bugs in the default formatting, as a JavaScript function of how many.
+fn bugs_node() -> String {
+    let node = &prosemirror_doc(&Password::default(), 1)["content"][0]["content"][0];
+    format!(
+        "(count) => Object.assign({}, {{ text: {:?}.repeat(count) }})",
+        node, BUG
+    )
+}
+
+/// JavaScript expression for the number of bugs in the password box's `editor`.
+fn bug_count() -> String {
+    format!(
+        "Array.from(editor.getText()).filter((c) => c === {:?}).length",
+        BUG
+    )
+}
+
+/// Script which replaces the contents of the password box with the given password (with its
+/// formatting) through the page's editor, followed by the given number of bugs, or as many as
+/// there are now if not given. Returns the number of bugs, or -1 if the editor isn't there.
+pub fn set_password_script(password: &Password, bugs: Option<usize>) -> String {
+    format!(
+        r#"(() => {{
+            const box = document.querySelector("div.ProseMirror");
+            const editor = box && box.editor;
+            if (!editor) {{
+                return -1;
+            }}
+            const doc = {doc};
+            const bugs = {bugs} ?? {count};
+            if (bugs > 0) {{
+                doc.content[0].content.push(({node})(bugs));
+            }}
+            editor.commands.setContent(doc, true);
+            return bugs;
+        }})()"#,
+        doc = prosemirror_doc(password, 0),
+        bugs = bugs.map_or("null".to_owned(), |bugs| bugs.to_string()),
+        count = bug_count(),
+        node = bugs_node(),
+    )
+}
+
+/// Script which adds bugs to the end of the password box until there are `target` of them.
+/// Returns the number of bugs added.
+pub fn top_up_script(target: usize) -> String {
+    format!(
+        r#"(() => {{
+            const box = document.querySelector("div.ProseMirror");
+            const editor = box && box.editor;
+            if (!editor) {{
+                return 0;
+            }}
+            const missing = {target} - {count};
+            if (missing <= 0) {{
+                return 0;
+            }}
+            editor.commands.insertContentAt(
+                editor.state.doc.content.size - 1,
+                ({node})(missing)
+            );
+            return missing;
+        }})()"#,
+        target = target,
+        count = bug_count(),
+        node = bugs_node(),
+    )
+}
+
+/// Script which enters the given password into the empty final password box, returning
+/// whether there was one.
+pub fn final_password_script(password: &Password) -> String {
+    format!(
+        r#"(() => {{
+            const box = Array.from(document.querySelectorAll("div.ProseMirror")).find(
+                (box) => box.innerText.trim() === ""
+            );
+            const editor = box && box.editor;
+            if (!editor) {{
+                return false;
+            }}
+            editor.commands.setContent({doc}, true);
+            return true;
+        }})()"#,
+        doc = prosemirror_doc(password, 0),
+    )
+}
+
+/// Script which clicks the element matching the given selector, returning whether there was
+/// one.
+pub fn click_script(selector: &str) -> String {
+    format!(
+        r#"(() => {{
+            const element = document.querySelector({selector:?});
+            if (element) {{
+                element.click();
+            }}
+            return element !== null;
+        }})()"#
+    )
+}
+
+/// Script which sacrifices the given letters, returning whether the buttons were there. The
+/// letter buttons are assumed to be in alphabetical order.
+pub fn sacrifice_script(letters: &[char]) -> String {
+    let indices = letters
+        .iter()
+        .map(|letter| (*letter as u32 - 'a' as u32).to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"(() => {{
+            const letters = document.querySelectorAll("button.letter");
+            const sacrifice = document.querySelector("button.sacrafice-btn");
+            const indices = [{indices}];
+            if (!sacrifice || indices.some((i) => !letters[i])) {{
+                return false;
+            }}
+            for (const i of indices) {{
+                letters[i].click();
+            }}
+            sacrifice.click();
+            return true;
+        }})()"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{sacrifice_script, set_password_script, PageView};
+    use crate::password::Password;
+
+    #[test]
+    fn page_view() {
+        let view: PageView = serde_json::from_value(json!({
+            "rules": ["rule rule-error captcha"],
+            "password": "🐔a🐛b🐛",
+            "captcha": "/password-game/captchas/bnwpm.png",
+            "color": null,
+            "geo": null,
+            "chessImage": null,
+            "chessMove": null,
+            "youtube": null,
+            "ended": false,
+        }))
+        .unwrap();
+        assert_eq!(view.bugs(), 2);
+        assert_eq!(view.password_without_bugs(), "🐔ab");
+    }
+
+    #[test]
+    fn scripts() {
+        let password = Password::from_str("🥚ab");
+        let script = set_password_script(&password, Some(3));
+        assert!(script.contains(r#""text":"🥚ab""#));
+        assert!(script.contains("const bugs = 3 ??"));
+        assert!(set_password_script(&password, None).contains("const bugs = null ??"));
+
+        assert!(sacrifice_script(&['c', 'z']).contains("const indices = [2, 25];"));
+    }
+}
@@ -1,9 +1,9 @@
-use crate::password::{Change, MutablePassword};
-use rand::{prelude::*, seq::SliceRandom};
+use crate::password::{Change, ChangeError, MutablePassword};
+use rand::{seq::SliceRandom, Rng};
 use unicode_segmentation::UnicodeSegmentation;
 
 /// Start a fire in the password by replacing a random grapheme with "🔥".
-pub fn start_fire(password: &mut MutablePassword) {
+pub fn start_fire<R: Rng>(password: &mut MutablePassword, rng: &mut R) -> Result<(), ChangeError> {
     // Choose a random grapheme index at least 5 characters away from Paul ("🥚")
     let graphemes = password.as_str().graphemes(true).collect::<Vec<_>>();
     let valid_indices = if let Some(egg_index) = graphemes.iter().position(|g| *g == "🥚") {
@@ -13,19 +13,18 @@ pub fn start_fire(password: &mut MutablePassword) {
     } else {
         (0..graphemes.len()).collect::<Vec<usize>>()
     };
-    let mut rng = thread_rng();
-    let index = valid_indices.choose(&mut rng).unwrap();
+    let index = valid_indices.choose(rng).unwrap();
     password.queue_change(Change::Replace {
         index: *index,
         new_grapheme: "🔥".into(),
         ignore_protection: true,
     });
-    password.commit_changes();
+    password.commit_changes()
 }
 
 /// Spread the fire. Each contiguous section of 🔥 should grow by one in both directions.
 #[allow(dead_code)]
-pub fn spread_fire(password: &mut MutablePassword) {
+pub fn spread_fire(password: &mut MutablePassword) -> Result<(), ChangeError> {
     let graphemes = password.as_str().graphemes(true).collect::<Vec<_>>();
     let mut changes = Vec::new();
     for i in 0..password.len() {
@@ -45,11 +44,11 @@ pub fn spread_fire(password: &mut MutablePassword) {
     for change in changes {
         password.queue_change(change);
     }
-    password.commit_changes();
+    password.commit_changes()
 }
 
 // Hatch Paul, turning "🥚" into "🐔".
-pub fn hatch_egg(password: &mut MutablePassword) {
+pub fn hatch_egg(password: &mut MutablePassword) -> Result<(), ChangeError> {
     for (index, grapheme) in password.as_str().graphemes(true).enumerate() {
         if grapheme == "🥚" {
             password.queue_change(crate::password::Change::Replace {
@@ -57,23 +56,24 @@ pub fn hatch_egg(password: &mut MutablePassword) {
                 new_grapheme: "🐔".into(),
                 ignore_protection: true,
             });
-            password.commit_changes();
-            break;
+            return password.commit_changes();
         }
     }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::{spread_fire, start_fire};
     use crate::password::MutablePassword;
+    use rand::thread_rng;
     use std::collections::HashSet;
     use unicode_segmentation::UnicodeSegmentation;
 
     #[test]
     fn starting_fire() {
         let mut password = MutablePassword::from_str("hello");
-        start_fire(&mut password);
+        start_fire(&mut password, &mut thread_rng()).unwrap();
         assert!(password.as_str().contains("🔥"));
         assert_eq!(password.as_str().matches("🔥").count(), 1);
 
@@ -81,7 +81,7 @@ mod tests {
         let mut indices = HashSet::new();
         while indices.len() < 5 {
             let mut password = MutablePassword::from_str("hello");
-            start_fire(&mut password);
+            start_fire(&mut password, &mut thread_rng()).unwrap();
             assert!(password.as_str().contains("🔥"));
             for (index, grapheme) in password.as_str().graphemes(true).enumerate() {
                 if grapheme == "🔥" {
@@ -95,7 +95,7 @@ mod tests {
         let mut indices = HashSet::new();
         while indices.len() < 6 {
             let mut password = MutablePassword::from_str("avoid the🥚egg foo");
-            start_fire(&mut password);
+            start_fire(&mut password, &mut thread_rng()).unwrap();
             assert!(password.as_str().contains("🔥"));
             for (index, grapheme) in password.as_str().graphemes(true).enumerate() {
                 if grapheme == "🔥" {
@@ -112,11 +112,11 @@ mod tests {
     #[test]
     fn spreading_fire() {
         let mut password = MutablePassword::from_str("he🔥lo");
-        spread_fire(&mut password);
+        spread_fire(&mut password).unwrap();
         assert_eq!(password.as_str(), "h🔥🔥🔥o");
 
         let mut password = MutablePassword::from_str("🔥hello🔥");
-        spread_fire(&mut password);
+        spread_fire(&mut password).unwrap();
         assert_eq!(password.as_str(), "🔥🔥ell🔥🔥");
     }
 }
@@ -1,12 +1,30 @@
-use log::info;
+use log::{debug, info, warn};
+use rand::{rngs::StdRng, SeedableRng};
+use std::path::PathBuf;
+use unicode_segmentation::UnicodeSegmentation;
 
-use super::{Driver, DriverError};
+use super::{Driver, DriverConfig, DriverError};
 use crate::{
-    game::{Game, Rule},
-    solver::Solver,
+    game::{
+        rule::{Color, RuleInstance},
+        Game, Rule,
+    },
+    http,
+    logging::DRIVER_DIRECT,
+    solver::{
+        reroll::{RerollKind, Reroller},
+        scoring::{digit_budget, ScoringBudget},
+        Solver,
+    },
+    youtube,
 };
 
-mod game_logic;
+pub mod game_logic;
+mod replay;
+mod visualize;
+
+/// Most rounds of solving the violated rules a game can take. A game needs a few dozen.
+const MAX_TURNS: usize = 200;
 
 /// A driver for direct interaction with an instance of `Game`.
 /// Will spawn a random instance of the game on creation.
@@ -15,12 +33,85 @@ pub struct DirectDriver {
     game: Game,
     /// The solver which will attempt to play the game.
     solver: Solver,
+    /// Source of the game's random events (e.g., where the fire starts).
+    rng: StdRng,
+    /// Number of changes made to the password so far.
+    changes_made: usize,
+    /// How hard to try to find a cheap captcha or color when rerolling them.
+    reroll: ScoringBudget,
+    /// If set, an HTML visualization of the final password and rules is written here once
+    /// the game is finished (whether or not it was won).
+    pub visualization_path: Option<PathBuf>,
 }
 
 impl DirectDriver {
+    /// Number of graphemes in the password.
+    pub fn password_length(&self) -> usize {
+        self.solver.password.len()
+    }
+
+    /// Number of changes made to the password so far.
+    pub fn changes_made(&self) -> usize {
+        self.changes_made
+    }
+
+    /// Play the given instance data instead of what was chosen for the game.
+    pub fn set_instance(&mut self, instance: RuleInstance) {
+        self.game.set_instance(instance);
+    }
+
+    /// Reroll the captcha until the solver's reroll policy accepts it, as the web driver does
+    /// when the captcha rule appears.
+    fn reroll_captcha(&mut self) {
+        let digit_budget = digit_budget(&self.solver.password, &self.game.state);
+        let mut reroller = Reroller::new(RerollKind::Captcha, &self.reroll);
+        while !reroller.accept(
+            self.game.captcha().unwrap_or_default(),
+            &self.game.state.sacrificed_letters,
+            digit_budget,
+        ) {
+            let captcha = self.game.refresh_captcha(&mut self.rng);
+            debug!(
+                target: DRIVER_DIRECT,
+                "Rerolled captcha to {:?} (digit sum {}, roman numerals: {})",
+                captcha.answer, captcha.digit_sum, captcha.has_roman_numerals
+            );
+        }
+        if reroller.rerolls() > 0 {
+            info!(target: DRIVER_DIRECT, "Rerolled the captcha {} times", reroller.rerolls());
+        }
+    }
+
+    /// Reroll the hex color until the solver's reroll policy accepts it, as the web driver does
+    /// when the hex rule appears.
+    fn reroll_color(&mut self) {
+        let digit_budget = digit_budget(&self.solver.password, &self.game.state);
+        let mut reroller = Reroller::new(RerollKind::Color, &self.reroll);
+        while !reroller.accept(
+            &self
+                .game
+                .color()
+                .map(Color::to_hex_string)
+                .unwrap_or_default(),
+            &self.game.state.sacrificed_letters,
+            digit_budget,
+        ) {
+            let color = self.game.refresh_color(&mut self.rng);
+            debug!(
+                target: DRIVER_DIRECT,
+                "Rerolled color to {}",
+                color.to_hex_string()
+            );
+        }
+        if reroller.rerolls() > 0 {
+            info!(target: DRIVER_DIRECT, "Rerolled the color {} times", reroller.rerolls());
+        }
+    }
+
     fn get_violated_rules(&mut self) -> Result<Vec<Rule>, DriverError> {
         let mut violated_rules = Vec::new();
-        for rule in &self.game.rules {
+        for index in 0..self.game.rules.len() {
+            let rule = &self.game.rules[index];
             if rule.number() - 1 < self.game.state.highest_rule {
                 if !rule.validate(self.solver.password.raw_password(), &self.game.state) {
                     violated_rules.push(rule.clone());
@@ -31,17 +122,19 @@ impl DirectDriver {
 
                 // Some rules require game state updates
                 match rule {
+                    Rule::Captcha(_) => self.reroll_captcha(),
+                    Rule::Hex(_) => self.reroll_color(),
                     Rule::Egg => {
                         self.game.state.egg_placed = true;
                     }
                     Rule::Fire => {
                         self.game.state.fire_started = true;
-                        game_logic::start_fire(&mut self.solver.password);
+                        game_logic::start_fire(&mut self.solver.password, &mut self.rng)?;
                         // TODO: Implement fire spread logic. Every 1100ms fire should spread.
                     }
                     Rule::Hatch => {
-                        self.game.state.paul_hatched = true;
-                        game_logic::hatch_egg(&mut self.solver.password);
+                        self.game.state.paul.hatched = true;
+                        game_logic::hatch_egg(&mut self.solver.password)?;
                         // TODO: Implement Paul eating logic:
                         //       Every 20 seconds, a bug is removed from the password.
                         //       If there aren't any bugs in the password, game over
@@ -51,6 +144,7 @@ impl DirectDriver {
                     _ => {}
                 }
 
+                let rule = &self.game.rules[index];
                 if !rule.validate(self.solver.password.raw_password(), &self.game.state) {
                     violated_rules.push(rule.clone());
                 }
@@ -58,33 +152,63 @@ impl DirectDriver {
         }
         Ok(violated_rules)
     }
-}
 
-impl Driver for DirectDriver {
-    fn new(solver: Solver) -> Result<Self, DriverError> {
-        Ok(DirectDriver {
-            game: Game::new(),
-            solver,
-        })
-    }
-
-    fn play(&mut self) -> Result<(), DriverError> {
+    /// Play the game until it's complete or a rule can't be satisfied (including when the
+    /// rules are still violated after `MAX_TURNS` rounds of solving them, which would otherwise
+    /// go on forever).
+    fn play_game(&mut self) -> Result<(), DriverError> {
         let mut violated_rules = self.get_violated_rules()?;
+        let mut turns = 0;
         while !violated_rules.is_empty() {
+            turns += 1;
+            if turns > MAX_TURNS {
+                return Err(DriverError::CouldNotSatisfyRule(
+                    violated_rules.pop().unwrap(),
+                ));
+            }
             info!(
+                target: DRIVER_DIRECT,
                 "Password: {:?}, violated rules: {:?}",
                 self.solver.password.as_str(),
                 violated_rules
             );
-            let first_rule = violated_rules.pop().unwrap();
-            let changes = self.solver.solve_rule(&first_rule, &self.game.state, 0);
-            if let Some(changes) = changes {
+            if let Some(plans) = self.solver.solve_all(&violated_rules, &self.game.state) {
+                for plan in &plans {
+                    self.changes_made += plan.changes.len();
+                    self.solver.carry_out(plan)?;
+                }
+            } else {
+                // Fall back to the highest violated rule on its own
+                let first_rule = violated_rules.pop().unwrap();
+                let Some(plan) = self
+                    .solver
+                    .plan_rule(&first_rule, &self.game.state)
+                    .filter(|plan| self.solver.password.preview_changes(&plan.changes).is_ok())
+                else {
+                    return Err(DriverError::CouldNotSatisfyRule(first_rule));
+                };
+                self.changes_made += plan.changes.len();
+                self.solver.carry_out(&plan)?;
+            }
+            if self.game.state.paul.hatched {
+                let bugs = self
+                    .solver
+                    .password
+                    .as_str()
+                    .graphemes(true)
+                    .filter(|g| *g == "🐛")
+                    .count();
+                self.game
+                    .state
+                    .paul
+                    .count_bugs(bugs, self.game.state.clock.now());
+            }
+            if let Some(changes) = self.solver.refresh_time_string(&self.game.state) {
+                self.changes_made += changes.len();
                 for change in changes {
                     self.solver.password.queue_change(change);
                 }
-                self.solver.password.commit_changes();
-            } else {
-                return Err(DriverError::CouldNotSatisfyRule(first_rule));
+                self.solver.password.commit_changes()?;
             }
             if self.game.state.sacrificed_letters != self.solver.sacrificed_letters {
                 self.game.state.sacrificed_letters.clear();
@@ -96,7 +220,86 @@ impl Driver for DirectDriver {
 
             violated_rules = self.get_violated_rules()?;
         }
-        info!("Game complete!");
+        info!(target: DRIVER_DIRECT, "Game complete!");
         Ok(())
     }
+
+    /// Write the HTML visualization of the game, if requested.
+    fn write_visualization(&self) {
+        let Some(path) = &self.visualization_path else {
+            return;
+        };
+        let html = visualize::render_html(
+            self.solver.password.raw_password(),
+            &self.game.rules,
+            &self.game.state,
+        );
+        match std::fs::write(path, html) {
+            Ok(()) => {
+                info!(target: DRIVER_DIRECT, "Wrote game visualization to {}", path.display())
+            }
+            Err(e) => warn!(target: DRIVER_DIRECT, "Failed to write game visualization: {}", e),
+        }
+    }
+}
+
+impl Driver for DirectDriver {
+    fn new(solver: Solver, config: DriverConfig) -> Result<Self, DriverError> {
+        http::configure(config.http);
+        youtube::configure(config.videos_path.as_deref(), config.youtube_search_budget);
+        let (mut game, rng) = match config.seed {
+            Some(seed) => (Game::new_with_seed(seed), StdRng::seed_from_u64(seed)),
+            None => (Game::new(), StdRng::from_entropy()),
+        };
+        game.state.clock = config.clock;
+        game.state.lookup = config.lookup;
+        game.state.ruleset = config.ruleset.clone();
+        Ok(DirectDriver {
+            game,
+            solver,
+            rng,
+            changes_made: 0,
+            reroll: config.reroll,
+            visualization_path: None,
+        })
+    }
+
+    fn play(&mut self) -> Result<(), DriverError> {
+        let result = self.play_game();
+        self.write_visualization();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DirectDriver;
+    use crate::{
+        driver::{Driver, DriverConfig},
+        game::data::CAPTCHAS,
+        solver::Solver,
+    };
+
+    #[test]
+    fn reroll_captcha() {
+        let config = DriverConfig {
+            seed: Some(42),
+            ..Default::default()
+        };
+        let mut driver = DirectDriver::new(Solver::default(), config).unwrap();
+
+        // Sacrificed letters are never accepted, so a captcha containing one is rerolled
+        let letter = driver
+            .game
+            .captcha()
+            .unwrap()
+            .chars()
+            .find(char::is_ascii_lowercase)
+            .unwrap();
+        driver.game.state.sacrificed_letters.push(letter);
+        driver.reroll_captcha();
+        let answer = driver.game.captcha().unwrap();
+        assert!(!answer.contains(letter));
+        assert!(CAPTCHAS.iter().any(|c| c.answer == answer));
+    }
 }
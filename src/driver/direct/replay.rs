@@ -0,0 +1,165 @@
+use chrono::{DateTime, Local};
+use log::{info, warn};
+use std::path::Path;
+use strum::IntoEnumIterator;
+
+use super::DirectDriver;
+use crate::{
+    driver::DriverError,
+    game::rule::{Rule, RuleKind},
+    logging::DRIVER_DIRECT,
+    recorder::{self, Event},
+};
+
+impl DirectDriver {
+    /// Replay a recorded playthrough against the current password logic and rules, to help
+    /// debug regressions after a failed run. The recorded changes are applied to our password
+    /// and, each time the rules were checked, the password and violated rules are compared
+    /// with what was recorded. Returns the number of divergences found (each is logged).
+    pub fn replay(&mut self, path: &Path) -> Result<usize, DriverError> {
+        let events = recorder::load(path)?;
+        info!(target: DRIVER_DIRECT, "Replaying {} events from {}", events.len(), path.display());
+
+        let mut started = Local::now();
+        let mut divergences = 0;
+        for record in events {
+            match record.event {
+                Event::Start { started: time } => {
+                    if let Ok(time) = DateTime::parse_from_rfc3339(&time) {
+                        started = time.with_timezone(&Local);
+                    }
+                }
+                Event::Changes { changes } => {
+                    for change in changes {
+                        self.solver.password.queue_change(change);
+                    }
+                    self.solver.password.commit_changes()?;
+                }
+                Event::Rules {
+                    highest_rule,
+                    password,
+                    violated,
+                } => {
+                    if self.solver.password.as_str() != password {
+                        warn!(
+                            target: DRIVER_DIRECT,
+                            "At {}ms: replayed password {:?}, recorded {:?}",
+                            record.elapsed_ms,
+                            self.solver.password.as_str(),
+                            password
+                        );
+                        divergences += 1;
+                    }
+
+                    self.sync_state(highest_rule);
+                    let time = started + chrono::Duration::milliseconds(record.elapsed_ms as i64);
+                    let password = self.solver.password.raw_password();
+                    for rule in &violated {
+                        if rule.validate_at_time(password, &self.game.state, &time) {
+                            warn!(
+                                target: DRIVER_DIRECT,
+                                "At {}ms: {:?} was violated, but validates",
+                                record.elapsed_ms, rule
+                            );
+                            divergences += 1;
+                        }
+                    }
+                    // Rules with instance data can only be checked if they were violated, and
+                    // the sacrificed letters aren't recorded
+                    for kind in RuleKind::iter().filter(|kind| {
+                        kind.number() <= highest_rule
+                            && !kind.has_instance()
+                            && *kind != RuleKind::Sacrifice
+                            && !violated.iter().any(|rule| rule.kind() == *kind)
+                    }) {
+                        let rule = Rule::from(kind);
+                        if !rule.validate_at_time(password, &self.game.state, &time) {
+                            warn!(
+                                target: DRIVER_DIRECT,
+                                "At {}ms: {:?} wasn't violated, but fails validation",
+                                record.elapsed_ms, rule
+                            );
+                            divergences += 1;
+                        }
+                    }
+                }
+                Event::Cursor { .. } => {}
+                Event::End { error } => match error {
+                    Some(error) => {
+                        info!(target: DRIVER_DIRECT, "Recorded run ended with error: {}", error)
+                    }
+                    None => info!(target: DRIVER_DIRECT, "Recorded run was won"),
+                },
+            }
+        }
+        info!(target: DRIVER_DIRECT, "Replay found {} divergences", divergences);
+        Ok(divergences)
+    }
+
+    /// Bring the game state up to date with a recorded highest rule.
+    fn sync_state(&mut self, highest_rule: usize) {
+        let state = &mut self.game.state;
+        state.highest_rule = highest_rule;
+        state.egg_placed = highest_rule >= Rule::Egg.number();
+        state.fire_started = highest_rule >= Rule::Fire.number();
+        state.paul.hatched = highest_rule >= Rule::Hatch.number();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use crate::{
+        driver::{direct::DirectDriver, Driver, DriverConfig},
+        game::Rule,
+        password::Change,
+        recorder::{Event, EventRecord},
+        solver::Solver,
+        storage::to_record,
+    };
+
+    #[test]
+    fn replay() {
+        let events = [
+            Event::Changes {
+                changes: vec![Change::Append {
+                    string: "abc".to_owned(),
+                    protected: false,
+                }],
+            },
+            Event::Rules {
+                highest_rule: 2,
+                password: "abc".to_owned(),
+                violated: vec![Rule::Number, Rule::MinLength],
+            },
+            Event::Changes {
+                changes: vec![Change::Append {
+                    string: "12".to_owned(),
+                    protected: false,
+                }],
+            },
+            // Wrong password, a violated rule which validates, and a missing violated rule
+            Event::Rules {
+                highest_rule: 3,
+                password: "abc1".to_owned(),
+                violated: vec![Rule::Number],
+            },
+        ];
+        let path = std::env::temp_dir().join(format!("replay-test-{}.jsonl", std::process::id()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        for (i, event) in events.into_iter().enumerate() {
+            let record = EventRecord {
+                elapsed_ms: i as u64,
+                event,
+            };
+            writeln!(file, "{}", to_record(&record).unwrap()).unwrap();
+        }
+        drop(file);
+
+        let mut driver = DirectDriver::new(Solver::default(), DriverConfig::default()).unwrap();
+        let divergences = driver.replay(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(divergences, 3);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{
+    game::{GameState, Rule},
+    password::{Format, Password},
+};
+
+/// Styling for the visualization, loosely following the real game's look.
+const STYLE: &str = r#"body { font-family: sans-serif; max-width: 600px; margin: 40px auto; }
+.password { border: 1px solid #999; border-radius: 12px; padding: 16px; font-family: Monospace; font-size: 28px; overflow-wrap: anywhere; }
+.rule { border-radius: 8px; margin: 8px 0; padding: 8px 12px; }
+.rule.pass { background: #e4ffe4; border: 1px solid #5a5; }
+.rule.fail { background: #ffe4e4; border: 1px solid #d55; }"#;
+
+/// Render a standalone HTML page showing the password with its formatting, followed by a
+/// checklist of the rules reached so far and whether each is satisfied.
+pub fn render_html(password: &Password, rules: &[Rule], game_state: &GameState) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Password Game</title>\n<style>\n");
+    html.push_str(STYLE);
+    html.push_str("\n</style>\n</head>\n<body>\n<div class=\"password\">");
+
+    // Group runs of graphemes with the same formatting into a single span
+    let mut run: Option<(&Format, String)> = None;
+    for (_, grapheme, format) in password.annotated() {
+        match &mut run {
+            Some((run_format, text)) if *run_format == format => text.push_str(grapheme),
+            _ => {
+                if let Some((run_format, text)) = run.take() {
+                    push_span(&mut html, run_format, &text);
+                }
+                run = Some((format, grapheme.to_owned()));
+            }
+        }
+    }
+    if let Some((run_format, text)) = run {
+        push_span(&mut html, run_format, &text);
+    }
+    html.push_str("</div>\n");
+
+    for rule in rules
+        .iter()
+        .filter(|rule| rule.number() <= game_state.highest_rule)
+    {
+        let (class, mark) = if rule.validate(password, game_state) {
+            ("pass", "✔")
+        } else {
+            ("fail", "✘")
+        };
+        html.push_str(&format!(
+            "<div class=\"rule {}\">{} Rule {}: {}</div>\n",
+            class,
+            mark,
+            rule.number(),
+            escape(&format!("{:?}", rule))
+        ));
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Append a span with the given formatting as inline CSS.
+fn push_span(html: &mut String, format: &Format, text: &str) {
+    html.push_str(&format!(
+        "<span style=\"font-family: {}; font-size: {}px; font-weight: {}; font-style: {}\">{}</span>",
+        format.font_family.css_name(),
+        format.font_size.px(),
+        if format.bold { "bold" } else { "normal" },
+        if format.italic { "italic" } else { "normal" },
+        escape(text)
+    ));
+}
+
+/// Escape text for inclusion in HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_html;
+    use crate::{
+        game::{GameState, Rule},
+        password::{FormatChange, Password},
+    };
+
+    #[test]
+    fn render() {
+        let mut password = Password::from_str("a<b1");
+        password.format(0, &FormatChange::BoldOn);
+        let game_state = GameState {
+            highest_rule: 2,
+            ..Default::default()
+        };
+        let html = render_html(
+            &password,
+            &[Rule::MinLength, Rule::Number, Rule::Uppercase],
+            &game_state,
+        );
+
+        assert!(html.contains(
+            "<span style=\"font-family: Monospace; font-size: 28px; font-weight: bold; font-style: normal\">a</span>"
+        ));
+        assert!(html.contains("font-style: normal\">&lt;b1</span>"));
+        assert!(html.contains("<div class=\"rule fail\">✘ Rule 1: MinLength</div>"));
+        assert!(html.contains("<div class=\"rule pass\">✔ Rule 2: Number</div>"));
+        assert!(!html.contains("Uppercase"));
+    }
+}
@@ -1,14 +1,34 @@
+use std::{path::PathBuf, time::Duration};
 use thiserror::Error;
 
-use crate::{game::Rule, solver::Solver};
+use crate::{
+    game::{
+        clock::SharedClock,
+        lookup::{LookupError, SharedLookup},
+        ruleset::RuleSet,
+        Rule,
+    },
+    http::{self, HttpConfig, HttpError},
+    password::ChangeError,
+    solver::{scoring::ScoringBudget, strategy::StrategyKind, Solver},
+    storage::StorageError,
+    youtube::{self, DEFAULT_SEARCH_BUDGET},
+};
+use web::{control::Control, tui::Tui, InputBackend};
 
+#[cfg(feature = "async")]
+pub mod async_web;
 pub mod direct;
+pub mod prefetch;
 pub mod web;
 
+/// The real game.
+const GAME_URL: &str = "https://neal.fun/password-game/";
+
 /// Defines a password game driver that a bot can use to play the game.
 pub trait Driver {
-    /// Construct a new instance of the driver with the given solver.
-    fn new(solver: Solver) -> Result<Self, DriverError>
+    /// Construct a new instance of the driver with the given solver and config.
+    fn new(solver: Solver, config: DriverConfig) -> Result<Self, DriverError>
     where
         Self: Sized;
 
@@ -16,8 +36,119 @@ pub trait Driver {
     fn play(&mut self) -> Result<(), DriverError>;
 }
 
+/// Defines a password game driver whose waits on the game don't block, so it can work in the
+/// background (e.g., feeding Paul) while it plays. Needs a tokio runtime.
+#[cfg(feature = "async")]
+pub trait AsyncDriver {
+    /// Construct a new instance of the driver with the given solver and config.
+    fn new(
+        solver: Solver,
+        config: DriverConfig,
+    ) -> impl std::future::Future<Output = Result<Self, DriverError>>
+    where
+        Self: Sized;
+
+    /// Play the game.
+    fn play(&mut self) -> impl std::future::Future<Output = Result<(), DriverError>>;
+}
+
+/// Options for launching a driver. Drivers which don't use a browser ignore the browser ones.
+#[derive(Debug, Clone)]
+pub struct DriverConfig {
+    /// Whether to run the browser without a window (e.g., on a CI box).
+    pub headless: bool,
+    /// How long the browser can go without any commands before it's closed.
+    pub idle_browser_timeout: Duration,
+    /// URL of the game, which can point to a locally hosted copy.
+    pub game_url: String,
+    /// Source of the current time for time-sensitive rules. Should only be replaced when
+    /// driving a simulated game.
+    pub clock: SharedClock,
+    /// Source of the outside information some rules need (e.g., the Wordle answer). Should only
+    /// be replaced when driving a simulated game.
+    pub lookup: SharedLookup,
+    /// Retries and timeouts for requests to neal.fun and YouTube.
+    pub http: HttpConfig,
+    /// How key presses for moving the caret and navigating the page are sent.
+    pub input: InputBackend,
+    /// Seed for random choices, so a run can be repeated: the rules and events of a simulated
+    /// game, and the solver's choices when the driver has to start over with a fresh solver.
+    /// Chosen randomly if not set.
+    pub seed: Option<u64>,
+    /// How the solver chooses between equally valid solutions.
+    pub strategy: StrategyKind,
+    /// How hard to try to find cheap captchas and hex colors when rerolling them.
+    pub reroll: ScoringBudget,
+    /// Longest any rule can stay violated before the playthrough is abandoned. No limit if not
+    /// set.
+    pub rule_timeout: Option<Duration>,
+    /// Longest a playthrough can take before it's abandoned. No limit if not set.
+    pub game_timeout: Option<Duration>,
+    /// YouTube video database to load (in the format written by `youtube harvest`), rather
+    /// than the copy built into the binary.
+    pub videos_path: Option<PathBuf>,
+    /// How long to spend searching YouTube for a video of the length the YouTube rule asks for,
+    /// if the database doesn't have one. Zero to never search.
+    pub youtube_search_budget: Duration,
+    /// The numbers in the rules, for the version of the game being played.
+    pub ruleset: RuleSet,
+    /// Whether to look up the outside information rules might need (e.g., the Wordle answer)
+    /// in the background as soon as the driver starts. Only the web driver does.
+    pub prefetch: bool,
+    /// Where to save a snapshot of the run after every turn, so it can be resumed if the bot
+    /// crashes. Off if not set.
+    pub snapshot_path: Option<PathBuf>,
+    /// Lets the user pause the bot and take over the password box. Only the web driver can be
+    /// paused.
+    pub control: Option<Control>,
+    /// Terminal UI to show the game in progress on. Only the web driver shows it.
+    pub tui: Option<Tui>,
+    /// Webhook to post the outcome of each playthrough to (in Discord's message format). Only
+    /// the web driver posts.
+    pub webhook_url: Option<String>,
+}
+
+impl Default for DriverConfig {
+    fn default() -> Self {
+        DriverConfig {
+            headless: false,
+            idle_browser_timeout: Duration::from_secs(10 * 60),
+            game_url: GAME_URL.to_owned(),
+            clock: SharedClock::default(),
+            lookup: SharedLookup::default(),
+            http: HttpConfig::default(),
+            input: InputBackend::default(),
+            seed: None,
+            strategy: StrategyKind::default(),
+            reroll: ScoringBudget::default(),
+            rule_timeout: None,
+            game_timeout: None,
+            videos_path: None,
+            youtube_search_budget: DEFAULT_SEARCH_BUDGET,
+            ruleset: RuleSet::default(),
+            prefetch: true,
+            snapshot_path: None,
+            control: None,
+            tui: None,
+            webhook_url: None,
+        }
+    }
+}
+
+/// Configure the lookups for a game in a browser, and start looking up everything the rules
+/// might need (if prefetching is on).
+fn prepare_lookups(config: &DriverConfig) {
+    http::configure(config.http.clone());
+    youtube::configure(config.videos_path.as_deref(), config.youtube_search_budget);
+    if config.prefetch {
+        // Left to finish in the background, filling the helpers' caches
+        prefetch::start(config);
+    }
+}
+
 /// Failure modes for drivers.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum DriverError {
     #[error("could not satisfy rule {0:?}")]
     CouldNotSatisfyRule(Rule),
@@ -25,13 +156,49 @@ pub enum DriverError {
     GameOver,
     #[error("lost password sync")]
     LostSync,
+    #[error("page reloaded unexpectedly")]
+    PageReloaded,
+    #[error("run went stale while the machine was asleep")]
+    StaleRun,
+    #[error("timed out on rule {0:?}")]
+    Timeout(Rule),
+    #[error("game timed out after {0:?}")]
+    GameTimeout(Duration),
     #[error("launch options builder failed")]
     LaunchOptionsBuilderError,
     #[cfg(target_os = "macos")]
     #[error("apple script error")]
     AppleScriptError,
+    #[cfg(target_os = "linux")]
+    #[error("key injection failed: {0}")]
+    KeyInjection(String),
     #[error("headless chrome error")]
     HeadlessChrome(#[from] anyhow::Error),
-    #[error("failed to deserialize game rule")]
-    RuleDeserialization(#[from] serde_plain::Error),
+    #[cfg(feature = "async")]
+    #[error("chromiumoxide error")]
+    Chromiumoxide(Box<chromiumoxide::error::CdpError>),
+    #[cfg(feature = "async")]
+    #[error("background task failed")]
+    Task(#[from] tokio::task::JoinError),
+    #[error("network error")]
+    Network(#[from] HttpError),
+    #[error("lookup failed")]
+    Lookup(#[from] LookupError),
+    #[error("invalid change: {0}")]
+    InvalidChange(#[from] ChangeError),
+    #[error("malformed element: {0}")]
+    MalformedElement(String),
+    #[error("unknown rule {0:?}")]
+    UnknownRule(String),
+    #[error("can't resume run: {0}")]
+    Resume(String),
+    #[error("failed to load recording")]
+    Recording(#[from] StorageError),
+}
+
+#[cfg(feature = "async")]
+impl From<chromiumoxide::error::CdpError> for DriverError {
+    fn from(e: chromiumoxide::error::CdpError) -> Self {
+        DriverError::Chromiumoxide(Box::new(e))
+    }
 }
@@ -0,0 +1,105 @@
+//! Warming the caches of the helpers which look things up for rules, in the background as soon
+//! as a game starts, so solving a rule never waits on the network or on loading a database.
+//!
+//! Only what can be known before the rule appears is fetched: today's Wordle answer, the
+//! reverse geocoder and the country of every Geo game, and the best move in every chess puzzle
+//! (which starts the chess engine). The moon phase is worked out locally, and the chess board
+//! image and video to check depend on the rule, so they're still looked up when needed. The
+//! video database is loaded by `youtube::configure`.
+
+use log::{debug, warn};
+use ordered_float::NotNan;
+use std::{
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+use super::DriverConfig;
+use crate::{
+    game::{
+        data::{CHESS_PUZZLES, GEO_GAMES},
+        helpers::{get_optimal_move, warm_up_geocoder},
+        rule::Coords,
+    },
+    logging::PREFETCH,
+};
+
+/// Start looking everything up, each kind of lookup on its own thread. The results end up in
+/// the helpers' caches, so the handles only need joining to wait for them.
+pub fn start(config: &DriverConfig) -> Vec<JoinHandle<()>> {
+    let lookup = config.lookup.clone();
+    let date = config.clock.now().date_naive();
+    let wordle = spawn("Wordle answer", move || {
+        if let Err(e) = lookup.wordle_answer(date) {
+            warn!(target: PREFETCH, "Couldn't prefetch the Wordle answer: {}", e);
+        }
+    });
+
+    let lookup = config.lookup.clone();
+    let countries = spawn("countries", move || {
+        warm_up_geocoder();
+        for game in GEO_GAMES.iter() {
+            let (lat, long) = game.coordindates;
+            let (Ok(lat), Ok(long)) = (NotNan::new(lat), NotNan::new(long)) else {
+                continue;
+            };
+            lookup.country(&Coords { lat, long });
+        }
+    });
+
+    let chess_moves = spawn("chess moves", || {
+        for puzzle in CHESS_PUZZLES.iter() {
+            get_optimal_move(fen_as_read(&puzzle.fen));
+        }
+    });
+
+    vec![wordle, countries, chess_moves]
+}
+
+/// Run the given lookup on a new thread, logging how long it took.
+fn spawn(name: &'static str, lookup: impl FnOnce() + Send + 'static) -> JoinHandle<()> {
+    thread::Builder::new()
+        .name(format!("prefetch {}", name))
+        .spawn(move || {
+            let start = Instant::now();
+            lookup();
+            debug!(
+                target: PREFETCH,
+                "Prefetched {} in {:.2}s",
+                name,
+                start.elapsed().as_secs_f32()
+            );
+        })
+        .expect("failed to spawn prefetch thread")
+}
+
+/// The given puzzle's FEN as the web driver reads it from the puzzle image, so it's cached
+/// under the same key. The image only shows the board, so castling and en passant are never
+/// available.
+fn fen_as_read(fen: &str) -> String {
+    let mut fields = fen.split_whitespace();
+    let board = fields.next().unwrap_or_default();
+    let to_move = fields.next().unwrap_or("w");
+    format!("{} {} - - 0 1", board, to_move)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fen_as_read;
+    use crate::driver::web::helpers::extract_fen_from_svg;
+
+    #[test]
+    fn puzzle_fen_as_read() {
+        assert_eq!(
+            fen_as_read("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 0 1"),
+            "r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w - - 0 1"
+        );
+
+        // Matches what's read from the puzzle image
+        let svg = "<svg><desc><pre>. . . . . . . k\n. . . . . . . .\n. . . . . . . .\n. . . . . . . .\n. . . . . . . .\n. . . . . . . .\n. . . . . . . .\nK . . . . . . .</pre></desc></svg>";
+        assert_eq!(
+            extract_fen_from_svg(svg, 'b').unwrap(),
+            fen_as_read("7k/8/8/8/8/8/8/K7 b - - 0 1")
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use log::{info, warn};
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use crate::logging::DRIVER_WEB;
+
+/// Lets the user pause the bot to take over the password box for a while (e.g., to fix a rule
+/// it's stuck on), via a small HTTP endpoint on a local port: a POST to `/pause` pauses, a POST
+/// to `/resume` resumes, and anything else (e.g., a GET, which a browser might make just by
+/// prefetching the link) only shows whether the bot is paused. Shared by every run, so it only
+/// has to be started once.
+#[derive(Debug, Clone)]
+pub struct Control {
+    paused: Arc<AtomicBool>,
+    port: u16,
+}
+
+impl Control {
+    /// Start listening on the given local port (or one chosen by the OS if it's 0).
+    pub fn listen(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let control = Control {
+            paused: Arc::new(AtomicBool::new(false)),
+            port: listener.local_addr()?.port(),
+        };
+        let paused = control.paused.clone();
+        thread::Builder::new()
+            .name("control".into())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if let Err(e) = stream.and_then(|stream| respond(stream, &paused)) {
+                        warn!(target: DRIVER_WEB, "Failed to handle control request: {}", e);
+                    }
+                }
+            })?;
+        info!(
+            target: DRIVER_WEB,
+            "Pause the bot with a POST to http://127.0.0.1:{}/pause",
+            control.port
+        );
+        Ok(control)
+    }
+
+    /// Has the user asked the bot to pause?
+    pub fn paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+}
+
+/// Act on the request on the given connection, and reply with whether the bot is now paused.
+fn respond(mut stream: TcpStream, paused: &AtomicBool) -> io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("GET");
+    let path = parts.next().unwrap_or("/");
+    let status = act(method, path, paused);
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status.len(),
+        status
+    )
+}
+
+/// Pause or resume the bot if the request is a POST to a path which asks to, returning whether
+/// it's paused.
+fn act(method: &str, path: &str, paused: &AtomicBool) -> &'static str {
+    match (method, path.trim_end_matches('/')) {
+        ("POST", "/pause") => paused.store(true, Ordering::SeqCst),
+        ("POST", "/resume") => paused.store(false, Ordering::SeqCst),
+        _ => {}
+    }
+    if paused.load(Ordering::SeqCst) {
+        "paused\n"
+    } else {
+        "playing\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+    };
+
+    use super::Control;
+
+    fn request(control: &Control, method: &str, path: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", control.port)).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            method, path
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn pause_and_resume() {
+        let control = Control::listen(0).unwrap();
+        assert!(!control.paused());
+        assert!(request(&control, "GET", "/").ends_with("playing\n"));
+        // Only a POST changes anything
+        assert!(request(&control, "GET", "/pause").ends_with("playing\n"));
+        assert!(!control.paused());
+
+        let response = request(&control, "POST", "/pause");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("paused\n"));
+        assert!(control.paused());
+        // Clones see the same state, so every run can share one
+        assert!(control.clone().paused());
+
+        request(&control, "POST", "/resume/");
+        assert!(!control.paused());
+    }
+}
@@ -0,0 +1,176 @@
+use log::debug;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::logging::DRIVER_WEB;
+
+/// How often the watchdog checks whether Paul is due a feed.
+const WATCHDOG_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Input the watchdog asks the play loop to interleave with whatever it's doing, at the next
+/// point where it's safe to move the cursor away and back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputRequest {
+    /// Top up Paul's bugs.
+    FeedPaul,
+}
+
+/// State shared between the driver and the watchdog thread.
+#[derive(Debug)]
+struct Shared {
+    /// Time when Paul was last fed, if he's hatched.
+    last_fed: Mutex<Option<Instant>>,
+    /// How long Paul can go between feeds.
+    interval: Mutex<Duration>,
+    /// Input waiting to be interleaved by the play loop.
+    queue: Mutex<VecDeque<InputRequest>>,
+    stop: AtomicBool,
+}
+
+impl Shared {
+    /// Queue a feed if Paul is due one and one isn't queued already.
+    fn check(&self, now: Instant) {
+        let Some(last_fed) = *self.last_fed.lock().unwrap() else {
+            return;
+        };
+        if now.saturating_duration_since(last_fed) < *self.interval.lock().unwrap() {
+            return;
+        }
+        let mut queue = self.queue.lock().unwrap();
+        if !queue.contains(&InputRequest::FeedPaul) {
+            debug!(target: DRIVER_WEB, "Paul is due a feed, queueing one");
+            queue.push_back(InputRequest::FeedPaul);
+        }
+    }
+}
+
+/// Watches how long it's been since Paul was fed from a background thread, so he can be fed
+/// in the middle of long operations (like formatting passes) rather than only between rules.
+/// The watchdog never types itself, as only the play loop knows where the cursor is; it queues
+/// requests which the play loop services at safe points (between changes, between captcha and
+/// colour rerolls, and in the middle of YouTube searches).
+#[derive(Debug)]
+pub struct Feeder {
+    shared: Arc<Shared>,
+    watchdog: Option<JoinHandle<()>>,
+}
+
+impl Feeder {
+    /// Start a watchdog which asks for a feed every `interval`.
+    pub fn new(interval: Duration) -> Self {
+        let shared = Arc::new(Shared {
+            last_fed: Mutex::new(None),
+            interval: Mutex::new(interval),
+            queue: Mutex::new(VecDeque::new()),
+            stop: AtomicBool::new(false),
+        });
+        let watchdog = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                while !shared.stop.load(Ordering::Relaxed) {
+                    shared.check(Instant::now());
+                    thread::park_timeout(WATCHDOG_POLL_INTERVAL);
+                }
+            })
+        };
+        Feeder {
+            shared,
+            watchdog: Some(watchdog),
+        }
+    }
+
+    /// Change how long Paul can go between feeds.
+    pub fn set_interval(&self, interval: Duration) {
+        *self.shared.interval.lock().unwrap() = interval;
+    }
+
+    /// Record that Paul has just been fed, dropping any queued feed.
+    pub fn fed(&self) {
+        *self.shared.last_fed.lock().unwrap() = Some(Instant::now());
+        self.shared
+            .queue
+            .lock()
+            .unwrap()
+            .retain(|r| *r != InputRequest::FeedPaul);
+    }
+
+    /// Forget about Paul, e.g., when starting a new game.
+    pub fn reset(&self) {
+        *self.shared.last_fed.lock().unwrap() = None;
+        self.shared.queue.lock().unwrap().clear();
+    }
+
+    /// When Paul will next be due a feed, if he's hatched.
+    pub fn next_feed(&self) -> Option<Instant> {
+        let last_fed = (*self.shared.last_fed.lock().unwrap())?;
+        Some(last_fed + *self.shared.interval.lock().unwrap())
+    }
+
+    /// Take the next queued request, if any.
+    pub fn take_request(&self) -> Option<InputRequest> {
+        self.shared.queue.lock().unwrap().pop_front()
+    }
+}
+
+impl Drop for Feeder {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        if let Some(watchdog) = self.watchdog.take() {
+            watchdog.thread().unpark();
+            let _ = watchdog.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Feeder, InputRequest};
+
+    #[test]
+    fn watchdog() {
+        let feeder = Feeder::new(Duration::from_secs(60));
+        let shared = feeder.shared.clone();
+        let now = Instant::now();
+
+        // Not hatched yet
+        shared.check(now);
+        assert_eq!(feeder.take_request(), None);
+
+        assert_eq!(feeder.next_feed(), None);
+
+        feeder.fed();
+        shared.check(now);
+        assert_eq!(feeder.take_request(), None);
+        assert!(feeder.next_feed().unwrap() > now + Duration::from_secs(59));
+
+        // Only queued once, however long it's been
+        let later = Instant::now() + Duration::from_secs(61);
+        shared.check(later);
+        shared.check(later);
+        assert_eq!(feeder.take_request(), Some(InputRequest::FeedPaul));
+        assert_eq!(feeder.take_request(), None);
+
+        // Feeding drops a queued request
+        shared.check(later);
+        feeder.fed();
+        assert_eq!(feeder.take_request(), None);
+
+        // The interval can be shortened after tuning the pacing
+        feeder.set_interval(Duration::from_secs(1));
+        shared.check(Instant::now() + Duration::from_secs(2));
+        assert_eq!(feeder.take_request(), Some(InputRequest::FeedPaul));
+
+        feeder.reset();
+        shared.check(later);
+        assert_eq!(feeder.take_request(), None);
+    }
+}
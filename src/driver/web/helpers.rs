@@ -7,22 +7,106 @@ use lightningcss::{
     values::{length, percentage},
 };
 use scraper::{Html, Node, Selector};
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem,
+};
 use svg::parser::Event;
+use thiserror::Error;
 use unicode_segmentation::UnicodeSegmentation;
 
+use ordered_float::NotNan;
+
 use crate::{
-    game::rule::Color,
-    password::{format, Format},
+    game::rule::{Color, Coords},
+    password::{format, Change, Format, FormatChange, Password},
 };
 
+/// Ways the password's HTML can fail to parse as formatting, most likely because the game's
+/// markup has changed.
+#[derive(Debug, PartialEq, Error)]
+#[non_exhaustive]
+pub enum FormatParseError {
+    #[error("no paragraph in the password box")]
+    MissingParagraph,
+    #[error("unknown element {0:?}")]
+    UnknownElement(String),
+    #[error("unknown css property {0:?}")]
+    UnknownProperty(String),
+    #[error("unknown font {0:?}")]
+    UnknownFont(String),
+    #[error("unknown font size {0:?}")]
+    UnknownFontSize(String),
+}
+
+/// Parse the properties from an inline style attribute.
+fn parse_style(style: &str) -> Result<Vec<Property<'_>>, FormatParseError> {
+    style
+        .split(';')
+        .filter(|part| !part.trim().is_empty())
+        .map(|part| {
+            let unknown = || FormatParseError::UnknownProperty(part.trim().to_owned());
+            let (property_id_str, property_str) = part.split_once(':').ok_or_else(unknown)?;
+            let property_id =
+                PropertyId::parse_string(property_id_str.trim()).map_err(|_| unknown())?;
+            Property::parse_string(property_id, property_str, ParserOptions::default())
+                .map_err(|_| unknown())
+        })
+        .collect()
+}
+
+/// Apply a property from a span's style to the current format.
+fn apply_property(property: &Property, format: &mut Format) -> Result<(), FormatParseError> {
+    match property {
+        Property::FontFamily(font_families) => {
+            let name = match font_families.first() {
+                Some(font::FontFamily::Generic(generic)) => generic.as_str().to_owned(),
+                Some(font::FontFamily::FamilyName(name)) => name.to_string(),
+                None => String::new(),
+            };
+            format.font_family = format::FontFamily::from_css_name(&name)
+                .ok_or(FormatParseError::UnknownFont(name))?;
+        }
+        Property::FontSize(font::FontSize::Length(percentage::DimensionPercentage::Dimension(
+            length::LengthValue::Px(px),
+        ))) => {
+            format.font_size = format::FontSize::try_from(*px as u32)
+                .map_err(|_| FormatParseError::UnknownFontSize(format!("{}px", px)))?;
+        }
+        Property::FontSize(font_size) => {
+            return Err(FormatParseError::UnknownFontSize(format!(
+                "{:?}",
+                font_size
+            )));
+        }
+        p => {
+            return Err(FormatParseError::UnknownProperty(format!(
+                "{:?}",
+                p.property_id()
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// Reset the aspect of the current format set by a property, at the end of its span.
+fn reset_property(property: &Property, format: &mut Format) {
+    match property {
+        Property::FontFamily(_) => format.font_family = format::FontFamily::default(),
+        Property::FontSize(_) => format.font_size = format::FontSize::default(),
+        // Anything else was rejected when the span was opened
+        _ => {}
+    }
+}
+
 /// Parse formatting from raw HTML.
-pub fn parse_formatting(html: &str) -> Vec<Format> {
+pub fn parse_formatting(html: &str) -> Result<Vec<Format>, FormatParseError> {
     let fragment = Html::parse_fragment(html);
     let p = fragment
         .select(&Selector::parse("p").unwrap())
         .next()
-        .unwrap();
-    // let password = p.text().collect::<Vec<_>>().join("");
+        .ok_or(FormatParseError::MissingParagraph)?;
 
     let mut current_format = Format::default();
     let mut formatting = Vec::new();
@@ -31,77 +115,8 @@ pub fn parse_formatting(html: &str) -> Vec<Format> {
             Edge::Open(node) => match node.value() {
                 Node::Element(e) => match e.name() {
                     "span" => {
-                        if let Some(style) = e.attr("style") {
-                            for part in style.split(';') {
-                                if part.trim().is_empty() {
-                                    continue;
-                                }
-                                let (property_id_str, property_str) =
-                                    part.split_once(':').unwrap_or_else(|| {
-                                        panic!("style property should contain a `:`: {:?}", part)
-                                    });
-                                let property_id =
-                                    PropertyId::parse_string(property_id_str).unwrap();
-                                let property = Property::parse_string(
-                                    property_id,
-                                    property_str,
-                                    ParserOptions::default(),
-                                )
-                                .unwrap();
-                                match property {
-                                    Property::FontFamily(font_families) => {
-                                        match font_families.first().unwrap() {
-                                            font::FontFamily::Generic(generic) => match generic {
-                                                font::GenericFontFamily::Monospace => {
-                                                    current_format.font_family =
-                                                        format::FontFamily::Monospace;
-                                                }
-                                                f => panic!("unexpected font {:?}", f),
-                                            },
-                                            font::FontFamily::FamilyName(name) => {
-                                                match name.to_string().as_str() {
-                                                    "Comic Sans" => {
-                                                        current_format.font_family =
-                                                            format::FontFamily::ComicSans;
-                                                    }
-                                                    "Wingdings" => {
-                                                        current_format.font_family =
-                                                            format::FontFamily::Wingdings;
-                                                    }
-                                                    "Times New Roman" => {
-                                                        current_format.font_family =
-                                                            format::FontFamily::TimesNewRoman;
-                                                    }
-                                                    f => panic!("unexpected font {:?}", f),
-                                                }
-                                            }
-                                        }
-                                    }
-                                    Property::FontSize(font_size) => match font_size {
-                                        font::FontSize::Length(l) => match l {
-                                            percentage::DimensionPercentage::Dimension(d) => {
-                                                match d {
-                                                    length::LengthValue::Px(px) => {
-                                                        match format::FontSize::try_from(px as u32)
-                                                        {
-                                                            Ok(s) => current_format.font_size = s,
-                                                            Err(_) => {
-                                                                panic!("invalid font size {:?}", px)
-                                                            }
-                                                        }
-                                                    }
-                                                    d => panic!("unexpected font size {:?}", d),
-                                                }
-                                            }
-                                            l => panic!("unexpected font size {:?}", l),
-                                        },
-                                        s => panic!("unexpected font size {:?}", s),
-                                    },
-                                    p => {
-                                        panic!("unexpected css property {:?}", p)
-                                    }
-                                }
-                            }
+                        for property in parse_style(e.attr("style").unwrap_or_default())? {
+                            apply_property(&property, &mut current_format)?;
                         }
                     }
                     "strong" => {
@@ -112,7 +127,7 @@ pub fn parse_formatting(html: &str) -> Vec<Format> {
                     }
                     "p" => {}
                     e => {
-                        panic!("unexpected element {:?}", e);
+                        return Err(FormatParseError::UnknownElement(e.to_owned()));
                     }
                 },
                 Node::Text(t) => {
@@ -122,86 +137,238 @@ pub fn parse_formatting(html: &str) -> Vec<Format> {
                         }
                     }
                 }
-                n => {
-                    panic!("unexpected node {:?}", n)
-                }
+                // Comments and the like don't affect the password
+                _ => {}
             },
-            Edge::Close(node) => match node.value() {
-                Node::Element(e) => match e.name() {
-                    "span" => {
-                        if let Some(style) = e.attr("style") {
-                            for part in style.split(';') {
-                                if part.trim().is_empty() {
-                                    continue;
-                                }
-                                let (property_id_str, property_str) = part.split_once(':').unwrap();
-                                let property_id =
-                                    PropertyId::parse_string(property_id_str).unwrap();
-                                let property = Property::parse_string(
-                                    property_id,
-                                    property_str,
-                                    ParserOptions::default(),
-                                )
-                                .unwrap();
-                                match property {
-                                    Property::FontFamily(_) => {
-                                        current_format.font_family = format::FontFamily::default();
-                                    }
-                                    Property::FontSize(_) => {
-                                        current_format.font_size = format::FontSize::default();
-                                    }
-                                    p => {
-                                        panic!("unexpected css property {:?}", p)
-                                    }
-                                }
+            Edge::Close(node) => {
+                if let Node::Element(e) = node.value() {
+                    match e.name() {
+                        "span" => {
+                            for property in parse_style(e.attr("style").unwrap_or_default())? {
+                                reset_property(&property, &mut current_format);
                             }
                         }
+                        "strong" => {
+                            current_format.bold = false;
+                        }
+                        "em" => {
+                            current_format.italic = false;
+                        }
+                        _ => {}
                     }
-                    "strong" => {
-                        current_format.bold = false;
-                    }
-                    "em" => {
-                        current_format.italic = false;
+                }
+            }
+        }
+    }
+    Ok(formatting)
+}
+
+/// Group the given formatting into contiguous runs of non-default formatting, as
+/// `(start index, length, change)` tuples, such that applying each change to its run (starting
+/// from default formatting) reproduces the formatting.
+pub fn format_runs(formatting: &[Format]) -> Vec<(usize, usize, FormatChange)> {
+    let change_at = |f: &Format| {
+        [
+            f.bold.then_some(FormatChange::BoldOn),
+            f.italic.then_some(FormatChange::ItalicOn),
+            (f.font_size != format::FontSize::default())
+                .then(|| FormatChange::FontSize(f.font_size.clone())),
+            (f.font_family != format::FontFamily::default())
+                .then(|| FormatChange::FontFamily(f.font_family.clone())),
+        ]
+    };
+
+    let mut runs = Vec::new();
+    for aspect in 0..4 {
+        let mut current: Option<(usize, usize, FormatChange)> = None;
+        for (i, f) in formatting.iter().enumerate() {
+            let change = change_at(f)[aspect].clone();
+            match (&mut current, change) {
+                (Some((_, length, c)), Some(change)) if *c == change => *length += 1,
+                (_, change) => {
+                    if let Some(run) = current.take() {
+                        runs.push(run);
                     }
-                    "p" => {}
-                    e => {
-                        panic!("unexpected element {:?}", e);
+                    current = change.map(|c| (i, 1, c));
+                }
+            }
+        }
+        if let Some(run) = current {
+            runs.push(run);
+        }
+    }
+    runs
+}
+
+/// Group the graphemes whose formatting on the page (`actual`) differs from ours (`expected`)
+/// into contiguous runs, as `(start index, length, change)` tuples like `format_runs`, such that
+/// applying each change to its run fixes just that aspect of it. Bold and italic are fixed by
+/// toggling them, so each of their runs is all on or all off on the page.
+pub fn format_repairs(expected: &[Format], actual: &[Format]) -> Vec<(usize, usize, FormatChange)> {
+    // Each change is keyed on what the page has, so toggles aren't applied to mixed runs
+    let repair_at = |e: &Format, a: &Format| {
+        [
+            (e.bold != a.bold).then_some((FormatChange::BoldOn, a.bold)),
+            (e.italic != a.italic).then_some((FormatChange::ItalicOn, a.italic)),
+            (e.font_size != a.font_size)
+                .then(|| (FormatChange::FontSize(e.font_size.clone()), false)),
+            (e.font_family != a.font_family)
+                .then(|| (FormatChange::FontFamily(e.font_family.clone()), false)),
+        ]
+    };
+
+    let mut runs = Vec::new();
+    for aspect in 0..4 {
+        let mut current: Option<(usize, usize, (FormatChange, bool))> = None;
+        for (i, (e, a)) in expected.iter().zip(actual).enumerate() {
+            let repair = repair_at(e, a)[aspect].clone();
+            match (&mut current, repair) {
+                (Some((_, length, r)), Some(repair)) if *r == repair => *length += 1,
+                (_, repair) => {
+                    if let Some((start, length, (change, _))) = current.take() {
+                        runs.push((start, length, change));
                     }
-                },
-                Node::Text(_) => {}
-                n => {
-                    panic!("unexpected node {:?}", n)
+                    current = repair.map(|r| (i, 1, r));
                 }
+            }
+        }
+        if let Some((start, length, (change, _))) = current {
+            runs.push((start, length, change));
+        }
+    }
+    runs
+}
+
+/// Group the format changes among the given changes into as few selections as possible, as
+/// `(start index, length, changes)` tuples, such that applying all of a selection's changes to
+/// its range makes them. Runs of a font size or family can extend over graphemes which already
+/// have it (and aren't having it changed), but bold and italic are toggles, so their runs only
+/// cover the graphemes being changed.
+pub fn plan_selections(
+    changes: &[Change],
+    formatting: &[Format],
+) -> Vec<(usize, usize, Vec<FormatChange>)> {
+    let mut indices: BTreeMap<&FormatChange, Vec<usize>> = BTreeMap::new();
+    let mut changing = HashSet::new();
+    for change in changes {
+        if let Change::Format {
+            index,
+            format_change,
+        } = change
+        {
+            indices.entry(format_change).or_default().push(*index);
+            changing.insert((mem::discriminant(format_change), *index));
+        }
+    }
+    let already_has = |format_change: &FormatChange, index: usize| {
+        !changing.contains(&(mem::discriminant(format_change), index))
+            && match format_change {
+                FormatChange::BoldOn | FormatChange::ItalicOn => false,
+                FormatChange::FontSize(font_size) => formatting[index].font_size == *font_size,
+                FormatChange::FontFamily(font_family) => {
+                    formatting[index].font_family == *font_family
+                }
+            }
+    };
+
+    let mut selections: BTreeMap<(usize, usize), Vec<FormatChange>> = BTreeMap::new();
+    for (format_change, mut indices) in indices {
+        indices.sort();
+        indices.dedup();
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for index in indices {
+            match runs.last_mut() {
+                Some((start, length))
+                    if (*start + *length..index).all(|i| already_has(format_change, i)) =>
+                {
+                    *length = index + 1 - *start;
+                }
+                _ => runs.push((index, 1)),
+            }
+        }
+        for run in runs {
+            selections
+                .entry(run)
+                .or_default()
+                .push(format_change.clone());
+        }
+    }
+    selections
+        .into_iter()
+        .map(|((start, length), format_changes)| (start, length, format_changes))
+        .collect()
+}
+
+/// Build a ProseMirror JSON document for the given password, with formatting as marks,
+/// followed by the given number of bugs.
+pub fn prosemirror_doc(password: &Password, bugs: usize) -> serde_json::Value {
+    let text_node = |text: String, format: &Format| {
+        let mut marks = Vec::new();
+        if format.bold {
+            marks.push(json!({ "type": "bold" }));
+        }
+        if format.italic {
+            marks.push(json!({ "type": "italic" }));
+        }
+        marks.push(json!({
+            "type": "textStyle",
+            "attrs": {
+                "fontFamily": format.font_family.css_name(),
+                "fontSize": format!("{}px", format.font_size.px()),
             },
+        }));
+        json!({ "type": "text", "text": text, "marks": marks })
+    };
+
+    // Group graphemes with the same formatting into a single text node
+    let mut content = Vec::new();
+    let mut current: Option<(String, &Format)> = None;
+    for (grapheme, format) in password
+        .as_str()
+        .graphemes(true)
+        .zip(password.formatting().iter())
+    {
+        match &mut current {
+            Some((text, f)) if *f == format => text.push_str(grapheme),
+            _ => {
+                if let Some((text, f)) = current.take() {
+                    content.push(text_node(text, f));
+                }
+                current = Some((grapheme.to_owned(), format));
+            }
         }
     }
-    formatting
+    if let Some((text, f)) = current {
+        content.push(text_node(text, f));
+    }
+    if bugs > 0 {
+        content.push(text_node("🐛".repeat(bugs), &Format::default()));
+    }
+
+    json!({
+        "type": "doc",
+        "content": [{ "type": "paragraph", "content": content }],
+    })
 }
 
-/// Extract chess FEN from chess puzzle SVG.
-pub fn extract_fen_from_svg(svg_contents: &str, turn: char) -> String {
+/// Extract chess FEN from chess puzzle SVG, if it's valid SVG with a board in it.
+pub fn extract_fen_from_svg(svg_contents: &str, turn: char) -> Option<String> {
     let mut in_pre = false;
     let mut pre = None;
-    for event in svg::read(svg_contents).unwrap() {
+    for event in svg::read(svg_contents).ok()? {
         match event {
-            Event::Tag(path, tag_type, _) => {
-                if path == "pre" {
-                    match tag_type {
-                        svg::node::element::tag::Type::Start => in_pre = true,
-                        svg::node::element::tag::Type::End => break,
-                        _ => {}
-                    }
-                }
-            }
-            Event::Text(text) => {
-                if in_pre {
-                    pre = Some(text);
-                }
+            Event::Tag("pre", tag_type, _) => match tag_type {
+                svg::node::element::tag::Type::Start => in_pre = true,
+                svg::node::element::tag::Type::End => break,
+                _ => {}
+            },
+            Event::Text(text) if in_pre => {
+                pre = Some(text);
             }
             _ => {}
         }
     }
-    let pre = pre.unwrap();
+    let pre = pre?;
 
     let mut fen = String::new();
     for rank in pre.lines() {
@@ -234,7 +401,82 @@ pub fn extract_fen_from_svg(svg_contents: &str, turn: char) -> String {
     fen.push(turn);
     fen.push_str(" - - 0 1");
 
-    fen
+    Some(fen)
+}
+
+/// Pair up a flat list of attribute names and values, as returned by CDP. A trailing name
+/// without a value is given an empty value, as for valueless attributes in HTML.
+pub fn attributes_from_list(list: Vec<String>) -> HashMap<String, String> {
+    let mut attribs = HashMap::new();
+    let mut list = list.into_iter();
+    while let Some(name) = list.next() {
+        attribs.insert(name, list.next().unwrap_or_default());
+    }
+    attribs
+}
+
+/// Get the coordinates from a Google Maps embed URL.
+///
+/// The `pb` parameter is a list of `!`-separated fields, each a field number, a type letter
+/// and a value. Rather than relying on fixed positions, this looks for the coordinate fields
+/// wherever they are:
+/// - Street View embeds have `!1d<lat>!2d<long>`.
+/// - Place embeds have `!1d<distance>!2d<long>!3d<lat>`.
+pub fn extract_coords_from_embed_url(url: &str) -> Option<Coords> {
+    let url = urlencoding::decode(url).ok()?;
+    let query = url.split_once('?').map(|(_, q)| q).unwrap_or(&url);
+    let pb = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("pb="))
+        .unwrap_or(query);
+
+    let fields = pb.split('!').collect::<Vec<&str>>();
+    let double = |index: usize, number: &str| -> Option<f64> {
+        let value = fields.get(index)?.strip_prefix(number)?.strip_prefix('d')?;
+        value.parse::<f64>().ok().filter(|v| v.is_finite())
+    };
+    for i in 0..fields.len() {
+        let (lat, long) = if let (Some(long), Some(lat)) = (double(i, "2"), double(i + 1, "3")) {
+            (lat, long)
+        } else if let (Some(lat), Some(long)) = (double(i, "1"), double(i + 1, "2")) {
+            if double(i + 2, "3").is_some() {
+                // Place embed, so the coordinates are in the next two fields
+                continue;
+            }
+            (lat, long)
+        } else {
+            continue;
+        };
+        if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&long) {
+            return Some(Coords {
+                lat: NotNan::new(lat).ok()?,
+                long: NotNan::new(long).ok()?,
+            });
+        }
+    }
+    None
+}
+
+/// Get the captcha's answer from the captcha image's `src`, which is the image's filename
+/// (percent-decoded, as it may contain non-ASCII characters).
+pub fn extract_captcha_from_img_src(src: &str) -> Option<String> {
+    let (name, _) = src.split('/').find_map(|part| part.rsplit_once(".png"))?;
+    Some(
+        urlencoding::decode(name)
+            .map(|n| n.into_owned())
+            .unwrap_or_else(|_| name.to_owned()),
+    )
+}
+
+/// Get the video duration (in seconds) from the YouTube rule's text.
+pub fn extract_duration_from_rule_text(text: &str) -> Option<u32> {
+    let captures = regex!(r"(\d+) minute(?: (\d+) second)?").captures(text)?;
+    let minutes = captures.get(1)?.as_str().parse::<u32>().ok()?;
+    let seconds = match captures.get(2) {
+        Some(seconds) => seconds.as_str().parse::<u32>().ok()?,
+        None => 0,
+    };
+    Some(minutes * 60 + seconds)
 }
 
 /// Get RGB color from CSS style.
@@ -250,13 +492,20 @@ pub fn extract_color_from_css_style(style: &str) -> Color {
 
 #[cfg(test)]
 mod tests {
-    use super::{extract_fen_from_svg, parse_formatting};
-    use crate::password::Format;
+    use super::{
+        attributes_from_list, extract_captcha_from_img_src, extract_coords_from_embed_url,
+        extract_duration_from_rule_text, extract_fen_from_svg, format_repairs, format_runs,
+        parse_formatting, plan_selections, prosemirror_doc, FormatParseError,
+    };
+    use crate::password::{
+        format::{FontFamily, FontSize},
+        Change, Format, FormatChange, Password,
+    };
 
     #[test]
     fn formatting() {
         let html = "<div contenteditable=\"true\" translate=\"no\" class=\"ProseMirror ProseMirror-focused\" tabindex=\"0\"><p><span style=\"font-family: Monospace; font-size: 28px\">🥚b<strong>a</strong>n<strong>ua</strong>g🏋\u{fe0f}\u{200d}♂\u{fe0f}c<strong>a</strong></span></p></div>";
-        let formatting = parse_formatting(html);
+        let formatting = parse_formatting(html).unwrap();
         assert_eq!(
             formatting,
             vec![
@@ -274,6 +523,189 @@ mod tests {
         );
     }
 
+    #[test]
+    fn formatting_font_families() {
+        let html = "<div><p><span style=\"font-family: Comic Sans\">a</span><span style=\"font-family: &quot;Comic Sans MS&quot;, cursive\">b</span><span style=\"font-family: monospace\">c</span></p></div>";
+        let comic_sans = Format {
+            font_family: FontFamily::ComicSans,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_formatting(html).unwrap(),
+            vec![comic_sans.clone(), comic_sans, Format::default()]
+        );
+    }
+
+    #[test]
+    fn formatting_malformed() {
+        let parse = |inner: &str| parse_formatting(&format!("<div><p>{}</p></div>", inner));
+        assert_eq!(
+            parse("<u>a</u>"),
+            Err(FormatParseError::UnknownElement("u".to_owned()))
+        );
+        assert_eq!(
+            parse("<span style=\"font-family: Papyrus\">a</span>"),
+            Err(FormatParseError::UnknownFont("Papyrus".to_owned()))
+        );
+        assert_eq!(
+            parse("<span style=\"font-size: 13px\">a</span>"),
+            Err(FormatParseError::UnknownFontSize("13px".to_owned()))
+        );
+        assert!(matches!(
+            parse("<span style=\"color: red\">a</span>"),
+            Err(FormatParseError::UnknownProperty(_))
+        ));
+        assert!(matches!(
+            parse("<span style=\"font-size\">a</span>"),
+            Err(FormatParseError::UnknownProperty(_))
+        ));
+        assert_eq!(
+            parse_formatting("<div>a</div>"),
+            Err(FormatParseError::MissingParagraph)
+        );
+        // Comments are ignored
+        assert_eq!(parse("a<!-- b -->"), Ok(vec![Format::default()]));
+    }
+
+    #[test]
+    fn formatting_runs() {
+        let formatting = vec![
+            Format::bold(),
+            Format::bold(),
+            Format::default(),
+            Format {
+                bold: true,
+                font_size: FontSize::Px36,
+                ..Default::default()
+            },
+            Format {
+                font_size: FontSize::Px36,
+                font_family: FontFamily::Wingdings,
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            format_runs(&formatting),
+            vec![
+                (0, 2, FormatChange::BoldOn),
+                (3, 1, FormatChange::BoldOn),
+                (3, 2, FormatChange::FontSize(FontSize::Px36)),
+                (4, 1, FormatChange::FontFamily(FontFamily::Wingdings)),
+            ]
+        );
+        assert!(format_runs(&vec![Format::default(); 3]).is_empty());
+    }
+
+    #[test]
+    fn formatting_repairs() {
+        let expected = vec![
+            Format::bold(),
+            Format::bold(),
+            Format::default(),
+            Format::default(),
+            Format {
+                font_size: FontSize::Px36,
+                ..Default::default()
+            },
+        ];
+        assert!(format_repairs(&expected, &expected).is_empty());
+
+        // Only the grapheme which lost its bold is touched
+        let mut actual = expected.clone();
+        actual[1].bold = false;
+        assert_eq!(
+            format_repairs(&expected, &actual),
+            vec![(1, 1, FormatChange::BoldOn)]
+        );
+
+        // Adjacent graphemes needing bold toggled in opposite directions are separate runs
+        actual[2].bold = true;
+        actual[3].bold = true;
+        actual[3].font_size = FontSize::Px36;
+        actual[4].font_size = FontSize::Px12;
+        assert_eq!(
+            format_repairs(&expected, &actual),
+            vec![
+                (1, 1, FormatChange::BoldOn),
+                (2, 2, FormatChange::BoldOn),
+                (3, 1, FormatChange::FontSize(FontSize::default())),
+                (4, 1, FormatChange::FontSize(FontSize::Px36)),
+            ]
+        );
+    }
+
+    #[test]
+    fn selections() {
+        let format = |index, format_change| Change::Format {
+            index,
+            format_change,
+        };
+        let wingdings = FormatChange::FontFamily(FontFamily::Wingdings);
+        let mut formatting = vec![Format::default(); 8];
+        formatting[2].font_family = FontFamily::Wingdings;
+        formatting[5].font_family = FontFamily::Wingdings;
+        let changes = vec![
+            format(0, wingdings.clone()),
+            format(1, wingdings.clone()),
+            format(3, wingdings.clone()),
+            format(4, wingdings.clone()),
+            format(0, FormatChange::ItalicOn),
+            format(1, FormatChange::ItalicOn),
+            format(3, FormatChange::ItalicOn),
+            format(6, FormatChange::FontSize(FontSize::Px36)),
+            format(6, FormatChange::ItalicOn),
+            format(5, FormatChange::FontFamily(FontFamily::TimesNewRoman)),
+            format(7, wingdings.clone()),
+            Change::Append {
+                string: "abc".into(),
+                protected: false,
+            },
+        ];
+        assert_eq!(
+            plan_selections(&changes, &formatting),
+            vec![
+                // Bold and italic can't be toggled over graphemes which aren't changing
+                (0, 2, vec![FormatChange::ItalicOn]),
+                // Fonts can be set over graphemes which already have them
+                (0, 5, vec![wingdings.clone()]),
+                (3, 1, vec![FormatChange::ItalicOn]),
+                // But not over graphemes which are changing to something else
+                (
+                    5,
+                    1,
+                    vec![FormatChange::FontFamily(FontFamily::TimesNewRoman)]
+                ),
+                // Changes to the same graphemes are made in one selection
+                (
+                    6,
+                    1,
+                    vec![
+                        FormatChange::ItalicOn,
+                        FormatChange::FontSize(FontSize::Px36)
+                    ]
+                ),
+                (7, 1, vec![wingdings.clone()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn prosemirror_document() {
+        let mut password = Password::from_str("ab🥚");
+        password.format(0, &FormatChange::BoldOn);
+        password.format(1, &FormatChange::BoldOn);
+        password.format(2, &FormatChange::FontFamily(FontFamily::Wingdings));
+        let doc = prosemirror_doc(&password, 2);
+        let content = &doc["content"][0]["content"];
+        assert_eq!(content.as_array().unwrap().len(), 3);
+        assert_eq!(content[0]["text"], "ab");
+        assert_eq!(content[0]["marks"][0]["type"], "bold");
+        assert_eq!(content[0]["marks"][1]["attrs"]["fontSize"], "28px");
+        assert_eq!(content[1]["text"], "🥚");
+        assert_eq!(content[1]["marks"][0]["attrs"]["fontFamily"], "Wingdings");
+        assert_eq!(content[2]["text"], "🐛🐛");
+    }
+
     #[test]
     fn extract_fen() {
         let svg_contents = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" version="1.2" baseProfile="tiny" viewBox="0 0 390 390"><desc><pre>r . b . . k . r
@@ -285,8 +717,114 @@ mod tests {
             P P P . . P P P
             R . . . R . K .</pre></desc></svg>"#;
         assert_eq!(
-            extract_fen_from_svg(svg_contents, 'w'),
+            extract_fen_from_svg(svg_contents, 'w').unwrap(),
             "r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 0 1"
         );
+        assert_eq!(extract_fen_from_svg("<svg></svg>", 'w'), None);
+    }
+
+    #[test]
+    fn attributes() {
+        let list = ["class", "rule", "src", "/a.png"]
+            .map(String::from)
+            .to_vec();
+        let attribs = attributes_from_list(list);
+        assert_eq!(attribs.len(), 2);
+        assert_eq!(attribs["class"], "rule");
+        assert_eq!(attribs["src"], "/a.png");
+
+        // Valueless trailing attribute
+        let attribs = attributes_from_list(["id", "x", "disabled"].map(String::from).to_vec());
+        assert_eq!(attribs["id"], "x");
+        assert_eq!(attribs["disabled"], "");
+
+        assert!(attributes_from_list(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn coords_from_embed_url() {
+        let coords = |url| {
+            extract_coords_from_embed_url(url).map(|c| (c.lat.into_inner(), c.long.into_inner()))
+        };
+
+        // Street View embed, as used by the game
+        assert_eq!(
+            coords(
+                "https://www.google.com/maps/embed?pb=!4v1688019437536!6m8!1m7\
+                !1sCAoSLEFGMVFpcE1fVkFUa2Z0!2m2!1d48.8583701!2d2.2944813!3f0!4f0!5f0.78"
+            ),
+            Some((48.8583701, 2.2944813))
+        );
+        // Extra parameters, with fields in a different position
+        assert_eq!(
+            coords(
+                "https://www.google.com/maps/embed?hl=de&pb=!6m8!1m7!1sabc!2m2\
+                !1d-33.8567844!2d151.2152967!3f75!4f0!5f0.78&gl=de"
+            ),
+            Some((-33.8567844, 151.2152967))
+        );
+        // Percent-encoded separators
+        assert_eq!(
+            coords(
+                "https://www.google.com/maps/embed?pb=%216m8%211m7%211sabc%212m2\
+                %211d40.6892494%212d-74.0445004%213f0"
+            ),
+            Some((40.6892494, -74.0445004))
+        );
+        // Place embed, where 1d is the viewing distance
+        assert_eq!(
+            coords(
+                "https://www.google.com/maps/embed?pb=!1m18!1m12!1m3!1d3022.2\
+                !2d-73.9856644!3d40.7484405!2m3!1f0!2f0!3f0"
+            ),
+            Some((40.7484405, -73.9856644))
+        );
+        // Place embed with a small viewing distance
+        assert_eq!(
+            coords("https://www.google.com/maps/embed?pb=!1m3!1d45.1!2d-0.1276!3d51.5072"),
+            Some((51.5072, -0.1276))
+        );
+
+        assert_eq!(
+            coords("https://www.google.com/maps/embed?pb=!4v1!6m8"),
+            None
+        );
+        assert_eq!(
+            coords("https://www.google.com/maps/embed?pb=!1d123.4!2d567.8"),
+            None
+        );
+    }
+
+    #[test]
+    fn captcha_from_img_src() {
+        assert_eq!(
+            extract_captcha_from_img_src("/password-game/captchas/bnwpm.png").as_deref(),
+            Some("bnwpm")
+        );
+        assert_eq!(
+            extract_captcha_from_img_src("/captchas/y5%C3%A9n8.png?v=2").as_deref(),
+            Some("y5\u{e9}n8")
+        );
+        assert_eq!(extract_captcha_from_img_src("/captchas/bnwpm.svg"), None);
+    }
+
+    #[test]
+    fn duration_from_rule_text() {
+        assert_eq!(
+            extract_duration_from_rule_text(
+                "Your password must include the URL of a 12 minute 3 second long YouTube video."
+            ),
+            Some(723)
+        );
+        assert_eq!(
+            extract_duration_from_rule_text(
+                "Your password must include the URL of a 7 minute long YouTube video."
+            ),
+            Some(420)
+        );
+        assert_eq!(
+            extract_duration_from_rule_text("Your password must include"),
+            None
+        );
     }
 }
@@ -0,0 +1,115 @@
+//! Key presses sent over DevTools as `Input.dispatchKeyEvent`s. Editing keys carry the editor
+//! command they should trigger, so caret movement and selection behave the same on every OS
+//! (and headless), without needing OS-level focus.
+
+use headless_chrome::{browser::tab::ModifierKey, protocol::cdp::Input, Tab};
+use serde::Deserialize;
+
+use crate::driver::DriverError;
+
+/// How key presses which move the caret or navigate the page are sent.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputBackend {
+    /// Dispatch key events over DevTools. Works on any OS, and headless.
+    #[default]
+    Cdp,
+    /// Inject key presses via the OS (`winapi` on Windows, `osascript` on macOS, `xdotool` or
+    /// `wtype` on Linux). Needs a visible browser window which keeps focus. Falls back to
+    /// DevTools where there's no OS backend.
+    Os,
+}
+
+/// Keys sent through the input backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Left,
+    Right,
+    Up,
+    Down,
+    Tab,
+    Backspace,
+}
+
+impl Key {
+    /// The DOM `key` (which is also its `code`), and the Windows virtual key code.
+    fn definition(self) -> (&'static str, u32) {
+        match self {
+            Key::Left => ("ArrowLeft", 37),
+            Key::Right => ("ArrowRight", 39),
+            Key::Up => ("ArrowUp", 38),
+            Key::Down => ("ArrowDown", 40),
+            Key::Tab => ("Tab", 9),
+            Key::Backspace => ("Backspace", 8),
+        }
+    }
+
+    /// The editor command the key triggers in a text field, if any.
+    fn command(self, shift: bool) -> Option<&'static str> {
+        match (self, shift) {
+            (Key::Left, false) => Some("moveLeft"),
+            (Key::Left, true) => Some("moveLeftAndModifySelection"),
+            (Key::Right, false) => Some("moveRight"),
+            (Key::Right, true) => Some("moveRightAndModifySelection"),
+            (Key::Backspace, _) => Some("deleteBackward"),
+            _ => None,
+        }
+    }
+}
+
+/// Press and release a key, holding shift if `shift` is true.
+pub fn press(tab: &Tab, key: Key, shift: bool) -> Result<(), DriverError> {
+    let (name, key_code) = key.definition();
+    let modifiers = if shift { ModifierKey::Shift as u32 } else { 0 };
+    let commands = key.command(shift).map(|command| vec![command.to_owned()]);
+    dispatch(tab, name, name, key_code, modifiers, commands)
+}
+
+/// Select everything in the focused field with Ctrl/Cmd+A.
+pub fn select_all(tab: &Tab) -> Result<(), DriverError> {
+    #[cfg(target_os = "macos")]
+    let modifier = ModifierKey::Meta;
+    #[cfg(not(target_os = "macos"))]
+    let modifier = ModifierKey::Ctrl;
+    dispatch(
+        tab,
+        "a",
+        "KeyA",
+        65,
+        modifier as u32,
+        Some(vec!["selectAll".to_owned()]),
+    )
+}
+
+fn dispatch(
+    tab: &Tab,
+    key: &str,
+    code: &str,
+    key_code: u32,
+    modifiers: u32,
+    commands: Option<Vec<String>>,
+) -> Result<(), DriverError> {
+    for (event_type, commands) in [
+        (Input::DispatchKeyEventTypeOption::RawKeyDown, commands),
+        (Input::DispatchKeyEventTypeOption::KeyUp, None),
+    ] {
+        tab.call_method(Input::DispatchKeyEvent {
+            Type: event_type,
+            modifiers: Some(modifiers),
+            timestamp: None,
+            text: None,
+            unmodified_text: None,
+            key_identifier: None,
+            code: Some(code.to_owned()),
+            key: Some(key.to_owned()),
+            windows_virtual_key_code: Some(key_code),
+            native_virtual_key_code: Some(key_code),
+            auto_repeat: None,
+            is_keypad: None,
+            is_system_key: None,
+            location: None,
+            commands,
+        })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use lazy_static::lazy_static;
+use log::trace;
+use std::{collections::HashMap, process::Command};
+
+use crate::{driver::DriverError, logging::DRIVER_WEB};
+
+/// Pause between repeated key presses, in milliseconds.
+const REPEAT_DELAY_MS: usize = 10;
+
+lazy_static! {
+    pub static ref KEYS: HashMap<&'static str, &'static str> = {
+        let mut m = HashMap::new();
+        // X keysym names, understood by both xdotool and wtype
+        m.insert("Tab", "Tab");
+        m.insert("Backspace", "BackSpace");
+        m.insert("LeftArrow", "Left");
+        m.insert("RightArrow", "Right");
+        m.insert("UpArrow", "Up");
+        m.insert("DownArrow", "Down");
+        m.insert("0", "0");
+        m
+    };
+    static ref TOOL: Option<Tool> = Tool::detect();
+}
+
+/// Command line tools for injecting key presses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tool {
+    /// For X11 sessions.
+    Xdotool,
+    /// For Wayland sessions.
+    Wtype,
+}
+
+impl Tool {
+    /// Pick the tool for the current desktop session, if there is one and the tool is installed.
+    fn detect() -> Option<Self> {
+        let tool = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            Tool::Wtype
+        } else if std::env::var_os("DISPLAY").is_some() {
+            Tool::Xdotool
+        } else {
+            return None;
+        };
+        Command::new(tool.program())
+            .arg("--help")
+            .output()
+            .is_ok()
+            .then_some(tool)
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Tool::Xdotool => "xdotool",
+            Tool::Wtype => "wtype",
+        }
+    }
+
+    /// Arguments to press the given key `times` times, while holding `modifier` (if any).
+    fn key_args(self, modifier: Option<&str>, key: &str, times: usize) -> Vec<String> {
+        match self {
+            Tool::Xdotool => {
+                let combo = match modifier {
+                    Some(modifier) => format!("{}+{}", modifier, key),
+                    None => key.to_owned(),
+                };
+                vec![
+                    "key".to_owned(),
+                    "--delay".to_owned(),
+                    REPEAT_DELAY_MS.to_string(),
+                    "--repeat".to_owned(),
+                    times.to_string(),
+                    combo,
+                ]
+            }
+            Tool::Wtype => {
+                let mut args = Vec::new();
+                if let Some(modifier) = modifier {
+                    args.extend(["-M".to_owned(), modifier.to_owned()]);
+                }
+                for i in 0..times {
+                    if i > 0 {
+                        args.extend(["-s".to_owned(), REPEAT_DELAY_MS.to_string()]);
+                    }
+                    args.extend(["-k".to_owned(), key.to_owned()]);
+                }
+                if let Some(modifier) = modifier {
+                    args.extend(["-m".to_owned(), modifier.to_owned()]);
+                }
+                args
+            }
+        }
+    }
+}
+
+/// Is there a desktop session and tool we can inject key presses with?
+pub fn available() -> bool {
+    TOOL.is_some()
+}
+
+fn run_tool(modifier: Option<&str>, key: &str, times: usize) -> Result<(), DriverError> {
+    let tool = TOOL.ok_or_else(|| DriverError::KeyInjection("no tool available".to_owned()))?;
+    let args = tool.key_args(modifier, key, times);
+    trace!(target: DRIVER_WEB, "Running {}: {:?}", tool.program(), args);
+    let output = Command::new(tool.program())
+        .args(&args)
+        .output()
+        .map_err(|e| DriverError::KeyInjection(e.to_string()))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(DriverError::KeyInjection(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ))
+    }
+}
+
+pub fn press_key(key: &str) -> Result<(), DriverError> {
+    run_tool(None, key, 1)
+}
+
+pub fn press_key_multiple(key: &str, times: usize) -> Result<(), DriverError> {
+    run_tool(None, key, times)
+}
+
+/// Press a key while holding the given modifier (e.g., "ctrl" or "shift").
+pub fn press_key_with_modifier(modifier: &str, key: &str) -> Result<(), DriverError> {
+    run_tool(Some(modifier), key, 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tool;
+
+    #[test]
+    fn key_args() {
+        assert_eq!(
+            Tool::Xdotool.key_args(Some("shift"), "Right", 1),
+            ["key", "--delay", "10", "--repeat", "1", "shift+Right"]
+        );
+        assert_eq!(
+            Tool::Wtype.key_args(None, "Left", 2),
+            ["-k", "Left", "-s", "10", "-k", "Left"]
+        );
+        assert_eq!(
+            Tool::Wtype.key_args(Some("ctrl"), "0", 1),
+            ["-M", "ctrl", "-k", "0", "-m", "ctrl"]
+        );
+    }
+}
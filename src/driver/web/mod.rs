@@ -1,39 +1,138 @@
-use anyhow::Context;
-use headless_chrome::{browser::tab::ModifierKey, Browser, LaunchOptionsBuilder, Tab};
-use lazy_regex::regex;
-use log::{debug, error, info, trace};
-use ordered_float::NotNan;
-use std::{collections::HashMap, sync::Arc, time::Instant};
+use headless_chrome::{
+    browser::tab::{element::Element, ModifierKey},
+    protocol::cdp::Page,
+    Browser, LaunchOptionsBuilder, Tab,
+};
+use log::{debug, error, info, trace, warn};
+use serde::Deserialize;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::PathBuf,
+    sync::Arc,
+    time::Instant,
+};
 use strum::EnumCount;
 use unicode_segmentation::UnicodeSegmentation;
 
-use super::{Driver, DriverError};
+use super::{prepare_lookups, Driver, DriverConfig, DriverError};
 use crate::{
-    game::{GameState, Rule},
+    game::{
+        rule::{RuleInstance, RuleKind},
+        GameState, Rule,
+    },
+    http,
+    logging::{DRIVER_WEB, DRIVER_WEB_CURSOR, DRIVER_WEB_FORMAT},
+    notifier::Notifier,
     password::{
+        diff::{diff, Edit},
         format::{FontFamily, FontSize},
-        Change, FormatChange,
+        keystrokes, Change, Format, FormatChange, Password,
+    },
+    recorder::{Event, Recorder},
+    solver::{
+        reroll::{RerollKind, Reroller},
+        risk::{self, RuleStats},
+        scoring::digit_budget,
+        Solver, SolverRng,
     },
-    solver::Solver,
+    stats::{PlayStats, StatsCollector},
+    youtube,
 };
-use helpers::{extract_color_from_css_style, extract_fen_from_svg, parse_formatting};
-
-mod helpers;
+use control::Control;
+use feeder::{Feeder, InputRequest};
+pub use helpers::extract_coords_from_embed_url;
+pub use input::InputBackend;
+pub use rule_list::UnknownRulePolicy;
+
+use helpers::{
+    attributes_from_list, extract_captcha_from_img_src, extract_color_from_css_style,
+    extract_duration_from_rule_text, extract_fen_from_svg, format_repairs, format_runs,
+    parse_formatting, plan_selections, prosemirror_doc,
+};
+use input::Key;
+use pacing::Pacing;
+use profile::GameProfile;
+use reload::ReloadWatcher;
+use report::ErrorReport;
+pub(crate) use rule_list::rule_classes;
+#[cfg(feature = "async")]
+pub(crate) use rule_list::SCAN_SCRIPT;
+use rule_list::{find_rule_element, scan_rule_errors};
+use snapshot::Snapshot;
+use stall::StallDetector;
+use toolbar::ToolbarState;
+use tui::{PaulStatus, RuleStatus, Status};
+use viewport::ViewportMetrics;
+
+pub mod control;
+mod feeder;
+pub mod helpers;
+mod input;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(all(test, feature = "os-integration"))]
+mod os_tests;
 #[cfg(target_os = "macos")]
 mod osascript;
+mod pacing;
+mod profile;
+mod reload;
+mod report;
+mod rule_list;
+pub mod snapshot;
+mod stall;
 #[cfg(test)]
 mod tests;
+mod toolbar;
+pub mod tui;
+mod viewport;
 #[cfg(target_os = "windows")]
 mod winapi;
 
-const RULE_VALIDATION_WAIT_TIME: std::time::Duration = std::time::Duration::from_millis(100);
-const GAME_URL: &str = "https://neal.fun/password-game/";
+/// Number of times to try retyping the password to resync it before giving up.
+const RESYNC_RETYPE_ATTEMPTS: usize = 2;
+/// Time to wait for the page to re-layout after resetting the zoom.
+const ZOOM_RESET_WAIT_TIME: std::time::Duration = std::time::Duration::from_millis(200);
+/// Longest to wait for the end screen after entering the final password.
+const END_SCREEN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Number of times to restart after the page is reloaded before giving up on this browser.
+const MAX_RELOAD_RESTARTS: usize = 3;
+/// String typed and deleted to measure key injection latency. Short enough not to satisfy the
+/// first rule.
+const LATENCY_PROBE: &str = "abcd";
+/// String copied and pasted to check the clipboard works.
+const CLIPBOARD_PROBE: &str = "wxyz";
+/// Id of the scratch box the clipboard check is run in, outside of the game.
+const CLIPBOARD_SCRATCH_ID: &str = "password-game-bot-clipboard-probe";
+/// Longest to wait for a key press to reach the page when measuring latency.
+const LATENCY_PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+/// How often to check whether a key press has reached the page when measuring latency. Short,
+/// to measure it closely, without polling the page so hard that it slows down.
+const LATENCY_PROBE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(1);
+/// Largest fraction of the password which can be on fire before it's quicker to retype the
+/// whole thing than to put out each run of fire.
+const MAX_BURNT_FRACTION: f64 = 0.5;
+/// Largest fraction of the password which can differ from the page for a resync to edit the
+/// page password in place, rather than retyping it.
+const MAX_REPAIR_FRACTION: f64 = 0.25;
+/// How often to check whether the user has resumed the bot, while it's paused.
+const PAUSE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
+/// Number of times to look for the game's tab when resuming, 200ms apart.
+const GAME_TAB_ATTEMPTS: usize = 25;
 
 /// A driver for the actual game at https://neal.fun/password-game/.
 pub struct WebDriver {
     /// A browser handle. Needs to be kept around because if it's dropped the connection
-    /// to the browser is closed.
-    _browser: Browser,
+    /// to the browser is closed. Only taken to leave the browser open after a failed run
+    /// which won't be retried, or a crash.
+    browser: Option<Browser>,
+    /// Port the browser accepts DevTools connections on, if we know it.
+    debug_port: Option<u16>,
+    /// Whether we're picking up a run from a snapshot, rather than starting a new one.
+    resumed: bool,
+    /// Whether to leave the browser open when the driver is dropped (see `detach`).
+    detached: bool,
     /// The active tab with the password game open.
     pub tab: Arc<Tab>,
     /// The solver which will attempt to play the game.
@@ -42,126 +141,304 @@ pub struct WebDriver {
     pub game_state: GameState,
     /// Position of the cursor in the password field.
     cursor: usize,
-    /// Time when we started playing the game.
+    /// Time when we started playing the game, moved on by any time spent paused since.
     start_time: Option<Instant>,
-    /// Time when Paul was last fed.
-    paul_last_fed: Option<Instant>,
+    /// Watches when Paul was last fed, and asks for feeds in the middle of long operations.
+    feeder: Feeder,
+    /// Notices if the machine sleeps during a run.
+    stall_detector: StallDetector,
+    /// How the password is re-entered into the final password box.
+    pub final_entry: FinalEntry,
+    /// If retyping the password fails to resync it, allow setting the password directly via
+    /// the page's editor API as a last resort. Off by default, as this bypasses "real" typing.
+    pub allow_dom_recovery: bool,
+    /// Most recently seen viewport metrics, used to translate click coordinates.
+    viewport: RefCell<Option<ViewportMetrics>>,
+    /// What we know of the toolbar's formatting state, forgotten whenever the cursor moves.
+    toolbar: RefCell<ToolbarState>,
+    /// Notices if the page is reloaded underneath us.
+    reload_watcher: ReloadWatcher,
+    /// How often each rule has been solved in previous runs.
+    rule_stats: RuleStats,
+    /// Structure of the game page seen so far this run.
+    profile: GameProfile,
+    /// How to work through the violated rules.
+    pub strategy: Strategy,
+    /// Whether to solve violated early rules (1-8) with a single batch of appends, rather than
+    /// one at a time. Off by default.
+    pub batch_early_rules: bool,
+    /// Whether to measure key injection latency before playing, and tune the pacing to match.
+    pub benchmark_pacing: bool,
+    /// Whether to insert whole strings in one round trip, rather than typing them a grapheme
+    /// at a time. Turn off if the editor mangles bulk inserts.
+    pub bulk_typing: bool,
+    /// Whether to choose fonts and font sizes in the toolbar's menus directly, rather than by
+    /// tabbing to them with the keyboard (which is still used if a menu can't be found).
+    pub dom_font_menus: bool,
+    /// Directory to save a report into (with a screenshot, the password box's HTML and our
+    /// password) when a run loses sync, can't satisfy a rule or times out. Off if not set.
+    pub error_report_dir: Option<PathBuf>,
+    /// What to do when the page shows a violated rule we don't know.
+    pub unknown_rules: UnknownRulePolicy,
+    /// Class names of the unknown rules seen so far.
+    unknown_rule_classes: BTreeSet<String>,
+    /// Every known rule seen so far, with its instance data as it was last seen violated.
+    revealed_rules: BTreeMap<RuleKind, Rule>,
+    /// Timing for interacting with the page.
+    pacing: Pacing,
+    /// Options the browser was launched with.
+    config: DriverConfig,
+    /// Key presses sent to the game so far.
+    keystrokes: Cell<usize>,
+    /// Estimated and actual key presses spent entering changes this run.
+    entry_keystrokes: (usize, usize),
+    /// Records the events of the current playthrough, once it's started.
+    recorder: Option<Recorder>,
+    /// Statistics for the current playthrough.
+    stats: StatsCollector,
+}
+
+/// Strategies for working through the violated rules.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Solve and type one rule at a time.
+    #[default]
+    RuleByRule,
+    /// When several rules are violated at once, solve them all (along with any satisfied rules
+    /// the solutions would break) before typing anything, then replace the whole password in
+    /// one go. Falls back to one rule at a time for rules which need extra interaction with
+    /// the game.
+    Compose,
+}
+
+/// Methods for re-entering the password into the final password box.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FinalEntry {
+    /// Use the clipboard if copying and pasting works in a quick self-test before playing, and
+    /// insert the text otherwise.
+    #[default]
+    Auto,
+    /// Copy and paste via the OS clipboard shortcuts. Formatting is carried over by the paste,
+    /// but the shortcuts differ per OS and sometimes fail.
+    Clipboard,
+    /// Insert the text via CDP `Input.insertText`, bypassing the clipboard, then re-apply
+    /// formatting.
+    InsertText,
 }
 
 impl Driver for WebDriver {
-    fn new(solver: crate::solver::Solver) -> Result<Self, DriverError> {
-        let browser = Browser::new(
-            LaunchOptionsBuilder::default()
-                .headless(false)
-                .idle_browser_timeout(std::time::Duration::from_secs(10 * 60))
-                .build()
-                .map_err(|_| DriverError::LaunchOptionsBuilderError)?,
-        )?;
+    fn new(solver: crate::solver::Solver, config: DriverConfig) -> Result<Self, DriverError> {
+        prepare_lookups(&config);
+        info!(target: DRIVER_WEB, "Playing with rule set {}", config.ruleset.version);
+        let mut driver = WebDriver::launch(solver, config)?;
+        driver.focus_password_box()?;
+        driver.profile = profile::probe(&driver.tab)?;
+        driver.profile.warn_if_changed();
+        Ok(driver)
+    }
 
-        let tabs = browser.get_tabs();
-        let tab = if tabs
-            .lock()
-            .expect("failed to get lock on browser tabs")
-            .is_empty()
-        {
-            browser.new_tab()?
-        } else {
-            tabs.lock()
-                .expect("failed to get lock on browser tabs")
-                .last()
-                .unwrap()
-                .clone()
-        };
-        tab.activate()?;
+    fn play(&mut self) -> Result<(), DriverError> {
+        if self.benchmark_pacing && !self.resumed {
+            self.tune_pacing()?;
+        }
+        if self.final_entry == FinalEntry::Auto {
+            // The self-test moves focus away from the password box, so isn't run part way
+            // through a game
+            self.final_entry = if self.resumed {
+                FinalEntry::InsertText
+            } else if self.clipboard_works()? {
+                FinalEntry::Clipboard
+            } else {
+                warn!(
+                    target: DRIVER_WEB,
+                    "Copy and paste doesn't work here, so the final password will be inserted"
+                );
+                FinalEntry::InsertText
+            };
+        }
 
-        tab.navigate_to(GAME_URL)?;
-        tab.wait_for_element("div.ProseMirror")?.click()?;
+        let mut restarts = 0;
+        loop {
+            let result = self.play_once();
+            if result.is_ok() {
+                self.attach_screenshot();
+            }
+            self.record(Event::End {
+                error: result.as_ref().err().map(|e| e.to_string()),
+            });
+            let stats = self.stats.finish(
+                self.keystrokes.get(),
+                result.as_ref().err().map(|e| e.to_string()),
+            );
+            log_stats(&stats);
+            stats.save();
+            match &result {
+                Ok(()) => risk::record_run(self.game_state.highest_rule, None),
+                Err(DriverError::CouldNotSatisfyRule(rule) | DriverError::Timeout(rule)) => {
+                    risk::record_run(self.game_state.highest_rule, Some(rule.kind()))
+                }
+                Err(_) => {}
+            }
+            self.record_profile(&result);
+            if let Err(e) = &result {
+                self.report_error(e);
+            }
+            match result {
+                Err(DriverError::PageReloaded) if restarts < MAX_RELOAD_RESTARTS => {
+                    restarts += 1;
+                    info!(
+                        target: DRIVER_WEB,
+                        "Page was reloaded, restarting from the starting password ({}/{})",
+                        restarts,
+                        MAX_RELOAD_RESTARTS
+                    );
+                    self.reinitialize()?;
+                }
+                result => return result,
+            }
+        }
+    }
+}
 
-        // Set focus to password field
-        #[cfg(target_os = "windows")]
-        for _ in 0..5 {
-            winapi::press_and_release_key(winapi::KEYS.get("Tab").unwrap());
+impl Drop for WebDriver {
+    fn drop(&mut self) {
+        // Leave the browser open if we've been detached or crashed, so the run can be resumed
+        // in it from the last snapshot. Otherwise close it, so retried runs don't leave
+        // browsers behind
+        if self.config.snapshot_path.is_some() && (self.detached || std::thread::panicking()) {
+            std::mem::forget(self.browser.take());
         }
-        #[cfg(target_os = "macos")]
-        osascript::press_key_code_multiple(*osascript::KEYS.get("Tab").unwrap(), 5)?;
+    }
+}
 
-        Ok(WebDriver {
-            _browser: browser,
-            tab,
-            solver,
-            game_state: GameState::default(),
-            cursor: 0,
-            start_time: None,
-            paul_last_fed: None,
-        })
+/// The tab in the given browser with the game open, waiting a little for the browser to tell
+/// us about its tabs after connecting.
+fn find_game_tab(browser: &Browser, game_url: &str) -> Result<Arc<Tab>, DriverError> {
+    for _ in 0..GAME_TAB_ATTEMPTS {
+        let tabs = browser
+            .get_tabs()
+            .lock()
+            .expect("failed to get lock on browser tabs")
+            .clone();
+        if let Some(tab) = tabs
+            .into_iter()
+            .find(|tab| tab.get_url().starts_with(game_url))
+        {
+            return Ok(tab);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
     }
+    Err(DriverError::Resume(format!(
+        "no tab with the game open at {}",
+        game_url
+    )))
+}
 
-    fn play(&mut self) -> Result<(), DriverError> {
+/// The result of a sync check of the passwore.
+#[derive(Debug)]
+enum CheckResult {
+    /// Password is in sync.
+    Synced,
+    /// Password out of sync due to fire.
+    Fire,
+    /// Password out of sync due to Paul hatching.
+    Hatched,
+}
+
+impl WebDriver {
+    /// Play the game through once, from the starting password.
+    fn play_once(&mut self) -> Result<(), DriverError> {
         // Start playthrough timer
         self.start_time = Some(Instant::now());
-
-        // Enter initial password to trigger rule evaluation
-        let mut changes = self.solver.starting_password();
-        self.update_password(&mut changes)?;
+        self.stall_detector = StallDetector::default();
+        let mut recorder = Recorder::start(self.game_state.clock.now());
+        if let Some(url) = &self.config.webhook_url {
+            recorder = recorder.with_notifier(Notifier::new(url));
+        }
+        self.recorder = Some(recorder);
+        self.stats = StatsCollector::start(self.keystrokes.get());
+
+        if std::mem::take(&mut self.resumed) {
+            // Carry on from the snapshot, once our password matches the one on the page
+            self.count_bugs()?;
+            self.check_password_or_resync()?;
+            self.verify_cursor()?;
+        } else {
+            // Enter initial password to trigger rule evaluation
+            let mut plan = self.solver.starting_password(&self.game_state);
+            self.solver.apply_plan(&plan);
+            self.update_password(&mut plan.changes)?;
+        }
 
         let mut violated_rules = self.get_violated_rules()?;
+        self.show_status(&violated_rules);
         while !violated_rules.is_empty() {
+            self.reload_watcher.check()?;
+            if self.config.control.as_ref().is_some_and(Control::paused) {
+                self.wait_while_paused()?;
+                violated_rules = self.get_violated_rules()?;
+                if violated_rules.is_empty() {
+                    break;
+                }
+            }
+            if let Some(gap) = self.stall_detector.tick() {
+                self.recover_from_stall(gap)?;
+                violated_rules = self.get_violated_rules()?;
+                if violated_rules.is_empty() {
+                    break;
+                }
+            }
+            self.check_timeouts(&violated_rules)?;
+            self.search_for_missing_videos(&violated_rules)?;
+            for rule in &violated_rules {
+                if let Some(reason) = risk::infeasibility(&self.solver, rule, &self.game_state) {
+                    info!(
+                        target: DRIVER_WEB,
+                        "Giving up early, as {:?} can't be satisfied: {}",
+                        rule,
+                        reason
+                    );
+                    return Err(DriverError::CouldNotSatisfyRule(rule.clone()));
+                }
+            }
+            self.normalize_zoom()?;
             info!(
+                target: DRIVER_WEB,
                 "Password: {:?}, violated rules: {:?}",
                 self.solver.password.as_str(),
                 violated_rules
             );
 
             if violated_rules.len() == 1 && violated_rules[0] == Rule::Final {
-                #[cfg(target_os = "macos")]
-                let modifier = ModifierKey::Meta;
-                #[cfg(not(target_os = "macos"))]
-                let modifier = ModifierKey::Ctrl;
-
-                // Copy our password, so we can quickly "retype" it
-                self.tab.find_element("div.ProseMirror")?.click()?;
-                self.tab.press_key_with_modifiers("A", Some(&[modifier]))?;
-                self.tab.press_key_with_modifiers("C", Some(&[modifier]))?;
-
-                // Click yes, this is our final password
-                let buttons = self.tab.find_elements(".final-password button")?;
-                for button in buttons {
-                    if button.get_inner_text()?.trim() == "Yes" {
-                        button.click()?;
-                        break;
-                    }
-                }
-
-                // Wait for the second box
-                std::thread::sleep(std::time::Duration::from_millis(500));
-
-                // Paste to "retype" our password
-                let input_boxes = self.tab.find_elements("div.ProseMirror")?;
-                for input_box in input_boxes.iter() {
-                    if input_box.get_inner_text()?.trim().is_empty() {
-                        input_box.click()?;
-                        self.tab.press_key_with_modifiers("V", Some(&[modifier]))?;
-
-                        break;
-                    }
-                }
-
-                // Confirm success
-                let _ = self.tab.wait_for_element(".end-screen")?;
+                self.confirm_final_password()?;
                 info!(
+                    target: DRIVER_WEB,
                     "Completed game in {:.2}",
                     self.time_since_start().unwrap().as_secs_f32()
                 );
                 return Ok(());
-            } else if violated_rules.iter().any(|r| *r == Rule::Fire) {
-                // Just delete the whole password and retype it to get rid of the fire
-                self.delete_and_retype_passsword()?;
+            } else if violated_rules.contains(&Rule::Fire) {
+                self.extinguish_fire()?;
                 // Wait a bit for rules to update
                 std::thread::sleep(std::time::Duration::from_millis(500));
+            } else if let Some(mut changes) = self.early_rules_batch(&violated_rules) {
+                debug!(
+                    target: DRIVER_WEB,
+                    "Solving {} early rules in one batch",
+                    violated_rules.len()
+                );
+                self.update_password(&mut changes)?;
+            } else if self.strategy == Strategy::Compose
+                && self.compose_and_type(&violated_rules)?
+            {
+                // All the violated rules were solved and entered at once
             } else {
-                if violated_rules.iter().any(|r| *r == Rule::Hatch) {
+                if violated_rules.contains(&Rule::Hatch) {
                     // Paul hatched, so we need to resync the password
-                    self.solver.password.raw_password_mut().replace(0, "🐔");
+                    if let Some(paul) = self.solver.paul_index() {
+                        self.solver.password.raw_password_mut().replace(paul, "🐔");
+                    }
                     assert_eq!(self.solver.password.as_str(), self.get_password()?);
                 }
 
@@ -174,24 +451,22 @@ impl Driver for WebDriver {
                 {
                     // We're just waiting for the number of bugs to make the password length correct,
                     // so we can just adjust the number bugs manually
-                    debug!("Manually adjusting bugs to match goal length");
-                    let current_bugs = self
-                        .get_password()?
-                        .graphemes(true)
-                        .filter(|g| *g == "🐛")
-                        .count();
+                    debug!(target: DRIVER_WEB, "Manually adjusting bugs to match goal length");
+                    let current_bugs = self.count_bugs()?;
                     let current_length = self.solver.password.len();
                     let goal_length = *self.solver.goal_length.as_ref().unwrap();
                     if current_length + current_bugs < goal_length {
                         // Add bugs
                         let total_to_add = goal_length - (current_length + current_bugs);
-                        let (bugs_to_add, padding_to_add) = if total_to_add + current_bugs > 8 {
-                            // Don't overfeed Paul!
-                            let bugs_to_add = total_to_add.min(8 - current_bugs);
-                            (bugs_to_add, total_to_add - bugs_to_add)
-                        } else {
-                            (total_to_add, 0)
-                        };
+                        let (bugs_to_add, padding_to_add) =
+                            if total_to_add + current_bugs > self.config.ruleset.max_bugs {
+                                // Don't overfeed Paul!
+                                let bugs_to_add = total_to_add
+                                    .min(self.config.ruleset.max_bugs.saturating_sub(current_bugs));
+                                (bugs_to_add, total_to_add - bugs_to_add)
+                            } else {
+                                (total_to_add, 0)
+                            };
                         self.cursor_to(self.solver.password.len())?;
                         for _ in 0..bugs_to_add {
                             self.tab.send_character("🐛")?;
@@ -199,11 +474,11 @@ impl Driver for WebDriver {
                         for _ in 0..bugs_to_add {
                             self.cursor_left(true)?;
                         }
-                        self.paul_last_fed = Some(Instant::now());
+                        self.paul_fed(bugs_to_add);
 
                         if padding_to_add > 0 {
                             Some(vec![Change::Append {
-                                string: "-".repeat(padding_to_add),
+                                string: self.solver.strategy.padding().repeat(padding_to_add),
                                 protected: false,
                             }])
                         } else {
@@ -219,15 +494,28 @@ impl Driver for WebDriver {
                         for _ in 0..to_remove {
                             self.tab.press_key("Backspace")?;
                         }
+                        // The bugs on the page can be fewer than we removed (e.g., if Paul's
+                        // just eaten one)
+                        self.game_state.paul.bug_count = current_bugs.saturating_sub(to_remove);
                         None
                     } else {
                         unreachable!();
                     }
                 } else {
-                    // Assume 3 extra bugs:
-                    // - if currently fewer, we'll feed Paul eventually
-                    // - if currently more, Paul will eat his way down to 3 eventually
-                    self.solver.solve_rule(&first_rule, &self.game_state, 3)
+                    if first_rule == Rule::IncludeLength {
+                        // Shrink the password as far as we can before its length is fixed, as
+                        // every grapheme makes retyping it slower
+                        let mut removals = self.solver.compact_password(&self.game_state);
+                        if !removals.is_empty() {
+                            self.update_password(&mut removals)?;
+                        }
+                    } else if Solver::wants_garbage_collection(&first_rule) {
+                        let mut garbage = self.solver.collect_garbage(&self.game_state);
+                        if !garbage.is_empty() {
+                            self.update_password(&mut garbage)?;
+                        }
+                    }
+                    self.solver.solve_rule(&first_rule, &self.game_state)
                 };
 
                 if let Some(mut changes) = changes {
@@ -238,16 +526,18 @@ impl Driver for WebDriver {
                         // representation of the password. Then we continue as normal,
                         // and when Paul eats a bug, it doesn't mess with our sync.
                         self.cursor_to(self.solver.password.len())?;
-                        // We can insert up to 8 🐛's before Paul is overfed
-                        for _ in 0..8 {
+                        for _ in 0..self.pacing.bug_target {
                             self.tab.send_character("🐛")?;
                         }
-                        for _ in 0..8 {
+                        for _ in 0..self.pacing.bug_target {
                             self.cursor_left(true)?;
                         }
-                        self.paul_last_fed = Some(Instant::now());
+                        self.paul_fed(self.pacing.bug_target);
                     } else {
                         self.update_password(&mut changes)?;
+                        if first_rule == Rule::Wingdings {
+                            self.verify_wingdings()?;
+                        }
                     }
                 } else {
                     return Err(DriverError::CouldNotSatisfyRule(first_rule));
@@ -267,27 +557,23 @@ impl Driver for WebDriver {
                     for (i, button) in button_elements.iter().enumerate() {
                         for letter in &self.game_state.sacrificed_letters {
                             if i == *letter as usize - 'a' as usize {
-                                button.click()?;
+                                self.click(button)?;
                                 buttons_clicked += 1;
                             }
                         }
                     }
                     assert_eq!(buttons_clicked, 2);
                     let sacrifice_button = self.tab.find_element("button.sacrafice-btn")?;
-                    sacrifice_button.click()?;
+                    self.click(&sacrifice_button)?;
 
                     // Focus back on password field
-                    self.tab
-                        .find_element("div.ProseMirror")
-                        .unwrap()
-                        .click()
-                        .unwrap();
+                    self.click(&self.tab.find_element("div.ProseMirror")?)?;
                     // And move cursor to start (clicking back in the box seems to change the cursor
                     // position)
                     for _ in 0..self.solver.password.len() {
                         self.cursor_left(true)?;
                     }
-                    trace!("Cursor {}->0", self.cursor);
+                    trace!(target: DRIVER_WEB_CURSOR, "Cursor {}->0", self.cursor);
                     self.cursor = 0;
                 }
             }
@@ -296,298 +582,1151 @@ impl Driver for WebDriver {
                 // Make sure Paul doesn't starve
                 self.feed_paul()?;
             }
+            self.refresh_time_string()?;
 
             violated_rules = self.get_violated_rules()?;
+            self.save_snapshot();
+            self.show_status(&violated_rules);
             info!(
-                "Play time: {:.2} seconds",
-                self.time_since_start().unwrap().as_secs_f32()
+                target: DRIVER_WEB,
+                "Play time: {:.2} seconds, entry keystrokes: {} estimated, {} actual",
+                self.time_since_start().unwrap().as_secs_f32(),
+                self.entry_keystrokes.0,
+                self.entry_keystrokes.1
             );
         }
         Ok(())
     }
-}
 
-/// The result of a sync check of the passwore.
-#[derive(Debug)]
-enum CheckResult {
-    /// Password is in sync.
-    Synced,
-    /// Password out of sync due to fire.
-    Fire,
-    /// Password out of sync due to Paul hatching.
-    Hatched,
-}
+    /// Open a browser at the configured URL, and click into the password box there.
+    fn launch(solver: Solver, config: DriverConfig) -> Result<Self, DriverError> {
+        // Snapshots need to know where to find the browser again
+        let debug_port = config
+            .snapshot_path
+            .as_ref()
+            .and_then(|_| snapshot::free_port());
+        let browser = Browser::new(
+            LaunchOptionsBuilder::default()
+                .headless(config.headless)
+                .idle_browser_timeout(config.idle_browser_timeout)
+                .port(debug_port)
+                .build()
+                .map_err(|_| DriverError::LaunchOptionsBuilderError)?,
+        )?;
 
-impl WebDriver {
-    /// Get the current duration of time since we started playing.
-    /// Returns none if we haven't started playing yet.
-    fn time_since_start(&self) -> Option<std::time::Duration> {
+        let tabs = browser.get_tabs();
+        let tab = if tabs
+            .lock()
+            .expect("failed to get lock on browser tabs")
+            .is_empty()
+        {
+            browser.new_tab()?
+        } else {
+            tabs.lock()
+                .expect("failed to get lock on browser tabs")
+                .last()
+                .unwrap()
+                .clone()
+        };
+        tab.activate()?;
+        tab.navigate_to(&config.game_url)?;
+        WebDriver::attach(browser, tab, debug_port, solver, config)
+    }
+
+    /// Pick up a run from a snapshot, in the browser it was playing in (which must still be
+    /// open, with the game part way through). Our password is checked against the one on the
+    /// page (and resynced if they differ) before play carries on from whichever rules are
+    /// violated.
+    pub fn resume(snapshot: Snapshot, config: DriverConfig) -> Result<Self, DriverError> {
+        prepare_lookups(&config);
+        info!(
+            target: DRIVER_WEB,
+            "Resuming a run at rule {} with rule set {}",
+            snapshot.game_state.highest_rule,
+            config.ruleset.version
+        );
+        let version = http::get_text(&snapshot::version_url(snapshot.debug_port))?;
+        let Some(websocket_url) = snapshot::websocket_url(&version) else {
+            return Err(DriverError::Resume(format!(
+                "no browser to connect to on port {}",
+                snapshot.debug_port
+            )));
+        };
+        let browser = Browser::connect_with_timeout(websocket_url, config.idle_browser_timeout)?;
+        let tab = find_game_tab(&browser, &config.game_url)?;
+        tab.activate()?;
+
+        let mut solver = snapshot.solver.with_strategy(config.strategy.into());
+        if let Some(seed) = config.seed {
+            solver.rng = SolverRng::seed_from_u64(seed);
+        }
+        let game_state = GameState {
+            clock: config.clock.clone(),
+            lookup: config.lookup.clone(),
+            ruleset: config.ruleset.clone(),
+            ..snapshot.game_state
+        };
+        let mut driver =
+            WebDriver::attach(browser, tab, Some(snapshot.debug_port), solver, config)?;
+        driver.game_state = game_state;
+        driver.resumed = true;
+        driver.focus_password_box()?;
+        driver.profile = profile::probe(&driver.tab)?;
+        Ok(driver)
+    }
+
+    /// Click into the password box in the given tab (with the game already open), ready to play.
+    fn attach(
+        browser: Browser,
+        tab: Arc<Tab>,
+        debug_port: Option<u16>,
+        solver: Solver,
+        config: DriverConfig,
+    ) -> Result<Self, DriverError> {
+        let metrics = viewport::refresh_metrics(&tab, None)?;
+        viewport::click_element(&tab, &tab.wait_for_element("div.ProseMirror")?, &metrics)?;
+        let reload_watcher = ReloadWatcher::attach(&tab)?;
+
+        Ok(WebDriver {
+            browser: Some(browser),
+            debug_port,
+            resumed: false,
+            detached: false,
+            tab,
+            solver,
+            game_state: GameState {
+                clock: config.clock.clone(),
+                lookup: config.lookup.clone(),
+                ruleset: config.ruleset.clone(),
+                ..Default::default()
+            },
+            cursor: 0,
+            start_time: None,
+            feeder: Feeder::new(Pacing::default().feeding_interval),
+            stall_detector: StallDetector::default(),
+            final_entry: FinalEntry::default(),
+            allow_dom_recovery: false,
+            viewport: RefCell::new(Some(metrics)),
+            toolbar: RefCell::new(ToolbarState::default()),
+            reload_watcher,
+            rule_stats: RuleStats::load(),
+            profile: GameProfile::default(),
+            strategy: Strategy::default(),
+            batch_early_rules: false,
+            benchmark_pacing: false,
+            bulk_typing: true,
+            dom_font_menus: true,
+            error_report_dir: None,
+            unknown_rules: UnknownRulePolicy::default(),
+            unknown_rule_classes: BTreeSet::new(),
+            revealed_rules: BTreeMap::new(),
+            pacing: Pacing {
+                bug_target: config.ruleset.max_bugs,
+                ..Default::default()
+            },
+            config,
+            keystrokes: Cell::new(0),
+            entry_keystrokes: (0, 0),
+            recorder: None,
+            stats: StatsCollector::start(0),
+        })
+    }
+
+    /// Make sure the password box has keyboard focus at the OS level, which is needed for key
+    /// presses sent directly to the window.
+    fn focus_password_box(&self) -> Result<(), DriverError> {
+        if self.config.input != InputBackend::Os {
+            return Ok(());
+        }
+        #[cfg(target_os = "windows")]
+        for _ in 0..5 {
+            winapi::press_and_release_key(winapi::KEYS.get("Tab").unwrap());
+        }
+        #[cfg(target_os = "macos")]
+        osascript::press_key_code_multiple(*osascript::KEYS.get("Tab").unwrap(), 5)?;
+        #[cfg(target_os = "linux")]
+        if self.linux_input() {
+            linux::press_key_multiple(linux::KEYS.get("Tab").unwrap(), 5)?;
+        }
+        Ok(())
+    }
+
+    /// Whether to inject key presses via the OS on Linux. Needs a visible browser, a desktop
+    /// session, and `xdotool` (X11) or `wtype` (Wayland) installed. Otherwise key presses are
+    /// sent via DevTools.
+    #[cfg(target_os = "linux")]
+    fn linux_input(&self) -> bool {
+        !self.config.headless && linux::available()
+    }
+
+    /// Press a key (holding shift if `shift` is true) via the configured input backend.
+    fn press_key(&self, key: Key, shift: bool) -> Result<(), DriverError> {
+        // The toolbar shows the formatting where the cursor is (menu navigation doesn't move it)
+        if matches!(key, Key::Left | Key::Right | Key::Backspace) {
+            self.forget_toolbar();
+        }
+        match self.config.input {
+            InputBackend::Cdp => input::press(&self.tab, key, shift),
+            InputBackend::Os => self.press_os_key(key, shift),
+        }
+    }
+
+    /// Press a key via the OS, falling back to DevTools where that isn't possible.
+    fn press_os_key(&self, key: Key, shift: bool) -> Result<(), DriverError> {
+        #[cfg(target_os = "windows")]
+        let result = {
+            let name = match key {
+                Key::Left => "NumpadLeft",
+                Key::Right => "NumpadRight",
+                Key::Up => "NumpadUp",
+                Key::Down => "NumpadDown",
+                Key::Tab => "Tab",
+                Key::Backspace => "Backspace",
+            };
+            if shift {
+                winapi::press_key(winapi::KEYS.get("Shift").unwrap());
+                winapi::press_key(winapi::KEYS.get("RShift").unwrap());
+            }
+            winapi::press_and_release_key(winapi::KEYS.get(name).unwrap());
+            if shift {
+                winapi::release_key(winapi::KEYS.get("RShift").unwrap());
+                winapi::release_key(winapi::KEYS.get("Shift").unwrap());
+            }
+            Ok(())
+        };
+        #[cfg(target_os = "macos")]
+        let result = {
+            let code = *osascript::KEYS.get(os_key_name(key)).unwrap();
+            if shift {
+                osascript::press_key_code_with_shift(code)
+            } else {
+                osascript::press_key_code(code)
+            }
+        };
+        #[cfg(target_os = "linux")]
+        let result = if self.linux_input() {
+            let name = linux::KEYS.get(os_key_name(key)).unwrap();
+            if shift {
+                linux::press_key_with_modifier("shift", name)
+            } else {
+                linux::press_key(name)
+            }
+        } else {
+            input::press(&self.tab, key, shift)
+        };
+        #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+        let result = input::press(&self.tab, key, shift);
+        result
+    }
+
+    /// Press a key several times with a single command via the OS, where the OS backend
+    /// supports that. Returns false (without pressing anything) if it doesn't, in which case
+    /// the key needs pressing one at a time.
+    fn press_os_key_multiple(&self, key: Key, times: usize) -> Result<bool, DriverError> {
+        if self.config.input != InputBackend::Os {
+            return Ok(false);
+        }
+        #[cfg(target_os = "macos")]
+        {
+            self.forget_toolbar();
+            osascript::press_key_code_multiple(
+                *osascript::KEYS.get(os_key_name(key)).unwrap(),
+                times,
+            )?;
+            Ok(true)
+        }
+        #[cfg(target_os = "linux")]
+        {
+            if !self.linux_input() {
+                return Ok(false);
+            }
+            self.forget_toolbar();
+            linux::press_key_multiple(linux::KEYS.get(os_key_name(key)).unwrap(), times)?;
+            Ok(true)
+        }
+        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+        {
+            let _ = (key, times);
+            Ok(false)
+        }
+    }
+
+    /// Load a fresh game in the existing tab, and reset our state to match.
+    fn reinitialize(&mut self) -> Result<(), DriverError> {
+        self.tab
+            .navigate_to(&self.config.game_url)?
+            .wait_until_navigated()?;
+        let password_box = self.tab.wait_for_element("div.ProseMirror")?;
+        // Don't report the reload we just caused
+        self.reload_watcher.clear();
+
+        self.viewport.replace(None);
+        self.click(&password_box)?;
+        self.focus_password_box()?;
+
+        self.solver = match self.config.seed {
+            Some(seed) => Solver::with_seed(seed),
+            None => Solver::default(),
+        }
+        .with_strategy(self.config.strategy.into());
+        self.game_state = GameState {
+            clock: self.config.clock.clone(),
+            lookup: self.config.lookup.clone(),
+            ruleset: self.config.ruleset.clone(),
+            ..Default::default()
+        };
+        self.cursor = 0;
+        self.start_time = None;
+        self.entry_keystrokes = (0, 0);
+        self.feeder.reset();
+        // The new game has its own rules (e.g., a different CAPTCHA)
+        self.revealed_rules.clear();
+        self.unknown_rule_classes.clear();
+        self.forget_toolbar();
+
+        // The reload may have been caused by the game being updated
+        self.profile = profile::probe(&self.tab)?;
+        self.profile.warn_if_changed();
+        Ok(())
+    }
+
+    /// Persist the game profile at the end of a run. If the run ended with an unexpected error,
+    /// show how the page differs from the last healthy run, in case that's the cause.
+    fn record_profile(&mut self, result: &Result<(), DriverError>) {
+        match profile::probe(&self.tab) {
+            Ok(profile) => self.profile.merge(profile),
+            Err(e) => warn!(target: DRIVER_WEB, "Failed to probe game profile: {}", e),
+        }
+        let healthy = matches!(
+            result,
+            Ok(()) | Err(DriverError::CouldNotSatisfyRule(_) | DriverError::GameOver)
+        );
+        if !healthy {
+            self.profile.warn_if_changed();
+        }
+        self.profile.record(healthy);
+    }
+
+    /// Number of graphemes in our password (not counting Paul's bugs).
+    pub fn password_length(&self) -> usize {
+        self.solver.password.len()
+    }
+
+    /// If snapshots are enabled, save one of the run so far.
+    fn save_snapshot(&self) {
+        let (Some(path), Some(debug_port)) = (&self.config.snapshot_path, self.debug_port) else {
+            return;
+        };
+        let snapshot = Snapshot {
+            debug_port,
+            game_state: self.game_state.clone(),
+            solver: self.solver.clone(),
+        };
+        if let Err(e) = snapshot.save(path) {
+            warn!(target: DRIVER_WEB, "Failed to save snapshot: {}", e);
+        }
+    }
+
+    /// If the terminal UI is showing, bring it up to date.
+    fn show_status(&self, violated_rules: &[Rule]) {
+        let Some(tui) = &self.config.tui else {
+            return;
+        };
+        let paul = self.game_state.paul.last_fed.map(|last_fed| {
+            let since_fed = (self.game_state.clock.now() - last_fed)
+                .to_std()
+                .unwrap_or_default();
+            PaulStatus {
+                last_fed: Instant::now()
+                    .checked_sub(since_fed)
+                    .unwrap_or_else(Instant::now),
+                feeding_interval: self.pacing.feeding_interval,
+                bugs: self.game_state.paul.bug_count,
+            }
+        });
+        tui.show(Status {
+            seed: self.config.seed,
+            password: self.solver.password.raw_password().clone(),
+            rules: RuleStatus::all(self.game_state.highest_rule, violated_rules),
+            started: self.start_time,
+            paul,
+        });
+    }
+
+    /// If error reports are enabled and the error is worth one, save a report of the page and
+    /// our password, and log where it went.
+    fn report_error(&self, error: &DriverError) {
+        let Some(dir) = &self.error_report_dir else {
+            return;
+        };
+        if !report::worth_reporting(error) {
+            return;
+        }
+        let report = ErrorReport::capture(
+            &self.tab,
+            error,
+            self.solver.password.raw_password(),
+            self.game_state.highest_rule,
+        );
+        match report.save(dir) {
+            Ok(path) => error!(
+                target: DRIVER_WEB,
+                "Run failed ({}), error report saved to {}",
+                error,
+                path.display()
+            ),
+            Err(e) => warn!(target: DRIVER_WEB, "Failed to save error report: {}", e),
+        }
+    }
+
+    /// If the video database has no video which fits the password for a violated YouTube rule,
+    /// search YouTube for one (adding it to the database), as the solver only plans with the
+    /// database. The search can take longer than Paul can go without bugs, so it breaks off
+    /// to feed him whenever he's due.
+    fn search_for_missing_videos(&mut self, violated_rules: &[Rule]) -> Result<(), DriverError> {
+        for rule in violated_rules {
+            let Rule::Youtube(seconds) = rule else {
+                continue;
+            };
+            if self
+                .solver
+                .choose_video(*seconds, &self.game_state.ruleset)
+                .is_some()
+            {
+                continue;
+            }
+            let end = Instant::now() + youtube::search_budget();
+            let end = self.deadline().map_or(end, |deadline| deadline.min(end));
+            loop {
+                let feed = self.feed_due().filter(|due| *due < end);
+                let found = self.solver.search_video(
+                    *seconds,
+                    &self.game_state.ruleset,
+                    Some(feed.unwrap_or(end)),
+                );
+                // Only carry on if the search stopped for Paul, rather than running out of
+                // budget or queries
+                let now = Instant::now();
+                if found.is_some() || now >= end || feed.is_none_or(|due| now < due) {
+                    break;
+                }
+                debug!(target: DRIVER_WEB, "Feeding Paul mid-search");
+                self.top_up_bugs()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// When Paul will next need feeding, if he still needs feeding at all.
+    fn feed_due(&self) -> Option<Instant> {
+        if !self.game_state.paul.hatched || self.game_state.highest_rule >= Rule::Final.number() {
+            return None;
+        }
+        self.feeder.next_feed()
+    }
+
+    /// Abandon the playthrough if any of the violated rules has been violated for longer than
+    /// the rule timeout, or the playthrough has gone on longer than the game timeout (see
+    /// `check_game_time`, blaming the rule we'd work on next).
+    fn check_timeouts(&self, violated_rules: &[Rule]) -> Result<(), DriverError> {
+        if let Some(timeout) = self.config.rule_timeout {
+            for rule in violated_rules {
+                if let Some(violated_for) = self.stats.violated_for(rule.kind()) {
+                    if violated_for > timeout {
+                        warn!(
+                            target: DRIVER_WEB,
+                            "{:?} has been violated for {:.0} seconds, giving up",
+                            rule,
+                            violated_for.as_secs_f32()
+                        );
+                        return Err(DriverError::Timeout(rule.clone()));
+                    }
+                }
+            }
+        }
+        self.check_game_time(violated_rules.last())
+    }
+
+    /// Abandon the playthrough if it's gone on longer than the game timeout, blaming the given
+    /// rule if there is one. Blocking waits check this as they go (or wait no longer than
+    /// `deadline`), so a step which hangs doesn't hold up the timeout.
+    fn check_game_time(&self, rule: Option<&Rule>) -> Result<(), DriverError> {
+        let (Some(timeout), Some(elapsed)) = (self.config.game_timeout, self.time_since_start())
+        else {
+            return Ok(());
+        };
+        if elapsed <= timeout {
+            return Ok(());
+        }
+        match rule {
+            Some(rule) => {
+                warn!(
+                    target: DRIVER_WEB,
+                    "Playthrough has taken {:.0} seconds, giving up on {:?}",
+                    elapsed.as_secs_f32(),
+                    rule
+                );
+                Err(DriverError::Timeout(rule.clone()))
+            }
+            None => {
+                warn!(
+                    target: DRIVER_WEB,
+                    "Playthrough has taken {:.0} seconds, giving up",
+                    elapsed.as_secs_f32()
+                );
+                Err(DriverError::GameTimeout(elapsed))
+            }
+        }
+    }
+
+    /// When the game timeout runs out, if there is one and we've started playing.
+    fn deadline(&self) -> Option<Instant> {
+        let (timeout, elapsed) = self.config.game_timeout.zip(self.time_since_start())?;
+        Some(Instant::now() + timeout.saturating_sub(elapsed))
+    }
+
+    /// How long to wait for something on the page: the given timeout, or whatever's left of
+    /// the game timeout if that's sooner.
+    fn wait_timeout(&self, timeout: std::time::Duration) -> std::time::Duration {
+        self.deadline().map_or(timeout, |deadline| {
+            timeout.min(deadline.saturating_duration_since(Instant::now()))
+        })
+    }
+
+    /// Leave the browser open when the driver is dropped (if there's a snapshot to resume the
+    /// run from), e.g., after a failed run which won't be retried.
+    pub fn detach(&mut self) {
+        self.detached = true;
+    }
+
+    /// Get the current duration of time since we started playing, not counting any time spent
+    /// paused. Returns none if we haven't started playing yet.
+    pub fn time_since_start(&self) -> Option<std::time::Duration> {
         self.start_time.map(|t| t.elapsed())
     }
 
     /// Check if Paul needs feeding, and if so, add some bugs.
     fn feed_paul(&mut self) -> Result<(), DriverError> {
-        if !self.game_state.paul_hatched {
+        let Some(last_fed) = self.game_state.paul.last_fed else {
             return Ok(());
-        }
-        let time_since_last_fed = self.paul_last_fed.unwrap().elapsed();
+        };
+        let time_since_last_fed = (self.game_state.clock.now() - last_fed)
+            .to_std()
+            .unwrap_or_default();
         debug!(
+            target: DRIVER_WEB,
             "Paul last fed {} seconds ago",
             time_since_last_fed.as_secs_f32()
         );
 
-        // Every 60 seconds, top up his bugs
-        if time_since_last_fed.as_secs_f32() >= 60.0 {
-            let current_bugs = self
-                .get_password()?
-                .graphemes(true)
-                .filter(|g| *g == "🐛")
-                .count();
-            let bugs_to_add = 8 - current_bugs;
+        // Top up his bugs every so often (depending on the pacing)
+        if time_since_last_fed >= self.pacing.feeding_interval {
+            self.top_up_bugs()?;
+        }
 
-            self.cursor_to(self.solver.password.len())?;
+        Ok(())
+    }
 
-            self.reset_formatting()?;
+    /// Bring the time string up to date if the minute has rolled over, rather than waiting for
+    /// the game to flag the time rule.
+    fn refresh_time_string(&mut self) -> Result<(), DriverError> {
+        if let Some(mut changes) = self.solver.refresh_time_string(&self.game_state) {
+            info!(target: DRIVER_WEB, "Minute rolled over, updating the time string");
+            self.update_password(&mut changes)?;
+        }
+        Ok(())
+    }
+
+    /// Add enough bugs to the end of the password to give Paul a full supply.
+    fn top_up_bugs(&mut self) -> Result<(), DriverError> {
+        let current_bugs = self.count_bugs()?;
+        let bugs_to_add = self.pacing.bug_target.saturating_sub(current_bugs);
+
+        self.cursor_to(self.solver.password.len())?;
+
+        self.reset_formatting()?;
+
+        for _ in 0..bugs_to_add {
+            self.tab.send_character("🐛")?;
+        }
+        for _ in 0..bugs_to_add {
+            self.cursor_left(true)?;
+        }
+        self.paul_fed(bugs_to_add);
+        Ok(())
+    }
+
+    /// Count the bugs on the page, and keep Paul's bug count up to date with it.
+    fn count_bugs(&mut self) -> Result<usize, DriverError> {
+        let bugs = self
+            .get_password()?
+            .graphemes(true)
+            .filter(|g| *g == "🐛")
+            .count();
+        self.game_state
+            .paul
+            .count_bugs(bugs, self.game_state.clock.now());
+        Ok(bugs)
+    }
+
+    /// Make sure enough of the password is still in Wingdings now it's been entered, as Paul
+    /// may have been fed in the meantime, and top it up if not.
+    fn verify_wingdings(&mut self) -> Result<(), DriverError> {
+        self.count_bugs()?;
+        if Rule::Wingdings.validate(self.solver.password.raw_password(), &self.game_state) {
+            return Ok(());
+        }
+        debug!(target: DRIVER_WEB, "Wingdings dipped below the fraction, topping up");
+        let Some(mut changes) = self.solver.solve_rule(&Rule::Wingdings, &self.game_state) else {
+            return Err(DriverError::CouldNotSatisfyRule(Rule::Wingdings));
+        };
+        self.update_password(&mut changes)
+    }
 
-            for _ in 0..bugs_to_add {
-                self.tab.send_character("🐛")?;
+    /// Record that the given number of bugs have just been added to the password for Paul.
+    fn paul_fed(&mut self, bugs: usize) {
+        self.feeder.fed();
+        self.game_state.paul.fed(bugs, self.game_state.clock.now());
+    }
+
+    /// Service any input the feeding watchdog has queued. Only call where the password in the
+    /// game matches our password (apart from formatting), as feeding moves the cursor to the
+    /// end of it and leaves it there.
+    fn service_input_queue(&mut self) -> Result<(), DriverError> {
+        while let Some(request) = self.feeder.take_request() {
+            match request {
+                InputRequest::FeedPaul => {
+                    if self.game_state.paul.hatched
+                        && self.game_state.highest_rule < Rule::Final.number()
+                    {
+                        debug!(target: DRIVER_WEB, "Feeding Paul mid-update");
+                        self.top_up_bugs()?;
+                    }
+                }
             }
-            for _ in 0..bugs_to_add {
-                self.cursor_left(true)?;
+        }
+        Ok(())
+    }
+
+    /// Get the run going again after a long gap in the play loop (most likely because the
+    /// machine slept or the screen locked), during which the game's timers kept running but
+    /// ours may not have. Returns a `StaleRun` error if we can't resync with the game.
+    fn recover_from_stall(&mut self, gap: std::time::Duration) -> Result<(), DriverError> {
+        warn!(
+            target: DRIVER_WEB,
+            "No progress for {:.0}s (was the machine asleep?), resyncing",
+            gap.as_secs_f32()
+        );
+        let resynced = self
+            .reload_watcher
+            .check()
+            .and_then(|()| self.check_password_or_resync())
+            .and_then(|_| self.verify_cursor());
+        match resynced {
+            Ok(()) => {}
+            Err(e @ (DriverError::GameOver | DriverError::PageReloaded)) => return Err(e),
+            Err(e) => {
+                error!(target: DRIVER_WEB, "Failed to resync after stall: {}", e);
+                return Err(DriverError::StaleRun);
             }
-            self.paul_last_fed = Some(Instant::now());
         }
 
+        // Paul may not have been fed for a while
+        if self.game_state.paul.hatched {
+            self.top_up_bugs()?;
+        }
         Ok(())
     }
 
-    /// Delete the whole password and retype it. Useful for putting out the fire.
-    /// To avoid slaying Paul ("🥚"), we actually don't delete the whole password,
-    /// but replace it with "🥚" in one go (then retype the rest of the password).
-    pub fn delete_and_retype_passsword(&mut self) -> Result<(), DriverError> {
-        #[cfg(target_os = "macos")]
-        let modifier = ModifierKey::Meta;
-        #[cfg(not(target_os = "macos"))]
-        let modifier = ModifierKey::Ctrl;
+    /// Leave the password box to the user until they resume the bot, then adopt whatever they
+    /// changed in the password.
+    fn wait_while_paused(&mut self) -> Result<(), DriverError> {
+        info!(
+            target: DRIVER_WEB,
+            "Paused, the password box is yours until the bot is resumed"
+        );
+        let paused_at = Instant::now();
+        while self.config.control.as_ref().is_some_and(Control::paused) {
+            std::thread::sleep(PAUSE_POLL_INTERVAL);
+        }
+        info!(target: DRIVER_WEB, "Resumed, looking for changes to the password");
 
-        self.tab.press_key_with_modifiers("A", Some(&[modifier]))?;
-        self.tab.send_character("🥚")?;
+        // Time spent paused doesn't count towards the timeouts or the play time
+        let paused = paused_at.elapsed();
+        if let Some(start_time) = &mut self.start_time {
+            *start_time += paused;
+        }
+        self.stats.exclude_pause(paused);
+
+        // Anything on the page could have changed while we weren't looking
+        self.stall_detector = StallDetector::default();
+        self.viewport.replace(None);
+        self.forget_toolbar();
+        self.reload_watcher.check()?;
+        self.count_bugs()?;
+        let edits = diff(self.solver.password.raw_password(), &self.page_password()?);
+        if edits.is_empty() {
+            self.verify_cursor()
+        } else {
+            info!(
+                target: DRIVER_WEB,
+                "Adopting {} edits made to the password while paused",
+                edits.len()
+            );
+            self.adopt_page_password()
+        }
+    }
+
+    /// Put out the fire by retyping just the burnt graphemes, falling back to retyping the whole
+    /// password if the fire has spread too far (or the page password can't be repaired).
+    /// If the fire spreads while we're putting it out, what's left is dealt with next time
+    /// round the play loop.
+    fn extinguish_fire(&mut self) -> Result<(), DriverError> {
+        if !self.repair_page_password(MAX_BURNT_FRACTION)? {
+            debug!(target: DRIVER_WEB, "Fire has spread too far, retyping the password");
+            return self.retype_with_formatting();
+        }
+
+        match self.check_password() {
+            Ok(_) => Ok(()),
+            Err(DriverError::LostSync) => {
+                warn!(
+                    target: DRIVER_WEB,
+                    "Putting out the fire lost sync, retyping the password"
+                );
+                self.retype_with_formatting()
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Retype the whole password, then give each grapheme whose formatting didn't survive the
+    /// retype its formatting back (see `repair_formatting`), so the formatting rules aren't
+    /// broken again.
+    fn retype_with_formatting(&mut self) -> Result<(), DriverError> {
+        self.delete_and_retype_passsword()?;
+        let page_password = self.page_password()?;
+        if page_password.formatting() != self.solver.password.raw_password().formatting() {
+            self.repair_formatting(page_password.formatting())?;
+        }
+        Ok(())
+    }
+
+    /// Make the password on the page match ours by applying the edits between them (see
+    /// `password::diff`), working backwards so the indices of those yet to be made stay valid.
+    /// Graphemes are retyped with their formatting. Returns false without typing anything if
+    /// more than `max_fraction` of the password needs editing, it differs in formatting alone
+    /// (see `repair_formatting`), or an edit would remove Paul from the page, so the caller can
+    /// deal with it another way. Whether the repair worked is up to the caller to check.
+    fn repair_page_password(&mut self, max_fraction: f64) -> Result<bool, DriverError> {
+        let page_password = self.page_password()?;
+        let edits = diff(&page_password, self.solver.password.raw_password());
+        if edits.len() as f64 > self.solver.password.len() as f64 * max_fraction {
+            return Ok(false);
+        }
+        let is_paul = |index: usize| matches!(page_password.grapheme(index), "🥚" | "🐔");
+        let repairable = edits.iter().all(|edit| match edit {
+            Edit::Replace {
+                index, grapheme, ..
+            } => page_password.grapheme(*index) != grapheme && !is_paul(*index),
+            Edit::Remove { index } => !is_paul(*index),
+            Edit::Insert { .. } => true,
+        });
+        if !repairable {
+            return Ok(false);
+        }
+
+        // Group the edits into runs which can each be made in one go
+        let mut runs: Vec<Vec<&Edit>> = Vec::new();
+        for edit in edits.iter().rev() {
+            let continues = runs
+                .last()
+                .is_some_and(|run| match (run.last().unwrap(), edit) {
+                    (Edit::Replace { index: a, .. }, Edit::Replace { index: b, .. })
+                    | (Edit::Remove { index: a }, Edit::Remove { index: b }) => b + 1 == *a,
+                    (Edit::Insert { index: a, .. }, Edit::Insert { index: b, .. }) => a == b,
+                    _ => false,
+                });
+            if continues {
+                runs.last_mut().unwrap().push(edit);
+            } else {
+                runs.push(vec![edit]);
+            }
+        }
+
+        debug!(
+            target: DRIVER_WEB,
+            "Repairing the page password with {} edits in {} runs",
+            edits.len(),
+            runs.len()
+        );
+        for mut run in runs {
+            run.reverse();
+            let start = run[0].index();
+            let (graphemes, formatting): (String, Vec<Format>) = run
+                .iter()
+                .filter_map(|edit| match edit {
+                    Edit::Insert {
+                        grapheme, format, ..
+                    }
+                    | Edit::Replace {
+                        grapheme, format, ..
+                    } => Some((grapheme.as_str(), format.clone())),
+                    Edit::Remove { .. } => None,
+                })
+                .unzip();
+            if !matches!(run[0], Edit::Insert { .. }) {
+                // Delete the graphemes being replaced rather than typing over them, so the new
+                // ones don't pick up their formatting
+                self.select_range(start, run.len())?;
+                self.press_key(Key::Backspace, false)?;
+                self.count_keystrokes(1);
+                trace!(target: DRIVER_WEB_CURSOR, "Cursor {}->{}", self.cursor, start);
+                self.cursor = start;
+            }
+            if formatting.is_empty() {
+                continue;
+            }
+            self.cursor_to(start)?;
+            self.reset_formatting()?;
+            self.type_graphemes(&graphemes)?;
+            trace!(
+                target: DRIVER_WEB_CURSOR,
+                "Cursor {}->{}",
+                self.cursor,
+                self.cursor + formatting.len()
+            );
+            self.cursor += formatting.len();
+            self.apply_formatting(start, &formatting)?;
+        }
+        self.reset_bold()?;
+        Ok(true)
+    }
+
+    /// Give the graphemes starting at `start`, which were typed with the default formatting,
+    /// the given formatting.
+    fn apply_formatting(&mut self, start: usize, formatting: &[Format]) -> Result<(), DriverError> {
+        for (offset, length, format_change) in format_runs(formatting) {
+            self.format_range(start + offset, length, &format_change)?;
+        }
+        Ok(())
+    }
+
+    /// Delete the whole password and retype it, with its formatting. Useful for putting out the
+    /// fire. To avoid slaying Paul, we actually don't delete the whole password, but replace it
+    /// with everything up to and including Paul in one go (then retype the rest of the
+    /// password).
+    pub fn delete_and_retype_passsword(&mut self) -> Result<(), DriverError> {
+        let password = self.solver.password.as_str().to_owned();
+        let graphemes = password.graphemes(true).collect::<Vec<_>>();
+        let head_len = self.solver.paul_index().map_or(0, |paul| paul + 1);
+        self.select_all()?;
+        if head_len > 0 {
+            self.tab.send_character(&graphemes[..head_len].concat())?;
+        } else {
+            self.tab.press_key("Backspace")?;
+        }
 
         // The Ctrl/Cmd+A select all doesn't seem to always get the whole thing,
         // so clean up after it if necessary
         let remaining_password_len = self.get_password()?.graphemes(true).count();
-        if remaining_password_len > 1 {
-            for _ in 0..(remaining_password_len - 1) {
+        if remaining_password_len > head_len {
+            for _ in 0..(remaining_password_len - head_len) {
                 self.cursor_right(true)?;
             }
-            for _ in 0..(remaining_password_len - 1) {
+            for _ in 0..(remaining_password_len - head_len) {
                 self.tab.press_key("Backspace")?;
             }
         }
 
-        let formatting = self.solver.password.raw_password().formatting();
-        // Start with bold in a known state
-        if self.is_bold()? {
-            self.toggle_bold()?;
+        // Type the rest unformatted too, then format the whole thing
+        self.reset_formatting()?;
+        self.type_graphemes(&graphemes[head_len..].concat())?;
+        trace!(target: DRIVER_WEB_CURSOR, "Cursor {}->{}", self.cursor, self.solver.password.len());
+        self.cursor = self.solver.password.len();
+        let formatting = self.solver.password.raw_password().formatting().to_vec();
+        self.apply_formatting(0, &formatting)?;
+        self.reset_bold()?;
+
+        if self.solver.password.as_str() != self.get_password()? {
+            return Err(DriverError::LostSync);
         }
-        for (i, grapheme) in self
-            .solver
-            .password
-            .as_str()
-            .graphemes(true)
-            .enumerate()
-            .skip(1)
-        {
-            if (formatting[i].bold && !formatting[i - 1].bold)
-                || (!formatting[i].bold && formatting[i - 1].bold)
-            {
-                self.toggle_bold()?;
+
+        Ok(())
+    }
+
+    /// Set the contents of the password box directly through the page's editor API, rather
+    /// than by typing. Any bugs currently in the password are kept (at the end). Leaves the
+    /// cursor at the start of the password.
+    fn set_password_via_dom(&mut self) -> Result<(), DriverError> {
+        let bugs = self.count_bugs()?;
+        let doc = prosemirror_doc(self.solver.password.raw_password(), bugs);
+        let script = format!(
+            r#"(() => {{
+                const editor = document.querySelector("div.ProseMirror").editor;
+                if (!editor) {{
+                    return false;
+                }}
+                editor.commands.setContent({}, true);
+                editor.commands.focus("start");
+                return true;
+            }})()"#,
+            doc
+        );
+        let result = self.tab.evaluate(&script, false)?;
+        if result.value != Some(serde_json::Value::Bool(true)) {
+            error!(target: DRIVER_WEB, "Failed to find the editor to set the password via the DOM");
+            return Err(DriverError::LostSync);
+        }
+        trace!(target: DRIVER_WEB_CURSOR, "Cursor {}->0", self.cursor);
+        self.cursor = 0;
+        self.forget_toolbar();
+        Ok(())
+    }
+
+    /// Try to get the password on the page back in sync with our stored password, first by
+    /// editing just the graphemes which differ (if there aren't many), then by retyping it, and
+    /// then (if enabled) by setting it directly via the DOM. If none of those work, adopt
+    /// whatever password is on the page and carry on from there.
+    fn resync(&mut self) -> Result<CheckResult, DriverError> {
+        self.stats.count_resync();
+        if self.repair_page_password(MAX_REPAIR_FRACTION)? {
+            info!(target: DRIVER_WEB, "Resynced password by repairing it");
+            if let Ok(result) = self.check_password() {
+                return Ok(result);
             }
-            self.tab.send_character(grapheme)?;
         }
-        if formatting.last().unwrap().bold {
-            // Leave bold off
-            self.toggle_bold()?;
+
+        for attempt in 1..=RESYNC_RETYPE_ATTEMPTS {
+            info!(target: DRIVER_WEB, "Resyncing password by retyping (attempt {})", attempt);
+            self.click(&self.tab.find_element("div.ProseMirror")?)?;
+            if self.delete_and_retype_passsword().is_ok() {
+                if let Ok(result) = self.check_password() {
+                    return Ok(result);
+                }
+            }
         }
-        trace!("Cursor {}->{}", self.cursor, self.solver.password.len());
-        self.cursor = self.solver.password.len();
 
-        assert_eq!(self.solver.password.as_str(), self.get_password()?);
+        if self.allow_dom_recovery {
+            info!(target: DRIVER_WEB, "Resyncing password via the DOM");
+            if self.set_password_via_dom().is_ok() {
+                if let Ok(result) = self.check_password() {
+                    return Ok(result);
+                }
+            }
+        }
 
-        Ok(())
+        info!(target: DRIVER_WEB, "Resyncing by adopting the password on the page");
+        self.adopt_page_password()?;
+        self.check_password()
     }
 
-    fn check_password_formatting(&mut self) -> Result<CheckResult, DriverError> {
-        let password_box = self.tab.find_element("div.ProseMirror")?;
-        let html = password_box.get_content()?;
-        let formatting = parse_formatting(&html);
+    /// Replace our stored password with the one on the page (minus any bugs), along with its
+    /// formatting.
+    fn adopt_page_password(&mut self) -> Result<(), DriverError> {
+        let page_password = self.page_password()?;
+        if !self
+            .solver
+            .adopt_password(page_password.as_str(), page_password.formatting().to_vec())
+        {
+            return Err(DriverError::LostSync);
+        }
+        // The caret could be anywhere relative to the new password
+        self.verify_cursor()
+    }
 
-        if formatting == self.solver.password.raw_password().formatting() {
-            Ok(CheckResult::Synced)
-        } else {
-            error!("Formatting mismatch:");
-            error!(
-                "Expected: {:?}",
-                self.solver.password.raw_password().formatting()
-            );
-            error!("Actual: {:?}", formatting);
-            Err(DriverError::LostSync)
+    /// Check the password is in sync, and if we've lost sync for an unknown reason, try to
+    /// recover.
+    fn check_password_or_resync(&mut self) -> Result<CheckResult, DriverError> {
+        match self.check_password() {
+            Err(DriverError::LostSync) => self.resync(),
+            result => result,
         }
     }
 
+    /// Read the password on the page (minus any bugs), along with its formatting.
+    fn page_password(&self) -> Result<Password, DriverError> {
+        self.read_password_box(&self.tab.find_element("div.ProseMirror")?)
+    }
+
+    /// Read the password in the given password box (minus any bugs), along with its formatting.
+    /// If the formatting can't be parsed (most likely because the game's markup has changed),
+    /// we've lost sync.
+    fn read_password_box(&self, password_box: &Element) -> Result<Password, DriverError> {
+        let formatting = parse_formatting(&password_box.get_content()?).map_err(|e| {
+            error!(target: DRIVER_WEB_FORMAT, "Couldn't parse the password's formatting: {}", e);
+            DriverError::LostSync
+        })?;
+        let page_password = password_box
+            .get_inner_text()?
+            .trim_end_matches('\n')
+            .to_owned();
+        if page_password.graphemes(true).count() != formatting.len() {
+            error!(target: DRIVER_WEB, "Couldn't read the formatting of the password on the page");
+            return Err(DriverError::LostSync);
+        }
+        let (password, formatting): (String, Vec<_>) = page_password
+            .graphemes(true)
+            .zip(formatting)
+            .filter(|(grapheme, _)| *grapheme != "🐛")
+            .unzip();
+        Ok(Password::with_formatting(&password, formatting))
+    }
+
     /// Check if the password on the page is the same as what we've stored.
     /// This could fail if:
     ///  - Something went wrong when we updated the password
     ///  - Fire was started in the password
     ///  - Paul hatched from an egg into a chicken
     ///  - Paul ate a bug
+    ///
     /// This function will resync the password in the latter three cases, or
     /// just panic in the first case.
     fn check_password(&mut self) -> Result<CheckResult, DriverError> {
-        let actual_password = self.get_password()?.replace('🐛', "");
-        if actual_password == self.solver.password.as_str() {
-            return self.check_password_formatting();
+        self.stats.count_sync_check();
+        let page_password = self.page_password()?;
+        let edits = diff(self.solver.password.raw_password(), &page_password);
+        if edits.is_empty() {
+            return Ok(CheckResult::Synced);
         }
 
-        // The fire was started – this is dealt with in the `play` function
-        if actual_password.contains('🔥') {
-            debug!("Password sync lost due to fire");
-            return Ok(CheckResult::Fire);
+        // Only the formatting differs, which can be fixed in place
+        if page_password.as_str() == self.solver.password.as_str() {
+            return self.repair_formatting(page_password.formatting());
         }
 
-        // Paul hatched
-        if self.solver.password.as_str().replace('🥚', "🐔") == actual_password {
-            debug!("Password sync lost due to Paul hatching");
-            // Paul is always at index 0, which makes this easier
-            self.solver.password.raw_password_mut().replace(0, "🐔");
-            return Ok(CheckResult::Hatched);
+        // The fire was started – this is dealt with in the `play` function
+        if edits.iter().any(|edit| {
+            matches!(edit, Edit::Insert { grapheme, .. } | Edit::Replace { grapheme, .. } if grapheme == "🔥")
+        }) {
+            debug!(target: DRIVER_WEB, "Password sync lost due to fire");
+            return Ok(CheckResult::Fire);
         }
 
-        // Paul died
-        if self.solver.password.as_str().replace('🐔', "🪦") == actual_password {
-            debug!("Password sync lost due to Paul starving");
-            // We can't recover from this, it's game over
-            return Err(DriverError::GameOver);
+        if let [Edit::Replace {
+            index, grapheme, ..
+        }] = edits.as_slice()
+        {
+            let is_paul = self.solver.paul_index() == Some(*index);
+            match (
+                self.solver.password.raw_password().grapheme(*index),
+                grapheme.as_str(),
+            ) {
+                ("🥚", "🐔") if is_paul => {
+                    debug!(target: DRIVER_WEB, "Password sync lost due to Paul hatching");
+                    self.solver
+                        .password
+                        .raw_password_mut()
+                        .replace(*index, "🐔");
+                    return Ok(CheckResult::Hatched);
+                }
+                ("🐔", "🪦") if is_paul => {
+                    debug!(target: DRIVER_WEB, "Password sync lost due to Paul starving");
+                    // We can't recover from this, it's game over
+                    return Err(DriverError::GameOver);
+                }
+                _ => {}
+            }
         }
 
         // Otherwise, we've lost sync for some other reason, and don't know how to recover
-        error!("Password sync lost due to unknown reason");
+        error!(target: DRIVER_WEB, "Password sync lost due to unknown reason");
         error!(
+            target: DRIVER_WEB,
             "Expected: {:?}, found: {:?}",
             self.solver.password.as_str(),
-            actual_password
+            page_password.as_str()
         );
+        debug!(target: DRIVER_WEB, "Edits to match the page: {:?}", edits);
         Err(DriverError::LostSync)
     }
 
+    /// Fix the formatting of just the graphemes on the page whose formatting differs from ours
+    /// (given the page password is otherwise the same), e.g., if a grapheme lost its bold.
+    fn repair_formatting(&mut self, actual: &[Format]) -> Result<CheckResult, DriverError> {
+        let expected = self.solver.password.raw_password().formatting().to_vec();
+        let repairs = format_repairs(&expected, actual);
+        warn!(
+            target: DRIVER_WEB_FORMAT,
+            "Formatting mismatch, repairing {} runs",
+            repairs.len()
+        );
+        for (start, length, format_change) in repairs {
+            self.format_range(start, length, &format_change)?;
+        }
+        self.ensure_bold(false)?;
+
+        let page_password = self.page_password()?;
+        if page_password.formatting() == expected {
+            Ok(CheckResult::Synced)
+        } else {
+            error!(target: DRIVER_WEB_FORMAT, "Formatting mismatch:");
+            error!(target: DRIVER_WEB_FORMAT, "Expected: {:?}", expected);
+            error!(target: DRIVER_WEB_FORMAT, "Actual: {:?}", page_password.formatting());
+            Err(DriverError::LostSync)
+        }
+    }
+
     /// Update the password by processing the given changes.
     pub fn update_password(&mut self, changes: &mut [Change]) -> Result<(), DriverError> {
         if changes.is_empty() {
             return Ok(());
         }
+        // Check the changes before typing any of them, so an invalid one can't leave the page
+        // and our password out of sync
+        self.solver.password.preview_changes(changes)?;
+        // Don't type into a fresh game
+        self.reload_watcher.check()?;
+        self.forget_toolbar();
 
         if self.game_state.highest_rule > Rule::BoldVowels.number() {
             // Don't bother checking until we get to a stage where the game can modify the password
             // underneath us
-            self.check_password()?;
+            self.check_password_or_resync()?;
         }
 
-        Self::sort_changes_for_entry(changes);
-
-        // Combine formatting for speed if possible
-        let deduped_formatting_changes = {
-            let mut c = Vec::new();
-            for change in changes.iter() {
-                if let Change::Format { format_change, .. } = change {
-                    c.push(format_change);
-                }
-            }
-            c.sort();
-            c.dedup();
-            c
-        };
-        if changes.iter().all(|c| matches!(c, Change::Format { .. }))
-            && deduped_formatting_changes.len() == 1
-        {
-            let (mut start_index, format_change) = match &changes[0] {
-                Change::Format {
-                    index,
-                    format_change,
-                } => (*index, format_change),
-                _ => unreachable!(),
-            };
-            let mut length = 1;
-            let mut combined_changes = Vec::new();
-            for change in changes.iter().skip(1) {
-                let index = match &change {
-                    Change::Format { index, .. } => *index,
-                    _ => unreachable!(),
-                };
-                if index > start_index + length {
-                    combined_changes.push((start_index, length));
-                    start_index = index;
-                    length = 1;
-                } else {
-                    length += 1;
-                }
-            }
-            combined_changes.push((start_index, length));
-
+        let changes =
+            &Self::sort_changes_for_entry(changes, self.cursor, self.solver.password.len());
+        self.record(Event::Changes {
+            changes: changes.clone(),
+        });
+
+        // Combine formatting into as few selections as possible for speed
+        if changes.iter().all(|c| matches!(c, Change::Format { .. })) {
+            let selections =
+                plan_selections(changes, self.solver.password.raw_password().formatting());
+            debug!(
+                target: DRIVER_WEB_FORMAT,
+                "Entering {} format changes in {} selections",
+                changes.len(),
+                selections.len()
+            );
             let mut touched_bold = false;
-            for (start_index, length) in combined_changes {
-                self.cursor_to(start_index)?;
-                // Select
-                #[cfg(target_os = "windows")]
-                {
-                    winapi::press_key(winapi::KEYS.get("Shift").unwrap());
-                    winapi::press_key(winapi::KEYS.get("RShift").unwrap());
-                }
-                for _ in 0..length {
-                    #[cfg(target_os = "windows")]
-                    winapi::press_and_release_key(winapi::KEYS.get("NumpadRight").unwrap());
-                    #[cfg(not(target_os = "windows"))]
-                    self.tab
-                        .press_key_with_modifiers("ArrowRight", Some(&[ModifierKey::Shift]))?;
-                    trace!("Cursor {}->{}", self.cursor, self.cursor + 1);
-                    self.cursor += 1;
-                }
-                #[cfg(target_os = "windows")]
-                {
-                    winapi::release_key(winapi::KEYS.get("RShift").unwrap());
-                    winapi::release_key(winapi::KEYS.get("Shift").unwrap());
-                }
-                // Format
-                match format_change {
-                    FormatChange::BoldOn => {
-                        touched_bold = true;
-                        self.toggle_bold()?;
-                    }
-                    FormatChange::ItalicOn => {
-                        self.toggle_italic()?;
-                    }
-                    FormatChange::FontSize(font_size) => {
-                        self.select_font_size(font_size, None)?;
-                    }
-                    FormatChange::FontFamily(font_family) => {
-                        self.select_font(font_family)?;
-                    }
-                }
-                // Deselect
-                self.tab.press_key("ArrowRight")?;
+            for (start, length, format_changes) in selections {
+                self.service_input_queue()?;
+                self.format_selection(start, length, &format_changes)?;
+                touched_bold |= format_changes.contains(&FormatChange::BoldOn);
             }
-            if touched_bold && self.is_bold()? {
-                self.toggle_bold()?;
+            if touched_bold {
+                self.ensure_bold(false)?;
             }
             for change in changes.iter() {
                 self.solver.password.queue_change(change.clone());
             }
         } else {
-            let mut removed_count = 0;
+            let estimated = keystrokes::estimate(changes, self.cursor, self.solver.password.len());
+            let keystrokes_before = self.keystrokes.get();
+            let mut removed = Vec::new();
             let mut already_appended = false;
             let mut already_prepended = false;
             let mut touched_bold = false;
+            // Whether the game's password is still the same length as ours, so Paul can be fed
+            let mut length_unchanged = true;
             for change in changes.iter() {
-                debug!("Applying change {:?}", change);
+                if length_unchanged {
+                    self.service_input_queue()?;
+                }
+                debug!(target: DRIVER_WEB, "Applying change {:?}", change);
                 match change {
                     Change::Format {
                         index,
@@ -595,8 +1734,8 @@ impl WebDriver {
                     } => {
                         self.cursor_to(*index)?;
                         // Select
-                        self.tab
-                            .press_key_with_modifiers("ArrowRight", Some(&[ModifierKey::Shift]))?;
+                        self.press_key(Key::Right, true)?;
+                        self.count_keystrokes(1);
                         // Format
                         match format_change {
                             FormatChange::BoldOn => {
@@ -621,8 +1760,14 @@ impl WebDriver {
                             }
                         }
                         // Deselect
-                        self.tab.press_key("ArrowRight")?;
-                        trace!("Cursor {}->{}", self.cursor, self.cursor + 1);
+                        self.press_key(Key::Right, false)?;
+                        self.count_keystrokes(2);
+                        trace!(
+                            target: DRIVER_WEB_CURSOR,
+                            "Cursor {}->{}",
+                            self.cursor,
+                            self.cursor + 1
+                        );
                         self.cursor += 1;
                     }
                     Change::Append { string, .. } => {
@@ -638,10 +1783,9 @@ impl WebDriver {
                             self.reset_formatting()?;
                         }
                         // self.tab.type_str(string)?;
-                        for grapheme in string.graphemes(true) {
-                            self.tab.send_character(grapheme)?;
-                        }
+                        self.type_graphemes(string)?;
                         trace!(
+                            target: DRIVER_WEB_CURSOR,
                             "Cursor {}->{}",
                             self.cursor,
                             self.cursor + string.graphemes(true).count()
@@ -656,11 +1800,10 @@ impl WebDriver {
 
                         self.reset_formatting()?;
 
-                        for grapheme in string.graphemes(true) {
-                            self.tab.send_character(grapheme)?;
-                        }
+                        self.type_graphemes(string)?;
                         // self.tab.send_character(string)?;
                         trace!(
+                            target: DRIVER_WEB_CURSOR,
                             "Cursor {}->{}",
                             self.cursor,
                             self.cursor + string.graphemes(true).count()
@@ -673,10 +1816,9 @@ impl WebDriver {
 
                         self.reset_formatting()?;
 
-                        for grapheme in string.graphemes(true) {
-                            self.tab.send_character(grapheme)?;
-                        }
+                        self.type_graphemes(string)?;
                         trace!(
+                            target: DRIVER_WEB_CURSOR,
                             "Cursor {}->{}",
                             self.cursor,
                             self.cursor + string.graphemes(true).count()
@@ -689,89 +1831,624 @@ impl WebDriver {
                         ..
                     } => {
                         self.cursor_to(*index + 1)?;
-                        self.tab
-                            .press_key_with_modifiers("ArrowLeft", Some(&[ModifierKey::Shift]))?;
+                        self.press_key(Key::Left, true)?;
                         self.tab.send_character(new_grapheme)?;
+                        self.count_keystrokes(2);
                     }
                     Change::Remove { index, .. } => {
-                        // This works because we remove in order of index
-                        // So whatever index we're supposed to remove, we're actually missing
-                        // `removed_count` indices prior to that due to those removals
-                        self.cursor_to(*index + 1 - removed_count)?;
-                        self.tab.press_key("Backspace")?;
-                        trace!("Cursor {}->{}", self.cursor, self.cursor + 1);
+                        // Removal indices are relative to the password before any removals,
+                        // so account for those we've already made earlier in the password
+                        let removed_before = removed.iter().filter(|i| **i < *index).count();
+                        self.cursor_to(*index + 1 - removed_before)?;
+                        self.press_key(Key::Backspace, false)?;
+                        self.count_keystrokes(1);
+                        trace!(
+                            target: DRIVER_WEB_CURSOR,
+                            "Cursor {}->{}",
+                            self.cursor,
+                            self.cursor + 1
+                        );
                         self.cursor -= 1;
-                        removed_count += 1;
+                        removed.push(*index);
                     }
                 }
+                if !matches!(change, Change::Format { .. } | Change::Replace { .. }) {
+                    length_unchanged = false;
+                }
                 self.solver.password.queue_change(change.clone());
             }
-            if touched_bold && self.is_bold()? {
-                self.toggle_bold()?;
+            let actual = self.keystrokes.get() - keystrokes_before;
+            debug!(
+                target: DRIVER_WEB,
+                "Entered changes in {} keystrokes (estimated {})",
+                actual,
+                estimated
+            );
+            self.entry_keystrokes.0 += estimated;
+            self.entry_keystrokes.1 += actual;
+            if touched_bold {
+                self.ensure_bold(false)?;
             }
         }
-        self.solver.password.commit_changes();
+        self.solver.password.commit_changes()?;
 
         if self.game_state.highest_rule > Rule::BoldVowels.number() {
             // Don't bother checking until we get to a stage where the game can modify the password
             // underneath us
-            self.check_password()?;
+            self.check_password_or_resync()?;
         }
 
         Ok(())
     }
 
-    /// Check if bold formatting is on or off.
-    pub fn is_bold(&self) -> Result<bool, DriverError> {
-        let buttons = self.tab.find_elements("div.toolbar button")?;
-        for button in buttons {
-            if button.get_inner_text()?.contains("Bold") {
-                let attribs = get_attributes(&button)?;
-                if let Some(class) = attribs.get("class") {
-                    return Ok(class.contains("is-active"));
+    /// Select `length` graphemes starting at `start`, leaving the cursor at the end of the
+    /// selection.
+    fn select_range(&mut self, start: usize, length: usize) -> Result<(), DriverError> {
+        self.cursor_to(start)?;
+        for _ in 0..length {
+            self.press_key(Key::Right, true)?;
+            trace!(target: DRIVER_WEB_CURSOR, "Cursor {}->{}", self.cursor, self.cursor + 1);
+            self.cursor += 1;
+        }
+        Ok(())
+    }
+
+    /// Apply a formatting change to `length` graphemes starting at `start`.
+    fn format_range(
+        &mut self,
+        start: usize,
+        length: usize,
+        format_change: &FormatChange,
+    ) -> Result<(), DriverError> {
+        self.format_selection(start, length, std::slice::from_ref(format_change))
+    }
+
+    /// Apply formatting changes to `length` graphemes starting at `start`, all while they're
+    /// selected.
+    fn format_selection(
+        &mut self,
+        start: usize,
+        length: usize,
+        format_changes: &[FormatChange],
+    ) -> Result<(), DriverError> {
+        debug!(
+            target: DRIVER_WEB_FORMAT,
+            "Applying {:?} to {} graphemes at {}",
+            format_changes, length, start
+        );
+        self.select_range(start, length)?;
+        for format_change in format_changes {
+            match format_change {
+                FormatChange::BoldOn => {
+                    self.toggle_bold()?;
+                }
+                FormatChange::ItalicOn => {
+                    self.toggle_italic()?;
+                }
+                FormatChange::FontSize(font_size) => {
+                    self.select_font_size(font_size, None)?;
+                }
+                FormatChange::FontFamily(font_family) => {
+                    self.select_font(font_family)?;
                 }
             }
         }
-        panic!("no bold button found");
+        // Deselect
+        self.press_key(Key::Right, false)?;
+        Ok(())
     }
 
-    /// Check if italic formatting is on or off.
-    pub fn is_italic(&self) -> Result<bool, DriverError> {
-        let buttons = self.tab.find_elements("div.toolbar button")?;
+    /// Confirm our password as final, and re-enter it into the second box. The re-entry is
+    /// checked against our password, and retyped if it doesn't match (e.g., if the paste lost
+    /// its styling). If the end screen still doesn't appear, the password is retyped once more
+    /// before giving up.
+    fn confirm_final_password(&mut self) -> Result<(), DriverError> {
+        if self.final_entry == FinalEntry::Clipboard {
+            // Copy our password, so we can quickly "retype" it
+            let modifier = Self::modifier_key();
+            self.click(&self.tab.find_element("div.ProseMirror")?)?;
+            self.select_all()?;
+            self.tab.press_key_with_modifiers("C", Some(&[modifier]))?;
+        }
+
+        // Click yes, this is our final password
+        let buttons = self.tab.find_elements(".final-password button")?;
         for button in buttons {
-            if button.get_inner_text()?.contains("Italic") {
-                let attribs = get_attributes(&button)?;
-                if let Some(class) = attribs.get("class") {
-                    return Ok(class.contains("is-active"));
+            if button.get_inner_text()?.trim() == "Yes" {
+                self.click(&button)?;
+                break;
+            }
+        }
+
+        // Wait for the second box
+        std::thread::sleep(std::time::Duration::from_millis(500));
+
+        // "Retype" our password into the empty box
+        let tab = self.tab.clone();
+        let mut final_box = None;
+        for input_box in tab.find_elements("div.ProseMirror")? {
+            if input_box.get_inner_text()?.trim().is_empty() {
+                final_box = Some(input_box);
+                break;
+            }
+        }
+        let final_box = final_box.ok_or_else(|| {
+            DriverError::MalformedElement("no empty final password box".to_owned())
+        })?;
+        self.click(&final_box)?;
+        self.cursor = 0;
+        self.enter_final_password()?;
+        if !self.final_entry_matches(&final_box)? {
+            warn!(
+                target: DRIVER_WEB,
+                "Final password doesn't match ours, retyping it"
+            );
+            self.retype_final_password(&final_box)?;
+        }
+
+        // Confirm success
+        if tab
+            .wait_for_element_with_custom_timeout(
+                ".end-screen",
+                self.wait_timeout(END_SCREEN_TIMEOUT),
+            )
+            .is_err()
+        {
+            self.check_game_time(Some(&Rule::Final))?;
+            warn!(
+                target: DRIVER_WEB,
+                "No end screen after {:.0} seconds, retyping the final password",
+                END_SCREEN_TIMEOUT.as_secs_f32()
+            );
+            self.retype_final_password(&final_box)?;
+            tab.wait_for_element_with_custom_timeout(
+                ".end-screen",
+                self.wait_timeout(END_SCREEN_TIMEOUT),
+            )
+            .map_err(|_| DriverError::Timeout(Rule::Final))?;
+        }
+        Ok(())
+    }
+
+    /// Does the password in the final password box (minus any bugs) match ours, including its
+    /// formatting?
+    fn final_entry_matches(&self, final_box: &Element) -> Result<bool, DriverError> {
+        let entered = self.read_password_box(final_box)?;
+        let password = self.solver.password.raw_password();
+        Ok(entered.as_str() == password.as_str() && entered.formatting() == password.formatting())
+    }
+
+    /// Clear the final password box and type our password into it a grapheme at a time, then
+    /// apply its formatting. Fails if it still doesn't match ours.
+    fn retype_final_password(&mut self, final_box: &Element) -> Result<(), DriverError> {
+        self.click(final_box)?;
+        self.select_all()?;
+        self.tab.press_key("Backspace")?;
+        self.reset_formatting()?;
+
+        let password = self.solver.password.as_str().to_owned();
+        for grapheme in password.graphemes(true) {
+            self.tab.send_character(grapheme)?;
+            self.count_keystrokes(1);
+        }
+        trace!(target: DRIVER_WEB_CURSOR, "Cursor 0->{}", self.solver.password.len());
+        self.cursor = self.solver.password.len();
+
+        let formatting = self.solver.password.raw_password().formatting().to_vec();
+        for (start, length, format_change) in format_runs(&formatting) {
+            self.format_range(start, length, &format_change)?;
+        }
+        self.ensure_bold(false)?;
+
+        if self.final_entry_matches(final_box)? {
+            Ok(())
+        } else {
+            error!(
+                target: DRIVER_WEB,
+                "Final password still doesn't match ours after retyping it"
+            );
+            Err(DriverError::LostSync)
+        }
+    }
+
+    /// Check whether copying and pasting works (it doesn't in some headless setups), by copying
+    /// a probe string and pasting it back in a scratch box added to the page for the purpose, so
+    /// the game never sees it. Leaves the (empty) password box focused again.
+    fn clipboard_works(&mut self) -> Result<bool, DriverError> {
+        self.tab.evaluate(
+            &format!(
+                r#"(() => {{
+                    const scratch = document.createElement("div");
+                    scratch.id = "{}";
+                    scratch.contentEditable = "true";
+                    document.body.appendChild(scratch);
+                    scratch.focus();
+                }})()"#,
+                CLIPBOARD_SCRATCH_ID
+            ),
+            false,
+        )?;
+
+        let modifier = Self::modifier_key();
+        self.tab.send_character(CLIPBOARD_PROBE)?;
+        self.select_all()?;
+        self.tab.press_key_with_modifiers("C", Some(&[modifier]))?;
+        self.tab.press_key("Backspace")?;
+        self.tab.press_key_with_modifiers("V", Some(&[modifier]))?;
+        std::thread::sleep(self.pacing.rule_validation_wait);
+        let pasted = self.tab.evaluate(
+            &format!(
+                r#"(() => {{
+                    const scratch = document.getElementById("{}");
+                    const text = scratch.textContent;
+                    scratch.remove();
+                    return text;
+                }})()"#,
+                CLIPBOARD_SCRATCH_ID
+            ),
+            false,
+        )?;
+        let works = pasted.value == Some(serde_json::Value::String(CLIPBOARD_PROBE.to_owned()));
+
+        self.click(&self.tab.find_element("div.ProseMirror")?)?;
+        debug!(target: DRIVER_WEB, "Clipboard self-test passed: {}", works);
+        Ok(works)
+    }
+
+    /// Enter our password into the (focused, empty) final password box.
+    fn enter_final_password(&mut self) -> Result<(), DriverError> {
+        match self.final_entry {
+            FinalEntry::Clipboard => {
+                // The password was copied before clicking "Yes"
+                self.tab
+                    .press_key_with_modifiers("V", Some(&[Self::modifier_key()]))?;
+            }
+            // Settled before playing, but insert the text if it somehow wasn't
+            FinalEntry::InsertText | FinalEntry::Auto => {
+                self.insert_whole_password()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Insert the whole password in a single `Input.insertText` call (replacing any
+    /// selection), then re-apply the formatting on top of it.
+    fn insert_whole_password(&mut self) -> Result<(), DriverError> {
+        self.tab.send_character(self.solver.password.as_str())?;
+        trace!(
+            target: DRIVER_WEB_CURSOR,
+            "Cursor {}->{}",
+            self.cursor,
+            self.solver.password.len()
+        );
+        self.cursor = self.solver.password.len();
+
+        let formatting = self.solver.password.raw_password().formatting().to_vec();
+        self.apply_formatting(0, &formatting)?;
+        self.ensure_bold(false)?;
+        Ok(())
+    }
+
+    /// If enabled, and more than one rule is violated but they're all early rules, solve them
+    /// all at once.
+    fn early_rules_batch(&mut self, violated_rules: &[Rule]) -> Option<Vec<Change>> {
+        if !self.batch_early_rules || violated_rules.len() < 2 {
+            return None;
+        }
+        self.solver
+            .solve_early_rules(violated_rules, &self.game_state)
+    }
+
+    /// Can the given rule be solved as part of a composed batch? Rules which need extra
+    /// interaction with the game (or Paul's bugs) are handled one at a time.
+    fn is_composable(rule: &Rule) -> bool {
+        !matches!(
+            rule.kind(),
+            RuleKind::Fire
+                | RuleKind::Hatch
+                | RuleKind::Sacrifice
+                | RuleKind::Wingdings
+                | RuleKind::IncludeLength
+                | RuleKind::PrimeLength
+                | RuleKind::Final
+        )
+    }
+
+    /// Solve all the given rules on a copy of the solver, along with any satisfied rules the
+    /// solutions would break, and if that satisfies all of them, replace the password in the
+    /// game with the result in one go. Returns whether it did.
+    fn compose_and_type(&mut self, violated_rules: &[Rule]) -> Result<bool, DriverError> {
+        if self.game_state.paul.hatched
+            || violated_rules.len() < 2
+            || !violated_rules.iter().all(Self::is_composable)
+        {
+            return Ok(false);
+        }
+
+        // Paul hasn't hatched yet, so there are no bugs to plan around
+        let satisfied = self
+            .revealed_rules
+            .values()
+            .filter(|rule| !violated_rules.iter().any(|r| r.kind() == rule.kind()))
+            .cloned()
+            .collect::<Vec<_>>();
+        let Some((plans, batch)) =
+            self.solver
+                .solve_all_affected(violated_rules, &satisfied, &self.game_state)
+        else {
+            return Ok(false);
+        };
+        if !batch.iter().all(Self::is_composable) {
+            // Solving them would break a rule which has to be handled on its own
+            return Ok(false);
+        }
+        let mut solver = self.solver.clone();
+        for plan in &plans {
+            solver.carry_out(plan)?;
+        }
+        if !batch
+            .iter()
+            .all(|rule| rule.validate(solver.password.raw_password(), &self.game_state))
+        {
+            // Solving a later rule broke an earlier one, so go one at a time instead
+            return Ok(false);
+        }
+
+        info!(
+            target: DRIVER_WEB,
+            "Composed solution for {} rules, typing it in one go",
+            batch.len()
+        );
+        self.solver = solver;
+        self.select_all()?;
+        self.insert_whole_password()?;
+        self.check_password_or_resync()?;
+        Ok(true)
+    }
+
+    /// Select the whole password.
+    fn select_all(&self) -> Result<(), DriverError> {
+        self.forget_toolbar();
+        input::select_all(&self.tab)
+    }
+
+    /// Click the given element, translating its coordinates for any browser zoom or display
+    /// scaling.
+    fn click(&self, element: &Element) -> Result<(), DriverError> {
+        self.forget_toolbar();
+        let metrics = viewport::refresh_metrics(&self.tab, self.viewport.borrow().as_ref())?;
+        viewport::click_element(&self.tab, element, &metrics)?;
+        *self.viewport.borrow_mut() = Some(metrics);
+        Ok(())
+    }
+
+    /// If the page has been zoomed, reset it back to 100% and re-verify the cursor position.
+    /// Should only be called at points where we're not in the middle of an edit.
+    fn normalize_zoom(&mut self) -> Result<(), DriverError> {
+        let metrics = viewport::refresh_metrics(&self.tab, self.viewport.borrow().as_ref())?;
+        *self.viewport.borrow_mut() = Some(metrics.clone());
+        if metrics.mismatch().is_none() {
+            return Ok(());
+        }
+
+        info!(target: DRIVER_WEB, "Page is zoomed, resetting to 100%");
+        if metrics.pinch_zoomed() {
+            viewport::reset_pinch_zoom(&self.tab)?;
+        }
+        if metrics.browser_zoomed() {
+            self.reset_browser_zoom()?;
+        }
+        std::thread::sleep(ZOOM_RESET_WAIT_TIME);
+
+        let new_metrics = viewport::refresh_metrics(&self.tab, Some(&metrics))?;
+        if let Some(mismatch) = new_metrics.mismatch() {
+            warn!(
+                target: DRIVER_WEB,
+                "Failed to reset zoom ({}), continuing with translated clicks",
+                mismatch
+            );
+        }
+        *self.viewport.borrow_mut() = Some(new_metrics);
+
+        // Zooming can move the caret or take focus from the password box
+        self.verify_cursor()
+    }
+
+    /// Reset the browser zoom back to 100% with Ctrl+0 (Cmd+0 on macOS).
+    fn reset_browser_zoom(&self) -> Result<(), DriverError> {
+        #[cfg(target_os = "windows")]
+        {
+            winapi::press_key(winapi::KEYS.get("Control").unwrap());
+            winapi::press_and_release_key(winapi::KEYS.get("0").unwrap());
+            winapi::release_key(winapi::KEYS.get("Control").unwrap());
+        }
+        #[cfg(target_os = "macos")]
+        osascript::press_keystroke_with_command("0")?;
+        #[cfg(target_os = "linux")]
+        if self.linux_input() {
+            linux::press_key_with_modifier("ctrl", linux::KEYS.get("0").unwrap())?;
+        } else {
+            self.tab
+                .press_key_with_modifiers("0", Some(&[Self::modifier_key()]))?;
+        }
+        #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+        self.tab
+            .press_key_with_modifiers("0", Some(&[Self::modifier_key()]))?;
+        Ok(())
+    }
+
+    /// Check the caret in the password box is where we think it is, refocusing the box if
+    /// needed, and update our cursor position to match it if not.
+    fn verify_cursor(&mut self) -> Result<(), DriverError> {
+        let mut caret = self.caret_position()?;
+        if caret.is_none() {
+            debug!(target: DRIVER_WEB, "Password box lost focus, refocusing");
+            self.click(&self.tab.find_element("div.ProseMirror")?)?;
+            caret = self.caret_position()?;
+        }
+        let Some(actual) = caret else {
+            error!(target: DRIVER_WEB, "Failed to find the caret in the password box");
+            return Err(DriverError::LostSync);
+        };
+
+        if actual != self.cursor {
+            warn!(
+                target: DRIVER_WEB_CURSOR,
+                "Cursor was at {} rather than {}",
+                actual,
+                self.cursor
+            );
+            self.cursor = actual;
+        }
+        Ok(())
+    }
+
+    /// The grapheme index of the caret in the password box, or None if the box doesn't have
+    /// focus.
+    fn caret_position(&self) -> Result<Option<usize>, DriverError> {
+        let script = r#"(() => {
+            const editor = document.querySelector("div.ProseMirror");
+            const selection = window.getSelection();
+            if (!editor || !selection.focusNode || !editor.contains(selection.focusNode)) {
+                return null;
+            }
+            const range = document.createRange();
+            range.setStart(editor, 0);
+            range.setEnd(selection.focusNode, selection.focusOffset);
+            return range.toString();
+        })()"#;
+        match self.tab.evaluate(script, false)?.value {
+            Some(serde_json::Value::String(before_caret)) => {
+                Ok(Some(before_caret.graphemes(true).count()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Measure how long key presses take to reach the page, by typing, stepping over and
+    /// deleting a short probe string, and tune the pacing to match. Must be called with the
+    /// password box focused and empty. Returns the median keystroke and cursor step latencies.
+    pub fn tune_pacing(
+        &mut self,
+    ) -> Result<(std::time::Duration, std::time::Duration), DriverError> {
+        let probe_length = LATENCY_PROBE.graphemes(true).count();
+
+        let mut keystroke_latencies = Vec::new();
+        for (i, grapheme) in LATENCY_PROBE.graphemes(true).enumerate() {
+            let start = Instant::now();
+            self.tab.send_character(grapheme)?;
+            self.wait_for_latency_probe(|driver| {
+                Ok(driver.get_password()?.graphemes(true).count() == i + 1)
+            })?;
+            keystroke_latencies.push(start.elapsed());
+        }
+
+        let mut cursor_latencies = Vec::new();
+        for i in (0..probe_length).rev() {
+            let start = Instant::now();
+            self.cursor_left(true)?;
+            self.wait_for_latency_probe(|driver| Ok(driver.caret_position()? == Some(i)))?;
+            cursor_latencies.push(start.elapsed());
+        }
+
+        for i in (0..probe_length).rev() {
+            let start = Instant::now();
+            self.tab.press_key("Delete")?;
+            self.wait_for_latency_probe(|driver| {
+                Ok(driver.get_password()?.graphemes(true).count() == i)
+            })?;
+            keystroke_latencies.push(start.elapsed());
+        }
+
+        let keystroke_latency = pacing::median(&mut keystroke_latencies);
+        let cursor_latency = pacing::median(&mut cursor_latencies);
+        self.pacing = Pacing::from_latency(
+            keystroke_latency,
+            cursor_latency,
+            self.config.ruleset.max_bugs,
+        );
+        self.feeder.set_interval(self.pacing.feeding_interval);
+        info!(
+            target: DRIVER_WEB,
+            "Keystroke latency {:.1}ms, cursor step latency {:.1}ms, using {:?}",
+            keystroke_latency.as_secs_f64() * 1000.0,
+            cursor_latency.as_secs_f64() * 1000.0,
+            self.pacing
+        );
+        self.pacing.record(keystroke_latency, cursor_latency);
+        Ok((keystroke_latency, cursor_latency))
+    }
+
+    /// Poll until the given condition holds, as part of measuring latency.
+    fn wait_for_latency_probe<F>(&self, condition: F) -> Result<(), DriverError>
+    where
+        F: Fn(&Self) -> Result<bool, DriverError>,
+    {
+        let start = Instant::now();
+        while !condition(self)? {
+            if start.elapsed() > LATENCY_PROBE_TIMEOUT {
+                return Err(anyhow::anyhow!(
+                    "key press didn't reach the page when measuring latency"
+                )
+                .into());
+            }
+            std::thread::sleep(LATENCY_PROBE_POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    /// Type the given string, pausing between batches if the pacing calls for it. With bulk
+    /// typing, each batch is inserted in a single `Input.insertText` call, otherwise it's typed
+    /// one grapheme at a time.
+    fn type_graphemes(&self, string: &str) -> Result<(), DriverError> {
+        let graphemes = string.graphemes(true).collect::<Vec<_>>();
+        for (i, batch) in graphemes.chunks(self.pacing.typing_batch_size).enumerate() {
+            if i > 0 {
+                std::thread::sleep(self.pacing.typing_batch_pause);
+            }
+            if self.bulk_typing {
+                self.tab.send_character(&batch.concat())?;
+            } else {
+                for grapheme in batch {
+                    self.tab.send_character(grapheme)?;
                 }
             }
+            self.count_keystrokes(batch.len());
         }
-        panic!("no italic button found");
+        Ok(())
     }
 
-    /// Toggle bold formatting.
-    pub fn toggle_bold(&self) -> Result<(), DriverError> {
+    /// The modifier key used for shortcuts (Cmd on macOS, Ctrl elsewhere).
+    fn modifier_key() -> ModifierKey {
         #[cfg(target_os = "macos")]
         let modifier = ModifierKey::Meta;
         #[cfg(not(target_os = "macos"))]
         let modifier = ModifierKey::Ctrl;
-        self.tab.press_key_with_modifiers("B", Some(&[modifier]))?;
+        modifier
+    }
+
+    /// Toggle bold formatting.
+    pub fn toggle_bold(&self) -> Result<(), DriverError> {
+        self.tab
+            .press_key_with_modifiers("B", Some(&[Self::modifier_key()]))?;
         Ok(())
     }
 
     // Toggle italic formatting.
     pub fn toggle_italic(&self) -> Result<(), DriverError> {
-        #[cfg(target_os = "macos")]
-        let modifier = ModifierKey::Meta;
-        #[cfg(not(target_os = "macos"))]
-        let modifier = ModifierKey::Ctrl;
-        self.tab.press_key_with_modifiers("I", Some(&[modifier]))?;
+        self.tab
+            .press_key_with_modifiers("I", Some(&[Self::modifier_key()]))?;
         Ok(())
     }
 
     // Select font.
     pub fn select_font(&mut self, font_family: &FontFamily) -> Result<(), DriverError> {
-        debug!("Selecting font {:?}", font_family);
+        debug!(target: DRIVER_WEB_FORMAT, "Selecting font {:?}", font_family);
+        if self.dom_font_menus
+            && toolbar::choose_option(&self.tab, &FormatChange::FontFamily(font_family.clone()))?
+        {
+            return Ok(());
+        }
 
         // Tab to font select
         let tabs = if self.game_state.highest_rule >= Rule::DigitFontSize.number() {
@@ -780,26 +2457,17 @@ impl WebDriver {
             3
         };
         for _ in 0..tabs {
-            #[cfg(target_os = "windows")]
-            winapi::press_and_release_key(winapi::KEYS.get("Tab").unwrap());
-            #[cfg(not(target_os = "windows"))]
-            self.tab.press_key("Tab")?;
+            self.press_key(Key::Tab, false)?;
         }
         // Open menu
         self.tab.press_key("Enter")?;
         // Move to top of menu
         for _ in 0..FontFamily::COUNT {
-            #[cfg(target_os = "windows")]
-            winapi::press_and_release_key(winapi::KEYS.get("NumpadUp").unwrap());
-            #[cfg(not(target_os = "windows"))]
-            self.tab.press_key("ArrowUp")?;
+            self.press_key(Key::Up, false)?;
         }
         // Move down to font
         for _ in 0..font_family.index() {
-            #[cfg(target_os = "windows")]
-            winapi::press_and_release_key(winapi::KEYS.get("NumpadDown").unwrap());
-            #[cfg(not(target_os = "windows"))]
-            self.tab.press_key("ArrowDown")?;
+            self.press_key(Key::Down, false)?;
         }
         // Select font
         self.tab.press_key("Enter")?;
@@ -813,14 +2481,16 @@ impl WebDriver {
         font_size: &FontSize,
         current_font_size: Option<&FontSize>,
     ) -> Result<(), DriverError> {
-        debug!("Selecting font size {:?}", font_size);
+        debug!(target: DRIVER_WEB_FORMAT, "Selecting font size {:?}", font_size);
+        if self.dom_font_menus
+            && toolbar::choose_option(&self.tab, &FormatChange::FontSize(font_size.clone()))?
+        {
+            return Ok(());
+        }
 
         // Tab to font size select
         for _ in 0..3 {
-            #[cfg(target_os = "windows")]
-            winapi::press_and_release_key(winapi::KEYS.get("Tab").unwrap());
-            #[cfg(not(target_os = "windows"))]
-            self.tab.press_key("Tab")?;
+            self.press_key(Key::Tab, false)?;
         }
         // Open menu
         self.tab.press_key("Enter")?;
@@ -829,34 +2499,22 @@ impl WebDriver {
             if font_size.index() < current_font_size.index() {
                 let steps = current_font_size.index() - font_size.index();
                 for _ in 0..steps {
-                    #[cfg(target_os = "windows")]
-                    winapi::press_and_release_key(winapi::KEYS.get("NumpadUp").unwrap());
-                    #[cfg(not(target_os = "windows"))]
-                    self.tab.press_key("ArrowUp")?;
+                    self.press_key(Key::Up, false)?;
                 }
             } else {
                 let steps = font_size.index() - current_font_size.index();
                 for _ in 0..steps {
-                    #[cfg(target_os = "windows")]
-                    winapi::press_and_release_key(winapi::KEYS.get("NumpadDown").unwrap());
-                    #[cfg(not(target_os = "windows"))]
-                    self.tab.press_key("ArrowDown")?;
+                    self.press_key(Key::Down, false)?;
                 }
             }
         } else {
             // Move to top of menu
             for _ in 0..FontSize::COUNT {
-                #[cfg(target_os = "windows")]
-                winapi::press_and_release_key(winapi::KEYS.get("NumpadUp").unwrap());
-                #[cfg(not(target_os = "windows"))]
-                self.tab.press_key("ArrowUp")?;
+                self.press_key(Key::Up, false)?;
             }
             // Move down to font size
             for _ in 0..font_size.index() {
-                #[cfg(target_os = "windows")]
-                winapi::press_and_release_key(winapi::KEYS.get("NumpadDown").unwrap());
-                #[cfg(not(target_os = "windows"))]
-                self.tab.press_key("ArrowDown")?;
+                self.press_key(Key::Down, false)?;
             }
         }
         // Select font size
@@ -877,17 +2535,17 @@ impl WebDriver {
 
     /// Reset bold formatting to the default (if bold formatting is available)
     fn reset_bold(&mut self) -> Result<(), DriverError> {
-        if self.game_state.highest_rule > Rule::BoldVowels.number() && self.is_bold()? {
-            self.toggle_bold()?;
+        if self.game_state.highest_rule > Rule::BoldVowels.number() {
+            self.ensure_bold(false)?;
         }
         Ok(())
     }
 
     /// Reset italic formatting to the default (if italic formatting is available)
     fn reset_italic(&mut self) -> Result<(), DriverError> {
-        if self.game_state.highest_rule > Rule::TwiceItalic.number() && self.is_italic()? {
+        if self.game_state.highest_rule > Rule::TwiceItalic.number() {
             // Make sure italic is off before we start typing
-            self.toggle_italic()?;
+            self.ensure_italic(false)?;
         }
         Ok(())
     }
@@ -895,10 +2553,7 @@ impl WebDriver {
     /// Reset font size to the default (if font size formatting is available)
     fn reset_font_size(&mut self) -> Result<(), DriverError> {
         if self.game_state.highest_rule > Rule::DigitFontSize.number() {
-            // Type and delete something to make sure we're focused on password field
-            self.tab.send_character("-")?;
-            self.tab.press_key("Backspace")?;
-            self.select_font_size(&FontSize::default(), None)?;
+            self.ensure_font_size(&FontSize::default())?;
         }
 
         Ok(())
@@ -907,48 +2562,109 @@ impl WebDriver {
     /// Reset font family to the default (if font family formatting is available)
     fn reset_font(&mut self) -> Result<(), DriverError> {
         if self.game_state.highest_rule > Rule::Wingdings.number() {
-            // Type and delete something to make sure we're focused on password field
-            self.tab.send_character("-")?;
-            self.tab.press_key("Backspace")?;
-            self.select_font(&FontFamily::default())?;
+            self.ensure_font(&FontFamily::default())?;
+        }
+
+        Ok(())
+    }
+
+    /// Forget what we know of the toolbar's state, e.g., because the cursor has moved.
+    fn forget_toolbar(&self) {
+        *self.toolbar.borrow_mut() = ToolbarState::default();
+    }
+
+    /// The toolbar's state, with the bold and italic buttons read from the page if we don't
+    /// know them already.
+    fn toolbar(&self) -> Result<ToolbarState, DriverError> {
+        let mut state = self.toolbar.borrow_mut();
+        if state.bold.is_none() || state.italic.is_none() {
+            let read = toolbar::read(&self.tab)?;
+            state.bold = state.bold.or(read.bold);
+            state.italic = state.italic.or(read.italic);
+        }
+        Ok(state.clone())
+    }
+
+    /// Turn bold formatting on or off, if it isn't already.
+    fn ensure_bold(&self, bold: bool) -> Result<(), DriverError> {
+        let Some(current) = self.toolbar()?.bold else {
+            return Err(DriverError::MalformedElement("no bold button found".into()));
+        };
+        if current != bold {
+            self.toggle_bold()?;
+        }
+        self.toolbar.borrow_mut().bold = Some(bold);
+        Ok(())
+    }
+
+    /// Turn italic formatting on or off, if it isn't already.
+    fn ensure_italic(&self, italic: bool) -> Result<(), DriverError> {
+        let Some(current) = self.toolbar()?.italic else {
+            return Err(DriverError::MalformedElement(
+                "no italic button found".into(),
+            ));
+        };
+        if current != italic {
+            self.toggle_italic()?;
+        }
+        self.toolbar.borrow_mut().italic = Some(italic);
+        Ok(())
+    }
+
+    /// Select the given font size for what's typed next, unless we know it already is.
+    fn ensure_font_size(&mut self, font_size: &FontSize) -> Result<(), DriverError> {
+        if self.toolbar.borrow().font_size.as_ref() == Some(font_size) {
+            return Ok(());
         }
+        // Type and delete something to make sure we're focused on password field
+        self.tab.send_character("-")?;
+        self.tab.press_key("Backspace")?;
+        self.select_font_size(font_size, None)?;
+        self.toolbar.borrow_mut().font_size = Some(font_size.clone());
+        Ok(())
+    }
 
+    /// Select the given font for what's typed next, unless we know it already is.
+    fn ensure_font(&mut self, font_family: &FontFamily) -> Result<(), DriverError> {
+        if self.toolbar.borrow().font_family.as_ref() == Some(font_family) {
+            return Ok(());
+        }
+        // Type and delete something to make sure we're focused on password field
+        self.tab.send_character("-")?;
+        self.tab.press_key("Backspace")?;
+        self.select_font(font_family)?;
+        self.toolbar.borrow_mut().font_family = Some(font_family.clone());
         Ok(())
     }
 
     /// Move the cursor to the given index.
     pub fn cursor_to(&mut self, index: usize) -> Result<(), DriverError> {
-        trace!("Cursor {}->{}", self.cursor, index);
+        trace!(target: DRIVER_WEB_CURSOR, "Cursor {}->{}", self.cursor, index);
         if index > self.solver.password.len() {
             panic!("invalid cursor index");
         }
+        if index != self.cursor {
+            self.record(Event::Cursor {
+                from: self.cursor,
+                to: index,
+            });
+        }
 
-        #[cfg(target_os = "macos")]
-        {
-            if index > self.cursor {
-                let times = index - self.cursor;
-                osascript::press_key_code_multiple(
-                    *osascript::KEYS.get("RightArrow").unwrap(),
-                    times,
-                )?;
-                self.cursor += times;
-            } else if index < self.cursor {
-                let times = self.cursor - index;
-                osascript::press_key_code_multiple(
-                    *osascript::KEYS.get("LeftArrow").unwrap(),
-                    times,
-                )?;
-                self.cursor -= times;
-            }
+        let (key, times) = if index > self.cursor {
+            (Key::Right, index - self.cursor)
+        } else {
+            (Key::Left, self.cursor - index)
+        };
+        if times > 0 && self.press_os_key_multiple(key, times)? {
+            std::thread::sleep(self.pacing.cursor_step_delay * times as u32);
+            self.count_keystrokes(times);
+            self.cursor = index;
         }
-        #[cfg(not(target_os = "macos"))]
-        {
-            while self.cursor < index {
-                self.cursor_right(false)?;
-            }
-            while self.cursor > index {
-                self.cursor_left(false)?;
-            }
+        while self.cursor < index {
+            self.cursor_right(false)?;
+        }
+        while self.cursor > index {
+            self.cursor_left(false)?;
         }
 
         assert_eq!(self.cursor, index);
@@ -964,17 +2680,14 @@ impl WebDriver {
             return Ok(());
         }
 
-        trace!("Cursor left");
+        trace!(target: DRIVER_WEB_CURSOR, "Cursor left");
 
-        #[cfg(target_os = "windows")]
-        winapi::press_and_release_key(winapi::KEYS.get("NumpadLeft").unwrap());
-        #[cfg(target_os = "macos")]
-        osascript::press_key_code(*osascript::KEYS.get("LeftArrow").unwrap())?;
-        // #[cfg(not(or(target_os = "window", target_os = "macos")))]
-        // self.tab.press_key("ArrowLeft")?;
+        self.press_key(Key::Left, false)?;
+        std::thread::sleep(self.pacing.cursor_step_delay);
+        self.count_keystrokes(1);
 
         if !direct {
-            trace!("Cursor {}->{}", self.cursor, self.cursor - 1);
+            trace!(target: DRIVER_WEB_CURSOR, "Cursor {}->{}", self.cursor, self.cursor - 1);
             self.cursor -= 1;
         }
         Ok(())
@@ -989,26 +2702,28 @@ impl WebDriver {
             return Ok(());
         }
 
-        trace!("Cursor right");
+        trace!(target: DRIVER_WEB_CURSOR, "Cursor right");
 
-        #[cfg(target_os = "windows")]
-        winapi::press_and_release_key(winapi::KEYS.get("NumpadRight").unwrap());
-        #[cfg(target_os = "macos")]
-        osascript::press_key_code(*osascript::KEYS.get("RightArrow").unwrap())?;
-        // #[cfg(not(target_os = "windows"))]
-        // self.tab.press_key("ArrowRight")?;
+        self.press_key(Key::Right, false)?;
+        std::thread::sleep(self.pacing.cursor_step_delay);
+        self.count_keystrokes(1);
 
         if !direct {
-            trace!("Cursor {}->{}", self.cursor, self.cursor + 1);
+            trace!(target: DRIVER_WEB_CURSOR, "Cursor {}->{}", self.cursor, self.cursor + 1);
             self.cursor += 1;
         }
         Ok(())
     }
 
-    /// Sort changes such that they can be entered into the game.
-    fn sort_changes_for_entry(changes: &mut [Change]) {
-        // Default sort is correct for this
-        changes.sort();
+    /// Order changes such that they can be entered into the game, with as few key presses as
+    /// possible from the given cursor position in a password of the given length.
+    fn sort_changes_for_entry(changes: &[Change], cursor: usize, length: usize) -> Vec<Change> {
+        keystrokes::order_for_entry(changes.to_vec(), cursor, length)
+    }
+
+    /// Count key presses sent to the game, for comparing against the estimates.
+    fn count_keystrokes(&self, count: usize) {
+        self.keystrokes.set(self.keystrokes.get() + count);
     }
 
     /// Get the password as entered into the game.
@@ -1022,178 +2737,292 @@ impl WebDriver {
 
     /// Get the list of all currently violated rules.
     fn get_violated_rules(&mut self) -> Result<Vec<Rule>, DriverError> {
-        std::thread::sleep(RULE_VALIDATION_WAIT_TIME);
+        std::thread::sleep(self.pacing.rule_validation_wait);
 
         let mut violated_rules = Vec::new();
 
-        let rule_errors = self.tab.find_elements("div.rule-error")?;
-        for rule_element in &rule_errors {
-            let attribs = get_attributes(rule_element)?;
-            let classes = attribs
-                .get("class")
-                .map(|c| {
-                    c.split_ascii_whitespace()
-                        .filter(|c| *c != "rule" && *c != "rule-error")
-                        .collect::<Vec<&str>>()
-                })
-                .unwrap_or_else(Vec::new);
-            for class in classes {
-                let mut rule = serde_plain::from_str::<Rule>(class)?;
+        // The rules list may be virtualized, so scan all of it rather than just what's rendered
+        let rule_errors = scan_rule_errors(&self.tab)?;
+        for class_attribute in &rule_errors {
+            for class in rule_classes(class_attribute) {
+                self.profile.note_rule_class(class);
+                let Ok(kind) = serde_plain::from_str::<RuleKind>(class) else {
+                    self.note_unknown_rule(class)?;
+                    violated_rules.push(Rule::Unknown(class.to_owned()));
+                    continue;
+                };
 
-                if self.game_state.highest_rule < rule.number() {
-                    self.game_state.highest_rule = rule.number();
+                if self.game_state.highest_rule < kind.number() {
+                    self.game_state.highest_rule = kind.number();
+                    debug!(
+                        target: DRIVER_WEB,
+                        "New rule {:?}, solved in an estimated {:.0}% of runs",
+                        kind,
+                        self.rule_stats.success_probability(kind) * 100.0
+                    );
                 }
 
                 // Special cases
-                match &mut rule {
-                    Rule::Egg => {
+                let instance = match kind {
+                    RuleKind::Egg => {
                         self.game_state.egg_placed = true;
+                        None
                     }
-                    Rule::Fire => {
+                    RuleKind::Fire => {
                         self.game_state.fire_started = true;
+                        None
                     }
-                    Rule::Hatch => {
-                        self.game_state.paul_hatched = true;
+                    RuleKind::Hatch => {
+                        self.game_state.paul.hatched = true;
+                        None
                     }
-                    Rule::Captcha(captcha) => {
-                        let captcha_refresh = self.tab.find_element("img.captcha-refresh")?;
+                    RuleKind::Captcha => {
+                        // Our own handle on the tab, so Paul can be fed between rerolls
+                        let tab = self.tab.clone();
+                        let captcha_refresh = find_rule_element(&tab, "img.captcha-refresh")?;
 
                         // Captcha solution is in the image filename
-                        // Re-roll until we avoid a large digit sum
-                        let captcha_img = self.tab.find_element("img.captcha-img")?;
+                        // Re-roll until we get something that won't constrain other rules
+                        let captcha_img = find_rule_element(&tab, "img.captcha-img")?;
                         let mut captcha_answer = get_img_src(&captcha_img)?;
                         let mut rerolled = false;
-                        while captcha_answer
-                            .chars()
-                            .filter(|ch| ch.is_ascii_digit())
-                            .fold(0, |sum, ch| sum + ch.to_string().parse::<u32>().unwrap())
-                            > 2
-                        {
-                            debug!("Rerolling captcha...");
-                            captcha_refresh.click()?;
+                        let digit_budget = digit_budget(&self.solver.password, &self.game_state);
+                        let mut reroller = Reroller::new(RerollKind::Captcha, &self.config.reroll);
+                        while !reroller.accept(
+                            &captcha_answer,
+                            &self.game_state.sacrificed_letters,
+                            digit_budget,
+                        ) {
+                            self.check_game_time(Some(&kind.into()))?;
+                            self.service_input_queue()?;
+                            debug!(target: DRIVER_WEB, "Rerolling captcha...");
+                            self.click(&captcha_refresh)?;
                             captcha_answer = get_img_src(&captcha_img)?;
                             rerolled = true;
                         }
+                        self.stats
+                            .count_rerolls(RerollKind::Captcha, reroller.rerolls());
                         if rerolled {
                             self.tab.send_character("-")?;
                             self.tab.press_key("Backspace")?;
                         }
-                        *captcha = captcha_answer;
+                        Some(RuleInstance::Captcha(captcha_answer))
                     }
-                    Rule::Geo(geo) => {
+                    RuleKind::Geo => {
                         // Lat/long are in the embed URL
-                        let geo_iframe = self
-                            .tab
-                            .find_element("iframe.geo")
-                            .expect("failed to get iframe.geo element");
-                        let attribs = geo_iframe.get_attributes()?.unwrap();
-                        for i in (0..attribs.len()).step_by(2) {
-                            if attribs[i] == "src" {
-                                let url = &attribs[i + 1];
-                                let parts = url.split('!').collect::<Vec<&str>>();
-                                geo.lat = NotNan::new(
-                                    parts[6].replace("1d", "").parse::<f64>().context(
-                                        "failed to parse latitude from Google Maps embed URL",
-                                    )?,
-                                )
-                                .unwrap();
-                                geo.long = NotNan::new(
-                                    parts[7].replace("2d", "").parse::<f64>().context(
-                                        "failed to parse longitude from Google Maps embed URL",
-                                    )?,
-                                )
-                                .unwrap();
-                            }
-                        }
+                        let geo_iframe = find_rule_element(&self.tab, "iframe.geo")?;
+                        let url = get_attribute(&geo_iframe, "src")?;
+                        let Some(geo) = extract_coords_from_embed_url(&url) else {
+                            return Err(DriverError::MalformedElement(format!(
+                                "no coordinates in Google Maps embed URL {:?}",
+                                url
+                            )));
+                        };
+                        Some(RuleInstance::Geo(geo))
                     }
-                    Rule::Chess(fen) => {
+                    RuleKind::Chess => {
                         // Player to move is in the text
-                        let move_div = self.tab.find_element("div.move")?;
+                        let move_div = find_rule_element(&self.tab, "div.move")?;
                         let text = move_div.get_inner_text()?;
                         let to_move = if text.contains("White") { 'w' } else { 'b' };
                         // FEN notation for the position is in the SVG
-                        let chess_img = self.tab.find_element("img.chess-img")?;
-                        let attribs = get_attributes(&chess_img)?;
-                        let path = attribs.get("src").unwrap();
+                        let chess_img = find_rule_element(&self.tab, "img.chess-img")?;
+                        let path = get_attribute(&chess_img, "src")?;
                         let url = format!("https://neal.fun{}", path);
-                        let body = reqwest::blocking::get(url)
-                            .context("failed to request chess SVG")?
-                            .text()
-                            .context("failed to get chess SVG request response body")?;
-                        *fen = extract_fen_from_svg(&body, to_move);
+                        let body = http::get_text(&url)?;
+                        let Some(fen) = extract_fen_from_svg(&body, to_move) else {
+                            return Err(DriverError::MalformedElement(format!(
+                                "no chess board in the puzzle image {:?}",
+                                url
+                            )));
+                        };
+                        Some(RuleInstance::Chess(fen))
                     }
-                    Rule::Youtube(duration) => {
+                    RuleKind::Wordle => {
+                        // Fetch the answer now, so a network failure ends the run here rather
+                        // than looking like the rule can't be solved
+                        self.game_state
+                            .lookup
+                            .wordle_answer(self.game_state.clock.now().date_naive())?;
+                        None
+                    }
+                    RuleKind::Youtube => {
+                        let rule_element =
+                            find_rule_element(&self.tab, &format!("div.rule-error.{}", class))?;
                         let rule_text = rule_element.get_inner_text()?;
-                        let re = regex!(r"(\d+) minute(?: (\d+) second)?");
-                        let captures = re.captures(&rule_text).unwrap();
-                        let minutes = captures.get(1).unwrap().as_str().parse::<u32>().unwrap();
-                        let seconds = captures
-                            .get(2)
-                            .map(|m| m.as_str().parse::<u32>().unwrap())
-                            .unwrap_or_default();
-                        *duration = minutes * 60 + seconds;
+                        let Some(seconds) = extract_duration_from_rule_text(&rule_text) else {
+                            return Err(DriverError::MalformedElement(format!(
+                                "no video length in the YouTube rule {:?}",
+                                rule_text
+                            )));
+                        };
+                        Some(RuleInstance::Youtube(seconds))
                     }
-                    Rule::Hex(color) => {
-                        let color_refresh = self.tab.find_element("img.refresh")?;
+                    RuleKind::Hex => {
+                        let tab = self.tab.clone();
+                        let color_refresh = find_rule_element(&tab, "img.refresh")?;
 
-                        let color_div = self.tab.find_element("div.rand-color")?;
+                        let color_div = find_rule_element(&tab, "div.rand-color")?;
 
-                        let attribs = get_attributes(&color_div)?;
-                        let style = attribs.get("style").unwrap();
-                        let mut current_color = extract_color_from_css_style(style);
+                        let style = get_attribute(&color_div, "style")?;
+                        let mut current_color = extract_color_from_css_style(&style);
                         let mut rerolled = false;
-                        while current_color
-                            .to_hex_string()
-                            .chars()
-                            .filter(|ch| ch.is_ascii_digit())
-                            .fold(0, |sum, ch| sum + ch.to_string().parse::<u32>().unwrap())
-                            > 2
-                        {
-                            debug!("Rerolling color...");
-                            color_refresh.click()?;
-                            let attribs = get_attributes(&color_div)?;
-                            let style = attribs.get("style").unwrap();
-                            current_color = extract_color_from_css_style(style);
+                        let digit_budget = digit_budget(&self.solver.password, &self.game_state);
+                        let mut reroller = Reroller::new(RerollKind::Color, &self.config.reroll);
+                        while !reroller.accept(
+                            &current_color.to_hex_string(),
+                            &self.game_state.sacrificed_letters,
+                            digit_budget,
+                        ) {
+                            self.check_game_time(Some(&kind.into()))?;
+                            self.service_input_queue()?;
+                            debug!(target: DRIVER_WEB, "Rerolling color...");
+                            self.click(&color_refresh)?;
+                            let style = get_attribute(&color_div, "style")?;
+                            current_color = extract_color_from_css_style(&style);
                             rerolled = true;
                         }
+                        self.stats
+                            .count_rerolls(RerollKind::Color, reroller.rerolls());
                         if rerolled {
                             self.tab.send_character("-")?;
                             self.tab.press_key("Backspace")?;
                         }
-                        *color = current_color;
+                        Some(RuleInstance::Hex(current_color))
+                    }
+                    _ => None,
+                };
+                let rule = Rule::new(kind, instance);
+
+                if let Some(previous) = self.revealed_rules.get(&kind) {
+                    if previous.instance() != rule.instance() {
+                        debug!(
+                            target: DRIVER_WEB,
+                            "{:?} changed from {:?} to {:?}",
+                            kind,
+                            previous.instance(),
+                            rule.instance()
+                        );
                     }
-                    _ => {}
                 }
-
+                self.revealed_rules.insert(kind, rule.clone());
                 violated_rules.push(rule);
             }
         }
-        violated_rules.sort();
+        violated_rules.sort_by_key(Rule::kind);
         violated_rules.reverse();
+        self.record(Event::Rules {
+            highest_rule: self.game_state.highest_rule,
+            password: self.solver.password.as_str().to_owned(),
+            violated: violated_rules.clone(),
+        });
+        // We can't solve unknown rules, so just work on the rest
+        violated_rules.retain(|rule| !matches!(rule, Rule::Unknown(_)));
+        self.stats
+            .observe_rules(violated_rules.iter().map(|rule| rule.kind()));
         Ok(violated_rules)
     }
-}
 
-/// Get the src of an img element.
-fn get_img_src(element: &headless_chrome::Element) -> Result<String, DriverError> {
-    let attribs = get_attributes(element)?;
-    let path = attribs.get("src").unwrap();
-    for part in path.split('/') {
-        if part.contains(".png") {
-            return Ok(part.split('.').next().unwrap().to_owned());
+    /// Deal with a violated rule we don't know, as the unknown rule policy says.
+    fn note_unknown_rule(&mut self, class: &str) -> Result<(), DriverError> {
+        match self.unknown_rules {
+            UnknownRulePolicy::Ignore => {}
+            UnknownRulePolicy::Warn => {
+                if self.unknown_rule_classes.insert(class.to_owned()) {
+                    warn!(
+                        target: DRIVER_WEB,
+                        "Unknown rule {:?} is violated, carrying on with the rules we know", class
+                    );
+                }
+            }
+            UnknownRulePolicy::Abort => return Err(DriverError::UnknownRule(class.to_owned())),
+        }
+        Ok(())
+    }
+
+    /// If the outcome of the playthrough will be posted to a webhook, attach an image of the
+    /// password box to it.
+    fn attach_screenshot(&self) {
+        let Some(notifier) = self.recorder.as_ref().and_then(Recorder::notifier) else {
+            return;
+        };
+        let screenshot = self
+            .tab
+            .find_element("div.ProseMirror")
+            .and_then(|element| {
+                element.capture_screenshot(Page::CaptureScreenshotFormatOption::Png)
+            });
+        match screenshot {
+            Ok(png) => notifier.attach_screenshot(png),
+            Err(e) => warn!(target: DRIVER_WEB, "Couldn't take screenshot of password: {}", e),
         }
     }
-    panic!("image has no src")
+
+    /// Record an event in the current playthrough's recording, if there is one.
+    fn record(&self, event: Event) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(event);
+        }
+    }
+}
+
+/// Log a summary of a playthrough's statistics, including the rules which took longest.
+fn log_stats(stats: &PlayStats) {
+    info!(
+        target: DRIVER_WEB,
+        "Playthrough stats: {:.2} seconds, {} keystrokes, {} sync checks ({} resyncs), rerolls {:?}",
+        stats.play_ms as f64 / 1000.0,
+        stats.keystrokes,
+        stats.sync_checks,
+        stats.resyncs,
+        stats.rerolls
+    );
+    for (kind, duration) in stats.slowest_rules(5) {
+        info!(
+            target: DRIVER_WEB,
+            "  {:?} violated for {:.2} seconds",
+            kind,
+            duration.as_secs_f32()
+        );
+    }
+}
+
+/// Name of the given key in the macOS and Linux key tables.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn os_key_name(key: Key) -> &'static str {
+    match key {
+        Key::Left => "LeftArrow",
+        Key::Right => "RightArrow",
+        Key::Up => "UpArrow",
+        Key::Down => "DownArrow",
+        Key::Tab => "Tab",
+        Key::Backspace => "Backspace",
+    }
+}
+
+/// Get the filename (without extension) of the src of an img element.
+fn get_img_src(element: &headless_chrome::Element) -> Result<String, DriverError> {
+    let path = get_attribute(element, "src")?;
+    extract_captcha_from_img_src(&path)
+        .ok_or_else(|| DriverError::MalformedElement(format!("image src {:?} is not a png", path)))
 }
 
 /// Get the attributes of the given element as a HashMap.
 fn get_attributes(
     element: &headless_chrome::Element,
 ) -> Result<HashMap<String, String>, DriverError> {
-    let attribs_vec = element.get_attributes().unwrap().unwrap();
-    let mut attribs = HashMap::new();
-    for i in (0..attribs_vec.len()).step_by(2) {
-        attribs.insert(attribs_vec[i].clone(), attribs_vec[i + 1].clone());
-    }
-    Ok(attribs)
+    let Some(attribs) = element.get_attributes()? else {
+        return Err(DriverError::MalformedElement(format!(
+            "{} has no attributes",
+            element.tag_name
+        )));
+    };
+    Ok(attributes_from_list(attribs))
+}
+
+/// Get the value of the named attribute of the given element.
+fn get_attribute(element: &headless_chrome::Element, name: &str) -> Result<String, DriverError> {
+    get_attributes(element)?.remove(name).ok_or_else(|| {
+        DriverError::MalformedElement(format!("{} has no {} attribute", element.tag_name, name))
+    })
 }
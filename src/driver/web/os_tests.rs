@@ -0,0 +1,149 @@
+//! Smoke tests for the OS-specific key injection, run against a local fixture rather than the
+//! live game. Enabled with the `os-integration` feature, and need a desktop session with Chrome.
+
+use super::{InputBackend, WebDriver};
+use crate::{driver::DriverConfig, password::Change, solver::Solver};
+
+/// Minimal stand-in for the game's password box.
+const FIXTURE: &str = include_str!("fixtures/editor.html");
+
+/// Open the fixture, with the password box focused.
+fn fixture_driver() -> WebDriver {
+    let config = DriverConfig {
+        game_url: format!(
+            "data:text/html;charset=utf-8,{}",
+            urlencoding::encode(FIXTURE)
+        ),
+        input: InputBackend::Os,
+        ..Default::default()
+    };
+    let driver = WebDriver::launch(Solver::default(), config).unwrap();
+    driver.tab.bring_to_front().unwrap();
+    assert!(driver.get_password().unwrap().is_empty());
+    driver
+}
+
+fn append(driver: &mut WebDriver, string: &str) {
+    driver
+        .update_password(&mut [Change::Append {
+            string: string.into(),
+            protected: false,
+        }])
+        .unwrap();
+}
+
+#[test]
+fn cursor_left_right() {
+    let mut driver = fixture_driver();
+    append(&mut driver, "ace");
+    assert_eq!(driver.cursor, 3);
+
+    driver.cursor_to(1).unwrap();
+    driver.tab.send_character("b").unwrap();
+    driver.cursor += 1;
+    driver.cursor_to(4).unwrap();
+    driver.tab.send_character("f").unwrap();
+    driver.cursor_left(true).unwrap();
+    driver.cursor_left(true).unwrap();
+    driver.tab.send_character("d").unwrap();
+    assert_eq!(driver.get_password().unwrap(), "abcdef");
+}
+
+#[test]
+fn cursor_over_emoji() {
+    let mut driver = fixture_driver();
+    append(&mut driver, "a🏋️‍♂️🥚b");
+
+    // Each grapheme should take exactly one arrow press
+    driver.cursor_to(1).unwrap();
+    driver.cursor_right(true).unwrap();
+    driver.cursor_right(true).unwrap();
+    driver.tab.send_character("-").unwrap();
+    assert_eq!(driver.get_password().unwrap(), "a🏋️‍♂️🥚-b");
+}
+
+#[test]
+fn shift_selection() {
+    let mut driver = fixture_driver();
+    append(&mut driver, "hello");
+
+    driver.select_range(1, 3).unwrap();
+    assert_eq!(driver.cursor, 4);
+    driver.tab.send_character("ipp").unwrap();
+    assert_eq!(driver.get_password().unwrap(), "hippo");
+}
+
+#[test]
+fn modifier_select_all() {
+    let mut driver = fixture_driver();
+    append(&mut driver, "hello");
+
+    driver
+        .tab
+        .press_key_with_modifiers("A", Some(&[WebDriver::modifier_key()]))
+        .unwrap();
+    driver.tab.send_character("bye").unwrap();
+    assert_eq!(driver.get_password().unwrap(), "bye");
+}
+
+#[cfg(target_os = "windows")]
+mod windows {
+    use super::fixture_driver;
+    use crate::driver::web::winapi::{press_and_release_key, press_key, release_key, KEYS};
+
+    #[test]
+    fn enter_text() {
+        let driver = fixture_driver();
+
+        press_and_release_key(KEYS.get("f").unwrap());
+        press_and_release_key(KEYS.get("o").unwrap());
+        press_and_release_key(KEYS.get("o").unwrap());
+        assert_eq!(driver.get_password().unwrap(), "foo");
+    }
+
+    #[test]
+    fn select_text() {
+        let driver = fixture_driver();
+
+        press_and_release_key(KEYS.get("f").unwrap());
+        press_and_release_key(KEYS.get("o").unwrap());
+        press_and_release_key(KEYS.get("o").unwrap());
+        assert_eq!(driver.get_password().unwrap(), "foo");
+
+        for _ in 0..3 {
+            press_and_release_key(KEYS.get("NumpadLeft").unwrap());
+        }
+        press_key(KEYS.get("Shift").unwrap());
+        press_key(KEYS.get("RShift").unwrap());
+        for _ in 0..3 {
+            press_and_release_key(KEYS.get("NumpadRight").unwrap());
+        }
+        release_key(KEYS.get("Shift").unwrap());
+        release_key(KEYS.get("RShift").unwrap());
+
+        press_and_release_key(KEYS.get("b").unwrap());
+        press_and_release_key(KEYS.get("a").unwrap());
+        press_and_release_key(KEYS.get("r").unwrap());
+        assert_eq!(driver.get_password().unwrap(), "bar");
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::{append, fixture_driver};
+    use crate::driver::web::osascript::{press_key_code, press_key_code_multiple, KEYS};
+
+    #[test]
+    fn arrow_keys() {
+        let mut driver = fixture_driver();
+        append(&mut driver, "ac");
+
+        press_key_code(*KEYS.get("LeftArrow").unwrap()).unwrap();
+        driver.tab.send_character("b").unwrap();
+        press_key_code_multiple(*KEYS.get("LeftArrow").unwrap(), 2).unwrap();
+        driver.tab.send_character("-").unwrap();
+        press_key_code(*KEYS.get("RightArrow").unwrap()).unwrap();
+        driver.tab.send_character("-").unwrap();
+        assert_eq!(driver.get_password().unwrap(), "-a-bc");
+    }
+}
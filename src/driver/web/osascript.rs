@@ -2,13 +2,14 @@ use lazy_static::lazy_static;
 use log::trace;
 use std::{collections::HashMap, process::Command};
 
-use crate::driver::DriverError;
+use crate::{driver::DriverError, logging::DRIVER_WEB};
 
 lazy_static! {
     pub static ref KEYS: HashMap<&'static str, u8> = {
         let mut m = HashMap::new();
         // From https://eastmanreference.com/complete-list-of-applescript-key-codes
         m.insert("Tab", 48);
+        m.insert("Backspace", 51);
         m.insert("LeftArrow", 123);
         m.insert("RightArrow", 124);
         m.insert("UpArrow", 126);
@@ -18,7 +19,7 @@ lazy_static! {
 }
 
 fn run_applescript(script: &str) -> Result<(), DriverError> {
-    trace!("Running AppleScript: {:?}", script);
+    trace!(target: DRIVER_WEB, "Running AppleScript: {:?}", script);
     let process = Command::new("osascript")
         .arg("-l")
         .arg("AppleScript")
@@ -41,6 +42,22 @@ pub fn press_key_code(code: u8) -> Result<(), DriverError> {
     ))
 }
 
+/// Press a key (by code) while holding the shift key.
+pub fn press_key_code_with_shift(code: u8) -> Result<(), DriverError> {
+    run_applescript(&format!(
+        r#"tell application "System Events" to key code {} using shift down"#,
+        code
+    ))
+}
+
+/// Press a key (by character) while holding the command key.
+pub fn press_keystroke_with_command(key: &str) -> Result<(), DriverError> {
+    run_applescript(&format!(
+        r#"tell application "System Events" to keystroke "{}" using command down"#,
+        key
+    ))
+}
+
 pub fn press_key_code_multiple(code: u8, times: usize) -> Result<(), DriverError> {
     let mut script = String::from("tell application \"System Events\"\n");
     script.push_str(&format!("key code {}\ndelay 0.01\n", code).repeat(times));
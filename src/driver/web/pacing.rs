@@ -0,0 +1,229 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{
+    game::ruleset::RuleSet,
+    logging::DRIVER_WEB,
+    storage::{to_record, Artifact, StorageError, STORAGE},
+};
+
+/// Storage stream for the pacing chosen in each run.
+const PACING_STREAM: &str = "stats.pacing";
+
+/// Time to wait for the game to re-validate the rules after a change, when not tuned.
+const DEFAULT_RULE_VALIDATION_WAIT: Duration = Duration::from_millis(100);
+/// Longest we'll wait for rule validation, however slow the machine.
+const MAX_RULE_VALIDATION_WAIT: Duration = Duration::from_secs(1);
+/// How many keystroke round trips to allow for rule validation.
+const RULE_VALIDATION_KEYSTROKES: u32 = 5;
+/// Keystrokes slower than this get typed in batches, pausing between them.
+const SLOW_KEYSTROKE: Duration = Duration::from_millis(10);
+/// How long a typing batch should take at the measured latency.
+const TYPING_BATCH_DURATION: Duration = Duration::from_millis(100);
+/// Cursor steps slower than this get paced.
+const SLOW_CURSOR_STEP: Duration = Duration::from_millis(5);
+/// How often Paul eats a bug.
+const BUG_EATING_INTERVAL: Duration = Duration::from_secs(20);
+/// Roughly how many keystrokes the slowest iterations of the play loop take (e.g., retyping
+/// the whole password with formatting), during which we can't feed Paul.
+const STALL_KEYSTROKES: u32 = 2500;
+/// Shortest stall to plan for, however fast the machine.
+const MIN_STALL: Duration = Duration::from_secs(5);
+/// Bugs to keep in reserve on top of those eaten during a stall.
+const RESERVE_MARGIN_BUGS: usize = 2;
+/// Bugs we'd like Paul to eat between feeds, to keep the interval reasonably long.
+const BUGS_PER_FEED: usize = 3;
+
+/// Timing for interacting with the page. The defaults work on most machines, but can be tuned
+/// to a measurement of how long key presses take to reach the page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pacing {
+    /// Time to wait for the game to re-validate the rules after a change.
+    pub rule_validation_wait: Duration,
+    /// Number of graphemes to type before pausing to let the page catch up.
+    pub typing_batch_size: usize,
+    /// Pause after each typing batch.
+    pub typing_batch_pause: Duration,
+    /// Pause after each cursor step, so steps injected via the OS land before whatever we
+    /// type next.
+    pub cursor_step_delay: Duration,
+    /// Number of bugs to top Paul up to when feeding him.
+    pub bug_target: usize,
+    /// Time between feeding Paul.
+    pub feeding_interval: Duration,
+    /// Number of bugs to keep in reserve, so Paul doesn't starve during the slowest stretches
+    /// where he can't be fed.
+    pub bug_reserve: usize,
+}
+
+impl Default for Pacing {
+    fn default() -> Self {
+        Pacing {
+            rule_validation_wait: DEFAULT_RULE_VALIDATION_WAIT,
+            typing_batch_size: usize::MAX,
+            typing_batch_pause: Duration::ZERO,
+            cursor_step_delay: Duration::ZERO,
+            bug_target: RuleSet::default().max_bugs,
+            feeding_interval: Duration::from_secs(60),
+            bug_reserve: 3,
+        }
+    }
+}
+
+impl Pacing {
+    /// Pacing for the given median latencies of a keystroke (from sending it to it appearing
+    /// in the password box) and a cursor step (from pressing the arrow to the caret moving),
+    /// when Paul can have at most `max_bugs` bugs.
+    pub fn from_latency(keystroke: Duration, cursor_step: Duration, max_bugs: usize) -> Self {
+        let mut pacing = Pacing {
+            rule_validation_wait: (keystroke * RULE_VALIDATION_KEYSTROKES)
+                .clamp(DEFAULT_RULE_VALIDATION_WAIT, MAX_RULE_VALIDATION_WAIT),
+            ..Default::default()
+        };
+        if keystroke > SLOW_KEYSTROKE {
+            pacing.typing_batch_size =
+                (TYPING_BATCH_DURATION.as_secs_f64() / keystroke.as_secs_f64()).max(1.0) as usize;
+            pacing.typing_batch_pause = keystroke;
+        }
+        if cursor_step > SLOW_CURSOR_STEP {
+            pacing.cursor_step_delay = cursor_step;
+        }
+
+        // Keep enough bugs in reserve that Paul won't starve during the slowest stretches
+        // where we can't feed him, and feed him often enough to stay above that
+        let stall = (keystroke * STALL_KEYSTROKES).max(MIN_STALL);
+        let stall_bugs = (stall.as_secs_f64() / BUG_EATING_INTERVAL.as_secs_f64()).ceil() as usize;
+        let reserve = (stall_bugs + RESERVE_MARGIN_BUGS).min(max_bugs.saturating_sub(1));
+        pacing.bug_target = (reserve + BUGS_PER_FEED).min(max_bugs);
+        pacing.feeding_interval = BUG_EATING_INTERVAL * (pacing.bug_target - reserve) as u32;
+        pacing.bug_reserve = reserve;
+        pacing
+    }
+
+    /// Persist the pacing chosen for the given latencies.
+    pub fn record(&self, keystroke: Duration, cursor_step: Duration) {
+        let record = PacingRecord {
+            keystroke_latency_ms: keystroke.as_secs_f64() * 1000.0,
+            cursor_step_latency_ms: cursor_step.as_secs_f64() * 1000.0,
+            bug_target: self.bug_target,
+            feeding_interval_secs: self.feeding_interval.as_secs(),
+            bug_reserve: self.bug_reserve,
+        };
+        if let Err(e) = to_record(&record)
+            .and_then(|record| STORAGE.lock().unwrap().append(PACING_STREAM, &record))
+        {
+            warn!(target: DRIVER_WEB, "Failed to record pacing: {}", e);
+        }
+    }
+}
+
+/// The measured latencies and resulting choices for Paul, as persisted.
+#[derive(Debug, Serialize, Deserialize)]
+struct PacingRecord {
+    keystroke_latency_ms: f64,
+    cursor_step_latency_ms: f64,
+    bug_target: usize,
+    feeding_interval_secs: u64,
+    bug_reserve: usize,
+}
+
+impl Artifact for PacingRecord {
+    const KIND: &'static str = "pacing";
+    const SCHEMA_VERSION: u32 = 2;
+
+    fn migrate(record: &mut serde_json::Value, version: u32) -> Result<(), StorageError> {
+        match version {
+            // The reserve was assumed to be in the password when planning its length
+            1 => {
+                if let Some(fields) = record.as_object_mut() {
+                    if let Some(reserve) = fields.remove("assumed_bugs") {
+                        fields.insert("bug_reserve".to_owned(), reserve);
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(StorageError::UnsupportedSchema {
+                kind: Self::KIND,
+                version,
+            }),
+        }
+    }
+}
+
+/// The median of some latency samples, or zero if there are none.
+pub fn median(samples: &mut [Duration]) -> Duration {
+    if samples.is_empty() {
+        return Duration::ZERO;
+    }
+    samples.sort();
+    samples[samples.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{median, Pacing, PacingRecord};
+    use crate::storage::from_record;
+
+    #[test]
+    fn tuning() {
+        // Fast machine, so the default timings are fine, and Paul needs fewer bugs
+        let pacing = Pacing::from_latency(Duration::from_millis(2), Duration::from_millis(1), 8);
+        assert_eq!(
+            pacing,
+            Pacing {
+                bug_target: 6,
+                feeding_interval: Duration::from_secs(60),
+                bug_reserve: 3,
+                ..Default::default()
+            }
+        );
+
+        let pacing = Pacing::from_latency(Duration::from_millis(40), Duration::from_millis(30), 8);
+        assert_eq!(pacing.rule_validation_wait, Duration::from_millis(200));
+        assert_eq!(pacing.typing_batch_size, 2);
+        assert_eq!(pacing.typing_batch_pause, Duration::from_millis(40));
+        assert_eq!(pacing.cursor_step_delay, Duration::from_millis(30));
+        assert_eq!(pacing.bug_target, 8);
+        assert_eq!(pacing.feeding_interval, Duration::from_secs(20));
+        assert_eq!(pacing.bug_reserve, 7);
+
+        // Very slow
+        let pacing = Pacing::from_latency(Duration::from_millis(500), Duration::ZERO, 8);
+        assert_eq!(pacing.rule_validation_wait, Duration::from_secs(1));
+        assert_eq!(pacing.typing_batch_size, 1);
+        assert_eq!(pacing.cursor_step_delay, Duration::ZERO);
+        assert_eq!(pacing.bug_target, 8);
+        assert_eq!(pacing.feeding_interval, Duration::from_secs(20));
+
+        // Paul can't have as many bugs in this version of the game
+        let pacing = Pacing::from_latency(Duration::from_millis(500), Duration::ZERO, 6);
+        assert_eq!(pacing.bug_target, 6);
+        assert_eq!(pacing.bug_reserve, 5);
+    }
+
+    #[test]
+    fn migrate_record() {
+        let record: PacingRecord = from_record(serde_json::json!({
+            "schema_version": 1,
+            "keystroke_latency_ms": 2.0,
+            "cursor_step_latency_ms": 1.0,
+            "bug_target": 6,
+            "feeding_interval_secs": 60,
+            "assumed_bugs": 3,
+        }))
+        .unwrap();
+        assert_eq!(record.bug_reserve, 3);
+    }
+
+    #[test]
+    fn median_latency() {
+        assert_eq!(median(&mut []), Duration::ZERO);
+        assert_eq!(
+            median(&mut [3, 1, 2].map(Duration::from_millis)),
+            Duration::from_millis(2)
+        );
+    }
+}
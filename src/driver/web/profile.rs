@@ -0,0 +1,243 @@
+use headless_chrome::Tab;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use super::rule_list::rule_classes;
+use crate::{
+    driver::DriverError,
+    game::rule::RuleKind,
+    logging::DRIVER_WEB,
+    storage::{from_record, to_record, Artifact, StorageBackend, StorageError, STORAGE},
+};
+
+/// Storage stream for the game profiles seen in each run.
+const PROFILE_STREAM: &str = "runs.game_profiles";
+
+/// Collects the parts of the page the driver depends on. Script paths include the build's
+/// content hashes, so they change whenever the game is redeployed.
+const PROBE_SCRIPT: &str = r#"() => JSON.stringify({
+    scripts: Array.from(document.scripts, (s) => s.src)
+        .filter((src) => src)
+        .map((src) => new URL(src).pathname),
+    toolbar: Array.from(
+        document.querySelectorAll("div.toolbar button, div.toolbar select"),
+        (e) => e.tagName.toLowerCase() + ":" + e.innerText.trim().split("\n")[0]
+    ),
+    rule_classes: Array.from(document.querySelectorAll("div.rule"), (e) => e.className),
+})"#;
+
+/// The structure of the game page, as far as the driver cares about it.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameProfile {
+    /// Paths of the page's scripts.
+    pub scripts: BTreeSet<String>,
+    /// Toolbar controls, in order, as `<tag>:<label>`. Empty until the toolbar is shown.
+    pub toolbar: Vec<String>,
+    /// Rule class names seen so far.
+    pub rule_classes: BTreeSet<String>,
+}
+
+/// A game profile from a run, as persisted.
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileRecord {
+    profile: GameProfile,
+    /// Whether the run ended in a way that means the driver understood the page (winning, or
+    /// failing a rule or the game), rather than an unexpected error.
+    healthy: bool,
+}
+
+impl Artifact for ProfileRecord {
+    const KIND: &'static str = "game profile";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+/// The profile as returned by the probe script.
+#[derive(Deserialize)]
+struct RawProfile {
+    scripts: Vec<String>,
+    toolbar: Vec<String>,
+    rule_classes: Vec<String>,
+}
+
+/// Record the current structure of the game page.
+pub fn probe(tab: &Tab) -> Result<GameProfile, DriverError> {
+    let result = tab.evaluate(&format!("({})()", PROBE_SCRIPT), false)?;
+    let Some(serde_json::Value::String(json)) = result.value else {
+        return Err(anyhow::anyhow!("game profile probe returned nothing").into());
+    };
+    let raw = serde_json::from_str::<RawProfile>(&json)
+        .map_err(|e| anyhow::anyhow!("failed to parse game profile: {}", e))?;
+
+    let mut profile = GameProfile {
+        scripts: raw.scripts.into_iter().collect(),
+        toolbar: raw.toolbar,
+        ..Default::default()
+    };
+    for class_attribute in &raw.rule_classes {
+        for class in rule_classes(class_attribute) {
+            profile.note_rule_class(class);
+        }
+    }
+    Ok(profile)
+}
+
+impl GameProfile {
+    /// Record that a rule with the given class name was seen.
+    pub fn note_rule_class(&mut self, class: &str) {
+        if !self.rule_classes.contains(class) {
+            self.rule_classes.insert(class.to_owned());
+        }
+    }
+
+    /// Add what was seen in a later probe of the same page.
+    pub fn merge(&mut self, other: GameProfile) {
+        self.scripts.extend(other.scripts);
+        if !other.toolbar.is_empty() {
+            self.toolbar = other.toolbar;
+        }
+        self.rule_classes.extend(other.rule_classes);
+    }
+
+    /// Describe how this profile differs from the given known-good one, one line per
+    /// difference. Parts which only get filled in later in a run (the toolbar and rule classes)
+    /// are only compared once both profiles have seen them.
+    pub fn diff(&self, known_good: &GameProfile) -> Vec<String> {
+        let mut lines = Vec::new();
+        for script in known_good.scripts.difference(&self.scripts) {
+            lines.push(format!("- script {}", script));
+        }
+        for script in self.scripts.difference(&known_good.scripts) {
+            lines.push(format!("+ script {}", script));
+        }
+        if !self.toolbar.is_empty()
+            && !known_good.toolbar.is_empty()
+            && self.toolbar != known_good.toolbar
+        {
+            lines.push(format!("- toolbar {:?}", known_good.toolbar));
+            lines.push(format!("+ toolbar {:?}", self.toolbar));
+        }
+        // Runs see different rules depending on how far they get, so only new classes which
+        // we don't know how to handle are interesting
+        for class in self.rule_classes.difference(&known_good.rule_classes) {
+            if serde_plain::from_str::<RuleKind>(class).is_err() {
+                lines.push(format!("+ rule class {}", class));
+            }
+        }
+        lines
+    }
+
+    /// Warn if the page differs from the one seen in the last healthy run.
+    pub fn warn_if_changed(&self) {
+        let known_good = match known_good(&*STORAGE.lock().unwrap()) {
+            Ok(Some(known_good)) => known_good,
+            Ok(None) => return,
+            Err(e) => {
+                warn!(target: DRIVER_WEB, "Failed to load game profiles: {}", e);
+                return;
+            }
+        };
+        let diff = self.diff(&known_good);
+        if !diff.is_empty() {
+            warn!(
+                target: DRIVER_WEB,
+                "Game updated since the last healthy run, selectors may be stale:\n{}",
+                diff.join("\n")
+            );
+        }
+    }
+
+    /// Persist the profile along with whether the run was healthy.
+    pub fn record(&self, healthy: bool) {
+        let record = ProfileRecord {
+            profile: self.clone(),
+            healthy,
+        };
+        if let Err(e) = to_record(&record)
+            .and_then(|record| STORAGE.lock().unwrap().append(PROFILE_STREAM, &record))
+        {
+            warn!(target: DRIVER_WEB, "Failed to record game profile: {}", e);
+        }
+    }
+}
+
+/// The profile from the most recent healthy run, if any.
+fn known_good(storage: &dyn StorageBackend) -> Result<Option<GameProfile>, StorageError> {
+    let mut known_good = None;
+    for record in storage.load(PROFILE_STREAM)? {
+        let record: ProfileRecord = from_record(record)?;
+        if record.healthy {
+            known_good = Some(record.profile);
+        }
+    }
+    Ok(known_good)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{known_good, GameProfile, PROFILE_STREAM};
+    use crate::storage::{MemoryStorage, StorageBackend};
+
+    fn profile(scripts: &[&str], toolbar: &[&str], rule_classes: &[&str]) -> GameProfile {
+        GameProfile {
+            scripts: scripts.iter().map(|s| s.to_string()).collect(),
+            toolbar: toolbar.iter().map(|s| s.to_string()).collect(),
+            rule_classes: rule_classes.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let known_good = profile(
+            &["/_nuxt/a1.js", "/_nuxt/b2.js"],
+            &["button:Bold", "button:Italic"],
+            &["captcha", "youtube"],
+        );
+        assert!(known_good.diff(&known_good).is_empty());
+
+        // Toolbar not shown yet, and fewer rules reached
+        let current = profile(&["/_nuxt/a1.js", "/_nuxt/b2.js"], &[], &["captcha"]);
+        assert!(current.diff(&known_good).is_empty());
+
+        let current = profile(
+            &["/_nuxt/a1.js", "/_nuxt/c3.js"],
+            &["button:Bold", "select:28px"],
+            &["captcha", "new-rule"],
+        );
+        assert_eq!(
+            current.diff(&known_good),
+            vec![
+                "- script /_nuxt/b2.js",
+                "+ script /_nuxt/c3.js",
+                "- toolbar [\"button:Bold\", \"button:Italic\"]",
+                "+ toolbar [\"button:Bold\", \"select:28px\"]",
+                "+ rule class new-rule",
+            ]
+        );
+    }
+
+    #[test]
+    fn latest_healthy_profile() {
+        let mut storage = MemoryStorage::default();
+        assert_eq!(known_good(&storage).unwrap(), None);
+
+        for (script, healthy) in [("/a.js", true), ("/b.js", true), ("/c.js", false)] {
+            storage
+                .append(
+                    PROFILE_STREAM,
+                    &json!({
+                        "profile": {"scripts": [script], "toolbar": [], "rule_classes": []},
+                        "healthy": healthy,
+                        "schema_version": 1,
+                    }),
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            known_good(&storage).unwrap(),
+            Some(profile(&["/b.js"], &[], &[]))
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use headless_chrome::{protocol::cdp::types::Event, Tab};
+use log::warn;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::{driver::DriverError, logging::DRIVER_WEB};
+
+/// Watches for the page being reloaded, navigated away from, or crashing, any of which reset
+/// the game underneath us.
+pub struct ReloadWatcher {
+    reloaded: Arc<AtomicBool>,
+}
+
+impl ReloadWatcher {
+    /// Start watching the given tab. Navigations before this is called aren't reported.
+    pub fn attach(tab: &Tab) -> Result<Self, DriverError> {
+        let reloaded = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&reloaded);
+        tab.add_event_listener(Arc::new(move |event: &Event| {
+            if is_reload_event(event) {
+                flag.store(true, Ordering::SeqCst);
+            }
+        }))?;
+        Ok(ReloadWatcher { reloaded })
+    }
+
+    /// Return an error if the page has been reloaded since we last checked.
+    pub fn check(&self) -> Result<(), DriverError> {
+        if self.reloaded.swap(false, Ordering::SeqCst) {
+            warn!(target: DRIVER_WEB, "Page was reloaded unexpectedly");
+            return Err(DriverError::PageReloaded);
+        }
+        Ok(())
+    }
+
+    /// Forget about any reloads seen so far (e.g., ones we caused ourselves).
+    pub fn clear(&self) {
+        self.reloaded.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Does the event mean the game's page has been replaced? Navigations of iframes (such as the
+/// map or video embeds) don't count.
+fn is_reload_event(event: &Event) -> bool {
+    match event {
+        Event::PageFrameNavigated(navigated) => navigated.params.frame.parent_id.is_none(),
+        Event::InspectorTargetCrashed(_) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,180 @@
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Local;
+use headless_chrome::{protocol::cdp::Page, Tab};
+use log::warn;
+use serde::Serialize;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use super::rule_list::{rule_classes, scan_rule_errors};
+use crate::{driver::DriverError, logging::DRIVER_WEB, password::Password};
+
+/// Forensic information about the game when a run failed, to work out what went wrong.
+#[derive(Debug, Default)]
+pub struct ErrorReport {
+    /// What the bot thought of the game.
+    pub state: ReportState,
+    /// Inner HTML of the password box, if it could be read.
+    pub password_html: Option<String>,
+    /// PNG screenshot of the whole page, if one could be taken.
+    pub screenshot: Option<Vec<u8>>,
+}
+
+/// The bot's side of an error report, saved as JSON.
+#[derive(Debug, Default, Serialize)]
+pub struct ReportState {
+    /// The error the run failed with.
+    pub error: String,
+    /// Our password, as it should be on the page.
+    pub password: String,
+    /// Each grapheme of our password, with its formatting.
+    pub formatting: Vec<(String, String)>,
+    /// Class names of the rules shown as violated on the page.
+    pub violated_rules: Vec<String>,
+    /// Number of the highest rule seen.
+    pub highest_rule: usize,
+}
+
+impl ErrorReport {
+    /// Capture a report of the page in the given tab, alongside our view of it. Anything which
+    /// can't be read from the page is left out, rather than failing the whole report.
+    pub fn capture(
+        tab: &Tab,
+        error: &DriverError,
+        password: &Password,
+        highest_rule: usize,
+    ) -> Self {
+        let violated_rules = scan_rule_errors(tab)
+            .map(|errors| {
+                errors
+                    .iter()
+                    .flat_map(|attribute| rule_classes(attribute))
+                    .map(str::to_owned)
+                    .collect()
+            })
+            .unwrap_or_else(|e| {
+                warn!(target: DRIVER_WEB, "Couldn't read violated rules for report: {}", e);
+                Vec::new()
+            });
+        let password_html = tab
+            .find_element("div.ProseMirror")
+            .and_then(|element| element.get_content())
+            .map_err(|e| warn!(target: DRIVER_WEB, "Couldn't read password for report: {}", e))
+            .ok();
+        let screenshot = full_page_screenshot(tab)
+            .map_err(|e| warn!(target: DRIVER_WEB, "Couldn't take screenshot for report: {}", e))
+            .ok();
+
+        ErrorReport {
+            state: ReportState {
+                error: error.to_string(),
+                password: password.as_str().to_owned(),
+                formatting: password
+                    .annotated()
+                    .map(|(_, grapheme, format)| (grapheme.to_owned(), format.to_string()))
+                    .collect(),
+                violated_rules,
+                highest_rule,
+            },
+            password_html,
+            screenshot,
+        }
+    }
+
+    /// Save the report into a new timestamped directory within `parent`, returning its path.
+    pub fn save(&self, parent: &Path) -> io::Result<PathBuf> {
+        let dir = parent.join(Local::now().format("%Y-%m-%dT%H-%M-%S%.3f").to_string());
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(
+            dir.join("state.json"),
+            serde_json::to_string_pretty(&self.state)?,
+        )?;
+        if let Some(html) = &self.password_html {
+            std::fs::write(dir.join("password.html"), html)?;
+        }
+        if let Some(screenshot) = &self.screenshot {
+            std::fs::write(dir.join("screenshot.png"), screenshot)?;
+        }
+        Ok(dir)
+    }
+}
+
+/// Whether a run failing with the given error is worth a report: the errors which mean the bot
+/// misunderstood the game, rather than the game being lost or interrupted.
+pub fn worth_reporting(error: &DriverError) -> bool {
+    matches!(
+        error,
+        DriverError::LostSync
+            | DriverError::CouldNotSatisfyRule(_)
+            | DriverError::Timeout(_)
+            | DriverError::GameTimeout(_)
+            | DriverError::UnknownRule(_)
+    )
+}
+
+/// Take a PNG screenshot of the whole page, including anything scrolled out of view.
+fn full_page_screenshot(tab: &Tab) -> anyhow::Result<Vec<u8>> {
+    let data = tab
+        .call_method(Page::CaptureScreenshot {
+            format: Some(Page::CaptureScreenshotFormatOption::Png),
+            quality: None,
+            clip: None,
+            from_surface: Some(true),
+            capture_beyond_viewport: Some(true),
+        })?
+        .data;
+    Ok(general_purpose::STANDARD.decode(data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{worth_reporting, ErrorReport, ReportState};
+    use crate::{
+        driver::DriverError,
+        game::Rule,
+        password::{Format, Password},
+    };
+
+    #[test]
+    fn save_report() {
+        let password = Password::with_formatting("a🥚", vec![Format::bold(), Format::default()]);
+        let report = ErrorReport {
+            state: ReportState {
+                error: DriverError::LostSync.to_string(),
+                password: password.as_str().to_owned(),
+                formatting: vec![
+                    ("a".to_owned(), Format::bold().to_string()),
+                    ("🥚".to_owned(), Format::default().to_string()),
+                ],
+                violated_rules: vec!["digits".to_owned()],
+                highest_rule: 5,
+            },
+            password_html: Some("<p><strong>a</strong>🥚</p>".to_owned()),
+            screenshot: None,
+        };
+
+        let parent =
+            std::env::temp_dir().join(format!("password-game-bot-reports-{}", std::process::id()));
+        let dir = report.save(&parent).unwrap();
+        assert!(dir.starts_with(&parent));
+        let state: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("state.json")).unwrap())
+                .unwrap();
+        assert_eq!(state["error"], "lost password sync");
+        assert_eq!(state["formatting"][0][1], "bold 28px Monospace");
+        assert_eq!(state["formatting"][1][1], "28px Monospace");
+        assert!(dir.join("password.html").exists());
+        assert!(!dir.join("screenshot.png").exists());
+        std::fs::remove_dir_all(&parent).unwrap();
+
+        assert!(worth_reporting(&DriverError::CouldNotSatisfyRule(
+            Rule::Digits
+        )));
+        assert!(worth_reporting(&DriverError::UnknownRule(
+            "new-rule".to_owned()
+        )));
+        assert!(!worth_reporting(&DriverError::GameOver));
+    }
+}
@@ -0,0 +1,132 @@
+use headless_chrome::{browser::tab::element::Element, Tab};
+use log::debug;
+use serde::Deserialize;
+
+use super::get_attributes;
+use crate::{driver::DriverError, logging::DRIVER_WEB};
+
+/// Scrolls the rules list from top to bottom, so that every rule element gets rendered even
+/// if the list is virtualized (as it can be on small windows). Takes an optional selector:
+/// - If given, stops as soon as an element matching the selector exists (leaving the list
+///   scrolled there), and returns whether one was found.
+/// - Otherwise, returns the class attributes of every rule error seen, and restores the
+///   original scroll position.
+pub(crate) const SCAN_SCRIPT: &str = r#"async (selector) => {
+    const firstRule = document.querySelector("div.rule");
+    let container = firstRule ? firstRule.parentElement : null;
+    while (container && container.scrollHeight <= container.clientHeight) {
+        container = container.parentElement;
+    }
+    container = container || document.scrollingElement;
+
+    const seen = [];
+    const collect = () => {
+        if (selector) {
+            return document.querySelector(selector) !== null;
+        }
+        for (const element of document.querySelectorAll("div.rule-error")) {
+            if (!seen.includes(element.className)) {
+                seen.push(element.className);
+            }
+        }
+        return false;
+    };
+    const nextFrame = () => new Promise((resolve) => requestAnimationFrame(() => resolve()));
+
+    if (collect()) {
+        return true;
+    }
+    const start = container.scrollTop;
+    const step = Math.max(container.clientHeight / 2, 100);
+    container.scrollTop = 0;
+    for (;;) {
+        await nextFrame();
+        if (collect()) {
+            return true;
+        }
+        const previous = container.scrollTop;
+        container.scrollTop += step;
+        if (container.scrollTop === previous) {
+            break;
+        }
+    }
+    container.scrollTop = start;
+    return selector ? false : JSON.stringify(seen);
+}"#;
+
+/// Get the class attributes of every violated rule in the rules list, including those which
+/// aren't currently rendered.
+pub fn scan_rule_errors(tab: &Tab) -> Result<Vec<String>, DriverError> {
+    let result = tab.evaluate(&format!("({})(null)", SCAN_SCRIPT), true)?;
+    let classes = match result.value {
+        Some(serde_json::Value::String(json)) => serde_json::from_str::<Vec<String>>(&json)
+            .map_err(|e| anyhow::anyhow!("failed to parse rule error classes: {}", e))?,
+        _ => {
+            // Scanning failed for some reason, so fall back to whatever is rendered
+            let mut classes = Vec::new();
+            for element in tab.find_elements("div.rule-error")? {
+                if let Some(class) = get_attributes(&element)?.remove("class") {
+                    classes.push(class);
+                }
+            }
+            classes
+        }
+    };
+    Ok(classes)
+}
+
+/// Find the element matching the given selector, scrolling the rules list to render it first
+/// if needed.
+pub fn find_rule_element<'a>(tab: &'a Tab, selector: &str) -> Result<Element<'a>, DriverError> {
+    if let Ok(element) = tab.find_element(selector) {
+        return Ok(element);
+    }
+    debug!(target: DRIVER_WEB, "Scrolling rules list to find {:?}", selector);
+    let selector_json = serde_json::to_string(selector).unwrap();
+    tab.evaluate(&format!("({})({})", SCAN_SCRIPT, selector_json), true)?;
+    Ok(tab.find_element(selector)?)
+}
+
+/// What to do when the page shows a violated rule we don't know (e.g., one added to the game
+/// since the bot was written).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownRulePolicy {
+    /// Carry on with the rules we know, without saying anything.
+    Ignore,
+    /// Carry on with the rules we know, warning about each unknown rule the first time it's
+    /// seen.
+    #[default]
+    Warn,
+    /// End the run.
+    Abort,
+}
+
+/// Get the rule names from a rule element's class attribute.
+pub fn rule_classes(class_attribute: &str) -> Vec<&str> {
+    class_attribute
+        .split_ascii_whitespace()
+        .filter(|c| *c != "rule" && *c != "rule-error")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rule_classes, UnknownRulePolicy};
+
+    #[test]
+    fn classes() {
+        assert_eq!(rule_classes("rule rule-error captcha"), vec!["captcha"]);
+        assert_eq!(rule_classes("rule-error  rule youtube "), vec!["youtube"]);
+        assert!(rule_classes("rule rule-error").is_empty());
+    }
+
+    #[test]
+    fn unknown_rule_policy() {
+        assert_eq!(
+            serde_json::from_str::<UnknownRulePolicy>(r#""abort""#).unwrap(),
+            UnknownRulePolicy::Abort
+        );
+        assert!(serde_json::from_str::<UnknownRulePolicy>(r#""fail""#).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::{io, net::TcpListener, path::Path};
+use thiserror::Error;
+
+use crate::{game::GameState, solver::Solver};
+
+/// Our side of a run in progress, saved after every turn so that if the bot crashes, the run
+/// can be picked up again in the browser it was playing in (see `WebDriver::resume`).
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Port the browser accepts DevTools connections on.
+    pub debug_port: u16,
+    pub game_state: GameState,
+    pub solver: Solver,
+}
+
+/// Failure modes for saving and loading snapshots.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum SnapshotError {
+    #[error("failed to read or write snapshot: {0}")]
+    Io(#[from] io::Error),
+    #[error("malformed snapshot: {0}")]
+    Malformed(#[from] serde_json::Error),
+}
+
+impl Snapshot {
+    /// Save the snapshot to the given path, replacing the previous one. Written to a temporary
+    /// file first, so a crash part way through doesn't leave a broken snapshot behind.
+    pub fn save(&self, path: &Path) -> Result<(), SnapshotError> {
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, serde_json::to_string(self)?)?;
+        std::fs::rename(temp_path, path)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SnapshotError> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// A local port which is free for the browser to accept DevTools connections on, so we know
+/// where to find it again. Chosen by the browser if there isn't one.
+pub fn free_port() -> Option<u16> {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).ok()?;
+    Some(listener.local_addr().ok()?.port())
+}
+
+/// URL of the DevTools endpoint to connect to a browser on the given port with.
+pub fn version_url(debug_port: u16) -> String {
+    format!("http://127.0.0.1:{}/json/version", debug_port)
+}
+
+/// The browser's websocket URL from its version endpoint, if it's there.
+pub fn websocket_url(version: &str) -> Option<String> {
+    let version = serde_json::from_str::<serde_json::Value>(version).ok()?;
+    Some(version.get("webSocketDebuggerUrl")?.as_str()?.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{websocket_url, Snapshot};
+    use crate::{
+        game::GameState,
+        password::{
+            format::{FontFamily, FontSize},
+            Change, FormatChange, TrackedRegion,
+        },
+        solver::Solver,
+    };
+
+    #[test]
+    fn snapshot_round_trip() {
+        let mut solver = Solver::with_seed(1);
+        solver.password.queue_change(Change::Append {
+            string: "🥚".into(),
+            protected: true,
+        });
+        solver.password.queue_change(Change::Append {
+            string: "ab12".into(),
+            protected: false,
+        });
+        solver.password.commit_changes().unwrap();
+        solver.password.queue_change(Change::Format {
+            index: 2,
+            format_change: FormatChange::FontFamily(FontFamily::Wingdings),
+        });
+        solver.password.queue_change(Change::Format {
+            index: 3,
+            format_change: FormatChange::FontSize(FontSize::Px28),
+        });
+        solver.password.commit_changes().unwrap();
+        solver.paul = Some(solver.password.track(TrackedRegion::new(0, 1)));
+        solver.goal_length = Some(101);
+        let mut game_state = GameState {
+            highest_rule: 17,
+            egg_placed: true,
+            ..Default::default()
+        };
+        game_state.paul.bug_count = 4;
+
+        let path = std::env::temp_dir().join(format!("snapshot-{}.json", std::process::id()));
+        Snapshot {
+            debug_port: 9222,
+            game_state,
+            solver,
+        }
+        .save(&path)
+        .unwrap();
+        let snapshot = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(snapshot.debug_port, 9222);
+        assert_eq!(snapshot.game_state.highest_rule, 17);
+        assert!(snapshot.game_state.egg_placed);
+        assert_eq!(snapshot.game_state.paul.bug_count, 4);
+        let password = &snapshot.solver.password;
+        assert_eq!(password.as_str(), "🥚ab12");
+        assert_eq!(password.len(), 5);
+        assert_eq!(
+            password.raw_password().formatting()[2].font_family,
+            FontFamily::Wingdings
+        );
+        assert_eq!(
+            password.raw_password().formatting()[3].font_size,
+            FontSize::Px28
+        );
+        assert!(password.is_protected(0));
+        assert!(!password.is_protected(4));
+        assert_eq!(
+            password.tracked(snapshot.solver.paul.unwrap()),
+            Some(&TrackedRegion::new(0, 1))
+        );
+        assert_eq!(snapshot.solver.goal_length, Some(101));
+
+        // A password whose formatting doesn't cover it isn't loaded
+        assert!(serde_json::from_str::<crate::password::Password>(
+            r#"{"password": "ab", "formatting": []}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn find_websocket_url() {
+        assert_eq!(
+            websocket_url(
+                r#"{"Browser": "Chrome/120.0", "webSocketDebuggerUrl": "ws://127.0.0.1:9222/devtools/browser/abc"}"#
+            ),
+            Some("ws://127.0.0.1:9222/devtools/browser/abc".into())
+        );
+        assert_eq!(websocket_url("{}"), None);
+        assert_eq!(websocket_url("not json"), None);
+    }
+}
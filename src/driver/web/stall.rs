@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant, SystemTime};
+
+/// Longest gap between ticks before we assume the run has stalled (e.g., because the machine
+/// slept or the screen locked). Paul needs feeding about this often, so anything longer needs
+/// attention anyway.
+const MAX_TICK_GAP: Duration = Duration::from_secs(60);
+
+/// Watches the time between iterations of the play loop, to notice when the machine has been
+/// asleep. Checks both the monotonic clock (which doesn't advance during sleep on some
+/// platforms) and the wall clock (which does).
+#[derive(Debug, Default)]
+pub struct StallDetector {
+    last_tick: Option<(Instant, SystemTime)>,
+}
+
+impl StallDetector {
+    /// Record a tick. Returns the time since the last tick if it was long enough that the run
+    /// may have gone stale.
+    pub fn tick(&mut self) -> Option<Duration> {
+        self.tick_at(Instant::now(), SystemTime::now())
+    }
+
+    fn tick_at(&mut self, instant: Instant, system_time: SystemTime) -> Option<Duration> {
+        let last_tick = self.last_tick.replace((instant, system_time));
+        let (last_instant, last_system_time) = last_tick?;
+        let gap = instant.saturating_duration_since(last_instant).max(
+            system_time
+                .duration_since(last_system_time)
+                .unwrap_or_default(),
+        );
+        (gap > MAX_TICK_GAP).then_some(gap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant, SystemTime};
+
+    use super::StallDetector;
+
+    #[test]
+    fn jumps() {
+        let mut detector = StallDetector::default();
+        let instant = Instant::now();
+        let system_time = SystemTime::now();
+        assert_eq!(detector.tick_at(instant, system_time), None);
+
+        let second = Duration::from_secs(1);
+        assert_eq!(
+            detector.tick_at(instant + second, system_time + second),
+            None
+        );
+
+        // Asleep, so only the wall clock moved
+        let hour = Duration::from_secs(60 * 60);
+        assert_eq!(
+            detector.tick_at(instant + 2 * second, system_time + hour),
+            Some(hour - second)
+        );
+
+        // Stalled
+        assert_eq!(
+            detector.tick_at(instant + hour, system_time + 2 * hour),
+            Some(hour)
+        );
+
+        // Wall clock set backwards
+        assert_eq!(detector.tick_at(instant + hour + second, system_time), None);
+    }
+}
@@ -1,17 +1,25 @@
 use headless_chrome::browser::tab::ModifierKey;
 
-use super::{super::Driver, WebDriver};
-use crate::{password::Change, solver::Solver};
+use super::{
+    super::{Driver, DriverConfig, DriverError},
+    toolbar::ToolbarState,
+    WebDriver,
+};
+use crate::{
+    game::rule::{Rule, RuleKind},
+    password::{helpers::STRENGTH_EMOJI, Change, ChangeError},
+    solver::Solver,
+};
 
 #[test]
 #[ignore]
 fn get_password() {
     let solver = Solver::default();
-    let mut driver = WebDriver::new(solver).unwrap();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
     assert!(driver.get_password().unwrap().is_empty());
 
     driver
-        .update_password(&mut vec![Change::Append {
+        .update_password(&mut [Change::Append {
             string: "hello".into(),
             protected: false,
         }])
@@ -19,23 +27,26 @@ fn get_password() {
     assert_eq!(driver.get_password().unwrap(), "hello");
 
     driver
-        .update_password(&mut vec![Change::Append {
-            string: "🏋️‍♂️".into(),
+        .update_password(&mut [Change::Append {
+            string: STRENGTH_EMOJI.into(),
             protected: false,
         }])
         .unwrap();
-    assert_eq!(driver.get_password().unwrap(), "hello🏋️‍♂️");
+    assert_eq!(
+        driver.get_password().unwrap(),
+        format!("hello{}", STRENGTH_EMOJI)
+    );
 }
 
 #[test]
 #[ignore]
 fn update_password_append() {
     let solver = Solver::default();
-    let mut driver = WebDriver::new(solver).unwrap();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
     assert!(driver.get_password().unwrap().is_empty());
 
     driver
-        .update_password(&mut vec![Change::Append {
+        .update_password(&mut [Change::Append {
             string: "01234".into(),
             protected: false,
         }])
@@ -43,15 +54,84 @@ fn update_password_append() {
     assert_eq!(driver.get_password().unwrap(), "01234");
 }
 
+#[test]
+#[ignore]
+fn update_password_invalid() {
+    let solver = Solver::default();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
+    driver
+        .update_password(&mut [Change::Append {
+            string: "01234".into(),
+            protected: true,
+        }])
+        .unwrap();
+
+    // The append is fine, but the removal isn't, so neither is typed
+    let result = driver.update_password(&mut [
+        Change::Append {
+            string: "abc".into(),
+            protected: false,
+        },
+        Change::Remove {
+            index: 0,
+            ignore_protection: false,
+        },
+    ]);
+    assert!(matches!(
+        result,
+        Err(DriverError::InvalidChange(ChangeError::Protected(0)))
+    ));
+    assert_eq!(driver.get_password().unwrap(), "01234");
+    assert_eq!(driver.solver.password.as_str(), "01234");
+}
+
+#[test]
+#[ignore]
+fn clipboard_works_outside_game() {
+    let solver = Solver::default();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
+    driver.clipboard_works().unwrap();
+
+    // The probe never reaches the password box, which is left focused
+    assert!(driver.get_password().unwrap().is_empty());
+    driver
+        .update_password(&mut [Change::Append {
+            string: "abc".into(),
+            protected: false,
+        }])
+        .unwrap();
+    assert_eq!(driver.get_password().unwrap(), "abc");
+}
+
+#[test]
+#[ignore]
+fn update_password_per_grapheme() {
+    let solver = Solver::default();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
+    driver.bulk_typing = false;
+    assert!(driver.get_password().unwrap().is_empty());
+
+    driver
+        .update_password(&mut [Change::Append {
+            string: format!("ab{}", STRENGTH_EMOJI),
+            protected: false,
+        }])
+        .unwrap();
+    assert_eq!(
+        driver.get_password().unwrap(),
+        format!("ab{}", STRENGTH_EMOJI)
+    );
+}
+
 #[test]
 #[ignore]
 fn update_password_multiple_appends() {
     let solver = Solver::default();
-    let mut driver = WebDriver::new(solver).unwrap();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
     assert!(driver.get_password().unwrap().is_empty());
 
     driver
-        .update_password(&mut vec![
+        .update_password(&mut [
             Change::Append {
                 string: "a".into(),
                 protected: false,
@@ -69,17 +149,17 @@ fn update_password_multiple_appends() {
 #[ignore]
 fn update_password_insert() {
     let solver = Solver::default();
-    let mut driver = WebDriver::new(solver).unwrap();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
     assert!(driver.get_password().unwrap().is_empty());
 
     driver
-        .update_password(&mut vec![Change::Append {
+        .update_password(&mut [Change::Append {
             string: "for".into(),
             protected: false,
         }])
         .unwrap();
     driver
-        .update_password(&mut vec![Change::Insert {
+        .update_password(&mut [Change::Insert {
             index: 2,
             string: "oba".into(),
             protected: false,
@@ -92,17 +172,17 @@ fn update_password_insert() {
 #[ignore]
 fn update_password_replace() {
     let solver = Solver::default();
-    let mut driver = WebDriver::new(solver).unwrap();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
     assert!(driver.get_password().unwrap().is_empty());
 
     driver
-        .update_password(&mut vec![Change::Append {
+        .update_password(&mut [Change::Append {
             string: "01234".into(),
             protected: false,
         }])
         .unwrap();
     driver
-        .update_password(&mut vec![Change::Replace {
+        .update_password(&mut [Change::Replace {
             index: 2,
             new_grapheme: "t".into(),
             ignore_protection: false,
@@ -115,17 +195,17 @@ fn update_password_replace() {
 #[ignore]
 fn update_password_remove() {
     let solver = Solver::default();
-    let mut driver = WebDriver::new(solver).unwrap();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
     assert!(driver.get_password().unwrap().is_empty());
 
     driver
-        .update_password(&mut vec![Change::Append {
+        .update_password(&mut [Change::Append {
             string: "01234".into(),
             protected: false,
         }])
         .unwrap();
     driver
-        .update_password(&mut vec![Change::Remove {
+        .update_password(&mut [Change::Remove {
             index: 3,
             ignore_protection: false,
         }])
@@ -137,17 +217,17 @@ fn update_password_remove() {
 #[ignore]
 fn update_password_multiple_removals() {
     let solver = Solver::default();
-    let mut driver = WebDriver::new(solver).unwrap();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
     assert!(driver.get_password().unwrap().is_empty());
 
     driver
-        .update_password(&mut vec![Change::Append {
+        .update_password(&mut [Change::Append {
             string: "01234".into(),
             protected: false,
         }])
         .unwrap();
     driver
-        .update_password(&mut vec![
+        .update_password(&mut [
             Change::Remove {
                 index: 1,
                 ignore_protection: false,
@@ -165,18 +245,18 @@ fn update_password_multiple_removals() {
 #[ignore]
 fn update_password_remove_emoji() {
     let solver = Solver::default();
-    let mut driver = WebDriver::new(solver).unwrap();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
     assert!(driver.get_password().unwrap().is_empty());
 
     driver
-        .update_password(&mut vec![Change::Append {
+        .update_password(&mut [Change::Append {
             string: "🔥".into(),
             protected: false,
         }])
         .unwrap();
     assert_eq!(driver.get_password().unwrap(), "🔥");
     driver
-        .update_password(&mut vec![Change::Remove {
+        .update_password(&mut [Change::Remove {
             index: 0,
             ignore_protection: false,
         }])
@@ -188,18 +268,18 @@ fn update_password_remove_emoji() {
 #[ignore]
 fn update_password_remove_zwj_emoji() {
     let solver = Solver::default();
-    let mut driver = WebDriver::new(solver).unwrap();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
     assert!(driver.get_password().unwrap().is_empty());
 
     driver
-        .update_password(&mut vec![Change::Append {
+        .update_password(&mut [Change::Append {
             string: "👨‍👩‍👧‍👧foo".into(),
             protected: false,
         }])
         .unwrap();
     assert_eq!(driver.get_password().unwrap(), "👨‍👩‍👧‍👧foo");
     driver
-        .update_password(&mut vec![Change::Remove {
+        .update_password(&mut [Change::Remove {
             index: 0,
             ignore_protection: false,
         }])
@@ -210,11 +290,11 @@ fn update_password_remove_zwj_emoji() {
 #[ignore]
 fn cursor_movement_zwj_emoji() {
     let solver = Solver::default();
-    let mut driver = WebDriver::new(solver).unwrap();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
     assert!(driver.get_password().unwrap().is_empty());
 
     driver
-        .update_password(&mut vec![Change::Append {
+        .update_password(&mut [Change::Append {
             string: "👨‍👩‍👧‍👧foo".into(),
             protected: false,
         }])
@@ -224,7 +304,7 @@ fn cursor_movement_zwj_emoji() {
     driver.cursor_to(0).unwrap();
 
     driver
-        .update_password(&mut vec![Change::Append {
+        .update_password(&mut [Change::Append {
             string: "bar".into(),
             protected: false,
         }])
@@ -236,11 +316,11 @@ fn cursor_movement_zwj_emoji() {
 #[ignore]
 fn key_press_with_modifiers() {
     let solver = Solver::default();
-    let mut driver = WebDriver::new(solver).unwrap();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
     assert!(driver.get_password().unwrap().is_empty());
 
     driver
-        .update_password(&mut vec![Change::Append {
+        .update_password(&mut [Change::Append {
             string: "hello".into(),
             protected: false,
         }])
@@ -271,11 +351,11 @@ fn key_press_with_modifiers() {
 #[ignore]
 fn delete_password() {
     let solver = Solver::default();
-    let mut driver = WebDriver::new(solver).unwrap();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
     assert!(driver.get_password().unwrap().is_empty());
 
     driver
-        .update_password(&mut vec![Change::Append {
+        .update_password(&mut [Change::Append {
             string: "🥚ello".into(),
             protected: false,
         }])
@@ -287,3 +367,42 @@ fn delete_password() {
     driver.delete_and_retype_passsword().unwrap();
     assert_eq!(driver.get_password().unwrap(), "🥚ello");
 }
+
+#[test]
+#[ignore]
+fn compose_and_type() {
+    let solver = Solver::default();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
+    driver
+        .update_password(&mut [Change::Append {
+            string: "hello".into(),
+            protected: false,
+        }])
+        .unwrap();
+
+    let rules = [Rule::Number, Rule::Uppercase, Rule::Special];
+    assert!(driver.compose_and_type(&rules).unwrap());
+    let password = driver.get_password().unwrap();
+    assert_eq!(password, driver.solver.password.as_str());
+    assert!(rules
+        .iter()
+        .all(|rule| rule.validate(driver.solver.password.raw_password(), &driver.game_state)));
+}
+
+#[test]
+#[ignore]
+fn reinitialize_forgets_rules() {
+    let solver = Solver::default();
+    let mut driver = WebDriver::new(solver, DriverConfig::default()).unwrap();
+    driver
+        .revealed_rules
+        .insert(RuleKind::Captcha, Rule::Captcha("aaaaa".into()));
+    driver.unknown_rule_classes.insert("new-rule".into());
+    driver.toolbar.borrow_mut().bold = Some(true);
+
+    driver.reinitialize().unwrap();
+    assert!(driver.revealed_rules.is_empty());
+    assert!(driver.unknown_rule_classes.is_empty());
+    assert_eq!(*driver.toolbar.borrow(), ToolbarState::default());
+    assert!(driver.get_password().unwrap().is_empty());
+}
@@ -0,0 +1,261 @@
+use headless_chrome::Tab;
+use serde::Deserialize;
+
+use crate::{
+    driver::DriverError,
+    password::{
+        format::{FontFamily, FontSize},
+        FormatChange,
+    },
+};
+
+/// Reads every toolbar button in one go, rather than a round trip per button and attribute.
+const READ_SCRIPT: &str = r#"() => JSON.stringify(Array.from(
+    document.querySelectorAll("div.toolbar button"),
+    (b) => ({
+        text: b.innerText,
+        title: b.getAttribute("title"),
+        aria_label: b.getAttribute("aria-label"),
+        aria_pressed: b.getAttribute("aria-pressed"),
+        class: b.className,
+    })
+))"#;
+
+/// Reads the options of every menu in the toolbar.
+const MENUS_SCRIPT: &str = r#"() => JSON.stringify(Array.from(
+    document.querySelectorAll("div.toolbar select"),
+    (s) => Array.from(s.options, (o) => ({ text: o.text, value: o.value }))
+))"#;
+
+/// Chooses an option in a toolbar menu, as if it had been picked from the menu.
+const CHOOSE_SCRIPT: &str = r#"(menu, value) => {
+    const select = document.querySelectorAll("div.toolbar select")[menu];
+    if (!select) {
+        return false;
+    }
+    select.value = value;
+    select.dispatchEvent(new Event("input", { bubbles: true }));
+    select.dispatchEvent(new Event("change", { bubbles: true }));
+    return select.value === value;
+}"#;
+
+/// An option in a toolbar menu, as read from the page.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct MenuOption {
+    pub text: String,
+    pub value: String,
+}
+
+impl MenuOption {
+    /// Does choosing this option make the given change? Matched on either its text or value,
+    /// whichever the game uses for the font's name or size.
+    fn makes(&self, format_change: &FormatChange) -> bool {
+        [&self.text, &self.value]
+            .into_iter()
+            .any(|label| match format_change {
+                FormatChange::FontFamily(font_family) => {
+                    FontFamily::from_css_name(label.trim()).as_ref() == Some(font_family)
+                }
+                FormatChange::FontSize(font_size) => {
+                    label.trim().trim_end_matches("px").trim().parse() == Ok(font_size.px())
+                }
+                FormatChange::BoldOn | FormatChange::ItalicOn => false,
+            })
+    }
+}
+
+/// The menu (by index among the toolbar's menus) and value of the option which makes the
+/// given change, if there is one.
+pub fn find_option(
+    menus: &[Vec<MenuOption>],
+    format_change: &FormatChange,
+) -> Option<(usize, String)> {
+    menus.iter().enumerate().find_map(|(menu, options)| {
+        options
+            .iter()
+            .find(|option| option.makes(format_change))
+            .map(|option| (menu, option.value.clone()))
+    })
+}
+
+/// Choose the option in the toolbar's menus which makes the given change (a font or font size)
+/// directly, rather than by navigating the menu with the keyboard. Returns whether there was
+/// such an option.
+pub fn choose_option(tab: &Tab, format_change: &FormatChange) -> Result<bool, DriverError> {
+    let result = tab.evaluate(&format!("({})()", MENUS_SCRIPT), false)?;
+    let Some(serde_json::Value::String(json)) = result.value else {
+        return Ok(false);
+    };
+    let menus = serde_json::from_str::<Vec<Vec<MenuOption>>>(&json)
+        .map_err(|e| DriverError::MalformedElement(format!("toolbar menus: {}", e)))?;
+    let Some((menu, value)) = find_option(&menus, format_change) else {
+        return Ok(false);
+    };
+    let value_json = serde_json::to_string(&value).unwrap();
+    let result = tab.evaluate(
+        &format!("({})({}, {})", CHOOSE_SCRIPT, menu, value_json),
+        false,
+    )?;
+    Ok(result.value == Some(serde_json::Value::Bool(true)))
+}
+
+/// A toolbar button, as read from the page.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct ToolbarButton {
+    pub text: String,
+    pub title: Option<String>,
+    pub aria_label: Option<String>,
+    pub aria_pressed: Option<String>,
+    pub class: String,
+}
+
+impl ToolbarButton {
+    /// Is this the button with the given name? Matched loosely on its text, title and aria
+    /// label, so small changes to the game's markup don't matter.
+    fn is(&self, name: &str) -> bool {
+        [
+            Some(&self.text),
+            self.title.as_ref(),
+            self.aria_label.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|label| label.to_lowercase().contains(name))
+    }
+
+    fn is_active(&self) -> bool {
+        self.class.split_whitespace().any(|c| c == "is-active")
+            || self.aria_pressed.as_deref() == Some("true")
+    }
+}
+
+/// The formatting the toolbar says will be applied to what's typed next. Each part is `None`
+/// until it's known, either from reading the toolbar or from setting it ourselves.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ToolbarState {
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
+    pub font_size: Option<FontSize>,
+    pub font_family: Option<FontFamily>,
+}
+
+impl ToolbarState {
+    /// The state shown by the given buttons. The fonts are chosen from menus which don't show
+    /// the current choice, so are left unknown.
+    pub fn from_buttons(buttons: &[ToolbarButton]) -> Self {
+        let active = |name| {
+            buttons
+                .iter()
+                .find(|button| button.is(name))
+                .map(ToolbarButton::is_active)
+        };
+        ToolbarState {
+            bold: active("bold"),
+            italic: active("italic"),
+            ..Default::default()
+        }
+    }
+}
+
+/// Read the state of the toolbar from the page.
+pub fn read(tab: &Tab) -> Result<ToolbarState, DriverError> {
+    let result = tab.evaluate(&format!("({})()", READ_SCRIPT), false)?;
+    let Some(serde_json::Value::String(json)) = result.value else {
+        return Err(DriverError::MalformedElement(
+            "toolbar buttons couldn't be read".into(),
+        ));
+    };
+    let buttons = serde_json::from_str::<Vec<ToolbarButton>>(&json)
+        .map_err(|e| DriverError::MalformedElement(format!("toolbar buttons: {}", e)))?;
+    Ok(ToolbarState::from_buttons(&buttons))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_option, MenuOption, ToolbarButton, ToolbarState};
+    use crate::password::{
+        format::{FontFamily, FontSize},
+        FormatChange,
+    };
+
+    fn button(text: &str, class: &str) -> ToolbarButton {
+        ToolbarButton {
+            text: text.into(),
+            class: class.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn state_from_buttons() {
+        let buttons = vec![
+            button("Bold", "is-active"),
+            button("Italic", "toolbar-button"),
+        ];
+        let state = ToolbarState::from_buttons(&buttons);
+        assert_eq!(state.bold, Some(true));
+        assert_eq!(state.italic, Some(false));
+        assert_eq!(state.font_family, None);
+
+        // Buttons are found by their labels if their text changes (e.g., to an icon)
+        let buttons = vec![
+            ToolbarButton {
+                text: "B".into(),
+                aria_label: Some("Toggle bold".into()),
+                aria_pressed: Some("false".into()),
+                ..Default::default()
+            },
+            ToolbarButton {
+                text: "I".into(),
+                title: Some("Italic (Ctrl+I)".into()),
+                aria_pressed: Some("true".into()),
+                ..Default::default()
+            },
+        ];
+        let state = ToolbarState::from_buttons(&buttons);
+        assert_eq!(state.bold, Some(false));
+        assert_eq!(state.italic, Some(true));
+
+        // Missing buttons are unknown, rather than a panic
+        assert_eq!(ToolbarState::from_buttons(&[]), ToolbarState::default());
+    }
+
+    #[test]
+    fn find_menu_options() {
+        let option = |text: &str, value: &str| MenuOption {
+            text: text.into(),
+            value: value.into(),
+        };
+        let menus = vec![
+            vec![
+                option("28px", "28px"),
+                option("0px", "0px"),
+                option("81px", "81px"),
+            ],
+            vec![
+                option("Monospace", "monospace"),
+                option("Comic Sans", "comic-sans"),
+                option("Wingdings", "wingdings"),
+            ],
+        ];
+        assert_eq!(
+            find_option(&menus, &FormatChange::FontSize(FontSize::Px0)),
+            Some((0, "0px".into()))
+        );
+        assert_eq!(
+            find_option(&menus, &FormatChange::FontFamily(FontFamily::ComicSans)),
+            Some((1, "comic-sans".into()))
+        );
+
+        // The keyboard is used for anything which isn't in a menu
+        assert_eq!(
+            find_option(&menus, &FormatChange::FontFamily(FontFamily::TimesNewRoman)),
+            None
+        );
+        assert_eq!(find_option(&menus, &FormatChange::BoldOn), None);
+        assert_eq!(
+            find_option(&[], &FormatChange::FontSize(FontSize::Px0)),
+            None
+        );
+    }
+}
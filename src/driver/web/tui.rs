@@ -0,0 +1,396 @@
+use std::{
+    io,
+    sync::{mpsc::Sender, Arc, Mutex},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+use strum::IntoEnumIterator;
+
+use crate::{
+    game::{rule::RuleKind, Rule},
+    password::Password,
+};
+
+/// What the terminal UI shows of a game in progress, sent by the web driver after every turn.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub struct Status {
+    /// Seed for the solver's random choices in this game, if it's known.
+    pub seed: Option<u64>,
+    pub password: Password,
+    pub rules: Vec<RuleStatus>,
+    /// When the game started.
+    pub started: Option<Instant>,
+    /// Paul, once he's been fed.
+    pub paul: Option<PaulStatus>,
+}
+
+/// Whether a rule shown by the game is currently satisfied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleStatus {
+    /// The rule's number, or 0 for rules we don't know.
+    pub number: usize,
+    pub name: String,
+    pub passing: bool,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub struct PaulStatus {
+    pub last_fed: Instant,
+    /// How long we leave between feeds.
+    pub feeding_interval: Duration,
+    /// Bugs in the password as of when they were last counted.
+    pub bugs: usize,
+}
+
+impl RuleStatus {
+    /// The status of every rule the game is showing, from the ones it says are violated. Rules
+    /// we don't know are listed last, by their class in the page.
+    pub fn all(highest_rule: usize, violated_rules: &[Rule]) -> Vec<Self> {
+        let known = RuleKind::iter()
+            .filter(|kind| kind.number() <= highest_rule)
+            .map(|kind| RuleStatus {
+                number: kind.number(),
+                name: format!("{:?}", kind),
+                passing: !violated_rules.iter().any(|rule| rule.kind() == kind),
+            });
+        let unknown = violated_rules.iter().filter_map(|rule| match rule {
+            Rule::Unknown(class) => Some(RuleStatus {
+                number: 0,
+                name: class.clone(),
+                passing: false,
+            }),
+            _ => None,
+        });
+        known.chain(unknown).collect()
+    }
+}
+
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+enum Message {
+    Status(Box<Status>),
+    Stop,
+}
+
+/// A terminal UI showing the game in progress, drawn on the terminal's alternate screen by a
+/// background thread. Shared by every run (like `Control`), and put away when the last handle
+/// is dropped. Logs would draw over it, so should be turned off while it's showing.
+#[derive(Debug, Clone)]
+pub struct Tui {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    sender: Sender<Message>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Tui {
+    /// Take over the terminal. Fails if the bot was built without the `tui` feature.
+    pub fn start() -> io::Result<Self> {
+        #[cfg(not(feature = "tui"))]
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the terminal UI needs building with `--features tui`",
+        ));
+        #[cfg(feature = "tui")]
+        {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            let thread = screen::spawn(receiver)?;
+            Ok(Tui {
+                inner: Arc::new(Inner {
+                    sender,
+                    thread: Mutex::new(Some(thread)),
+                }),
+            })
+        }
+    }
+
+    /// Show the given status, replacing the last one.
+    pub fn show(&self, status: Status) {
+        // If drawing failed, the terminal is already back to normal and there's nothing to do
+        let _ = self.inner.sender.send(Message::Status(Box::new(status)));
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        let _ = self.sender.send(Message::Stop);
+        if let Some(thread) = self.thread.lock().unwrap().take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+mod screen {
+    use ratatui::{
+        backend::CrosstermBackend,
+        crossterm::{
+            cursor::{Hide, Show},
+            execute,
+            terminal::{EnterAlternateScreen, LeaveAlternateScreen},
+        },
+        layout::{Constraint, Layout},
+        style::{Color, Modifier, Style, Stylize},
+        text::{Line, Span},
+        widgets::{Block, List, Paragraph, Wrap},
+        Frame, Terminal,
+    };
+    use std::{
+        io::{self, Stdout},
+        sync::mpsc::{Receiver, RecvTimeoutError},
+        thread::{self, JoinHandle},
+        time::Duration,
+    };
+
+    use super::{Message, Status};
+    use crate::password::{
+        format::{FontFamily, FontSize},
+        Format,
+    };
+
+    /// How often the screen is redrawn when nothing's changed, to keep the timers ticking.
+    const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Switch to the alternate screen and start drawing statuses from the receiver on it,
+    /// until told to stop. Raw mode is left off, so Ctrl+C still stops the bot.
+    pub fn spawn(receiver: Receiver<Message>) -> io::Result<JoinHandle<()>> {
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, Hide)?;
+        let terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+        thread::Builder::new()
+            .name("tui".into())
+            .spawn(move || run(terminal, receiver))
+    }
+
+    fn run(mut terminal: Terminal<CrosstermBackend<Stdout>>, receiver: Receiver<Message>) {
+        let mut status = Status::default();
+        loop {
+            if terminal.draw(|frame| draw(frame, &status)).is_err() {
+                break;
+            }
+            match receiver.recv_timeout(REDRAW_INTERVAL) {
+                Ok(Message::Status(new_status)) => status = *new_status,
+                Err(RecvTimeoutError::Timeout) => {}
+                Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        let _ = execute!(terminal.backend_mut(), LeaveAlternateScreen, Show);
+    }
+
+    fn draw(frame: &mut Frame, status: &Status) {
+        let [header, password, legend, rules] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(1),
+            Constraint::Fill(3),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(Paragraph::new(header_line(status)), header);
+        frame.render_widget(
+            Paragraph::new(password_line(status))
+                .wrap(Wrap { trim: false })
+                .block(
+                    Block::bordered()
+                        .title(format!(" Password ({} characters) ", status.password.len())),
+                ),
+            password,
+        );
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                "bold".bold(),
+                " ".into(),
+                "italic".italic(),
+                " ".into(),
+                "Comic Sans".fg(font_color(&FontFamily::ComicSans)),
+                " ".into(),
+                "Wingdings".fg(font_color(&FontFamily::Wingdings)),
+                " ".into(),
+                "Times New Roman".fg(font_color(&FontFamily::TimesNewRoman)),
+                " ".into(),
+                "not 28px".underlined(),
+            ]))
+            .dim(),
+            legend,
+        );
+
+        let passing = status.rules.iter().filter(|rule| rule.passing).count();
+        let items = status.rules.iter().map(|rule| {
+            let number = match rule.number {
+                0 => "?".to_owned(),
+                number => number.to_string(),
+            };
+            let line = format!("{:>2} {}", number, rule.name);
+            if rule.passing {
+                Line::from(format!("✓ {}", line)).green()
+            } else {
+                Line::from(format!("✗ {}", line)).red().bold()
+            }
+        });
+        frame.render_widget(
+            List::new(items).block(Block::bordered().title(format!(
+                " Rules ({}/{} passing) ",
+                passing,
+                status.rules.len()
+            ))),
+            rules,
+        );
+    }
+
+    fn header_line(status: &Status) -> Line<'static> {
+        let mut spans = Vec::new();
+        if let Some(seed) = status.seed {
+            spans.push(format!("Seed {}", seed).bold());
+        }
+        if let Some(started) = status.started {
+            spans.push(format!("Playing for {}", clock(started.elapsed())).into());
+        }
+        if let Some(paul) = &status.paul {
+            let since_fed = paul.last_fed.elapsed();
+            let due = paul.feeding_interval.saturating_sub(since_fed);
+            spans.push(
+                format!(
+                    "Paul fed {} ago, next feed in {}, {} bugs",
+                    clock(since_fed),
+                    clock(due),
+                    paul.bugs
+                )
+                .fg(if due.is_zero() {
+                    Color::Red
+                } else {
+                    Color::Reset
+                }),
+            );
+        }
+        let mut line = Vec::new();
+        for (i, span) in spans.into_iter().enumerate() {
+            if i > 0 {
+                line.push(Span::raw(" · "));
+            }
+            line.push(span);
+        }
+        Line::from(line)
+    }
+
+    /// The password, with each character styled to show its formatting.
+    fn password_line(status: &Status) -> Line<'static> {
+        Line::from(
+            status
+                .password
+                .annotated()
+                .map(|(_, grapheme, format)| Span::styled(grapheme.to_owned(), style(format)))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn style(format: &Format) -> Style {
+        let mut style = Style::new().fg(font_color(&format.font_family));
+        if format.bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if format.italic {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        if format.font_size != FontSize::default() {
+            style = style.add_modifier(Modifier::UNDERLINED);
+        }
+        style
+    }
+
+    fn font_color(font_family: &FontFamily) -> Color {
+        match font_family {
+            FontFamily::Monospace => Color::Reset,
+            FontFamily::ComicSans => Color::Yellow,
+            FontFamily::Wingdings => Color::Magenta,
+            FontFamily::TimesNewRoman => Color::Cyan,
+        }
+    }
+
+    /// A duration as minutes and seconds.
+    fn clock(duration: Duration) -> String {
+        let secs = duration.as_secs();
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use ratatui::{backend::TestBackend, Terminal};
+        use std::time::{Duration, Instant};
+
+        use super::draw;
+        use crate::{
+            driver::web::tui::{PaulStatus, RuleStatus, Status},
+            game::Rule,
+            password::Password,
+        };
+
+        #[test]
+        fn draw_status() {
+            let status = Status {
+                seed: Some(7),
+                password: Password::from_markup("**a**b🥚").unwrap(),
+                rules: RuleStatus::all(3, &[Rule::Uppercase]),
+                started: Some(Instant::now()),
+                paul: Some(PaulStatus {
+                    last_fed: Instant::now(),
+                    feeding_interval: Duration::from_secs(30),
+                    bugs: 6,
+                }),
+            };
+            let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+            terminal.draw(|frame| draw(frame, &status)).unwrap();
+            let screen = terminal
+                .backend()
+                .buffer()
+                .content()
+                .iter()
+                .map(|cell| cell.symbol())
+                .collect::<String>();
+            for shown in [
+                "Seed 7",
+                "Playing for 0:00",
+                "Paul fed 0:00 ago",
+                "6 bugs",
+                "Password (3 characters)",
+                "Rules (2/3 passing)",
+                "✓  1 MinLength",
+                "✗  3 Uppercase",
+            ] {
+                assert!(screen.contains(shown), "{:?} not in {:?}", shown, screen);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RuleStatus;
+    use crate::game::Rule;
+
+    #[test]
+    fn rule_statuses() {
+        let rules = RuleStatus::all(5, &[Rule::Number, Rule::Unknown("rule-new".into())]);
+        assert_eq!(
+            rules
+                .iter()
+                .map(|rule| (rule.number, rule.passing))
+                .collect::<Vec<_>>(),
+            [
+                (1, true),
+                (2, false),
+                (3, true),
+                (4, true),
+                (5, true),
+                (0, false)
+            ]
+        );
+        assert_eq!(rules[1].name, "Number");
+        assert_eq!(rules[5].name, "rule-new");
+
+        assert!(RuleStatus::all(0, &[]).is_empty());
+    }
+}
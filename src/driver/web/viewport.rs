@@ -0,0 +1,184 @@
+use headless_chrome::{
+    browser::tab::{element::Element, point::Point},
+    protocol::cdp::{Emulation, Page},
+    Tab,
+};
+use log::{debug, warn};
+
+use crate::{driver::DriverError, logging::DRIVER_WEB};
+
+/// How far a scale factor can be from 1 before we consider it a mismatch.
+const SCALE_TOLERANCE: f64 = 0.01;
+
+/// Viewport metrics, used to translate element coordinates (CSS pixels relative to the
+/// layout viewport) into the coordinates expected by CDP mouse events.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewportMetrics {
+    /// Ratio of device pixels to CSS pixels (OS display scaling and browser zoom combined).
+    pub device_pixel_ratio: f64,
+    /// Browser zoom factor (ratio of CSS pixels to device independent pixels).
+    pub zoom: f64,
+    /// Pinch-zoom scale of the visual viewport.
+    pub scale: f64,
+    /// Offset of the visual viewport within the layout viewport, in CSS pixels.
+    pub offset: Point,
+}
+
+impl Default for ViewportMetrics {
+    fn default() -> Self {
+        ViewportMetrics {
+            device_pixel_ratio: 1.0,
+            zoom: 1.0,
+            scale: 1.0,
+            offset: Point { x: 0.0, y: 0.0 },
+        }
+    }
+}
+
+impl ViewportMetrics {
+    /// Get the current viewport metrics of the given tab.
+    pub fn fetch(tab: &Tab) -> Result<Self, DriverError> {
+        let metrics = tab.call_method(Page::GetLayoutMetrics(None))?;
+        let device_pixel_ratio = tab
+            .evaluate("window.devicePixelRatio", false)?
+            .value
+            .and_then(|v| v.as_f64())
+            .unwrap_or(1.0);
+        let visual_viewport = metrics.css_visual_viewport;
+        Ok(ViewportMetrics {
+            device_pixel_ratio,
+            zoom: visual_viewport.zoom.unwrap_or(1.0),
+            scale: visual_viewport.scale,
+            offset: Point {
+                x: visual_viewport.offset_x,
+                y: visual_viewport.offset_y,
+            },
+        })
+    }
+
+    /// Translate a point in element coordinates into a point for a CDP mouse event.
+    pub fn translate(&self, point: Point) -> Point {
+        Point {
+            x: (point.x - self.offset.x) * self.scale * self.zoom,
+            y: (point.y - self.offset.y) * self.scale * self.zoom,
+        }
+    }
+
+    /// Is the page zoomed in or out via the browser (e.g., Ctrl +/-)?
+    pub fn browser_zoomed(&self) -> bool {
+        (self.zoom - 1.0).abs() > SCALE_TOLERANCE
+    }
+
+    /// Is the page pinch-zoomed or panned?
+    pub fn pinch_zoomed(&self) -> bool {
+        (self.scale - 1.0).abs() > SCALE_TOLERANCE || self.offset.x != 0.0 || self.offset.y != 0.0
+    }
+
+    /// Describe any scaling which means element coordinates won't line up with mouse event
+    /// coordinates without translation, or None if they will.
+    pub fn mismatch(&self) -> Option<String> {
+        let mut reasons = Vec::new();
+        if self.browser_zoomed() {
+            reasons.push(format!("browser zoom is {:.0}%", self.zoom * 100.0));
+        }
+        if (self.scale - 1.0).abs() > SCALE_TOLERANCE {
+            reasons.push(format!("pinch zoom scale is {:.2}", self.scale));
+        }
+        if self.offset.x != 0.0 || self.offset.y != 0.0 {
+            reasons.push(format!(
+                "visual viewport is offset by ({}, {})",
+                self.offset.x, self.offset.y
+            ));
+        }
+        if reasons.is_empty() {
+            None
+        } else {
+            Some(reasons.join(", "))
+        }
+    }
+}
+
+/// Click the middle of the given element, translating its coordinates with the given
+/// viewport metrics.
+pub fn click_element(
+    tab: &Tab,
+    element: &Element,
+    metrics: &ViewportMetrics,
+) -> Result<(), DriverError> {
+    element.scroll_into_view()?;
+    let midpoint = element.get_midpoint()?;
+    let point = metrics.translate(midpoint);
+    debug!(target: DRIVER_WEB, "Clicking {:?} (element midpoint {:?})", point, midpoint);
+    tab.click_point(point)?;
+    Ok(())
+}
+
+/// Reset any pinch zoom (and panning) back to 100%.
+pub fn reset_pinch_zoom(tab: &Tab) -> Result<(), DriverError> {
+    tab.call_method(Emulation::SetPageScaleFactor {
+        page_scale_factor: 1.0,
+    })?;
+    Ok(())
+}
+
+/// Fetch the current viewport metrics, warning if they've changed to something which needs
+/// translation.
+pub fn refresh_metrics(
+    tab: &Tab,
+    previous: Option<&ViewportMetrics>,
+) -> Result<ViewportMetrics, DriverError> {
+    let metrics = ViewportMetrics::fetch(tab)?;
+    if previous != Some(&metrics) {
+        debug!(target: DRIVER_WEB, "Viewport metrics: {:?}", metrics);
+        if let Some(mismatch) = metrics.mismatch() {
+            warn!(
+                target: DRIVER_WEB,
+                "Element coordinates don't match device pixels ({}), translating clicks",
+                mismatch
+            );
+        }
+    }
+    Ok(metrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Point, ViewportMetrics};
+
+    #[test]
+    fn translate() {
+        let metrics = ViewportMetrics::default();
+        assert!(metrics.mismatch().is_none());
+        assert!(!metrics.browser_zoomed());
+        assert!(!metrics.pinch_zoomed());
+        assert_eq!(
+            metrics.translate(Point { x: 10.0, y: 20.0 }),
+            Point { x: 10.0, y: 20.0 }
+        );
+
+        // Device pixel ratio alone (e.g., OS display scaling) doesn't need translation
+        let metrics = ViewportMetrics {
+            device_pixel_ratio: 1.5,
+            ..Default::default()
+        };
+        assert!(metrics.mismatch().is_none());
+        assert_eq!(
+            metrics.translate(Point { x: 10.0, y: 20.0 }),
+            Point { x: 10.0, y: 20.0 }
+        );
+
+        let metrics = ViewportMetrics {
+            device_pixel_ratio: 2.0,
+            zoom: 1.25,
+            scale: 2.0,
+            offset: Point { x: 4.0, y: 8.0 },
+        };
+        assert!(metrics.mismatch().unwrap().contains("125%"));
+        assert!(metrics.browser_zoomed());
+        assert!(metrics.pinch_zoomed());
+        assert_eq!(
+            metrics.translate(Point { x: 10.0, y: 20.0 }),
+            Point { x: 15.0, y: 30.0 }
+        );
+    }
+}
@@ -220,54 +220,3 @@ pub fn release_key(key: &Key) {
     }
     std::thread::sleep(WAIT_TIME);
 }
-
-#[cfg(test)]
-mod tests {
-    use super::{press_and_release_key, press_key, release_key, KEYS};
-    use crate::{
-        driver::{web::WebDriver, Driver},
-        solver::Solver,
-    };
-
-    #[test]
-    #[ignore]
-    fn enter_text() {
-        let solver = Solver::default();
-        let driver = WebDriver::new(solver).unwrap();
-        assert!(driver.get_password().unwrap().is_empty());
-
-        press_and_release_key(KEYS.get("f").unwrap());
-        press_and_release_key(KEYS.get("o").unwrap());
-        press_and_release_key(KEYS.get("o").unwrap());
-        assert_eq!(driver.get_password().unwrap(), "foo");
-    }
-
-    #[test]
-    #[ignore]
-    fn select_text() {
-        let solver = Solver::default();
-        let driver = WebDriver::new(solver).unwrap();
-        assert!(driver.get_password().unwrap().is_empty());
-
-        press_and_release_key(KEYS.get("f").unwrap());
-        press_and_release_key(KEYS.get("o").unwrap());
-        press_and_release_key(KEYS.get("o").unwrap());
-        assert_eq!(driver.get_password().unwrap(), "foo");
-
-        for _ in 0..3 {
-            press_and_release_key(KEYS.get("NumpadLeft").unwrap());
-        }
-        press_key(KEYS.get("Shift").unwrap());
-        press_key(KEYS.get("RShift").unwrap());
-        for _ in 0..3 {
-            press_and_release_key(KEYS.get("NumpadRight").unwrap());
-        }
-        release_key(KEYS.get("Shift").unwrap());
-        release_key(KEYS.get("RShift").unwrap());
-
-        press_and_release_key(KEYS.get("b").unwrap());
-        press_and_release_key(KEYS.get("a").unwrap());
-        press_and_release_key(KEYS.get("r").unwrap());
-        assert_eq!(driver.get_password().unwrap(), "bar");
-    }
-}
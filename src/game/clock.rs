@@ -0,0 +1,75 @@
+use chrono::{DateTime, Local};
+use std::{fmt::Debug, sync::Arc};
+
+/// A source of the current wall-clock time, for rules which depend on it (such as `Time`,
+/// `Wordle` and `MoonPhase`).
+pub trait Clock: Debug + Send + Sync {
+    /// The current local time.
+    fn now(&self) -> DateTime<Local>;
+}
+
+/// The system clock.
+#[derive(Debug, Default)]
+pub struct RealClock;
+
+impl Clock for RealClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// A clock which always reads the same time, for checks which shouldn't depend on when
+/// they're run.
+#[derive(Debug)]
+pub struct FixedClock(pub DateTime<Local>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Local> {
+        self.0
+    }
+}
+
+/// A clock shared between the game state, solver and driver. Defaults to the system clock.
+#[derive(Debug, Clone)]
+pub struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        SharedClock(clock)
+    }
+
+    /// The current local time.
+    pub fn now(&self) -> DateTime<Local> {
+        self.0.now()
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        SharedClock(Arc::new(RealClock))
+    }
+}
+
+/// A clock which only moves when told to, for deterministic tests.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct MockClock(std::sync::Mutex<DateTime<Local>>);
+
+#[cfg(test)]
+impl MockClock {
+    pub fn new(now: DateTime<Local>) -> Self {
+        MockClock(std::sync::Mutex::new(now))
+    }
+
+    /// Move the clock forwards by the given amount.
+    pub fn advance(&self, duration: chrono::Duration) {
+        *self.0.lock().unwrap() += duration;
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Local> {
+        *self.0.lock().unwrap()
+    }
+}
@@ -1,4 +1,28 @@
 use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+use crate::password::helpers::{get_digits, get_roman_numerals};
+
+/// A captcha, with what its answer would add to the password.
+#[derive(Debug, Clone)]
+pub struct Captcha {
+    /// The answer, as shown in the image.
+    pub answer: &'static str,
+    /// Sum of the digits in the answer.
+    pub digit_sum: u32,
+    /// Whether the answer contains any roman numerals.
+    pub has_roman_numerals: bool,
+}
+
+impl Captcha {
+    fn new(answer: &'static str) -> Self {
+        Captcha {
+            answer,
+            digit_sum: get_digits(answer).iter().map(|(d, _)| d).sum(),
+            has_roman_numerals: !get_roman_numerals(answer).is_empty(),
+        }
+    }
+}
 
 /// A chess puzzle.
 #[derive(Debug, Clone)]
@@ -19,14 +43,24 @@ pub struct GeoGame {
 }
 
 lazy_static! {
-    pub static ref CAPTCHAS: Vec<&'static str> = {
+    pub static ref CAPTCHAS: Vec<Captcha> = {
         let mut v = Vec::new();
         let captchas_raw = include_str!("data/captchas.txt");
         for line in captchas_raw.lines().filter(|l| !l.is_empty()) {
-            v.push(line);
+            v.push(Captcha::new(line));
         }
         v
     };
+    /// The game's names for countries whose ISO names it doesn't use, by ISO name (in lower
+    /// case).
+    pub static ref COUNTRY_ALIASES: HashMap<&'static str, &'static str> = {
+        let aliases_raw = include_str!("data/country_aliases.txt");
+        aliases_raw
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(|line| line.split_once('=').expect("malformed country alias"))
+            .collect()
+    };
     pub static ref GEO_GAMES: Vec<GeoGame> = {
         let mut v = Vec::new();
         let coordinates_raw = include_str!("data/coordinates.txt");
@@ -63,6 +97,12 @@ lazy_static! {
         }
         v
     };
+    /// Wordle answers in the order the original (pre-NYT) game used them, one a day starting on
+    /// 2021-06-19. Used when the game's Wordle API can't be reached.
+    pub static ref WORDLE_ANSWERS: Vec<&'static str> = include_str!("data/wordle_answers.txt")
+        .lines()
+        .filter(|l| !l.is_empty())
+        .collect();
 }
 
 #[cfg(test)]
@@ -74,17 +114,48 @@ mod tests {
         use super::CAPTCHAS;
 
         assert_eq!(CAPTCHAS.len(), 149);
-        assert!(CAPTCHAS.iter().all(|c| c.len() == 5));
+        assert!(CAPTCHAS.iter().all(|c| c.answer.len() == 5));
+
+        let captcha = CAPTCHAS.iter().find(|c| c.answer == "2b827").unwrap();
+        assert_eq!(captcha.digit_sum, 19);
+        assert!(!captcha.has_roman_numerals);
+        // Captchas are all lowercase, so never contain roman numerals
+        assert!(CAPTCHAS.iter().all(|c| !c.has_roman_numerals));
+    }
+
+    #[test]
+    fn captcha_metadata() {
+        use super::Captcha;
+
+        let captcha = Captcha::new("4XIb2");
+        assert_eq!(captcha.digit_sum, 6);
+        assert!(captcha.has_roman_numerals);
+
+        let captcha = Captcha::new("xi7mv");
+        assert_eq!(captcha.digit_sum, 7);
+        assert!(!captcha.has_roman_numerals);
     }
 
     #[test]
-    #[ignore]
     fn load_geo_games() {
-        use super::GEO_GAMES;
+        use super::{COUNTRY_ALIASES, GEO_GAMES};
         use crate::game::helpers::get_country_from_coordinates;
 
         assert_eq!(GEO_GAMES.len(), 63);
+        assert_eq!(COUNTRY_ALIASES["russian federation"], "russia");
+
+        // Known locations, in the order the game lists them
+        for (index, coordinates, country) in [
+            (0, (-25.35068396746521, 131.0463222711639), "australia"),
+            (18, (64.78260329999999, -21.5608493), "iceland"),
+            (60, (1.280652667541553, 103.8642833171509), "singapore"),
+            (62, (65.80550118091678, 21.67888296764118), "sweden"),
+        ] {
+            assert_eq!(GEO_GAMES[index].coordindates, coordinates);
+            assert_eq!(GEO_GAMES[index].country, country);
+        }
 
+        // Every location's country comes out of the geocoder as the game names it
         for geo_game in GEO_GAMES.iter() {
             let country = get_country_from_coordinates(
                 NotNan::new(geo_game.coordindates.0).unwrap(),
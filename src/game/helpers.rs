@@ -1,25 +1,179 @@
 use cached::proc_macro::cached;
 use chrono::prelude::*;
+#[cfg(feature = "native")]
 use iso8601_duration::Duration;
 use isocountry::CountryCode;
+use lazy_static::lazy_static;
+use log::warn;
+#[cfg(feature = "native")]
+use log::{debug, info};
 use ordered_float::NotNan;
-use pleco::{bots::JamboreeSearcher, tools::Searcher, BitMove, Board};
+use pleco::{bots::JamboreeSearcher, tools::Searcher, BitMove, Board, PieceType};
 use reverse_geocoder::{Locations, ReverseGeocoder};
+#[cfg(feature = "native")]
 use scraper::{Html, Selector};
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+    process::{ChildStdout, Command, Stdio},
+    str::FromStr,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::Instant,
+};
 use suncalc::{moon_illumination, Timestamp};
+use thiserror::Error;
 
-use super::rule::MoonPhase;
+use super::{
+    data::{COUNTRY_ALIASES, WORDLE_ANSWERS},
+    rule::MoonPhase,
+};
+use crate::logging::{CHESS, LOOKUP};
+#[cfg(feature = "native")]
+use crate::{
+    http::{self, HttpError},
+    logging::YOUTUBE,
+    storage::STORAGE,
+};
 
-/// Get today's Wordle answer from neal.fun API for the given date.
-#[cached]
-pub fn get_wordle_answer(date: NaiveDate) -> String {
-    let url = format!(
-        "https://neal.fun/api/password-game/wordle?date={}",
-        date.format("%Y-%m-%d")
-    );
-    let body = reqwest::blocking::get(url).unwrap().text().unwrap();
-    let json = serde_json::from_str::<serde_json::Value>(&body).unwrap();
-    json["answer"].to_string().trim_matches('"').to_owned()
+/// Storage stream for persistently cached YouTube video durations.
+#[cfg(feature = "native")]
+const YOUTUBE_DURATION_CACHE: &str = "cache.youtube_duration";
+/// Storage stream for persistently cached Wordle answers, keyed by local date (so they're
+/// effectively invalidated at local midnight).
+#[cfg(feature = "native")]
+const WORDLE_ANSWER_CACHE: &str = "cache.wordle_answer";
+/// Environment variable with the path of a Wordle answer list to use instead of the bundled one
+/// (e.g., the full list, to work out answers for recent dates without the API).
+pub const WORDLE_ANSWERS_ENV: &str = "BOT_WORDLE_ANSWERS";
+/// Environment variable used to choose the chess engine.
+pub const CHESS_ENGINE_ENV: &str = "BOT_CHESS_ENGINE";
+/// Search depth for pleco, unless configured otherwise.
+const DEFAULT_PLECO_DEPTH: u16 = 4;
+/// Search depth for external UCI engines.
+const UCI_DEPTH: u16 = 18;
+/// Longest to wait for an external engine to finish a search, including the UCI handshake.
+const UCI_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+lazy_static! {
+    /// Engine used to find the best move in chess puzzles, chosen via the `BOT_CHESS_ENGINE`
+    /// environment variable. Defaults to pleco.
+    static ref ENGINE: Box<dyn Engine> = engine_from_env();
+    /// Wordle answers to work out answers from offline, chosen via the `BOT_WORDLE_ANSWERS`
+    /// environment variable. Defaults to the bundled list.
+    static ref OFFLINE_WORDLE_ANSWERS: Vec<String> = wordle_answers_from_env();
+    /// Places to reverse geocode coordinates against, loaded once as it's slow.
+    static ref LOCATIONS: Locations = Locations::from_memory();
+    static ref GEOCODER: ReverseGeocoder<'static> = ReverseGeocoder::new(&LOCATIONS);
+}
+
+/// Get today's Wordle answer from neal.fun API for the given date, falling back to the bundled
+/// answer list if the API fails.
+#[cfg(feature = "native")]
+#[cached(result = true)]
+pub fn get_wordle_answer(date: NaiveDate) -> Result<String, HttpError> {
+    let key = date.format("%Y-%m-%d").to_string();
+    match STORAGE.lock().unwrap().cache_get(WORDLE_ANSWER_CACHE, &key) {
+        Ok(Some(answer)) => {
+            info!(target: LOOKUP, "Wordle answer for {} is {:?} (cached)", key, answer);
+            return Ok(answer);
+        }
+        Ok(None) => {}
+        Err(e) => warn!(target: LOOKUP, "Failed to read Wordle answer cache: {}", e),
+    }
+
+    let url = format!("https://neal.fun/api/password-game/wordle?date={}", key);
+    let answer = match http::get_text(&url).and_then(|body| parse_wordle_answer(&url, &body)) {
+        Ok(answer) => answer,
+        Err(e) => {
+            let Some(answer) = offline_wordle_answer(date) else {
+                warn!(
+                    target: LOOKUP,
+                    "Failed to get Wordle answer from the API, and the bundled list doesn't \
+                     go up to {}: {}",
+                    key,
+                    e
+                );
+                return Err(e);
+            };
+            warn!(
+                target: LOOKUP,
+                "Failed to get Wordle answer from the API, using the bundled list: {}",
+                e
+            );
+            info!(target: LOOKUP, "Wordle answer for {} is {:?} (bundled)", key, answer);
+            // Not stored in the cache, so later runs try the API again
+            return Ok(answer.to_owned());
+        }
+    };
+    info!(target: LOOKUP, "Wordle answer for {} is {:?} (API)", key, answer);
+    if let Err(e) = STORAGE
+        .lock()
+        .unwrap()
+        .cache_put(WORDLE_ANSWER_CACHE, &key, &answer)
+    {
+        warn!(target: LOOKUP, "Failed to write Wordle answer cache: {}", e);
+    }
+    Ok(answer)
+}
+
+/// Get the answer from a response of the Wordle API, checking it looks like a Wordle answer.
+#[cfg(feature = "native")]
+fn parse_wordle_answer(url: &str, body: &str) -> Result<String, HttpError> {
+    let unexpected = |reason: String| HttpError::UnexpectedResponse {
+        url: url.to_owned(),
+        reason,
+    };
+    let json =
+        serde_json::from_str::<serde_json::Value>(body).map_err(|e| unexpected(e.to_string()))?;
+    let Some(answer) = json["answer"].as_str() else {
+        return Err(unexpected("no answer".into()));
+    };
+    if answer.len() != 5 || !answer.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(unexpected(format!("{:?} isn't a Wordle answer", answer)));
+    }
+    Ok(answer.to_ascii_lowercase())
+}
+
+/// The Wordle answer for the given date by the original game's algorithm (the answer list
+/// in order, one a day), if the answer list (see `BOT_WORDLE_ANSWERS`) goes up to that date.
+pub fn offline_wordle_answer(date: NaiveDate) -> Option<&'static str> {
+    wordle_answer_from(&OFFLINE_WORDLE_ANSWERS, date)
+}
+
+/// The answer for the given date from a list of Wordle answers, one a day from 2021-06-19.
+fn wordle_answer_from(answers: &[String], date: NaiveDate) -> Option<&str> {
+    let first_day = NaiveDate::from_ymd_opt(2021, 6, 19).unwrap();
+    let index = usize::try_from((date - first_day).num_days()).ok()?;
+    answers.get(index).map(String::as_str)
+}
+
+/// Parse a Wordle answer list, one answer a line, skipping blank lines.
+fn parse_wordle_answers(list: &str) -> Vec<String> {
+    list.lines()
+        .map(|line| line.trim().to_ascii_lowercase())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Load the Wordle answer list from the path in the `BOT_WORDLE_ANSWERS` environment variable,
+/// falling back to the bundled list if it's unset or can't be read.
+fn wordle_answers_from_env() -> Vec<String> {
+    let bundled = || WORDLE_ANSWERS.iter().map(|a| (*a).to_owned()).collect();
+    let Ok(path) = std::env::var(WORDLE_ANSWERS_ENV) else {
+        return bundled();
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(list) => parse_wordle_answers(&list),
+        Err(e) => {
+            warn!(
+                target: LOOKUP,
+                "Failed to read Wordle answers from {}, using the bundled list: {}",
+                path,
+                e
+            );
+            bundled()
+        }
+    }
 }
 
 /// Get the phase of the moon on the given date.
@@ -61,88 +215,392 @@ pub fn is_prime(n: usize) -> bool {
     }
     let limit = (n as f64).sqrt() as usize;
     for i in 2..=limit {
-        if n % i == 0 {
+        if n.is_multiple_of(i) {
             return false;
         }
     }
     true
 }
 
-/// Convert a pleco::BitMove into standard algebraic notation (SAN).
-/// Note that this function only supports a subset of SAN, enough to cover all the
-/// solution moves to puzzles in the password game.
+/// A chess engine which can find the best move in a position.
+pub trait Engine: Send + Sync {
+    /// The best move for the side to play, or `None` if the engine couldn't find one.
+    fn best_move(&self, board: &Board) -> Option<BitMove>;
+}
+
+/// Pleco's built-in searcher. Runs offline, but occasionally disagrees with the game at low
+/// depths.
+#[derive(Debug)]
+pub struct PlecoEngine {
+    pub depth: u16,
+}
+
+impl Default for PlecoEngine {
+    fn default() -> Self {
+        PlecoEngine {
+            depth: DEFAULT_PLECO_DEPTH,
+        }
+    }
+}
+
+impl Engine for PlecoEngine {
+    fn best_move(&self, board: &Board) -> Option<BitMove> {
+        let best_move = JamboreeSearcher::best_move(board.clone(), self.depth);
+        (!best_move.is_null()).then_some(best_move)
+    }
+}
+
+/// An external engine (e.g., a local Stockfish binary) spoken to over UCI. A fresh process is
+/// started for each search, as puzzles are rare and results are cached.
+#[derive(Debug)]
+pub struct UciEngine {
+    pub path: PathBuf,
+}
+
+impl UciEngine {
+    /// Run a search, returning the best move in UCI notation (e.g., `e7e8q`), or `None` if the
+    /// engine exits without one.
+    fn search(&self, fen: &str) -> io::Result<Option<String>> {
+        self.search_within(fen, UCI_TIMEOUT)
+    }
+
+    /// Run a search, killing the engine and returning a `TimedOut` error if it hasn't finished
+    /// within `timeout`.
+    fn search_within(&self, fen: &str, timeout: std::time::Duration) -> io::Result<Option<String>> {
+        let deadline = Instant::now() + timeout;
+        let mut child = Command::new(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let lines = read_lines(child.stdout.take().expect("stdout is piped"));
+
+        let mut search = || -> io::Result<Option<String>> {
+            writeln!(stdin, "uci")?;
+            if wait_for_line(&lines, deadline, |line| line == "uciok")?.is_none() {
+                return Ok(None);
+            }
+            writeln!(stdin, "isready")?;
+            if wait_for_line(&lines, deadline, |line| line == "readyok")?.is_none() {
+                return Ok(None);
+            }
+            writeln!(stdin, "position fen {}", fen)?;
+            writeln!(stdin, "go depth {}", UCI_DEPTH)?;
+            let best_move = wait_for_line(&lines, deadline, |line| line.starts_with("bestmove "))?;
+            Ok(best_move.and_then(|line| line.split_whitespace().nth(1).map(|m| m.to_owned())))
+        };
+        let result = search();
+
+        if result.is_err() {
+            child.kill().ok();
+        } else {
+            writeln!(stdin, "quit").ok();
+        }
+        drop(stdin);
+        child.wait()?;
+        result
+    }
+}
+
+/// Read an engine's output a line at a time on a separate thread, so that we can stop waiting
+/// for it.
+fn read_lines(stdout: ChildStdout) -> Receiver<io::Result<String>> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// Wait for a line of engine output matching `is_wanted`, skipping any others. Returns `None` if
+/// the engine exits first, or a `TimedOut` error if the deadline passes.
+fn wait_for_line(
+    lines: &Receiver<io::Result<String>>,
+    deadline: Instant,
+    is_wanted: impl Fn(&str) -> bool,
+) -> io::Result<Option<String>> {
+    loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match lines.recv_timeout(timeout) {
+            Ok(line) => {
+                let line = line?;
+                if is_wanted(line.trim()) {
+                    return Ok(Some(line));
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "engine didn't respond in time",
+                ))
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(None),
+        }
+    }
+}
+
+impl Engine for UciEngine {
+    fn best_move(&self, board: &Board) -> Option<BitMove> {
+        let uci_move = match self.search(&board.fen()) {
+            Ok(uci_move) => uci_move?,
+            Err(e) => {
+                warn!(target: CHESS, "Failed to run {}: {}", self.path.display(), e);
+                return None;
+            }
+        };
+        let best_move = board
+            .generate_moves()
+            .iter()
+            .find(|m| m.stringify() == uci_move)
+            .copied();
+        if best_move.is_none() {
+            warn!(target: CHESS, "Engine returned unknown move {:?}", uci_move);
+        }
+        best_move
+    }
+}
+
+/// Chess engines which can be chosen via `BOT_CHESS_ENGINE`.
+#[derive(Debug, PartialEq)]
+pub enum EngineKind {
+    /// `pleco` or `pleco:<depth>`.
+    Pleco(u16),
+    /// `uci:<path to engine binary>`.
+    Uci(PathBuf),
+}
+
+#[derive(Debug, Error)]
+#[error("invalid chess engine specification {0:?}")]
+pub struct InvalidEngineSpec(String);
+
+impl FromStr for EngineKind {
+    type Err = InvalidEngineSpec;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "pleco" => Ok(EngineKind::Pleco(DEFAULT_PLECO_DEPTH)),
+            Some(("pleco", depth)) => depth
+                .parse()
+                .ok()
+                .filter(|depth| *depth > 0)
+                .map(EngineKind::Pleco)
+                .ok_or_else(|| InvalidEngineSpec(s.to_owned())),
+            Some(("uci", path)) if !path.is_empty() => Ok(EngineKind::Uci(path.into())),
+            _ => Err(InvalidEngineSpec(s.to_owned())),
+        }
+    }
+}
+
+impl EngineKind {
+    fn engine(self) -> Box<dyn Engine> {
+        match self {
+            EngineKind::Pleco(depth) => Box::new(PlecoEngine { depth }),
+            EngineKind::Uci(path) => Box::new(UciEngine { path }),
+        }
+    }
+}
+
+/// Create the engine specified by the `BOT_CHESS_ENGINE` environment variable, falling back
+/// to pleco if it's unset or invalid.
+fn engine_from_env() -> Box<dyn Engine> {
+    let Ok(spec) = std::env::var(CHESS_ENGINE_ENV) else {
+        return Box::<PlecoEngine>::default();
+    };
+    match spec.parse::<EngineKind>() {
+        Ok(kind) => kind.engine(),
+        Err(e) => {
+            warn!(target: CHESS, "{}, using pleco", e);
+            Box::<PlecoEngine>::default()
+        }
+    }
+}
+
+/// Convert a pleco::BitMove into standard algebraic notation (SAN). Checkmates are marked
+/// with `+` rather than `#`, to match the game's solutions.
 fn bitmove_to_san(mut board: Board, bit_move: BitMove) -> String {
-    let dest_square = bit_move.get_dest().to_string();
-    let piece = board
-        .piece_at_sq(bit_move.get_src())
-        .to_string()
-        .to_ascii_uppercase();
-    let capture = if bit_move.is_capture() { "x" } else { "" };
+    let mut san = if bit_move.is_king_castle() {
+        "O-O".to_owned()
+    } else if bit_move.is_queen_castle() {
+        "O-O-O".to_owned()
+    } else {
+        let src = bit_move.get_src().to_string();
+        let dest = bit_move.get_dest().to_string();
+        let capture = if bit_move.is_capture() { "x" } else { "" };
+        let piece = board.piece_at_sq(bit_move.get_src()).type_of();
+        if piece == PieceType::P {
+            // Pawn captures are identified by the file they're from
+            let file = if bit_move.is_capture() { &src[..1] } else { "" };
+            let promotion = if bit_move.is_promo() {
+                format!("={}", bit_move.promo_piece().char_upper())
+            } else {
+                String::new()
+            };
+            format!("{}{}{}{}", file, capture, dest, promotion)
+        } else {
+            format!(
+                "{}{}{}{}",
+                piece.char_upper(),
+                disambiguation(&board, bit_move),
+                capture,
+                dest
+            )
+        }
+    };
     board.apply_move(bit_move);
-    let check = if board.in_check() { "+" } else { "" };
-    format!(
-        "{}{}{}{}",
-        if piece == "P" { "" } else { &piece },
-        capture,
-        dest_square,
-        check
-    )
+    if board.in_check() {
+        san.push('+');
+    }
+    san
+}
+
+/// The source file and/or rank needed in a piece move's SAN to tell it apart from moves of
+/// other pieces of the same type to the same square.
+fn disambiguation(board: &Board, bit_move: BitMove) -> String {
+    let src = bit_move.get_src();
+    let piece = board.piece_at_sq(src);
+    let others = board
+        .generate_moves()
+        .iter()
+        .filter(|m| {
+            !m.is_castle()
+                && m.get_dest() == bit_move.get_dest()
+                && m.get_src() != src
+                && board.piece_at_sq(m.get_src()) == piece
+        })
+        .map(|m| m.get_src())
+        .collect::<Vec<_>>();
+
+    let src_string = src.to_string();
+    if others.is_empty() {
+        String::new()
+    } else if others.iter().all(|other| other.file() != src.file()) {
+        src_string[..1].to_owned()
+    } else if others.iter().all(|other| other.rank() != src.rank()) {
+        src_string[1..].to_owned()
+    } else {
+        src_string
+    }
 }
 
-/// Get the optimal move in algebraic notation for the given position.
+/// Get the optimal move in algebraic notation for the given position, falling back to pleco
+/// if the configured engine fails.
 #[cached]
 pub fn get_optimal_move(fen: String) -> String {
     let board = Board::from_fen(&fen).expect("failed to parse FEN");
-    let optimal_move = JamboreeSearcher::best_move(board.clone(), 4);
+    let optimal_move = ENGINE.best_move(&board).unwrap_or_else(|| {
+        warn!(target: CHESS, "Chess engine failed, falling back to pleco");
+        PlecoEngine::default()
+            .best_move(&board)
+            .expect("no legal moves")
+    });
     bitmove_to_san(board, optimal_move)
 }
 
-/// Locate the country of the given lat/long coordinate pair.
+/// Load the reverse geocoder's places now, rather than on the first lookup (it takes a while).
+pub fn warm_up_geocoder() {
+    lazy_static::initialize(&GEOCODER);
+}
+
+/// Locate the country of the given lat/long coordinate pair, named as the game names it.
 #[cached]
 pub fn get_country_from_coordinates(lat: NotNan<f64>, long: NotNan<f64>) -> String {
-    let locations = Locations::from_memory();
-    let geocoder = ReverseGeocoder::new(&locations);
-    let search_result = geocoder
+    let search_result = GEOCODER
         .search((lat.into_inner(), long.into_inner()))
         .expect("failed to search coordinates");
     let country_code = &search_result.record.cc;
     let country = CountryCode::for_alpha2(country_code).expect("failed to match country code");
     let country_name = country.name().to_ascii_lowercase();
-    match country_name.as_str() {
-        "russian federation" => "russia".into(),
-        "venezuela (bolivarian republic of)" => "venezuela".into(),
-        "iran (islamic republic of)" => "iran".into(),
-        "holy see" => "italy".into(),
-        _ => country_name,
+    match COUNTRY_ALIASES.get(country_name.as_str()) {
+        Some(alias) => alias.to_string(),
+        None => country_name,
     }
 }
 
 /// Get the duration of the given YouTube video in seconds.
-#[cached]
-pub fn get_youtube_duration(id: String) -> u32 {
+#[cfg(feature = "native")]
+#[cached(result = true)]
+pub fn get_youtube_duration(id: String) -> Result<u32, HttpError> {
+    match STORAGE
+        .lock()
+        .unwrap()
+        .cache_get(YOUTUBE_DURATION_CACHE, &id)
+    {
+        Ok(Some(duration)) => return Ok(duration),
+        Ok(None) => {}
+        Err(e) => warn!(target: YOUTUBE, "Failed to read duration cache: {}", e),
+    }
+
+    debug!(target: YOUTUBE, "Fetching duration of video {}", id);
     let url = format!("https://www.youtube.com/watch?v={}", id);
-    let body = reqwest::blocking::get(&url).unwrap().text().unwrap();
+    let body = http::get_text(&url)?;
     let document = Html::parse_document(&body);
     let selector = Selector::parse("meta").unwrap();
     for element in document.select(&selector) {
         if let Some(itemprop) = element.value().attr("itemprop") {
             if itemprop == "duration" {
-                let duration_str = element.value().attr("content").unwrap();
-                let duration = duration_str
+                let duration_str = element.value().attr("content").unwrap_or_default();
+                let Some(duration) = duration_str
                     .parse::<Duration>()
-                    .unwrap()
-                    .num_seconds()
-                    .unwrap() as u32;
-                return duration;
+                    .ok()
+                    .and_then(|d| d.num_seconds())
+                else {
+                    return Err(HttpError::UnexpectedResponse {
+                        url,
+                        reason: format!("malformed duration {:?}", duration_str),
+                    });
+                };
+                let duration = duration as u32;
+                if let Err(e) =
+                    STORAGE
+                        .lock()
+                        .unwrap()
+                        .cache_put(YOUTUBE_DURATION_CACHE, &id, duration)
+                {
+                    warn!(target: YOUTUBE, "Failed to write duration cache: {}", e);
+                }
+                return Ok(duration);
             }
         }
     }
-    panic!("failed to get youtube video duration");
+    Err(HttpError::UnexpectedResponse {
+        url,
+        reason: "no duration".into(),
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{get_optimal_move, get_youtube_duration};
+    use chrono::NaiveDate;
+    use pleco::Board;
+    use std::{
+        io,
+        path::PathBuf,
+        time::{Duration, Instant},
+    };
+
+    use super::{
+        bitmove_to_san, get_optimal_move, get_wordle_answer, get_youtube_duration,
+        offline_wordle_answer, parse_wordle_answer, parse_wordle_answers, wordle_answer_from,
+        Engine, EngineKind, UciEngine, DEFAULT_PLECO_DEPTH, WORDLE_ANSWER_CACHE,
+    };
+    use crate::storage::STORAGE;
+
+    /// SAN for the given move, in UCI notation, in the given position.
+    fn san(fen: &str, uci_move: &str) -> String {
+        let board = Board::from_fen(fen).unwrap();
+        let bit_move = board
+            .generate_moves()
+            .iter()
+            .find(|m| m.stringify() == uci_move)
+            .copied()
+            .expect("move is legal");
+        bitmove_to_san(board, bit_move)
+    }
 
     #[test]
     fn chess_puzzles() {
@@ -153,9 +611,150 @@ mod tests {
         assert_eq!(get_optimal_move(fen.to_owned()), "Ne7");
     }
 
+    #[test]
+    fn san_disambiguation() {
+        assert_eq!(san("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1", "b1d2"), "Nbd2");
+        assert_eq!(san("7k/8/8/8/8/4R3/8/K3R3 w - - 0 1", "e1e2"), "R1e2");
+        assert_eq!(san("8/7k/8/8/8/Q7/8/Q1Q4K w - - 0 1", "a1b2"), "Qa1b2");
+        assert_eq!(san("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5"), "exd5");
+    }
+
+    #[test]
+    fn san_promotion_and_castling() {
+        assert_eq!(san("7k/P7/8/8/8/8/8/K7 w - - 0 1", "a7a8q"), "a8=Q+");
+        assert_eq!(san("7k/P7/8/8/8/8/8/K7 w - - 0 1", "a7a8n"), "a8=N");
+        assert_eq!(san("1r5k/P7/8/8/8/8/8/K7 w - - 0 1", "a7b8q"), "axb8=Q+");
+
+        let fen = "4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1";
+        assert_eq!(san(fen, "e1g1"), "O-O");
+        assert_eq!(san(fen, "e1c1"), "O-O-O");
+    }
+
+    #[test]
+    fn engine_spec() {
+        assert_eq!(
+            "pleco".parse::<EngineKind>().unwrap(),
+            EngineKind::Pleco(DEFAULT_PLECO_DEPTH)
+        );
+        assert_eq!(
+            "pleco:6".parse::<EngineKind>().unwrap(),
+            EngineKind::Pleco(6)
+        );
+        assert_eq!(
+            "uci:/usr/bin/stockfish".parse::<EngineKind>().unwrap(),
+            EngineKind::Uci(PathBuf::from("/usr/bin/stockfish"))
+        );
+        for spec in ["", "pleco:0", "pleco:deep", "uci:", "stockfish"] {
+            assert!(spec.parse::<EngineKind>().is_err());
+        }
+    }
+
+    /// Write a shell script standing in for a UCI engine.
+    #[cfg(unix)]
+    fn fake_engine(name: &str, script: &str) -> UciEngine {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("uci-{}-{}.sh", name, std::process::id()));
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        UciEngine { path }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn uci_handshake() {
+        // Only answers once it's been through the handshake
+        let engine = fake_engine(
+            "handshake",
+            r#"#!/bin/sh
+ready=no
+while read line; do
+  case "$line" in
+    uci) echo 'id name fake'; echo uciok;;
+    isready) ready=yes; echo readyok;;
+    go*) [ $ready = yes ] && echo 'info depth 1' && echo 'bestmove e2e4 ponder e7e5';;
+    quit) exit 0;;
+  esac
+done
+"#,
+        );
+        let fen = Board::start_pos().fen();
+        assert_eq!(engine.search(&fen).unwrap(), Some("e2e4".to_owned()));
+        assert_eq!(
+            engine.best_move(&Board::start_pos()).map(|m| m.stringify()),
+            Some("e2e4".to_owned())
+        );
+        std::fs::remove_file(&engine.path).ok();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn uci_timeout() {
+        let engine = fake_engine("hung", "#!/bin/sh\nexec sleep 60\n");
+        let fen = Board::start_pos().fen();
+        let start = Instant::now();
+        let error = engine
+            .search_within(&fen, Duration::from_millis(200))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(10));
+        std::fs::remove_file(&engine.path).ok();
+    }
+
+    #[test]
+    fn wordle_answer_cached() {
+        STORAGE
+            .lock()
+            .unwrap()
+            .cache_put(WORDLE_ANSWER_CACHE, "2099-01-01", "crane")
+            .unwrap();
+        let date = NaiveDate::from_ymd_opt(2099, 1, 1).unwrap();
+        assert_eq!(get_wordle_answer(date).unwrap(), "crane");
+    }
+
+    #[test]
+    fn wordle_answer_fallback() {
+        let url = "https://neal.fun/api/password-game/wordle";
+        assert_eq!(
+            parse_wordle_answer(url, r#"{"answer":"Crane"}"#).unwrap(),
+            "crane"
+        );
+        for body in ["", "{}", r#"{"answer":""}"#, r#"{"answer":"<html>"}"#] {
+            assert!(parse_wordle_answer(url, body).is_err());
+        }
+
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(offline_wordle_answer(date(2021, 6, 19)), Some("cigar"));
+        assert_eq!(offline_wordle_answer(date(2022, 1, 1)), Some("rebus"));
+        assert_eq!(offline_wordle_answer(date(2022, 2, 1)), Some("those"));
+        assert_eq!(offline_wordle_answer(date(2021, 6, 18)), None);
+
+        // A longer list (e.g., the full one, from `BOT_WORDLE_ANSWERS`) carries on the indexing
+        // from the end of the bundled one to recent dates
+        let mut list = include_str!("data/wordle_answers.txt").to_owned();
+        let days = (date(2026, 10, 16) - date(2022, 2, 1)).num_days();
+        for day in 1..=days {
+            list.push_str(&format!("\nW{:04}\n", day));
+        }
+        let answers = parse_wordle_answers(&list);
+        assert_eq!(
+            wordle_answer_from(&answers, date(2022, 2, 1)),
+            Some("those")
+        );
+        assert_eq!(
+            wordle_answer_from(&answers, date(2022, 2, 2)),
+            Some("w0001")
+        );
+        assert_eq!(
+            wordle_answer_from(&answers, date(2026, 10, 16)),
+            Some(format!("w{:04}", days).as_str())
+        );
+        assert_eq!(wordle_answer_from(&answers, date(2026, 10, 17)), None);
+    }
+
     #[test]
     #[ignore]
     fn youtube_duration() {
-        assert_eq!(get_youtube_duration("Hc6J5rlKhIc".into()), 15);
+        assert_eq!(get_youtube_duration("Hc6J5rlKhIc".into()).unwrap(), 15);
     }
 }
@@ -0,0 +1,165 @@
+use chrono::NaiveDate;
+use std::{error::Error, fmt::Debug, sync::Arc};
+use thiserror::Error;
+
+use super::{
+    data::GEO_GAMES,
+    helpers::{get_country_from_coordinates, offline_wordle_answer},
+    rule::Coords,
+};
+use crate::youtube::database::videos;
+#[cfg(feature = "native")]
+use crate::{
+    game::helpers::{get_wordle_answer, get_youtube_duration},
+    http::HttpError,
+};
+
+/// Failure to look something up, whichever source it came from.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum LookupError {
+    /// The source has no answer (e.g., a video which isn't in the database).
+    #[error("no answer for {0}")]
+    NotFound(String),
+    /// The source couldn't be reached, or gave an answer that didn't make sense.
+    #[error(transparent)]
+    Source(Box<dyn Error + Send + Sync>),
+}
+
+#[cfg(feature = "native")]
+impl From<HttpError> for LookupError {
+    fn from(e: HttpError) -> Self {
+        LookupError::Source(Box::new(e))
+    }
+}
+
+/// A source of the outside information some rules need (the Wordle answer, YouTube video
+/// durations, and the country at some coordinates). Injected via the game state, so rules can
+/// be validated and solved without network access or the geocoder where needed.
+pub trait Lookup: Debug + Send + Sync {
+    /// The Wordle answer for the given date.
+    fn wordle_answer(&self, date: NaiveDate) -> Result<String, LookupError>;
+
+    /// The duration of the given YouTube video in seconds.
+    fn youtube_duration(&self, id: &str) -> Result<u32, LookupError>;
+
+    /// The name of the country at the given coordinates, as the game spells it.
+    fn country(&self, coords: &Coords) -> String;
+}
+
+/// Looks things up online (and with the offline reverse geocoder), caching the results.
+#[cfg(feature = "native")]
+#[derive(Debug, Default)]
+pub struct OnlineLookup;
+
+#[cfg(feature = "native")]
+impl Lookup for OnlineLookup {
+    fn wordle_answer(&self, date: NaiveDate) -> Result<String, LookupError> {
+        Ok(get_wordle_answer(date)?)
+    }
+
+    fn youtube_duration(&self, id: &str) -> Result<u32, LookupError> {
+        Ok(get_youtube_duration(id.to_owned())?)
+    }
+
+    fn country(&self, coords: &Coords) -> String {
+        get_country_from_coordinates(coords.lat, coords.long)
+    }
+}
+
+/// Looks things up without the network, for simulated games: Wordle answers from the answer
+/// list (failing for dates it doesn't cover), video durations from the video database,
+/// and countries from the locations the game uses (with the reverse geocoder for any others).
+#[derive(Debug, Default)]
+pub struct OfflineLookup;
+
+impl Lookup for OfflineLookup {
+    fn wordle_answer(&self, date: NaiveDate) -> Result<String, LookupError> {
+        offline_wordle_answer(date)
+            .map(str::to_owned)
+            .ok_or_else(|| LookupError::NotFound(format!("Wordle answer for {}", date)))
+    }
+
+    fn youtube_duration(&self, id: &str) -> Result<u32, LookupError> {
+        videos()
+            .iter()
+            .find_map(|(seconds, video_id)| (video_id == id).then_some(seconds))
+            .ok_or_else(|| LookupError::NotFound(format!("video {} in the database", id)))
+    }
+
+    fn country(&self, coords: &Coords) -> String {
+        GEO_GAMES
+            .iter()
+            .find(|game| game.coordindates == (*coords.lat, *coords.long))
+            .map(|game| game.country.clone())
+            .unwrap_or_else(|| get_country_from_coordinates(coords.lat, coords.long))
+    }
+}
+
+/// A lookup shared between the game state, solver and driver. Defaults to looking things up
+/// online, or offline without the `native` feature (e.g., for WebAssembly, where anything else
+/// can be injected with `SharedLookup::new`).
+#[derive(Debug, Clone)]
+pub struct SharedLookup(Arc<dyn Lookup>);
+
+impl SharedLookup {
+    pub fn new(lookup: Arc<dyn Lookup>) -> Self {
+        SharedLookup(lookup)
+    }
+
+    /// The Wordle answer for the given date.
+    pub fn wordle_answer(&self, date: NaiveDate) -> Result<String, LookupError> {
+        self.0.wordle_answer(date)
+    }
+
+    /// The duration of the given YouTube video in seconds.
+    pub fn youtube_duration(&self, id: &str) -> Result<u32, LookupError> {
+        self.0.youtube_duration(id)
+    }
+
+    /// The name of the country at the given coordinates.
+    pub fn country(&self, coords: &Coords) -> String {
+        self.0.country(coords)
+    }
+}
+
+#[cfg(feature = "native")]
+impl Default for SharedLookup {
+    fn default() -> Self {
+        SharedLookup(Arc::new(OnlineLookup))
+    }
+}
+
+#[cfg(not(feature = "native"))]
+impl Default for SharedLookup {
+    fn default() -> Self {
+        SharedLookup(Arc::new(OfflineLookup))
+    }
+}
+
+/// Fixed answers, for tests which shouldn't need the network.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MockLookup {
+    pub wordle_answer: String,
+    pub youtube_durations: std::collections::HashMap<String, u32>,
+    pub country: String,
+}
+
+#[cfg(test)]
+impl Lookup for MockLookup {
+    fn wordle_answer(&self, _date: NaiveDate) -> Result<String, LookupError> {
+        Ok(self.wordle_answer.clone())
+    }
+
+    fn youtube_duration(&self, id: &str) -> Result<u32, LookupError> {
+        self.youtube_durations
+            .get(id)
+            .copied()
+            .ok_or_else(|| LookupError::NotFound(format!("video {}", id)))
+    }
+
+    fn country(&self, _coords: &Coords) -> String {
+        self.country.clone()
+    }
+}
@@ -5,12 +5,15 @@ use strum::IntoEnumIterator;
 pub use rule::Rule;
 pub use state::GameState;
 
-use data::{CAPTCHAS, CHESS_PUZZLES, GEO_GAMES};
-use rule::{Color, Coords};
+use data::{Captcha, CAPTCHAS, CHESS_PUZZLES, GEO_GAMES};
+use rule::{Color, Coords, RuleInstance, RuleKind};
 
+pub mod clock;
 pub mod data;
 pub mod helpers;
+pub mod lookup;
 pub mod rule;
+pub mod ruleset;
 mod state;
 #[cfg(test)]
 mod tests;
@@ -28,40 +31,102 @@ impl Game {
     /// Start a new game. Instance-specific rules will be chosen randomly.
     pub fn new() -> Self {
         Game {
-            rules: Game::random_rules(),
+            rules: Game::random_rules(&mut thread_rng()),
             state: GameState::default(),
         }
     }
 
+    /// Start a new game with instance-specific rules chosen from the given seed, so the same
+    /// game can be played again.
+    pub fn new_with_seed(seed: u64) -> Self {
+        Game {
+            rules: Game::random_rules(&mut StdRng::seed_from_u64(seed)),
+            state: GameState::default(),
+        }
+    }
+
+    /// The answer to the captcha rule.
+    pub fn captcha(&self) -> Option<&str> {
+        self.rules.iter().find_map(|rule| match rule {
+            Rule::Captcha(answer) => Some(answer.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Refresh the captcha, as the refresh button next to it does: a new one is chosen at random
+    /// (which may be the same one again).
+    pub fn refresh_captcha<R: Rng>(&mut self, rng: &mut R) -> &'static Captcha {
+        let captcha = CAPTCHAS.choose(rng).unwrap();
+        for rule in &mut self.rules {
+            if let Rule::Captcha(answer) = rule {
+                *answer = captcha.answer.to_owned();
+            }
+        }
+        captcha
+    }
+
+    /// Refresh the hex color, as the refresh button next to it does: a new one is chosen at
+    /// random.
+    pub fn refresh_color<R: Rng>(&mut self, rng: &mut R) -> Color {
+        let color = Color {
+            r: rng.gen::<u8>(),
+            g: rng.gen::<u8>(),
+            b: rng.gen::<u8>(),
+        };
+        for rule in &mut self.rules {
+            if let Rule::Hex(current) = rule {
+                *current = color.clone();
+            }
+        }
+        color
+    }
+
+    /// The color the hex rule asks for.
+    pub fn color(&self) -> Option<&Color> {
+        self.rules.iter().find_map(|rule| match rule {
+            Rule::Hex(color) => Some(color),
+            _ => None,
+        })
+    }
+
+    /// Replace the instance data of the rule of the same kind (e.g., to play a particular chess
+    /// puzzle).
+    pub fn set_instance(&mut self, instance: RuleInstance) {
+        let kind = instance.kind();
+        if let Some(existing) = self.rules.iter_mut().find(|r| r.kind() == kind) {
+            *existing = instance.into();
+        }
+    }
+
     /// Get a full set of game rules, with any instance-specific rules chosen randomly.
-    fn random_rules() -> Vec<Rule> {
-        let mut rng = thread_rng();
+    fn random_rules<R: Rng>(rng: &mut R) -> Vec<Rule> {
         let mut rules = Vec::new();
-        for rule in Rule::iter() {
-            match rule {
-                Rule::Captcha(_) => rules.push(Rule::Captcha(
-                    CAPTCHAS.choose(&mut rng).unwrap().to_string(),
+        for kind in RuleKind::iter() {
+            let instance = match kind {
+                RuleKind::Captcha => Some(RuleInstance::Captcha(
+                    CAPTCHAS.choose(rng).unwrap().answer.to_owned(),
                 )),
-                Rule::Geo { .. } => {
-                    let game = GEO_GAMES.choose(&mut rng).unwrap().clone();
-                    rules.push(Rule::Geo(Coords {
+                RuleKind::Geo => {
+                    let game = GEO_GAMES.choose(rng).unwrap().clone();
+                    Some(RuleInstance::Geo(Coords {
                         lat: NotNan::new(game.coordindates.0).unwrap(),
                         long: NotNan::new(game.coordindates.1).unwrap(),
                     }))
                 }
-                Rule::Chess { .. } => rules.push(Rule::Chess(
-                    CHESS_PUZZLES.choose(&mut rng).unwrap().fen.clone(),
+                RuleKind::Chess => Some(RuleInstance::Chess(
+                    CHESS_PUZZLES.choose(rng).unwrap().fen.clone(),
                 )),
-                Rule::Hex(_) => rules.push(Rule::Hex(Color {
+                RuleKind::Hex => Some(RuleInstance::Hex(Color {
                     r: rng.gen::<u8>(),
                     g: rng.gen::<u8>(),
                     b: rng.gen::<u8>(),
                 })),
-                Rule::Youtube { .. } => rules.push(Rule::Youtube(
+                RuleKind::Youtube => Some(RuleInstance::Youtube(
                     (2000.0 * rng.gen::<f64>()).floor() as u32 + 180,
                 )),
-                _ => rules.push(rule),
-            }
+                _ => None,
+            };
+            rules.push(Rule::new(kind, instance));
         }
         rules
     }
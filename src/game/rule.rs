@@ -1,24 +1,27 @@
 use chrono::prelude::*;
 use lazy_regex::regex;
+use log::warn;
 use ordered_float::NotNan;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use strum::EnumIter;
+use strum::{EnumDiscriminants, EnumIter, IntoEnumIterator};
 use unicode_segmentation::UnicodeSegmentation;
 
 use super::{
-    helpers::{
-        get_country_from_coordinates, get_moon_phase, get_optimal_move, get_wordle_answer,
-        get_youtube_duration, is_prime,
-    },
+    helpers::{get_moon_phase, get_optimal_move, is_prime},
     GameState,
 };
-use crate::password::{
-    format::{FontFamily, FontSize},
-    helpers::{get_digits, get_elements, get_roman_numerals, get_youtube_id},
-    Password,
+use crate::{
+    logging::{LOOKUP, YOUTUBE},
+    password::{
+        format::{FontFamily, FontSize},
+        helpers::{count_strength, get_digits, get_elements, get_roman_numerals, get_youtube_id},
+        Password,
+    },
 };
 
+/// Number of strength emojis needed to satisfy rule 21.
+pub const STRENGTH_EMOJIS_REQUIRED: usize = 3;
 pub const SPONSORS: [&str; 3] = ["pepsi", "starbucks", "shell"];
 pub const MONTHS: [&str; 12] = [
     "january",
@@ -64,13 +67,13 @@ impl MoonPhase {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
 pub struct Coords {
     pub lat: NotNan<f64>,
     pub long: NotNan<f64>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -83,8 +86,19 @@ impl Color {
     }
 }
 
-#[derive(Debug, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, EnumIter)]
-#[serde(rename_all = "kebab-case")]
+/// A rule, including its instance-specific data (if any), which is also available on its own
+/// as a `RuleInstance`. Rules aren't ordered themselves; sort them by `kind()`, which is
+/// generated from this with the same variants and no instance data, and is (de)serialized as
+/// the rule's class on the page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, EnumDiscriminants, EnumIter)]
+#[strum_discriminants(
+    name(RuleKind),
+    derive(Serialize, Deserialize, Hash, PartialOrd, Ord, EnumIter),
+    serde(rename_all = "kebab-case"),
+    // Only attributes with arguments are passed through
+    cfg_attr(all(), non_exhaustive)
+)]
+#[non_exhaustive]
 pub enum Rule {
     /// Rule 1: Your password must be at least 5 characters.
     MinLength,
@@ -105,7 +119,7 @@ pub enum Rule {
     /// Rule 9: The roman numerals in your password should multiply to 35.
     RomanMultiply,
     /// Rule 10: Your password must include this CAPTCHA.
-    Captcha(#[serde(skip_deserializing)] String),
+    Captcha(String),
     /// Rule 11: Your password must include today's Wordle answer.
     Wordle,
     /// Rule 12: Your password must include a two letter symbol from the periodic table.
@@ -113,11 +127,11 @@ pub enum Rule {
     /// Rule 13: Your password must include the current phase of the moon as an emoji.
     MoonPhase,
     /// Rule 14: Your password must include the name of this country.
-    Geo(#[serde(skip_deserializing)] Coords),
+    Geo(Coords),
     /// Rule 15: Your password must include a leap year.
     LeapYear,
     /// Rule 16: Your password must include the best move in algebraic chess notation.
-    Chess(#[serde(skip_deserializing)] String),
+    Chess(String),
     /// Rule 17: 🥚 This my chicken Paul. He hasn’t hatched yet. Please put him in your password and keep him safe.
     Egg,
     /// Rule 18: The elements in your password must have atomic numbers that add up to 200.
@@ -133,16 +147,16 @@ pub enum Rule {
     /// Rule 23: Paul has hatched🐔! Please don’t forget to feed him. He eats three 🐛 every minute.
     Hatch,
     /// Rule 24: Your password must include the URL of a YouTube video of this exact length.
-    Youtube(#[serde(skip_deserializing)] u32),
+    Youtube(u32),
     /// Rule 25: A sacrifice must be made. Pick 2 letters that you will no longer be able to use.
-    #[serde(rename = "sacrafice")]
+    #[strum_discriminants(serde(rename = "sacrafice"))]
     Sacrifice,
     /// Rule 26: Your password must contain twice as many italic characters as bold.
     TwiceItalic,
     /// Rule 27: At least 30% of your password must be in the Wingdings font.
     Wingdings,
     /// Rule 28: Your password must include this color in hex.
-    Hex(#[serde(skip_deserializing)] Color),
+    Hex(Color),
     /// Rule 29: All roman numerals must be in Times New Roman.
     TimesNewRoman,
     /// Rule 30: The font size of every digit must be equal to its square.
@@ -159,51 +173,116 @@ pub enum Rule {
     Time,
     /// Rule 36: Is this your final password?
     Final,
+    /// A rule we don't know about, by its class name on the page.
+    #[strum(disabled)]
+    #[strum_discriminants(strum(disabled))]
+    Unknown(String),
 }
 
-impl Rule {
+impl RuleKind {
     /// The rule's number (starting at 1).
     pub fn number(&self) -> usize {
         match self {
-            Rule::MinLength => 1,
-            Rule::Number => 2,
-            Rule::Uppercase => 3,
-            Rule::Special => 4,
-            Rule::Digits => 5,
-            Rule::Month => 6,
-            Rule::Roman => 7,
-            Rule::Sponsors => 8,
-            Rule::RomanMultiply => 9,
-            Rule::Captcha(_) => 10,
-            Rule::Wordle => 11,
-            Rule::PeriodicTable => 12,
-            Rule::MoonPhase => 13,
-            Rule::Geo { .. } => 14,
-            Rule::LeapYear => 15,
-            Rule::Chess { .. } => 16,
-            Rule::Egg => 17,
-            Rule::AtomicNumber => 18,
-            Rule::BoldVowels => 19,
-            Rule::Fire => 20,
-            Rule::Strength => 21,
-            Rule::Affirmation => 22,
-            Rule::Hatch => 23,
-            Rule::Youtube { .. } => 24,
-            Rule::Sacrifice => 25,
-            Rule::TwiceItalic => 26,
-            Rule::Wingdings => 27,
-            Rule::Hex(_) => 28,
-            Rule::TimesNewRoman => 29,
-            Rule::DigitFontSize => 30,
-            Rule::LetterFontSize => 31,
-            Rule::IncludeLength => 32,
-            Rule::PrimeLength => 33,
-            Rule::Skip => 34,
-            Rule::Time => 35,
-            Rule::Final => 36,
+            // Unknown rules sort after all the ones we know
+            RuleKind::Unknown => usize::MAX,
+            // The rules are declared in order
+            kind => *kind as usize + 1,
+        }
+    }
+
+    /// Does this rule have instance-specific data (chosen randomly for each game)?
+    pub fn has_instance(&self) -> bool {
+        matches!(
+            self,
+            RuleKind::Captcha | RuleKind::Geo | RuleKind::Chess | RuleKind::Youtube | RuleKind::Hex
+        )
+    }
+}
+
+impl From<RuleKind> for Rule {
+    /// Convert a rule kind into a rule, with default instance data if it has any.
+    fn from(kind: RuleKind) -> Self {
+        Rule::iter()
+            .find(|rule| rule.kind() == kind)
+            .unwrap_or_else(|| Rule::Unknown(String::new()))
+    }
+}
+
+/// Instance-specific data for the rules which have it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RuleInstance {
+    /// The CAPTCHA answer.
+    Captcha(String),
+    /// Coordinates of the location to identify.
+    Geo(Coords),
+    /// The chess position (in FEN).
+    Chess(String),
+    /// The required video duration in seconds.
+    Youtube(u32),
+    /// The color to include.
+    Hex(Color),
+}
+
+impl RuleInstance {
+    /// The kind of rule this is an instance of.
+    pub fn kind(&self) -> RuleKind {
+        match self {
+            RuleInstance::Captcha(_) => RuleKind::Captcha,
+            RuleInstance::Geo(_) => RuleKind::Geo,
+            RuleInstance::Chess(_) => RuleKind::Chess,
+            RuleInstance::Youtube(_) => RuleKind::Youtube,
+            RuleInstance::Hex(_) => RuleKind::Hex,
+        }
+    }
+}
+
+impl From<RuleInstance> for Rule {
+    fn from(instance: RuleInstance) -> Self {
+        match instance {
+            RuleInstance::Captcha(captcha) => Rule::Captcha(captcha),
+            RuleInstance::Geo(coords) => Rule::Geo(coords),
+            RuleInstance::Chess(fen) => Rule::Chess(fen),
+            RuleInstance::Youtube(seconds) => Rule::Youtube(seconds),
+            RuleInstance::Hex(color) => Rule::Hex(color),
+        }
+    }
+}
+
+impl Rule {
+    /// The rule of the given kind, with the given instance data (or the default, if it has
+    /// instance data but none is given).
+    pub fn new(kind: RuleKind, instance: Option<RuleInstance>) -> Self {
+        match instance {
+            Some(instance) => {
+                debug_assert_eq!(instance.kind(), kind);
+                instance.into()
+            }
+            None => kind.into(),
+        }
+    }
+
+    /// The rule's identity, without any instance-specific data.
+    pub fn kind(&self) -> RuleKind {
+        self.into()
+    }
+
+    /// The rule's instance-specific data, if it has any.
+    pub fn instance(&self) -> Option<RuleInstance> {
+        match self {
+            Rule::Captcha(captcha) => Some(RuleInstance::Captcha(captcha.clone())),
+            Rule::Geo(coords) => Some(RuleInstance::Geo(coords.clone())),
+            Rule::Chess(fen) => Some(RuleInstance::Chess(fen.clone())),
+            Rule::Youtube(seconds) => Some(RuleInstance::Youtube(*seconds)),
+            Rule::Hex(color) => Some(RuleInstance::Hex(color.clone())),
+            _ => None,
         }
     }
 
+    /// The rule's number (starting at 1).
+    pub fn number(&self) -> usize {
+        self.kind().number()
+    }
+
     /// Does the given password satisfy this rule at the given time?
     pub fn validate_at_time(
         &self,
@@ -226,7 +305,7 @@ impl Rule {
                     .copied()
                     .reduce(|sum, d| sum + d)
                     .unwrap_or_default()
-                    == 25
+                    == game_state.ruleset.digit_sum
             }
             Rule::Month => {
                 let lowercase_password = password.as_str().to_lowercase();
@@ -247,11 +326,16 @@ impl Rule {
                     == 35
             }
             Rule::Captcha(captcha) => password.as_str().contains(captcha),
-            Rule::Wordle => {
-                let wordle_answer = &get_wordle_answer(datetime.date_naive());
-                let lowercase_password = password.as_str().to_lowercase();
-                lowercase_password.contains(wordle_answer)
-            }
+            Rule::Wordle => match game_state.lookup.wordle_answer(datetime.date_naive()) {
+                Ok(wordle_answer) => {
+                    let lowercase_password = password.as_str().to_lowercase();
+                    lowercase_password.contains(&wordle_answer)
+                }
+                Err(e) => {
+                    warn!(target: LOOKUP, "Can't check the Wordle rule: {}", e);
+                    false
+                }
+            },
             Rule::PeriodicTable => get_elements(password.as_str())
                 .iter()
                 .any(|(e, _)| e.symbol.len() == 2),
@@ -259,15 +343,16 @@ impl Rule {
                 let valid_emojis = get_moon_phase(*datetime).emojis();
                 let mut found = false;
                 for grapheme in password.as_str().graphemes(true) {
-                    if valid_emojis.iter().any(|e| *e == grapheme) {
+                    if valid_emojis.contains(&grapheme) {
                         found = true;
                     }
                 }
                 found
             }
             Rule::Geo(geo) => {
-                let country_name = get_country_from_coordinates(geo.lat, geo.long);
-                let lowercase_password = password.as_str().to_lowercase();
+                // Spaces are optional (e.g., "elsalvador" is fine)
+                let country_name = game_state.lookup.country(geo).replace(' ', "");
+                let lowercase_password = password.as_str().to_lowercase().replace(' ', "");
                 lowercase_password.contains(&country_name)
             }
             Rule::LeapYear => {
@@ -288,7 +373,7 @@ impl Rule {
                 password.as_str().contains(&solution)
             }
             Rule::Egg => {
-                if game_state.paul_hatched {
+                if game_state.paul.hatched {
                     password.as_str().graphemes(true).any(|g| g == "🐔")
                 } else if game_state.egg_placed {
                     password.as_str().graphemes(true).any(|g| g == "🥚")
@@ -302,25 +387,16 @@ impl Rule {
                     .map(|(e, _)| e.atomic_number)
                     .reduce(|sum, n| sum + n)
                     .unwrap_or_default()
-                    == 200
+                    == game_state.ruleset.atomic_number_sum
             }
             Rule::BoldVowels => password
-                .as_str()
-                .graphemes(true)
-                .enumerate()
-                .filter(|(_, g)| VOWELS.contains(g))
-                .all(|(i, _)| password.formatting()[i].bold),
+                .annotated()
+                .filter(|(_, g, _)| VOWELS.contains(g))
+                .all(|(_, _, format)| format.bold),
             Rule::Fire => {
                 game_state.fire_started && !password.as_str().graphemes(true).any(|g| g == "🔥")
             }
-            Rule::Strength => {
-                password
-                    .as_str()
-                    .graphemes(true)
-                    .filter(|g| *g == "🏋️‍♂️")
-                    .count()
-                    >= 3
-            }
+            Rule::Strength => count_strength(password.as_str()) >= STRENGTH_EMOJIS_REQUIRED,
             Rule::Affirmation => {
                 let lowercase_password = password.as_str().to_lowercase();
                 AFFIRMATIONS.iter().any(|m| {
@@ -329,7 +405,7 @@ impl Rule {
                 })
             }
             Rule::Hatch => {
-                if !game_state.paul_hatched {
+                if !game_state.paul.hatched {
                     true
                 } else {
                     game_state.paul_eating || password.as_str().graphemes(true).any(|g| g == "🐛")
@@ -337,8 +413,13 @@ impl Rule {
             }
             Rule::Youtube(seconds) => {
                 if let Some(video_id) = get_youtube_id(password.as_str()) {
-                    let duration = get_youtube_duration(video_id);
-                    duration <= *seconds + 1 && duration >= *seconds - 1
+                    match game_state.lookup.youtube_duration(&video_id) {
+                        Ok(duration) => duration.abs_diff(*seconds) <= 1,
+                        Err(e) => {
+                            warn!(target: YOUTUBE, "Can't check the YouTube rule: {}", e);
+                            false
+                        }
+                    }
                 } else {
                     false
                 }
@@ -370,7 +451,8 @@ impl Rule {
                     .iter()
                     .filter(|f| f.font_family == FontFamily::Wingdings)
                     .count();
-                wingdings_count as f32 / password.len() as f32 >= 0.3
+                wingdings_count as f32 / game_state.page_length(password) as f32
+                    >= game_state.ruleset.wingdings_fraction
             }
             Rule::Hex(Color { r, g, b }) => {
                 let hex = format!("{:02x}{:02x}{:02x}", r, g, b);
@@ -395,7 +477,7 @@ impl Rule {
             Rule::LetterFontSize => {
                 let mut letter_font_sizes: HashMap<char, HashSet<FontSize>> = HashMap::new();
                 let mut valid = true;
-                for (i, grapheme) in password.as_str().graphemes(true).enumerate() {
+                for (_, grapheme, format) in password.annotated() {
                     if grapheme.len() != 1 {
                         continue;
                     }
@@ -403,7 +485,7 @@ impl Rule {
                     if !ch.is_ascii_alphabetic() {
                         continue;
                     }
-                    let font_size = &password.formatting()[i].font_size;
+                    let font_size = &format.font_size;
                     let font_sizes = letter_font_sizes.entry(ch).or_default();
                     let is_new = font_sizes.insert(font_size.clone());
                     if !is_new {
@@ -427,11 +509,13 @@ impl Rule {
                 password.as_str().contains(&time_string)
             }
             Rule::Final => true,
+            // We can't check it, so only the page knows
+            Rule::Unknown(_) => true,
         }
     }
 
-    /// Does the given password satisfy this rule at the current time?
+    /// Does the given password satisfy this rule at the game's current time?
     pub fn validate(&self, password: &Password, game_state: &GameState) -> bool {
-        self.validate_at_time(password, game_state, &Local::now())
+        self.validate_at_time(password, game_state, &game_state.clock.now())
     }
 }
@@ -0,0 +1,122 @@
+//! The numbers in the game's rules, which neal.fun sometimes tweaks. They're grouped into
+//! versioned rule sets, so the bot can play more than one version of the game.
+
+use serde::Deserialize;
+
+/// Version of the rule set of the game as first released.
+pub const ORIGINAL_VERSION: &str = "2023-06";
+
+/// The numbers in the rules for one version of the game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleSet {
+    /// Identifies the version of the game these rules are from.
+    pub version: String,
+    /// What the digits in the password must add up to (rule 5).
+    pub digit_sum: u32,
+    /// What the atomic numbers of the elements in the password must add up to (rule 18).
+    pub atomic_number_sum: u32,
+    /// Fraction of the password which must be in Wingdings (rule 27).
+    pub wingdings_fraction: f32,
+    /// Most bugs Paul can have in the password without being overfed (rule 23).
+    pub max_bugs: usize,
+}
+
+impl RuleSet {
+    /// The rule set of the game as first released.
+    pub fn original() -> Self {
+        RuleSet {
+            version: ORIGINAL_VERSION.to_owned(),
+            digit_sum: 25,
+            atomic_number_sum: 200,
+            wingdings_fraction: 0.3,
+            max_bugs: 8,
+        }
+    }
+
+    /// All the built-in rule sets, oldest first.
+    pub fn builtin() -> Vec<Self> {
+        vec![RuleSet::original()]
+    }
+
+    /// The built-in rule set with the given version, if there is one.
+    pub fn find(version: &str) -> Option<Self> {
+        RuleSet::builtin()
+            .into_iter()
+            .find(|ruleset| ruleset.version == version)
+    }
+
+    /// This rule set with some of its numbers changed. The version is marked as customised if
+    /// anything was overridden.
+    pub fn with_overrides(mut self, overrides: &RuleOverrides) -> Self {
+        if *overrides == RuleOverrides::default() {
+            return self;
+        }
+        if let Some(digit_sum) = overrides.digit_sum {
+            self.digit_sum = digit_sum;
+        }
+        if let Some(atomic_number_sum) = overrides.atomic_number_sum {
+            self.atomic_number_sum = atomic_number_sum;
+        }
+        if let Some(wingdings_fraction) = overrides.wingdings_fraction {
+            self.wingdings_fraction = wingdings_fraction;
+        }
+        if let Some(max_bugs) = overrides.max_bugs {
+            self.max_bugs = max_bugs;
+        }
+        self.version.push_str("+custom");
+        self
+    }
+}
+
+impl Default for RuleSet {
+    /// The latest built-in rule set.
+    fn default() -> Self {
+        RuleSet::builtin().pop().unwrap()
+    }
+}
+
+/// Changes to some of the numbers in a rule set, e.g., to keep up with a tweak to the game
+/// before there's a built-in rule set for it.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleOverrides {
+    pub digit_sum: Option<u32>,
+    pub atomic_number_sum: Option<u32>,
+    pub wingdings_fraction: Option<f32>,
+    pub max_bugs: Option<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RuleOverrides, RuleSet, ORIGINAL_VERSION};
+    use crate::{
+        game::{GameState, Rule},
+        password::Password,
+    };
+
+    #[test]
+    fn select_ruleset() {
+        assert_eq!(RuleSet::default().version, ORIGINAL_VERSION);
+        assert_eq!(RuleSet::find(ORIGINAL_VERSION), Some(RuleSet::original()));
+        assert_eq!(RuleSet::find("1999-01"), None);
+
+        assert_eq!(
+            RuleSet::original().with_overrides(&RuleOverrides::default()),
+            RuleSet::original()
+        );
+        let ruleset = RuleSet::original().with_overrides(&RuleOverrides {
+            digit_sum: Some(30),
+            ..Default::default()
+        });
+        assert_eq!(ruleset.version, "2023-06+custom");
+        assert_eq!(ruleset.digit_sum, 30);
+        assert_eq!(ruleset.atomic_number_sum, 200);
+
+        // Rules are validated against the game's rule set
+        let password = Password::from_str("9993");
+        let mut game_state = GameState::default();
+        assert!(!Rule::Digits.validate(&password, &game_state));
+        game_state.ruleset = ruleset;
+        assert!(Rule::Digits.validate(&password, &game_state));
+    }
+}
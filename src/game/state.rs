@@ -1,5 +1,13 @@
-/// Game state.
-#[derive(Debug, Default)]
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{clock::SharedClock, lookup::SharedLookup, ruleset::RuleSet};
+use crate::password::Password;
+
+/// Game state. Saved along with the solver in snapshots of a run, except for the parts set up
+/// from the driver config.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct GameState {
     /// The highest numbered rule currently being checked.
     pub highest_rule: usize,
@@ -7,10 +15,89 @@ pub struct GameState {
     pub fire_started: bool,
     /// Paul's egg has been placed into the password.
     pub egg_placed: bool,
-    /// Paul has hatched.
-    pub paul_hatched: bool,
+    /// Paul, and the bugs kept in the password to feed him.
+    pub paul: Paul,
     /// Paul is currently eating.
     pub paul_eating: bool,
     /// The letters the player has chosen to sacrifice.
     pub sacrificed_letters: Vec<char>,
+    /// Source of the current time for time-sensitive rules.
+    #[serde(skip)]
+    pub clock: SharedClock,
+    /// Source of the outside information some rules need.
+    #[serde(skip)]
+    pub lookup: SharedLookup,
+    /// The numbers in the rules, for the version of the game being played.
+    #[serde(skip)]
+    pub ruleset: RuleSet,
+}
+
+impl GameState {
+    /// Length of the given password as it is in the game, including any of Paul's bugs which
+    /// aren't part of it (the web driver keeps them outside its copy of the password).
+    pub fn page_length(&self, password: &Password) -> usize {
+        let bugs = password
+            .as_str()
+            .graphemes(true)
+            .filter(|g| *g == "🐛")
+            .count();
+        password.len() + self.paul.bug_count.saturating_sub(bugs)
+    }
+}
+
+/// Paul's state once he's hatched, kept up to date by the driver as it feeds him.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Paul {
+    /// Paul has hatched.
+    pub hatched: bool,
+    /// Time when Paul was last fed, if he has been.
+    pub last_fed: Option<DateTime<Local>>,
+    /// Number of bugs in the password, as of when they were last counted.
+    pub bug_count: usize,
+    /// Total number of bugs fed to Paul.
+    pub fed_total: usize,
+}
+
+impl Paul {
+    /// Record that the given number of bugs have just been added to the password.
+    pub fn fed(&mut self, bugs: usize, now: DateTime<Local>) {
+        self.last_fed = Some(now);
+        self.bug_count += bugs;
+        self.fed_total += bugs;
+    }
+
+    /// Update the bug count from the number of bugs in the password, counting any increase as
+    /// a feed.
+    pub fn count_bugs(&mut self, bugs: usize, now: DateTime<Local>) {
+        if bugs > self.bug_count {
+            self.fed(bugs - self.bug_count, now);
+        }
+        self.bug_count = bugs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Local};
+
+    use super::Paul;
+
+    #[test]
+    fn count_bugs() {
+        let mut paul = Paul::default();
+        let now = Local::now();
+        paul.fed(6, now);
+        assert_eq!(paul.last_fed, Some(now));
+
+        // Paul's eaten some, which isn't a feed
+        paul.count_bugs(4, now + Duration::seconds(30));
+        assert_eq!((paul.bug_count, paul.fed_total), (4, 6));
+        assert_eq!(paul.last_fed, Some(now));
+
+        // Bugs have been added
+        let later = now + Duration::seconds(60);
+        paul.count_bugs(7, later);
+        assert_eq!((paul.bug_count, paul.fed_total), (7, 9));
+        assert_eq!(paul.last_fed, Some(later));
+    }
 }
@@ -1 +1,29 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use super::{data::CAPTCHAS, Game};
+
 mod rules;
+
+#[test]
+fn seeded_game() {
+    // Rules are compared by kind, so compare their instance data too
+    let rules = |seed| format!("{:?}", Game::new_with_seed(seed).rules);
+    assert_eq!(rules(42), rules(42));
+    assert_ne!(rules(42), rules(43));
+}
+
+#[test]
+fn refresh_captcha() {
+    let mut game = Game::new_with_seed(42);
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut answers = std::collections::HashSet::new();
+    for _ in 0..10 {
+        let captcha = game.refresh_captcha(&mut rng);
+        assert_eq!(game.captcha(), Some(captcha.answer));
+        answers.insert(captcha.answer);
+    }
+    assert!(answers.len() > 1);
+    assert!(answers
+        .iter()
+        .all(|answer| CAPTCHAS.iter().any(|c| c.answer == *answer)));
+}
@@ -1,15 +1,85 @@
 use chrono::prelude::*;
 use ordered_float::NotNan;
+use std::sync::Arc;
+use strum::IntoEnumIterator;
 
 use super::super::{
-    rule::{Color, Coords},
+    lookup::{MockLookup, SharedLookup},
+    rule::{Color, Coords, RuleInstance, RuleKind},
     GameState, Rule,
 };
 use crate::password::{
     format::{FontFamily, FontSize},
+    helpers::append_strength,
     FormatChange, Password,
 };
 
+#[test]
+fn rule_kinds() {
+    for (i, kind) in RuleKind::iter().enumerate() {
+        assert_eq!(kind.number(), i + 1);
+        assert_eq!(Rule::from(kind).kind(), kind);
+    }
+    assert_eq!(RuleKind::iter().filter(|k| k.has_instance()).count(), 5);
+
+    assert_eq!(
+        serde_plain::from_str::<RuleKind>("sacrafice").unwrap(),
+        RuleKind::Sacrifice
+    );
+    assert_eq!(
+        serde_plain::from_str::<RuleKind>("min-length").unwrap(),
+        RuleKind::MinLength
+    );
+
+    // Rules added to the game since are unknown, and come after all the others
+    assert!(serde_plain::from_str::<RuleKind>("new-rule").is_err());
+    let rule = Rule::Unknown("new-rule".to_owned());
+    assert_eq!(rule.kind(), RuleKind::Unknown);
+    assert!(rule.kind() > RuleKind::Final);
+    assert!(rule.validate(&Password::from_str(""), &GameState::default()));
+
+    let rule = Rule::Youtube(183);
+    assert_eq!(rule.kind(), RuleKind::Youtube);
+    assert_eq!(rule.number(), 24);
+    assert!(matches!(Rule::from(RuleKind::Youtube), Rule::Youtube(0)));
+
+    // Rules are compared by their instance data too, but sorted by kind alone
+    assert_ne!(Rule::Captcha("aaaaa".into()), Rule::Captcha("zzzzz".into()));
+    assert_ne!(
+        Rule::Unknown("rule-a".into()),
+        Rule::Unknown("rule-b".into())
+    );
+    let mut rules = [
+        Rule::Wordle,
+        Rule::Captcha("zzzzz".into()),
+        Rule::MinLength,
+        Rule::Captcha("aaaaa".into()),
+    ];
+    rules.sort_by_key(Rule::kind);
+    assert_eq!(
+        rules,
+        [
+            Rule::MinLength,
+            Rule::Captcha("zzzzz".into()),
+            Rule::Captcha("aaaaa".into()),
+            Rule::Wordle,
+        ]
+    );
+
+    // Instance data converts to and from rules
+    let instance = RuleInstance::Hex(Color { r: 1, g: 2, b: 3 });
+    assert_eq!(instance.kind(), RuleKind::Hex);
+    let rule = Rule::from(instance.clone());
+    assert_eq!(rule, Rule::Hex(Color { r: 1, g: 2, b: 3 }));
+    assert_eq!(rule.instance(), Some(instance.clone()));
+    assert_eq!(Rule::new(RuleKind::Hex, Some(instance)), rule);
+    assert_eq!(Rule::new(RuleKind::Hex, None), Rule::Hex(Color::default()));
+    assert_eq!(Rule::Wordle.instance(), None);
+    for kind in RuleKind::iter() {
+        assert_eq!(Rule::from(kind).instance().is_some(), kind.has_instance());
+    }
+}
+
 #[test]
 fn rule_min_length() {
     let game_state = GameState::default();
@@ -217,11 +287,22 @@ fn rule_fire() {
 fn rule_strength() {
     let game_state = GameState::default();
 
-    assert!(Rule::Strength.validate(&Password::from_str("🏋️‍♂️🏋️‍♂️🏋️‍♂️"), &game_state));
-    assert!(Rule::Strength.validate(&Password::from_str("foo🏋️‍♂️🏋️‍♂️🏋️‍♂️🏋️‍♂️🏋️‍♂️"), &game_state));
+    assert!(Rule::Strength.validate(&Password::from_str(&append_strength(3)), &game_state));
+    assert!(Rule::Strength.validate(
+        &Password::from_str(&format!("foo{}", append_strength(5))),
+        &game_state
+    ));
+    // Without variation selectors
+    assert!(Rule::Strength.validate(
+        &Password::from_str(&"\u{1f3cb}\u{200d}\u{2642}".repeat(3)),
+        &game_state
+    ));
 
     assert!(!Rule::Strength.validate(&Password::from_str("hello"), &game_state));
-    assert!(!Rule::Strength.validate(&Password::from_str("🏋️‍♂️🏋️‍♂️bar"), &game_state));
+    assert!(!Rule::Strength.validate(
+        &Password::from_str(&format!("{}bar", append_strength(2))),
+        &game_state
+    ));
 }
 
 #[test]
@@ -257,18 +338,12 @@ fn rule_time() {
 
     let datetime_now = chrono::Local::now();
     assert!(Rule::Time.validate(
-        &Password::from_str(&datetime_now.format("%l:%M").to_string().trim().to_owned()),
+        &Password::from_str(datetime_now.format("%l:%M").to_string().trim()),
         &game_state
     ));
     let datetime_future = chrono::Local::now() + chrono::Duration::seconds(1_000_000);
     assert!(!Rule::Time.validate(
-        &Password::from_str(
-            &datetime_future
-                .format("%l:%M")
-                .to_string()
-                .trim()
-                .to_owned()
-        ),
+        &Password::from_str(datetime_future.format("%l:%M").to_string().trim()),
         &game_state
     ));
 
@@ -288,7 +363,7 @@ fn rule_egg() {
     assert!(Rule::Egg.validate(&Password::from_str("egg: 🥚"), &game_state));
     assert!(!Rule::Egg.validate(&Password::from_str("no egg"), &game_state));
 
-    game_state.paul_hatched = true;
+    game_state.paul.hatched = true;
     assert!(Rule::Egg.validate(&Password::from_str("paul: 🐔"), &game_state));
     assert!(!Rule::Egg.validate(&Password::from_str("no paul"), &game_state));
     // Paul has been slain
@@ -328,6 +403,31 @@ fn rule_wordle() {
     assert!(!Rule::Wordle.validate_at_time(&Password::from_str("hello"), &game_state, &datetime));
 }
 
+#[test]
+fn rules_with_injected_lookup() {
+    let game_state = GameState {
+        lookup: SharedLookup::new(Arc::new(MockLookup {
+            wordle_answer: "crane".into(),
+            youtube_durations: [("dQw4w9WgXcQ".to_owned(), 212)].into(),
+            country: "narnia".into(),
+        })),
+        ..Default::default()
+    };
+
+    assert!(Rule::Wordle.validate(&Password::from_str("abcRANEcrane"), &game_state));
+    assert!(!Rule::Wordle.validate(&Password::from_str("enter"), &game_state));
+
+    assert!(Rule::Youtube(212).validate(&Password::from_str("youtu.be/dQw4w9WgXcQ"), &game_state));
+    assert!(!Rule::Youtube(300).validate(&Password::from_str("youtu.be/dQw4w9WgXcQ"), &game_state));
+    assert!(!Rule::Youtube(0).validate(&Password::from_str("youtu.be/dQw4w9WgXcQ"), &game_state));
+
+    let geo = Rule::Geo(Coords {
+        lat: NotNan::new(0.0).unwrap(),
+        long: NotNan::new(0.0).unwrap(),
+    });
+    assert!(geo.validate(&Password::from_str("NARNIA"), &game_state));
+}
+
 #[test]
 fn rule_hatch() {
     let mut game_state = GameState::default();
@@ -337,7 +437,7 @@ fn rule_hatch() {
     assert!(Rule::Hatch.validate(&Password::from_str("nobugs"), &game_state));
 
     // Paul is hatched and hungry
-    game_state.paul_hatched = true;
+    game_state.paul.hatched = true;
     assert!(Rule::Hatch.validate(&Password::from_str("🐛"), &game_state));
     assert!(Rule::Hatch.validate(&Password::from_str("bugs🐛🐛🐛"), &game_state));
 
@@ -476,6 +576,14 @@ fn rule_geo() {
     assert!(rule.validate(&Password::from_str("ausTraLiA"), &game_state));
 
     assert!(!rule.validate(&Password::from_str("austria"), &game_state));
+
+    // Spaces in the country's name are optional
+    let rule = Rule::Geo(Coords {
+        lat: NotNan::new(13.3166881).unwrap(),
+        long: NotNan::new(-87.76447499999999).unwrap(),
+    });
+    assert!(rule.validate(&Password::from_str("elsalvador"), &game_state));
+    assert!(rule.validate(&Password::from_str("El Salvador"), &game_state));
 }
 
 #[test]
@@ -546,7 +654,7 @@ fn rule_twice_italic() {
 
 #[test]
 fn rule_wingdings() {
-    let game_state = GameState::default();
+    let mut game_state = GameState::default();
     let mut password = Password::from_str("foobar");
 
     // 1/6 < 0.3
@@ -556,4 +664,16 @@ fn rule_wingdings() {
     // 2/6 >= 0.3
     password.format(3, &FormatChange::FontFamily(FontFamily::Wingdings));
     assert!(Rule::Wingdings.validate(&password, &game_state));
+
+    // Bugs kept outside the password count towards its length: 2/8 < 0.3
+    game_state.paul.bug_count = 2;
+    assert!(!Rule::Wingdings.validate(&password, &game_state));
+
+    // But not twice if they're in it: 2/7 < 0.3, 2/6 >= 0.3
+    let mut password = Password::from_str("fo🐛bar");
+    password.format(0, &FormatChange::FontFamily(FontFamily::Wingdings));
+    password.format(3, &FormatChange::FontFamily(FontFamily::Wingdings));
+    assert!(!Rule::Wingdings.validate(&password, &game_state));
+    game_state.paul.bug_count = 1;
+    assert!(Rule::Wingdings.validate(&password, &game_state));
 }
@@ -0,0 +1,108 @@
+//! Adding locations for the Geo rule to the game data, from the Google Maps embed URLs the game
+//! shows, with their countries found by reverse geocoding.
+
+use log::{info, warn};
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{
+    driver::web::extract_coords_from_embed_url,
+    game::{data::GEO_GAMES, helpers::get_country_from_coordinates, rule::Coords},
+    logging::GEO,
+};
+
+/// The coordinates of each location, one `lat,long` per line.
+const COORDINATES_PATH: &str = "src/game/data/coordinates.txt";
+/// The country of each location, on the same line as its coordinates.
+const COUNTRIES_PATH: &str = "src/game/data/countries.txt";
+
+/// Add the locations in the given Google Maps embed URLs to the game data, skipping any which
+/// can't be parsed or are already there. Returns the number added.
+pub fn ingest(urls: &[String]) -> io::Result<usize> {
+    ingest_into(urls, Path::new(COORDINATES_PATH), Path::new(COUNTRIES_PATH))
+}
+
+fn ingest_into(
+    urls: &[String],
+    coordinates_path: &Path,
+    countries_path: &Path,
+) -> io::Result<usize> {
+    let mut known = GEO_GAMES
+        .iter()
+        .map(|game| game.coordindates)
+        .collect::<Vec<_>>();
+    let mut coordinates = Vec::new();
+    let mut countries = Vec::new();
+    for url in urls {
+        let Some(Coords { lat, long }) = extract_coords_from_embed_url(url) else {
+            warn!(target: GEO, "No coordinates in {:?}, skipping it", url);
+            continue;
+        };
+        let country = get_country_from_coordinates(lat, long);
+        let (lat, long) = (lat.into_inner(), long.into_inner());
+        if known.contains(&(lat, long)) {
+            info!(target: GEO, "Already have {},{}", lat, long);
+            continue;
+        }
+        info!(target: GEO, "Adding {},{} in {}", lat, long, country);
+        known.push((lat, long));
+        coordinates.push(format!("{},{}", lat, long));
+        countries.push(country);
+    }
+
+    append_lines(coordinates_path, &coordinates)?;
+    append_lines(countries_path, &countries)?;
+    Ok(coordinates.len())
+}
+
+/// Append the given lines to a data file.
+fn append_lines(path: &Path, lines: &[String]) -> io::Result<()> {
+    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+    for line in lines {
+        writeln!(file, "{}", line)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ingest_into;
+    use crate::game::data::GEO_GAMES;
+
+    #[test]
+    fn ingest_urls() {
+        let dir =
+            std::env::temp_dir().join(format!("password-game-bot-geo-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let coordinates_path = dir.join("coordinates.txt");
+        let countries_path = dir.join("countries.txt");
+
+        let (lat, long) = GEO_GAMES[0].coordindates;
+        let urls = [
+            "https://www.google.com/maps/embed?pb=!4v1688019437536!6m8!1m7\
+            !1sCAoSLEFGMVFpcE1fVkFUa2Z0!2m2!1d48.8583701!2d2.2944813!3f0!4f0!5f0.78"
+                .to_owned(),
+            // Already known, or repeated
+            format!("https://www.google.com/maps/embed?pb=!6m8!1m7!1sabc!2m2!1d{lat}!2d{long}"),
+            "https://www.google.com/maps/embed?pb=!6m8!1m7!1sabc!2m2!1d48.8583701!2d2.2944813"
+                .to_owned(),
+            "not a url".to_owned(),
+        ];
+        assert_eq!(
+            ingest_into(&urls, &coordinates_path, &countries_path).unwrap(),
+            1
+        );
+        assert_eq!(
+            std::fs::read_to_string(&coordinates_path).unwrap(),
+            "48.8583701,2.2944813\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&countries_path).unwrap(),
+            "france\n"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
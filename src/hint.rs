@@ -0,0 +1,284 @@
+//! Suggesting edits to a password pasted by a human playing the game themselves, using the
+//! solver without a browser. Passwords are read and written in markup (see
+//! `password::markup`), so their formatting can be given and shown.
+
+use ordered_float::NotNan;
+use std::fmt;
+use strum::IntoEnumIterator;
+use thiserror::Error;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    cli::HintArgs,
+    driver::DriverConfig,
+    game::{
+        rule::{Color, Coords, RuleInstance, RuleKind},
+        GameState, Rule,
+    },
+    password::{
+        diff::{diff, Edit},
+        ChangeError, MarkupError, Password,
+    },
+    solver::Solver,
+};
+
+/// Failure modes for hints.
+#[derive(Debug, Error)]
+pub enum HintError {
+    #[error("there's no rule {0}")]
+    UnknownRule(usize),
+    #[error("rule {0} needs --{1}")]
+    MissingData(usize, &'static str),
+    #[error("invalid --{0}: {1:?}")]
+    InvalidData(&'static str, String),
+    #[error("invalid markup: {0}")]
+    Markup(#[from] MarkupError),
+    #[error("the rules can't all be satisfied from this password")]
+    Unsolvable,
+    #[error("invalid change: {0}")]
+    InvalidChange(#[from] ChangeError),
+}
+
+/// The suggested edits to a password, and the password they lead to.
+#[derive(Debug)]
+pub struct Hint {
+    /// Descriptions of the edits, from the end of the password backwards.
+    pub edits: Vec<String>,
+    /// The password after the edits, in markup.
+    pub password: String,
+    /// Numbers of the rules still violated after the edits.
+    pub still_violated: Vec<usize>,
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.edits.is_empty() {
+            writeln!(f, "No edits needed")?;
+        } else {
+            writeln!(
+                f,
+                "Working back from the end (positions count from 1 in the password as it is now):"
+            )?;
+            for (i, edit) in self.edits.iter().enumerate() {
+                writeln!(f, "{:>3}. {}", i + 1, edit)?;
+            }
+        }
+        writeln!(f, "Password: {}", self.password)?;
+        if !self.still_violated.is_empty() {
+            writeln!(f, "Still violated: {:?}", self.still_violated)?;
+        }
+        Ok(())
+    }
+}
+
+/// Suggest edits to the given password (in markup) to satisfy the rules it violates, as listed
+/// in the args along with the data those rules need.
+pub fn run(config: &DriverConfig, markup: &str, args: &HintArgs) -> Result<Hint, HintError> {
+    let password = Password::from_markup(markup)?;
+    let rules = args
+        .violated
+        .iter()
+        .map(|number| rule(*number, args))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let graphemes = password.as_str().graphemes(true).collect::<Vec<_>>();
+    let mut game_state = GameState {
+        highest_rule: rules.iter().map(Rule::number).max().unwrap_or_default(),
+        egg_placed: graphemes.iter().any(|g| *g == "🥚" || *g == "🐔"),
+        clock: config.clock.clone(),
+        lookup: config.lookup.clone(),
+        ruleset: config.ruleset.clone(),
+        ..Default::default()
+    };
+    game_state.paul.hatched = graphemes.contains(&"🐔");
+    game_state.paul.bug_count = graphemes.iter().filter(|g| **g == "🐛").count();
+
+    let mut solver = Solver::default().with_strategy(config.strategy.into());
+    solver.adopt_password(password.as_str(), password.formatting().to_vec());
+    let plans = solver
+        .solve_all(&rules, &game_state)
+        .ok_or(HintError::Unsolvable)?;
+    for plan in &plans {
+        solver.carry_out(plan)?;
+    }
+
+    let solved = solver.password.raw_password();
+    Ok(Hint {
+        edits: describe_edits(&password, &diff(&password, solved)),
+        password: solved.to_markup(),
+        still_violated: rules
+            .iter()
+            .filter(|rule| !rule.validate(solved, &game_state))
+            .map(Rule::number)
+            .collect(),
+    })
+}
+
+/// The rule with the given number, with its instance data from the args.
+fn rule(number: usize, args: &HintArgs) -> Result<Rule, HintError> {
+    let kind = RuleKind::iter()
+        .find(|kind| kind.number() == number)
+        .ok_or(HintError::UnknownRule(number))?;
+    let missing = |arg| HintError::MissingData(number, arg);
+    let instance = match kind {
+        RuleKind::Captcha => RuleInstance::Captcha(args.captcha.clone().ok_or(missing("captcha"))?),
+        RuleKind::Geo => {
+            let geo = args.geo.as_deref().ok_or(missing("geo"))?;
+            RuleInstance::Geo(parse_coords(geo).ok_or(HintError::InvalidData("geo", geo.into()))?)
+        }
+        RuleKind::Chess => RuleInstance::Chess(args.chess.clone().ok_or(missing("chess"))?),
+        RuleKind::Youtube => RuleInstance::Youtube(args.youtube.ok_or(missing("youtube"))?),
+        RuleKind::Hex => {
+            let hex = args.hex.as_deref().ok_or(missing("hex"))?;
+            RuleInstance::Hex(parse_color(hex).ok_or(HintError::InvalidData("hex", hex.into()))?)
+        }
+        kind => return Ok(kind.into()),
+    };
+    Ok(instance.into())
+}
+
+/// Coordinates written as `lat,long`.
+fn parse_coords(coords: &str) -> Option<Coords> {
+    let (lat, long) = coords.split_once(',')?;
+    Some(Coords {
+        lat: NotNan::new(lat.trim().parse().ok()?).ok()?,
+        long: NotNan::new(long.trim().parse().ok()?).ok()?,
+    })
+}
+
+/// A color written as six hex digits, with or without a leading `#`.
+fn parse_color(hex: &str) -> Option<Color> {
+    let hex = hex.trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(Color {
+        r: channel(0)?,
+        g: channel(2)?,
+        b: channel(4)?,
+    })
+}
+
+/// Describe the given edits to the password (as from `diff`), grouping runs of them which can
+/// be made together, from the end of the password backwards so the positions stay right.
+pub fn describe_edits(password: &Password, edits: &[Edit]) -> Vec<String> {
+    let graphemes = password.as_str().graphemes(true).collect::<Vec<_>>();
+    // Replacing a character with itself only changes its formatting
+    let reformats = |edit: &Edit| match edit {
+        Edit::Replace {
+            index, grapheme, ..
+        } => graphemes[*index] == grapheme,
+        _ => false,
+    };
+
+    let mut runs: Vec<Vec<&Edit>> = Vec::new();
+    for edit in edits {
+        let continues = runs
+            .last()
+            .is_some_and(|run| match (run.last().unwrap(), edit) {
+                (previous @ Edit::Replace { index: a, .. }, Edit::Replace { index: b, .. }) => {
+                    *a + 1 == *b && reformats(previous) == reformats(edit)
+                }
+                (Edit::Remove { index: a }, Edit::Remove { index: b }) => *a + 1 == *b,
+                (Edit::Insert { index: a, .. }, Edit::Insert { index: b, .. }) => a == b,
+                _ => false,
+            });
+        if continues {
+            runs.last_mut().unwrap().push(edit);
+        } else {
+            runs.push(vec![edit]);
+        }
+    }
+
+    let positions = |run: &[&Edit]| {
+        let (first, last) = (run[0].index() + 1, run[run.len() - 1].index() + 1);
+        let old = graphemes[first - 1..last].concat();
+        if first == last {
+            format!("character {} ({:?})", first, old)
+        } else {
+            format!("characters {}-{} ({:?})", first, last, old)
+        }
+    };
+    let new_markup = |run: &[&Edit]| {
+        let (string, formatting): (String, Vec<_>) = run
+            .iter()
+            .filter_map(|edit| match edit {
+                Edit::Insert {
+                    grapheme, format, ..
+                }
+                | Edit::Replace {
+                    grapheme, format, ..
+                } => Some((grapheme.as_str(), format.clone())),
+                Edit::Remove { .. } => None,
+            })
+            .unzip();
+        Password::with_formatting(&string, formatting).to_markup()
+    };
+
+    runs.iter()
+        .rev()
+        .map(|run| match run[0] {
+            Edit::Insert { index, .. } if *index == graphemes.len() => {
+                format!("Add {:?} at the end", new_markup(run))
+            }
+            Edit::Insert { index, .. } => format!(
+                "Insert {:?} before character {} ({:?})",
+                new_markup(run),
+                index + 1,
+                graphemes[*index]
+            ),
+            Edit::Remove { .. } => format!("Delete {}", positions(run)),
+            edit @ Edit::Replace { .. } => {
+                if reformats(edit) {
+                    format!("Reformat {} as {:?}", positions(run), new_markup(run))
+                } else {
+                    format!("Replace {} with {:?}", positions(run), new_markup(run))
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{describe_edits, parse_color, parse_coords};
+    use crate::password::{diff::diff, Password};
+
+    #[test]
+    fn describe() {
+        let from = Password::from_markup("abcdef").unwrap();
+        let to = Password::from_markup("xabc**d**12").unwrap();
+        assert_eq!(
+            describe_edits(&from, &diff(&from, &to)),
+            vec![
+                "Replace characters 5-6 (\"ef\") with \"12\"",
+                "Reformat character 4 (\"d\") as \"**d**\"",
+                "Insert \"x\" before character 1 (\"a\")",
+            ]
+        );
+
+        let to = Password::from_markup("abcdef!!").unwrap();
+        assert_eq!(
+            describe_edits(&from, &diff(&from, &to)),
+            vec!["Add \"!!\" at the end"]
+        );
+        let to = Password::from_markup("af").unwrap();
+        assert_eq!(
+            describe_edits(&from, &diff(&from, &to)),
+            vec!["Delete characters 2-5 (\"bcde\")"]
+        );
+    }
+
+    #[test]
+    fn rule_data() {
+        let coords = parse_coords("13.5, -87.25").unwrap();
+        assert_eq!((*coords.lat, *coords.long), (13.5, -87.25));
+        assert!(parse_coords("13.5").is_none());
+
+        let color = parse_color("#0aff10").unwrap();
+        assert_eq!((color.r, color.g, color.b), (10, 255, 16));
+        assert!(parse_color("fff").is_none());
+        assert!(parse_color("zzzzzz").is_none());
+    }
+}
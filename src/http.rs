@@ -0,0 +1,200 @@
+//! Outbound HTTP requests (to neal.fun, YouTube and the notification webhook), with retries
+//! and timeouts so that a hiccup doesn't end the run.
+
+use lazy_static::lazy_static;
+use log::warn;
+use reqwest::{
+    blocking::{Client, RequestBuilder, Response},
+    StatusCode,
+};
+use std::{sync::RwLock, time::Duration};
+use thiserror::Error;
+
+use crate::logging::LOOKUP;
+
+lazy_static! {
+    static ref CLIENT: Client = Client::new();
+    static ref CONFIG: RwLock<HttpConfig> = RwLock::new(HttpConfig::default());
+}
+
+/// Retry and timeout settings for outbound requests.
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    /// Number of times to retry a request after a transient failure.
+    pub retries: u32,
+    /// Wait before the first retry, doubling for each one after.
+    pub initial_backoff: Duration,
+    /// Longest to wait between retries.
+    pub max_backoff: Duration,
+    /// Timeout for each attempt.
+    pub timeout: Duration,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(5),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl HttpConfig {
+    /// How long to wait before the given retry (starting from 0).
+    fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+}
+
+/// Failure modes for outbound requests.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum HttpError {
+    #[error("request to {url} failed after {attempts} attempts")]
+    Request {
+        url: String,
+        attempts: u32,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("request to {url} returned {status}")]
+    Status { url: String, status: StatusCode },
+    #[error("unexpected response from {url}: {reason}")]
+    UnexpectedResponse { url: String, reason: String },
+}
+
+/// Use the given settings for all later requests.
+pub fn configure(config: HttpConfig) {
+    *CONFIG.write().unwrap() = config;
+}
+
+/// Fetch the body of the given URL as text, retrying on connection failures, timeouts and
+/// server errors.
+pub fn get_text(url: &str) -> Result<String, HttpError> {
+    let config = CONFIG.read().unwrap().clone();
+    get_text_with(url, &config)
+}
+
+fn get_text_with(url: &str, config: &HttpConfig) -> Result<String, HttpError> {
+    send_with(url, config, || CLIENT.get(url), |response| response.text())
+}
+
+/// Post the given JSON to the given URL, retrying like `get_text`.
+pub fn post_json(url: &str, body: &serde_json::Value) -> Result<(), HttpError> {
+    let config = CONFIG.read().unwrap().clone();
+    send_with(url, &config, || CLIENT.post(url).json(body), |_| Ok(()))
+}
+
+/// Post a multipart form to the given URL, retrying like `get_text`. The form is built afresh
+/// for each attempt, as sending it uses it up.
+pub fn post_form(
+    url: &str,
+    form: impl Fn() -> reqwest::blocking::multipart::Form,
+) -> Result<(), HttpError> {
+    let config = CONFIG.read().unwrap().clone();
+    send_with(
+        url,
+        &config,
+        || CLIENT.post(url).multipart(form()),
+        |_| Ok(()),
+    )
+}
+
+/// Send the request made by `request`, reading a successful response with `read`, and
+/// retrying on connection failures, timeouts and server errors.
+fn send_with<T>(
+    url: &str,
+    config: &HttpConfig,
+    request: impl Fn() -> RequestBuilder,
+    read: impl Fn(Response) -> reqwest::Result<T>,
+) -> Result<T, HttpError> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let result = request()
+            .timeout(config.timeout)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(&read);
+        let error = match result {
+            Ok(body) => return Ok(body),
+            Err(e) => e,
+        };
+
+        if let Some(status) = error.status() {
+            if !is_transient(status) {
+                return Err(HttpError::Status {
+                    url: url.to_owned(),
+                    status,
+                });
+            }
+        }
+        if attempt > config.retries {
+            return Err(match error.status() {
+                Some(status) => HttpError::Status {
+                    url: url.to_owned(),
+                    status,
+                },
+                None => HttpError::Request {
+                    url: url.to_owned(),
+                    attempts: attempt,
+                    source: error,
+                },
+            });
+        }
+
+        let backoff = config.backoff(attempt - 1);
+        warn!(
+            target: LOOKUP,
+            "Request to {} failed ({}), retrying in {:.1}s",
+            url,
+            error,
+            backoff.as_secs_f32()
+        );
+        std::thread::sleep(backoff);
+    }
+}
+
+/// Is a response with the given status worth retrying?
+fn is_transient(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use std::time::Duration;
+
+    use super::{get_text_with, is_transient, HttpConfig, HttpError};
+
+    #[test]
+    fn backoff() {
+        let config = HttpConfig::default();
+        assert_eq!(config.backoff(0), Duration::from_millis(500));
+        assert_eq!(config.backoff(2), Duration::from_secs(2));
+        assert_eq!(config.backoff(10), Duration::from_secs(5));
+
+        assert!(is_transient(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_transient(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_transient(StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn connection_refused() {
+        let config = HttpConfig {
+            retries: 2,
+            initial_backoff: Duration::ZERO,
+            ..Default::default()
+        };
+        // Nothing listens on the discard port
+        let result = get_text_with("http://127.0.0.1:9/", &config);
+        assert!(matches!(
+            result,
+            Err(HttpError::Request { attempts: 3, .. })
+        ));
+    }
+}
@@ -0,0 +1,56 @@
+//! A bot to beat [The Password Game](https://neal.fun/password-game/).
+//!
+//! The public API is the `Bot` facade, the `Driver` trait and its drivers, the `Solver`, the
+//! password types and `Rule` (along with the rest of the `game` module), and follows the
+//! policy under "Versioning" in the README. The other modules are public for the `main` binary
+//! only, and may change at any time.
+//!
+//! Everything which needs the network or the OS is behind the default `native` feature, so the
+//! game and solver can be built for WebAssembly with `--no-default-features --features wasm`.
+
+#[cfg(feature = "native")]
+#[doc(hidden)]
+pub mod bench;
+#[cfg(feature = "native")]
+mod bot;
+#[cfg(feature = "native")]
+#[doc(hidden)]
+pub mod cli;
+#[cfg(feature = "native")]
+#[doc(hidden)]
+pub mod config;
+#[cfg(feature = "native")]
+pub mod driver;
+pub mod game;
+#[cfg(feature = "native")]
+#[doc(hidden)]
+pub mod geo;
+#[cfg(feature = "native")]
+#[doc(hidden)]
+pub mod hint;
+#[cfg(feature = "native")]
+mod http;
+#[doc(hidden)]
+pub mod logging;
+#[cfg(feature = "native")]
+mod notifier;
+#[cfg(feature = "native")]
+#[doc(hidden)]
+pub mod orchestrator;
+pub mod password;
+#[cfg(feature = "native")]
+mod recorder;
+#[cfg(feature = "native")]
+#[doc(hidden)]
+pub mod simulate;
+pub mod solver;
+#[cfg(feature = "native")]
+mod stats;
+mod storage;
+#[cfg(test)]
+mod testing;
+#[doc(hidden)]
+pub mod youtube;
+
+#[cfg(feature = "native")]
+pub use bot::Bot;
@@ -0,0 +1,59 @@
+//! Log targets for each subsystem, so verbosity can be set per subsystem with `RUST_LOG`.
+//!
+//! Targets are matched by prefix, so e.g.
+//! `RUST_LOG=info,driver.web=debug,driver.web.cursor=trace` gets trace-level cursor logs,
+//! debug-level logs from the rest of the web driver (including formatting), and info-level
+//! logs from everything else.
+//!
+//! Every log in the crate names one of these targets, including the command-line tools (the
+//! simulator, the benchmarks, the YouTube harvester and Geo location ingestion), so none of them
+//! fall back to logging under their module paths.
+
+/// The solver.
+pub const SOLVER: &str = "solver";
+/// The web driver, other than cursor movement and formatting.
+pub const DRIVER_WEB: &str = "driver.web";
+/// Cursor movement in the web driver (very noisy at trace level).
+pub const DRIVER_WEB_CURSOR: &str = "driver.web.cursor";
+/// Formatting changes and checks in the web driver.
+pub const DRIVER_WEB_FORMAT: &str = "driver.web.format";
+/// The async web driver, including its background tasks.
+pub const DRIVER_ASYNC: &str = "driver.async";
+/// The direct driver, which plays against our implementation of the rules.
+pub const DRIVER_DIRECT: &str = "driver.direct";
+/// Looking things up for rules in the background at the start of a game.
+pub const PREFETCH: &str = "driver.prefetch";
+/// Looking up outside information for rules (e.g., the Wordle answer), and retrying failed
+/// HTTP requests.
+pub const LOOKUP: &str = "lookup";
+/// YouTube video lookups.
+pub const YOUTUBE: &str = "youtube";
+/// The simulator and the benchmarks, which play many games with the direct driver.
+pub const SIMULATE: &str = "simulate";
+/// Adding locations for the Geo rule to the game data.
+pub const GEO: &str = "geo";
+/// Chess engines.
+pub const CHESS: &str = "chess";
+/// Deciding whether to play again after each run, and the history of runs.
+pub const ORCHESTRATOR: &str = "orchestrator";
+/// Posting the outcome of each playthrough to a webhook.
+pub const NOTIFIER: &str = "notifier";
+/// Data persisted across runs.
+pub const STORAGE: &str = "storage";
+
+/// Initialise logging, configured via the `RUST_LOG` environment variable. If that isn't set,
+/// `verbosity` picks the level: 0 logs errors only, each step up adds a level (up to 4 for
+/// trace), and -1 turns logging off.
+pub fn init(verbosity: i8) {
+    let level = match verbosity {
+        i8::MIN..=-1 => "off",
+        0 => "error",
+        1 => "warn",
+        2 => "info",
+        3 => "debug",
+        _ => "trace",
+    };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level))
+        .try_init()
+        .unwrap_or(());
+}
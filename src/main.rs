@@ -1,53 +1,242 @@
-use driver::Driver;
+use clap::Parser;
 use log::{error, info};
+use password_game_bot::{
+    bench,
+    cli::{
+        BenchCommand, Cli, Command, GeoCommand, HintArgs, PlayArgs, SimulateArgs, YoutubeCommand,
+    },
+    config::Config,
+    driver::{
+        direct::DirectDriver,
+        web::{control::Control, snapshot::Snapshot, tui::Tui, WebDriver},
+        Driver,
+    },
+    geo, hint, logging,
+    orchestrator::{self, Next, Orchestrator},
+    simulate, solver, youtube,
+};
 
-mod driver;
-mod game;
-mod password;
-mod solver;
+#[cfg(feature = "async")]
+use password_game_bot::driver::{async_web::AsyncWebDriver, AsyncDriver};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    env_logger::try_init().unwrap_or(());
+    let cli = Cli::parse();
+    let mut config = match &cli.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    if cli.strategy.is_some() {
+        config.strategy = cli.strategy;
+    }
+    let verbosity = cli.verbosity();
+    let command = cli
+        .command
+        .unwrap_or_else(|| Command::Play(PlayArgs::default()));
+    // Logs would draw over the terminal UI
+    let tui = matches!(command, Command::Play(_)) && config.tui == Some(true);
+    logging::init(if tui { -1 } else { verbosity });
+
+    match command {
+        Command::Play(args) => play(&config, args),
+        Command::Simulate(args) => simulate(&config, args),
+        Command::Youtube {
+            command: YoutubeCommand::Harvest { duration },
+        } => {
+            youtube::harvest(duration);
+            Ok(())
+        }
+        Command::Geo {
+            command: GeoCommand::Ingest { urls },
+        } => {
+            let added = geo::ingest(&urls)?;
+            info!(target: logging::GEO, "Added {} locations", added);
+            Ok(())
+        }
+        Command::Bench { command: None } => bench(&config),
+        Command::Bench {
+            command:
+                Some(BenchCommand::Solver {
+                    seed,
+                    games,
+                    samples,
+                }),
+        } => {
+            print!(
+                "{}",
+                bench::run(&config.driver_config(), seed, games, samples)
+            );
+            Ok(())
+        }
+        #[cfg(feature = "async")]
+        Command::Bench {
+            command: Some(BenchCommand::Drivers { seed, runs }),
+        } => {
+            let runtime = tokio::runtime::Runtime::new()?;
+            print!("{}", bench::compare_drivers(&config, &runtime, seed, runs)?);
+            Ok(())
+        }
+        Command::Hint(args) => hint(&config, args),
+    }
+}
 
+/// Play the real game over and over (picking up the snapshot's run first, if given), until
+/// the orchestrator says to stop, then show the history of runs.
+fn play(config: &Config, args: PlayArgs) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "async")]
+    if args.async_driver {
+        return play_async(config, args);
+    }
+    let mut seed = args.seed.unwrap_or_else(rand::random);
+    let mut snapshot = args.resume.as_deref().map(Snapshot::load).transpose()?;
+    let control = config.control_port.map(Control::listen).transpose()?;
+    let mut orchestrator = Orchestrator::start(orchestrator::Options {
+        max_attempts: args.max_attempts,
+        cooldown: std::time::Duration::from_secs(args.cooldown),
+        stop_on_success: args.stop_on_success,
+    })?;
+    let tui = match config.tui {
+        Some(true) => Some(Tui::start()?),
+        _ => None,
+    };
     loop {
-        let solver = solver::Solver::default();
-        let mut driver = driver::web::WebDriver::new(solver)?;
-        match driver.play() {
-            Ok(()) => {
-                // Success! Sleep to give the user time to enjoy it
-                std::thread::sleep(std::time::Duration::from_secs(1000));
+        if tui.is_none() {
+            println!("Playing with seed {}", seed);
+        }
+        let mut driver_config = config.driver_config();
+        driver_config.seed = Some(seed);
+        driver_config.control = control.clone();
+        driver_config.tui = tui.clone();
+        if driver_config.snapshot_path.is_none() {
+            driver_config.snapshot_path = args.resume.clone();
+        }
+        let solver = solver::Solver::with_seed(seed).with_strategy(driver_config.strategy.into());
+        let run_seed = seed;
+        seed = seed.wrapping_add(1);
+        let mut driver = match snapshot.take() {
+            Some(snapshot) => WebDriver::resume(snapshot, driver_config)?,
+            None => WebDriver::new(solver, driver_config)?,
+        };
+        config.apply(&mut driver);
+        let result = driver
+            .play()
+            .map(|()| driver.time_since_start().unwrap_or_default());
+        match orchestrator.finish_run(run_seed, result.as_ref().copied()) {
+            Next::PlayAgain(delay) => std::thread::sleep(delay),
+            Next::Won => break,
+            Next::OutOfAttempts => {
+                // The last run can only be resumed by hand now, so leave its browser open
+                if result.is_err() {
+                    driver.detach();
+                }
                 break;
             }
-            Err(e) => {
-                match e {
-                    driver::DriverError::CouldNotSatisfyRule(rule) => {
-                        // Try again
-                        info!("Failed to satisfy rule {:?}, playing again...", rule);
-                        continue;
-                    }
-                    driver::DriverError::GameOver => {
-                        // Try again
-                        info!("Game over, playing again...");
-                        continue;
-                    }
-                    driver::DriverError::LostSync => {
-                        // Try again
-                        info!(
-                            "Lost password sync for unknown reason, playing again in 30 seconds..."
-                        );
-                        std::thread::sleep(std::time::Duration::from_secs(30));
-                        continue;
-                    }
-                    e => {
-                        // Other error, give user time to debug
-                        error!("An error occurred: {:?}", e);
-                        std::thread::sleep(std::time::Duration::from_secs(1000));
-                        break;
-                    }
-                }
+            Next::Failed => {
+                driver.detach();
+                error!(target: logging::ORCHESTRATOR, "An error occurred: {:?}", result.unwrap_err());
+                break;
             }
         }
     }
 
+    drop(tui);
+    print!("{}", orchestrator.history());
+    Ok(())
+}
+
+/// Play the real game over and over with the async web driver, until the orchestrator says to
+/// stop, then show the history of runs.
+#[cfg(feature = "async")]
+fn play_async(config: &Config, args: PlayArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut seed = args.seed.unwrap_or_else(rand::random);
+    let mut orchestrator = Orchestrator::start(orchestrator::Options {
+        max_attempts: args.max_attempts,
+        cooldown: std::time::Duration::from_secs(args.cooldown),
+        stop_on_success: args.stop_on_success,
+    })?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    loop {
+        println!("Playing with seed {}", seed);
+        let mut driver_config = config.driver_config();
+        driver_config.seed = Some(seed);
+        let solver = solver::Solver::with_seed(seed).with_strategy(driver_config.strategy.into());
+        let run_seed = seed;
+        seed = seed.wrapping_add(1);
+        let result = runtime.block_on(async {
+            let mut driver = AsyncWebDriver::new(solver, driver_config).await?;
+            config.apply_async(&mut driver);
+            let result = driver.play().await;
+            let time = driver.time_since_start().unwrap_or_default();
+            driver.close().await?;
+            result.map(|()| time)
+        });
+        match orchestrator.finish_run(run_seed, result.as_ref().copied()) {
+            Next::PlayAgain(delay) => std::thread::sleep(delay),
+            Next::OutOfAttempts | Next::Won => break,
+            Next::Failed => {
+                error!(target: logging::ORCHESTRATOR, "An error occurred: {:?}", result.unwrap_err());
+                break;
+            }
+        }
+    }
+
+    print!("{}", orchestrator.history());
+    Ok(())
+}
+
+/// Play simulated games (or replay a recorded one) with the direct driver.
+fn simulate(config: &Config, args: SimulateArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = &args.replay {
+        let driver_config = config.driver_config();
+        let solver = solver::Solver::default().with_strategy(driver_config.strategy.into());
+        let mut driver = DirectDriver::new(solver, driver_config)?;
+        let divergences = driver.replay(path)?;
+        println!("Replay diverged from the recording {} times", divergences);
+        return Ok(());
+    }
+
+    let first_seed = args.seed.unwrap_or_else(rand::random);
+    if args.strict {
+        println!("Checking every combination with seed {}", first_seed);
+        let report = simulate::run_strict(
+            &config.driver_config(),
+            first_seed,
+            simulate::Combination::all(),
+        )?;
+        print!("{}", report);
+        if !report.unsolvable.is_empty() {
+            return Err(format!("{} combinations can't be solved", report.unsolvable.len()).into());
+        }
+        return Ok(());
+    }
+    println!("Simulating from seed {}", first_seed);
+    let summary = simulate::run(&config.driver_config(), first_seed, args.iterations)?;
+    print!("{}", summary);
+    Ok(())
+}
+
+/// Print the edits which fix the violated rules in a password (read from stdin if not given).
+fn hint(config: &Config, args: HintArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let password = match &args.password {
+        Some(password) => password.clone(),
+        None => {
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            line.trim_end_matches(['\r', '\n']).to_owned()
+        }
+    };
+    print!("{}", hint::run(&config.driver_config(), &password, &args)?);
+    Ok(())
+}
+
+/// Measure key injection latency against the real game, recording the tuned pacing.
+fn bench(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let mut driver = WebDriver::new(solver::Solver::default(), config.driver_config())?;
+    config.apply(&mut driver);
+    let (keystroke_latency, cursor_latency) = driver.tune_pacing()?;
+    println!(
+        "Keystroke latency {:.1}ms, cursor step latency {:.1}ms",
+        keystroke_latency.as_secs_f64() * 1000.0,
+        cursor_latency.as_secs_f64() * 1000.0
+    );
     Ok(())
 }
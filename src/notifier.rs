@@ -0,0 +1,223 @@
+//! Posting the outcome of each playthrough to a webhook (e.g., a Discord channel's), so long
+//! unattended runs report back. Follows the same events as the recorder, so it's attached to
+//! a playthrough's `Recorder`.
+
+use log::{info, warn};
+use reqwest::blocking::multipart::{Form, Part};
+use std::{
+    cell::RefCell,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    game::Rule,
+    http,
+    logging::NOTIFIER,
+    recorder::{Event, EventRecord},
+};
+
+/// Longest password (in bytes) to include in a notification, as webhooks limit the length of
+/// messages.
+const MAX_PASSWORD_LEN: usize = 1500;
+/// Longest to wait for the message to be posted when the notifier is dropped (i.e. before the
+/// next playthrough starts, or the bot exits).
+const POST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Posts a message to a webhook when the playthrough it's following ends, saying whether it
+/// was won and how long it took, along with the final password or where it went wrong.
+#[derive(Debug)]
+pub struct Notifier {
+    url: String,
+    /// The last time the rules were checked, as `(highest rule, password, violated rules)`.
+    last_rules: RefCell<Option<(usize, String, Vec<Rule>)>>,
+    /// PNG image of the password box to attach to the next message.
+    screenshot: RefCell<Option<Vec<u8>>>,
+    /// The message being posted in the background, if there is one.
+    posting: RefCell<Option<JoinHandle<()>>>,
+}
+
+impl Notifier {
+    /// Notify the webhook at the given URL (which takes Discord's message format).
+    pub fn new(url: &str) -> Self {
+        Notifier {
+            url: url.to_owned(),
+            last_rules: RefCell::new(None),
+            screenshot: RefCell::new(None),
+            posting: RefCell::new(None),
+        }
+    }
+
+    /// Attach an image of the password to the message sent when the playthrough ends.
+    pub fn attach_screenshot(&self, png: Vec<u8>) {
+        *self.screenshot.borrow_mut() = Some(png);
+    }
+
+    /// Follow an event of the playthrough, posting its outcome in the background if it's ended.
+    pub fn observe(&self, record: &EventRecord) {
+        match &record.event {
+            Event::Rules {
+                highest_rule,
+                password,
+                violated,
+            } => {
+                *self.last_rules.borrow_mut() =
+                    Some((*highest_rule, password.clone(), violated.clone()));
+            }
+            Event::End { error } => {
+                let content = message(
+                    Duration::from_millis(record.elapsed_ms),
+                    error.as_deref(),
+                    self.last_rules.borrow().as_ref(),
+                );
+                let url = self.url.clone();
+                let screenshot = self.screenshot.take();
+                *self.posting.borrow_mut() =
+                    Some(thread::spawn(move || post(&url, &content, screenshot)));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for Notifier {
+    /// Wait (up to `POST_TIMEOUT`) for the message to be posted, so it isn't lost when the bot
+    /// exits.
+    fn drop(&mut self) {
+        let Some(posting) = self.posting.take() else {
+            return;
+        };
+        let deadline = Instant::now() + POST_TIMEOUT;
+        while !posting.is_finished() {
+            if Instant::now() >= deadline {
+                warn!(
+                    target: NOTIFIER,
+                    "Gave up waiting for the outcome of the run to be posted to the webhook"
+                );
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        posting.join().unwrap_or(());
+    }
+}
+
+/// Post a message to the webhook, with the given PNG image if there is one.
+fn post(url: &str, content: &str, screenshot: Option<Vec<u8>>) {
+    let payload = serde_json::json!({ "content": content });
+    let result = match screenshot {
+        Some(png) => http::post_form(url, || {
+            Form::new().text("payload_json", payload.to_string()).part(
+                "files[0]",
+                Part::bytes(png.clone())
+                    .file_name("password.png")
+                    .mime_str("image/png")
+                    .unwrap(),
+            )
+        }),
+        None => http::post_json(url, &payload),
+    };
+    match result {
+        Ok(()) => info!(target: NOTIFIER, "Posted the outcome of the run to the webhook"),
+        Err(e) => warn!(
+            target: NOTIFIER,
+            "Failed to post the outcome of the run to the webhook: {}",
+            e
+        ),
+    }
+}
+
+/// The message for a playthrough which ended after the given time, with the given error if it
+/// failed, given the last time the rules were checked.
+fn message(
+    elapsed: Duration,
+    error: Option<&str>,
+    last_rules: Option<&(usize, String, Vec<Rule>)>,
+) -> String {
+    let secs = elapsed.as_secs();
+    let time = format!("{}:{:02}", secs / 60, secs % 60);
+    let mut message = match error {
+        None => format!("Beat the Password Game in {}", time),
+        Some(error) => format!("Run failed after {}: {}", time, error),
+    };
+    if let Some((highest_rule, password, violated)) = last_rules {
+        if error.is_some() {
+            message.push_str(&format!("\nReached rule {}", highest_rule));
+            if !violated.is_empty() {
+                let numbers = violated
+                    .iter()
+                    .map(|rule| rule.number().to_string())
+                    .collect::<Vec<_>>();
+                message.push_str(&format!(", violating {}", numbers.join(", ")));
+            }
+        }
+        let mut end = password.len().min(MAX_PASSWORD_LEN);
+        while !password.is_char_boundary(end) {
+            end -= 1;
+        }
+        let ellipsis = if end < password.len() { "…" } else { "" };
+        message.push_str(&format!(
+            "\nPassword: `{}{}`",
+            password[..end].replace('`', "'"),
+            ellipsis
+        ));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{message, Notifier, MAX_PASSWORD_LEN};
+    use crate::{
+        game::Rule,
+        recorder::{Event, EventRecord},
+    };
+
+    #[test]
+    fn messages() {
+        let rules = (36, "🥚abc`1".to_owned(), vec![]);
+        assert_eq!(
+            message(Duration::from_secs(192), None, Some(&rules)),
+            "Beat the Password Game in 3:12\nPassword: `🥚abc'1`"
+        );
+
+        let rules = (16, "abc".to_owned(), vec![Rule::Number, Rule::Fire]);
+        assert_eq!(
+            message(
+                Duration::from_millis(65_500),
+                Some("could not satisfy rule"),
+                Some(&rules)
+            ),
+            "Run failed after 1:05: could not satisfy rule\nReached rule 16, violating 2, 20\nPassword: `abc`"
+        );
+        assert_eq!(
+            message(Duration::ZERO, Some("game over"), None),
+            "Run failed after 0:00: game over"
+        );
+
+        // Long passwords are cut short, between characters
+        let rules = (5, "🐛".repeat(MAX_PASSWORD_LEN), vec![]);
+        let long = message(Duration::ZERO, None, Some(&rules));
+        assert!(long.ends_with("🐛…`"));
+        assert!(long.len() < MAX_PASSWORD_LEN + 50);
+    }
+
+    #[test]
+    fn follows_rules() {
+        let notifier = Notifier::new("http://127.0.0.1:9/");
+        notifier.observe(&EventRecord {
+            elapsed_ms: 10,
+            event: Event::Rules {
+                highest_rule: 3,
+                password: "abc".to_owned(),
+                violated: vec![Rule::Uppercase],
+            },
+        });
+        assert_eq!(
+            *notifier.last_rules.borrow(),
+            Some((3, "abc".to_owned(), vec![Rule::Uppercase]))
+        );
+    }
+}
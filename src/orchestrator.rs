@@ -0,0 +1,350 @@
+//! Playing the real game over and over: deciding whether to play again after each run (and
+//! how long to wait first), and keeping a history of every run in storage so what happened in
+//! earlier sessions isn't lost.
+
+use chrono::{DateTime, Local};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use crate::{
+    driver::DriverError,
+    logging::ORCHESTRATOR,
+    storage::{from_record, to_record, Artifact, StorageBackend, StorageError, STORAGE},
+};
+
+/// Stream the history of runs is kept in, as a single record replaced after every run. Kept in
+/// storage rather than a file of its own so it follows the storage backend (`BOT_STORAGE`).
+const HISTORY_STREAM: &str = "runs.history";
+
+/// How long to wait before playing again after the network or our password sync failed, to
+/// give them time to recover.
+const RECOVERY_DELAY: Duration = Duration::from_secs(30);
+
+/// Every run played, across sessions.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunHistory {
+    pub attempts: u64,
+    pub wins: u64,
+    /// How many runs failed for each cause (see `cause`).
+    pub failures: BTreeMap<String, u64>,
+    /// The fastest win.
+    pub best: Option<BestRun>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BestRun {
+    /// How long the game took, in seconds.
+    pub secs: f64,
+    pub seed: u64,
+    /// When the run ended.
+    pub finished: DateTime<Local>,
+}
+
+impl Artifact for RunHistory {
+    const KIND: &'static str = "run history";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl RunHistory {
+    /// Load the history from storage, starting a new one if there isn't one there.
+    pub fn load(storage: &dyn StorageBackend) -> Result<Self, StorageError> {
+        match storage.load(HISTORY_STREAM)?.pop() {
+            Some(record) => from_record(record),
+            None => Ok(RunHistory::default()),
+        }
+    }
+
+    /// Save the history to storage, replacing the previous one.
+    pub fn save(&self, storage: &mut dyn StorageBackend) -> Result<(), StorageError> {
+        storage.replace(HISTORY_STREAM, &[to_record(self)?])
+    }
+
+    /// Add a run with the given seed, which took the given time if it was won.
+    fn record(&mut self, seed: u64, outcome: Result<Duration, &DriverError>, now: DateTime<Local>) {
+        self.attempts += 1;
+        match outcome {
+            Ok(time) => {
+                self.wins += 1;
+                if self
+                    .best
+                    .as_ref()
+                    .is_none_or(|best| time.as_secs_f64() < best.secs)
+                {
+                    self.best = Some(BestRun {
+                        secs: time.as_secs_f64(),
+                        seed,
+                        finished: now,
+                    });
+                }
+            }
+            Err(error) => *self.failures.entry(cause(error)).or_default() += 1,
+        }
+    }
+}
+
+impl fmt::Display for RunHistory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Won {} of {} runs", self.wins, self.attempts)?;
+        if let Some(best) = &self.best {
+            writeln!(
+                f,
+                "Best time {:.2}s, with seed {} on {}",
+                best.secs,
+                best.seed,
+                best.finished.format("%Y-%m-%d %H:%M")
+            )?;
+        }
+        let mut failures = self.failures.iter().collect::<Vec<_>>();
+        failures.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (cause, count) in failures {
+            writeln!(f, "  {}: {}", cause, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Why a run failed, grouping errors which have the same cause.
+pub fn cause(error: &DriverError) -> String {
+    match error {
+        DriverError::CouldNotSatisfyRule(rule) => format!("Couldn't satisfy {:?}", rule.kind()),
+        DriverError::Timeout(rule) => format!("Timed out on {:?}", rule.kind()),
+        DriverError::GameTimeout(_) => "Game timed out".to_owned(),
+        DriverError::Network(_) => "Network error".to_owned(),
+        DriverError::Lookup(_) => "Lookup failed".to_owned(),
+        DriverError::UnknownRule(_) => "Unknown rule".to_owned(),
+        DriverError::KeyInjection(_) => "Key injection failed".to_owned(),
+        DriverError::MalformedElement(_) => "Malformed element".to_owned(),
+        DriverError::Resume(_) => "Couldn't resume".to_owned(),
+        error => {
+            let mut cause = error.to_string();
+            if let Some(first) = cause.get_mut(..1) {
+                first.make_ascii_uppercase();
+            }
+            cause
+        }
+    }
+}
+
+/// How long to wait before playing again after a run failed with the given error, or `None` if
+/// the error means something's wrong which playing again won't fix.
+fn retry_delay(error: &DriverError) -> Option<Duration> {
+    match error {
+        DriverError::CouldNotSatisfyRule(_)
+        | DriverError::Timeout(_)
+        | DriverError::GameTimeout(_)
+        | DriverError::GameOver
+        | DriverError::PageReloaded
+        | DriverError::StaleRun => Some(Duration::ZERO),
+        DriverError::Network(_) | DriverError::Lookup(_) | DriverError::LostSync => {
+            Some(RECOVERY_DELAY)
+        }
+        _ => None,
+    }
+}
+
+/// When to stop playing.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Most runs to play. No limit if not set.
+    pub max_attempts: Option<u64>,
+    /// Least time to wait between runs.
+    pub cooldown: Duration,
+    /// Stop once a run is won, rather than playing on for a better time.
+    pub stop_on_success: bool,
+}
+
+/// What to do after a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Next {
+    /// Play again after waiting this long.
+    PlayAgain(Duration),
+    /// The run was won, and that's enough.
+    Won,
+    /// The most runs allowed have been played.
+    OutOfAttempts,
+    /// The run failed in a way which playing again won't fix.
+    Failed,
+}
+
+/// Keeps track of the runs played, deciding after each one whether to play again.
+#[derive(Debug)]
+pub struct Orchestrator {
+    options: Options,
+    /// Runs played in this session.
+    attempts: u64,
+    /// Runs played in every session, including this one.
+    history: RunHistory,
+}
+
+impl Orchestrator {
+    /// Start a session, carrying on the history of runs from earlier ones.
+    pub fn start(options: Options) -> Result<Self, StorageError> {
+        let history = RunHistory::load(&*STORAGE.lock().unwrap())?;
+        Ok(Orchestrator {
+            options,
+            attempts: 0,
+            history,
+        })
+    }
+
+    pub fn history(&self) -> &RunHistory {
+        &self.history
+    }
+
+    /// Record how the run with the given seed went (how long the game took if it was won),
+    /// saving the history, and decide what to do next.
+    pub fn finish_run(&mut self, seed: u64, outcome: Result<Duration, &DriverError>) -> Next {
+        self.attempts += 1;
+        self.history.record(seed, outcome, Local::now());
+        if let Err(e) = self.history.save(&mut *STORAGE.lock().unwrap()) {
+            warn!(target: ORCHESTRATOR, "Failed to save run history: {}", e);
+        }
+
+        let delay = match outcome {
+            Ok(time) => {
+                info!(
+                    target: ORCHESTRATOR,
+                    "Won in {:.2}s (best {:.2}s)",
+                    time.as_secs_f32(),
+                    self.history.best.as_ref().map_or(0.0, |best| best.secs)
+                );
+                if self.options.stop_on_success {
+                    return Next::Won;
+                }
+                Duration::ZERO
+            }
+            Err(error) => {
+                let Some(delay) = retry_delay(error) else {
+                    return Next::Failed;
+                };
+                info!(target: ORCHESTRATOR, "Run failed ({})", cause(error));
+                delay
+            }
+        };
+        if self
+            .options
+            .max_attempts
+            .is_some_and(|max_attempts| self.attempts >= max_attempts)
+        {
+            return Next::OutOfAttempts;
+        }
+        let delay = delay.max(self.options.cooldown);
+        info!(
+            target: ORCHESTRATOR,
+            "Playing again in {:.0} seconds (attempt {}, {} overall)",
+            delay.as_secs_f32(),
+            self.attempts + 1,
+            self.history.attempts + 1
+        );
+        Next::PlayAgain(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Local;
+    use std::time::Duration;
+
+    use super::{cause, Next, Options, Orchestrator, RunHistory, HISTORY_STREAM};
+    use crate::{
+        driver::DriverError,
+        game::{lookup::LookupError, Rule},
+        storage::{MemoryStorage, StorageBackend, STORAGE},
+    };
+
+    fn default_options() -> Options {
+        Options {
+            max_attempts: None,
+            cooldown: Duration::ZERO,
+            stop_on_success: false,
+        }
+    }
+
+    #[test]
+    fn play_again() {
+        let options = Options {
+            max_attempts: Some(4),
+            cooldown: Duration::from_secs(5),
+            ..default_options()
+        };
+        let mut orchestrator = Orchestrator::start(options).unwrap();
+
+        let error = DriverError::CouldNotSatisfyRule(Rule::Chess(String::new()));
+        assert_eq!(
+            orchestrator.finish_run(1, Err(&error)),
+            Next::PlayAgain(Duration::from_secs(5))
+        );
+        assert_eq!(
+            orchestrator.finish_run(2, Err(&DriverError::LostSync)),
+            Next::PlayAgain(Duration::from_secs(30))
+        );
+        assert_eq!(
+            orchestrator.finish_run(3, Ok(Duration::from_secs(200))),
+            Next::PlayAgain(Duration::from_secs(5))
+        );
+        assert_eq!(
+            orchestrator.finish_run(4, Ok(Duration::from_secs(150))),
+            Next::OutOfAttempts
+        );
+
+        // The history carries on into the next session
+        let options = Options {
+            stop_on_success: true,
+            ..default_options()
+        };
+        let mut orchestrator = Orchestrator::start(options).unwrap();
+        assert_eq!(orchestrator.history().attempts, 4);
+        assert_eq!(
+            orchestrator.finish_run(5, Ok(Duration::from_secs(180))),
+            Next::Won
+        );
+        assert_eq!(
+            orchestrator.finish_run(6, Err(&DriverError::LaunchOptionsBuilderError)),
+            Next::Failed
+        );
+        let history = RunHistory::load(&*STORAGE.lock().unwrap()).unwrap();
+        assert_eq!((history.attempts, history.wins), (6, 3));
+        let best = history.best.unwrap();
+        assert_eq!((best.secs, best.seed), (150.0, 4));
+        assert_eq!(history.failures["Couldn't satisfy Chess"], 1);
+        assert_eq!(history.failures["Lost password sync"], 1);
+        assert_eq!(history.failures["Launch options builder failed"], 1);
+    }
+
+    #[test]
+    fn history() {
+        let mut storage = MemoryStorage::default();
+        assert_eq!(RunHistory::load(&storage).unwrap(), RunHistory::default());
+
+        let mut history = RunHistory::default();
+        history.record(7, Ok(Duration::from_millis(95_250)), Local::now());
+        history.record(8, Err(&DriverError::GameOver), Local::now());
+        history.record(9, Err(&DriverError::GameOver), Local::now());
+        history.save(&mut storage).unwrap();
+        history.save(&mut storage).unwrap();
+        assert_eq!(storage.load(HISTORY_STREAM).unwrap().len(), 1);
+        assert_eq!(RunHistory::load(&storage).unwrap(), history);
+        storage
+            .replace(HISTORY_STREAM, &[serde_json::json!("not a history")])
+            .unwrap();
+        assert!(RunHistory::load(&storage).is_err());
+
+        let shown = history.to_string();
+        assert!(shown.starts_with("Won 1 of 3 runs\nBest time 95.25s, with seed 7"));
+        assert!(shown.ends_with("  Game over: 2\n"));
+
+        assert_eq!(
+            cause(&DriverError::Timeout(Rule::Youtube(60))),
+            "Timed out on Youtube"
+        );
+        assert_eq!(
+            cause(&DriverError::GameTimeout(Duration::from_secs(600))),
+            "Game timed out"
+        );
+        assert_eq!(
+            cause(&DriverError::Lookup(LookupError::NotFound("video".into()))),
+            "Lookup failed"
+        );
+    }
+}
@@ -1,9 +1,11 @@
 use derivative::Derivative;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use super::format::{FontFamily, FontSize};
 
 /// A modification to formatting.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum FormatChange {
     BoldOn,
     ItalicOn,
@@ -12,7 +14,7 @@ pub enum FormatChange {
 }
 
 /// A modification to a password.
-#[derive(Debug, Clone, Derivative)]
+#[derive(Debug, Clone, Derivative, Serialize, Deserialize)]
 #[derivative(
     PartialEq,
     Eq,
@@ -46,7 +48,6 @@ pub enum Change {
         protected: bool,
     },
     /// Insert a string at the given index.
-    #[allow(dead_code)]
     Insert {
         /// The index where the string should be inserted.
         index: usize,
@@ -73,3 +74,17 @@ pub enum Change {
         ignore_protection: bool,
     },
 }
+
+/// Why a change can't be applied to a password.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ChangeError {
+    #[error("grapheme index {index} out of bounds for password of length {len}")]
+    IndexOutOfBounds { index: usize, len: usize },
+    #[error("grapheme at index {0} is protected")]
+    Protected(usize),
+    #[error("index {index} is inside the protected {label:?} block")]
+    InsideBlock { index: usize, label: String },
+    #[error("grapheme at index {0} is removed more than once")]
+    OverlappingRemoves(usize),
+}
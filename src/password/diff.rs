@@ -0,0 +1,174 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{Format, Password};
+
+/// A single step in turning one password into another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    /// Insert a grapheme with the given formatting before the grapheme at `index`.
+    Insert {
+        index: usize,
+        grapheme: String,
+        format: Format,
+    },
+    /// Remove the grapheme at `index`.
+    Remove { index: usize },
+    /// Replace the grapheme at `index` and its formatting (either of which may be unchanged).
+    Replace {
+        index: usize,
+        grapheme: String,
+        format: Format,
+    },
+}
+
+impl Edit {
+    /// The index in the original password the edit applies at.
+    pub fn index(&self) -> usize {
+        match self {
+            Edit::Insert { index, .. } | Edit::Remove { index } | Edit::Replace { index, .. } => {
+                *index
+            }
+        }
+    }
+}
+
+/// A minimal grapheme-level edit script turning `from` into `to`, where a grapheme with
+/// different formatting counts as different. Indices are into `from`, in ascending order
+/// (several inserts can share an index), so the edits can be made one after another starting
+/// from the end.
+pub fn diff(from: &Password, to: &Password) -> Vec<Edit> {
+    let from = from
+        .as_str()
+        .graphemes(true)
+        .zip(from.formatting())
+        .collect::<Vec<_>>();
+    let to = to
+        .as_str()
+        .graphemes(true)
+        .zip(to.formatting())
+        .collect::<Vec<_>>();
+
+    // Edit distances between the suffixes of each
+    let mut distances = vec![vec![0; to.len() + 1]; from.len() + 1];
+    for i in (0..=from.len()).rev() {
+        for j in (0..=to.len()).rev() {
+            distances[i][j] = if i == from.len() {
+                to.len() - j
+            } else if j == to.len() {
+                from.len() - i
+            } else if from[i] == to[j] {
+                distances[i + 1][j + 1]
+            } else {
+                1 + distances[i + 1][j + 1]
+                    .min(distances[i + 1][j])
+                    .min(distances[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < from.len() || j < to.len() {
+        if i < from.len() && j < to.len() && from[i] == to[j] {
+            i += 1;
+            j += 1;
+        } else if i < from.len() && j < to.len() && distances[i][j] == 1 + distances[i + 1][j + 1] {
+            edits.push(Edit::Replace {
+                index: i,
+                grapheme: to[j].0.to_owned(),
+                format: to[j].1.clone(),
+            });
+            i += 1;
+            j += 1;
+        } else if i < from.len() && distances[i][j] == 1 + distances[i + 1][j] {
+            edits.push(Edit::Remove { index: i });
+            i += 1;
+        } else {
+            edits.push(Edit::Insert {
+                index: i,
+                grapheme: to[j].0.to_owned(),
+                format: to[j].1.clone(),
+            });
+            j += 1;
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, Edit};
+    use crate::password::{Format, Password};
+
+    fn replace(index: usize, grapheme: &str) -> Edit {
+        Edit::Replace {
+            index,
+            grapheme: grapheme.into(),
+            format: Format::default(),
+        }
+    }
+
+    #[test]
+    fn edit_script() {
+        let password = |s| Password::from_str(s);
+        assert_eq!(diff(&password("abc"), &password("abc")), vec![]);
+
+        // Fire
+        assert_eq!(
+            diff(&password("🥚a🔥🔥d🔥🔥"), &password("🥚abcdef🏋️‍♂️")),
+            vec![
+                replace(2, "b"),
+                replace(3, "c"),
+                replace(5, "e"),
+                replace(6, "f"),
+                Edit::Insert {
+                    index: 7,
+                    grapheme: "🏋️‍♂️".into(),
+                    format: Format::default()
+                },
+            ]
+        );
+
+        // Paul hatching
+        assert_eq!(
+            diff(&password("🥚abc"), &password("🐔abc")),
+            vec![replace(0, "🐔")]
+        );
+
+        // Inserts and removals rather than replacing everything after them
+        assert_eq!(
+            diff(&password("xabcd"), &password("abcyyd")),
+            vec![
+                Edit::Remove { index: 0 },
+                Edit::Insert {
+                    index: 4,
+                    grapheme: "y".into(),
+                    format: Format::default()
+                },
+                Edit::Insert {
+                    index: 4,
+                    grapheme: "y".into(),
+                    format: Format::default()
+                },
+            ]
+        );
+        assert_eq!(
+            diff(&password("abc"), &password("")),
+            (0..3)
+                .map(|index| Edit::Remove { index })
+                .collect::<Vec<_>>()
+        );
+
+        // Formatting
+        let bold = Password::with_formatting("ab", vec![Format::default(), Format::bold()]);
+        assert_eq!(
+            diff(&password("ab"), &bold),
+            vec![Edit::Replace {
+                index: 1,
+                grapheme: "b".into(),
+                format: Format::bold()
+            }]
+        );
+        assert_eq!(diff(&password("ab"), &bold)[0].index(), 1);
+    }
+}
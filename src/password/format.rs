@@ -1,9 +1,23 @@
+use serde::{Deserialize, Serialize};
 use strum::{EnumCount, EnumIter};
 
 use super::FormatChange;
 
 /// Font size options.
-#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter, EnumCount)]
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    EnumIter,
+    EnumCount,
+    Serialize,
+    Deserialize,
+)]
 pub enum FontSize {
     #[default]
     Px28,
@@ -46,7 +60,33 @@ impl TryFrom<u32> for FontSize {
     }
 }
 
+impl std::fmt::Display for FontSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}px", self.px())
+    }
+}
+
 impl FontSize {
+    /// The font size in pixels.
+    pub fn px(&self) -> u32 {
+        match self {
+            FontSize::Px0 => 0,
+            FontSize::Px1 => 1,
+            FontSize::Px4 => 4,
+            FontSize::Px9 => 9,
+            FontSize::Px12 => 12,
+            FontSize::Px16 => 16,
+            FontSize::Px25 => 25,
+            FontSize::Px28 => 28,
+            FontSize::Px32 => 32,
+            FontSize::Px36 => 36,
+            FontSize::Px42 => 42,
+            FontSize::Px49 => 49,
+            FontSize::Px64 => 64,
+            FontSize::Px81 => 81,
+        }
+    }
+
     pub fn index(&self) -> usize {
         match self {
             FontSize::Px0 => 0,
@@ -68,17 +108,50 @@ impl FontSize {
 }
 
 /// Font family options.
-#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, EnumCount)]
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, EnumCount, Serialize, Deserialize,
+)]
 pub enum FontFamily {
     #[default]
     Monospace,
-    #[allow(dead_code)]
     ComicSans,
     Wingdings,
     TimesNewRoman,
 }
 
+impl std::fmt::Display for FontFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.css_name())
+    }
+}
+
 impl FontFamily {
+    /// The name of the font family as used in the game's CSS.
+    pub fn css_name(&self) -> &'static str {
+        match self {
+            FontFamily::Monospace => "Monospace",
+            FontFamily::ComicSans => "Comic Sans",
+            FontFamily::Wingdings => "Wingdings",
+            FontFamily::TimesNewRoman => "Times New Roman",
+        }
+    }
+
+    /// The font family with the given name in the game's CSS, allowing for the variations
+    /// browsers and the game have used.
+    pub fn from_css_name(name: &str) -> Option<Self> {
+        match name
+            .trim_matches(|c| c == '"' || c == '\'')
+            .to_lowercase()
+            .as_str()
+        {
+            "monospace" => Some(FontFamily::Monospace),
+            "comic sans" | "comic sans ms" | "cursive" => Some(FontFamily::ComicSans),
+            "wingdings" => Some(FontFamily::Wingdings),
+            "times new roman" => Some(FontFamily::TimesNewRoman),
+            _ => None,
+        }
+    }
+
     pub fn index(&self) -> usize {
         match self {
             FontFamily::Monospace => 0,
@@ -90,7 +163,7 @@ impl FontFamily {
 }
 
 /// Formatting properties of a grapheme cluster.
-#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Format {
     /// Bold.
     pub bold: bool,
@@ -120,6 +193,18 @@ impl std::fmt::Debug for Format {
     }
 }
 
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.bold {
+            f.write_str("bold ")?;
+        }
+        if self.italic {
+            f.write_str("italic ")?;
+        }
+        write!(f, "{} {}", self.font_size, self.font_family)
+    }
+}
+
 impl Format {
     pub fn change(&mut self, change: &FormatChange) {
         match change {
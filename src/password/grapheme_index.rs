@@ -0,0 +1,110 @@
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The byte range of each grapheme cluster in a string, so graphemes can be looked up by
+/// index without segmenting the whole string each time.
+#[derive(Debug, Default, Clone)]
+pub struct GraphemeIndex {
+    ranges: Vec<Range<usize>>,
+}
+
+impl GraphemeIndex {
+    /// Index the grapheme clusters in the given string.
+    pub fn new(string: &str) -> Self {
+        GraphemeIndex {
+            ranges: string
+                .grapheme_indices(true)
+                .map(|(offset, grapheme)| offset..offset + grapheme.len())
+                .collect(),
+        }
+    }
+
+    /// The number of grapheme clusters.
+    pub fn count(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// The byte range of the grapheme cluster at `index`.
+    pub fn byte_range(&self, index: usize) -> Range<usize> {
+        self.ranges[index].clone()
+    }
+
+    /// The byte offset of the grapheme cluster at `index`, or the end of the string if
+    /// `index` is the number of grapheme clusters.
+    pub fn byte_offset(&self, index: usize) -> usize {
+        match self.ranges.get(index) {
+            Some(range) => range.start,
+            None if index == self.ranges.len() => self.ranges.last().map_or(0, |r| r.end),
+            None => panic!("grapheme index {} out of bounds", index),
+        }
+    }
+
+    /// Update the index after the grapheme clusters in `removed` were replaced with
+    /// `inserted_len` bytes, given the string after the change. Only the changed part and the
+    /// grapheme clusters either side of it (which an insertion could join onto) are
+    /// re-segmented; the rest are just shifted.
+    pub fn splice(&mut self, string: &str, removed: Range<usize>, inserted_len: usize) {
+        let start = removed.start.saturating_sub(1);
+        let end = (removed.end + 1).min(self.count());
+        let removed_len = self.byte_offset(removed.end) - self.byte_offset(removed.start);
+        let window_start = self.byte_offset(start);
+        let window_end = self.byte_offset(end) + inserted_len - removed_len;
+
+        let resegmented = string[window_start..window_end]
+            .grapheme_indices(true)
+            .map(|(offset, grapheme)| window_start + offset..window_start + offset + grapheme.len())
+            .collect::<Vec<_>>();
+        for range in &mut self.ranges[end..] {
+            *range =
+                range.start + inserted_len - removed_len..range.end + inserted_len - removed_len;
+        }
+        self.ranges.splice(start..end, resegmented);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GraphemeIndex;
+
+    #[test]
+    fn index() {
+        let index = GraphemeIndex::new("a🏋️‍♂️b");
+        assert_eq!(index.count(), 3);
+        assert_eq!(index.byte_range(0), 0..1);
+        assert_eq!(index.byte_range(1), 1..17);
+        assert_eq!(index.byte_offset(2), 17);
+        assert_eq!(index.byte_offset(3), 18);
+
+        assert_eq!(GraphemeIndex::new("").byte_offset(0), 0);
+    }
+
+    #[test]
+    fn splice() {
+        // (before, graphemes replaced, replacement)
+        let cases = [
+            ("", 0..0, "abc"),
+            ("abc", 3..3, "🏋️‍♂️d"),
+            ("abc", 0..0, "🥚"),
+            ("a🏋️‍♂️b", 1..2, ""),
+            ("a🏋️‍♂️b", 1..2, "x"),
+            ("a🏋️‍♂️b", 0..3, ""),
+            ("abc", 1..2, "🔥🔥"),
+            // Joins onto the graphemes either side
+            ("a🏋b", 2..2, "\u{fe0f}"),
+            ("🏋️‍b", 2..2, "♂"),
+        ];
+        for (before, removed, replacement) in cases {
+            let mut index = GraphemeIndex::new(before);
+            let mut string = before.to_owned();
+            let bytes = index.byte_offset(removed.start)..index.byte_offset(removed.end);
+            string.replace_range(bytes, replacement);
+            index.splice(&string, removed, replacement.len());
+            assert_eq!(
+                index.ranges,
+                GraphemeIndex::new(&string).ranges,
+                "{:?}",
+                string
+            );
+        }
+    }
+}
@@ -2,6 +2,32 @@ use lazy_regex::regex;
 use numerals::roman::Roman;
 use unicode_segmentation::UnicodeSegmentation;
 
+/// The "weight lifter" emoji needed for rule 21 ("🏋️‍♂️"). A five codepoint ZWJ sequence:
+/// person lifting weights, variation selector, ZWJ, male sign, variation selector.
+pub const STRENGTH_EMOJI: &str = "\u{1f3cb}\u{fe0f}\u{200d}\u{2642}\u{fe0f}";
+
+/// Is the given grapheme the strength emoji? Variation selectors are ignored, as they're
+/// often dropped or added when the emoji is copied or typed.
+pub fn is_strength_emoji(grapheme: &str) -> bool {
+    grapheme
+        .chars()
+        .filter(|ch| *ch != '\u{fe0f}')
+        .eq(['\u{1f3cb}', '\u{200d}', '\u{2642}'])
+}
+
+/// Count the number of strength emojis in a string.
+pub fn count_strength(string: &str) -> usize {
+    string
+        .graphemes(true)
+        .filter(|g| is_strength_emoji(g))
+        .count()
+}
+
+/// A string of `n` strength emojis.
+pub fn append_strength(n: usize) -> String {
+    STRENGTH_EMOJI.repeat(n)
+}
+
 /// Get all element symbols in a string, along with their grapheme index.
 /// Two-letter symbols will be preferenced over single-letter symbols, if they are overlapping.
 /// (e.g., "Fe" will result in "Fe", not "F")
@@ -44,7 +70,7 @@ pub fn get_elements(string: &str) -> Vec<(&periodic_table::Element, usize)> {
         elements.remove(j);
     }
 
-    elements.sort_by(|a, b| a.1.cmp(&b.1));
+    elements.sort_by_key(|e| e.1);
     elements
 }
 
@@ -119,18 +145,17 @@ pub fn get_youtube_id(string: &str) -> Option<String> {
     let re1 = regex!(r"youtube\.com/watch\?v=(.{11})");
     let re2 = regex!(r"youtu\.be/(.{11})");
 
-    if let Some(captures) = re1.captures(string) {
-        Some(captures.get(1).unwrap().as_str().to_owned())
-    } else if let Some(captures) = re2.captures(string) {
-        Some(captures.get(1).unwrap().as_str().to_owned())
-    } else {
-        None
-    }
+    re1.captures(string)
+        .or_else(|| re2.captures(string))
+        .map(|captures| captures.get(1).unwrap().as_str().to_owned())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{get_digits, get_elements, get_roman_numerals, get_youtube_id};
+    use super::{
+        append_strength, count_strength, get_digits, get_elements, get_roman_numerals,
+        get_youtube_id, STRENGTH_EMOJI,
+    };
 
     #[test]
     fn elements() {
@@ -162,6 +187,23 @@ mod tests {
         assert!(get_roman_numerals("i").is_empty());
     }
 
+    #[test]
+    fn strength() {
+        assert_eq!(STRENGTH_EMOJI, "🏋️‍♂️");
+        assert_eq!(STRENGTH_EMOJI.chars().count(), 5);
+        assert_eq!(count_strength(&append_strength(3)), 3);
+        assert_eq!(count_strength(&format!("a{}b", append_strength(2))), 2);
+        assert_eq!(count_strength(""), 0);
+
+        // Missing variation selectors
+        assert_eq!(count_strength("\u{1f3cb}\u{200d}\u{2642}"), 1);
+        assert_eq!(count_strength("\u{1f3cb}\u{fe0f}\u{200d}\u{2642}"), 1);
+
+        // Other weight lifters don't count
+        assert_eq!(count_strength("🏋️"), 0);
+        assert_eq!(count_strength("🏋️‍♀️"), 0);
+    }
+
     #[test]
     fn youtube_id() {
         assert_eq!(
@@ -0,0 +1,176 @@
+//! Estimates of how many key presses it takes to enter a set of changes into the game, so that
+//! we can prefer the cheapest way of making them.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::Change;
+
+/// Key presses to select a grapheme, format it, and deselect it again.
+const FORMAT_KEYSTROKES: usize = 3;
+/// Key presses to select a grapheme and type over it.
+const REPLACE_KEYSTROKES: usize = 2;
+
+/// Estimate the key presses (cursor steps, typed graphemes, backspaces, etc.) needed to enter
+/// the given changes in order, starting with the cursor at `cursor` in a password of `length`
+/// graphemes. Mirrors the way the `WebDriver` enters changes.
+pub fn estimate(changes: &[Change], cursor: usize, length: usize) -> usize {
+    let mut cursor = cursor;
+    let mut length = length;
+    let mut keystrokes = 0;
+    let mut already_appended = false;
+    let mut already_prepended = false;
+    let mut removed = Vec::new();
+    for change in changes {
+        match change {
+            Change::Format { index, .. } => {
+                keystrokes += cursor.abs_diff(*index) + FORMAT_KEYSTROKES;
+                cursor = index + 1;
+            }
+            Change::Append { string, .. } => {
+                if !already_appended {
+                    keystrokes += cursor.abs_diff(length);
+                    cursor = length;
+                    already_appended = true;
+                }
+                let count = string.graphemes(true).count();
+                keystrokes += count;
+                cursor += count;
+                length += count;
+            }
+            Change::Prepend { string, .. } => {
+                if !already_prepended {
+                    keystrokes += cursor;
+                    cursor = 0;
+                    already_prepended = true;
+                }
+                let count = string.graphemes(true).count();
+                keystrokes += count;
+                cursor += count;
+                length += count;
+            }
+            Change::Insert { index, string, .. } => {
+                let count = string.graphemes(true).count();
+                keystrokes += cursor.abs_diff(*index) + count;
+                cursor = index + count;
+                length += count;
+            }
+            Change::Replace { index, .. } => {
+                keystrokes += cursor.abs_diff(index + 1) + REPLACE_KEYSTROKES;
+                cursor = index + 1;
+            }
+            Change::Remove { index, .. } => {
+                // Removal indices are relative to the password before any removals
+                let position = index - removed.iter().filter(|i| **i < *index).count();
+                keystrokes += cursor.abs_diff(position + 1) + 1;
+                cursor = position;
+                length -= 1;
+                removed.push(*index);
+            }
+        }
+    }
+    keystrokes
+}
+
+/// Put the given changes in an order they can be entered into the game in, starting with the
+/// cursor at `cursor` in a password of `length` graphemes. Inserts which end up next to each
+/// other are merged, and replacements and removals (which don't shift each other's indices)
+/// are worked through in whichever direction takes the fewest key presses.
+pub fn order_for_entry(mut changes: Vec<Change>, cursor: usize, length: usize) -> Vec<Change> {
+    // Default sort gives a valid order
+    changes.sort();
+
+    let mut ordered: Vec<Change> = Vec::with_capacity(changes.len());
+    for change in changes {
+        if let (
+            Some(Change::Insert {
+                index,
+                string,
+                protected,
+            }),
+            Change::Insert {
+                index: next_index,
+                string: next_string,
+                protected: next_protected,
+            },
+        ) = (ordered.last_mut(), &change)
+        {
+            if protected == next_protected && *index + string.graphemes(true).count() == *next_index
+            {
+                string.push_str(next_string);
+                continue;
+            }
+        }
+        ordered.push(change);
+    }
+
+    let groups: [fn(&Change) -> bool; 2] = [
+        |c| matches!(c, Change::Replace { .. }),
+        |c| matches!(c, Change::Remove { .. }),
+    ];
+    for in_group in groups {
+        let Some(start) = ordered.iter().position(in_group) else {
+            continue;
+        };
+        let end = ordered.iter().rposition(in_group).unwrap() + 1;
+        let forwards = estimate(&ordered, cursor, length);
+        ordered[start..end].reverse();
+        if estimate(&ordered, cursor, length) >= forwards {
+            ordered[start..end].reverse();
+        }
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate, order_for_entry};
+    use crate::password::Change;
+
+    fn remove(index: usize) -> Change {
+        Change::Remove {
+            index,
+            ignore_protection: false,
+        }
+    }
+
+    fn insert(index: usize, string: &str) -> Change {
+        Change::Insert {
+            index,
+            string: string.into(),
+            protected: false,
+        }
+    }
+
+    #[test]
+    fn estimate_keystrokes() {
+        let append = Change::Append {
+            string: "abc".into(),
+            protected: false,
+        };
+        // Already at the end, so just typing
+        assert_eq!(estimate(std::slice::from_ref(&append), 5, 5), 3);
+        // Plus stepping to the end first
+        assert_eq!(estimate(&[append], 0, 5), 8);
+
+        // Removing 1 and 3 from the start: step to 2, backspace, step to 3, backspace
+        assert_eq!(estimate(&[remove(1), remove(3)], 0, 5), 6);
+        // Same removals in reverse need no index adjustment
+        assert_eq!(estimate(&[remove(3), remove(1)], 5, 5), 4);
+    }
+
+    #[test]
+    fn entry_order() {
+        // Cursor at the end, so work through removals backwards
+        let ordered = order_for_entry(vec![remove(1), remove(3)], 5, 5);
+        assert_eq!(ordered, vec![remove(3), remove(1)]);
+        // Cursor at the start, so work forwards
+        let ordered = order_for_entry(vec![remove(3), remove(1)], 0, 5);
+        assert_eq!(ordered, vec![remove(1), remove(3)]);
+
+        // Adjacent inserts are typed in one go
+        let ordered = order_for_entry(vec![insert(4, "de"), insert(2, "ab")], 0, 5);
+        assert_eq!(ordered, vec![insert(2, "abde")]);
+        let ordered = order_for_entry(vec![insert(5, "de"), insert(2, "ab")], 0, 5);
+        assert_eq!(ordered, vec![insert(2, "ab"), insert(5, "de")]);
+    }
+}
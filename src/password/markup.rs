@@ -0,0 +1,274 @@
+//! A plain text markup for formatted passwords, for people to read and write: `**bold**`,
+//! `_italic_`, `{size=36}...{/}` and `{font=wingdings}...{/}`. Bold and italic are toggled on
+//! and off, while font and size groups nest, each `{/}` closing the innermost one. A backslash
+//! makes the next character literal (e.g., `\_`), and a single `*` is always literal.
+
+use thiserror::Error;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{
+    format::{FontFamily, FontSize},
+    Format, Password,
+};
+
+/// Characters which mean something in markup, so are escaped when they're in a password.
+const SPECIAL: &[char] = &['\\', '*', '_', '{', '}'];
+
+/// Failure modes for reading markup.
+#[derive(Debug, Error, PartialEq)]
+#[non_exhaustive]
+pub enum MarkupError {
+    #[error("unknown tag {{{0}}}")]
+    UnknownTag(String),
+    #[error("unknown font {0:?}")]
+    UnknownFont(String),
+    #[error("unknown font size {0:?}")]
+    UnknownFontSize(String),
+    #[error("missing }} at the end of a tag")]
+    UnterminatedTag,
+    #[error("{{/}} without a group to close")]
+    UnmatchedClose,
+    #[error("{0} isn't closed")]
+    Unclosed(&'static str),
+}
+
+/// A group opened by a tag, until the matching `{/}`.
+enum Group {
+    Size(FontSize),
+    Font(FontFamily),
+}
+
+impl Password {
+    /// Read a password written in markup.
+    pub fn from_markup(markup: &str) -> Result<Self, MarkupError> {
+        let mut string = String::new();
+        let mut formatting = Vec::new();
+        let mut format = Format::default();
+        let mut groups = Vec::new();
+
+        let mut rest = markup;
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("**") {
+                format.bold = !format.bold;
+                rest = after;
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix('_') {
+                format.italic = !format.italic;
+                rest = after;
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix('{') {
+                let (tag, after) = after.split_once('}').ok_or(MarkupError::UnterminatedTag)?;
+                if tag == "/" {
+                    groups.pop().ok_or(MarkupError::UnmatchedClose)?;
+                } else {
+                    groups.push(parse_tag(tag)?);
+                }
+                (format.font_size, format.font_family) = group_formats(&groups);
+                rest = after;
+                continue;
+            }
+
+            let escaped = rest.strip_prefix('\\').filter(|after| !after.is_empty());
+            let grapheme = escaped.unwrap_or(rest).graphemes(true).next().unwrap();
+            string.push_str(grapheme);
+            formatting.push(format.clone());
+            rest = &escaped.unwrap_or(rest)[grapheme.len()..];
+        }
+
+        if format.bold {
+            return Err(MarkupError::Unclosed("**"));
+        }
+        if format.italic {
+            return Err(MarkupError::Unclosed("_"));
+        }
+        match groups.last() {
+            Some(Group::Size(_)) => Err(MarkupError::Unclosed("{size}")),
+            Some(Group::Font(_)) => Err(MarkupError::Unclosed("{font}")),
+            None => Ok(Password::with_formatting(&string, formatting)),
+        }
+    }
+
+    /// Write the password in markup, which reads back as the same password. Bold and italic
+    /// are turned off before any group closes and on after any opens, so they read as nested.
+    pub fn to_markup(&self) -> String {
+        let mut markup = String::new();
+        let mut current = Format::default();
+        // Nothing in the default format at the end, to close everything that's still open
+        let end = Format::default();
+        let formats = self
+            .annotated()
+            .map(|(_, grapheme, format)| (grapheme, format))
+            .chain([("", &end)]);
+        for (grapheme, format) in formats {
+            if current.italic && !format.italic {
+                markup.push('_');
+            }
+            if current.bold && !format.bold {
+                markup.push_str("**");
+            }
+            markup.push_str(&group_tags(&current, format));
+            if !current.bold && format.bold {
+                markup.push_str("**");
+            }
+            if !current.italic && format.italic {
+                markup.push('_');
+            }
+            current = format.clone();
+
+            if grapheme.starts_with(SPECIAL) {
+                markup.push('\\');
+            }
+            markup.push_str(grapheme);
+        }
+        markup
+    }
+}
+
+fn parse_tag(tag: &str) -> Result<Group, MarkupError> {
+    if let Some(name) = tag.strip_prefix("font=") {
+        FontFamily::from_css_name(name)
+            .map(Group::Font)
+            .ok_or_else(|| MarkupError::UnknownFont(name.into()))
+    } else if let Some(size) = tag.strip_prefix("size=") {
+        size.trim_end_matches("px")
+            .parse::<u32>()
+            .ok()
+            .and_then(|px| FontSize::try_from(px).ok())
+            .map(Group::Size)
+            .ok_or_else(|| MarkupError::UnknownFontSize(size.into()))
+    } else {
+        Err(MarkupError::UnknownTag(tag.into()))
+    }
+}
+
+/// The font size and family inside the given groups, from the innermost of each.
+fn group_formats(groups: &[Group]) -> (FontSize, FontFamily) {
+    let font_size = groups.iter().rev().find_map(|group| match group {
+        Group::Size(font_size) => Some(font_size.clone()),
+        Group::Font(_) => None,
+    });
+    let font_family = groups.iter().rev().find_map(|group| match group {
+        Group::Font(font_family) => Some(font_family.clone()),
+        Group::Size(_) => None,
+    });
+    (
+        font_size.unwrap_or_default(),
+        font_family.unwrap_or_default(),
+    )
+}
+
+/// Tags to go from one format's font and size to another's. The groups are always opened font
+/// first, so they can be closed in order without tracking them.
+fn group_tags(from: &Format, to: &Format) -> String {
+    let groups = |format: &Format| {
+        let mut groups = Vec::new();
+        if format.font_family != FontFamily::default() {
+            groups.push(format!(
+                "{{font={}}}",
+                format.font_family.css_name().to_lowercase()
+            ));
+        }
+        if format.font_size != FontSize::default() {
+            groups.push(format!("{{size={}}}", format.font_size.px()));
+        }
+        groups
+    };
+    let (from, to) = (groups(from), groups(to));
+    // Groups shared with the start of the new ones can stay open
+    let kept = from
+        .iter()
+        .zip(&to)
+        .take_while(|(from, to)| from == to)
+        .count();
+    let mut tags = "{/}".repeat(from.len() - kept);
+    for group in &to[kept..] {
+        tags.push_str(group);
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MarkupError;
+    use crate::password::{
+        format::{FontFamily, FontSize},
+        Password,
+    };
+
+    #[test]
+    fn from_markup() {
+        let password =
+            Password::from_markup("a**b_c_**{font=wingdings}{size=36}d{/}e{/}\\_*").unwrap();
+        assert_eq!(password.as_str(), "abcde_*");
+        let formatting = password.formatting();
+        assert!(!formatting[0].bold);
+        assert!(formatting[1].bold && !formatting[1].italic);
+        assert!(formatting[2].bold && formatting[2].italic);
+        assert_eq!(formatting[3].font_family, FontFamily::Wingdings);
+        assert_eq!(formatting[3].font_size, FontSize::Px36);
+        assert_eq!(formatting[4].font_family, FontFamily::Wingdings);
+        assert_eq!(formatting[4].font_size, FontSize::default());
+        assert_eq!(formatting[5], Default::default());
+        assert_eq!(formatting[6], Default::default());
+
+        // Font names are read as the game writes them too
+        let password = Password::from_markup("{font=Comic Sans}🥚{/}").unwrap();
+        assert_eq!(password.formatting()[0].font_family, FontFamily::ComicSans);
+
+        assert_eq!(
+            Password::from_markup("a{/}").unwrap_err(),
+            MarkupError::UnmatchedClose
+        );
+        assert_eq!(
+            Password::from_markup("**a").unwrap_err(),
+            MarkupError::Unclosed("**")
+        );
+        assert_eq!(
+            Password::from_markup("{size=36}a").unwrap_err(),
+            MarkupError::Unclosed("{size}")
+        );
+        assert_eq!(
+            Password::from_markup("{size=13}a{/}").unwrap_err(),
+            MarkupError::UnknownFontSize("13".into())
+        );
+        assert_eq!(
+            Password::from_markup("{font=papyrus}a{/}").unwrap_err(),
+            MarkupError::UnknownFont("papyrus".into())
+        );
+        assert_eq!(
+            Password::from_markup("{color=red}a{/}").unwrap_err(),
+            MarkupError::UnknownTag("color=red".into())
+        );
+        assert_eq!(
+            Password::from_markup("{size=36").unwrap_err(),
+            MarkupError::UnterminatedTag
+        );
+    }
+
+    #[test]
+    fn to_markup() {
+        let password =
+            Password::from_markup("a**b_c_**{font=wingdings}{size=36}d{/}e{/}\\_*").unwrap();
+        assert_eq!(
+            password.to_markup(),
+            "a**b_c_**{font=wingdings}{size=36}d{/}e{/}\\_\\*"
+        );
+
+        // Anything written reads back the same
+        for markup in [
+            "",
+            "plain",
+            "**_bold and italic**_",
+            "{size=0}a{/}{font=times new roman}{size=81}**b**{/}{/}",
+            "\\{not a tag\\} \\\\ 🥚🐛",
+            "_{font=comic sans}a_b{/}",
+        ] {
+            let password = Password::from_markup(markup).unwrap();
+            let reread = Password::from_markup(&password.to_markup()).unwrap();
+            assert_eq!(reread.as_str(), password.as_str(), "{}", markup);
+            assert_eq!(reread.formatting(), password.formatting(), "{}", markup);
+        }
+    }
+}
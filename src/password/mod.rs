@@ -1,39 +1,131 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
 use unicode_segmentation::UnicodeSegmentation;
 
-pub use change::{Change, FormatChange};
+pub use change::{Change, ChangeError, FormatChange};
 pub use format::Format;
+use grapheme_index::GraphemeIndex;
+pub use markup::MarkupError;
 pub use mutable::MutablePassword;
-pub use protected::ProtectedPassword;
+pub use protected::{Block, ProtectedPassword};
+pub use tracked::{RegionId, TrackedRegion};
 
 mod change;
+pub mod diff;
 pub mod format;
+mod grapheme_index;
 pub mod helpers;
+pub mod keystrokes;
+mod markup;
 mod mutable;
 mod protected;
+mod tracked;
 
 /// A password with formatting. Conceptualised as a sequence of grapheme clusters.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(into = "SavedPassword", try_from = "SavedPassword")]
 pub struct Password {
     /// The current password.
     password: String,
     /// Formatting of each grapheme.
     /// The length of this Vec corresponds to `password.graphemes().count()`.
     formatting: Vec<Format>,
+    /// Location of each grapheme in `password`, kept up to date with every change.
+    graphemes: GraphemeIndex,
+}
+
+/// A password as it's saved, without the grapheme index, which is rebuilt when it's loaded.
+#[derive(Serialize, Deserialize)]
+struct SavedPassword {
+    password: String,
+    formatting: Vec<Format>,
+}
+
+impl From<Password> for SavedPassword {
+    fn from(password: Password) -> Self {
+        SavedPassword {
+            password: password.password,
+            formatting: password.formatting,
+        }
+    }
+}
+
+impl TryFrom<SavedPassword> for Password {
+    type Error = String;
+
+    fn try_from(saved: SavedPassword) -> Result<Self, Self::Error> {
+        let graphemes = saved.password.graphemes(true).count();
+        if saved.formatting.len() != graphemes {
+            return Err(format!(
+                "password has {} graphemes but {} formats",
+                graphemes,
+                saved.formatting.len()
+            ));
+        }
+        Ok(Password::with_formatting(&saved.password, saved.formatting))
+    }
 }
 
 impl Password {
     /// Construct a new password from the given string. Assumes default formatting.
     #[cfg(test)]
-    pub fn from_str(string: &str) -> Self {
+    pub(crate) fn from_str(string: &str) -> Self {
         Password {
             password: string.to_owned(),
             formatting: vec![Format::default(); string.graphemes(true).count()],
+            graphemes: GraphemeIndex::new(string),
         }
     }
 
+    /// Construct a password from the given string and the formatting of each of its grapheme
+    /// clusters.
+    pub fn with_formatting(string: &str, formatting: Vec<Format>) -> Self {
+        let mut password = Password {
+            password: string.to_owned(),
+            formatting,
+            graphemes: GraphemeIndex::default(),
+        };
+        password.reindex();
+        password
+    }
+
     /// The length of the password in terms of grapheme clusters.
     pub fn len(&self) -> usize {
-        self.password.graphemes(true).count()
+        self.graphemes.count()
+    }
+
+    /// Is the password empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The grapheme cluster at `index`.
+    pub fn grapheme(&self, index: usize) -> &str {
+        &self.password[self.graphemes.byte_range(index)]
+    }
+
+    /// Each grapheme cluster along with its index and formatting (see
+    /// `ProtectedPassword::annotated` for whether each one is protected too).
+    pub fn annotated(&self) -> impl Iterator<Item = (usize, &str, &Format)> {
+        self.formatting
+            .iter()
+            .enumerate()
+            .map(|(index, format)| (index, self.grapheme(index), format))
+    }
+
+    /// Re-index the grapheme clusters after the password string has changed.
+    fn reindex(&mut self) {
+        self.graphemes = GraphemeIndex::new(&self.password);
+        debug_assert_eq!(self.len(), self.formatting.len());
+    }
+
+    /// Replace the grapheme clusters in `range` with the given string, updating the index
+    /// without re-segmenting the whole password. The formatting should already be updated.
+    fn splice(&mut self, range: Range<usize>, string: &str) {
+        let bytes = self.graphemes.byte_offset(range.start)..self.graphemes.byte_offset(range.end);
+        self.password.replace_range(bytes, string);
+        self.graphemes.splice(&self.password, range, string.len());
+        debug_assert_eq!(self.len(), self.formatting.len());
     }
 
     /// The password as a string slice.
@@ -48,71 +140,32 @@ impl Password {
 
     /// Append a string to the password. Assumes default formatting.
     pub fn append(&mut self, string: &str) {
-        self.password.push_str(string);
-        for _ in 0..string.graphemes(true).count() {
-            self.formatting.push(Format::default());
-        }
-
-        debug_assert_eq!(self.len(), self.formatting.len());
+        self.insert(self.len(), string);
     }
 
     /// Prepend a string to the password. Assumes default formatting.
     pub fn prepend(&mut self, string: &str) {
-        self.password.insert_str(0, string);
-        for _ in 0..string.graphemes(true).count() {
-            self.formatting.insert(0, Format::default());
-        }
-
-        debug_assert_eq!(self.len(), self.formatting.len());
+        self.insert(0, string);
     }
 
     /// Insert a string at the given index. Assumes default formatting.
     pub fn insert(&mut self, index: usize, string: &str) {
-        if index == 0 {
-            self.prepend(string);
-            return;
-        }
-        if index == self.len() {
-            self.append(string);
-            return;
-        }
-
-        let byte_index = self.password.grapheme_indices(true).nth(index).unwrap().0;
-        self.password.insert_str(byte_index, string);
-        for _ in 0..string.graphemes(true).count() {
-            self.formatting.insert(index, Format::default());
-        }
-
-        debug_assert_eq!(self.len(), self.formatting.len());
+        self.formatting.splice(
+            index..index,
+            vec![Format::default(); string.graphemes(true).count()],
+        );
+        self.splice(index..index, string);
     }
 
     /// Remove the grapheme cluster at `index` from the password.
     pub fn remove(&mut self, index: usize) {
         self.formatting.remove(index);
-
-        let grapheme_indices = self.password.grapheme_indices(true).collect::<Vec<_>>();
-        let (byte_offset, grapheme) = grapheme_indices[index];
-        let (left, right) = self.password.split_at(byte_offset);
-
-        let mut new_password = left.to_string();
-        new_password.push_str(&right[grapheme.len()..]);
-        self.password = new_password;
-
-        debug_assert_eq!(self.len(), self.formatting.len());
+        self.splice(index..index + 1, "");
     }
 
     /// Replace the grapheme cluster at `index` with the one given. Formatting will stay the same.
     pub fn replace(&mut self, index: usize, replacement: &str) {
-        let grapheme_indices = self.password.grapheme_indices(true).collect::<Vec<_>>();
-        let (byte_offset, grapheme) = grapheme_indices[index];
-        let (left, right) = self.password.split_at(byte_offset);
-
-        let mut new_password = left.to_string();
-        new_password.push_str(replacement);
-        new_password.push_str(&right[grapheme.len()..]);
-        self.password = new_password;
-
-        debug_assert_eq!(self.len(), self.formatting.len());
+        self.splice(index..index + 1, replacement);
     }
 
     /// Format the grapheme cluster at `index`.
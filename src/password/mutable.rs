@@ -1,32 +1,40 @@
-use super::{Change, Password, ProtectedPassword};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::{
+    tracked::TrackedRegions, Block, Change, ChangeError, Format, Password, ProtectedPassword,
+    RegionId, TrackedRegion,
+};
 
 /// A password which can have `Change`s applied to it.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct MutablePassword {
     /// The password with associated notion of protected graphemes which
     /// can't be removed.
     password: ProtectedPassword,
     /// The current set of queued changes to the password.
     changes: Vec<Change>,
+    /// Label for the protected blocks added by the changes committed next.
+    block_label: Option<String>,
+    /// Regions of the password whose locations are kept up to date with every commit.
+    regions: TrackedRegions,
 }
 
 impl MutablePassword {
     /// Wrap the given protected password into a mutable password.
-    #[allow(dead_code)]
     pub fn new(password: ProtectedPassword) -> Self {
         MutablePassword {
             password,
             changes: Vec::new(),
+            block_label: None,
+            regions: TrackedRegions::default(),
         }
     }
 
     /// Construct a new password from the given string.
     #[cfg(test)]
-    pub fn from_str(string: &str) -> Self {
-        MutablePassword {
-            password: ProtectedPassword::from_str(string),
-            changes: Vec::new(),
-        }
+    pub(crate) fn from_str(string: &str) -> Self {
+        MutablePassword::new(ProtectedPassword::from_str(string))
     }
 
     /// The underlying `Password`.
@@ -40,15 +48,64 @@ impl MutablePassword {
     }
 
     /// Get the protected graphemes.
-    pub fn protected_graphemes(&self) -> &[bool] {
+    pub fn protected_graphemes(&self) -> impl ExactSizeIterator<Item = bool> + '_ {
         self.password.protected_graphemes()
     }
 
+    /// Whether the grapheme at the given index is protected.
+    pub fn is_protected(&self, index: usize) -> bool {
+        self.password.is_protected(index)
+    }
+
+    /// The protected blocks, in order.
+    pub fn blocks(&self) -> Vec<Block> {
+        self.password.blocks()
+    }
+
+    /// Label the protected blocks added by the changes committed next (e.g., with the rule
+    /// they're for). The label is cleared once they're committed. Without a label, blocks are
+    /// labelled with their contents.
+    pub fn label_new_blocks(&mut self, label: Option<String>) {
+        self.block_label = label;
+    }
+
+    /// Track the given region, which is already in the password.
+    pub fn track(&mut self, region: TrackedRegion) -> RegionId {
+        self.regions.track(region)
+    }
+
+    /// Track the region of a string which the changes to be committed next add, given where
+    /// the change adding it puts it in the password as it is now (see
+    /// `TrackedRegions::track_added`).
+    pub fn track_added(&mut self, region: TrackedRegion) -> RegionId {
+        self.regions.track_added(region)
+    }
+
+    /// Stop tracking the given region.
+    pub fn untrack(&mut self, id: RegionId) {
+        self.regions.untrack(id);
+    }
+
+    /// The current location of the given tracked region.
+    pub fn tracked(&self, id: RegionId) -> Option<&TrackedRegion> {
+        self.regions.get(id)
+    }
+
+    /// Each grapheme cluster along with its index, formatting, and whether it's protected.
+    pub fn annotated(&self) -> impl Iterator<Item = (usize, &str, &Format, bool)> {
+        self.password.annotated()
+    }
+
     /// The length of the password in terms of grapheme clusters.
     pub fn len(&self) -> usize {
         self.password.len()
     }
 
+    /// Is the password empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     /// The password as a string slice.
     pub fn as_str(&self) -> &str {
         self.password.as_str()
@@ -66,65 +123,19 @@ impl MutablePassword {
         &self.changes
     }
 
-    /// Queue the given change to the password. Panics if the given change is invalid
-    /// (e.g., if an index is invalid, or a protected grapheme would be modified/removed).
+    /// Queue the given change to the password. It's checked when the queue is committed,
+    /// against the password as the changes before it leave it, in the order they're committed
+    /// in (so a removal can be of a grapheme after a queued insert).
     pub fn queue_change(&mut self, change: Change) {
-        let is_valid = match &change {
-            Change::Append { .. } => {
-                // Appends are always valid
-                true
-            }
-            Change::Prepend { .. } => {
-                // Prepends are always valid
-                true
-            }
-            Change::Insert { .. } => {
-                // Inserts are always valid
-                // Note that inserting between two protected graphemes probably
-                // shouldn't be allowed, but we currently don't know if they're
-                // part of the same protected "block" or not. So for now, rely
-                // on the caller knowing what they're doing.
-                true
-            }
-            Change::Remove {
-                index,
-                ignore_protection,
-            } => {
-                // Valid as long as the grapheme isn't protected
-                *ignore_protection || !self.password.protected_graphemes()[*index]
-            }
-            Change::Replace {
-                index,
-                ignore_protection,
-                ..
-            } => {
-                // Valid as long as the grapheme isn't protected
-                *ignore_protection || !self.password.protected_graphemes()[*index]
-            }
-            Change::Format { index, .. } => {
-                // Only invalid if the index is invalid (formatting is not protected)
-                *index < self.password.len()
-            }
-        };
-        if !is_valid {
-            panic!("invalid change: {:?}", change);
-        }
-
         self.changes.push(change);
     }
 
-    /// Sort changes such that they can be committed.
-    fn sort_changes_for_commit(&mut self) {
-        // Default sort is correct, other than that removals need to be reversed
-        self.changes.sort();
-        let first_removal = self
-            .changes
-            .iter()
-            .position(|c| matches!(c, Change::Remove { .. }));
-        if let Some(first_removal) = first_removal {
-            let (_, right) = self.changes.split_at_mut(first_removal);
-            right.reverse();
-        }
+    /// Apply the given changes to a copy of the password, as `commit_changes` would, without
+    /// touching the password or the queue. Returns an error if any of them is invalid, so a
+    /// plan can be checked before it's typed.
+    pub fn preview_changes(&self, changes: &[Change]) -> Result<Password, ChangeError> {
+        let (password, _) = self.apply_to_copy(changes)?;
+        Ok(password.raw_password().clone())
     }
 
     /// Commit the current set of queued changes. Will perform operations in the
@@ -133,31 +144,87 @@ impl MutablePassword {
     ///  - append
     ///  - replace
     ///  - remove
+    ///
     /// Additionally, removals will be performed starting at the end of the string
-    /// and working backwards.
-    pub fn commit_changes(&mut self) {
-        self.sort_changes_for_commit();
-        for change in self.changes.drain(..) {
-            self.password.apply_change(&change);
-        }
+    /// and working backwards. Tracked regions are moved along with each change.
+    ///
+    /// Returns an error if any change is invalid (e.g., if an index is invalid, or a protected
+    /// grapheme would be modified/removed), in which case none of them are made. The queue is
+    /// emptied either way.
+    pub fn commit_changes(&mut self) -> Result<(), ChangeError> {
+        let changes = std::mem::take(&mut self.changes);
+        (self.password, self.regions) = self.apply_to_copy(&changes)?;
+        self.block_label = None;
+        Ok(())
     }
 
-    /// Raw insert into the password.
+    /// Apply the given changes, in commit order, to copies of the password and tracked regions,
+    /// checking each one against the password as the changes before it leave it.
+    fn apply_to_copy(
+        &self,
+        changes: &[Change],
+    ) -> Result<(ProtectedPassword, TrackedRegions), ChangeError> {
+        check_removes(changes)?;
+        let mut changes = changes.to_vec();
+        sort_for_commit(&mut changes);
+        let mut password = self.password.clone();
+        let mut regions = self.regions.clone();
+        for change in &changes {
+            password.check_change(change)?;
+            password.apply_labelled_change(change, self.block_label.as_deref());
+            regions.remap(change);
+        }
+        regions.committed();
+        Ok((password, regions))
+    }
 
     /// Protect the given grapheme.
     #[cfg(test)]
     pub fn protect(&mut self, index: usize) {
         self.password.protect(index);
     }
+
+    /// Protect the given range of graphemes as a single block with the given label.
+    pub fn protect_range(&mut self, range: std::ops::Range<usize>, label: &str) {
+        self.password.protect_range(range, label);
+    }
+}
+
+/// Check that no two of the given changes remove the same grapheme.
+fn check_removes(changes: &[Change]) -> Result<(), ChangeError> {
+    let mut removed = HashSet::new();
+    for change in changes {
+        if let Change::Remove { index, .. } = change {
+            if !removed.insert(*index) {
+                return Err(ChangeError::OverlappingRemoves(*index));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sort changes such that they can be committed.
+fn sort_for_commit(changes: &mut [Change]) {
+    // Default sort is correct, other than that removals need to be reversed
+    changes.sort();
+    let first_removal = changes
+        .iter()
+        .position(|c| matches!(c, Change::Remove { .. }));
+    if let Some(first_removal) = first_removal {
+        let (_, right) = changes.split_at_mut(first_removal);
+        right.reverse();
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{MutablePassword, ProtectedPassword};
-    use crate::password::{change::Change, Password};
+    use crate::password::{
+        change::{Change, ChangeError},
+        Password,
+    };
 
     #[test]
-    #[should_panic]
     fn remove_protected() {
         let mut password = MutablePassword::new(ProtectedPassword::new(Password::from_str("foo")));
         password.password.protect(0);
@@ -165,18 +232,27 @@ mod tests {
             index: 0,
             ignore_protection: false,
         });
+        assert_eq!(password.commit_changes(), Err(ChangeError::Protected(0)));
+        assert_eq!(password.as_str(), "foo");
+        assert_eq!(password.queue_len(), 0);
     }
 
     #[test]
-    #[should_panic]
     fn replace_protected() {
         let mut password = MutablePassword::new(ProtectedPassword::new(Password::from_str("foo")));
         password.password.protect(0);
+        // The valid change isn't made either
+        password.queue_change(Change::Append {
+            string: "bar".into(),
+            protected: false,
+        });
         password.queue_change(Change::Replace {
             index: 0,
             new_grapheme: "b".into(),
             ignore_protection: false,
         });
+        assert_eq!(password.commit_changes(), Err(ChangeError::Protected(0)));
+        assert_eq!(password.as_str(), "foo");
     }
 
     #[test]
@@ -191,7 +267,7 @@ mod tests {
             index: 1,
             ignore_protection: false,
         });
-        password.commit_changes();
+        password.commit_changes().unwrap();
         assert_eq!(password.as_str(), "c");
 
         // Changes in reverse order
@@ -204,7 +280,74 @@ mod tests {
             index: 0,
             ignore_protection: false,
         });
-        password.commit_changes();
+        password.commit_changes().unwrap();
         assert_eq!(password.as_str(), "b");
     }
+
+    #[test]
+    fn preview_changes() {
+        let mut password = MutablePassword::from_str("abc");
+        password.protect(1);
+        let preview = password
+            .preview_changes(&[
+                Change::Remove {
+                    index: 0,
+                    ignore_protection: false,
+                },
+                Change::Replace {
+                    index: 2,
+                    new_grapheme: "d".into(),
+                    ignore_protection: false,
+                },
+                Change::Append {
+                    string: "e".into(),
+                    protected: false,
+                },
+            ])
+            .unwrap();
+        assert_eq!(preview.as_str(), "bde");
+        assert_eq!(password.as_str(), "abc");
+        assert_eq!(password.queue_len(), 0);
+
+        let remove = |index| Change::Remove {
+            index,
+            ignore_protection: false,
+        };
+        assert_eq!(
+            password.preview_changes(&[remove(1)]).unwrap_err(),
+            ChangeError::Protected(1)
+        );
+        assert_eq!(
+            password.preview_changes(&[remove(3)]).unwrap_err(),
+            ChangeError::IndexOutOfBounds { index: 3, len: 3 }
+        );
+        assert_eq!(
+            password
+                .preview_changes(&[remove(0), remove(0)])
+                .unwrap_err(),
+            ChangeError::OverlappingRemoves(0)
+        );
+    }
+
+    #[test]
+    fn block_labels_cleared() {
+        let mut password = MutablePassword::from_str("a");
+        let append = |string: &str| Change::Append {
+            string: string.into(),
+            protected: true,
+        };
+        password.label_new_blocks(Some("Sponsor".into()));
+        password.queue_change(append("pepsi"));
+        password.commit_changes().unwrap();
+        password.queue_change(append("may"));
+        password.commit_changes().unwrap();
+        assert_eq!(
+            password
+                .blocks()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["Sponsor 1..6", "may 6..9"]
+        );
+    }
 }
@@ -1,35 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, ops::Range};
 use unicode_segmentation::UnicodeSegmentation;
 
-use super::{Change, Password};
+use super::{Change, ChangeError, Format, Password};
+
+/// A run of protected grapheme clusters which were added together (e.g., a sponsor), which
+/// can't be modified or inserted into, only found again as a whole.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    /// What the block is for (e.g., the rule which added it).
+    pub label: String,
+    /// Grapheme indices of the block.
+    pub range: Range<usize>,
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:?}", self.label, self.range)
+    }
+}
 
 /// A password combined with the notion of protected graphemes.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ProtectedPassword {
     /// The password.
     password: Password,
-    /// The grapheme clusters in the password which mustn't be modified.
-    /// The length of this Vec corresponds to `password.len()`.
-    protected_graphemes: Vec<bool>,
+    /// The protected block each grapheme cluster belongs to, if any, as an index into
+    /// `labels`. The length of this Vec corresponds to `password.len()`.
+    blocks: Vec<Option<usize>>,
+    /// The label of every block created so far (blocks are never reused, even once removed).
+    labels: Vec<String>,
 }
 
 impl ProtectedPassword {
     /// Add protection to the given password.
-    #[cfg(test)]
     pub fn new(password: Password) -> Self {
-        let protected_graphemes = vec![false; password.len()];
+        let blocks = vec![None; password.len()];
         ProtectedPassword {
             password,
-            protected_graphemes,
+            blocks,
+            labels: Vec::new(),
         }
     }
 
     /// Construct a new password from the given string.
     #[cfg(test)]
-    pub fn from_str(string: &str) -> Self {
-        ProtectedPassword {
-            password: Password::from_str(string),
-            protected_graphemes: vec![false; string.graphemes(true).count()],
-        }
+    pub(crate) fn from_str(string: &str) -> Self {
+        ProtectedPassword::new(Password::from_str(string))
     }
 
     /// The underlying `Password`.
@@ -47,14 +64,53 @@ impl ProtectedPassword {
         self.password.len()
     }
 
+    /// Is the password empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     /// The password as a string slice.
     pub fn as_str(&self) -> &str {
         self.password.as_str()
     }
 
-    /// Get the protected graphemes.
-    pub fn protected_graphemes(&self) -> &[bool] {
-        &self.protected_graphemes
+    /// Whether each grapheme is protected.
+    pub fn protected_graphemes(&self) -> impl ExactSizeIterator<Item = bool> + '_ {
+        self.blocks.iter().map(Option::is_some)
+    }
+
+    /// Whether the grapheme at the given index is protected.
+    pub fn is_protected(&self, index: usize) -> bool {
+        self.blocks[index].is_some()
+    }
+
+    /// The protected blocks, in order.
+    pub fn blocks(&self) -> Vec<Block> {
+        let mut blocks: Vec<(usize, Range<usize>)> = Vec::new();
+        for (index, block) in self.blocks.iter().enumerate() {
+            let Some(block) = *block else {
+                continue;
+            };
+            match blocks.last_mut() {
+                Some((id, range)) if *id == block && range.end == index => range.end += 1,
+                _ => blocks.push((block, index..index + 1)),
+            }
+        }
+        blocks
+            .into_iter()
+            .map(|(id, range)| Block {
+                label: self.labels[id].clone(),
+                range,
+            })
+            .collect()
+    }
+
+    /// Each grapheme cluster along with its index, formatting, and whether it's protected.
+    pub fn annotated(&self) -> impl Iterator<Item = (usize, &str, &Format, bool)> {
+        self.password
+            .annotated()
+            .zip(self.blocks.iter())
+            .map(|((index, grapheme, format), block)| (index, grapheme, format, block.is_some()))
     }
 
     /// Get the protected graphemes as a bitstring.
@@ -62,20 +118,92 @@ impl ProtectedPassword {
     /// The results will be of length `password.len()`.
     #[cfg(test)]
     pub fn protected_chars_bitstring(&self) -> String {
-        self.protected_graphemes
+        self.blocks
             .iter()
-            .map(|b| if *b { '1' } else { '0' })
+            .map(|b| if b.is_some() { '1' } else { '0' })
             .collect::<String>()
     }
 
-    /// Protect the given grapheme.
+    /// Start a new block with the given label, returning its ID.
+    fn new_block(&mut self, label: &str) -> usize {
+        self.labels.push(label.to_owned());
+        self.labels.len() - 1
+    }
+
+    /// Protect the given grapheme, as a block of its own.
     #[cfg(test)]
     pub fn protect(&mut self, index: usize) {
-        self.protected_graphemes[index] = true;
+        self.protect_range(index..index + 1, "");
+    }
+
+    /// Protect the given range of graphemes as a single block with the given label.
+    pub fn protect_range(&mut self, range: Range<usize>, label: &str) {
+        let block = self.new_block(label);
+        for index in range {
+            self.blocks[index] = Some(block);
+        }
     }
 
-    /// Apply the given change to the password. Panics if it's not valid.
+    /// Check the given change could be applied to the password as it is.
+    pub fn check_change(&self, change: &Change) -> Result<(), ChangeError> {
+        let (index, ignore_protection, len) = match change {
+            Change::Append { .. } | Change::Prepend { .. } => return Ok(()),
+            Change::Insert { index, .. } => {
+                if *index > self.len() {
+                    return Err(ChangeError::IndexOutOfBounds {
+                        index: *index,
+                        len: self.len(),
+                    });
+                }
+                // Inserting between two blocks is fine, but not into the middle of one
+                if let (Some(Some(before)), Some(Some(after))) = (
+                    index.checked_sub(1).and_then(|i| self.blocks.get(i)),
+                    self.blocks.get(*index),
+                ) {
+                    if before == after {
+                        return Err(ChangeError::InsideBlock {
+                            index: *index,
+                            label: self.labels[*before].clone(),
+                        });
+                    }
+                }
+                return Ok(());
+            }
+            // Formatting is not protected
+            Change::Format { index, .. } => (*index, true, self.len()),
+            Change::Remove {
+                index,
+                ignore_protection,
+            }
+            | Change::Replace {
+                index,
+                ignore_protection,
+                ..
+            } => (*index, *ignore_protection, self.len()),
+        };
+        if index >= len {
+            return Err(ChangeError::IndexOutOfBounds {
+                index,
+                len: self.len(),
+            });
+        }
+        if !ignore_protection && self.is_protected(index) {
+            return Err(ChangeError::Protected(index));
+        }
+        Ok(())
+    }
+
+    /// Apply the given change to the password. Panics if it's not valid. Protected strings
+    /// added become blocks labelled with their contents.
+    #[cfg(test)]
     pub fn apply_change(&mut self, change: &Change) {
+        self.apply_labelled_change(change, None);
+    }
+
+    /// Apply the given change to the password, with any protected string it adds becoming a
+    /// block with the given label (or its contents, if there's no label). Panics if it's not
+    /// valid.
+    pub fn apply_labelled_change(&mut self, change: &Change, label: Option<&str>) {
         match change {
             Change::Format {
                 index,
@@ -84,54 +212,57 @@ impl ProtectedPassword {
                 self.password.format(*index, format_change);
             }
             Change::Append { string, protected } => {
+                let block = protected.then(|| self.new_block(label.unwrap_or(string)));
                 self.password.append(string);
                 for _ in 0..string.graphemes(true).count() {
-                    self.protected_graphemes.push(*protected);
+                    self.blocks.push(block);
                 }
 
-                debug_assert_eq!(self.password.len(), self.protected_graphemes.len());
+                debug_assert_eq!(self.password.len(), self.blocks.len());
             }
             Change::Prepend { string, protected } => {
+                let block = protected.then(|| self.new_block(label.unwrap_or(string)));
                 self.password.prepend(string);
                 for _ in 0..string.graphemes(true).count() {
-                    self.protected_graphemes.insert(0, *protected);
+                    self.blocks.insert(0, block);
                 }
 
-                debug_assert_eq!(self.password.len(), self.protected_graphemes.len());
+                debug_assert_eq!(self.password.len(), self.blocks.len());
             }
             Change::Insert {
                 index,
                 string,
                 protected,
             } => {
+                let block = protected.then(|| self.new_block(label.unwrap_or(string)));
                 self.password.insert(*index, string);
                 for _ in 0..string.graphemes(true).count() {
-                    self.protected_graphemes.insert(*index, *protected);
+                    self.blocks.insert(*index, block);
                 }
 
-                debug_assert_eq!(self.password.len(), self.protected_graphemes.len());
+                debug_assert_eq!(self.password.len(), self.blocks.len());
             }
             Change::Remove {
                 index,
                 ignore_protection,
             } => {
-                assert!(*ignore_protection || !self.protected_graphemes[*index]);
+                assert!(*ignore_protection || !self.is_protected(*index));
 
                 self.password.remove(*index);
-                self.protected_graphemes.remove(*index);
+                self.blocks.remove(*index);
 
-                debug_assert_eq!(self.password.len(), self.protected_graphemes.len());
+                debug_assert_eq!(self.password.len(), self.blocks.len());
             }
             Change::Replace {
                 index,
                 new_grapheme,
                 ignore_protection,
             } => {
-                assert!(*ignore_protection || !self.protected_graphemes[*index]);
+                assert!(*ignore_protection || !self.is_protected(*index));
 
                 self.password.replace(*index, new_grapheme);
 
-                debug_assert_eq!(self.password.len(), self.protected_graphemes.len());
+                debug_assert_eq!(self.password.len(), self.blocks.len());
             }
         }
     }
@@ -139,27 +270,51 @@ impl ProtectedPassword {
 
 #[cfg(test)]
 mod tests {
-    use super::{Change, Password, ProtectedPassword};
+    use super::{Block, Change, Format, Password, ProtectedPassword};
+    use crate::password::{ChangeError, FormatChange};
+
+    /// A password with each of the given graphemes protected as a block of its own.
+    fn with_protection(password: Password, protected: &[bool]) -> ProtectedPassword {
+        let mut password = ProtectedPassword::new(password);
+        for (index, _) in protected.iter().enumerate().filter(|(_, p)| **p) {
+            password.protect(index);
+        }
+        password
+    }
 
     #[test]
     fn protected_bitstring() {
         // ASCII
-        let password = ProtectedPassword {
-            password: Password::from_str("hello"),
-            protected_graphemes: vec![false, false, true, true, false],
-        };
+        let password = with_protection(
+            Password::from_str("hello"),
+            &[false, false, true, true, false],
+        );
         let bitstring = password.protected_chars_bitstring();
         assert_eq!(bitstring, "00110");
 
         // Unicode
-        let password = ProtectedPassword {
-            password: Password::from_str("🏋️‍♂️1"),
-            protected_graphemes: vec![true, false],
-        };
+        let password = with_protection(Password::from_str("🏋️‍♂️1"), &[true, false]);
         let bitstring = password.protected_chars_bitstring();
         assert_eq!(bitstring, "10");
     }
 
+    #[test]
+    fn annotated() {
+        let mut password = with_protection(Password::from_str("a🏋️‍♂️b"), &[false, true, false]);
+        password.apply_change(&Change::Format {
+            index: 2,
+            format_change: FormatChange::BoldOn,
+        });
+        assert_eq!(
+            password.annotated().collect::<Vec<_>>(),
+            vec![
+                (0, "a", &Format::default(), false),
+                (1, "🏋️‍♂️", &Format::default(), true),
+                (2, "b", &Format::bold(), false),
+            ]
+        );
+    }
+
     #[test]
     fn append() {
         // Unprotected
@@ -169,7 +324,10 @@ mod tests {
             protected: false,
         });
         assert_eq!(password.as_str(), "foobar");
-        assert_eq!(password.protected_graphemes(), vec![false; 6]);
+        assert_eq!(
+            password.protected_graphemes().collect::<Vec<_>>(),
+            vec![false; 6]
+        );
 
         // Protected
         let mut password = ProtectedPassword::from_str("foo");
@@ -179,7 +337,7 @@ mod tests {
         });
         assert_eq!(password.as_str(), "foobar");
         assert_eq!(
-            password.protected_graphemes(),
+            password.protected_graphemes().collect::<Vec<_>>(),
             vec![false, false, false, true, true, true]
         );
     }
@@ -193,7 +351,10 @@ mod tests {
             protected: false,
         });
         assert_eq!(password.as_str(), "foobar");
-        assert_eq!(password.protected_graphemes(), vec![false; 6]);
+        assert_eq!(
+            password.protected_graphemes().collect::<Vec<_>>(),
+            vec![false; 6]
+        );
 
         // Protected
         let mut password = ProtectedPassword::from_str("bar");
@@ -203,7 +364,7 @@ mod tests {
         });
         assert_eq!(password.as_str(), "foobar");
         assert_eq!(
-            password.protected_graphemes(),
+            password.protected_graphemes().collect::<Vec<_>>(),
             vec![true, true, true, false, false, false]
         );
     }
@@ -218,7 +379,10 @@ mod tests {
             protected: false,
         });
         assert_eq!(password.as_str(), "foobar");
-        assert_eq!(password.protected_graphemes(), vec![false; 6]);
+        assert_eq!(
+            password.protected_graphemes().collect::<Vec<_>>(),
+            vec![false; 6]
+        );
 
         // Protected
         let mut password = ProtectedPassword::from_str("for");
@@ -229,7 +393,7 @@ mod tests {
         });
         assert_eq!(password.as_str(), "foobar");
         assert_eq!(
-            password.protected_graphemes(),
+            password.protected_graphemes().collect::<Vec<_>>(),
             vec![false, false, true, true, true, false]
         );
 
@@ -241,7 +405,10 @@ mod tests {
             protected: false,
         });
         assert_eq!(password.as_str(), "foobar");
-        assert_eq!(password.protected_graphemes(), vec![false; 6]);
+        assert_eq!(
+            password.protected_graphemes().collect::<Vec<_>>(),
+            vec![false; 6]
+        );
 
         // At end
         let mut password = ProtectedPassword::from_str("foo");
@@ -251,7 +418,10 @@ mod tests {
             protected: false,
         });
         assert_eq!(password.as_str(), "foobar");
-        assert_eq!(password.protected_graphemes(), vec![false; 6]);
+        assert_eq!(
+            password.protected_graphemes().collect::<Vec<_>>(),
+            vec![false; 6]
+        );
     }
 
     #[test]
@@ -262,16 +432,22 @@ mod tests {
             ignore_protection: false,
         });
         assert_eq!(password.as_str(), "fo");
-        assert_eq!(password.protected_graphemes(), vec![false, false]);
+        assert_eq!(
+            password.protected_graphemes().collect::<Vec<_>>(),
+            vec![false, false]
+        );
 
         let mut password = ProtectedPassword::from_str("foo");
-        password.protected_graphemes[1] = true;
+        password.protect(1);
         password.apply_change(&Change::Remove {
             index: 0,
             ignore_protection: false,
         });
         assert_eq!(password.as_str(), "oo");
-        assert_eq!(password.protected_graphemes(), vec![true, false]);
+        assert_eq!(
+            password.protected_graphemes().collect::<Vec<_>>(),
+            vec![true, false]
+        );
 
         // With unicode in the string
         let mut password = ProtectedPassword::from_str("🏋️‍♂️a");
@@ -280,7 +456,10 @@ mod tests {
             ignore_protection: false,
         });
         assert_eq!(password.as_str(), "🏋️‍♂️");
-        assert_eq!(password.protected_graphemes(), vec![false]);
+        assert_eq!(
+            password.protected_graphemes().collect::<Vec<_>>(),
+            vec![false]
+        );
     }
 
     #[test]
@@ -292,17 +471,23 @@ mod tests {
             ignore_protection: false,
         });
         assert_eq!(password.as_str(), "boo");
-        assert_eq!(password.protected_graphemes(), vec![false, false, false]);
+        assert_eq!(
+            password.protected_graphemes().collect::<Vec<_>>(),
+            vec![false, false, false]
+        );
 
         let mut password = ProtectedPassword::new(Password::from_str("foo"));
-        password.protected_graphemes[1] = true;
+        password.protect(1);
         password.apply_change(&Change::Replace {
             index: 0,
             new_grapheme: "b".into(),
             ignore_protection: false,
         });
         assert_eq!(password.as_str(), "boo");
-        assert_eq!(password.protected_graphemes(), vec![false, true, false]);
+        assert_eq!(
+            password.protected_graphemes().collect::<Vec<_>>(),
+            vec![false, true, false]
+        );
 
         // With unicode in the string
         let mut password = ProtectedPassword::new(Password::from_str("🏋️‍♂️a"));
@@ -312,7 +497,10 @@ mod tests {
             ignore_protection: false,
         });
         assert_eq!(password.as_str(), "🏋️‍♂️b");
-        assert_eq!(password.protected_graphemes(), vec![false, false]);
+        assert_eq!(
+            password.protected_graphemes().collect::<Vec<_>>(),
+            vec![false, false]
+        );
     }
 
     #[test]
@@ -337,4 +525,67 @@ mod tests {
             ignore_protection: false,
         });
     }
+
+    #[test]
+    fn blocks() {
+        let mut password = ProtectedPassword::from_str("ab");
+        password.apply_labelled_change(
+            &Change::Append {
+                string: "may".into(),
+                protected: true,
+            },
+            Some("Month"),
+        );
+        password.apply_change(&Change::Append {
+            string: "shell".into(),
+            protected: true,
+        });
+        password.apply_change(&Change::Append {
+            string: "z".into(),
+            protected: false,
+        });
+        assert_eq!(
+            password.blocks(),
+            vec![
+                Block {
+                    label: "Month".into(),
+                    range: 2..5
+                },
+                Block {
+                    label: "shell".into(),
+                    range: 5..10
+                },
+            ]
+        );
+        assert_eq!(password.blocks()[0].to_string(), "Month 2..5");
+
+        // Inserting between blocks is fine, but not inside one
+        let insert = |index| Change::Insert {
+            index,
+            string: "x".into(),
+            protected: false,
+        };
+        assert_eq!(password.check_change(&insert(2)), Ok(()));
+        assert_eq!(password.check_change(&insert(5)), Ok(()));
+        assert_eq!(password.check_change(&insert(10)), Ok(()));
+        assert_eq!(password.check_change(&insert(11)), Ok(()));
+        assert_eq!(
+            password.check_change(&insert(3)),
+            Err(ChangeError::InsideBlock {
+                index: 3,
+                label: "Month".into()
+            })
+        );
+        assert!(matches!(
+            password.check_change(&insert(12)),
+            Err(ChangeError::IndexOutOfBounds { .. })
+        ));
+
+        // Blocks stay whole as graphemes are removed around them
+        password.apply_change(&Change::Remove {
+            index: 0,
+            ignore_protection: false,
+        });
+        assert_eq!(password.blocks()[1].range, 4..9);
+    }
 }
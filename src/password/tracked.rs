@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, ops::Range};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::Change;
+
+/// A run of graphemes in the password (e.g., the length string) whose location and length are
+/// kept up to date as changes are committed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackedRegion {
+    /// Grapheme index of the first grapheme in the region.
+    pub index: usize,
+    /// Length of the region in grapheme clusters.
+    pub length: usize,
+}
+
+impl TrackedRegion {
+    pub fn new(index: usize, length: usize) -> Self {
+        TrackedRegion { index, length }
+    }
+
+    /// Grapheme indices of the region.
+    pub fn range(&self) -> Range<usize> {
+        self.index..self.index + self.length
+    }
+
+    /// Does the region cover the given grapheme index?
+    pub fn contains(&self, index: usize) -> bool {
+        self.range().contains(&index)
+    }
+}
+
+/// Handle to a tracked region of a password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegionId(usize);
+
+/// The regions tracked in a password, moved along with every committed change.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TrackedRegions {
+    /// The regions still tracked, by ID.
+    regions: BTreeMap<usize, TrackedRegion>,
+    /// ID of the next region tracked (IDs are never reused, even once untracked).
+    next_id: usize,
+    /// Regions of strings which the next commit adds. An insert at the start of one of these
+    /// is the string itself, so doesn't move it.
+    added: Vec<RegionId>,
+}
+
+impl TrackedRegions {
+    /// Track a region already in the password.
+    pub fn track(&mut self, region: TrackedRegion) -> RegionId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.regions.insert(id, region);
+        RegionId(id)
+    }
+
+    /// Track the region of a string which the next commit adds, given where the change adding
+    /// it puts it in the password as it is now (e.g., the index it's inserted at, or the
+    /// current length for an append), and its length once the commit is made. The commit's
+    /// other changes move it like any other region, but don't resize it.
+    pub fn track_added(&mut self, region: TrackedRegion) -> RegionId {
+        let id = self.track(region);
+        self.added.push(id);
+        id
+    }
+
+    /// Stop tracking the given region.
+    pub fn untrack(&mut self, id: RegionId) {
+        self.regions.remove(&id.0);
+        self.added.retain(|added| *added != id);
+    }
+
+    /// The current location of the given region, if it's still tracked.
+    pub fn get(&self, id: RegionId) -> Option<&TrackedRegion> {
+        self.regions.get(&id.0)
+    }
+
+    /// Move the regions to account for the given change, which has just been made, growing or
+    /// shrinking those it's made inside of (other than regions the commit adds).
+    pub fn remap(&mut self, change: &Change) {
+        for (id, region) in self.regions.iter_mut() {
+            let added = self.added.contains(&RegionId(*id));
+            match change {
+                Change::Insert { index, string, .. } => {
+                    let count = string.graphemes(true).count();
+                    if *index < region.index || (*index == region.index && !added) {
+                        region.index += count;
+                    } else if !added && *index > region.index && *index < region.range().end {
+                        region.length += count;
+                    }
+                }
+                Change::Prepend { string, .. } => {
+                    if region.index > 0 || !added {
+                        region.index += string.graphemes(true).count();
+                    }
+                }
+                Change::Remove { index, .. } => {
+                    if *index < region.index {
+                        region.index -= 1;
+                    } else if !added && region.contains(*index) {
+                        region.length -= 1;
+                    }
+                }
+                Change::Format { .. } | Change::Append { .. } | Change::Replace { .. } => {}
+            }
+        }
+    }
+
+    /// Note that the changes which added the strings tracked with `track_added` are committed.
+    pub fn committed(&mut self) {
+        self.added.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TrackedRegion, TrackedRegions};
+    use crate::password::Change;
+
+    fn insert(index: usize, string: &str) -> Change {
+        Change::Insert {
+            index,
+            string: string.into(),
+            protected: false,
+        }
+    }
+
+    #[test]
+    fn remap_regions() {
+        let mut regions = TrackedRegions::default();
+        let existing = regions.track(TrackedRegion::new(2, 3));
+        let added = regions.track_added(TrackedRegion::new(2, 2));
+        let untracked = regions.track(TrackedRegion::new(0, 1));
+        regions.untrack(untracked);
+
+        // Inserting the new string moves whatever was already there along
+        regions.remap(&insert(2, "ab"));
+        regions.committed();
+        assert_eq!(regions.get(existing), Some(&TrackedRegion::new(4, 3)));
+        assert_eq!(regions.get(added), Some(&TrackedRegion::new(2, 2)));
+        assert_eq!(regions.get(untracked), None);
+
+        // Once committed, it's moved along like any other region
+        regions.remap(&insert(2, "c"));
+        regions.remap(&Change::Prepend {
+            string: "🥚".into(),
+            protected: true,
+        });
+        regions.remap(&Change::Remove {
+            index: 3,
+            ignore_protection: false,
+        });
+        assert_eq!(regions.get(added), Some(&TrackedRegion::new(3, 2)));
+        assert_eq!(regions.get(existing), Some(&TrackedRegion::new(5, 3)));
+        assert!(regions.get(existing).unwrap().contains(7));
+        assert_eq!(regions.get(existing).unwrap().range(), 5..8);
+
+        // Changes inside a region resize it
+        regions.remap(&insert(6, "de"));
+        assert_eq!(regions.get(existing), Some(&TrackedRegion::new(5, 5)));
+        regions.remap(&Change::Remove {
+            index: 5,
+            ignore_protection: false,
+        });
+        assert_eq!(regions.get(existing), Some(&TrackedRegion::new(5, 4)));
+        // Inserting at the end is after the region
+        regions.remap(&insert(9, "f"));
+        assert_eq!(regions.get(existing), Some(&TrackedRegion::new(5, 4)));
+    }
+
+    #[test]
+    fn untracked_regions_are_dropped() {
+        let mut regions = TrackedRegions::default();
+        let ids = (0..3)
+            .map(|i| regions.track_added(TrackedRegion::new(i, 1)))
+            .collect::<Vec<_>>();
+        regions.untrack(ids[1]);
+        assert_eq!(regions.regions.len(), 2);
+        assert_eq!(regions.added, vec![ids[0], ids[2]]);
+
+        // IDs aren't reused
+        let id = regions.track(TrackedRegion::new(0, 1));
+        assert!(!ids.contains(&id));
+        assert_eq!(regions.get(ids[1]), None);
+    }
+}
@@ -0,0 +1,195 @@
+//! Recording of each playthrough as a file of events (changes made, rules violated, cursor
+//! movements), so a failed run can be replayed against the current solver and rules. The
+//! notifier follows the same events to report how the run went.
+
+use chrono::{DateTime, Local};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use crate::{
+    game::Rule,
+    logging::STORAGE,
+    notifier::Notifier,
+    password::Change,
+    storage::{from_record, to_record, Artifact, StorageError, DEFAULT_STORAGE_DIR},
+};
+
+/// Directory (within the storage directory) each playthrough is recorded to, in a JSON Lines
+/// file named with the time the playthrough started.
+const RECORDINGS_DIR: &str = "recordings";
+
+/// Something that happened during a playthrough.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The playthrough started, at the given time (RFC 3339).
+    Start { started: String },
+    /// The rules were checked.
+    Rules {
+        highest_rule: usize,
+        /// Our password at the time.
+        password: String,
+        violated: Vec<Rule>,
+    },
+    /// Changes were entered into the game, in the order given.
+    Changes { changes: Vec<Change> },
+    /// The cursor was moved.
+    Cursor { from: usize, to: usize },
+    /// The playthrough ended, with the given error if it failed.
+    End { error: Option<String> },
+}
+
+/// An event, as persisted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// Time since the start of the playthrough.
+    pub elapsed_ms: u64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+impl Artifact for EventRecord {
+    const KIND: &'static str = "recorded event";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+/// Records the events of a single playthrough to its own file, passing them on to the notifier
+/// if there is one.
+#[derive(Debug)]
+pub struct Recorder {
+    path: PathBuf,
+    /// Writer for the recording, or `None` if the file couldn't be created or written to.
+    writer: RefCell<Option<BufWriter<File>>>,
+    start: Instant,
+    notifier: Option<Notifier>,
+}
+
+impl Recorder {
+    /// Start recording a playthrough which started at `now`.
+    pub fn start(now: DateTime<Local>) -> Self {
+        let dir = Path::new(DEFAULT_STORAGE_DIR).join(RECORDINGS_DIR);
+        let path = dir.join(format!("{}.jsonl", now.format("%Y%m%d-%H%M%S")));
+        let writer = std::fs::create_dir_all(&dir)
+            .and_then(|()| File::create(&path))
+            .map(BufWriter::new)
+            .map_err(
+                |e| warn!(target: STORAGE, "Failed to create recording {}: {}", path.display(), e),
+            )
+            .ok();
+        let recorder = Recorder {
+            path,
+            writer: RefCell::new(writer),
+            start: Instant::now(),
+            notifier: None,
+        };
+        recorder.record(Event::Start {
+            started: now.to_rfc3339(),
+        });
+        recorder
+    }
+
+    /// Pass every later event on to the given notifier.
+    pub fn with_notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    pub fn notifier(&self) -> Option<&Notifier> {
+        self.notifier.as_ref()
+    }
+
+    /// Record an event. Failures are logged rather than interrupting the playthrough, and stop
+    /// any more events being written. The recording is flushed once the playthrough ends.
+    pub fn record(&self, event: Event) {
+        let record = EventRecord {
+            elapsed_ms: self.start.elapsed().as_millis() as u64,
+            event,
+        };
+        let mut writer = self.writer.borrow_mut();
+        if let Some(file) = writer.as_mut() {
+            let result = to_record(&record).and_then(|value| {
+                serde_json::to_writer(&mut *file, &value)?;
+                writeln!(file)?;
+                if matches!(record.event, Event::End { .. }) {
+                    file.flush()?;
+                }
+                Ok(())
+            });
+            if let Err(e) = result {
+                warn!(target: STORAGE, "Failed to record event to {}: {}", self.path.display(), e);
+                *writer = None;
+            }
+        }
+        drop(writer);
+        if let Some(notifier) = &self.notifier {
+            notifier.observe(&record);
+        }
+    }
+}
+
+/// Load a recording, as written by [`Recorder`].
+pub fn load(path: &Path) -> Result<Vec<EventRecord>, StorageError> {
+    let mut events = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            events.push(from_record(serde_json::from_str(&line)?)?);
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, EventRecord};
+    use crate::{
+        game::{rule::Color, Rule},
+        password::{Change, FormatChange},
+        storage::{from_record, to_record},
+    };
+
+    #[test]
+    fn event_record_round_trip() {
+        let records = [
+            EventRecord {
+                elapsed_ms: 10,
+                event: Event::Rules {
+                    highest_rule: 28,
+                    password: "abc".to_owned(),
+                    violated: vec![Rule::Hex(Color { r: 1, g: 2, b: 3 }), Rule::Number],
+                },
+            },
+            EventRecord {
+                elapsed_ms: 20,
+                event: Event::Changes {
+                    changes: vec![
+                        Change::Format {
+                            index: 1,
+                            format_change: FormatChange::BoldOn,
+                        },
+                        Change::Append {
+                            string: "1".to_owned(),
+                            protected: true,
+                        },
+                    ],
+                },
+            },
+            EventRecord {
+                elapsed_ms: 30,
+                event: Event::End { error: None },
+            },
+        ];
+        for record in records {
+            let value = to_record(&record).unwrap();
+            assert_eq!(value["schema_version"], 1);
+            assert_eq!(from_record::<EventRecord>(value).unwrap(), record);
+        }
+    }
+}
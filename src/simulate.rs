@@ -0,0 +1,475 @@
+//! Playing many simulated games in parallel with the direct driver, and summarising how the
+//! solver did, so solver changes can be checked without playing the real game.
+
+use chrono::{Local, TimeZone};
+use log::{debug, info};
+use ordered_float::NotNan;
+use rayon::prelude::*;
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    driver::{direct::DirectDriver, Driver, DriverConfig, DriverError},
+    game::{
+        clock::{FixedClock, SharedClock},
+        data::{CAPTCHAS, CHESS_PUZZLES, GEO_GAMES},
+        lookup::{OfflineLookup, SharedLookup},
+        rule::{Color, Coords, RuleInstance, RuleKind},
+    },
+    logging::SIMULATE,
+    solver::Solver,
+    youtube::{database::videos, id_penalty},
+};
+
+/// Number of failing seeds to list in the summary.
+const MAX_LISTED_SEEDS: usize = 10;
+
+/// Levels of each channel in the grid of colors the strict check plays against.
+const COLOR_LEVELS: [u8; 3] = [0, 128, 255];
+
+/// Combinations checked between progress updates in the strict check.
+const STRICT_PROGRESS_INTERVAL: usize = 10_000;
+
+/// The outcome of a single simulated game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameOutcome {
+    /// Seed the game was played with.
+    pub seed: u64,
+    /// The rule which couldn't be satisfied, if the game was lost.
+    pub failed_rule: Option<RuleKind>,
+    /// Any other error which ended the game.
+    pub error: Option<String>,
+    /// Graphemes in the password at the end of the game.
+    pub final_length: usize,
+    /// Changes made to the password during the game.
+    pub changes: usize,
+}
+
+impl GameOutcome {
+    pub fn won(&self) -> bool {
+        self.failed_rule.is_none() && self.error.is_none()
+    }
+}
+
+/// Statistics over a set of simulated games.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Summary {
+    pub games: usize,
+    pub wins: usize,
+    /// How many games were lost on each rule.
+    pub failed_rules: BTreeMap<RuleKind, usize>,
+    /// Games which ended with an error other than an unsatisfiable rule.
+    pub errors: usize,
+    pub mean_final_length: f64,
+    pub mean_changes: f64,
+    /// Seeds of (some of) the games which weren't won, for replaying them.
+    pub failed_seeds: Vec<u64>,
+}
+
+impl Summary {
+    pub fn from_outcomes(outcomes: &[GameOutcome]) -> Self {
+        let mut summary = Summary {
+            games: outcomes.len(),
+            ..Default::default()
+        };
+        if outcomes.is_empty() {
+            return summary;
+        }
+        for outcome in outcomes {
+            if outcome.won() {
+                summary.wins += 1;
+            } else if summary.failed_seeds.len() < MAX_LISTED_SEEDS {
+                summary.failed_seeds.push(outcome.seed);
+            }
+            if let Some(rule) = outcome.failed_rule {
+                *summary.failed_rules.entry(rule).or_default() += 1;
+            }
+            if outcome.error.is_some() {
+                summary.errors += 1;
+            }
+        }
+        let games = outcomes.len() as f64;
+        summary.mean_final_length =
+            outcomes.iter().map(|o| o.final_length).sum::<usize>() as f64 / games;
+        summary.mean_changes = outcomes.iter().map(|o| o.changes).sum::<usize>() as f64 / games;
+        summary
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rate = if self.games == 0 {
+            0.0
+        } else {
+            self.wins as f64 / self.games as f64 * 100.0
+        };
+        writeln!(
+            f,
+            "Won {} of {} games ({:.1}%)",
+            self.wins, self.games, rate
+        )?;
+        writeln!(
+            f,
+            "Mean final password length {:.1}, mean changes {:.1}",
+            self.mean_final_length, self.mean_changes
+        )?;
+        let mut failed_rules = self.failed_rules.iter().collect::<Vec<_>>();
+        failed_rules.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (rule, count) in failed_rules {
+            writeln!(f, "  Failed on {:?}: {}", rule, count)?;
+        }
+        if self.errors > 0 {
+            writeln!(f, "  Other errors: {}", self.errors)?;
+        }
+        if !self.failed_seeds.is_empty() {
+            writeln!(f, "Failing seeds: {:?}", self.failed_seeds)?;
+        }
+        Ok(())
+    }
+}
+
+/// Play a single simulated game with the given seed.
+pub fn play_one(config: &DriverConfig, seed: u64) -> Result<GameOutcome, DriverError> {
+    play(config, seed, None)
+}
+
+/// Play a single simulated game with the given seed, with the instance-specific rules of the
+/// given combination if there is one.
+fn play(
+    config: &DriverConfig,
+    seed: u64,
+    combination: Option<&Combination>,
+) -> Result<GameOutcome, DriverError> {
+    let mut config = config.clone();
+    config.seed = Some(seed);
+    let solver = Solver::with_seed(seed).with_strategy(config.strategy.into());
+    let mut driver = DirectDriver::new(solver, config)?;
+    for instance in combination.map(Combination::instances).unwrap_or_default() {
+        driver.set_instance(instance);
+    }
+    let result = driver.play();
+    let mut outcome = GameOutcome {
+        seed,
+        failed_rule: None,
+        error: None,
+        final_length: driver.password_length(),
+        changes: driver.changes_made(),
+    };
+    match result {
+        Ok(()) => debug!(target: SIMULATE, "Simulated game with seed {} won", seed),
+        Err(DriverError::CouldNotSatisfyRule(rule)) => {
+            info!(target: SIMULATE, "Simulated game with seed {} failed on {:?}", seed, rule);
+            outcome.failed_rule = Some(rule.kind());
+        }
+        Err(e) => {
+            info!(target: SIMULATE, "Simulated game with seed {} failed: {}", seed, e);
+            outcome.error = Some(e.to_string());
+        }
+    }
+    Ok(outcome)
+}
+
+/// Play `iterations` simulated games in parallel, with consecutive seeds from `first_seed`.
+pub fn run(
+    config: &DriverConfig,
+    first_seed: u64,
+    iterations: usize,
+) -> Result<Summary, DriverError> {
+    let outcomes = (0..iterations as u64)
+        .into_par_iter()
+        .map(|i| play_one(config, first_seed.wrapping_add(i)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Summary::from_outcomes(&outcomes))
+}
+
+/// The colors the strict check plays against: every combination of `COLOR_LEVELS`.
+fn color_grid() -> Vec<Color> {
+    let mut colors = Vec::new();
+    for r in COLOR_LEVELS {
+        for g in COLOR_LEVELS {
+            for b in COLOR_LEVELS {
+                colors.push(Color { r, g, b });
+            }
+        }
+    }
+    colors
+}
+
+/// The video duration the YouTube rule asks for when playing combinations: the one with the
+/// best video in the database. The real bot searches YouTube for a video which suits the rest
+/// of the password, which can't be done offline, and the database only has a few videos of
+/// each duration, so the rule is pinned rather than failing on whichever video a seed gets.
+fn strict_video_duration() -> u32 {
+    videos()
+        .iter()
+        .min_by_key(|(seconds, id)| (id_penalty(id), *seconds))
+        .map(|(seconds, _)| seconds)
+        .expect("the video database is empty")
+}
+
+/// The instance-specific rules of a game which are drawn from the game's own data: the
+/// captcha, chess puzzle and location (as indices into `CAPTCHAS`, `CHESS_PUZZLES` and
+/// `GEO_GAMES`), and the color (an index into the color grid).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Combination {
+    pub captcha: usize,
+    pub chess: usize,
+    pub geo: usize,
+    pub color: usize,
+}
+
+impl Combination {
+    /// The rule instances to play for the combination, along with the pinned YouTube video
+    /// length (see `strict_video_duration`).
+    fn instances(&self) -> Vec<RuleInstance> {
+        let (lat, long) = GEO_GAMES[self.geo].coordindates;
+        vec![
+            RuleInstance::Captcha(CAPTCHAS[self.captcha].answer.to_owned()),
+            RuleInstance::Chess(CHESS_PUZZLES[self.chess].fen.clone()),
+            RuleInstance::Geo(Coords {
+                lat: NotNan::new(lat).unwrap(),
+                long: NotNan::new(long).unwrap(),
+            }),
+            RuleInstance::Hex(color_grid()[self.color].clone()),
+            RuleInstance::Youtube(strict_video_duration()),
+        ]
+    }
+
+    /// Every combination.
+    pub fn all() -> impl Iterator<Item = Combination> + Send {
+        let colors = color_grid().len();
+        (0..CAPTCHAS.len()).flat_map(move |captcha| {
+            (0..CHESS_PUZZLES.len()).flat_map(move |chess| {
+                (0..GEO_GAMES.len()).flat_map(move |geo| {
+                    (0..colors).map(move |color| Combination {
+                        captcha,
+                        chess,
+                        geo,
+                        color,
+                    })
+                })
+            })
+        })
+    }
+
+    /// Just enough combinations to play every captcha, chess puzzle, location and color at
+    /// least once.
+    #[cfg(test)]
+    pub fn covering() -> Vec<Combination> {
+        let lens = [
+            CAPTCHAS.len(),
+            CHESS_PUZZLES.len(),
+            GEO_GAMES.len(),
+            color_grid().len(),
+        ];
+        (0..lens.into_iter().max().unwrap_or_default())
+            .map(|i| Combination {
+                captcha: i % lens[0],
+                chess: i % lens[1],
+                geo: i % lens[2],
+                color: i % lens[3],
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Combination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "captcha {} ({:?}), chess puzzle {} ({:?}), location {} ({}), color {}",
+            self.captcha,
+            CAPTCHAS[self.captcha].answer,
+            self.chess,
+            CHESS_PUZZLES[self.chess].fen,
+            self.geo,
+            GEO_GAMES[self.geo].country,
+            color_grid()[self.color].to_hex_string()
+        )
+    }
+}
+
+/// The combinations which couldn't be solved in the strict check.
+#[derive(Debug, Default)]
+pub struct StrictReport {
+    /// Number of combinations played.
+    pub combinations: usize,
+    /// The combinations which weren't won, and how they went.
+    pub unsolvable: Vec<(Combination, GameOutcome)>,
+}
+
+impl fmt::Display for StrictReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} of {} combinations couldn't be solved",
+            self.unsolvable.len(),
+            self.combinations
+        )?;
+        for (combination, outcome) in &self.unsolvable {
+            let reason = match (&outcome.failed_rule, &outcome.error) {
+                (Some(rule), _) => format!("failed on {:?}", rule),
+                (None, Some(error)) => error.clone(),
+                (None, None) => "won".into(),
+            };
+            writeln!(f, "  {}: {}", combination, reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// The message a panic was raised with.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| payload.downcast_ref::<&str>().copied())
+        .unwrap_or("unknown")
+}
+
+/// Play each of the given combinations once with the same seed, without the network and with
+/// the clock stopped at 10:00 (so the time string only adds 1 to the digit sum) on a day the
+/// bundled Wordle answers cover, and report which can't be solved.
+pub fn run_strict(
+    config: &DriverConfig,
+    seed: u64,
+    combinations: impl Iterator<Item = Combination> + Send,
+) -> Result<StrictReport, DriverError> {
+    let mut config = config.clone();
+    config.lookup = SharedLookup::new(Arc::new(OfflineLookup));
+    config.clock = SharedClock::new(Arc::new(FixedClock(
+        Local.with_ymd_and_hms(2021, 7, 1, 10, 0, 0).unwrap(),
+    )));
+
+    let played = AtomicUsize::new(0);
+    let mut unsolvable = combinations
+        .par_bridge()
+        .map(|combination| {
+            // A panic is reported like any other way of losing, rather than ending the check
+            let outcome =
+                panic::catch_unwind(AssertUnwindSafe(|| play(&config, seed, Some(&combination))))
+                    .unwrap_or_else(|payload| {
+                    Ok(GameOutcome {
+                        seed,
+                        failed_rule: None,
+                        error: Some(format!("panicked: {}", panic_message(&*payload))),
+                        final_length: 0,
+                        changes: 0,
+                    })
+                })?;
+            let played = played.fetch_add(1, Ordering::Relaxed) + 1;
+            if played.is_multiple_of(STRICT_PROGRESS_INTERVAL) {
+                info!(target: SIMULATE, "Played {} combinations", played);
+            }
+            Ok((!outcome.won()).then_some((combination, outcome)))
+        })
+        .filter_map(Result::transpose)
+        .collect::<Result<Vec<_>, DriverError>>()?;
+    unsolvable.sort_by_key(|(c, _)| (c.captcha, c.chess, c.geo, c.color));
+    Ok(StrictReport {
+        combinations: played.into_inner(),
+        unsolvable,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{color_grid, run_strict, Combination, GameOutcome, Summary};
+    use crate::{
+        driver::DriverConfig,
+        game::{
+            data::{CAPTCHAS, CHESS_PUZZLES, GEO_GAMES},
+            rule::RuleKind,
+        },
+    };
+
+    fn outcome(seed: u64, failed_rule: Option<RuleKind>, final_length: usize) -> GameOutcome {
+        GameOutcome {
+            seed,
+            failed_rule,
+            error: None,
+            final_length,
+            changes: 10,
+        }
+    }
+
+    #[test]
+    fn summarise_outcomes() {
+        let summary = Summary::from_outcomes(&[
+            outcome(1, None, 100),
+            outcome(2, Some(RuleKind::Youtube), 50),
+            outcome(3, Some(RuleKind::Youtube), 60),
+            outcome(4, Some(RuleKind::Chess), 30),
+        ]);
+        assert_eq!((summary.games, summary.wins), (4, 1));
+        assert_eq!(summary.failed_rules[&RuleKind::Youtube], 2);
+        assert_eq!(summary.failed_rules[&RuleKind::Chess], 1);
+        assert_eq!(summary.mean_final_length, 60.0);
+        assert_eq!(summary.mean_changes, 10.0);
+        assert_eq!(summary.failed_seeds, vec![2, 3, 4]);
+
+        assert_eq!(Summary::from_outcomes(&[]), Summary::default());
+    }
+
+    #[test]
+    fn covering_combinations() {
+        let combinations = Combination::covering();
+        for (len, index) in [
+            (
+                CAPTCHAS.len(),
+                (|c: &Combination| c.captcha) as fn(&Combination) -> usize,
+            ),
+            (CHESS_PUZZLES.len(), |c| c.chess),
+            (GEO_GAMES.len(), |c| c.geo),
+            (color_grid().len(), |c| c.color),
+        ] {
+            assert!((0..len).all(|i| combinations.iter().any(|c| index(c) == i)));
+        }
+        assert_eq!(combinations.len(), CHESS_PUZZLES.len());
+        assert_eq!(
+            Combination::all().take(2).last(),
+            Some(Combination {
+                captcha: 0,
+                chess: 0,
+                geo: 0,
+                color: 1
+            })
+        );
+    }
+
+    /// The first few covering combinations can be solved, as a quick check of the strict
+    /// simulation on every run.
+    #[test]
+    fn strict_check_solves_combinations() {
+        let report = run_strict(
+            &DriverConfig::default(),
+            0,
+            Combination::covering().into_iter().take(3),
+        )
+        .unwrap();
+        assert_eq!(report.combinations, 3);
+        assert!(report.unsolvable.is_empty(), "{}", report);
+    }
+
+    /// Every captcha, chess puzzle, location and color can be solved, each in at least one
+    /// combination. Ignored by default, as it takes minutes.
+    #[test]
+    #[ignore]
+    fn covering_combinations_solvable() {
+        let report = run_strict(
+            &DriverConfig::default(),
+            0,
+            Combination::covering().into_iter(),
+        )
+        .unwrap();
+        assert!(report.unsolvable.is_empty(), "{}", report);
+    }
+}
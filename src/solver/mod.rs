@@ -1,59 +1,143 @@
-use chrono::prelude::*;
-use lazy_static::lazy_static;
-use log::{debug, info};
+use log::{debug, info, warn};
 use numerals::roman::Roman;
-use rand::seq::SliceRandom;
-use rand::thread_rng;
-use serde::Deserialize;
-use std::collections::{HashMap, HashSet};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use strum::IntoEnumIterator;
 use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
     game::{
-        helpers::{
-            get_country_from_coordinates, get_moon_phase, get_optimal_move, get_wordle_answer,
-            is_prime,
-        },
+        helpers::{get_moon_phase, get_optimal_move, is_prime},
         GameState,
         {
-            rule::{AFFIRMATIONS, MONTHS, SPONSORS, VOWELS},
+            rule::{RuleKind, AFFIRMATIONS, MONTHS, SPONSORS, STRENGTH_EMOJIS_REQUIRED, VOWELS},
             Rule,
         },
     },
+    logging::SOLVER,
     password::{
-        helpers::{get_digits, get_elements, get_letters, get_roman_numerals},
-        Change, MutablePassword,
+        helpers::{append_strength, get_digits, get_letters, get_roman_numerals},
+        keystrokes, Change, ChangeError, MutablePassword, Password, RegionId, TrackedRegion,
         {
             format::{FontFamily, FontSize, FontSizeIter},
             FormatChange,
         },
     },
+    youtube::database::videos,
 };
+use strategy::SharedStrategy;
 
+pub mod reroll;
+mod resync;
+pub mod risk;
+pub mod sacrifice;
+pub mod scoring;
+pub mod strategy;
+mod sums;
 #[cfg(test)]
 mod tests;
 
-#[derive(Deserialize)]
-struct Video {
-    id: &'static str,
-    duration: u32,
-}
+/// Number of vowels we expect to be appended (and so made bold) after the twice italic rule is
+/// solved, e.g. in the hex color, which can contain up to 6 a's and e's.
+const FUTURE_BOLD_VOWELS: usize = 6;
 
-lazy_static! {
-    pub static ref VIDEOS: HashMap<u32, &'static str> = {
-        let videos: Vec<Video> =
-            serde_json::from_str(include_str!("../youtube/videos.json")).unwrap();
+/// Expendable graphemes either side of the safe zone, so fire spreading from elsewhere in the
+/// password has to burn through them before it reaches anything we care about. Hyphens don't
+/// count towards any rule (other than the special character one, which they only help).
+const SAFE_ZONE_FILLER: &str = "---";
 
-        let mut m = HashMap::new();
-        for video in &videos {
-            m.insert(video.duration, video.id);
+/// The videos which could satisfy the YouTube rule with the given duration, best first,
+/// leaving out IDs which contain any of the sacrificed letters (in either case, as the
+/// sacrifice rule ignores case). The game allows the duration to be off by a second, so the
+/// candidates for the exact duration are followed by those for the neighbouring durations,
+/// each lowest penalty first. If there's no video at all, the gap in the video database is
+/// logged, so it can be filled.
+pub fn video_candidates(seconds: u32, sacrificed_letters: &[char]) -> Vec<String> {
+    let videos = videos();
+    let candidates = [
+        Some(seconds),
+        seconds.checked_sub(1),
+        seconds.checked_add(1),
+    ]
+    .into_iter()
+    .flatten()
+    .flat_map(|duration| videos.candidates(duration))
+    .filter(|video_id| {
+        let usable = !has_sacrificed_letter(video_id, sacrificed_letters);
+        if !usable {
+            debug!(
+                target: SOLVER,
+                "Skipping video {} as it contains a sacrificed letter",
+                video_id
+            );
         }
-        m
+        usable
+    })
+    .cloned()
+    .collect::<Vec<_>>();
+
+    if candidates.is_empty() {
+        if let Some((first, last)) = videos.missing_range(seconds) {
+            warn!(
+                target: SOLVER,
+                "No video of length {}s in the {} video database, which is missing {}s to {}",
+                seconds,
+                videos.source(),
+                first,
+                last.map_or("the end".to_owned(), |last| format!("{}s", last))
+            );
+        }
+    }
+    candidates
+}
+
+/// Whether the given video ID contains any of the sacrificed letters, in either case.
+fn has_sacrificed_letter(video_id: &str, sacrificed_letters: &[char]) -> bool {
+    video_id
+        .to_lowercase()
+        .chars()
+        .any(|ch| sacrificed_letters.contains(&ch))
+}
+
+/// The shortest URL for the given YouTube video.
+pub fn youtube_url(video_id: &str) -> String {
+    format!("youtu.be/{}", video_id)
+}
+
+/// The current time, as the time rule wants it (e.g., "4:08").
+fn current_time_string(game_state: &GameState) -> String {
+    game_state
+        .clock
+        .now()
+        .format("%l:%M")
+        .to_string()
+        .trim()
+        .to_owned()
+}
+
+/// The shortest prime length of at least `min_length` (and three digits, to fit the length
+/// string) whose digits add up to no more than `digit_budget`, or failing that, whichever has
+/// the smallest digit sum. None if there's no such length.
+fn goal_length(min_length: usize, digit_budget: u32) -> Option<usize> {
+    let digit_sum = |length: &usize| {
+        length
+            .to_string()
+            .chars()
+            .filter_map(|c| c.to_digit(10))
+            .sum::<u32>()
     };
+    let mut candidates = (min_length.max(100)..1000).filter(|l| is_prime(*l));
+    let smallest_sum = candidates.clone().min_by_key(digit_sum);
+    candidates
+        .find(|l| digit_sum(l) <= digit_budget)
+        .or(smallest_sum)
 }
 
-#[derive(Default)]
+/// Solves the rules of the game by planning changes to the password. Saved along with the game
+/// state in snapshots of a run (except for the parts set up from the driver config, and the
+/// random number generator, which is reseeded).
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Solver {
     /// The current password as entered into the game.
     pub password: MutablePassword,
@@ -61,41 +145,352 @@ pub struct Solver {
     pub violated_rules: Vec<Rule>,
     /// Letters we've chosen to sacrifice.
     pub sacrificed_letters: Vec<char>,
-    /// Grapheme index and length of the password length string.
-    pub length_string: Option<InnerString>,
-    /// Grapheme index and length of the time string.
-    pub time_string: Option<InnerString>,
+    /// Tracked region of the password length string.
+    pub length_string: Option<RegionId>,
+    /// Tracked region of the time string.
+    pub time_string: Option<RegionId>,
+    /// Tracked region of Paul (the egg, and later the chicken), once he's in the password.
+    pub paul: Option<RegionId>,
     /// Goal password length we've chosen.
     pub goal_length: Option<usize>,
+    /// Tracked region of the safe zone, which keeps fragile protected strings together,
+    /// surrounded by filler (see `SAFE_ZONE_FILLER`).
+    pub safe_zone: Option<RegionId>,
+    /// The fragile protected strings in the safe zone, with their tracked regions.
+    pub regions: Vec<(RegionId, Region)>,
+    /// Source of random choices (e.g., which month to use).
+    #[serde(skip)]
+    pub rng: SolverRng,
+    /// How to choose between equally valid solutions.
+    #[serde(skip)]
+    pub strategy: SharedStrategy,
+    /// Strings with outside answers we've added for rules (e.g., the Wordle answer), which
+    /// we might need to add again.
+    pub known_strings: BTreeMap<RuleKind, String>,
 }
 
-/// Essentially a string slice in the password.
-#[derive(Debug)]
-pub struct InnerString {
-    /// Grapheme index of the first grapheme in the string.
-    index: usize,
-    /// Length of the string in grapheme clusters.
-    length: usize,
+/// The solver's random number generator. Randomly seeded by default. Only advanced through
+/// `&mut Solver`, so cloning a solver reproduces its choices.
+#[derive(Debug, Clone)]
+pub struct SolverRng(StdRng);
+
+impl SolverRng {
+    pub fn seed_from_u64(seed: u64) -> Self {
+        SolverRng(StdRng::seed_from_u64(seed))
+    }
+
+    /// Choose one of the given items.
+    fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        items.choose(&mut self.0).expect("nothing to choose from")
+    }
 }
 
-impl InnerString {
-    pub fn new(index: usize, length: usize) -> Self {
-        InnerString { index, length }
+impl Default for SolverRng {
+    fn default() -> Self {
+        SolverRng(StdRng::from_entropy())
     }
 }
 
+/// Names for the fragile protected strings kept in the safe zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegionName {
+    Captcha,
+    Country,
+    ChessMove,
+    YoutubeUrl,
+    HexColor,
+}
+
+/// A fragile protected string, along with what it should contain, so it can be repaired if
+/// the game damages it (e.g., with fire).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Region {
+    pub name: RegionName,
+    contents: String,
+}
+
+/// Number of passes over the rules when planning them together, as solving a later rule can
+/// break an earlier one.
+const SOLVE_ALL_PASSES: usize = 3;
+
+/// How well a sequence of plans does: the number of rules left violated, the resulting
+/// password length, and the estimated keystrokes to enter it. Lower is better.
+type PlanScore = (usize, usize, usize);
+
+/// A solution to a rule: the changes to make to the password, and the updates to the solver's
+/// own bookkeeping which go along with them.
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    pub changes: Vec<Change>,
+    pub state_delta: StateDelta,
+}
+
+/// Updates to the solver's bookkeeping from a plan. `None` fields are left as they are. Regions
+/// are where the strings the plan adds will end up, and are tracked from then on.
+#[derive(Debug, Clone, Default)]
+pub struct StateDelta {
+    pub sacrificed_letters: Option<Vec<char>>,
+    pub goal_length: Option<usize>,
+    pub length_string: Option<TrackedRegion>,
+    pub time_string: Option<TrackedRegion>,
+    pub paul: Option<TrackedRegion>,
+    pub safe_zone: Option<TrackedRegion>,
+    /// Regions added to the safe zone.
+    pub regions: Vec<(TrackedRegion, Region)>,
+    /// A string with an outside answer added for a rule.
+    pub known_string: Option<(RuleKind, String)>,
+    /// The rule the plan solves, which owns any protected blocks it adds.
+    pub rule: Option<RuleKind>,
+}
+
 impl Solver {
-    /// Produce a change (or series of changes) which solves the given rule.
-    /// If no solution can be found, return None.
-    pub fn solve_rule(
+    /// A solver whose random choices are made from the given seed, so a run can be repeated.
+    pub fn with_seed(seed: u64) -> Self {
+        Solver {
+            rng: SolverRng::seed_from_u64(seed),
+            ..Default::default()
+        }
+    }
+
+    /// Use the given strategy for choosing between equally valid solutions.
+    pub fn with_strategy(mut self, strategy: SharedStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Produce a change (or series of changes) which solves the given rule, and update the
+    /// solver's bookkeeping to match. If no valid solution can be found, return None.
+    pub fn solve_rule(&mut self, rule: &Rule, game_state: &GameState) -> Option<Vec<Change>> {
+        let plan = self.plan_rule(rule, game_state)?;
+        if let Err(e) = self.password.preview_changes(&plan.changes) {
+            warn!(target: SOLVER, "Invalid plan for rule {:?}: {}", rule, e);
+            return None;
+        }
+        self.apply_plan(&plan);
+        Some(plan.changes)
+    }
+
+    /// Produce changes which bring the time string up to date if the minute has rolled over
+    /// since it was entered, and update the solver's bookkeeping to match. Returns None if
+    /// there's no time string, or it's already up to date.
+    pub fn refresh_time_string(&mut self, game_state: &GameState) -> Option<Vec<Change>> {
+        let time_string = self.tracked(self.time_string)?;
+        let entered = self
+            .password
+            .as_str()
+            .graphemes(true)
+            .skip(time_string.index)
+            .take(time_string.length)
+            .collect::<String>();
+        if entered == current_time_string(game_state) {
+            return None;
+        }
+        debug!(target: SOLVER, "Time string {:?} is out of date", entered);
+        self.solve_rule(&Rule::Time, game_state)
+    }
+
+    /// Update the solver's bookkeeping for a plan which is being carried out. The plan's
+    /// changes still need to be made to the password separately.
+    pub fn apply_plan(&mut self, plan: &Plan) {
+        let delta = plan.state_delta.clone();
+        self.password
+            .label_new_blocks(delta.rule.map(|kind| format!("{:?}", kind)));
+        if let Some(sacrificed_letters) = delta.sacrificed_letters {
+            self.sacrificed_letters = sacrificed_letters;
+        }
+        if delta.goal_length.is_some() {
+            self.goal_length = delta.goal_length;
+        }
+        for (slot, region) in [
+            (&mut self.length_string, delta.length_string),
+            (&mut self.time_string, delta.time_string),
+            (&mut self.paul, delta.paul),
+            (&mut self.safe_zone, delta.safe_zone),
+        ] {
+            if let Some(region) = region {
+                if let Some(old) = slot.replace(self.password.track_added(region)) {
+                    self.password.untrack(old);
+                }
+            }
+        }
+        for (region, contents) in delta.regions {
+            self.regions
+                .push((self.password.track_added(region), contents));
+        }
+        if let Some((kind, string)) = delta.known_string {
+            self.known_strings.insert(kind, string);
+        }
+    }
+
+    /// The current location of the given tracked region, if there is one.
+    fn tracked(&self, id: Option<RegionId>) -> Option<&TrackedRegion> {
+        self.password.tracked(id?)
+    }
+
+    /// Grapheme index of Paul, if he's in the password.
+    pub fn paul_index(&self) -> Option<usize> {
+        self.tracked(self.paul).map(|paul| paul.index)
+    }
+
+    /// Make the changes in a plan to the password, and update the bookkeeping to match.
+    /// Returns an error if any of the changes is invalid, in which case nothing is changed.
+    pub fn carry_out(&mut self, plan: &Plan) -> Result<(), ChangeError> {
+        self.password.preview_changes(&plan.changes)?;
+        self.apply_plan(plan);
+        for change in plan.changes.iter().cloned() {
+            self.password.queue_change(change);
+        }
+        self.password.commit_changes()
+    }
+
+    /// Plan solutions to all the given rules together. Rather than greedily solving one rule
+    /// at a time, simulates solving them in different orders, and with each alternative
+    /// solution for rules which have several (e.g., the sponsor), and picks whichever leaves
+    /// the shortest password with every rule satisfied. Returns the plans in the order they
+    /// should be carried out (each relative to the password after the ones before it), or None
+    /// if the rules can't all be satisfied together.
+    pub fn solve_all(&mut self, rules: &[Rule], game_state: &GameState) -> Option<Vec<Plan>> {
+        let mut ascending = rules.to_vec();
+        ascending.sort_by_key(Rule::kind);
+        let mut descending = ascending.clone();
+        descending.reverse();
+
+        let mut best = None;
+        for order in [ascending, descending] {
+            let steps = (0..SOLVE_ALL_PASSES)
+                .flat_map(|_| order.iter().cloned())
+                .collect::<Vec<_>>();
+            self.search_plans(&steps, rules, game_state, Vec::new(), 0, &mut best);
+        }
+        let ((conflicts, length, keystrokes), plans) = best?;
+        debug!(
+            target: SOLVER,
+            "Best plan for {:?} leaves {} conflicts and length {}, in {} keystrokes",
+            rules,
+            conflicts,
+            length,
+            keystrokes
+        );
+        (conflicts == 0).then_some(plans)
+    }
+
+    /// Plan solutions to the given rules together, as `solve_all` does, along with any of the
+    /// other rules (e.g., the ones already satisfied) whose part of the password the plans
+    /// would touch and break. Returns the plans, and every rule they were made for, or None if
+    /// the rules can't all be satisfied together.
+    pub fn solve_all_affected(
         &mut self,
-        rule: &Rule,
+        rules: &[Rule],
+        others: &[Rule],
         game_state: &GameState,
-        bugs: usize,
-    ) -> Option<Vec<Change>> {
-        debug!("Solving rule {:?}", rule);
+    ) -> Option<(Vec<Plan>, Vec<Rule>)> {
+        let mut batch = rules.to_vec();
+        for _ in 0..SOLVE_ALL_PASSES {
+            let plans = self.solve_all(&batch, game_state)?;
+            let mut simulated = self.clone();
+            for plan in &plans {
+                simulated.carry_out(plan).ok()?;
+            }
+            let broken = others
+                .iter()
+                .filter(|rule| {
+                    !batch.iter().any(|r| r.kind() == rule.kind())
+                        && !rule.validate(simulated.password.raw_password(), game_state)
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if broken.is_empty() {
+                return Some((plans, batch));
+            }
+            debug!(
+                target: SOLVER,
+                "Plans for {:?} would break {:?}, adding them to the batch",
+                batch,
+                broken
+            );
+            batch.extend(broken);
+        }
+        None
+    }
+
+    /// Try every alternative for each of the remaining steps, keeping the plans which leave
+    /// the fewest violated rules, then the shortest password, then take the fewest keystrokes
+    /// to enter.
+    fn search_plans(
+        &mut self,
+        steps: &[Rule],
+        rules: &[Rule],
+        game_state: &GameState,
+        plans: Vec<Plan>,
+        keystrokes: usize,
+        best: &mut Option<(PlanScore, Vec<Plan>)>,
+    ) {
+        let Some((rule, steps)) = steps.split_first() else {
+            let conflicts = rules
+                .iter()
+                .filter(|rule| !rule.validate(self.password.raw_password(), game_state))
+                .count();
+            let score = (conflicts, self.password.len(), keystrokes);
+            if best
+                .as_ref()
+                .is_none_or(|(best_score, _)| score < *best_score)
+            {
+                *best = Some((score, plans));
+            }
+            return;
+        };
+
+        let alternatives = self.plan_alternatives(rule, game_state);
+        if alternatives.is_empty() {
+            // Leave it violated, and let the score reflect that
+            self.search_plans(steps, rules, game_state, plans, keystrokes, best);
+            return;
+        }
+        for plan in alternatives {
+            let mut solver = self.clone();
+            if let Err(e) = solver.carry_out(&plan) {
+                warn!(target: SOLVER, "Skipping invalid plan for rule {:?}: {}", rule, e);
+                continue;
+            }
+            // Assume the cursor is left at the end of the password between plans
+            let length = self.password.len();
+            let keystrokes = keystrokes + keystrokes::estimate(&plan.changes, length, length);
+            let mut plans = plans.clone();
+            if !plan.changes.is_empty() {
+                plans.push(plan);
+            }
+            solver.search_plans(steps, rules, game_state, plans, keystrokes, best);
+        }
+    }
+
+    /// Every way we know of to solve the given rule.
+    fn plan_alternatives(&mut self, rule: &Rule, game_state: &GameState) -> Vec<Plan> {
+        match rule {
+            Rule::Sponsors if !rule.validate(self.password.raw_password(), game_state) => SPONSORS
+                .iter()
+                .map(|sponsor| Plan {
+                    changes: vec![Change::Append {
+                        protected: true,
+                        string: sponsor.to_string(),
+                    }],
+                    state_delta: StateDelta {
+                        rule: Some(RuleKind::Sponsors),
+                        ..Default::default()
+                    },
+                })
+                .collect(),
+            _ => self.plan_rule(rule, game_state).into_iter().collect(),
+        }
+    }
+
+    /// Plan a solution to the given rule, without changing anything but the solver's random
+    /// number generator (so the same solver state always makes the same choices). If no
+    /// solution can be found, return None.
+    pub fn plan_rule(&mut self, rule: &Rule, game_state: &GameState) -> Option<Plan> {
+        debug!(target: SOLVER, "Solving rule {:?}", rule);
 
         let mut changes = Vec::new();
+        let mut delta = StateDelta::default();
 
         match rule {
             Rule::Wingdings | Rule::IncludeLength | Rule::PrimeLength => {
@@ -104,7 +499,7 @@ impl Solver {
             }
             _ => {
                 if rule.validate(self.password.raw_password(), game_state) {
-                    return Some(changes);
+                    return Some(Plan::default());
                 }
             }
         }
@@ -135,88 +530,9 @@ impl Solver {
                     string: "!".into(),
                 });
             }
-            Rule::Digits => {
-                let digits = {
-                    let mut d = get_digits(self.password.as_str());
-                    // For the sum, we don't care about the digit 0
-                    d.retain(|(d, _)| *d > 0);
-                    d
-                };
-                let mut digits_sum = digits
-                    .iter()
-                    .map(|(d, _)| d)
-                    .copied()
-                    .reduce(|sum, d| sum + d)
-                    .unwrap_or_default();
-                if digits_sum > 25 {
-                    // Need to remove or reduce digits
-                    let mut unprotected_digits = digits
-                        .iter()
-                        .filter(|(_, i)| !self.password.protected_graphemes()[*i])
-                        .collect::<Vec<_>>();
-
-                    let unprotected_sum = unprotected_digits
-                        .iter()
-                        .map(|(d, _)| d)
-                        .copied()
-                        .reduce(|sum, d| sum + d)
-                        .unwrap_or_default();
-                    if digits_sum - unprotected_sum > 25 {
-                        // The digits in strings which must appear in the password
-                        // sum to more than 25 :(
-                        // There are solutions here, but for now, just bail
-                        return None;
-                    }
-
-                    // We have a number of digits, and we need to reduce their sum by `to_reduce`
-                    let mut to_reduce = digits_sum - 25;
-                    unprotected_digits.sort_by(|a, b| a.0.cmp(&b.0).reverse());
-
-                    // First remove digits to reduce the sum, largest first
-                    let mut removed_digits = Vec::new();
-                    for (d, i) in &unprotected_digits {
-                        if *d <= to_reduce {
-                            changes.push(Change::Remove {
-                                index: *i,
-                                ignore_protection: false,
-                            });
-                            removed_digits.push(i);
-                            to_reduce -= d;
-                            if to_reduce == 0 {
-                                break;
-                            }
-                        }
-                    }
-                    unprotected_digits.retain(|(_, i)| !removed_digits.contains(&i));
-
-                    // If the sum is still too big, reduce an arbitrary digit appropriately
-                    if to_reduce > 0 {
-                        let (digit, i) = unprotected_digits[0];
-                        let new_digit = digit - to_reduce;
-                        changes.push(Change::Replace {
-                            index: *i,
-                            new_grapheme: new_digit.to_string(),
-                            ignore_protection: false,
-                        });
-                    }
-                } else {
-                    // Just add the largest digits possible until we hit 25
-                    let mut append = String::new();
-                    while digits_sum < 25 {
-                        let next_digit = (25 - digits_sum).min(9);
-                        append.push_str(&next_digit.to_string());
-                        digits_sum += next_digit;
-                    }
-                    changes.push(Change::Append {
-                        protected: false,
-                        string: append,
-                    });
-                }
-            }
+            Rule::Digits | Rule::AtomicNumber => changes = self.plan_sums(rule, game_state)?,
             Rule::Month => {
-                // let month = "may";
-                let mut rng = thread_rng();
-                let month = MONTHS.choose(&mut rng).unwrap();
+                let month = self.strategy.choose(&MONTHS, &mut self.rng);
                 changes.push(Change::Append {
                     protected: true,
                     string: month.to_string(),
@@ -229,9 +545,7 @@ impl Solver {
                 });
             }
             Rule::Sponsors => {
-                // let sponsor = "pepsi";
-                let mut rng = thread_rng();
-                let sponsor = SPONSORS.choose(&mut rng).unwrap();
+                let sponsor = self.strategy.choose(&SPONSORS, &mut self.rng);
                 changes.push(Change::Append {
                     protected: true,
                     string: sponsor.to_string(),
@@ -248,12 +562,16 @@ impl Solver {
                 for (number, _, _) in &numbers {
                     *number_counts.entry(*number).or_default() += 1;
                 }
-                let mut goal_numbers = if number_counts.contains_key(&35) {
-                    // Aim for 35 only
-                    vec![35]
-                } else {
+                let mut goal_numbers = if !number_counts.contains_key(&35)
+                    && number_counts.contains_key(&5)
+                    && number_counts.contains_key(&7)
+                {
                     // Aim for 5 and 7
                     vec![5, 7]
+                } else {
+                    // Aim for 35 only, which is shorter to add and has one "V" rather than two
+                    // (and two "I"s) for the atomic number rule
+                    vec![35]
                 };
 
                 for (number, start, length) in &numbers {
@@ -267,7 +585,7 @@ impl Solver {
                     } else {
                         // Remove it
                         for i in 0..*length {
-                            if self.password.protected_graphemes()[*start + i] {
+                            if self.password.is_protected(*start + i) {
                                 // A numeral we can't have is in a protected range :(
                                 return None;
                             }
@@ -292,13 +610,24 @@ impl Solver {
                 }
             }
             Rule::Captcha(captcha) => {
-                changes.push(Change::Append {
-                    protected: true,
-                    string: captcha.clone(),
-                });
+                changes.extend(self.place_in_safe_zone(
+                    &mut delta,
+                    RegionName::Captcha,
+                    captcha.clone(),
+                ));
             }
             Rule::Wordle => {
-                let wordle = get_wordle_answer(Local::now().date_naive());
+                let wordle = match game_state
+                    .lookup
+                    .wordle_answer(game_state.clock.now().date_naive())
+                {
+                    Ok(wordle) => wordle,
+                    Err(e) => {
+                        warn!(target: SOLVER, "Failed to get the Wordle answer: {}", e);
+                        return None;
+                    }
+                };
+                delta.known_string = Some((RuleKind::Wordle, wordle.clone()));
                 changes.push(Change::Append {
                     protected: true,
                     string: wordle,
@@ -314,7 +643,7 @@ impl Solver {
             Rule::MoonPhase => {
                 changes.push(Change::Append {
                     protected: true,
-                    string: get_moon_phase(Local::now())
+                    string: get_moon_phase(game_state.clock.now())
                         .emojis()
                         .first()
                         .unwrap()
@@ -322,11 +651,13 @@ impl Solver {
                 });
             }
             Rule::Geo(geo) => {
-                let country_name = get_country_from_coordinates(geo.lat, geo.long);
-                changes.push(Change::Append {
-                    protected: true,
-                    string: country_name.replace(' ', ""),
-                });
+                let country_name = game_state.lookup.country(geo).replace(' ', "");
+                delta.known_string = Some((RuleKind::Geo, country_name.clone()));
+                changes.extend(self.place_in_safe_zone(
+                    &mut delta,
+                    RegionName::Country,
+                    country_name,
+                ));
             }
             Rule::LeapYear => {
                 // 0 is a valid leap year, and doesn't affect the digit sum rule
@@ -337,93 +668,22 @@ impl Solver {
             }
             Rule::Chess(fen) => {
                 let optimal_move = get_optimal_move(fen.to_owned());
-                changes.push(Change::Append {
-                    protected: true,
-                    string: optimal_move,
-                })
+                changes.extend(self.place_in_safe_zone(
+                    &mut delta,
+                    RegionName::ChessMove,
+                    optimal_move,
+                ));
             }
-            Rule::Egg => changes.push(Change::Prepend {
-                protected: true,
-                string: "🥚".into(),
-            }),
-            Rule::AtomicNumber => {
-                let elements = get_elements(self.password.as_str());
-                let mut sum = elements
-                    .iter()
-                    .map(|(e, _)| e.atomic_number)
-                    .reduce(|sum, d| sum + d)
-                    .unwrap_or_default();
-
-                let nonroman_elements = periodic_table::periodic_table()
-                    .iter()
-                    .filter(|e| get_roman_numerals(e.symbol).is_empty())
-                    .collect::<Vec<_>>();
-
-                if sum > 200 {
-                    // See which elements we can remove
-                    let elements = get_elements(self.password.as_str());
-                    let mut unprotected_elements = Vec::new();
-                    for (element, index) in &elements {
-                        if !self.password.protected_graphemes()[*index]
-                            && (element.symbol.len() == 1
-                                || !self.password.protected_graphemes()[*index + 1])
-                        {
-                            unprotected_elements.push((element, index));
-                        }
-                    }
-                    unprotected_elements.sort_by(|a, b| a.0.atomic_number.cmp(&b.0.atomic_number));
-
-                    // Remove unprotected elements until we get <= 200, largest first
-                    // Also avoid touching roman numeral element symbols
-                    for (element, index) in unprotected_elements
-                        .iter()
-                        .filter(|(e, _)| nonroman_elements.iter().any(|e2| e2.symbol == e.symbol))
-                        .rev()
-                    {
-                        if sum <= 200 {
-                            break;
-                        }
-                        changes.push(Change::Remove {
-                            index: **index,
-                            ignore_protection: false,
-                        });
-                        if element.symbol.len() == 2 {
-                            changes.push(Change::Remove {
-                                index: *index + 1,
-                                ignore_protection: false,
-                            });
-                        }
-                        sum -= element.atomic_number;
-                    }
-
-                    // If now under < 200, the next part will take care of it
-                    // Otherwise, bail
-                    if sum > 200 {
-                        debug!("Atomic number sum is > 200 and we can't remove any more :(");
-                        return None;
-                    }
-                }
-
-                let mut to_add = 200 - sum;
-                while to_add > 0 {
-                    // Add the largest non-roman-numeral element that fits
-                    let element = nonroman_elements
-                        .iter()
-                        .filter(|e| e.atomic_number <= to_add)
-                        .last()
-                        .unwrap();
-                    changes.push(Change::Append {
-                        string: element.symbol.to_owned(),
-                        protected: false,
-                    });
-                    to_add -= element.atomic_number;
-                }
+            Rule::Egg => {
+                changes.push(Change::Prepend {
+                    protected: true,
+                    string: "🥚".into(),
+                });
+                delta.paul = Some(TrackedRegion::new(0, 1));
             }
             Rule::BoldVowels => {
-                for (index, grapheme) in self.password.as_str().graphemes(true).enumerate() {
-                    if VOWELS.contains(&grapheme)
-                        && !self.password.raw_password().formatting()[index].bold
-                    {
+                for (index, grapheme, format, _) in self.password.annotated() {
+                    if VOWELS.contains(&grapheme) && !format.bold {
                         changes.push(Change::Format {
                             index,
                             format_change: FormatChange::BoldOn,
@@ -432,96 +692,93 @@ impl Solver {
                 }
             }
             Rule::Fire => {
-                for (index, grapheme) in self.password.as_str().graphemes(true).enumerate() {
-                    if grapheme == "🔥" {
+                // Repair the safe zone first (the rule will still be broken afterwards if there's
+                // fire elsewhere), as removals would shift the indices of the repairs
+                let fires = self
+                    .password
+                    .as_str()
+                    .graphemes(true)
+                    .enumerate()
+                    .filter(|(_, grapheme)| *grapheme == "🔥")
+                    .map(|(index, _)| index)
+                    .collect::<Vec<_>>();
+                for &index in &fires {
+                    if let Some(original) = self.repair_grapheme(index) {
+                        changes.push(Change::Replace {
+                            index,
+                            new_grapheme: original,
+                            ignore_protection: true,
+                        });
+                    }
+                }
+                if changes.is_empty() {
+                    // A burnt protected grapheme is already lost, so it's removed like any
+                    // other, and the rule it was for is solved again once it's violated
+                    for index in fires {
                         changes.push(Change::Remove {
                             index,
-                            ignore_protection: false,
+                            ignore_protection: true,
                         });
                     }
                 }
             }
             Rule::Strength => {
                 changes.push(Change::Append {
-                    string: "🏋️‍♂️🏋️‍♂️🏋️‍♂️".into(),
+                    string: append_strength(STRENGTH_EMOJIS_REQUIRED),
                     protected: true,
                 });
             }
             Rule::Affirmation => {
-                let mut rng = thread_rng();
-                let affirmation = AFFIRMATIONS.choose(&mut rng).unwrap();
+                let affirmation = self.strategy.choose(&AFFIRMATIONS, &mut self.rng);
                 changes.push(Change::Append {
                     protected: true,
                     string: affirmation.replace(' ', ""),
                 });
             }
             Rule::Hatch => {
-                // We can insert up to 8 🐛's before Paul is overfed
+                // We can insert up to the maximum number of 🐛's before Paul is overfed
                 changes.push(Change::Append {
-                    string: "🐛🐛🐛🐛🐛🐛🐛🐛".into(),
+                    string: "🐛".repeat(game_state.ruleset.max_bugs),
                     protected: false,
                 });
             }
             Rule::Youtube(seconds) => {
-                let video_id = VIDEOS.get(seconds).expect("no video of length");
-                let url = format!("youtu.be/{}", video_id);
-                changes.push(Change::Append {
-                    string: url,
-                    protected: true,
-                });
+                // Only the database is used here, as searching YouTube would block planning (see
+                // `search_video`)
+                let Some(video_id) = self.choose_video(*seconds, &game_state.ruleset) else {
+                    debug!(target: SOLVER, "No usable video of length {}s :(", seconds);
+                    return None;
+                };
+                changes.extend(self.place_in_safe_zone(
+                    &mut delta,
+                    RegionName::YoutubeUrl,
+                    youtube_url(&video_id),
+                ));
             }
             Rule::Sacrifice => {
-                if self.sacrificed_letters.is_empty() {
-                    // Choose letters to sacrifice
-
-                    // First find all absent and unprotected letters
-                    // Start at g to immediately exclude hex digits (to avoid making the hex color
-                    //   rule harder to satisfy)
-                    // Also immediately exclude roman numerals V and X
-                    let mut absent_letters = ('g'..='z').collect::<HashSet<char>>();
-                    let mut unprotected_letters = ('g'..='z').collect::<HashSet<char>>();
-                    absent_letters.remove(&'v');
-                    absent_letters.remove(&'x');
-                    unprotected_letters.remove(&'v');
-                    unprotected_letters.remove(&'x');
-                    for (ch, index) in get_letters(self.password.as_str()) {
-                        let ch = ch.to_ascii_lowercase();
-                        absent_letters.remove(&ch);
-                        if self.password.protected_graphemes()[index] {
-                            unprotected_letters.remove(&ch);
-                        }
+                let mut sacrificed_letters = self.sacrificed_letters.clone();
+                if sacrificed_letters.is_empty() {
+                    // Choose the two letters least likely to get in the way, now or later
+                    let risks = self.sacrifice_risks(game_state);
+                    for risk in &risks {
+                        debug!(target: SOLVER, "Sacrifice risk: {:?}", risk);
                     }
-                    if absent_letters.union(&unprotected_letters).count() < 2 {
+                    if risks.len() < 2 || risks[1].blocks_rule() {
                         // Can't find 2 letters to sacrifice
                         return None;
                     }
-                    while !absent_letters.is_empty() && self.sacrificed_letters.len() < 2 {
-                        #[allow(clippy::clone_on_copy)]
-                        let letter = absent_letters.iter().next().unwrap().clone();
-                        absent_letters.remove(&letter);
-                        unprotected_letters.remove(&letter);
-                        self.sacrificed_letters.push(letter);
-                    }
-                    while !unprotected_letters.is_empty() && self.sacrificed_letters.len() < 2 {
-                        #[allow(clippy::clone_on_copy)]
-                        let letter = unprotected_letters.iter().next().unwrap().clone();
-                        unprotected_letters.remove(&letter);
-                        self.sacrificed_letters.push(letter);
-                    }
-                    if self.sacrificed_letters.len() < 2 {
-                        // Failed :(
-                        return None;
-                    }
+                    sacrificed_letters = risks.iter().take(2).map(|r| r.letter).collect();
 
-                    debug!("Sacrificing {:?}", self.sacrificed_letters);
+                    debug!(target: SOLVER, "Sacrificing {:?}", sacrificed_letters);
+                    delta.sacrificed_letters = Some(sacrificed_letters.clone());
                 }
 
                 // Remove sacrificed letters
-                debug_assert_eq!(self.sacrificed_letters.len(), 2);
+                debug_assert_eq!(sacrificed_letters.len(), 2);
                 for (ch, index) in get_letters(self.password.as_str()) {
                     let ch = ch.to_ascii_lowercase();
-                    if self.sacrificed_letters.contains(&ch) {
-                        if self.password.protected_graphemes()[index] {
+                    if sacrificed_letters.contains(&ch) {
+                        if self.password.is_protected(index) {
                             panic!("We sacrificed a protected letter");
                         }
                         changes.push(Change::Remove {
@@ -535,20 +792,41 @@ impl Solver {
                 let formatting = self.password.raw_password().formatting();
                 let bold_count = formatting.iter().filter(|f| f.bold).count();
                 let italic_count = formatting.iter().filter(|f| f.italic).count();
-                let needed_italic = 2 * bold_count - italic_count;
+                let needed_italic = (2 * bold_count).saturating_sub(italic_count);
 
-                let mut i = 0;
-                while changes.len() < needed_italic {
-                    if i == formatting.len() {
-                        return None;
-                    }
-                    if !formatting[i].italic {
-                        changes.push(Change::Format {
-                            index: i,
-                            format_change: FormatChange::ItalicOn,
-                        });
-                    }
-                    i += 1;
+                // Vowels which aren't bold yet will be made bold for the bold vowels rule, as
+                // will any in strings appended for later rules, so budget italics for those too
+                // rather than having this rule broken again each time
+                let unbolded_vowels = self
+                    .password
+                    .annotated()
+                    .filter(|(_, grapheme, format, _)| VOWELS.contains(grapheme) && !format.bold)
+                    .count();
+                let projected_bold_count = bold_count + unbolded_vowels + FUTURE_BOLD_VOWELS;
+                let wanted_italic = (2 * projected_bold_count).saturating_sub(italic_count);
+
+                // Prefer protected graphemes, as italics on anything else may be removed later
+                let mut candidates = self
+                    .password
+                    .annotated()
+                    .filter(|(_, _, format, _)| !format.italic)
+                    .map(|(index, _, _, protected)| (index, protected))
+                    .collect::<Vec<_>>();
+                if candidates.len() < needed_italic {
+                    return None;
+                }
+                candidates.sort_by_key(|(_, protected)| !protected);
+                let mut indices = candidates
+                    .into_iter()
+                    .take(wanted_italic)
+                    .map(|(index, _)| index)
+                    .collect::<Vec<_>>();
+                indices.sort();
+                for index in indices {
+                    changes.push(Change::Format {
+                        index,
+                        format_change: FormatChange::ItalicOn,
+                    });
                 }
             }
             Rule::Wingdings => {
@@ -565,40 +843,52 @@ impl Solver {
                     .iter()
                     .filter(|f| f.font_family == FontFamily::Wingdings)
                     .count();
-                // The extra 8 accounts for Paul's food that we store at the end of the password,
-                // rather than _in_ the password, in the web driver
-                let needed_wingdings =
-                    (0.3 * (self.password.len() + 8) as f32).ceil() as usize - wingdings_count;
+                // Paul's bugs count towards the length, even when they're not in our password
+                let length = game_state.page_length(self.password.raw_password());
+                let needed_wingdings = ((game_state.ruleset.wingdings_fraction * length as f32)
+                    .ceil() as usize)
+                    .saturating_sub(wingdings_count);
                 debug!(
+                    target: SOLVER,
                     "Current wingdings percent <= {}",
-                    wingdings_count as f32 / (self.password.len() + 8) as f32
+                    wingdings_count as f32 / length as f32
                 );
 
-                let mut i = 0;
-                while changes.len() < needed_wingdings {
-                    if i == formatting.len() {
-                        return None;
-                    }
-                    // Don't change font of roman numerals, they must be times new roman
-                    if roman_numeral_indices.contains(&i) {
-                        i += 1;
-                        continue;
-                    }
-
-                    if formatting[i].font_family != FontFamily::Wingdings {
-                        changes.push(Change::Format {
-                            index: i,
-                            format_change: FormatChange::FontFamily(FontFamily::Wingdings),
-                        });
-                    }
-                    i += 1;
+                // Don't change font of roman numerals, they must be times new roman
+                let mut candidates = self
+                    .password
+                    .annotated()
+                    .filter(|(index, _, format, _)| {
+                        format.font_family != FontFamily::Wingdings
+                            && !roman_numeral_indices.contains(index)
+                    })
+                    .map(|(index, grapheme, _, protected)| {
+                        (self.wingdings_preference(index, grapheme, protected), index)
+                    })
+                    .collect::<Vec<_>>();
+                if candidates.len() < needed_wingdings {
+                    return None;
+                }
+                candidates.sort();
+                let mut indices = candidates
+                    .into_iter()
+                    .take(needed_wingdings)
+                    .map(|(_, index)| index)
+                    .collect::<Vec<_>>();
+                indices.sort();
+                for index in indices {
+                    changes.push(Change::Format {
+                        index,
+                        format_change: FormatChange::FontFamily(FontFamily::Wingdings),
+                    });
                 }
             }
             Rule::Hex(color) => {
-                changes.push(Change::Append {
-                    string: color.to_hex_string(),
-                    protected: true,
-                });
+                changes.extend(self.place_in_safe_zone(
+                    &mut delta,
+                    RegionName::HexColor,
+                    color.to_hex_string(),
+                ));
             }
             Rule::TimesNewRoman => {
                 let formatting = self.password.raw_password().formatting();
@@ -651,52 +941,66 @@ impl Solver {
             Rule::IncludeLength => {
                 if self.length_string.is_none() {
                     // Pick a length we want to aim for
-                    let mut padding = 0;
-                    self.goal_length = {
-                        // 3 for length string, 5 for time string
-                        let mut l = self.password.len() + 3 + 5 + bugs;
-                        // TODO: Maybe try to minimize the digit sum of `l` here too
-                        while l < 100 || !is_prime(l) {
-                            padding += 1;
-                            l += 1;
-                        }
-                        Some(l)
-                    };
-                    info!(
-                        "Password length will be {}",
-                        self.goal_length.as_ref().unwrap()
-                    );
+                    // 3 for length string, 5 for time string, plus any bugs Paul has which
+                    // aren't in our password
+                    let min_length = game_state.page_length(self.password.raw_password()) + 3 + 5;
+                    // The length's digits have to fit alongside the ones we can't remove
+                    let time = current_time_string(game_state);
+                    let fixed_digits = get_digits(self.password.as_str())
+                        .into_iter()
+                        .filter(|(_, i)| self.password.is_protected(*i))
+                        .chain(get_digits(&time))
+                        .map(|(d, _)| d)
+                        .sum::<u32>();
+                    let goal_length = goal_length(
+                        min_length,
+                        game_state.ruleset.digit_sum.saturating_sub(fixed_digits),
+                    )?;
+                    let padding = goal_length - min_length;
+                    info!(target: SOLVER, "Password length will be {}", goal_length);
+                    delta.goal_length = Some(goal_length);
 
                     // Append the length string to the end
-                    let length_string = self.goal_length.as_ref().unwrap().to_string();
+                    let length_string = goal_length.to_string();
                     let length_length = length_string.len();
                     assert_eq!(length_length, 3);
-                    self.length_string = Some(InnerString::new(self.password.len(), length_length));
+                    delta.length_string =
+                        Some(TrackedRegion::new(self.password.len(), length_length));
                     changes.push(Change::Append {
                         string: length_string,
                         protected: true,
                     });
 
                     // Add in time string
-                    let time = Local::now().format("%l:%M").to_string().trim().to_owned();
                     changes.push(Change::Append {
                         string: time.clone(),
                         protected: true,
                     });
-                    self.time_string = Some(InnerString::new(
+                    delta.time_string = Some(TrackedRegion::new(
                         self.password.len() + length_length,
                         time.len(),
                     ));
 
                     // Add padding
                     changes.push(Change::Append {
-                        string: "-".repeat(padding),
+                        string: self.strategy.padding().repeat(padding),
                         protected: false,
                     });
 
                     // At this point, the password may or may not be `goal_length` in length, but:
                     // - If it's too long, Paul will eat bugs until it's right
                     // - If it's too short, we'll eventually feed Paul more bugs until it's right
+                } else if let Some(goal_length) = self.goal_length {
+                    // Something else has since added or removed graphemes, so make up the
+                    // difference with padding
+                    let length = game_state.page_length(self.password.raw_password());
+                    if length != goal_length {
+                        changes.extend(self.keep_goal_length(
+                            length as isize - goal_length as isize,
+                            usize::MAX,
+                            game_state,
+                        ));
+                    }
                 }
             }
             Rule::PrimeLength => {
@@ -705,108 +1009,462 @@ impl Solver {
             }
             Rule::Skip => {}
             Rule::Time => {
-                let time = Local::now().format("%l:%M").to_string().trim().to_owned();
-                if let Some(InnerString { index, length }) = self.time_string {
-                    if length != time.len() {
-                        todo!("length of time string changed");
-                    }
-                    for (i, ch) in time.chars().enumerate() {
+                let time = current_time_string(game_state);
+                if let Some(&TrackedRegion { index, length }) = self.tracked(self.time_string) {
+                    // Update it in place, then make up any difference in length at the end
+                    // (e.g., when 9:59 becomes 10:00)
+                    for (i, ch) in time.chars().enumerate().take(length) {
                         changes.push(Change::Replace {
                             index: index + i,
                             new_grapheme: ch.to_string(),
                             ignore_protection: true,
                         });
                     }
+                    if time.len() > length {
+                        changes.push(Change::Insert {
+                            index: index + length,
+                            string: time[length..].to_owned(),
+                            protected: true,
+                        });
+                    }
+                    for i in time.len()..length {
+                        changes.push(Change::Remove {
+                            index: index + i,
+                            ignore_protection: true,
+                        });
+                    }
+                    if time.len() != length {
+                        delta.time_string = Some(TrackedRegion::new(index, time.len()));
+                        if self.goal_length.is_some() {
+                            changes.extend(self.keep_goal_length(
+                                time.len() as isize - length as isize,
+                                index + length,
+                                game_state,
+                            ));
+                        }
+                    }
                 } else {
                     // Just append time to the end
                     changes.push(Change::Append {
                         string: time.clone(),
                         protected: true,
                     });
-                    self.time_string = Some(InnerString::new(self.password.len(), time.len()));
+                    delta.time_string = Some(TrackedRegion::new(self.password.len(), time.len()));
                 }
             }
             Rule::Final => {}
+            Rule::Unknown(_) => return None,
         }
 
-        // Update location of length string if necessary
-        if let Some(InnerString {
-            index: length_string_index,
-            ..
-        }) = self.length_string.as_mut()
+        changes.extend(self.style_padding(&changes));
+        delta.rule = Some(rule.kind());
+        Some(Plan {
+            changes,
+            state_delta: delta,
+        })
+    }
+
+    /// Changes which set the padding in the password in the strategy's padding font, other
+    /// than graphemes the given changes already touch. Padding can't be formatted as it's
+    /// added (formatting is done before appending), so it's styled by the plan after.
+    fn style_padding(&self, changes: &[Change]) -> Vec<Change> {
+        let font = self.strategy.padding_font();
+        if font == FontFamily::default() {
+            return Vec::new();
+        }
+        let touched = changes
+            .iter()
+            .filter_map(|change| match change {
+                Change::Format { index, .. }
+                | Change::Replace { index, .. }
+                | Change::Remove { index, .. } => Some(*index),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        self.password
+            .annotated()
+            .filter(|(index, grapheme, format, protected)| {
+                !protected
+                    && *grapheme == self.strategy.padding()
+                    && format.font_family == FontFamily::default()
+                    && !touched.contains(index)
+            })
+            .map(|(index, ..)| Change::Format {
+                index,
+                format_change: FormatChange::FontFamily(font.clone()),
+            })
+            .collect()
+    }
+
+    /// Produce changes which add a fragile protected string to the safe zone, creating the
+    /// zone at the end of the password if there isn't one yet. The filler either side is
+    /// protected separately, so later strings can be inserted between the blocks.
+    fn place_in_safe_zone(
+        &self,
+        delta: &mut StateDelta,
+        name: RegionName,
+        string: String,
+    ) -> Vec<Change> {
+        let length = string.graphemes(true).count();
+        let filler_length = SAFE_ZONE_FILLER.graphemes(true).count();
+        match delta
+            .safe_zone
+            .clone()
+            .or_else(|| self.tracked(self.safe_zone).cloned())
         {
-            for change in changes.iter() {
-                match change {
-                    Change::Insert { index, string, .. } => {
-                        if index < length_string_index {
-                            *length_string_index += string.graphemes(true).count();
-                        }
-                    }
-                    Change::Prepend { string, .. } => {
-                        *length_string_index += string.graphemes(true).count();
-                    }
-                    Change::Remove { index, .. } => {
-                        if index < length_string_index {
-                            *length_string_index -= 1;
-                        }
-                    }
-                    _ => {}
+            Some(mut safe_zone) => {
+                // Insert before the trailing filler
+                let index = safe_zone.index + safe_zone.length - filler_length;
+                safe_zone.length += length;
+                delta.safe_zone = Some(safe_zone);
+                delta.regions.push((
+                    TrackedRegion::new(index, length),
+                    Region {
+                        name,
+                        contents: string.clone(),
+                    },
+                ));
+                vec![Change::Insert {
+                    index,
+                    string,
+                    protected: true,
+                }]
+            }
+            None => {
+                let index = self.password.len();
+                delta.safe_zone = Some(TrackedRegion::new(index, length + 2 * filler_length));
+                delta.regions.push((
+                    TrackedRegion::new(index + filler_length, length),
+                    Region {
+                        name,
+                        contents: string.clone(),
+                    },
+                ));
+                [
+                    SAFE_ZONE_FILLER.to_owned(),
+                    string,
+                    SAFE_ZONE_FILLER.to_owned(),
+                ]
+                .into_iter()
+                .map(|string| Change::Append {
+                    string,
+                    protected: true,
+                })
+                .collect()
+            }
+        }
+    }
+
+    /// Produce changes which make up for the password growing by `growth` graphemes (or
+    /// shrinking, if it's negative), such as when the hour in the time string gains or loses a
+    /// digit, so the password stays at the goal length and the length rules stay satisfied.
+    /// Padding is removed for growth, latest first, as long as removing it along with the rest
+    /// doesn't break anything, and padding is appended for shrinkage.
+    /// `time_end` is the index just past the time string before it changes, as removals are
+    /// made after it's grown (`usize::MAX` if it isn't changing).
+    fn keep_goal_length(
+        &self,
+        growth: isize,
+        time_end: usize,
+        game_state: &GameState,
+    ) -> Vec<Change> {
+        if growth < 0 {
+            return vec![Change::Append {
+                string: self.strategy.padding().repeat(growth.unsigned_abs()),
+                protected: false,
+            }];
+        }
+
+        let rules = Self::removal_checked_rules(game_state);
+        let mut padding = Vec::new();
+        for (index, ..) in self
+            .password
+            .annotated()
+            .filter(|(_, grapheme, _, protected)| {
+                !protected && *grapheme == self.strategy.padding()
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+        {
+            if padding.len() == growth as usize {
+                break;
+            }
+            padding.push(index);
+            if !rules_broken_by_removal(self.password.raw_password(), &padding, &rules, game_state)
+                .is_empty()
+            {
+                padding.pop();
+            }
+        }
+        if padding.len() < growth as usize {
+            warn!(
+                target: SOLVER,
+                "Only {} padding to remove for the time string growing by {}, so Paul will have \
+                 to eat the rest",
+                padding.len(),
+                growth
+            );
+        }
+        padding
+            .into_iter()
+            .map(|index| Change::Remove {
+                index: if index >= time_end {
+                    index + growth as usize
+                } else {
+                    index
+                },
+                ignore_protection: false,
+            })
+            .collect()
+    }
+
+    /// How much we'd rather not put the grapheme at `index` in Wingdings, lowest first: padding,
+    /// then other unprotected filler, then protected strings (like the sponsor), then digits
+    /// (so their font sizes can still be checked by eye), and last the strings with outside
+    /// answers in the safe zone (like the captcha and video URL).
+    fn wingdings_preference(&self, index: usize, grapheme: &str, protected: bool) -> usize {
+        let in_region = self.regions.iter().any(|(id, _)| {
+            self.password
+                .tracked(*id)
+                .is_some_and(|region| region.contains(index))
+        });
+        if in_region {
+            4
+        } else if grapheme.chars().all(|c| c.is_ascii_digit()) {
+            3
+        } else if grapheme == self.strategy.padding()
+            || SAFE_ZONE_FILLER.graphemes(true).any(|g| g == grapheme)
+        {
+            0
+        } else if !protected {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// What the grapheme at the given index should be restored to, if it's in the safe zone.
+    fn repair_grapheme(&self, index: usize) -> Option<String> {
+        if !self.tracked(self.safe_zone)?.contains(index) {
+            return None;
+        }
+        let grapheme = match self.regions.iter().find_map(|(id, region)| {
+            Some((self.password.tracked(*id)?, region)).filter(|(string, _)| string.contains(index))
+        }) {
+            Some((string, region)) => {
+                debug!(target: SOLVER, "Repairing the {:?} at {}", region.name, index);
+                region.contents.graphemes(true).nth(index - string.index)?
+            }
+            None => SAFE_ZONE_FILLER.graphemes(true).next()?,
+        };
+        Some(grapheme.to_owned())
+    }
+
+    /// Solve the given early rules (1-8) all at once, producing appends which satisfy all of
+    /// them, so they can be entered in one go. Returns None if any of the rules isn't an early
+    /// rule, or they can't all be satisfied by appending (e.g., if the digits already sum to
+    /// more than 25).
+    pub fn solve_early_rules(
+        &mut self,
+        rules: &[Rule],
+        game_state: &GameState,
+    ) -> Option<Vec<Change>> {
+        if rules
+            .iter()
+            .any(|rule| rule.number() > RuleKind::Sponsors.number())
+        {
+            return None;
+        }
+
+        let mut rules = rules.to_vec();
+        rules.sort_by_key(Rule::kind);
+        let mut solver = self.clone();
+        let mut appends: Vec<Change> = Vec::new();
+        for rule in &rules {
+            for change in solver.solve_rule(rule, game_state)? {
+                let Change::Append { string, protected } = &change else {
+                    return None;
+                };
+                // Combine with the previous append if possible
+                match appends.last_mut() {
+                    Some(Change::Append {
+                        string: previous_string,
+                        protected: previous_protected,
+                    }) if previous_protected == protected => previous_string.push_str(string),
+                    _ => appends.push(change.clone()),
                 }
+                solver.password.queue_change(change);
             }
+            solver.password.commit_changes().ok()?;
         }
 
-        // Update location of time string if necessary
-        if let Some(InnerString {
-            index: time_string_index,
-            ..
-        }) = self.time_string.as_mut()
+        if rules
+            .iter()
+            .all(|rule| rule.validate(solver.password.raw_password(), game_state))
         {
-            for change in changes.iter() {
-                match change {
-                    Change::Insert { index, string, .. } => {
-                        if index < time_string_index {
-                            *time_string_index += string.graphemes(true).count();
-                        }
-                    }
-                    Change::Prepend { string, .. } => {
-                        *time_string_index += string.graphemes(true).count();
-                    }
-                    Change::Remove { index, .. } => {
-                        if index < time_string_index {
-                            *time_string_index -= 1;
-                        }
-                    }
-                    _ => {}
+            Some(appends)
+        } else {
+            None
+        }
+    }
+
+    /// Is it worth removing leftovers from earlier rules before solving the given rule? These
+    /// rules get harder (or need more changes) the more digits, elements or letters there are.
+    pub fn wants_garbage_collection(rule: &Rule) -> bool {
+        matches!(
+            rule,
+            Rule::Digits | Rule::AtomicNumber | Rule::DigitFontSize | Rule::LetterFontSize
+        )
+    }
+
+    /// Produce changes which remove unprotected graphemes that no satisfied rule needs any
+    /// more (e.g., padding from the minimum length rule, or digits appended for earlier rules).
+    pub fn collect_garbage(&self, game_state: &GameState) -> Vec<Change> {
+        let changes = self.removable_graphemes(game_state, 1);
+        if !changes.is_empty() {
+            info!(
+                target: SOLVER,
+                "Removing {} leftover graphemes from earlier rules",
+                changes.len()
+            );
+        }
+        changes
+    }
+
+    /// Produce changes which shrink the password as far as possible without breaking any
+    /// satisfied rule, by removing unprotected graphemes until none are left that can go (as
+    /// removing one can make another redundant). Should be run before solving the length
+    /// rule, as the length is fixed after that.
+    pub fn compact_password(&self, game_state: &GameState) -> Vec<Change> {
+        let changes = self.removable_graphemes(game_state, usize::MAX);
+        if !changes.is_empty() {
+            info!(
+                target: SOLVER,
+                "Compacting password from {} to {} graphemes",
+                self.password.len(),
+                self.password.len() - changes.len()
+            );
+        }
+        changes
+    }
+
+    /// Removals of unprotected graphemes which don't break any satisfied rule, making up to
+    /// `max_passes` passes over the password. Indices are in descending order, and relative to
+    /// the current password.
+    fn removable_graphemes(&self, game_state: &GameState, max_passes: usize) -> Vec<Change> {
+        if self.goal_length.is_some() {
+            // The password length is fixed from here on
+            return Vec::new();
+        }
+
+        let rules = Self::removal_checked_rules(game_state);
+        let time_string = self.tracked(self.time_string).map(TrackedRegion::range);
+        let candidates = self
+            .password
+            .annotated()
+            .map(|(index, _, format, protected)| {
+                !protected
+                    && !time_string.as_ref().is_some_and(|s| s.contains(&index))
+                    && format.font_family != FontFamily::Wingdings
+            })
+            .collect::<Vec<_>>();
+        let mut password = self.password.raw_password().clone();
+        // Index in the current password of each grapheme left in the working copy
+        let mut original_indices = (0..password.len()).collect::<Vec<_>>();
+        let mut removed = Vec::new();
+        for _ in 0..max_passes {
+            let removed_before = removed.len();
+            // Go backwards, so the remaining indices stay valid as graphemes are removed
+            for index in (0..password.len()).rev() {
+                if candidates[original_indices[index]]
+                    && rules_broken_by_removal(&password, &[index], &rules, game_state).is_empty()
+                {
+                    password.remove(index);
+                    removed.push(original_indices.remove(index));
                 }
             }
+            if removed.len() == removed_before {
+                break;
+            }
         }
 
-        Some(changes)
+        removed.sort_unstable_by(|a, b| b.cmp(a));
+        removed
+            .into_iter()
+            .map(|index| Change::Remove {
+                index,
+                ignore_protection: false,
+            })
+            .collect()
     }
 
     /// Solve for the given rule and updates the password in one go.
-    /// Panics if a solution can't be found.
+    /// Panics if a solution can't be found, or it's invalid.
     #[cfg(test)]
     pub fn solve_rule_and_commit(&mut self, rule: &Rule, game_state: &GameState) {
-        let changes = self
-            .solve_rule(rule, game_state, 0)
+        let plan = self
+            .plan_rule(rule, game_state)
             .expect("could not find a solution");
-        for change in changes {
-            self.password.queue_change(change);
+        self.carry_out(&plan).expect("solution is invalid");
+    }
+
+    /// Which of the given rules are currently satisfied, but would be violated if the grapheme
+    /// at `index` was removed from the password.
+    pub fn impact_analysis(
+        &self,
+        index: usize,
+        rules: &[Rule],
+        game_state: &GameState,
+    ) -> Vec<Rule> {
+        rules_broken_by_removal(self.password.raw_password(), &[index], rules, game_state)
+    }
+
+    /// The rules to check before removing unprotected graphemes. Skips rules with
+    /// instance-specific data (their strings are protected anyway), the sacrifice itself, and
+    /// length-based rules (which the driver pads with bugs).
+    fn removal_checked_rules(game_state: &GameState) -> Vec<Rule> {
+        RuleKind::iter()
+            .filter(|kind| kind.number() <= game_state.highest_rule && !kind.has_instance())
+            .filter(|kind| {
+                !matches!(
+                    kind,
+                    RuleKind::Sacrifice
+                        | RuleKind::Wingdings
+                        | RuleKind::IncludeLength
+                        | RuleKind::PrimeLength
+                )
+            })
+            .map(Rule::from)
+            .collect()
+    }
+
+    /// The number of rules which would be broken by removing every instance of `letter` from
+    /// the password, as sacrificing it would.
+    fn letter_removal_impact(&self, letter: char, game_state: &GameState) -> usize {
+        let indices = get_letters(self.password.as_str())
+            .into_iter()
+            .filter(|(ch, _)| ch.to_ascii_lowercase() == letter)
+            .map(|(_, index)| index)
+            .collect::<Vec<_>>();
+        if indices.is_empty() {
+            return 0;
         }
-        self.password.commit_changes();
+        let rules = Self::removal_checked_rules(game_state);
+        rules_broken_by_removal(self.password.raw_password(), &indices, &rules, game_state).len()
     }
 
-    /// Generate the best starting password we can via a series of changes to the empty password.
-    pub fn starting_password(&self) -> Vec<Change> {
-        vec![
+    /// Generate the best starting password we can via a series of changes to the empty password,
+    /// starting with Paul (as an egg), who's tracked from then on.
+    pub fn starting_password(&self, game_state: &GameState) -> Plan {
+        let changes = vec![
             Change::Append {
                 protected: true,
                 string: "🥚0mayXXXVshell".into(),
             },
             Change::Append {
                 protected: true,
-                string: get_moon_phase(Local::now())
+                string: get_moon_phase(game_state.clock.now())
                     .emojis()
                     .first()
                     .unwrap()
@@ -816,6 +1474,35 @@ impl Solver {
                 protected: false,
                 string: "He997".into(),
             },
-        ]
+        ];
+        Plan {
+            changes,
+            state_delta: StateDelta {
+                paul: Some(TrackedRegion::new(0, 1)),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Which of the given rules are satisfied by `password`, but would be violated if the graphemes
+/// at `indices` were all removed.
+fn rules_broken_by_removal(
+    password: &Password,
+    indices: &[usize],
+    rules: &[Rule],
+    game_state: &GameState,
+) -> Vec<Rule> {
+    let mut removed = password.clone();
+    let mut indices = indices.to_vec();
+    indices.sort_unstable_by(|a, b| b.cmp(a));
+    indices.dedup();
+    for index in indices {
+        removed.remove(index);
     }
+    rules
+        .iter()
+        .filter(|rule| rule.validate(password, game_state) && !rule.validate(&removed, game_state))
+        .cloned()
+        .collect()
 }
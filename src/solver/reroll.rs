@@ -0,0 +1,289 @@
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use super::scoring::{
+    score_string, ScoringBudget, StringScore, DEFAULT_MAX_REROLLS, MAX_ACCEPTABLE_COST,
+};
+use crate::{
+    logging::SOLVER,
+    storage::{from_record, to_record, Artifact, StorageBackend, StorageError, STORAGE},
+};
+
+/// Storage stream for the costs of strings seen when rerolling.
+const STATS_STREAM: &str = "stats.rerolls";
+/// The most rerolls we want to spend on average to find an acceptable string.
+const MAX_EXPECTED_REROLLS: f64 = 5.0;
+/// Number of samples needed before the history is used instead of the defaults.
+const MIN_SAMPLES: usize = 20;
+/// How many times more rerolls than expected we'll do before accepting whatever we get.
+const BUDGET_FACTOR: f64 = 3.0;
+/// Number of rerolls before accepting whatever we get, when there's not enough history.
+const DEFAULT_BUDGET: usize = 50;
+
+/// Things the game lets us reroll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RerollKind {
+    Captcha,
+    Color,
+}
+
+/// A string seen when rerolling, as persisted.
+#[derive(Debug, Serialize, Deserialize)]
+struct RerollSample {
+    kind: RerollKind,
+    /// Cost of the string, ignoring sacrificed letters (which differ between runs).
+    cost: u32,
+}
+
+impl Artifact for RerollSample {
+    const KIND: &'static str = "reroll sample";
+    const SCHEMA_VERSION: u32 = 1;
+
+    fn migrate(_record: &mut serde_json::Value, version: u32) -> Result<(), StorageError> {
+        match version {
+            // Unversioned samples have the same fields
+            0 => Ok(()),
+            _ => Err(StorageError::UnsupportedSchema {
+                kind: Self::KIND,
+                version,
+            }),
+        }
+    }
+}
+
+/// Load the costs of all previously seen strings of the given kind.
+fn load_costs(storage: &dyn StorageBackend, kind: RerollKind) -> Result<Vec<u32>, StorageError> {
+    let mut costs = Vec::new();
+    for record in storage.load(STATS_STREAM)? {
+        let sample: RerollSample = from_record(record)?;
+        if sample.kind == kind {
+            costs.push(sample.cost);
+        }
+    }
+    Ok(costs)
+}
+
+/// When to accept a string rather than rerolling it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RerollPolicy {
+    /// Highest cost to accept.
+    pub max_cost: u32,
+    /// Number of rerolls after which any usable string is accepted.
+    pub budget: usize,
+    /// Number of rerolls after which any string is accepted, usable or not.
+    pub max_rerolls: usize,
+}
+
+impl Default for RerollPolicy {
+    fn default() -> Self {
+        RerollPolicy {
+            max_cost: MAX_ACCEPTABLE_COST,
+            budget: DEFAULT_BUDGET,
+            max_rerolls: DEFAULT_MAX_REROLLS,
+        }
+    }
+}
+
+impl RerollPolicy {
+    /// Choose a policy from the costs of previously seen strings: the lowest cost threshold
+    /// we expect to reach within `MAX_EXPECTED_REROLLS`, with a budget a few times that.
+    pub fn from_history(costs: &[u32]) -> Self {
+        if costs.len() < MIN_SAMPLES {
+            return RerollPolicy::default();
+        }
+
+        let mut thresholds = costs.to_vec();
+        thresholds.sort_unstable();
+        thresholds.dedup();
+        for max_cost in thresholds {
+            // Laplace smoothing, so we never expect a cost is guaranteed or impossible
+            let successes = costs.iter().filter(|c| **c <= max_cost).count();
+            let p = (successes + 1) as f64 / (costs.len() + 2) as f64;
+            let expected_rerolls = 1.0 / p;
+            if expected_rerolls <= MAX_EXPECTED_REROLLS {
+                return RerollPolicy {
+                    max_cost,
+                    budget: (expected_rerolls * BUDGET_FACTOR).ceil() as usize,
+                    ..Default::default()
+                };
+            }
+        }
+        unreachable!("the highest seen cost always has p close to 1");
+    }
+
+    /// Choose a policy from the costs of previously seen strings, with the limits from the
+    /// given budget applied.
+    pub fn with_budget(costs: &[u32], budget: &ScoringBudget) -> Self {
+        let policy = RerollPolicy::from_history(costs);
+        RerollPolicy {
+            max_cost: budget.max_cost.unwrap_or(policy.max_cost),
+            max_rerolls: budget.max_rerolls,
+            ..policy
+        }
+    }
+
+    /// Should we accept a string with the given score, having already rerolled `rerolls`
+    /// times, when the digits added to the password can add up to `digit_budget`? Unusable
+    /// strings are only accepted once we've run out of rerolls.
+    pub fn accept(&self, score: &StringScore, rerolls: usize, digit_budget: u32) -> bool {
+        if rerolls >= self.max_rerolls {
+            return true;
+        }
+        score.usable(digit_budget) && (score.cost() <= self.max_cost || rerolls >= self.budget)
+    }
+}
+
+/// Decides whether to reroll a string, recording what it's seen for future runs.
+#[derive(Debug)]
+pub struct Reroller {
+    kind: RerollKind,
+    policy: RerollPolicy,
+    rerolls: usize,
+}
+
+impl Reroller {
+    /// Create a reroller, with a policy based on what we've seen in previous runs and the
+    /// given budget.
+    pub fn new(kind: RerollKind, budget: &ScoringBudget) -> Self {
+        let costs = match load_costs(&*STORAGE.lock().unwrap(), kind) {
+            Ok(costs) => costs,
+            Err(e) => {
+                warn!(target: SOLVER, "Failed to load {:?} reroll stats: {}", kind, e);
+                Vec::new()
+            }
+        };
+        let policy = RerollPolicy::with_budget(&costs, budget);
+        debug!(
+            target: SOLVER,
+            "{:?} reroll policy {:?} (from {} samples)",
+            kind,
+            policy,
+            costs.len()
+        );
+        Reroller {
+            kind,
+            policy,
+            rerolls: 0,
+        }
+    }
+
+    /// Should we accept the given string, when the digits added to the password can add up to
+    /// `digit_budget`? If not, the caller should reroll it.
+    pub fn accept(&mut self, string: &str, sacrificed_letters: &[char], digit_budget: u32) -> bool {
+        let sample = RerollSample {
+            kind: self.kind,
+            cost: score_string(string, &[]).cost(),
+        };
+        if let Err(e) = to_record(&sample)
+            .and_then(|record| STORAGE.lock().unwrap().append(STATS_STREAM, &record))
+        {
+            warn!(target: SOLVER, "Failed to record {:?} reroll stats: {}", self.kind, e);
+        }
+
+        let score = score_string(string, sacrificed_letters);
+        let accepted = self.policy.accept(&score, self.rerolls, digit_budget);
+        if !accepted {
+            self.rerolls += 1;
+        } else if !score.usable(digit_budget) {
+            warn!(
+                target: SOLVER,
+                "Gave up after {} {:?} rerolls, accepting unusable {:?}",
+                self.rerolls,
+                self.kind,
+                string
+            );
+        }
+        accepted
+    }
+
+    /// Number of times we've asked for a reroll.
+    pub fn rerolls(&self) -> usize {
+        self.rerolls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{load_costs, RerollKind, RerollPolicy, STATS_STREAM};
+    use crate::{
+        solver::scoring::{score_string, ScoringBudget},
+        storage::{MemoryStorage, StorageBackend},
+    };
+
+    #[test]
+    fn policy_from_history() {
+        // Not enough history
+        assert_eq!(RerollPolicy::from_history(&[0; 5]), RerollPolicy::default());
+
+        // Cheap strings are common, so hold out for the cheapest
+        let costs = [0, 0, 0, 5, 10, 20].repeat(5);
+        let policy = RerollPolicy::from_history(&costs);
+        assert_eq!(policy.max_cost, 0);
+        assert!(policy.budget >= 3);
+
+        // Cheap strings are rare, so accept the more expensive ones
+        let mut costs = vec![0];
+        costs.extend([3; 10]);
+        costs.extend([15; 30]);
+        let policy = RerollPolicy::from_history(&costs);
+        assert_eq!(policy.max_cost, 3);
+    }
+
+    #[test]
+    fn policy_accept() {
+        let policy = RerollPolicy {
+            max_cost: 3,
+            budget: 4,
+            max_rerolls: 200,
+        };
+        assert!(policy.accept(&score_string("abc3", &[]), 0, 25));
+        assert!(!policy.accept(&score_string("abc9", &[]), 0, 25));
+        assert!(policy.accept(&score_string("abc9", &[]), 4, 25));
+        // Never accept sacrificed letters, or too many digits, until we run out of rerolls
+        assert!(!policy.accept(&score_string("abc", &['a']), 100, 25));
+        assert!(!policy.accept(&score_string("abc3", &[]), 0, 2));
+        assert!(!policy.accept(&score_string("abc9", &[]), 100, 5));
+        assert!(policy.accept(&score_string("abc", &['a']), 200, 25));
+    }
+
+    #[test]
+    fn policy_with_budget() {
+        let costs = [0, 0, 0, 5, 10, 20].repeat(5);
+        let policy = RerollPolicy::with_budget(&costs, &ScoringBudget::default());
+        assert_eq!(policy, RerollPolicy::from_history(&costs));
+
+        let budget = ScoringBudget {
+            max_cost: Some(5),
+            max_rerolls: 10,
+        };
+        let policy = RerollPolicy::with_budget(&costs, &budget);
+        assert_eq!(policy.max_cost, 5);
+        assert_eq!(policy.max_rerolls, 10);
+        assert_eq!(policy.budget, RerollPolicy::from_history(&costs).budget);
+    }
+
+    #[test]
+    fn load() {
+        let mut storage = MemoryStorage::default();
+        storage
+            .append(STATS_STREAM, &json!({"kind": "captcha", "cost": 3}))
+            .unwrap();
+        storage
+            .append(
+                STATS_STREAM,
+                &json!({"kind": "color", "cost": 7, "schema_version": 1}),
+            )
+            .unwrap();
+        storage
+            .append(STATS_STREAM, &json!({"kind": "captcha", "cost": 0}))
+            .unwrap();
+        assert_eq!(
+            load_costs(&storage, RerollKind::Captcha).unwrap(),
+            vec![3, 0]
+        );
+        assert_eq!(load_costs(&storage, RerollKind::Color).unwrap(), vec![7]);
+    }
+}
@@ -0,0 +1,183 @@
+//! Adopting the password as it actually is in the game, when it's drifted from ours in a way we
+//! can't explain (or fix by retyping).
+
+use log::{debug, info, warn};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::Solver;
+use crate::{
+    logging::SOLVER,
+    password::{Format, MutablePassword, Password, ProtectedPassword, RegionId, TrackedRegion},
+};
+
+impl Solver {
+    /// Replace our password with the given one (and its formatting), as read from the game.
+    /// Protected strings and the strings we keep track of (the length and time strings, Paul,
+    /// the safe zone and its regions) are found again in the new password, and any which can't be
+    /// found are dropped, to be re-solved. Returns false (leaving everything as it was) if the
+    /// formatting doesn't match the password.
+    pub fn adopt_password(&mut self, string: &str, formatting: Vec<Format>) -> bool {
+        let graphemes = string.graphemes(true).collect::<Vec<_>>();
+        if graphemes.len() != formatting.len() {
+            warn!(
+                target: SOLVER,
+                "Can't adopt password {:?} with formatting for {} graphemes",
+                string,
+                formatting.len()
+            );
+            return false;
+        }
+        info!(
+            target: SOLVER,
+            "Adopting password {:?} (was {:?})",
+            string,
+            self.password.as_str()
+        );
+
+        let old_graphemes = self.password.as_str().graphemes(true).collect::<Vec<_>>();
+        let relocate = |region: &TrackedRegion| {
+            find_graphemes(&graphemes, &old_graphemes[region.range()], region.index)
+                .map(|index| TrackedRegion::new(index, region.length))
+        };
+
+        let mut password = MutablePassword::new(ProtectedPassword::new(Password::with_formatting(
+            string, formatting,
+        )));
+        for block in self.password.blocks() {
+            match relocate(&TrackedRegion::new(block.range.start, block.range.len())) {
+                Some(found) => password.protect_range(found.range(), &block.label),
+                None => warn!(
+                    target: SOLVER,
+                    "Lost protected {} block {:?}",
+                    block,
+                    old_graphemes[block.range.clone()].concat()
+                ),
+            }
+        }
+
+        let old_password = &self.password;
+        let mut relocate_tracked = |id: Option<RegionId>| {
+            let found = relocate(old_password.tracked(id?)?)?;
+            Some(password.track(found))
+        };
+        self.length_string = relocate_tracked(self.length_string);
+        self.time_string = relocate_tracked(self.time_string);
+        self.safe_zone = relocate_tracked(self.safe_zone);
+        // Paul may have hatched since we last saw him
+        let paul = self.paul.and_then(|id| {
+            let paul = self.password.tracked(id)?;
+            ["🥚", "🐔"]
+                .into_iter()
+                .filter_map(|paul_grapheme| {
+                    find_graphemes(&graphemes, &[paul_grapheme], paul.index)
+                })
+                .min_by_key(|index| index.abs_diff(paul.index))
+        });
+        self.paul = paul.map(|index| password.track(TrackedRegion::new(index, 1)));
+        let regions = std::mem::take(&mut self.regions);
+        for (id, region) in regions {
+            let Some(string) = self.password.tracked(id) else {
+                continue;
+            };
+            let contents = region.contents.graphemes(true).collect::<Vec<_>>();
+            match find_graphemes(&graphemes, &contents, string.index) {
+                Some(index) => {
+                    let id = password.track(TrackedRegion::new(index, contents.len()));
+                    self.regions.push((id, region));
+                }
+                None => warn!(target: SOLVER, "Lost {:?} region", region.name),
+            }
+        }
+        if self.safe_zone.is_none() {
+            self.regions.clear();
+        }
+
+        self.password = password;
+        debug!(
+            target: SOLVER,
+            "Protected blocks: {}",
+            self.password
+                .blocks()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        true
+    }
+}
+
+/// Find the grapheme index of `needle` in `haystack`, picking the occurrence closest to `near`
+/// if there are several.
+fn find_graphemes(haystack: &[&str], needle: &[&str], near: usize) -> Option<usize> {
+    if needle.is_empty() || needle.len() > haystack.len() {
+        return None;
+    }
+    haystack
+        .windows(needle.len())
+        .enumerate()
+        .filter(|(_, window)| *window == needle)
+        .map(|(index, _)| index)
+        .min_by_key(|index| index.abs_diff(near))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_graphemes;
+    use crate::{
+        password::{Format, MutablePassword, TrackedRegion},
+        solver::Solver,
+    };
+
+    #[test]
+    fn adopt_password() {
+        let mut password = MutablePassword::from_str("🥚abc12xyz");
+        password.protect_range(0..1, "Egg");
+        password.protect_range(4..6, "IncludeLength");
+        let mut solver = Solver {
+            length_string: Some(password.track(TrackedRegion::new(4, 2))),
+            time_string: Some(password.track(TrackedRegion::new(6, 2))),
+            paul: Some(password.track(TrackedRegion::new(0, 1))),
+            password,
+            ..Default::default()
+        };
+
+        // The game dropped a letter, and something inserted a "q"
+        let page = "🥚qac12xz";
+        assert!(!solver.adopt_password(page, vec![Format::default(); 3]));
+        assert!(solver.adopt_password(page, vec![Format::default(); 8]));
+        assert_eq!(solver.password.as_str(), page);
+        assert_eq!(
+            solver.password.protected_graphemes().collect::<Vec<_>>(),
+            [true, false, false, false, true, true, false, false]
+        );
+        assert_eq!(
+            solver
+                .password
+                .blocks()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["Egg 0..1", "IncludeLength 4..6"]
+        );
+        assert_eq!(
+            solver.password.tracked(solver.length_string.unwrap()),
+            Some(&TrackedRegion::new(4, 2))
+        );
+        // "xy" is gone
+        assert!(solver.time_string.is_none());
+        assert_eq!(solver.paul_index(), Some(0));
+
+        // Paul is found even if he's hatched
+        assert!(solver.adopt_password("z🐔qac12xz", vec![Format::default(); 9]));
+        assert_eq!(solver.paul_index(), Some(1));
+    }
+
+    #[test]
+    fn find_closest() {
+        let haystack = ["a", "b", "a", "b", "a", "b"];
+        assert_eq!(find_graphemes(&haystack, &["a", "b"], 0), Some(0));
+        assert_eq!(find_graphemes(&haystack, &["a", "b"], 3), Some(2));
+        assert_eq!(find_graphemes(&haystack, &["b", "b"], 0), None);
+    }
+}
@@ -0,0 +1,330 @@
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(feature = "native")]
+use std::time::Instant;
+use strum::IntoEnumIterator;
+
+#[cfg(feature = "native")]
+use super::has_sacrificed_letter;
+use super::{video_candidates, youtube_url, Solver};
+#[cfg(feature = "native")]
+use crate::youtube;
+use crate::{
+    game::{rule::RuleKind, ruleset::RuleSet, GameState, Rule},
+    logging::SOLVER,
+    password::helpers::{get_digits, get_elements, get_roman_numerals},
+    storage::{from_record, to_record, Artifact, StorageBackend, StorageError, STORAGE},
+};
+
+/// Storage stream for whether each rule was solved in previous runs.
+const OUTCOMES_STREAM: &str = "stats.rule_outcomes";
+
+/// Check whether the given rule can still be satisfied, for rules which commonly doom a run
+/// but can be checked as soon as their instance data is known. Returns the reason if not.
+pub fn infeasibility(solver: &Solver, rule: &Rule, game_state: &GameState) -> Option<String> {
+    match rule {
+        Rule::Youtube(seconds) => {
+            let candidates = video_candidates(*seconds, &solver.sacrificed_letters);
+            let Some(best) = candidates.first() else {
+                return Some(format!("no usable video of length {}s", seconds));
+            };
+            if candidates.iter().any(|video_id| {
+                solver
+                    .video_conflict(&youtube_url(video_id), &game_state.ruleset)
+                    .is_none()
+            }) {
+                None
+            } else {
+                solver.video_conflict(&youtube_url(best), &game_state.ruleset)
+            }
+        }
+        Rule::AtomicNumber => {
+            // Protected elements can never be removed, and nor can elements which are also roman
+            // numerals, as they're left for the roman numeral rules
+            let password = solver.password.as_str();
+            let atomic_sum = get_elements(password)
+                .iter()
+                .filter(|(e, i)| {
+                    solver.password.is_protected(*i) || !get_roman_numerals(e.symbol).is_empty()
+                })
+                .map(|(e, _)| e.atomic_number)
+                .sum::<u32>();
+            if atomic_sum > game_state.ruleset.atomic_number_sum {
+                Some(format!(
+                    "elements which can't be removed already sum to {}",
+                    atomic_sum
+                ))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+impl Solver {
+    /// Choose the best video for the YouTube rule with the given duration which fits the
+    /// password as it is: none of its letters sacrificed, and its URL not pushing the digit sum,
+    /// atomic number sum or roman numeral product past what the given rule set allows.
+    pub fn choose_video(&self, seconds: u32, ruleset: &RuleSet) -> Option<String> {
+        video_candidates(seconds, &self.sacrificed_letters)
+            .into_iter()
+            .find(
+                |video_id| match self.video_conflict(&youtube_url(video_id), ruleset) {
+                    Some(reason) => {
+                        debug!(target: SOLVER, "Skipping video {}: {}", video_id, reason);
+                        false
+                    }
+                    None => true,
+                },
+            )
+    }
+
+    /// Search YouTube for a video for the YouTube rule with the given duration which fits the
+    /// password as it is, for when the database has none (see `choose_video`). A video found is
+    /// added to the database, so planning the rule picks it up from there. This blocks for up
+    /// to the search budget (or until the deadline, if that's sooner), so it's left to the web
+    /// driver to do before planning.
+    #[cfg(feature = "native")]
+    pub fn search_video(
+        &self,
+        seconds: u32,
+        ruleset: &RuleSet,
+        deadline: Option<Instant>,
+    ) -> Option<String> {
+        youtube::search_video(seconds, deadline, |video_id| {
+            !has_sacrificed_letter(video_id, &self.sacrificed_letters)
+                && self
+                    .video_conflict(&youtube_url(video_id), ruleset)
+                    .is_none()
+        })
+    }
+
+    /// Why adding the given YouTube URL would make another rule impossible to satisfy, if it
+    /// would. The URL will be protected, so its digits, elements and roman numerals can never
+    /// be removed, and nor can the ones already protected (or elements which are also roman
+    /// numerals, like the "V" in "XXXV", as they're left for the roman numeral rules).
+    pub fn video_conflict(&self, url: &str, ruleset: &RuleSet) -> Option<String> {
+        let password = self.password.as_str();
+
+        let digit_sum = get_digits(password)
+            .iter()
+            .filter(|(_, i)| self.password.is_protected(*i))
+            .chain(get_digits(url).iter())
+            .map(|(d, _)| d)
+            .sum::<u32>();
+        if digit_sum > ruleset.digit_sum {
+            return Some(format!("{} would make the digit sum {}", url, digit_sum));
+        }
+
+        let atomic_sum = get_elements(password)
+            .iter()
+            .filter(|(e, i)| {
+                self.password.is_protected(*i) || !get_roman_numerals(e.symbol).is_empty()
+            })
+            .chain(get_elements(url).iter())
+            .map(|(e, _)| e.atomic_number)
+            .sum::<u32>();
+        if atomic_sum > ruleset.atomic_number_sum {
+            return Some(format!(
+                "{} would make the atomic number sum {}",
+                url, atomic_sum
+            ));
+        }
+
+        let roman_product = get_roman_numerals(password)
+            .iter()
+            .filter(|(_, start, _)| self.password.is_protected(*start))
+            .chain(get_roman_numerals(url).iter())
+            .map(|(n, _, _)| n)
+            .product::<u64>();
+        if 35 % roman_product != 0 {
+            return Some(format!(
+                "{} would make the roman numeral product {}",
+                url, roman_product
+            ));
+        }
+
+        None
+    }
+}
+
+/// Whether a rule was solved in a run, as persisted for analysing which rules doom runs.
+#[derive(Debug, Serialize, Deserialize)]
+struct RuleOutcome {
+    rule: RuleKind,
+    solved: bool,
+}
+
+impl Artifact for RuleOutcome {
+    const KIND: &'static str = "rule outcome";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+/// Estimates of how likely each rule is to be solved, from previous runs.
+#[derive(Debug, Default)]
+pub struct RuleStats {
+    /// Number of runs in which each rule was solved, and the number it was seen in.
+    outcomes: HashMap<RuleKind, (usize, usize)>,
+}
+
+impl RuleStats {
+    /// Load the outcomes of previous runs.
+    pub fn load() -> Self {
+        match Self::from_storage(&*STORAGE.lock().unwrap()) {
+            Ok(stats) => stats,
+            Err(e) => {
+                warn!(target: SOLVER, "Failed to load rule outcome stats: {}", e);
+                RuleStats::default()
+            }
+        }
+    }
+
+    fn from_storage(storage: &dyn StorageBackend) -> Result<Self, StorageError> {
+        let mut stats = RuleStats::default();
+        for record in storage.load(OUTCOMES_STREAM)? {
+            let outcome: RuleOutcome = from_record(record)?;
+            let (solved, seen) = stats.outcomes.entry(outcome.rule).or_default();
+            *seen += 1;
+            if outcome.solved {
+                *solved += 1;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Estimated probability that the given rule will be solved (with Laplace smoothing, so
+    /// rules we've not seen before are a coin toss).
+    pub fn success_probability(&self, rule: RuleKind) -> f64 {
+        let (solved, seen) = self.outcomes.get(&rule).copied().unwrap_or_default();
+        (solved + 1) as f64 / (seen + 2) as f64
+    }
+}
+
+/// Record the outcome of a run: every rule up to `highest_rule` was solved, except for
+/// `failed_rule` (if any).
+pub fn record_run(highest_rule: usize, failed_rule: Option<RuleKind>) {
+    if let Err(e) = record_run_to(&mut *STORAGE.lock().unwrap(), highest_rule, failed_rule) {
+        warn!(target: SOLVER, "Failed to record rule outcome stats: {}", e);
+    }
+}
+
+fn record_run_to(
+    storage: &mut dyn StorageBackend,
+    highest_rule: usize,
+    failed_rule: Option<RuleKind>,
+) -> Result<(), StorageError> {
+    for rule in RuleKind::iter().filter(|rule| rule.number() <= highest_rule) {
+        let outcome = RuleOutcome {
+            rule,
+            solved: Some(rule) != failed_rule,
+        };
+        storage.append(OUTCOMES_STREAM, &to_record(&outcome)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{infeasibility, record_run_to, RuleOutcome, RuleStats, OUTCOMES_STREAM};
+    use crate::{
+        game::{rule::RuleKind, GameState, Rule},
+        password::{helpers::get_digits, MutablePassword},
+        solver::{video_candidates, youtube_url, Solver},
+        storage::{from_record, MemoryStorage, StorageBackend},
+        youtube::database::videos,
+    };
+
+    #[test]
+    fn youtube_feasibility() {
+        let game_state = GameState::default();
+        let solver = Solver {
+            password: MutablePassword::from_str("abc"),
+            ..Default::default()
+        };
+        assert!(infeasibility(&solver, &Rule::Youtube(u32::MAX), &game_state).is_some());
+
+        // Videos with an M can never satisfy the roman numeral product rule
+        let videos = videos();
+        let (seconds, video_id) = videos
+            .iter()
+            .find(|(_, id)| id.contains('M'))
+            .expect("some video has an M");
+        assert!(solver
+            .video_conflict(&youtube_url(video_id), &game_state.ruleset)
+            .is_some());
+        assert!(solver.choose_video(seconds, &game_state.ruleset).as_deref() != Some(video_id));
+
+        // Nothing fits once the protected digits are over the limit
+        let mut solver = Solver {
+            password: MutablePassword::from_str("9999"),
+            ..Default::default()
+        };
+        for index in 0..4 {
+            solver.password.protect(index);
+        }
+        let url = youtube_url(&video_candidates(seconds, &[])[0]);
+        let digit_sum = 36 + get_digits(&url).iter().map(|(d, _)| d).sum::<u32>();
+        assert_eq!(
+            infeasibility(&solver, &Rule::Youtube(seconds), &game_state),
+            Some(format!("{} would make the digit sum {}", url, digit_sum))
+        );
+
+        assert!(infeasibility(&solver, &Rule::Number, &game_state).is_none());
+    }
+
+    #[test]
+    fn atomic_number_feasibility() {
+        let game_state = GameState::default();
+        let mut solver = Solver {
+            password: MutablePassword::from_str("UUUUU"),
+            ..Default::default()
+        };
+        assert!(infeasibility(&solver, &Rule::AtomicNumber, &game_state).is_none());
+
+        // Uranium is 92, so five protected ones are over the limit of 200
+        for index in 0..5 {
+            solver.password.protect(index);
+        }
+        assert_eq!(
+            infeasibility(&solver, &Rule::AtomicNumber, &game_state),
+            Some("elements which can't be removed already sum to 460".to_owned())
+        );
+    }
+
+    #[test]
+    fn record_run() {
+        let mut storage = MemoryStorage::default();
+        record_run_to(&mut storage, 3, Some(RuleKind::Number)).unwrap();
+        let outcomes = storage
+            .load(OUTCOMES_STREAM)
+            .unwrap()
+            .into_iter()
+            .map(|record| {
+                let outcome: RuleOutcome = from_record(record).unwrap();
+                (outcome.rule, outcome.solved)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            vec![
+                (RuleKind::MinLength, true),
+                (RuleKind::Number, false),
+                (RuleKind::Uppercase, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn stats() {
+        let mut storage = MemoryStorage::default();
+        record_run_to(&mut storage, 2, None).unwrap();
+        record_run_to(&mut storage, 2, None).unwrap();
+        record_run_to(&mut storage, 2, Some(RuleKind::Number)).unwrap();
+        let stats = RuleStats::from_storage(&storage).unwrap();
+        assert_eq!(stats.success_probability(RuleKind::MinLength), 0.8);
+        assert_eq!(stats.success_probability(RuleKind::Number), 0.6);
+        assert_eq!(stats.success_probability(RuleKind::Youtube), 0.5);
+    }
+}
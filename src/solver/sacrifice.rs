@@ -0,0 +1,242 @@
+use lazy_static::lazy_static;
+use std::collections::HashSet;
+
+use super::Solver;
+use crate::{
+    game::{
+        rule::{RuleKind, AFFIRMATIONS, MONTHS, SPONSORS},
+        GameState,
+    },
+    password::helpers::{get_elements, get_letters},
+};
+
+/// Letters which are never sacrificed: hex digits (to avoid making the hex color rule harder
+/// to satisfy) and the roman numerals V and X.
+const NEVER_SACRIFICED: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'v', 'x'];
+
+lazy_static! {
+    /// Letters the solver usually sacrifices: those it rates as safe to sacrifice before
+    /// anything is in the password, as no sponsor or affirmation needs them.
+    pub static ref COMMON_SACRIFICES: Vec<char> = Solver::default()
+        .sacrifice_risks(&GameState::default())
+        .into_iter()
+        .filter(|risk| risk.alternatives.is_empty())
+        .map(|risk| risk.letter)
+        .collect();
+}
+
+/// How risky it would be to sacrifice a letter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SacrificeRisk {
+    pub letter: char,
+    /// Rules which would be broken by removing each instance of the letter from the password.
+    pub removal_impact: usize,
+    /// Instances of the letter in the password, which would have to be removed.
+    pub occurrences: usize,
+    /// Strings rules have needed (e.g., the sponsor, Wordle answer and element symbols) which
+    /// contain the letter, so couldn't be added again (e.g., after a fire).
+    pub known_strings: Vec<String>,
+    /// For rules we haven't chosen a string for yet, how many of the strings which would
+    /// satisfy them contain the letter, out of how many.
+    pub alternatives: Vec<(RuleKind, usize, usize)>,
+}
+
+impl SacrificeRisk {
+    /// Whether sacrificing the letter would leave some rule impossible to satisfy.
+    pub fn blocks_rule(&self) -> bool {
+        self.alternatives
+            .iter()
+            .any(|(_, containing, total)| containing == total)
+    }
+
+    /// Key to sort letters by, lowest risk first. Ties are broken by preferring letters which
+    /// aren't in the password (so there's less to remove), or with `prefer_present`, letters
+    /// which are (to shorten the password).
+    fn sort_key(&self, prefer_present: bool) -> impl Ord {
+        let occurrences = if prefer_present {
+            -(self.occurrences as isize)
+        } else {
+            self.occurrences as isize
+        };
+        (
+            self.blocks_rule(),
+            self.removal_impact,
+            self.known_strings.len(),
+            self.alternatives
+                .iter()
+                .map(|(_, containing, _)| containing)
+                .sum::<usize>(),
+            occurrences,
+            self.letter,
+        )
+    }
+}
+
+impl Solver {
+    /// Assess the risk of sacrificing each letter we could sacrifice (those without any
+    /// protected instances in the password), lowest risk first.
+    pub fn sacrifice_risks(&self, game_state: &GameState) -> Vec<SacrificeRisk> {
+        let letters = get_letters(self.password.as_str());
+        let protected_letters = letters
+            .iter()
+            .filter(|(_, index)| self.password.is_protected(*index))
+            .map(|(ch, _)| ch.to_ascii_lowercase())
+            .collect::<HashSet<_>>();
+
+        let known_strings = self.chosen_strings();
+        let alternatives = self.alternatives();
+
+        let mut risks = ('a'..='z')
+            .filter(|letter| {
+                !NEVER_SACRIFICED.contains(letter) && !protected_letters.contains(letter)
+            })
+            .map(|letter| SacrificeRisk {
+                letter,
+                removal_impact: self.letter_removal_impact(letter, game_state),
+                occurrences: letters
+                    .iter()
+                    .filter(|(ch, _)| ch.to_ascii_lowercase() == letter)
+                    .count(),
+                known_strings: known_strings
+                    .iter()
+                    .filter(|string| string.to_lowercase().contains(letter))
+                    .cloned()
+                    .collect(),
+                alternatives: alternatives
+                    .iter()
+                    .map(|(kind, options)| {
+                        let containing = options
+                            .iter()
+                            .filter(|option| option.contains(letter))
+                            .count();
+                        (*kind, containing, options.len())
+                    })
+                    .filter(|(_, containing, _)| *containing > 0)
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        let prefer_present = self.strategy.sacrifice_present_letters();
+        risks.sort_by_cached_key(|risk| risk.sort_key(prefer_present));
+        risks
+    }
+
+    /// Strings already chosen for rules which need particular strings: those with outside
+    /// answers we've recorded, the strings in the safe zone, the element symbols in the
+    /// password, and whichever month, sponsor and affirmation are in the password.
+    fn chosen_strings(&self) -> Vec<String> {
+        let password = self.password.as_str().to_lowercase();
+        let mut strings = self
+            .known_strings
+            .values()
+            .chain(self.regions.iter().map(|(_, region)| &region.contents))
+            .map(|s| s.to_lowercase())
+            .chain(
+                get_elements(self.password.as_str())
+                    .into_iter()
+                    .map(|(element, _)| element.symbol.to_lowercase()),
+            )
+            .chain(
+                MONTHS
+                    .iter()
+                    .chain(SPONSORS.iter())
+                    .map(|s| s.to_string())
+                    .chain(AFFIRMATIONS.iter().map(|s| s.replace(' ', "")))
+                    .filter(|s| password.contains(s)),
+            )
+            .collect::<Vec<_>>();
+        strings.sort();
+        strings.dedup();
+        strings
+    }
+
+    /// The strings which could satisfy each rule which needs one of a set of strings, for
+    /// those we haven't chosen one for yet.
+    fn alternatives(&self) -> Vec<(RuleKind, Vec<String>)> {
+        let password = self.password.as_str().to_lowercase();
+        let mut alternatives = Vec::new();
+        if !SPONSORS.iter().any(|s| password.contains(s)) {
+            alternatives.push((
+                RuleKind::Sponsors,
+                SPONSORS.iter().map(|s| s.to_string()).collect(),
+            ));
+        }
+        let affirmations = AFFIRMATIONS
+            .iter()
+            .map(|s| s.replace(' ', ""))
+            .collect::<Vec<_>>();
+        if !affirmations.iter().any(|s| password.contains(s)) {
+            alternatives.push((RuleKind::Affirmation, affirmations));
+        }
+        alternatives
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::COMMON_SACRIFICES;
+    use crate::{
+        game::{rule::RuleKind, Game},
+        password::MutablePassword,
+        solver::Solver,
+    };
+
+    #[test]
+    fn common_sacrifices() {
+        let mut letters = COMMON_SACRIFICES.clone();
+        letters.sort();
+        assert_eq!(letters, vec!['j', 'q', 'z']);
+    }
+
+    #[test]
+    fn sacrifice_risks_planned_strings() {
+        let game = Game::default();
+        let solver = Solver {
+            password: MutablePassword::from_str("mayOgHepepsi"),
+            ..Default::default()
+        };
+        let risks = solver.sacrifice_risks(&game.state);
+        let risk = |letter| risks.iter().find(|r| r.letter == letter).unwrap();
+
+        // The month and the elements would need adding again after a fire
+        assert_eq!(risk('y').known_strings, vec!["may".to_owned()]);
+        assert_eq!(risk('g').known_strings, vec!["og".to_owned()]);
+        assert_eq!(risk('h').known_strings, vec!["he".to_owned()]);
+        assert_eq!(risk('p').known_strings, vec!["pepsi".to_owned()]);
+        assert!(risk('k').known_strings.is_empty());
+    }
+
+    #[test]
+    fn sacrifice_risks() {
+        let game = Game::default();
+        let mut solver = Solver {
+            password: MutablePassword::from_str("pepsigqq"),
+            ..Default::default()
+        };
+        solver
+            .known_strings
+            .insert(RuleKind::Wordle, "JUMPY".to_owned());
+        let risks = solver.sacrifice_risks(&game.state);
+
+        // No hex digits or V/X, and nothing from the (unprotected) password is ruled out
+        assert!(!risks.iter().any(|r| "abcdefvx".contains(r.letter)));
+        assert_eq!(risks.len(), 18);
+
+        // Every affirmation contains "i", "a" and "m"
+        let i = risks.iter().find(|r| r.letter == 'i').unwrap();
+        assert!(i.blocks_rule());
+        assert_eq!(i.alternatives, vec![(RuleKind::Affirmation, 3, 3)]);
+        assert_eq!(i.occurrences, 1);
+
+        // Letters from the Wordle answer (or sponsor) are risky, but less so than blocking a rule
+        let j = risks.iter().find(|r| r.letter == 'j').unwrap();
+        assert_eq!(j.known_strings, vec!["jumpy".to_owned()]);
+        let position = |letter| risks.iter().position(|r| r.letter == letter).unwrap();
+        assert!(position('j') < position('i'));
+
+        // The safest letters aren't in anything we know of, or the password
+        assert_eq!(
+            risks.iter().take(2).map(|r| r.letter).collect::<Vec<_>>(),
+            vec!['k', 'z']
+        );
+    }
+}
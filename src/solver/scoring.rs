@@ -0,0 +1,225 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::current_time_string;
+use crate::{
+    game::GameState,
+    password::{
+        helpers::{get_digits, get_elements, get_roman_numerals},
+        MutablePassword,
+    },
+};
+
+/// The highest cost of a string we'll accept without rerolling it (when the game lets us),
+/// before we've learnt anything about the distribution of costs.
+pub const MAX_ACCEPTABLE_COST: u32 = 2;
+/// Number of rerolls of a single string before giving up and taking whatever we have.
+pub const DEFAULT_MAX_REROLLS: usize = 200;
+/// Digit sum to leave for the length string, which is only chosen once the strings we can
+/// reroll are in the password (103 and 211 are the lengths over 100 within it).
+const LENGTH_DIGIT_SUM: u32 = 4;
+
+/// Limits on how hard we try to find a cheap string when rerolling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoringBudget {
+    /// Highest cost to accept without rerolling. Learnt from previous runs if not set.
+    pub max_cost: Option<u32>,
+    /// Number of rerolls of a single string after which we take whatever we have, even if it
+    /// can't be used, rather than rerolling forever.
+    pub max_rerolls: usize,
+}
+
+impl Default for ScoringBudget {
+    fn default() -> Self {
+        ScoringBudget {
+            max_cost: None,
+            max_rerolls: DEFAULT_MAX_REROLLS,
+        }
+    }
+}
+
+/// How much including a string in the password would constrain later rules.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StringScore {
+    /// Sum of the digits in the string (counts against rule 5's budget).
+    pub digit_sum: u32,
+    /// Number of graphemes which are part of a roman numeral other than "I" (these break
+    /// rule 9, as they can't be removed from a protected string).
+    pub roman_numerals: usize,
+    /// Sum of the atomic numbers of the elements in the string (counts against rule 18's
+    /// budget).
+    pub atomic_number_sum: u32,
+    /// Number of graphemes which are sacrificed letters (these break rule 25).
+    pub sacrificed_letters: usize,
+}
+
+impl StringScore {
+    /// A single cost combining all the constraints. Strings containing sacrificed letters
+    /// can never be used, so have maximum cost.
+    pub fn cost(&self) -> u32 {
+        if self.sacrificed_letters > 0 {
+            return u32::MAX;
+        }
+        self.digit_sum + 10 * self.roman_numerals as u32 + self.atomic_number_sum / 50
+    }
+
+    /// Whether the string can be used at all, given how much more the digits in the password
+    /// can add up to (see `digit_budget`): it mustn't contain sacrificed letters, or take the
+    /// digit sum past rule 5's target.
+    pub fn usable(&self, digit_budget: u32) -> bool {
+        self.sacrificed_letters == 0 && self.digit_sum <= digit_budget
+    }
+}
+
+/// How much the digits added to the password can add up to without taking it past rule 5's
+/// target, given the digits which can't be removed because they're part of a string another
+/// rule needs (e.g., an earlier captcha), and the digits the length and time strings will need
+/// when they're added later.
+pub fn digit_budget(password: &MutablePassword, game_state: &GameState) -> u32 {
+    let locked_digit_sum = password
+        .annotated()
+        .filter(|(_, _, _, protected)| *protected)
+        .filter_map(|(_, grapheme, _, _)| {
+            let mut chars = grapheme.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => ch.to_digit(10),
+                _ => None,
+            }
+        })
+        .sum::<u32>();
+    let time_digit_sum = get_digits(&current_time_string(game_state))
+        .iter()
+        .map(|(d, _)| d)
+        .sum::<u32>();
+    game_state
+        .ruleset
+        .digit_sum
+        .saturating_sub(locked_digit_sum + LENGTH_DIGIT_SUM + time_digit_sum)
+}
+
+/// Score a string we need to add to the password (e.g., a captcha answer or hex color).
+pub fn score_string(string: &str, sacrificed_letters: &[char]) -> StringScore {
+    let digit_sum = get_digits(string).iter().map(|(d, _)| d).sum();
+    let roman_numerals = get_roman_numerals(string)
+        .iter()
+        .filter(|(n, _, _)| *n != 1)
+        .map(|(_, _, length)| length)
+        .sum();
+    let atomic_number_sum = get_elements(string)
+        .iter()
+        .map(|(e, _)| e.atomic_number)
+        .sum();
+    let sacrificed_letters = string
+        .graphemes(true)
+        .filter(|g| {
+            let mut chars = g.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => sacrificed_letters.contains(&ch.to_ascii_lowercase()),
+                _ => false,
+            }
+        })
+        .count();
+    StringScore {
+        digit_sum,
+        roman_numerals,
+        atomic_number_sum,
+        sacrificed_letters,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::prelude::*;
+    use std::sync::Arc;
+
+    use super::{digit_budget, score_string, StringScore, MAX_ACCEPTABLE_COST};
+    use crate::{
+        game::{
+            clock::{MockClock, SharedClock},
+            data::CAPTCHAS,
+            GameState,
+        },
+        password::MutablePassword,
+    };
+
+    fn is_acceptable(score: StringScore) -> bool {
+        score.cost() <= MAX_ACCEPTABLE_COST
+    }
+
+    #[test]
+    fn score() {
+        let score = score_string("2b827", &[]);
+        assert_eq!(score.digit_sum, 19);
+        assert_eq!(score.roman_numerals, 0);
+        assert!(!is_acceptable(score));
+
+        // Roman numerals (other than "I") and elements
+        let score = score_string("XHe", &[]);
+        assert_eq!(score.digit_sum, 0);
+        assert_eq!(score.roman_numerals, 1);
+        assert_eq!(score.atomic_number_sum, 2);
+        assert!(!is_acceptable(score));
+
+        // Sacrificed letters are unusable, regardless of case
+        let score = score_string("#00ff00", &['f', 'q']);
+        assert_eq!(score.sacrificed_letters, 2);
+        assert_eq!(score.cost(), u32::MAX);
+        assert!(is_acceptable(score_string("#00ff00", &['g', 'q'])));
+        assert!(!is_acceptable(score_string("Qa", &['g', 'q'])));
+
+        // Non-ASCII graphemes are handled
+        let score = score_string("🥚é1", &['e']);
+        assert_eq!(score.digit_sum, 1);
+        assert_eq!(score.sacrificed_letters, 0);
+    }
+
+    #[test]
+    fn usable_digits() {
+        // 1:05, so 6 of the time string's digits and 4 of the length string's are held back
+        let mut game_state = GameState {
+            clock: SharedClock::new(Arc::new(MockClock::new(
+                Local.with_ymd_and_hms(2023, 7, 12, 1, 5, 0).unwrap(),
+            ))),
+            ..Default::default()
+        };
+        let mut password = MutablePassword::from_str("99ax7y8");
+        assert_eq!(digit_budget(&password, &game_state), 15);
+        for index in 3..7 {
+            password.protect(index);
+        }
+        assert_eq!(digit_budget(&password, &game_state), 0);
+        game_state.ruleset.digit_sum = 40;
+        assert_eq!(digit_budget(&password, &game_state), 15);
+
+        assert!(score_string("d3", &[]).usable(10));
+        assert!(score_string("9a1", &[]).usable(10));
+        assert!(!score_string("9a2", &[]).usable(10));
+        assert!(!score_string("d3", &['d']).usable(25));
+    }
+
+    #[test]
+    fn score_captchas() {
+        for captcha in CAPTCHAS.iter() {
+            let score = score_string(captcha.answer, &[]);
+            assert_eq!(score.digit_sum, captcha.digit_sum);
+            // Captchas are all lowercase, so never contain roman numerals or elements
+            assert_eq!(score.cost(), captcha.digit_sum);
+        }
+
+        // Letter-only captchas are always acceptable
+        let letter_only = CAPTCHAS
+            .iter()
+            .map(|c| c.answer)
+            .filter(|c| c.chars().all(|ch| ch.is_ascii_alphabetic()))
+            .collect::<Vec<_>>();
+        assert!(!letter_only.is_empty());
+        assert!(letter_only
+            .iter()
+            .all(|c| is_acceptable(score_string(c, &[]))));
+
+        // Unless they contain a sacrificed letter
+        assert!(letter_only.iter().any(|c| {
+            let first = c.chars().next().unwrap();
+            !is_acceptable(score_string(c, &[first]))
+        }));
+    }
+}
@@ -0,0 +1,166 @@
+use serde::Deserialize;
+use std::{fmt::Debug, sync::Arc};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::SolverRng;
+use crate::password::format::FontFamily;
+
+/// Choices the solver makes where several solutions are equally valid, which decide what kind
+/// of password we end up with.
+pub trait Strategy: Debug + Send + Sync {
+    /// Choose one of the given strings (any of which satisfies a rule, e.g., the months) to
+    /// add to the password.
+    fn choose(&self, options: &[&'static str], rng: &mut SolverRng) -> &'static str;
+
+    /// The grapheme used to pad the password out to a given length.
+    fn padding(&self) -> &'static str {
+        "-"
+    }
+
+    /// The font family the padding is set in.
+    fn padding_font(&self) -> FontFamily {
+        FontFamily::default()
+    }
+
+    /// Whether to sacrifice letters which are in the password (and can be removed without
+    /// breaking anything), rather than letters which aren't.
+    fn sacrifice_present_letters(&self) -> bool {
+        false
+    }
+}
+
+/// Type as little as we can, choosing randomly between the options with the fewest graphemes.
+/// Used both to clear the game as quickly as possible, and (sacrificing letters which are in
+/// the password, as removing them takes longer) to keep the final password as short as possible.
+#[derive(Debug, Default)]
+pub struct Shortest {
+    pub sacrifice_present_letters: bool,
+}
+
+impl Strategy for Shortest {
+    fn choose(&self, options: &[&'static str], rng: &mut SolverRng) -> &'static str {
+        let length = |option: &&str| option.graphemes(true).count();
+        let shortest = options.iter().map(length).min().expect("no options");
+        let options = options
+            .iter()
+            .copied()
+            .filter(|option| length(option) == shortest)
+            .collect::<Vec<_>>();
+        rng.choose::<&str>(&options)
+    }
+
+    fn sacrifice_present_letters(&self) -> bool {
+        self.sacrifice_present_letters
+    }
+}
+
+/// Make the password as varied and silly as possible.
+#[derive(Debug, Default)]
+pub struct MaxStyle;
+
+impl Strategy for MaxStyle {
+    fn choose(&self, options: &[&'static str], rng: &mut SolverRng) -> &'static str {
+        rng.choose::<&str>(options)
+    }
+
+    fn padding(&self) -> &'static str {
+        "✨"
+    }
+
+    fn padding_font(&self) -> FontFamily {
+        FontFamily::ComicSans
+    }
+}
+
+/// A strategy shared between the solver and the driver. Defaults to `Fastest`.
+#[derive(Debug, Clone)]
+pub struct SharedStrategy(Arc<dyn Strategy>);
+
+impl SharedStrategy {
+    pub fn new(strategy: Arc<dyn Strategy>) -> Self {
+        SharedStrategy(strategy)
+    }
+
+    /// Choose one of the given strings to add to the password.
+    pub fn choose(&self, options: &[&'static str], rng: &mut SolverRng) -> &'static str {
+        self.0.choose(options, rng)
+    }
+
+    /// The grapheme used to pad the password out to a given length.
+    pub fn padding(&self) -> &'static str {
+        self.0.padding()
+    }
+
+    /// The font family the padding is set in.
+    pub fn padding_font(&self) -> FontFamily {
+        self.0.padding_font()
+    }
+
+    /// Whether to sacrifice letters which are in the password.
+    pub fn sacrifice_present_letters(&self) -> bool {
+        self.0.sacrifice_present_letters()
+    }
+}
+
+impl Default for SharedStrategy {
+    fn default() -> Self {
+        StrategyKind::default().into()
+    }
+}
+
+/// The built-in strategies, for choosing one from the command line or config file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum StrategyKind {
+    #[default]
+    Fastest,
+    ShortestPassword,
+    MaxStyle,
+}
+
+impl From<StrategyKind> for SharedStrategy {
+    fn from(kind: StrategyKind) -> Self {
+        SharedStrategy::new(match kind {
+            StrategyKind::Fastest => Arc::new(Shortest {
+                sacrifice_present_letters: false,
+            }),
+            StrategyKind::ShortestPassword => Arc::new(Shortest {
+                sacrifice_present_letters: true,
+            }),
+            StrategyKind::MaxStyle => Arc::new(MaxStyle),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SharedStrategy, StrategyKind};
+    use crate::{
+        game::rule::{MONTHS, SPONSORS},
+        password::format::FontFamily,
+        solver::SolverRng,
+    };
+
+    #[test]
+    fn choices() {
+        let mut rng = SolverRng::seed_from_u64(1);
+        let fastest = SharedStrategy::from(StrategyKind::Fastest);
+        assert_eq!(fastest.choose(&MONTHS, &mut rng), "may");
+        assert_eq!(fastest.padding(), "-");
+        assert_eq!(fastest.padding_font(), FontFamily::Monospace);
+        assert!(!fastest.sacrifice_present_letters());
+        for _ in 0..10 {
+            assert_ne!(fastest.choose(&SPONSORS, &mut rng), "starbucks");
+        }
+
+        let shortest = SharedStrategy::from(StrategyKind::ShortestPassword);
+        assert_eq!(shortest.choose(&MONTHS, &mut rng), "may");
+        assert!(shortest.sacrifice_present_letters());
+
+        let style = SharedStrategy::from(StrategyKind::MaxStyle);
+        assert!(!style.sacrifice_present_letters());
+        assert_eq!(style.padding_font(), FontFamily::ComicSans);
+        assert!((0..50).any(|_| style.choose(&MONTHS, &mut rng) != "may"));
+    }
+}
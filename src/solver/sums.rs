@@ -0,0 +1,303 @@
+use log::debug;
+use std::cmp::Reverse;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::Solver;
+use crate::{
+    game::{rule::RuleKind, GameState, Rule},
+    logging::SOLVER,
+    password::{
+        helpers::{get_digits, get_elements, get_roman_numerals},
+        Change,
+    },
+};
+
+/// The sums constrained by rules 5 and 18.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Sums {
+    digits: u32,
+    atomic_numbers: u32,
+}
+
+impl Sums {
+    fn of(string: &str) -> Self {
+        Sums {
+            digits: get_digits(string).iter().map(|(d, _)| d).sum(),
+            atomic_numbers: get_elements(string)
+                .iter()
+                .map(|(e, _)| e.atomic_number)
+                .sum(),
+        }
+    }
+}
+
+/// The password as removals and replacements are planned, so the effect of each one can be
+/// checked against the others (e.g., removing the "9" from "N9a" makes sodium).
+struct Draft {
+    graphemes: Vec<String>,
+    protected: Vec<bool>,
+    removed: Vec<bool>,
+}
+
+impl Draft {
+    /// Indices in the original password of the graphemes which haven't been removed.
+    fn kept(&self) -> Vec<usize> {
+        (0..self.graphemes.len())
+            .filter(|i| !self.removed[*i])
+            .collect()
+    }
+
+    /// The password as it would be with the given graphemes removed too.
+    fn without(&self, indices: &[usize]) -> String {
+        self.kept()
+            .into_iter()
+            .filter(|i| !indices.contains(i))
+            .map(|i| self.graphemes[i].as_str())
+            .collect()
+    }
+
+    /// Unprotected non-zero digits which could be removed, as (digit, original index).
+    fn removable_digits(&self) -> Vec<(u32, usize)> {
+        let kept = self.kept();
+        get_digits(&self.without(&[]))
+            .into_iter()
+            .filter(|(d, _)| *d > 0)
+            .map(|(d, i)| (d, kept[i]))
+            .filter(|(_, i)| !self.protected[*i])
+            .collect()
+    }
+
+    /// Elements which could be removed, other than those which are also roman numerals, as the
+    /// original indices of their unprotected graphemes. An element which is only partly
+    /// protected (e.g., filler "H" joined onto the "s" of a protected "spain") is broken up by
+    /// removing the rest of it.
+    fn removable_elements(&self) -> Vec<Vec<usize>> {
+        let kept = self.kept();
+        get_elements(&self.without(&[]))
+            .into_iter()
+            .filter(|(e, _)| get_roman_numerals(e.symbol).is_empty())
+            .map(|(e, i)| {
+                kept[i..i + e.symbol.len()]
+                    .iter()
+                    .copied()
+                    .filter(|i| !self.protected[*i])
+                    .collect::<Vec<_>>()
+            })
+            .filter(|indices| !indices.is_empty())
+            .collect()
+    }
+}
+
+impl Solver {
+    /// Plan changes which satisfy the digits rule and the atomic number rule together, for
+    /// whichever of them are in play, so solving one never breaks the other. Removals are
+    /// chosen greedily, skipping any which would change the other sum (by joining the letters
+    /// either side into a new element), and the shortfall in both sums is made up with one
+    /// append of elements followed by digits. Returns None if there's no such solution (e.g.,
+    /// protected digits already sum to more than 25).
+    pub(super) fn plan_sums(&self, rule: &Rule, game_state: &GameState) -> Option<Vec<Change>> {
+        let digits_in_play =
+            *rule == Rule::Digits || game_state.highest_rule >= RuleKind::Digits.number();
+        let atomic_numbers_in_play = *rule == Rule::AtomicNumber
+            || game_state.highest_rule >= RuleKind::AtomicNumber.number();
+
+        let mut draft = Draft {
+            graphemes: self
+                .password
+                .as_str()
+                .graphemes(true)
+                .map(str::to_owned)
+                .collect(),
+            protected: self.password.protected_graphemes().collect(),
+            removed: vec![false; self.password.len()],
+        };
+        let mut changes = Vec::new();
+        let mut sums = Sums::of(self.password.as_str());
+        let digit_sum_target = game_state.ruleset.digit_sum;
+        let atomic_number_target = game_state.ruleset.atomic_number_sum;
+
+        // Remove digits until the sum is low enough, then reduce a digit to make up the rest
+        while digits_in_play && sums.digits > digit_sum_target {
+            let excess = sums.digits - digit_sum_target;
+            let best = draft
+                .removable_digits()
+                .into_iter()
+                .filter(|(d, _)| *d <= excess)
+                .map(|(_, i)| (Sums::of(&draft.without(&[i])), i))
+                .filter(|(new_sums, _)| new_sums.atomic_numbers == sums.atomic_numbers)
+                .max_by_key(|(new_sums, i)| (Reverse(new_sums.digits), Reverse(*i)));
+            if let Some((new_sums, index)) = best {
+                changes.push(Change::Remove {
+                    index,
+                    ignore_protection: false,
+                });
+                draft.removed[index] = true;
+                sums = new_sums;
+                continue;
+            }
+
+            // Replacing a digit with another never joins letters into new elements
+            let Some((digit, index)) = draft
+                .removable_digits()
+                .into_iter()
+                .filter(|(d, _)| *d > excess)
+                .min()
+            else {
+                debug!(
+                    target: SOLVER,
+                    "Digit sum is {} and we can't reduce it any more :(", sums.digits
+                );
+                return None;
+            };
+            let new_grapheme = (digit - excess).to_string();
+            changes.push(Change::Replace {
+                index,
+                new_grapheme: new_grapheme.clone(),
+                ignore_protection: false,
+            });
+            draft.graphemes[index] = new_grapheme;
+            sums.digits -= excess;
+        }
+
+        // Remove elements, largest first, until the sum is low enough
+        while atomic_numbers_in_play && sums.atomic_numbers > atomic_number_target {
+            let best = draft
+                .removable_elements()
+                .into_iter()
+                .map(|indices| (Sums::of(&draft.without(&indices)), indices))
+                .filter(|(new_sums, _)| {
+                    new_sums.atomic_numbers < sums.atomic_numbers && new_sums.digits == sums.digits
+                })
+                .min_by_key(|(new_sums, _)| new_sums.atomic_numbers);
+            let Some((new_sums, indices)) = best else {
+                debug!(
+                    target: SOLVER,
+                    "Atomic number sum is {} and we can't remove any more :(", sums.atomic_numbers
+                );
+                return None;
+            };
+            for index in indices {
+                changes.push(Change::Remove {
+                    index,
+                    ignore_protection: false,
+                });
+                draft.removed[index] = true;
+            }
+            sums = new_sums;
+        }
+
+        // Make up the shortfalls with the largest elements (other than roman numerals) and
+        // digits that fit, elements first so the digits can't join onto a symbol
+        let mut append = String::new();
+        if atomic_numbers_in_play {
+            let mut to_add = atomic_number_target - sums.atomic_numbers;
+            while to_add > 0 {
+                let element = periodic_table::periodic_table()
+                    .iter()
+                    .filter(|e| get_roman_numerals(e.symbol).is_empty())
+                    .rfind(|e| e.atomic_number <= to_add)
+                    .unwrap();
+                append.push_str(element.symbol);
+                to_add -= element.atomic_number;
+            }
+        }
+        if digits_in_play {
+            let mut to_add = digit_sum_target - sums.digits;
+            while to_add > 0 {
+                let digit = to_add.min(9);
+                append.push_str(&digit.to_string());
+                to_add -= digit;
+            }
+        }
+
+        // Check nothing joined up in a way we didn't expect
+        let result = Sums::of(&(draft.without(&[]) + &append));
+        if (digits_in_play && result.digits != digit_sum_target)
+            || (atomic_numbers_in_play && result.atomic_numbers != atomic_number_target)
+        {
+            debug!(
+                target: SOLVER,
+                "Planned digit and atomic number sums came out as {:?}", result
+            );
+            return None;
+        }
+
+        if !append.is_empty() {
+            changes.push(Change::Append {
+                protected: false,
+                string: append,
+            });
+        }
+        Some(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sums;
+    use crate::{
+        game::{Game, Rule},
+        password::MutablePassword,
+        solver::Solver,
+    };
+
+    #[test]
+    fn digits_and_atomic_numbers() {
+        let mut game = Game::default();
+        game.state.highest_rule = Rule::AtomicNumber.number();
+        let password = "HgHgCaON9aB8!99";
+        assert_eq!(
+            Sums::of(password),
+            Sums {
+                digits: 35,
+                atomic_numbers: 200
+            }
+        );
+
+        // Removing the first 9 would make sodium, so the digits come from elsewhere
+        let mut solver = Solver {
+            password: MutablePassword::from_str(password),
+            ..Default::default()
+        };
+        solver.solve_rule_and_commit(&Rule::Digits, &game.state);
+        assert!(solver.password.as_str().starts_with("HgHgCaON9aB"));
+        for rule in [Rule::Digits, Rule::AtomicNumber] {
+            assert!(rule.validate(solver.password.raw_password(), &game.state));
+        }
+
+        // Both sums are fixed at once
+        let mut solver = Solver {
+            password: MutablePassword::from_str("Hg99999Hg"),
+            ..Default::default()
+        };
+        let changes = solver.plan_sums(&Rule::AtomicNumber, &game.state).unwrap();
+        for change in changes {
+            solver.password.queue_change(change);
+        }
+        solver.password.commit_changes().unwrap();
+        for rule in [Rule::Digits, Rule::AtomicNumber] {
+            assert!(rule.validate(solver.password.raw_password(), &game.state));
+        }
+
+        // Filler joined onto a protected letter is removed on its own ("Hs" is hassium)
+        let mut solver = Solver {
+            password: MutablePassword::from_str("OgHspain"),
+            ..Default::default()
+        };
+        for index in [0, 1, 3, 4, 5, 6, 7] {
+            solver.password.protect(index);
+        }
+        solver.solve_rule_and_commit(&Rule::AtomicNumber, &game.state);
+        assert!(solver.password.as_str().starts_with("Ogspain"));
+        assert!(Rule::AtomicNumber.validate(solver.password.raw_password(), &game.state));
+
+        // Only the rules in play are solved
+        game.state.highest_rule = Rule::Digits.number();
+        let solver = Solver {
+            password: MutablePassword::from_str("Hg9"),
+            ..Default::default()
+        };
+        let changes = solver.plan_sums(&Rule::Digits, &game.state).unwrap();
+        assert_eq!(changes.len(), 1);
+    }
+}
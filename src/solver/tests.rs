@@ -1,10 +1,20 @@
-use super::Solver;
+use chrono::prelude::*;
+use std::sync::Arc;
+
+use super::{goal_length, strategy::StrategyKind, video_candidates, Plan, Solver, StateDelta};
 use crate::{
     game::{
+        clock::{MockClock, SharedClock},
+        lookup::{OfflineLookup, SharedLookup},
+        ruleset::RuleSet,
         Game,
-        {rule::Color, Rule},
+        {
+            rule::{Color, RuleKind},
+            Rule,
+        },
     },
-    password::{Change, FormatChange, MutablePassword},
+    password::{format::FontFamily, Change, ChangeError, FormatChange, MutablePassword},
+    youtube::database::videos,
 };
 
 fn test_setup(rule: Rule, password: &str) -> (Game, Solver) {
@@ -16,6 +26,9 @@ fn test_setup(rule: Rule, password: &str) -> (Game, Solver) {
         length_string: None,
         time_string: None,
         goal_length: None,
+        safe_zone: None,
+        regions: Vec::new(),
+        ..Default::default()
     };
     (game, solver)
 }
@@ -129,6 +142,12 @@ fn rule_roman_multiply() {
     assert!(!rule.validate(solver.password.raw_password(), &game.state));
     solver.solve_rule_and_commit(&rule, &game.state);
     assert!(rule.validate(solver.password.raw_password(), &game.state));
+
+    // Without both a 5 and a 7 to keep, 35 is added
+    let (game, mut solver) = test_setup(rule.clone(), "aXXVa");
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.password.as_str(), "aa XXXV");
+    assert!(rule.validate(solver.password.raw_password(), &game.state));
 }
 
 #[test]
@@ -159,7 +178,7 @@ fn rule_atomic_number() {
 #[test]
 fn rule_skip() {
     let (game, mut solver) = test_setup(Rule::Skip, "foo");
-    let changes = solver.solve_rule(&Rule::Skip, &game.state, 0);
+    let changes = solver.solve_rule(&Rule::Skip, &game.state);
     assert!(changes.unwrap().is_empty());
 }
 
@@ -184,6 +203,39 @@ fn rule_fire() {
     assert!(rule.validate(solver.password.raw_password(), &game.state));
 }
 
+#[test]
+fn safe_zone() {
+    let captcha = Rule::Captcha("4ab2c".into());
+    let hex = Rule::Hex(Color { r: 1, g: 2, b: 3 });
+
+    let (mut game, mut solver) = test_setup(captcha.clone(), "ab");
+    solver.solve_rule_and_commit(&captcha, &game.state);
+    solver.solve_rule_and_commit(&hex, &game.state);
+    solver.password.queue_change(Change::Append {
+        string: "xyz".into(),
+        protected: false,
+    });
+    solver.password.commit_changes().unwrap();
+    assert_eq!(solver.password.as_str(), "ab---4ab2c#010203---xyz");
+
+    // Fire in the zone is repaired before fire elsewhere is removed
+    for index in [4, 6, 17, 21] {
+        solver.password.queue_change(Change::Replace {
+            index,
+            new_grapheme: "🔥".into(),
+            ignore_protection: true,
+        });
+    }
+    solver.password.commit_changes().unwrap();
+    game.state.fire_started = true;
+    let rule = Rule::Fire;
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.password.as_str(), "ab---4ab2c#010203---x🔥z");
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.password.as_str(), "ab---4ab2c#010203---xz");
+    assert!(rule.validate(solver.password.raw_password(), &game.state));
+}
+
 #[test]
 fn rule_strength() {
     let rule = Rule::Strength;
@@ -194,6 +246,17 @@ fn rule_strength() {
     assert!(rule.validate(solver.password.raw_password(), &game.state));
 }
 
+#[test]
+fn starting_password_tracks_paul() {
+    let game = Game::default();
+    let mut solver = Solver::default();
+    let plan = solver.starting_password(&game.state);
+    solver.carry_out(&plan).unwrap();
+    assert!(solver.password.as_str().starts_with("🥚"));
+    assert_eq!(solver.paul_index(), Some(0));
+    assert!(Rule::Egg.validate(solver.password.raw_password(), &game.state));
+}
+
 #[test]
 fn rule_egg() {
     let rule = Rule::Egg;
@@ -203,6 +266,57 @@ fn rule_egg() {
     assert!(!rule.validate(solver.password.raw_password(), &game.state));
     solver.solve_rule_and_commit(&rule, &game.state);
     assert!(rule.validate(solver.password.raw_password(), &game.state));
+    assert_eq!(solver.paul_index(), Some(0));
+
+    // Paul is followed as the password changes around him
+    solver
+        .carry_out(&Plan {
+            changes: vec![Change::Prepend {
+                protected: false,
+                string: "ab".into(),
+            }],
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(solver.paul_index(), Some(2));
+    solver
+        .carry_out(&Plan {
+            changes: vec![Change::Remove {
+                index: 0,
+                ignore_protection: false,
+            }],
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(solver.password.as_str(), "b🥚noegg");
+    assert_eq!(solver.paul_index(), Some(1));
+}
+
+#[test]
+fn carry_out_invalid_plan() {
+    let (_, mut solver) = test_setup(Rule::Egg, "abc");
+    solver.password.protect(0);
+
+    // Removing a protected grapheme isn't allowed, and nothing else in the plan is done either
+    let result = solver.carry_out(&Plan {
+        changes: vec![
+            Change::Append {
+                string: "ab".into(),
+                protected: false,
+            },
+            Change::Remove {
+                index: 0,
+                ignore_protection: false,
+            },
+        ],
+        state_delta: StateDelta {
+            goal_length: Some(20),
+            ..Default::default()
+        },
+    });
+    assert_eq!(result, Err(ChangeError::Protected(0)));
+    assert_eq!(solver.password.as_str(), "abc");
+    assert_eq!(solver.goal_length, None);
 }
 
 #[test]
@@ -211,22 +325,135 @@ fn rule_hatch() {
 
     let (mut game, mut solver) = test_setup(rule.clone(), "paul: 🐔");
     game.state.egg_placed = true;
-    game.state.paul_hatched = true;
+    game.state.paul.hatched = true;
     assert!(!rule.validate(solver.password.raw_password(), &game.state));
     solver.solve_rule_and_commit(&rule, &game.state);
     assert!(rule.validate(solver.password.raw_password(), &game.state));
 }
 
+#[test]
+fn rule_include_length_counts_bugs() {
+    let rule = Rule::IncludeLength;
+
+    let (mut game, mut solver) = test_setup(rule.clone(), &"z".repeat(93));
+    // The time's digits count against the length's, so keep them small
+    game.state.clock = SharedClock::new(Arc::new(MockClock::new(
+        Local.with_ymd_and_hms(2023, 7, 12, 1, 0, 0).unwrap(),
+    )));
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.goal_length, Some(101));
+
+    // Room is left for the bugs Paul has outside our password
+    let (_, mut solver) = test_setup(rule.clone(), &"z".repeat(93));
+    game.state.paul.bug_count = 3;
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.goal_length, Some(107));
+
+    // But bugs already in our password aren't counted twice
+    let (_, mut solver) = test_setup(rule.clone(), &format!("{}🐛🐛🐛", "z".repeat(90)));
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.goal_length, Some(101));
+}
+
+#[test]
+fn rule_include_length_digit_budget() {
+    assert_eq!(goal_length(100, 25), Some(101));
+    // 113 and 131 have digits adding up to 5, so 211 is the first to fit
+    assert_eq!(goal_length(110, 4), Some(211));
+    // Nothing fits, so the smallest digit sum is used
+    assert_eq!(goal_length(110, 0), Some(211));
+    assert_eq!(goal_length(1000, 25), None);
+}
+
+#[test]
+fn rule_include_length_kept() {
+    let rule = Rule::IncludeLength;
+
+    let (game, mut solver) = test_setup(rule.clone(), &"z".repeat(93));
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.goal_length, Some(101));
+
+    // Another rule removes a grapheme, so padding makes up for it
+    solver.password.queue_change(Change::Remove {
+        index: 0,
+        ignore_protection: false,
+    });
+    solver.password.commit_changes().unwrap();
+    assert!(!rule.validate(solver.password.raw_password(), &game.state));
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.password.len(), 101);
+    assert!(rule.validate(solver.password.raw_password(), &game.state));
+}
+
+#[test]
+fn rule_include_length_styled_padding() {
+    let rule = Rule::IncludeLength;
+
+    let (game, solver) = test_setup(rule.clone(), &"z".repeat(90));
+    let mut solver = solver.with_strategy(StrategyKind::MaxStyle.into());
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.goal_length, Some(101));
+    assert!(solver.password.as_str().ends_with("✨✨✨"));
+
+    // The padding is set in Comic Sans by the next plan
+    solver.solve_rule_and_commit(&Rule::PrimeLength, &game.state);
+    let formatting = solver.password.raw_password().formatting();
+    let (rest, padding) = formatting.split_at(formatting.len() - 3);
+    assert!(rest.iter().all(|f| f.font_family == FontFamily::Monospace));
+    assert!(padding
+        .iter()
+        .all(|f| f.font_family == FontFamily::ComicSans));
+}
+
 #[test]
 fn rule_youtube() {
     let rule = Rule::Youtube(13 * 60 + 3);
 
-    let (game, mut solver) = test_setup(rule.clone(), "foo");
+    let (mut game, mut solver) = test_setup(rule.clone(), "foo");
+    game.state.lookup = SharedLookup::new(Arc::new(OfflineLookup));
     assert!(!rule.validate(solver.password.raw_password(), &game.state));
     solver.solve_rule_and_commit(&rule, &game.state);
     assert!(rule.validate(solver.password.raw_password(), &game.state));
 }
 
+#[test]
+fn youtube_video_choice() {
+    let seconds = 13 * 60 + 3;
+    let videos = videos();
+    let video_id = videos.get(seconds).unwrap();
+    assert_eq!(video_candidates(seconds, &[])[0], video_id);
+
+    // Sacrificing a letter in the ID (in any case) means using another candidate, or one for a
+    // neighbouring duration
+    let letter = video_id
+        .to_lowercase()
+        .chars()
+        .find(|ch| ch.is_ascii_lowercase())
+        .unwrap();
+    let choice = video_candidates(seconds, &[letter]).first().cloned();
+    assert_ne!(choice.as_deref(), Some(video_id));
+    if let Some(choice) = choice {
+        assert!(!choice.to_lowercase().contains(letter));
+        assert!([seconds, seconds - 1, seconds + 1]
+            .iter()
+            .any(|duration| videos.candidates(*duration).contains(&choice)));
+    }
+
+    assert!(video_candidates(u32::MAX, &[]).is_empty());
+
+    // With the digit sum used up by protected digits, only IDs without digits will do
+    let mut solver = Solver {
+        password: MutablePassword::from_str("997"),
+        ..Default::default()
+    };
+    for index in 0..3 {
+        solver.password.protect(index);
+    }
+    let choice = solver.choose_video(seconds, &RuleSet::default()).unwrap();
+    assert!(video_candidates(seconds, &[]).contains(&choice));
+    assert!(!choice.chars().any(|ch| ch.is_ascii_digit() && ch != '0'));
+}
+
 #[test]
 fn rule_sacrifice() {
     let rule = Rule::Sacrifice;
@@ -267,21 +494,96 @@ fn rule_twice_italic() {
         index: 1,
         format_change: FormatChange::BoldOn,
     });
-    solver.password.commit_changes();
+    solver.password.commit_changes().unwrap();
     assert!(!rule.validate(solver.password.raw_password(), &game.state));
 
     solver.solve_rule_and_commit(&rule, &game.state);
     assert!(rule.validate(solver.password.raw_password(), &game.state));
+
+    // Should leave room for vowels bolded later, preferring protected graphemes
+    let (game, mut solver) = test_setup(rule.clone(), "");
+    solver.password.queue_change(Change::Append {
+        string: "bcd".into(),
+        protected: false,
+    });
+    solver.password.queue_change(Change::Append {
+        string: "fghjklmnpqrstvwxz".into(),
+        protected: true,
+    });
+    solver.password.queue_change(Change::Append {
+        string: "ea".into(),
+        protected: false,
+    });
+    solver.password.commit_changes().unwrap();
+    solver.password.queue_change(Change::Format {
+        index: 0,
+        format_change: FormatChange::BoldOn,
+    });
+    solver.password.commit_changes().unwrap();
+    solver.solve_rule_and_commit(&rule, &game.state);
+    let formatting = solver.password.raw_password().formatting();
+    let italic_count = formatting.iter().filter(|f| f.italic).count();
+    assert!(italic_count >= 2 * (1 + 2));
+    assert!(formatting[3..20].iter().all(|f| f.italic));
 }
 
 #[test]
 fn rule_wingdings() {
     let rule = Rule::Wingdings;
 
-    let (game, mut solver) = test_setup(rule.clone(), "0123456789");
+    let (mut game, mut solver) = test_setup(rule.clone(), "0123456789");
     assert!(!rule.validate(solver.password.raw_password(), &game.state));
     solver.solve_rule_and_commit(&rule, &game.state);
     assert!(rule.validate(solver.password.raw_password(), &game.state));
+    assert_eq!(
+        solver
+            .password
+            .raw_password()
+            .formatting()
+            .iter()
+            .filter(|f| f.font_family == FontFamily::Wingdings)
+            .count(),
+        3
+    );
+
+    // Paul's been fed, so the password dips below the fraction and needs topping up
+    game.state.paul.bug_count = 4;
+    assert!(!rule.validate(solver.password.raw_password(), &game.state));
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert!(rule.validate(solver.password.raw_password(), &game.state));
+}
+
+#[test]
+fn rule_wingdings_preference() {
+    let rule = Rule::Wingdings;
+    let wingdings = |solver: &Solver| {
+        solver
+            .password
+            .annotated()
+            .filter(|(_, _, format, _)| format.font_family == FontFamily::Wingdings)
+            .map(|(index, _, _, _)| index)
+            .collect::<Vec<_>>()
+    };
+
+    // Padding, then unprotected filler, ahead of the sponsor
+    let (game, mut solver) = test_setup(rule.clone(), "pepsi12-zz");
+    for index in 0..5 {
+        solver.password.protect(index);
+    }
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(wingdings(&solver), vec![7, 8, 9]);
+
+    // Protected strings ahead of digits, and never roman numerals
+    let (game, mut solver) = test_setup(rule.clone(), "XIpepsi12-");
+    for index in 0..7 {
+        solver.password.protect(index);
+    }
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(wingdings(&solver), vec![2, 3, 9]);
+
+    // So the numerals can be put in Times New Roman without undoing any of it
+    solver.solve_rule_and_commit(&Rule::TimesNewRoman, &game.state);
+    assert!(rule.validate(solver.password.raw_password(), &game.state));
 }
 
 #[test]
@@ -292,6 +594,19 @@ fn rule_times_new_roman() {
     assert!(!rule.validate(solver.password.raw_password(), &game.state));
     solver.solve_rule_and_commit(&rule, &game.state);
     assert!(rule.validate(solver.password.raw_password(), &game.state));
+
+    // Numerals in another font (e.g., Comic Sans from the page) are switched too
+    let (game, mut solver) = test_setup(rule.clone(), "fooXVII");
+    for index in 3..7 {
+        solver.password.queue_change(Change::Format {
+            index,
+            format_change: FormatChange::FontFamily(FontFamily::ComicSans),
+        });
+    }
+    solver.password.commit_changes().unwrap();
+    assert!(!rule.validate(solver.password.raw_password(), &game.state));
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert!(rule.validate(solver.password.raw_password(), &game.state));
 }
 
 #[test]
@@ -323,3 +638,345 @@ fn rule_time() {
     solver.solve_rule_and_commit(&rule, &game.state);
     assert!(rule.validate(solver.password.raw_password(), &game.state));
 }
+
+#[test]
+fn rule_time_rollover() {
+    let rule = Rule::Time;
+    let clock = Arc::new(MockClock::new(
+        Local.with_ymd_and_hms(2023, 7, 12, 4, 8, 59).unwrap(),
+    ));
+
+    let (mut game, mut solver) = test_setup(rule.clone(), "foo");
+    game.state.clock = SharedClock::new(clock.clone());
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.password.as_str(), "foo4:08");
+    assert!(rule.validate(solver.password.raw_password(), &game.state));
+
+    // The minute rolls over mid-game, so the existing time string is updated in place
+    clock.advance(chrono::Duration::seconds(1));
+    assert!(!rule.validate(solver.password.raw_password(), &game.state));
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.password.as_str(), "foo4:09");
+    assert!(rule.validate(solver.password.raw_password(), &game.state));
+}
+
+#[test]
+fn refresh_time_string() {
+    let rule = Rule::Time;
+    let clock = Arc::new(MockClock::new(
+        Local.with_ymd_and_hms(2023, 7, 12, 9, 59, 59).unwrap(),
+    ));
+    let (mut game, mut solver) = test_setup(rule.clone(), "foo");
+    game.state.clock = SharedClock::new(clock.clone());
+    assert!(solver.refresh_time_string(&game.state).is_none());
+    solver.solve_rule_and_commit(&rule, &game.state);
+    solver.password.queue_change(Change::Append {
+        string: "bar".into(),
+        protected: false,
+    });
+    solver.password.commit_changes().unwrap();
+    assert!(solver.refresh_time_string(&game.state).is_none());
+
+    let refresh = |solver: &mut Solver| {
+        let changes = solver.refresh_time_string(&game.state).unwrap();
+        for change in changes {
+            solver.password.queue_change(change);
+        }
+        solver.password.commit_changes().unwrap();
+        assert!(rule.validate(solver.password.raw_password(), &game.state));
+        solver.password.as_str().to_owned()
+    };
+
+    // The time string grows
+    clock.advance(chrono::Duration::seconds(1));
+    assert_eq!(refresh(&mut solver), "foo10:00bar");
+    // And shrinks
+    clock.advance(chrono::Duration::minutes(179));
+    assert_eq!(refresh(&mut solver), "foo12:59bar");
+    clock.advance(chrono::Duration::minutes(1));
+    assert_eq!(refresh(&mut solver), "foo1:00bar");
+    clock.advance(chrono::Duration::minutes(1));
+    assert_eq!(refresh(&mut solver), "foo1:01bar");
+}
+
+#[test]
+fn time_string_keeps_goal_length() {
+    let rule = Rule::Time;
+    let clock = Arc::new(MockClock::new(
+        Local.with_ymd_and_hms(2023, 7, 12, 9, 59, 59).unwrap(),
+    ));
+    let (mut game, mut solver) = test_setup(rule.clone(), "ab-");
+    game.state.clock = SharedClock::new(clock.clone());
+    solver.solve_rule_and_commit(&rule, &game.state);
+    solver.password.queue_change(Change::Append {
+        string: "-".into(),
+        protected: false,
+    });
+    solver.password.commit_changes().unwrap();
+    solver.goal_length = Some(8);
+    assert_eq!(solver.password.as_str(), "ab-9:59-");
+
+    // Padding goes to make room for the extra digit, and comes back when it's gone
+    clock.advance(chrono::Duration::seconds(1));
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.password.as_str(), "ab-10:00");
+    clock.advance(chrono::Duration::minutes(179));
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.password.as_str(), "ab-12:59");
+    clock.advance(chrono::Duration::minutes(1));
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.password.as_str(), "ab-1:00-");
+
+    clock.advance(chrono::Duration::hours(9));
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.password.as_str(), "ab-10:00");
+
+    // Without any padding left, the password is left for Paul to shorten
+    clock.advance(chrono::Duration::hours(3));
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.password.as_str(), "ab-1:00-");
+    for index in [2, 7] {
+        solver.password.queue_change(Change::Remove {
+            index,
+            ignore_protection: false,
+        });
+    }
+    solver.password.commit_changes().unwrap();
+    clock.advance(chrono::Duration::hours(9));
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.password.as_str(), "ab10:00");
+    assert!(rule.validate(solver.password.raw_password(), &game.state));
+}
+
+#[test]
+fn impact_analysis() {
+    let (game, solver) = test_setup(Rule::Skip, "MayZ9!");
+    let rules = vec![Rule::Number, Rule::Uppercase, Rule::Special, Rule::Month];
+    assert_eq!(
+        solver.impact_analysis(0, &rules, &game.state),
+        vec![Rule::Month]
+    );
+    assert!(solver.impact_analysis(3, &rules, &game.state).is_empty());
+    assert_eq!(
+        solver.impact_analysis(4, &rules, &game.state),
+        vec![Rule::Number]
+    );
+
+    // Rules which are already violated aren't reported
+    let (game, solver) = test_setup(Rule::Skip, "foo");
+    assert!(solver.impact_analysis(0, &rules, &game.state).is_empty());
+}
+
+#[test]
+fn letter_removal_impact() {
+    // Removing either "y" alone leaves a month, but sacrificing the letter removes both
+    let (mut game, solver) = test_setup(Rule::Skip, "MayMayXX");
+    game.state.highest_rule = Rule::Month.number();
+    assert_eq!(solver.letter_removal_impact('y', &game.state), 1);
+    assert_eq!(solver.letter_removal_impact('b', &game.state), 0);
+}
+
+#[test]
+fn rule_sacrifice_avoids_breaking_rules() {
+    let rule = Rule::Sacrifice;
+
+    // Every candidate letter is present, so we have to remove some, but removing "m" or "y"
+    // would break the month rule
+    let (mut game, mut solver) = test_setup(rule.clone(), "abcdefghijklnopqrstuwzmay");
+    game.state.highest_rule = Rule::Month.number();
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert!(!solver.sacrificed_letters.contains(&'m'));
+    assert!(!solver.sacrificed_letters.contains(&'y'));
+    assert!(Rule::Month.validate(solver.password.raw_password(), &game.state));
+}
+
+#[test]
+fn rule_sacrifice_shortest_password() {
+    let rule = Rule::Sacrifice;
+
+    // "g" and "h" can be removed without breaking anything, so they're sacrificed (rather than
+    // absent letters) to shorten the password
+    let (mut game, solver) = test_setup(rule.clone(), "gggghhhjkpepsiiamloved");
+    let mut solver = solver.with_strategy(StrategyKind::ShortestPassword.into());
+    solver.solve_rule_and_commit(&rule, &game.state);
+    assert_eq!(solver.sacrificed_letters, vec!['g', 'h']);
+    assert_eq!(solver.password.as_str(), "jkpepsiiamloved");
+    game.state
+        .sacrificed_letters
+        .extend(solver.sacrificed_letters.iter());
+    assert!(rule.validate(solver.password.raw_password(), &game.state));
+}
+
+#[test]
+fn plan_without_side_effects() {
+    let rule = Rule::Sacrifice;
+
+    let (game, mut solver) = test_setup(rule.clone(), "abcdefghijklmnopqrstuvwxyz");
+    let plan = solver.plan_rule(&rule, &game.state).unwrap();
+    assert!(solver.sacrificed_letters.is_empty());
+    assert_eq!(plan.changes.len(), 2);
+    assert_eq!(
+        plan.state_delta.sacrificed_letters.as_ref().map(Vec::len),
+        Some(2)
+    );
+
+    solver.apply_plan(&plan);
+    assert_eq!(
+        Some(solver.sacrificed_letters.clone()),
+        plan.state_delta.sacrificed_letters
+    );
+}
+
+#[test]
+fn solve_all() {
+    let rules = vec![
+        Rule::MinLength,
+        Rule::Number,
+        Rule::Uppercase,
+        Rule::Sponsors,
+    ];
+    let (game, mut solver) = test_setup(Rule::Skip, "ab");
+    let plans = solver.solve_all(&rules, &game.state).unwrap();
+
+    let mut simulated = solver.clone();
+    for plan in &plans {
+        simulated.carry_out(plan).unwrap();
+    }
+    assert!(rules
+        .iter()
+        .all(|rule| rule.validate(simulated.password.raw_password(), &game.state)));
+    // The sponsor also satisfies the minimum length, so no padding is needed, and the
+    // shortest sponsor is chosen
+    assert!(!simulated.password.as_str().contains('z'));
+    assert!(!simulated.password.as_str().contains("starbucks"));
+}
+
+#[test]
+fn solve_all_affected() {
+    let hex = Rule::Hex(Color {
+        r: 0x12,
+        g: 0x34,
+        b: 0x56,
+    });
+    let rules = vec![hex.clone(), Rule::Uppercase];
+    let others = vec![Rule::MinLength, Rule::Number, Rule::Special, Rule::Digits];
+    let (mut game, mut solver) = test_setup(hex, "abcde997!");
+    game.state.highest_rule = RuleKind::Hex.number();
+    assert!(others
+        .iter()
+        .all(|rule| rule.validate(solver.password.raw_password(), &game.state)));
+
+    let (plans, batch) = solver
+        .solve_all_affected(&rules, &others, &game.state)
+        .unwrap();
+    let mut simulated = solver.clone();
+    for plan in &plans {
+        simulated.carry_out(plan).unwrap();
+    }
+    // The color's digits break the digit sum, so that's solved along with the rest
+    assert_eq!(batch, [rules, vec![Rule::Digits]].concat());
+    assert!(batch
+        .iter()
+        .chain(&others)
+        .all(|rule| rule.validate(simulated.password.raw_password(), &game.state)));
+}
+
+#[test]
+fn collect_garbage() {
+    let (mut game, mut solver) = test_setup(Rule::Digits, "abzzz9Z!99");
+    game.state.highest_rule = Rule::Digits.number();
+    solver.password.protect(3);
+    for change in solver.collect_garbage(&game.state) {
+        solver.password.queue_change(change);
+    }
+    solver.password.commit_changes().unwrap();
+    assert_eq!(solver.password.as_str(), "az9Z!");
+    for rule in [
+        Rule::MinLength,
+        Rule::Number,
+        Rule::Uppercase,
+        Rule::Special,
+    ] {
+        assert!(rule.validate(solver.password.raw_password(), &game.state));
+    }
+
+    // Nothing is removed once the password length is fixed
+    let (game, mut solver) = test_setup(Rule::Digits, "abzzz9Z!99");
+    solver.goal_length = Some(10);
+    assert!(solver.collect_garbage(&game.state).is_empty());
+}
+
+#[test]
+fn compact_password() {
+    let (mut game, mut solver) = test_setup(Rule::IncludeLength, "zzzzHello-1!!--");
+    game.state.highest_rule = Rule::Special.number();
+    solver.password.protect(6);
+    let changes = solver.compact_password(&game.state);
+    for change in changes {
+        solver.password.queue_change(change);
+    }
+    solver.password.commit_changes().unwrap();
+    assert_eq!(solver.password.as_str(), "zHl-1");
+    for rule in [
+        Rule::MinLength,
+        Rule::Number,
+        Rule::Uppercase,
+        Rule::Special,
+    ] {
+        assert!(rule.validate(solver.password.raw_password(), &game.state));
+    }
+}
+
+#[test]
+fn solve_early_rules() {
+    let rules = [
+        Rule::MinLength,
+        Rule::Number,
+        Rule::Uppercase,
+        Rule::Special,
+        Rule::Digits,
+        Rule::Month,
+        Rule::Roman,
+        Rule::Sponsors,
+    ];
+
+    let (game, mut solver) = test_setup(Rule::Skip, "a");
+    let changes = solver.solve_early_rules(&rules, &game.state).unwrap();
+    assert!(changes.len() <= 4);
+    assert!(changes.iter().all(|c| matches!(c, Change::Append { .. })));
+    for change in changes {
+        solver.password.queue_change(change);
+    }
+    solver.password.commit_changes().unwrap();
+    for rule in &rules {
+        assert!(rule.validate(solver.password.raw_password(), &game.state));
+    }
+
+    // The digits can't be fixed by appending
+    let (game, mut solver) = test_setup(Rule::Skip, "99999");
+    assert!(solver.solve_early_rules(&rules, &game.state).is_none());
+
+    // Not an early rule
+    let (game, mut solver) = test_setup(Rule::Skip, "a");
+    assert!(solver
+        .solve_early_rules(&[Rule::Number, Rule::Wordle], &game.state)
+        .is_none());
+}
+
+#[test]
+fn seeded_choices() {
+    let game = Game::default();
+    let choices = |seed| {
+        let mut solver = Solver::with_seed(seed);
+        [Rule::Month, Rule::Sponsors, Rule::Affirmation]
+            .iter()
+            .map(|rule| solver.plan_rule(rule, &game.state).unwrap().changes)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(choices(3), choices(3));
+
+    // Planning from the same solver state always makes the same choice
+    let solver = Solver::with_seed(3);
+    let month = |mut solver: Solver| solver.plan_rule(&Rule::Month, &game.state).unwrap();
+    assert_eq!(month(solver.clone()).changes, month(solver).changes);
+}
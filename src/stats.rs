@@ -0,0 +1,251 @@
+//! Statistics about each playthrough (how long each rule stayed violated, and how many
+//! keystrokes, rerolls and sync checks it took), saved when the playthrough ends so it's clear
+//! where the time went.
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    game::rule::RuleKind,
+    logging::STORAGE as STORAGE_TARGET,
+    solver::reroll::RerollKind,
+    storage::{to_record, Artifact, STORAGE},
+};
+
+/// Storage stream for the statistics of each playthrough.
+const STATS_STREAM: &str = "runs.stats";
+
+/// How long a rule took to solve over a playthrough.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleTime {
+    /// Number of times the rule went from violated to satisfied.
+    pub solves: usize,
+    /// Total time the rule was violated for, including any time it was still violated at the
+    /// end of the playthrough.
+    pub violated_ms: u64,
+}
+
+/// Statistics for a single playthrough, as persisted.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayStats {
+    /// Length of the playthrough.
+    pub play_ms: u64,
+    /// Keystrokes sent to the page.
+    pub keystrokes: usize,
+    /// Rerolls of each kind of string.
+    pub rerolls: BTreeMap<RerollKind, usize>,
+    /// Times the password on the page was checked against ours.
+    pub sync_checks: usize,
+    /// Times the password had to be resynced after losing sync for an unknown reason.
+    pub resyncs: usize,
+    /// Time taken by each rule which was violated at some point.
+    pub rules: BTreeMap<RuleKind, RuleTime>,
+    /// The error the playthrough failed with, if it did.
+    pub error: Option<String>,
+}
+
+impl Artifact for PlayStats {
+    const KIND: &'static str = "playthrough stats";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl PlayStats {
+    /// The given number of rules which were violated for longest, longest first.
+    pub fn slowest_rules(&self, count: usize) -> Vec<(RuleKind, Duration)> {
+        let mut rules = self
+            .rules
+            .iter()
+            .map(|(kind, time)| (*kind, Duration::from_millis(time.violated_ms)))
+            .collect::<Vec<_>>();
+        rules.sort_by_key(|(kind, duration)| (std::cmp::Reverse(*duration), *kind));
+        rules.truncate(count);
+        rules
+    }
+
+    /// Save the statistics to storage. Failures are logged rather than returned.
+    pub fn save(&self) {
+        let result = to_record(self)
+            .and_then(|record| STORAGE.lock().unwrap().append(STATS_STREAM, &record));
+        if let Err(e) = result {
+            warn!(target: STORAGE_TARGET, "Failed to save playthrough stats: {}", e);
+        }
+    }
+}
+
+/// Collects statistics over a playthrough.
+#[derive(Debug)]
+pub struct StatsCollector {
+    start: Instant,
+    /// Keystrokes sent before the playthrough started.
+    keystrokes_before: usize,
+    stats: PlayStats,
+    /// Rules which are currently violated, with when they were first seen violated.
+    violated_since: BTreeMap<RuleKind, Instant>,
+}
+
+impl StatsCollector {
+    /// Start collecting statistics for a playthrough, given the keystrokes sent so far.
+    pub fn start(keystrokes_before: usize) -> Self {
+        StatsCollector {
+            start: Instant::now(),
+            keystrokes_before,
+            stats: PlayStats::default(),
+            violated_since: BTreeMap::new(),
+        }
+    }
+
+    /// Note which rules are currently violated. Rules which were violated but no longer are
+    /// count as solved.
+    pub fn observe_rules(&mut self, violated: impl IntoIterator<Item = RuleKind>) {
+        self.observe_rules_at(violated, Instant::now());
+    }
+
+    fn observe_rules_at(&mut self, violated: impl IntoIterator<Item = RuleKind>, now: Instant) {
+        let violated = violated.into_iter().collect::<Vec<_>>();
+        let solved = self
+            .violated_since
+            .keys()
+            .filter(|kind| !violated.contains(kind))
+            .copied()
+            .collect::<Vec<_>>();
+        for kind in solved {
+            let since = self.violated_since.remove(&kind).unwrap();
+            let time = self.stats.rules.entry(kind).or_default();
+            time.solves += 1;
+            time.violated_ms += now.duration_since(since).as_millis() as u64;
+        }
+        for kind in violated {
+            self.violated_since.entry(kind).or_insert(now);
+        }
+    }
+
+    /// Leave the given time spent paused out of the play time, and out of how long each rule
+    /// which is currently violated has been violated for.
+    pub fn exclude_pause(&mut self, paused: Duration) {
+        self.start += paused;
+        for since in self.violated_since.values_mut() {
+            *since += paused;
+        }
+    }
+
+    /// How long the given rule has been violated for, if it currently is.
+    pub fn violated_for(&self, kind: RuleKind) -> Option<Duration> {
+        self.violated_since.get(&kind).map(Instant::elapsed)
+    }
+
+    /// Count the rerolls made before accepting a string.
+    pub fn count_rerolls(&mut self, kind: RerollKind, rerolls: usize) {
+        *self.stats.rerolls.entry(kind).or_default() += rerolls;
+    }
+
+    /// Count a check of the password on the page against ours.
+    pub fn count_sync_check(&mut self) {
+        self.stats.sync_checks += 1;
+    }
+
+    /// Count a resync of the password.
+    pub fn count_resync(&mut self) {
+        self.stats.resyncs += 1;
+    }
+
+    /// The statistics so far, given the keystrokes sent so far and the error the playthrough
+    /// failed with (if it's over and it did).
+    pub fn finish(&self, keystrokes: usize, error: Option<String>) -> PlayStats {
+        self.finish_at(keystrokes, error, Instant::now())
+    }
+
+    fn finish_at(&self, keystrokes: usize, error: Option<String>, now: Instant) -> PlayStats {
+        let mut stats = self.stats.clone();
+        for (kind, since) in &self.violated_since {
+            stats.rules.entry(*kind).or_default().violated_ms +=
+                now.duration_since(*since).as_millis() as u64;
+        }
+        stats.play_ms = now.duration_since(self.start).as_millis() as u64;
+        stats.keystrokes = keystrokes.saturating_sub(self.keystrokes_before);
+        stats.error = error;
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{PlayStats, RuleTime, StatsCollector};
+    use crate::{
+        game::rule::RuleKind,
+        solver::reroll::RerollKind,
+        storage::{from_record, to_record},
+    };
+
+    #[test]
+    fn collect_stats() {
+        let mut collector = StatsCollector::start(10);
+        let start = collector.start;
+        let at = |secs| start + Duration::from_secs(secs);
+
+        collector.observe_rules_at([RuleKind::Digits, RuleKind::Uppercase], at(1));
+        collector.observe_rules_at([RuleKind::Digits], at(2));
+        collector.observe_rules_at([], at(5));
+        collector.observe_rules_at([RuleKind::Captcha], at(6));
+        assert!(collector.violated_for(RuleKind::Captcha).is_some());
+        assert_eq!(collector.violated_for(RuleKind::Digits), None);
+        collector.count_rerolls(RerollKind::Captcha, 3);
+        collector.count_rerolls(RerollKind::Captcha, 2);
+        collector.count_sync_check();
+
+        let stats = collector.finish_at(25, Some("game over".to_owned()), at(10));
+        assert_eq!(stats.play_ms, 10_000);
+        assert_eq!(stats.keystrokes, 15);
+        assert_eq!(stats.rerolls[&RerollKind::Captcha], 5);
+        assert_eq!(stats.sync_checks, 1);
+        assert_eq!(
+            stats.rules[&RuleKind::Digits],
+            RuleTime {
+                solves: 1,
+                violated_ms: 4_000
+            }
+        );
+        // Still violated at the end, so not solved
+        assert_eq!(
+            stats.rules[&RuleKind::Captcha],
+            RuleTime {
+                solves: 0,
+                violated_ms: 4_000
+            }
+        );
+        assert_eq!(
+            stats.slowest_rules(2),
+            vec![
+                (RuleKind::Digits, Duration::from_secs(4)),
+                (RuleKind::Captcha, Duration::from_secs(4))
+            ]
+        );
+
+        assert_eq!(
+            from_record::<PlayStats>(to_record(&stats).unwrap()).unwrap(),
+            stats
+        );
+    }
+
+    #[test]
+    fn exclude_pause() {
+        let mut collector = StatsCollector::start(0);
+        let start = collector.start;
+        let at = |secs| start + Duration::from_secs(secs);
+
+        collector.observe_rules_at([RuleKind::Digits, RuleKind::Captcha], at(1));
+        // Paused from 2s to 12s
+        collector.exclude_pause(Duration::from_secs(10));
+        collector.observe_rules_at([RuleKind::Captcha], at(14));
+
+        let stats = collector.finish_at(0, None, at(15));
+        assert_eq!(stats.play_ms, 5_000);
+        assert_eq!(stats.rules[&RuleKind::Digits].violated_ms, 3_000);
+        assert_eq!(stats.rules[&RuleKind::Captcha].violated_ms, 4_000);
+    }
+}
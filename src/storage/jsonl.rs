@@ -0,0 +1,101 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use super::{validate_stream, StorageBackend, StorageError};
+
+/// Storage as a directory of JSON Lines files, one per stream.
+#[derive(Debug)]
+pub struct JsonlStorage {
+    dir: PathBuf,
+}
+
+impl JsonlStorage {
+    /// Open storage in the given directory, creating it if needed.
+    pub fn open(dir: &Path) -> Result<Self, StorageError> {
+        fs::create_dir_all(dir)?;
+        Ok(JsonlStorage {
+            dir: dir.to_owned(),
+        })
+    }
+
+    fn path(&self, stream: &str) -> Result<PathBuf, StorageError> {
+        validate_stream(stream)?;
+        Ok(self.dir.join(format!("{}.jsonl", stream)))
+    }
+}
+
+impl StorageBackend for JsonlStorage {
+    fn append(&mut self, stream: &str, record: &serde_json::Value) -> Result<(), StorageError> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(stream)?)?;
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn load(&self, stream: &str) -> Result<Vec<serde_json::Value>, StorageError> {
+        let path = self.path(stream)?;
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut records = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                records.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(records)
+    }
+
+    fn replace(&mut self, stream: &str, records: &[serde_json::Value]) -> Result<(), StorageError> {
+        // Written to a temporary file first, so a crash part way through doesn't lose it
+        let path = self.path(stream)?;
+        let temp_path = path.with_extension("jsonl.tmp");
+        let mut file = BufWriter::new(File::create(&temp_path)?);
+        for record in records {
+            serde_json::to_writer(&mut file, record)?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()?;
+        fs::rename(temp_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{JsonlStorage, StorageBackend};
+
+    #[test]
+    fn append_and_load() {
+        let dir = std::env::temp_dir().join(format!("password-game-bot-{}", std::process::id()));
+        let mut storage = JsonlStorage::open(&dir).unwrap();
+        assert!(storage.load("runs").unwrap().is_empty());
+
+        storage.append("runs", &json!({"id": 1})).unwrap();
+        storage.append("runs", &json!({"id": 2})).unwrap();
+        assert!(dir.join("runs.jsonl").exists());
+
+        // Records persist across instances
+        let storage = JsonlStorage::open(&dir).unwrap();
+        assert_eq!(
+            storage.load("runs").unwrap(),
+            vec![json!({"id": 1}), json!({"id": 2})]
+        );
+
+        let mut storage = storage;
+        storage.replace("runs", &[json!({"id": 2})]).unwrap();
+        assert_eq!(storage.load("runs").unwrap(), vec![json!({"id": 2})]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
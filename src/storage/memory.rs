@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use super::{validate_stream, StorageBackend, StorageError};
+
+/// Storage which only lasts as long as the process.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    streams: HashMap<String, Vec<serde_json::Value>>,
+}
+
+impl StorageBackend for MemoryStorage {
+    fn append(&mut self, stream: &str, record: &serde_json::Value) -> Result<(), StorageError> {
+        validate_stream(stream)?;
+        self.streams
+            .entry(stream.to_owned())
+            .or_default()
+            .push(record.clone());
+        Ok(())
+    }
+
+    fn load(&self, stream: &str) -> Result<Vec<serde_json::Value>, StorageError> {
+        validate_stream(stream)?;
+        Ok(self.streams.get(stream).cloned().unwrap_or_default())
+    }
+
+    fn replace(&mut self, stream: &str, records: &[serde_json::Value]) -> Result<(), StorageError> {
+        validate_stream(stream)?;
+        self.streams.insert(stream.to_owned(), records.to_vec());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{MemoryStorage, StorageBackend};
+
+    #[test]
+    fn append_and_load() {
+        let mut storage = MemoryStorage::default();
+        assert!(storage.load("runs").unwrap().is_empty());
+
+        storage.append("runs", &json!({"id": 1})).unwrap();
+        storage.append("runs", &json!({"id": 2})).unwrap();
+        storage.append("stats", &json!(3)).unwrap();
+        assert_eq!(
+            storage.load("runs").unwrap(),
+            vec![json!({"id": 1}), json!({"id": 2})]
+        );
+        assert_eq!(storage.load("stats").unwrap(), vec![json!(3)]);
+
+        assert!(storage.append("../runs", &json!(1)).is_err());
+    }
+}
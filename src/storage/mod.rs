@@ -0,0 +1,387 @@
+use lazy_static::lazy_static;
+use log::{debug, warn};
+#[cfg(feature = "native")]
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    str::FromStr,
+    sync::Mutex,
+};
+use thiserror::Error;
+
+use crate::logging::STORAGE as STORAGE_TARGET;
+
+pub use jsonl::JsonlStorage;
+pub use memory::MemoryStorage;
+pub use schema::{from_record, to_record, Artifact};
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStorage;
+
+mod jsonl;
+mod memory;
+mod schema;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+/// Environment variable used to choose the storage backend (see `StorageKind` for the format).
+pub const STORAGE_ENV: &str = "BOT_STORAGE";
+/// Directory data is stored in (as JSON Lines) unless `BOT_STORAGE` says otherwise.
+pub const DEFAULT_STORAGE_DIR: &str = "data";
+/// Most records kept in a stream. When a stream grows past this, caches are compacted to the
+/// latest value of each key, and then the oldest records are dropped to make room.
+pub const MAX_STREAM_RECORDS: usize = 10_000;
+
+lazy_static! {
+    /// Storage shared by everything that persists data across runs, chosen via the
+    /// `BOT_STORAGE` environment variable. Defaults to JSON Lines files in `data/` (or
+    /// in-memory storage in tests).
+    pub static ref STORAGE: Mutex<Storage> = Mutex::new(Storage::new(from_env()));
+}
+
+/// A store of append-only streams of JSON records.
+pub trait StorageBackend: Send {
+    /// Append a record to the given stream.
+    fn append(&mut self, stream: &str, record: &serde_json::Value) -> Result<(), StorageError>;
+
+    /// Load all records from the given stream, oldest first. Streams which have never been
+    /// appended to are empty.
+    fn load(&self, stream: &str) -> Result<Vec<serde_json::Value>, StorageError>;
+
+    /// Replace all records in the given stream (to compact it).
+    fn replace(&mut self, stream: &str, records: &[serde_json::Value]) -> Result<(), StorageError>;
+}
+
+/// A storage backend, with an index of the cache streams so lookups don't scan them, and a
+/// limit on the size of every stream (`MAX_STREAM_RECORDS`).
+pub struct Storage {
+    backend: Box<dyn StorageBackend>,
+    /// Number of records in each stream appended to so far.
+    lengths: HashMap<String, usize>,
+    /// Latest value for each key of each cache stream used so far.
+    caches: HashMap<String, HashMap<String, serde_json::Value>>,
+}
+
+impl Storage {
+    pub fn new(backend: Box<dyn StorageBackend>) -> Self {
+        Storage {
+            backend,
+            lengths: HashMap::new(),
+            caches: HashMap::new(),
+        }
+    }
+
+    /// Append a record to the given stream, compacting the stream if it's too long.
+    pub fn append(&mut self, stream: &str, record: &serde_json::Value) -> Result<(), StorageError> {
+        let length = match self.lengths.get(stream) {
+            Some(length) => *length,
+            None => self.backend.load(stream)?.len(),
+        };
+        self.backend.append(stream, record)?;
+        self.lengths.insert(stream.to_owned(), length + 1);
+        if length + 1 > MAX_STREAM_RECORDS {
+            self.compact(stream)?;
+        }
+        Ok(())
+    }
+
+    /// Load all records from the given stream, oldest first.
+    pub fn load(&self, stream: &str) -> Result<Vec<serde_json::Value>, StorageError> {
+        self.backend.load(stream)
+    }
+
+    /// Get the most recent value stored for `key` in the given cache stream.
+    #[cfg(feature = "native")]
+    pub fn cache_get<T: DeserializeOwned>(
+        &mut self,
+        stream: &str,
+        key: &str,
+    ) -> Result<Option<T>, StorageError> {
+        match self.cache(stream)?.get(key) {
+            Some(value) => Ok(Some(serde_json::from_value(value.clone())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store a value for `key` in the given cache stream.
+    #[cfg(feature = "native")]
+    pub fn cache_put<T: Serialize>(
+        &mut self,
+        stream: &str,
+        key: &str,
+        value: T,
+    ) -> Result<(), StorageError> {
+        let entry = CacheEntry {
+            key: key.to_owned(),
+            value: serde_json::to_value(value)?,
+        };
+        self.cache(stream)?;
+        self.append(stream, &serde_json::to_value(&entry)?)?;
+        if let Some(cache) = self.caches.get_mut(stream) {
+            cache.insert(entry.key, entry.value);
+        }
+        Ok(())
+    }
+
+    /// The index of the given cache stream, reading the stream the first time it's used.
+    #[cfg(feature = "native")]
+    fn cache(
+        &mut self,
+        stream: &str,
+    ) -> Result<&mut HashMap<String, serde_json::Value>, StorageError> {
+        if !self.caches.contains_key(stream) {
+            let records = self.backend.load(stream)?;
+            self.lengths.insert(stream.to_owned(), records.len());
+            let mut cache = HashMap::new();
+            for record in records {
+                let entry: CacheEntry<serde_json::Value> = serde_json::from_value(record)?;
+                cache.insert(entry.key, entry.value);
+            }
+            self.caches.insert(stream.to_owned(), cache);
+        }
+        Ok(self.caches.get_mut(stream).unwrap())
+    }
+
+    /// Shrink the given stream to make room for new records, keeping only the latest value of
+    /// each key if it's a cache, and then dropping the oldest records.
+    fn compact(&mut self, stream: &str) -> Result<(), StorageError> {
+        let mut records = self.backend.load(stream)?;
+        let before = records.len();
+        if self.caches.contains_key(stream) {
+            let mut seen = HashSet::new();
+            records.reverse();
+            records.retain(|record| {
+                seen.insert(
+                    record
+                        .get("key")
+                        .and_then(|key| key.as_str())
+                        .map(str::to_owned),
+                )
+            });
+            records.reverse();
+        }
+        // Leave room to grow, so streams aren't rewritten on every append
+        let keep = MAX_STREAM_RECORDS * 3 / 4;
+        if records.len() > keep {
+            records.drain(..records.len() - keep);
+        }
+        self.backend.replace(stream, &records)?;
+        debug!(
+            target: STORAGE_TARGET,
+            "Compacted stream {} from {} to {} records",
+            stream,
+            before,
+            records.len()
+        );
+        self.lengths.insert(stream.to_owned(), records.len());
+        if let Some(cache) = self.caches.get_mut(stream) {
+            cache.clear();
+            for record in records {
+                let entry: CacheEntry<serde_json::Value> = serde_json::from_value(record)?;
+                cache.insert(entry.key, entry.value);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StorageBackend for Storage {
+    fn append(&mut self, stream: &str, record: &serde_json::Value) -> Result<(), StorageError> {
+        Storage::append(self, stream, record)
+    }
+
+    fn load(&self, stream: &str) -> Result<Vec<serde_json::Value>, StorageError> {
+        Storage::load(self, stream)
+    }
+
+    fn replace(&mut self, stream: &str, records: &[serde_json::Value]) -> Result<(), StorageError> {
+        self.backend.replace(stream, records)?;
+        self.lengths.remove(stream);
+        self.caches.remove(stream);
+        Ok(())
+    }
+}
+
+/// Failure modes for storage backends.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum StorageError {
+    #[error("invalid storage specification {0:?}")]
+    InvalidSpec(String),
+    #[error("invalid stream name {0:?}")]
+    InvalidStream(String),
+    #[error("storage io error")]
+    Io(#[from] std::io::Error),
+    #[error("failed to (de)serialize record")]
+    Json(#[from] serde_json::Error),
+    #[error("unsupported {kind} schema version {version}")]
+    UnsupportedSchema { kind: &'static str, version: u32 },
+    #[cfg(feature = "sqlite")]
+    #[error("sqlite error")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+/// The available storage backends, parsed from a specification string:
+/// - `memory`
+/// - `jsonl:<directory>` (one `<stream>.jsonl` file per stream)
+/// - `sqlite:<file>` (requires the `sqlite` feature)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageKind {
+    Memory,
+    Jsonl(PathBuf),
+    #[cfg(feature = "sqlite")]
+    Sqlite(PathBuf),
+}
+
+impl FromStr for StorageKind {
+    type Err = StorageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, path) = match s.split_once(':') {
+            Some((kind, path)) => (kind, Some(path)),
+            None => (s, None),
+        };
+        match (kind, path) {
+            ("memory", None) => Ok(StorageKind::Memory),
+            ("jsonl", Some(path)) if !path.is_empty() => Ok(StorageKind::Jsonl(path.into())),
+            #[cfg(feature = "sqlite")]
+            ("sqlite", Some(path)) if !path.is_empty() => Ok(StorageKind::Sqlite(path.into())),
+            _ => Err(StorageError::InvalidSpec(s.to_owned())),
+        }
+    }
+}
+
+impl StorageKind {
+    /// Open a backend of this kind.
+    pub fn open(&self) -> Result<Box<dyn StorageBackend>, StorageError> {
+        Ok(match self {
+            StorageKind::Memory => Box::<MemoryStorage>::default(),
+            StorageKind::Jsonl(dir) => Box::new(JsonlStorage::open(dir)?),
+            #[cfg(feature = "sqlite")]
+            StorageKind::Sqlite(path) => Box::new(SqliteStorage::open(path)?),
+        })
+    }
+}
+
+/// Open the backend specified by the `BOT_STORAGE` environment variable, or the default one if
+/// it's unset, falling back to in-memory storage if that fails.
+fn from_env() -> Box<dyn StorageBackend> {
+    let spec = match std::env::var(STORAGE_ENV) {
+        Ok(spec) => spec,
+        Err(_) if cfg!(test) => return Box::<MemoryStorage>::default(),
+        Err(_) => format!("jsonl:{}", DEFAULT_STORAGE_DIR),
+    };
+    match spec.parse::<StorageKind>().and_then(|kind| kind.open()) {
+        Ok(storage) => storage,
+        Err(e) => {
+            warn!(
+                target: STORAGE_TARGET,
+                "Failed to open storage {:?} ({}), using in-memory storage", spec, e
+            );
+            Box::<MemoryStorage>::default()
+        }
+    }
+}
+
+/// Stream names are used as file names by some backends, so are restricted to
+/// lowercase ASCII letters, digits, '_', '-' and '.'.
+fn validate_stream(stream: &str) -> Result<(), StorageError> {
+    if !stream.is_empty()
+        && !stream.starts_with('.')
+        && stream.chars().all(|ch| {
+            ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '_' || ch == '-' || ch == '.'
+        })
+    {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidStream(stream.to_owned()))
+    }
+}
+
+/// An entry in a persistent key-value cache stream.
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry<T> {
+    key: String,
+    value: T,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{
+        validate_stream, MemoryStorage, Storage, StorageBackend, StorageKind, MAX_STREAM_RECORDS,
+    };
+
+    #[test]
+    fn parse_kind() {
+        assert_eq!(
+            "memory".parse::<StorageKind>().unwrap(),
+            StorageKind::Memory
+        );
+        assert_eq!(
+            "jsonl:runs/data".parse::<StorageKind>().unwrap(),
+            StorageKind::Jsonl("runs/data".into())
+        );
+        assert!("jsonl:".parse::<StorageKind>().is_err());
+        assert!("memory:foo".parse::<StorageKind>().is_err());
+        assert!("postgres:foo".parse::<StorageKind>().is_err());
+    }
+
+    #[test]
+    fn stream_names() {
+        assert!(validate_stream("cache.youtube_duration").is_ok());
+        assert!(validate_stream("").is_err());
+        assert!(validate_stream("../foo").is_err());
+        assert!(validate_stream("foo/bar").is_err());
+        assert!(validate_stream("Foo").is_err());
+    }
+
+    #[test]
+    fn cache() {
+        let mut storage = Storage::new(Box::<MemoryStorage>::default());
+        assert_eq!(storage.cache_get::<u32>("cache", "a").unwrap(), None);
+
+        storage.cache_put("cache", "a", 1).unwrap();
+        storage.cache_put("cache", "b", 2).unwrap();
+        storage.cache_put("cache", "a", 3).unwrap();
+        assert_eq!(storage.cache_get::<u32>("cache", "a").unwrap(), Some(3));
+        assert_eq!(storage.cache_get::<u32>("cache", "b").unwrap(), Some(2));
+        assert_eq!(storage.cache_get::<u32>("other", "a").unwrap(), None);
+
+        // Caches already in the backend are read once, then kept up to date
+        let mut backend = MemoryStorage::default();
+        backend
+            .append("cache", &json!({"key": "a", "value": 1}))
+            .unwrap();
+        let mut storage = Storage::new(Box::new(backend));
+        assert_eq!(storage.cache_get::<u32>("cache", "a").unwrap(), Some(1));
+        storage.cache_put("cache", "a", 2).unwrap();
+        assert_eq!(storage.cache_get::<u32>("cache", "a").unwrap(), Some(2));
+        assert_eq!(storage.load("cache").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn stream_limit() {
+        let mut storage = Storage::new(Box::<MemoryStorage>::default());
+        for i in 0..=MAX_STREAM_RECORDS {
+            storage.append("stats", &json!(i)).unwrap();
+        }
+        let records = storage.load("stats").unwrap();
+        assert_eq!(records.len(), MAX_STREAM_RECORDS * 3 / 4);
+        assert_eq!(records.last(), Some(&json!(MAX_STREAM_RECORDS)));
+
+        // Caches keep the latest value of every key
+        for i in 0..=MAX_STREAM_RECORDS {
+            storage
+                .cache_put("cache", &(i % 10).to_string(), i)
+                .unwrap();
+        }
+        assert_eq!(storage.load("cache").unwrap().len(), 10);
+        assert_eq!(
+            storage.cache_get::<usize>("cache", "0").unwrap(),
+            Some(MAX_STREAM_RECORDS)
+        );
+    }
+}
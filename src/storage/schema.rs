@@ -0,0 +1,138 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::StorageError;
+
+/// Field holding the schema version in every persisted artifact record.
+pub const SCHEMA_VERSION_FIELD: &str = "schema_version";
+
+/// A type which is persisted for use by later runs or external tools. Records carry a
+/// `schema_version` field, so older records can be migrated when the type changes.
+///
+/// When changing the serialized form of an artifact, bump `SCHEMA_VERSION` and add a step to
+/// `migrate` which upgrades records from the previous version.
+pub trait Artifact: Serialize + DeserializeOwned {
+    /// Name of the artifact, used in errors.
+    const KIND: &'static str;
+    /// Current schema version. Records written before versioning was added are version 0.
+    const SCHEMA_VERSION: u32;
+
+    /// Upgrade a record from `version` to `version + 1`.
+    fn migrate(_record: &mut serde_json::Value, version: u32) -> Result<(), StorageError> {
+        Err(StorageError::UnsupportedSchema {
+            kind: Self::KIND,
+            version,
+        })
+    }
+}
+
+/// Serialize an artifact to a record tagged with its schema version.
+pub fn to_record<T: Artifact>(artifact: &T) -> Result<serde_json::Value, StorageError> {
+    let mut record = serde_json::to_value(artifact)?;
+    match record.as_object_mut() {
+        Some(fields) => {
+            fields.insert(SCHEMA_VERSION_FIELD.to_owned(), T::SCHEMA_VERSION.into());
+            Ok(record)
+        }
+        None => Err(StorageError::UnsupportedSchema {
+            kind: T::KIND,
+            version: T::SCHEMA_VERSION,
+        }),
+    }
+}
+
+/// Deserialize an artifact from a record, migrating it from older schema versions if needed.
+pub fn from_record<T: Artifact>(mut record: serde_json::Value) -> Result<T, StorageError> {
+    let version = match record.get(SCHEMA_VERSION_FIELD) {
+        None => 0,
+        Some(version) => version.as_u64().and_then(|v| u32::try_from(v).ok()).ok_or(
+            StorageError::UnsupportedSchema {
+                kind: T::KIND,
+                version: u32::MAX,
+            },
+        )?,
+    };
+    if version > T::SCHEMA_VERSION {
+        // Written by a newer version of the bot
+        return Err(StorageError::UnsupportedSchema {
+            kind: T::KIND,
+            version,
+        });
+    }
+    for version in version..T::SCHEMA_VERSION {
+        T::migrate(&mut record, version)?;
+    }
+    if let Some(fields) = record.as_object_mut() {
+        fields.remove(SCHEMA_VERSION_FIELD);
+    }
+    Ok(serde_json::from_value(record)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::{from_record, to_record, Artifact};
+    use crate::storage::StorageError;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Sample {
+        name: String,
+        count: u32,
+    }
+
+    impl Artifact for Sample {
+        const KIND: &'static str = "sample";
+        const SCHEMA_VERSION: u32 = 2;
+
+        fn migrate(record: &mut serde_json::Value, version: u32) -> Result<(), StorageError> {
+            match version {
+                // Version 0 had no count
+                0 => record["count"] = json!(1),
+                // Version 1 called the name "label"
+                1 => record["name"] = record["label"].take(),
+                _ => unreachable!(),
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let sample = Sample {
+            name: "a".into(),
+            count: 3,
+        };
+        let record = to_record(&sample).unwrap();
+        assert_eq!(
+            record,
+            json!({"name": "a", "count": 3, "schema_version": 2})
+        );
+        assert_eq!(from_record::<Sample>(record).unwrap(), sample);
+    }
+
+    #[test]
+    fn migrate() {
+        assert_eq!(
+            from_record::<Sample>(json!({"label": "b"})).unwrap(),
+            Sample {
+                name: "b".into(),
+                count: 1
+            }
+        );
+        assert_eq!(
+            from_record::<Sample>(json!({"label": "c", "count": 5, "schema_version": 1})).unwrap(),
+            Sample {
+                name: "c".into(),
+                count: 5
+            }
+        );
+        assert!(matches!(
+            from_record::<Sample>(json!({"name": "d", "count": 1, "schema_version": 3})),
+            Err(StorageError::UnsupportedSchema {
+                kind: "sample",
+                version: 3
+            })
+        ));
+    }
+}
@@ -0,0 +1,95 @@
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+use super::{validate_stream, StorageBackend, StorageError};
+
+/// Storage in a single SQLite database file, with all streams in one table.
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Connection,
+}
+
+impl SqliteStorage {
+    /// Open (or create) the database at the given path.
+    pub fn open(path: &Path) -> Result<Self, StorageError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, StorageError> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS records (
+                id INTEGER PRIMARY KEY,
+                stream TEXT NOT NULL,
+                record TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS records_stream ON records (stream, id);",
+        )?;
+        Ok(SqliteStorage { connection })
+    }
+}
+
+impl StorageBackend for SqliteStorage {
+    fn append(&mut self, stream: &str, record: &serde_json::Value) -> Result<(), StorageError> {
+        validate_stream(stream)?;
+        self.connection.execute(
+            "INSERT INTO records (stream, record) VALUES (?1, ?2)",
+            params![stream, serde_json::to_string(record)?],
+        )?;
+        Ok(())
+    }
+
+    fn load(&self, stream: &str) -> Result<Vec<serde_json::Value>, StorageError> {
+        validate_stream(stream)?;
+        let mut statement = self
+            .connection
+            .prepare("SELECT record FROM records WHERE stream = ?1 ORDER BY id")?;
+        let rows = statement.query_map(params![stream], |row| row.get::<_, String>(0))?;
+        let mut records = Vec::new();
+        for row in rows {
+            records.push(serde_json::from_str(&row?)?);
+        }
+        Ok(records)
+    }
+
+    fn replace(&mut self, stream: &str, records: &[serde_json::Value]) -> Result<(), StorageError> {
+        validate_stream(stream)?;
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM records WHERE stream = ?1", params![stream])?;
+        for record in records {
+            transaction.execute(
+                "INSERT INTO records (stream, record) VALUES (?1, ?2)",
+                params![stream, serde_json::to_string(record)?],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+    use serde_json::json;
+
+    use super::{SqliteStorage, StorageBackend};
+
+    #[test]
+    fn append_and_load() {
+        let mut storage =
+            SqliteStorage::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+        assert!(storage.load("runs").unwrap().is_empty());
+
+        storage.append("runs", &json!({"id": 1})).unwrap();
+        storage.append("stats", &json!(3)).unwrap();
+        storage.append("runs", &json!({"id": 2})).unwrap();
+        assert_eq!(
+            storage.load("runs").unwrap(),
+            vec![json!({"id": 1}), json!({"id": 2})]
+        );
+        assert_eq!(storage.load("stats").unwrap(), vec![json!(3)]);
+
+        storage.replace("runs", &[json!({"id": 3})]).unwrap();
+        assert_eq!(storage.load("runs").unwrap(), vec![json!({"id": 3})]);
+        assert_eq!(storage.load("stats").unwrap(), vec![json!(3)]);
+    }
+}
@@ -0,0 +1,218 @@
+//! Generators for property-based tests of password edits, and the properties themselves. Most
+//! panics in the bot have come from index arithmetic when applying changes, so the invariants
+//! of `Change` application are checked over random passwords and change sequences here, and
+//! the parsers of page content are fuzzed with arbitrary input.
+
+use proptest::prelude::*;
+
+use crate::password::{
+    format::{FontFamily, FontSize},
+    Change, FormatChange, MutablePassword,
+};
+
+/// Graphemes to build passwords and changes from: ASCII, multi-codepoint clusters, and the
+/// emoji the game uses. None of them combine with their neighbours.
+const GRAPHEMES: &[&str] = &[
+    "a",
+    "Z",
+    "5",
+    "-",
+    " ",
+    "e\u{301}",
+    "🥚",
+    "🐔",
+    "🐛",
+    "🔥",
+    "🌑",
+    "🏋️‍♂️",
+];
+
+/// Longest password generated.
+const MAX_PASSWORD_LENGTH: usize = 20;
+
+pub fn grapheme() -> impl Strategy<Value = &'static str> {
+    prop::sample::select(GRAPHEMES)
+}
+
+/// A non-empty string of a few graphemes.
+pub fn string() -> impl Strategy<Value = String> {
+    prop::collection::vec(grapheme(), 1..4).prop_map(|graphemes| graphemes.concat())
+}
+
+/// A password with some of its graphemes protected.
+pub fn password() -> impl Strategy<Value = MutablePassword> {
+    prop::collection::vec((grapheme(), any::<bool>()), 0..MAX_PASSWORD_LENGTH).prop_map(
+        |graphemes| {
+            let string = graphemes.iter().map(|(g, _)| *g).collect::<String>();
+            let mut password = MutablePassword::from_str(&string);
+            for (index, (_, protected)) in graphemes.iter().enumerate() {
+                if *protected {
+                    password.protect(index);
+                }
+            }
+            password
+        },
+    )
+}
+
+pub fn format_change() -> impl Strategy<Value = FormatChange> {
+    prop_oneof![
+        Just(FormatChange::BoldOn),
+        Just(FormatChange::ItalicOn),
+        prop::sample::select(vec![FontSize::Px0, FontSize::Px9, FontSize::Px64])
+            .prop_map(FormatChange::FontSize),
+        prop::sample::select(vec![FontFamily::Wingdings, FontFamily::TimesNewRoman])
+            .prop_map(FormatChange::FontFamily),
+    ]
+}
+
+/// A change which may or may not be valid for a given password. Never ignores protection.
+pub fn change() -> impl Strategy<Value = Change> {
+    let index = 0..MAX_PASSWORD_LENGTH + 4;
+    prop_oneof![
+        (index.clone(), format_change()).prop_map(|(index, format_change)| Change::Format {
+            index,
+            format_change
+        }),
+        (string(), any::<bool>())
+            .prop_map(|(string, protected)| Change::Prepend { string, protected }),
+        (string(), any::<bool>())
+            .prop_map(|(string, protected)| Change::Append { string, protected }),
+        (index.clone(), string(), any::<bool>()).prop_map(|(index, string, protected)| {
+            Change::Insert {
+                index,
+                string,
+                protected,
+            }
+        }),
+        (index.clone(), grapheme()).prop_map(|(index, new_grapheme)| Change::Replace {
+            index,
+            new_grapheme: new_grapheme.into(),
+            ignore_protection: false,
+        }),
+        index.prop_map(|index| Change::Remove {
+            index,
+            ignore_protection: false,
+        }),
+    ]
+}
+
+/// A password and a sequence of changes which can be queued on it together.
+pub fn password_and_changes() -> impl Strategy<Value = (MutablePassword, Vec<Change>)> {
+    (password(), prop::collection::vec(change(), 0..12)).prop_map(|(password, candidates)| {
+        let changes = valid_changes(&password, candidates);
+        (password, changes)
+    })
+}
+
+/// The candidates which can be queued on the password, keeping each one only if it's valid
+/// along with those kept before it.
+pub fn valid_changes(password: &MutablePassword, candidates: Vec<Change>) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for candidate in candidates {
+        changes.push(candidate);
+        if password.preview_changes(&changes).is_err() {
+            changes.pop();
+        }
+    }
+    changes
+}
+
+mod tests {
+    use proptest::prelude::*;
+    use unicode_segmentation::UnicodeSegmentation;
+
+    use super::password_and_changes;
+    use crate::{
+        driver::web::helpers::{extract_fen_from_svg, parse_formatting},
+        password::{Change, TrackedRegion},
+    };
+
+    proptest! {
+        #[test]
+        fn formatting_matches_graphemes((mut password, changes) in password_and_changes()) {
+            for change in changes {
+                password.queue_change(change);
+            }
+            password.commit_changes().unwrap();
+            let graphemes = password.as_str().graphemes(true).count();
+            prop_assert_eq!(password.len(), graphemes);
+            prop_assert_eq!(password.raw_password().formatting().len(), graphemes);
+            prop_assert_eq!(password.protected_graphemes().len(), graphemes);
+        }
+
+        #[test]
+        fn protected_graphemes_unchanged((mut password, changes) in password_and_changes()) {
+            let protected = password
+                .annotated()
+                .filter(|(_, _, _, protected)| *protected)
+                .map(|(index, grapheme, _, _)| (index, grapheme.to_owned()))
+                .collect::<Vec<_>>();
+            let regions = protected
+                .iter()
+                .map(|(index, _)| password.track(TrackedRegion::new(*index, 1)))
+                .collect::<Vec<_>>();
+
+            for change in changes {
+                password.queue_change(change);
+            }
+            password.commit_changes().unwrap();
+            for (id, (_, grapheme)) in regions.into_iter().zip(protected) {
+                let index = password.tracked(id).unwrap().index;
+                prop_assert_eq!(password.raw_password().grapheme(index), grapheme);
+                prop_assert!(password.is_protected(index));
+            }
+        }
+
+        /// Appends are made in the order they're queued, but otherwise the order changes are
+        /// queued in doesn't matter.
+        #[test]
+        fn commit_order_is_independent_of_queue_order(
+            (mut password, changes, shuffled) in password_and_changes().prop_flat_map(
+                |(password, changes)| {
+                    (Just(password), Just(changes.clone()), Just(changes).prop_shuffle())
+                }
+            )
+        ) {
+            let is_append = |change: &Change| matches!(change, Change::Append { .. });
+            let mut appends = changes.iter().filter(|c| is_append(c)).cloned();
+            let shuffled = shuffled
+                .into_iter()
+                .map(|change| {
+                    if is_append(&change) {
+                        appends.next().unwrap()
+                    } else {
+                        change
+                    }
+                })
+                .collect::<Vec<_>>();
+            let preview = password.preview_changes(&shuffled).unwrap();
+            for change in changes {
+                password.queue_change(change);
+            }
+            password.commit_changes().unwrap();
+            prop_assert_eq!(password.as_str(), preview.as_str());
+            prop_assert_eq!(password.raw_password().formatting(), preview.formatting());
+        }
+
+        #[test]
+        fn parse_formatting_never_panics(
+            html in prop_oneof![
+                ".*",
+                r#"<p>(<(strong|em|span)( style="(font-(size|family): ?[a-z0-9]*;?)*")?>[a-z🐛]{0,4}</(strong|em|span)>|[a-z]){0,6}</p>"#,
+            ]
+        ) {
+            let _ = parse_formatting(&html);
+        }
+
+        #[test]
+        fn extract_fen_from_svg_never_panics(
+            svg in prop_oneof![
+                ".*",
+                "<svg><desc><pre>([.PpKkQqRrBbNn ]{0,16}\n){0,9}</pre></desc></svg>",
+            ]
+        ) {
+            let _ = extract_fen_from_svg(&svg, 'w');
+        }
+    }
+}
@@ -4,7 +4,8 @@ use reqwest::StatusCode;
 use serde::Deserialize;
 use std::fs;
 
-use crate::{is_id_perfect, Video, VideoDuration};
+use super::{is_id_perfect, Video, VideoDuration};
+use crate::logging::YOUTUBE;
 
 impl std::fmt::Display for VideoDuration {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -76,10 +77,10 @@ pub fn search(
     query: &str,
 ) -> (Vec<String>, Option<String>) {
     let page_token_param = if let Some(page_token) = page_token {
-        info!("Searching for {}, page token {}", query, page_token);
+        info!(target: YOUTUBE, "Searching for {}, page token {}", query, page_token);
         format!("&pageToken={}", page_token)
     } else {
-        info!("Searching for {}", query);
+        info!(target: YOUTUBE, "Searching for {}", query);
         "".into()
     };
     let url = format!("https://youtube.googleapis.com/youtube/v3/search?q={}&part=snippet&maxResults=50&type=video&videoDuration={}&key={}{}", query, duration, api_key, page_token_param);
@@ -0,0 +1,334 @@
+use lazy_static::lazy_static;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+use thiserror::Error;
+
+use super::{id_penalty, Video, MAX_DURATION, MIN_DURATION};
+use crate::logging::YOUTUBE;
+
+/// The database harvested by `youtube harvest`, as of the build.
+const EMBEDDED_VIDEOS: &str = include_str!("videos.json");
+
+lazy_static! {
+    /// The video database in use: the embedded copy, unless `configure` loaded another.
+    static ref VIDEOS: RwLock<Arc<VideoDatabase>> =
+        RwLock::new(Arc::new(VideoDatabase::embedded()));
+}
+
+/// Failure modes for loading a video database.
+#[derive(Debug, Error)]
+pub enum VideoDatabaseError {
+    #[error("failed to read video database")]
+    Io(#[from] std::io::Error),
+    #[error("invalid video database")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Most videos to keep for each duration.
+const MAX_CANDIDATES: usize = 5;
+
+/// The candidate IDs for a single duration, as saved.
+#[derive(Debug, Deserialize, Serialize)]
+struct Candidates {
+    duration: u32,
+    ids: Vec<String>,
+}
+
+/// The saved database: a list of candidates for each duration, or the original format of a
+/// single list of videos (which can repeat durations).
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SavedDatabase {
+    Candidates(Vec<Candidates>),
+    Videos(Vec<Video>),
+}
+
+/// YouTube video IDs by duration in seconds, for the YouTube rule.
+#[derive(Debug, Default, Clone)]
+pub struct VideoDatabase {
+    /// Candidate IDs for each duration, lowest penalty first.
+    videos: HashMap<u32, Vec<String>>,
+    /// Where the database came from, for logging.
+    source: String,
+    /// The file the database was loaded from, if any, which videos found by searching are saved
+    /// back to.
+    path: Option<PathBuf>,
+}
+
+impl VideoDatabase {
+    /// Parse a database in the format written by `youtube harvest`, or the original format of
+    /// a single list of videos.
+    pub fn from_json(json: &str, source: &str) -> Result<Self, VideoDatabaseError> {
+        let videos = match serde_json::from_str(json)? {
+            SavedDatabase::Candidates(candidates) => candidates
+                .into_iter()
+                .flat_map(|Candidates { duration, ids }| {
+                    ids.into_iter().map(move |id| Video { id, duration })
+                })
+                .collect(),
+            SavedDatabase::Videos(videos) => videos,
+        };
+        Ok(VideoDatabase::from_videos(videos, source))
+    }
+
+    /// A database of the given videos, keeping the best few for each duration.
+    pub fn from_videos(videos: Vec<Video>, source: &str) -> Self {
+        let mut database = VideoDatabase {
+            source: source.to_owned(),
+            ..Default::default()
+        };
+        for video in videos {
+            database
+                .videos
+                .entry(video.duration)
+                .or_default()
+                .push(video.id);
+        }
+        for candidates in database.videos.values_mut() {
+            sort_candidates(candidates);
+        }
+        database
+    }
+
+    /// The copy of the database built into the binary.
+    pub fn embedded() -> Self {
+        VideoDatabase::from_json(EMBEDDED_VIDEOS, "embedded").expect("invalid embedded videos")
+    }
+
+    /// Load a database from the given file.
+    pub fn load(path: &Path) -> Result<Self, VideoDatabaseError> {
+        let mut database =
+            VideoDatabase::from_json(&std::fs::read_to_string(path)?, &path.display().to_string())?;
+        database.path = Some(path.to_owned());
+        Ok(database)
+    }
+
+    /// Save the database to the given file, as a list of candidates for each duration.
+    pub fn save(&self, path: &Path) -> Result<(), VideoDatabaseError> {
+        let mut candidates = self
+            .videos
+            .iter()
+            .map(|(duration, ids)| Candidates {
+                duration: *duration,
+                ids: ids.clone(),
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|candidates| candidates.duration);
+        std::fs::write(path, serde_json::to_string(&candidates)?)?;
+        Ok(())
+    }
+
+    /// Every video, shortest first.
+    pub fn to_videos(&self) -> Vec<Video> {
+        let mut videos = self
+            .videos
+            .iter()
+            .flat_map(|(duration, candidates)| {
+                candidates.iter().map(|id| Video {
+                    id: id.clone(),
+                    duration: *duration,
+                })
+            })
+            .collect::<Vec<_>>();
+        videos.sort_by_key(|video| video.duration);
+        videos
+    }
+
+    /// Add the given videos, skipping any outside the durations the YouTube rule can ask for or
+    /// already in the database, and any which aren't among the best few for their duration.
+    /// Returns the number of new durations and the number of better IDs (those which became
+    /// the best for their duration).
+    pub fn update(&mut self, new_videos: &[Video]) -> (usize, usize) {
+        let mut new_count = 0;
+        let mut update_count = 0;
+        for new_video in new_videos {
+            if new_video.duration < MIN_DURATION || new_video.duration > MAX_DURATION {
+                continue;
+            }
+            if self
+                .videos
+                .values()
+                .any(|candidates| candidates.contains(&new_video.id))
+            {
+                // Duplicate ID
+                continue;
+            }
+            let candidates = self.videos.entry(new_video.duration).or_default();
+            let is_new_duration = candidates.is_empty();
+            candidates.push(new_video.id.clone());
+            sort_candidates(candidates);
+            if is_new_duration {
+                new_count += 1;
+            } else if candidates[0] == new_video.id {
+                update_count += 1;
+            }
+        }
+        (new_count, update_count)
+    }
+
+    /// The best ID of the videos with the given duration, if there are any.
+    pub fn get(&self, seconds: u32) -> Option<&str> {
+        self.candidates(seconds).first().map(String::as_str)
+    }
+
+    /// The IDs of the videos with the given duration, lowest penalty first.
+    pub fn candidates(&self, seconds: u32) -> &[String] {
+        self.videos.get(&seconds).map_or(&[], Vec::as_slice)
+    }
+
+    /// The best video for each duration, as (duration, ID).
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.videos
+            .keys()
+            .filter_map(|seconds| Some((*seconds, self.get(*seconds)?)))
+    }
+
+    /// Where the database came from (a path, or "embedded").
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// If there's no video with the given duration, the run of durations around it which are
+    /// all missing, as `(first, last)`, where the last is None if there are no longer videos.
+    pub fn missing_range(&self, seconds: u32) -> Option<(u32, Option<u32>)> {
+        if self.videos.contains_key(&seconds) {
+            return None;
+        }
+        let durations = self.videos.keys().copied().collect::<BTreeSet<_>>();
+        let first = durations
+            .range(..seconds)
+            .next_back()
+            .map_or(0, |duration| duration + 1);
+        let last = durations
+            .range(seconds..)
+            .next()
+            .map(|duration| duration - 1);
+        Some((first, last))
+    }
+}
+
+/// Sort candidate IDs lowest penalty first (by ID for equal penalties, so the order is stable
+/// across saves), keeping only the best few.
+fn sort_candidates(candidates: &mut Vec<String>) {
+    candidates.sort_by_cached_key(|id| (id_penalty(id), id.clone()));
+    candidates.truncate(MAX_CANDIDATES);
+}
+
+/// Use the video database at the given path, falling back to the embedded copy if there's no
+/// path or it can't be loaded.
+pub fn configure(path: Option<&Path>) {
+    let database = match path.map(VideoDatabase::load) {
+        Some(Ok(database)) => {
+            info!(
+                target: YOUTUBE,
+                "Loaded {} videos from {}",
+                database.videos.len(),
+                database.source
+            );
+            database
+        }
+        Some(Err(e)) => {
+            warn!(target: YOUTUBE, "{}, using the embedded copy instead", e);
+            VideoDatabase::embedded()
+        }
+        None => VideoDatabase::embedded(),
+    };
+    *VIDEOS.write().unwrap() = Arc::new(database);
+}
+
+/// The video database in use.
+pub fn videos() -> Arc<VideoDatabase> {
+    VIDEOS.read().unwrap().clone()
+}
+
+/// Add a video found by searching to the database in use (if it's better than any already
+/// there), saving it back to its file if it was loaded from one.
+pub fn add_video(video: Video) {
+    let mut videos = VIDEOS.write().unwrap();
+    let database = Arc::make_mut(&mut videos);
+    database.update(&[video]);
+    if let Some(path) = &database.path {
+        if let Err(e) = database.save(path) {
+            warn!(target: YOUTUBE, "Failed to save video database: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VideoDatabase;
+    use crate::youtube::Video;
+
+    #[test]
+    fn video_database() {
+        let videos = VideoDatabase::from_json(
+            r#"[{"id": "a", "duration": 200}, {"id": "b9", "duration": 203}, {"id": "b", "duration": 203}, {"id": "c", "duration": 210}]"#,
+            "test",
+        )
+        .unwrap();
+        assert_eq!(videos.get(203), Some("b"));
+        assert_eq!(videos.candidates(203), ["b", "b9"]);
+        assert_eq!(videos.get(204), None);
+        assert_eq!(videos.iter().count(), 3);
+
+        assert_eq!(videos.missing_range(203), None);
+        assert_eq!(videos.missing_range(205), Some((204, Some(209))));
+        assert_eq!(videos.missing_range(100), Some((0, Some(199))));
+        assert_eq!(videos.missing_range(300), Some((211, None)));
+
+        // The current format
+        let videos =
+            VideoDatabase::from_json(r#"[{"duration": 200, "ids": ["a1", "b"]}]"#, "test").unwrap();
+        assert_eq!(videos.candidates(200), ["b", "a1"]);
+        assert_eq!(
+            VideoDatabase::from_json("[]", "test")
+                .unwrap()
+                .iter()
+                .count(),
+            0
+        );
+
+        assert!(VideoDatabase::from_json("{}", "test").is_err());
+        assert!(VideoDatabase::load(std::path::Path::new("/nonexistent/videos.json")).is_err());
+        assert!(VideoDatabase::embedded().iter().count() > 1000);
+    }
+
+    #[test]
+    fn save_video_database() {
+        let mut videos =
+            VideoDatabase::from_json(r#"[{"id": "a1", "duration": 200}]"#, "test").unwrap();
+        let video = |id: &str, duration| Video {
+            id: id.to_owned(),
+            duration,
+        };
+        assert_eq!(
+            videos.update(&[
+                video("b", 205),
+                video("c1", 205),
+                video("a1", 210),
+                video("d", 200),
+                video("e", 100)
+            ]),
+            (1, 1)
+        );
+        let path = std::env::temp_dir().join(format!(
+            "password-game-bot-videos-{}.json",
+            std::process::id()
+        ));
+        videos.save(&path).unwrap();
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .starts_with(r#"[{"duration":200,"ids":["d","a1"]}"#));
+        let loaded = VideoDatabase::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.candidates(200), ["d", "a1"]);
+        assert_eq!(loaded.candidates(205), ["b", "c1"]);
+        assert_eq!(loaded.get(210), None);
+        assert_eq!(loaded.path.as_deref(), Some(path.as_path()));
+    }
+}
@@ -0,0 +1,434 @@
+//! YouTube videos for the YouTube rule: the database of a video for each duration the rule can
+//! ask for, harvesting YouTube for them (into `src/youtube/videos.json`), and searching for a
+//! single video when a game asks for a duration the database doesn't have.
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{path::Path, sync::RwLock, time::Duration};
+
+use crate::{
+    password::helpers::get_elements,
+    solver::{sacrifice::COMMON_SACRIFICES, scoring::score_string},
+};
+
+// Harvesting and searching YouTube need the network
+#[cfg(feature = "native")]
+use crate::{game::helpers::get_youtube_duration, logging::YOUTUBE};
+#[cfg(feature = "native")]
+use database::VideoDatabase;
+#[cfg(feature = "native")]
+use log::{debug, info, warn};
+#[cfg(feature = "native")]
+use rand::{seq::SliceRandom, thread_rng};
+#[cfg(feature = "native")]
+use std::time::Instant;
+
+#[cfg(feature = "native")]
+#[allow(dead_code)]
+mod api;
+pub mod database;
+#[cfg(feature = "native")]
+mod web;
+
+const MIN_DURATION: u32 = 180;
+const MAX_DURATION: u32 = 2180;
+
+/// Where harvested videos are saved (the copy built into the binary).
+#[cfg(feature = "native")]
+const HARVEST_PATH: &str = "src/youtube/videos.json";
+
+/// Search queries, for harvesting and for finding videos which aren't in the database.
+#[cfg(feature = "native")]
+const NOUNS: &str = include_str!("top-1000-nouns.txt");
+
+/// Pages of results to look through for each query before trying another, when searching for a
+/// single video.
+#[cfg(feature = "native")]
+const PAGES_PER_QUERY: usize = 10;
+
+/// Default for how long to spend searching YouTube for a video the database doesn't have.
+pub const DEFAULT_SEARCH_BUDGET: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    /// How long to spend searching YouTube for a video the database doesn't have.
+    static ref SEARCH_BUDGET: RwLock<Duration> = RwLock::new(DEFAULT_SEARCH_BUDGET);
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum VideoDuration {
+    Any,
+    /// 20:01..
+    Long,
+    /// 4:00..=20:00
+    Medium,
+    /// 0:01..=3:59
+    Short,
+}
+
+impl VideoDuration {
+    pub fn to_web_api_param_type(&self) -> u8 {
+        0x18
+    }
+
+    pub fn to_web_api_param_value(&self) -> u8 {
+        match self {
+            VideoDuration::Any => 0x00,
+            VideoDuration::Long => 0x02,
+            VideoDuration::Medium => 0x03,
+            VideoDuration::Short => 0x01,
+        }
+    }
+
+    pub fn min_duration(&self) -> u32 {
+        match self {
+            VideoDuration::Any => MIN_DURATION,
+            VideoDuration::Long => 20 * 60 + 1,
+            VideoDuration::Medium => 4 * 60,
+            VideoDuration::Short => MIN_DURATION,
+        }
+    }
+
+    pub fn max_duration(&self) -> u32 {
+        match self {
+            VideoDuration::Any => MAX_DURATION,
+            VideoDuration::Long => MAX_DURATION,
+            VideoDuration::Medium => 20 * 60,
+            VideoDuration::Short => 4 * 60 - 1,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.max_duration() as usize - self.min_duration() as usize + 1
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Video {
+    pub id: String,
+    /// Duration in seconds
+    pub duration: u32,
+}
+
+/// Elements with atomic numbers above this take up enough of the atomic number sum to count
+/// against an ID.
+const LARGE_ATOMIC_NUMBER: u32 = 20;
+
+/// How much an ID is penalised for each part of its digit sum, each non-"I" roman numeral
+/// digit, each tenth of the atomic numbers of its large elements, and each letter the solver
+/// usually sacrifices (the URL is protected, so a letter in the ID can't be sacrificed, and a
+/// riskier one has to be chosen instead).
+const DIGIT_PENALTY: u32 = 2;
+const ROMAN_PENALTY: u32 = 10;
+const ELEMENT_PENALTY: u32 = 1;
+const SACRIFICE_PENALTY: u32 = 5;
+
+/// How much the given ID would get in the way of other rules once it's in the password: its
+/// digits (rule 5), non-"I" roman numerals (rule 16), large elements (rule 18) and letters
+/// which are commonly sacrificed (rule 30). Lower is better, and 0 is perfect.
+pub fn id_penalty(id: &str) -> u32 {
+    let score = score_string(id, &COMMON_SACRIFICES);
+    let large_atomic_numbers = get_elements(id)
+        .iter()
+        .map(|(element, _)| element.atomic_number)
+        .filter(|atomic_number| *atomic_number > LARGE_ATOMIC_NUMBER)
+        .sum::<u32>();
+    score.digit_sum * DIGIT_PENALTY
+        + score.roman_numerals as u32 * ROMAN_PENALTY
+        + large_atomic_numbers / 10 * ELEMENT_PENALTY
+        + score.sacrificed_letters as u32 * SACRIFICE_PENALTY
+}
+
+/// Determine whether the ID is fully useful (i.e., doesn't contain roman numerals other than
+/// "I", or non-zero digits).
+#[cfg(feature = "native")]
+fn is_id_perfect(id: &str) -> bool {
+    let score = score_string(id, &[]);
+    score.digit_sum == 0 && score.roman_numerals == 0
+}
+
+/// Load the harvested videos, or an empty database if there aren't any yet.
+#[cfg(feature = "native")]
+fn load_videos() -> VideoDatabase {
+    let path = Path::new(HARVEST_PATH);
+    if path.exists() {
+        VideoDatabase::load(path).expect("failed to load videos.json")
+    } else {
+        VideoDatabase::default()
+    }
+}
+
+#[cfg(feature = "native")]
+fn print_videos_summary(videos: &VideoDatabase, duration: VideoDuration) {
+    let in_range = videos
+        .iter()
+        .filter(|(seconds, _)| {
+            *seconds >= duration.min_duration() && *seconds <= duration.max_duration()
+        })
+        .collect::<Vec<_>>();
+    let count = in_range.len();
+    let prop = count as f32 / duration.count() as f32;
+    let perfect_count = in_range.iter().filter(|(_, id)| is_id_perfect(id)).count();
+    let perfect_prop = perfect_count as f32 / count as f32;
+    info!(
+        target: YOUTUBE,
+        "Summary ({:?}): Covered {} of {} durations ({:.1}%), {} ({:.1}%) of which are perfect",
+        duration,
+        count,
+        duration.count(),
+        prop * 100.0,
+        perfect_count,
+        perfect_prop * 100.0
+    );
+}
+
+#[cfg(feature = "native")]
+fn save_videos(videos: &VideoDatabase, duration: VideoDuration) {
+    videos
+        .save(Path::new(HARVEST_PATH))
+        .expect("failed to write to videos.json");
+    print_videos_summary(videos, duration);
+}
+
+#[cfg(feature = "native")]
+fn update_videos(videos: &mut VideoDatabase, new_videos: &[Video]) {
+    let (new_count, update_count) = videos.update(new_videos);
+    info!(target: YOUTUBE, "{} new durations, {} better IDs", new_count, update_count);
+}
+
+/// The search queries, in a random order.
+#[cfg(feature = "native")]
+fn shuffled_nouns() -> Vec<&'static str> {
+    let mut nouns = NOUNS
+        .lines()
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    nouns.shuffle(&mut thread_rng());
+    nouns
+}
+
+#[cfg(feature = "native")]
+#[allow(dead_code)]
+fn use_api(duration: VideoDuration) {
+    let nouns = shuffled_nouns();
+    let mut nouns_iter = nouns.into_iter();
+
+    let api_key = api::get_api_key();
+    let mut page_token = None;
+    let mut query = nouns_iter.next().unwrap();
+    let mut videos = load_videos();
+    info!(target: YOUTUBE, "Loaded {} videos from file", videos.iter().count());
+
+    while videos.iter().count() < 60 {
+        let (results_ids, next_page_token) =
+            api::search(&api_key, duration.clone(), &page_token, query);
+        if !results_ids.is_empty() {
+            let new_videos = api::get_video_durations(&api_key, &results_ids);
+            update_videos(&mut videos, &new_videos);
+            save_videos(&videos, duration.clone());
+            info!(target: YOUTUBE, "Saved {} videos to file", videos.iter().count());
+        }
+        if next_page_token.is_some() {
+            page_token = next_page_token;
+        } else {
+            // No more pages, change query
+            query = nouns_iter.next().expect("out of nouns");
+            page_token = None;
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+fn use_web_api(duration: VideoDuration) {
+    let nouns = shuffled_nouns();
+    let mut nouns_iter = nouns.into_iter();
+
+    let mut continuation_token = None;
+    let mut query = nouns_iter.next().unwrap();
+    info!(target: YOUTUBE, "New query: {:?}", query);
+    let mut videos = load_videos();
+    info!(target: YOUTUBE, "Loaded {} videos from file", videos.iter().count());
+
+    let mut query_request_count = 0;
+    while videos.iter().count() < (MAX_DURATION - MIN_DURATION + 1) as usize {
+        let (new_videos, next_continuation_token) =
+            web::search(duration.clone(), &continuation_token, query).expect("search failed");
+        query_request_count += 1;
+        update_videos(&mut videos, &new_videos);
+        save_videos(&videos, duration.clone());
+
+        if next_continuation_token.is_some() && query_request_count < 10 {
+            continuation_token = next_continuation_token;
+        } else {
+            // No more pages, change query
+            query = nouns_iter.next().expect("out of nouns");
+            query_request_count = 0;
+            continuation_token = None;
+            info!(target: YOUTUBE, "New query: {:?}", query);
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+#[allow(dead_code)]
+fn delete_non_embeddable() {
+    let api_key = api::get_api_key();
+    let videos = load_videos().to_videos();
+    info!(target: YOUTUBE, "Loaded {} videos from file", videos.len());
+
+    let mut embeddable_videos = Vec::new();
+    for chunk in videos.chunks(50) {
+        let embeddable = api::get_embeddable(
+            &api_key,
+            &chunk
+                .iter()
+                .map(|v| v.id.to_owned())
+                .collect::<Vec<String>>(),
+        );
+        for (video, is_embeddable) in chunk.iter().zip(embeddable.iter()) {
+            if *is_embeddable {
+                embeddable_videos.push(video.clone());
+            } else {
+                info!(target: YOUTUBE, "Removing un-embeddeable video {}", video.id);
+            }
+        }
+    }
+
+    save_videos(
+        &VideoDatabase::from_videos(embeddable_videos, HARVEST_PATH),
+        VideoDuration::Any,
+    );
+}
+
+/// Search for videos of the given durations until we have one for every duration, saving
+/// them as we go.
+#[cfg(feature = "native")]
+pub fn harvest(duration: VideoDuration) {
+    use_web_api(duration);
+    // delete_non_embeddable();
+}
+
+/// How long to spend searching YouTube for a video the database doesn't have.
+pub fn search_budget() -> Duration {
+    *SEARCH_BUDGET.read().unwrap()
+}
+
+/// Use the video database at the given path (see `database::configure`), and spend up to
+/// `search_budget` searching YouTube for any video it doesn't have.
+pub fn configure(videos_path: Option<&Path>, search_budget: Duration) {
+    *SEARCH_BUDGET.write().unwrap() = search_budget;
+    database::configure(videos_path);
+}
+
+/// The range of durations the web search can filter to which includes the given duration, if
+/// any does.
+#[cfg(feature = "native")]
+fn search_range(seconds: u32) -> Option<VideoDuration> {
+    [
+        VideoDuration::Short,
+        VideoDuration::Medium,
+        VideoDuration::Long,
+    ]
+    .into_iter()
+    .find(|range| (range.min_duration()..=range.max_duration()).contains(&seconds))
+}
+
+/// Search YouTube for a video of exactly the given duration, for when the database has none,
+/// giving up once the search budget is spent (or the given deadline passes, if that's sooner).
+/// Candidates are only taken if `usable`, and once their duration is confirmed from the
+/// video's own page. A video found is added to the database, so it's only searched for once.
+#[cfg(feature = "native")]
+pub fn search_video(
+    seconds: u32,
+    deadline: Option<Instant>,
+    usable: impl Fn(&str) -> bool,
+) -> Option<String> {
+    let budget = search_budget();
+    let range = search_range(seconds)?;
+    if budget.is_zero() {
+        return None;
+    }
+    let deadline = deadline.map_or(Instant::now() + budget, |deadline| {
+        deadline.min(Instant::now() + budget)
+    });
+    info!(target: YOUTUBE, "Searching YouTube for a video of length {}s", seconds);
+
+    let mut nouns = shuffled_nouns().into_iter();
+    let mut query = nouns.next()?;
+    let mut continuation_token = None;
+    let mut pages = 0;
+    while Instant::now() < deadline {
+        let (videos, next_continuation_token) =
+            match web::search(range.clone(), &continuation_token, query) {
+                Ok(results) => results,
+                Err(e) => {
+                    warn!(target: YOUTUBE, "Giving up searching YouTube: {}", e);
+                    return None;
+                }
+            };
+        for video in videos.iter().filter(|video| video.duration == seconds) {
+            if !usable(&video.id) {
+                continue;
+            }
+            match get_youtube_duration(video.id.clone()) {
+                Ok(duration) if duration == seconds => {
+                    info!(
+                        target: YOUTUBE,
+                        "Found video {} of length {}s", video.id, seconds
+                    );
+                    database::add_video(video.clone());
+                    return Some(video.id.clone());
+                }
+                Ok(duration) => debug!(
+                    target: YOUTUBE,
+                    "Video {} is listed as {}s but is {}s long", video.id, seconds, duration
+                ),
+                Err(e) => warn!(
+                    target: YOUTUBE,
+                    "Couldn't check the duration of video {}: {}", video.id, e
+                ),
+            }
+        }
+
+        pages += 1;
+        if next_continuation_token.is_some() && pages < PAGES_PER_QUERY {
+            continuation_token = next_continuation_token;
+        } else {
+            query = nouns.next()?;
+            continuation_token = None;
+            pages = 0;
+        }
+    }
+    warn!(
+        target: YOUTUBE,
+        "No video of length {}s found after searching for {}s",
+        seconds,
+        budget.as_secs()
+    );
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{id_penalty, is_id_perfect, search_range, VideoDuration};
+
+    #[test]
+    fn video_quality() {
+        assert_eq!(id_penalty("abc0_-"), 0);
+        assert_eq!(id_penalty("abc12"), 6);
+        assert_eq!(id_penalty("abcX"), 10);
+        // Potassium is small enough to be harmless, but not gold
+        assert_eq!(id_penalty("abKc"), 0);
+        assert_eq!(id_penalty("abAuc"), 7);
+        assert_eq!(id_penalty("jaQz"), 15);
+
+        assert!(is_id_perfect("abI0_-"));
+        assert!(!is_id_perfect("ab1"));
+        assert!(!is_id_perfect("abL"));
+
+        assert!(matches!(search_range(180), Some(VideoDuration::Short)));
+        assert!(matches!(search_range(240), Some(VideoDuration::Medium)));
+        assert!(matches!(search_range(2179), Some(VideoDuration::Long)));
+        assert!(search_range(u32::MAX).is_none());
+    }
+}